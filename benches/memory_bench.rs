@@ -81,6 +81,7 @@ impl TestContext {
             command: Some(command.clone()),
             session_id,
             authenticated_user: None,
+            write_guard: None,
         };
 
         let (resp, _outcome) = command.execute(&mut ctx).await?;
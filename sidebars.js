@@ -98,6 +98,10 @@ const sidebars = {
               type: 'doc',
               id: 'caching/content-negotiation-vary',
             },
+            {
+              type: 'doc',
+              id: 'caching/http-front-end',
+            },
           ],
         },
       ],
@@ -5,6 +5,7 @@
 use crate::core::acl::rules::AclRule;
 use crate::core::acl::user::AclUser;
 use crate::core::cluster::ClusterConfig;
+use crate::core::storage::cache_types::{CachePolicy, ContentEncoding};
 use anyhow::{Context, Result, anyhow};
 use serde::{Deserialize, Serialize};
 use std::fs;
@@ -299,6 +300,15 @@ pub struct CacheConfig {
     /// The maximum size of the on-disk cache in bytes. `0` means no limit.
     #[serde(default = "default_max_disk_size")]
     pub max_disk_size: u64,
+    /// If true, the in-memory bodies of cold cache keys are moved to `on_disk_path` under
+    /// memory pressure instead of the keys being evicted, and on-disk bodies of keys that
+    /// become hot again are moved back to memory.
+    #[serde(default)]
+    pub tiered: bool,
+    /// The access frequency (the key's LFU counter, which starts at 5 and grows
+    /// logarithmically with accesses) at which an on-disk body is promoted to memory.
+    #[serde(default = "default_promotion_min_frequency")]
+    pub promotion_min_frequency: u8,
     /// The maximum number of variants (from the Vary header) to store per cache key. `0` means no limit.
     #[serde(default = "default_max_variants_per_key")]
     pub max_variants_per_key: usize,
@@ -308,6 +318,97 @@ pub struct CacheConfig {
     /// The maximum number of concurrent file reads from the on-disk cache.
     #[serde(default = "default_on_disk_max_open_files")]
     pub on_disk_max_open_files: usize,
+    /// The origin response headers stored with cached content and returned by
+    /// `CACHE.GET`, `CACHE.PROXY` and the HTTP front end. Matched case-insensitively.
+    #[serde(default = "default_stored_response_headers")]
+    pub stored_response_headers: Vec<String>,
+    /// The origin response headers that carry surrogate keys (tags) for policies with
+    /// `SURROGATE_KEYS` enabled. Their values are split on whitespace and commas.
+    #[serde(default = "default_surrogate_key_headers")]
+    pub surrogate_key_headers: Vec<String>,
+    /// The encodings (`br`, `zstd`, `gzip`) that bodies may be served with for policies
+    /// with `NEGOTIATE_ENCODING` enabled. An empty list disables encoding.
+    #[serde(default = "default_response_encodings")]
+    pub response_encodings: Vec<ContentEncoding>,
+    /// The optional HTTP front end that serves cached content directly.
+    #[serde(default)]
+    pub http: CacheHttpConfig,
+    /// Cache policies declared in the config file (`[[cache.policies]]`). They are
+    /// installed at startup and replace stored policies with the same name.
+    #[serde(default)]
+    pub policies: Vec<CachePolicy>,
+}
+
+/// Configuration for the HTTP front end of the Intelligent Cache.
+///
+/// Each request is mapped to the cache key `{key_prefix}{host}{path}?{query}` (the host
+/// only with `include_host`), which is then matched against the cache policies.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CacheHttpConfig {
+    /// If true, an HTTP server will be started to serve cached content.
+    #[serde(default)]
+    pub enabled: bool,
+    /// The port for the cache HTTP server.
+    #[serde(default = "default_cache_http_port")]
+    pub port: u16,
+    /// A prefix prepended to every cache key derived from a request.
+    #[serde(default)]
+    pub key_prefix: String,
+    /// If true, the request's `Host` header becomes part of the cache key, so that
+    /// several sites can be served from one server.
+    #[serde(default)]
+    pub include_host: bool,
+    /// The database the front end serves the cache from.
+    #[serde(default)]
+    pub db: usize,
+    /// Range requests that miss are fetched from the origin in slices of this many
+    /// bytes, each cached under its own key. `0` fetches and caches the whole object.
+    #[serde(default = "default_cache_http_slice_size")]
+    pub slice_size_bytes: u64,
+}
+
+fn default_cache_http_port() -> u16 {
+    8880
+}
+
+fn default_cache_http_slice_size() -> u64 {
+    1024 * 1024 // 1 MB
+}
+
+impl Default for CacheHttpConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: default_cache_http_port(),
+            key_prefix: String::new(),
+            include_host: false,
+            db: 0,
+            slice_size_bytes: default_cache_http_slice_size(),
+        }
+    }
+}
+
+fn default_stored_response_headers() -> Vec<String> {
+    [
+        "content-type",
+        "content-language",
+        "content-disposition",
+        "cache-control",
+        "expires",
+        "access-control-allow-origin",
+        "access-control-allow-credentials",
+        "access-control-expose-headers",
+    ]
+    .map(String::from)
+    .to_vec()
+}
+
+fn default_surrogate_key_headers() -> Vec<String> {
+    ["surrogate-key", "cache-tag"].map(String::from).to_vec()
+}
+
+fn default_response_encodings() -> Vec<ContentEncoding> {
+    ContentEncoding::ALL.to_vec()
 }
 
 fn default_streaming_threshold() -> usize {
@@ -319,6 +420,9 @@ fn default_disk_path() -> String {
 fn default_max_disk_size() -> u64 {
     0 // No limit
 }
+fn default_promotion_min_frequency() -> u8 {
+    7
+}
 fn default_max_variants_per_key() -> usize {
     64
 }
@@ -335,9 +439,16 @@ impl Default for CacheConfig {
             streaming_threshold_bytes: default_streaming_threshold(),
             on_disk_path: default_disk_path(),
             max_disk_size: default_max_disk_size(),
+            tiered: false,
+            promotion_min_frequency: default_promotion_min_frequency(),
             max_variants_per_key: default_max_variants_per_key(),
             negative_cache_ttl_seconds: default_negative_cache_ttl(),
             on_disk_max_open_files: default_on_disk_max_open_files(),
+            stored_response_headers: default_stored_response_headers(),
+            surrogate_key_headers: default_surrogate_key_headers(),
+            response_encodings: default_response_encodings(),
+            http: CacheHttpConfig::default(),
+            policies: Vec::new(),
         }
     }
 }
@@ -404,12 +515,19 @@ impl Default for Config {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct PersistenceConfig {
     pub aof_enabled: bool,
+    /// The AOF file name. Its directory and file name locate the multi-part AOF,
+    /// which is stored in `aof_dirname` next to it using the file name as a prefix.
     pub aof_path: String,
+    /// The directory, relative to `aof_path`'s parent, holding the multi-part AOF.
+    #[serde(default = "default_aof_dirname")]
+    pub aof_dirname: String,
     pub appendfsync: AppendFsync,
     #[serde(default = "default_auto_aof_rewrite_percentage")]
     pub auto_aof_rewrite_percentage: u64,
     #[serde(default = "default_auto_aof_rewrite_min_size")]
     pub auto_aof_rewrite_min_size: u64,
+    /// No longer used: rewrites rotate to a new incremental file instead of buffering
+    /// writes in memory. Kept so that existing configuration files still parse.
     #[serde(default = "default_aof_rewrite_buffer_limit")]
     pub aof_rewrite_buffer_limit: usize,
     pub spldb_enabled: bool,
@@ -493,6 +611,7 @@ impl Default for PersistenceConfig {
         Self {
             aof_enabled: false,
             aof_path: default_aof_path(),
+            aof_dirname: default_aof_dirname(),
             appendfsync: default_appendfsync(),
             auto_aof_rewrite_percentage: default_auto_aof_rewrite_percentage(),
             auto_aof_rewrite_min_size: default_auto_aof_rewrite_min_size(),
@@ -507,6 +626,9 @@ impl Default for PersistenceConfig {
 fn default_aof_path() -> String {
     "spineldb_data/spineldb.aof".to_string()
 }
+fn default_aof_dirname() -> String {
+    "appendonlydir".to_string()
+}
 fn default_appendfsync() -> AppendFsync {
     AppendFsync::EverySec
 }
@@ -601,6 +723,13 @@ impl Config {
 
         if self.persistence.aof_enabled {
             self.validate_persistence_path(&self.persistence.aof_path, "aof_path")?;
+            let dirname = self.persistence.aof_dirname.trim();
+            if dirname.is_empty() || dirname.contains(['/', '\\']) || dirname == ".." {
+                return Err(anyhow!(
+                    "aof_dirname must be a plain directory name, got '{}'",
+                    self.persistence.aof_dirname
+                ));
+            }
         }
         if self.persistence.spldb_enabled {
             self.validate_persistence_path(&self.persistence.spldb_path, "spldb_path")?;
@@ -646,6 +775,16 @@ impl Config {
                     "WARNING: cluster.failover_quorum is set to 1. This configuration is not fault-tolerant and cannot prevent split-brain."
                 );
             }
+            if self.cluster.bus_tls && !self.cluster.bus_tcp_enabled {
+                return Err(anyhow!(
+                    "cluster.bus_tls requires cluster.bus_tcp_enabled to be true"
+                ));
+            }
+            if self.cluster.bus_tls && !self.tls.enabled {
+                return Err(anyhow!(
+                    "cluster.bus_tls requires tls.enabled to be true, as the bus uses the server certificate"
+                ));
+            }
         }
 
         if self.tls.enabled {
@@ -667,6 +806,46 @@ impl Config {
                 ));
             }
         }
+
+        if self.cache.http.enabled {
+            if self.cache.http.port == 0 {
+                return Err(anyhow!("cache.http.port cannot be 0"));
+            }
+            if self.cache.http.port == self.port {
+                return Err(anyhow!(
+                    "cache.http.port cannot be the same as the main server port"
+                ));
+            }
+            if self.metrics.enabled && self.cache.http.port == self.metrics.port {
+                return Err(anyhow!(
+                    "cache.http.port cannot be the same as metrics.port"
+                ));
+            }
+            if self.cache.http.db >= self.databases {
+                return Err(anyhow!(
+                    "cache.http.db must be lower than databases ({})",
+                    self.databases
+                ));
+            }
+        }
+
+        let mut policy_names = std::collections::HashSet::new();
+        for policy in &self.cache.policies {
+            if policy.name.is_empty() || policy.key_pattern.is_empty() {
+                return Err(anyhow!(
+                    "cache.policies entries must have a name and a key_pattern"
+                ));
+            }
+            if !policy_names.insert(&policy.name) {
+                return Err(anyhow!(
+                    "cache.policies contains duplicate policy name '{}'",
+                    policy.name
+                ));
+            }
+            policy
+                .validate_origin_limits()
+                .map_err(|e| anyhow!("cache.policies entry '{}': {e}", policy.name))?;
+        }
         Ok(())
     }
 
@@ -58,7 +58,13 @@ impl ConnectionHandler {
         shutdown_rx: broadcast::Receiver<()>,
         global_shutdown_rx: broadcast::Receiver<()>,
     ) -> Self {
-        let is_auth_required = state.config.lock().await.password.is_some();
+        let (is_auth_required, is_cross_slot) = {
+            let config = state.config.lock().await;
+            (
+                config.password.is_some(),
+                config.cluster.enabled && config.cluster.cross_slot_fanout,
+            )
+        };
         let acl_enabled = state.acl_config.read().await.enabled;
         Self {
             framed: Some(Framed::new(socket, RespFrameCodec)),
@@ -67,7 +73,7 @@ impl ConnectionHandler {
             session_id,
             shutdown_rx,
             global_shutdown_rx,
-            session: SessionState::new(is_auth_required, acl_enabled),
+            session: SessionState::new(is_auth_required, acl_enabled, is_cross_slot),
             role: ConnectionRole::Client,
         }
     }
@@ -235,8 +241,11 @@ impl ConnectionHandler {
             }
         }
 
-        // If SUBSCRIBE or PSUBSCRIBE was successful, transition to Pub/Sub mode.
-        if self.session.is_subscribed || self.session.is_pattern_subscribed {
+        // If SUBSCRIBE, PSUBSCRIBE or SSUBSCRIBE was successful, transition to Pub/Sub mode.
+        if self.session.is_subscribed
+            || self.session.is_pattern_subscribed
+            || self.session.is_shard_subscribed
+        {
             Ok(NextAction::EnterPubSub)
         } else {
             Ok(NextAction::Continue)
@@ -324,8 +333,10 @@ impl ConnectionHandler {
         // Clean up all subscription state upon exiting Pub/Sub mode.
         self.session.is_subscribed = false;
         self.session.is_pattern_subscribed = false;
+        self.session.is_shard_subscribed = false;
         self.session.subscribed_channels.clear();
         self.session.subscribed_patterns.clear();
+        self.session.subscribed_shard_channels.clear();
         self.session.pubsub_receivers.clear();
         result
     }
@@ -18,20 +18,32 @@ pub struct SessionState {
     pub is_in_transaction: bool,
     /// True for the one command immediately following an `ASKING` command.
     pub is_asking: bool,
+    /// True if the client sent `READONLY`, allowing a cluster replica to serve its reads.
+    pub is_readonly: bool,
+    /// True if multi-key commands spanning several cluster slots are fanned out to the
+    /// owning nodes (`CLIENT CROSSSLOT ON`) instead of failing with `CROSSSLOT`.
+    pub is_cross_slot: bool,
     /// True if the client is subscribed to one or more channels.
     pub is_subscribed: bool,
     /// True if the client is subscribed to one or more patterns.
     pub is_pattern_subscribed: bool,
+    /// True if the client is subscribed to one or more shard channels.
+    pub is_shard_subscribed: bool,
     /// The set of channels the client is directly subscribed to.
     pub subscribed_channels: HashSet<Bytes>,
     /// The set of patterns the client is subscribed to.
     pub subscribed_patterns: HashSet<Bytes>,
+    /// The set of shard channels the client is subscribed to.
+    pub subscribed_shard_channels: HashSet<Bytes>,
     /// A collection of `broadcast::Receiver`s for active subscriptions.
     pub pubsub_receivers: Vec<SubscriptionReceiver>,
     /// The index of the database the client is currently using.
     pub current_db_index: usize,
     /// The `AclUser` associated with the authenticated session, if any.
     pub authenticated_user: Option<Arc<AclUser>>,
+    /// The password the client authenticated with, if any. Connections made to other
+    /// cluster nodes on behalf of the client authenticate with it, as the same user.
+    pub auth_password: Option<String>,
 }
 
 /// An enum holding a receiver for either a channel or pattern subscription.
@@ -41,22 +53,29 @@ pub enum SubscriptionReceiver {
     Channel(Bytes, broadcast::Receiver<Bytes>),
     /// A receiver for a glob-style pattern.
     Pattern(Bytes, broadcast::Receiver<PMessage>),
+    /// A receiver for a shard channel.
+    ShardChannel(Bytes, broadcast::Receiver<Bytes>),
 }
 
 impl SessionState {
     /// Creates a new `SessionState` with default values.
-    pub(crate) fn new(is_auth_required: bool, acl_enabled: bool) -> Self {
+    pub(crate) fn new(is_auth_required: bool, acl_enabled: bool, is_cross_slot: bool) -> Self {
         Self {
             is_authenticated: !is_auth_required && !acl_enabled,
             is_in_transaction: false,
             is_asking: false,
+            is_readonly: false,
+            is_cross_slot,
             is_subscribed: false,
             is_pattern_subscribed: false,
+            is_shard_subscribed: false,
             subscribed_channels: HashSet::new(),
             subscribed_patterns: HashSet::new(),
+            subscribed_shard_channels: HashSet::new(),
             pubsub_receivers: Vec::new(),
             current_db_index: 0,
             authenticated_user: None,
+            auth_password: None,
         }
     }
 }
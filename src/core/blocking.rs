@@ -121,8 +121,10 @@ impl BlockerManager {
             ctx.session_id, keys
         );
 
-        // 4. Release locks and enter the blocking wait.
+        // 4. Release locks and the write admission, and enter the blocking wait. When
+        // woken, the value has already been popped by the notifying command.
         ctx.release_locks();
+        ctx.release_write_admission();
         let block_result = self
             .wait_with_polling(keys, &mut rx, wait_timeout, &ctx.state)
             .await;
@@ -182,8 +184,9 @@ impl BlockerManager {
             String::from_utf8_lossy(source_key)
         );
 
-        // 4. Release locks and block.
+        // 4. Release locks and the write admission, and block.
         ctx.release_locks();
+        let was_admitted = ctx.release_write_admission();
         let block_result = self
             .wait_with_polling(
                 std::slice::from_ref(source_key),
@@ -201,7 +204,11 @@ impl BlockerManager {
             BlockerOutcome::Moved(slot) => Err(ctx.state.moved_error(slot)),
             BlockerOutcome::Woken(WokenValue::List(popped)) => {
                 // The item was popped from the source by the notifier. Now we must push it
-                // to the destination to complete the move.
+                // to the destination to complete the move, as an admitted write again.
+                if was_admitted {
+                    ctx.reacquire_write_admission(&[source_key.clone(), dest_key.clone()])
+                        .await;
+                }
                 self.handle_blmove_push(ctx, dest_key, source_key, from, to, popped)
                     .await
             }
@@ -251,8 +258,9 @@ impl BlockerManager {
             ctx.session_id, keys
         );
 
-        // 4. Release locks and block.
+        // 4. Release locks and the write admission, and block.
         ctx.release_locks();
+        ctx.release_write_admission();
         let block_result = self
             .wait_with_polling(keys, &mut rx, wait_timeout, &ctx.state)
             .await;
@@ -296,15 +304,18 @@ impl BlockerManager {
 
         // In cluster mode, use a "lazy polling" loop to handle slot migrations.
         const POLLING_TIMEOUT: Duration = Duration::from_millis(500);
-        let deadline = Instant::now() + wait_timeout;
+        // A timeout of 0 blocks indefinitely, so it may have no deadline.
+        let deadline = Instant::now().checked_add(wait_timeout);
         let my_slot = get_slot(&keys[0]); // All keys must be in the same slot.
 
         loop {
             let now = Instant::now();
-            if now >= deadline {
+            let time_left = deadline.map_or(POLLING_TIMEOUT, |deadline| {
+                deadline.saturating_duration_since(now)
+            });
+            if time_left.is_zero() {
                 return BlockerOutcome::TimedOut;
             }
-            let time_left = deadline - now;
             let current_timeout = POLLING_TIMEOUT.min(time_left);
 
             match timeout(current_timeout, &mut *rx).await {
@@ -482,6 +493,7 @@ impl BlockerManager {
             command: Some(push_cmd.clone()),
             session_id: ctx.session_id,
             authenticated_user: ctx.authenticated_user.clone(),
+            write_guard: None,
         };
 
         if let Err(push_err) = push_cmd.execute(&mut dest_ctx).await {
@@ -532,6 +544,7 @@ impl BlockerManager {
             command: Some(return_push_cmd.clone()),
             session_id: ctx.session_id,
             authenticated_user: ctx.authenticated_user.clone(),
+            write_guard: None,
         };
 
         if let Err(return_err) = return_push_cmd.execute(&mut source_ctx).await {
@@ -0,0 +1,483 @@
+// src/core/cluster/bus.rs
+
+//! Implements the transport layer of the cluster bus.
+//!
+//! Every node listens on its bus port for both UDP datagrams and TCP connections.
+//! Each peer gets one persistent, optionally TLS-encrypted TCP link that is opened
+//! lazily and re-established with backoff when it breaks. Messages that must not be
+//! lost, such as `PurgeTags`, `ConfigUpdate`, failover votes and oversized pings, are
+//! sent over these links. Heartbeats that fit in a single datagram may still use UDP
+//! as a fast path.
+//!
+//! Both transports carry the same `SecureGossipMessage` HMAC envelope. On TCP, each
+//! envelope is prefixed with its length as a big-endian `u32`.
+
+use crate::core::SpinelDBError;
+use crate::core::cluster::gossip::GossipMessage;
+use crate::core::cluster::secure_gossip::SecureGossipMessage;
+use bincode::config;
+use bytes::Bytes;
+use dashmap::DashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadHalf, WriteHalf};
+use tokio::net::{TcpListener, TcpStream, UdpSocket};
+use tokio::sync::mpsc;
+use tokio::time;
+use tokio_rustls::{TlsAcceptor, TlsConnector, rustls};
+use tracing::{debug, info, warn};
+
+/// The largest encoded message sent as a single UDP datagram. Anything larger goes
+/// over TCP to avoid IP fragmentation and silent loss.
+pub const UDP_MAX_PAYLOAD_SIZE: usize = 1400;
+/// The largest frame accepted on a TCP link.
+const MAX_FRAME_SIZE: usize = 16 * 1024 * 1024;
+const UDP_BUFFER_SIZE: usize = 65535;
+/// The number of frames that can be queued for a peer while its link is down.
+const LINK_QUEUE_CAPACITY: usize = 1024;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const INITIAL_RECONNECT_DELAY: Duration = Duration::from_millis(100);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Transport settings for the cluster bus.
+#[derive(Clone, Default)]
+pub struct BusOptions {
+    /// Send reliable message classes over persistent TCP links.
+    pub tcp_enabled: bool,
+    /// Send heartbeats that fit in a datagram over UDP.
+    pub udp_heartbeats: bool,
+    /// Accepts TLS on inbound TCP links. TLS is used on the bus when this is set.
+    pub tls_acceptor: Option<TlsAcceptor>,
+}
+
+/// Where an inbound message came from, used to route replies.
+#[derive(Debug, Clone)]
+pub enum BusOrigin {
+    /// A UDP datagram. The source address is the sender's bus address.
+    Udp(SocketAddr),
+    /// A TCP link. Replies are written back on the same link.
+    Tcp {
+        peer: SocketAddr,
+        reply: mpsc::Sender<Bytes>,
+    },
+}
+
+impl BusOrigin {
+    /// Returns the remote address of the sender.
+    pub fn addr(&self) -> SocketAddr {
+        match self {
+            BusOrigin::Udp(addr) => *addr,
+            BusOrigin::Tcp { peer, .. } => *peer,
+        }
+    }
+}
+
+/// An envelope received from a peer, not yet verified, along with its origin.
+pub type InboundMessage = (SecureGossipMessage, BusOrigin);
+
+/// The sending half of the cluster bus, shared by the gossip and failover logic.
+pub struct ClusterBus {
+    udp: Arc<UdpSocket>,
+    options: BusOptions,
+    tls_connector: Option<TlsConnector>,
+    links: DashMap<SocketAddr, mpsc::Sender<Bytes>>,
+    inbound_tx: mpsc::Sender<InboundMessage>,
+}
+
+impl ClusterBus {
+    /// Binds the bus on `port` for both UDP and TCP and starts accepting messages.
+    /// Received envelopes are delivered, unverified, through the returned receiver.
+    pub async fn bind(
+        port: u16,
+        options: BusOptions,
+    ) -> Result<(Arc<Self>, mpsc::Receiver<InboundMessage>), SpinelDBError> {
+        let addr = format!("0.0.0.0:{port}");
+        let udp = Arc::new(UdpSocket::bind(&addr).await?);
+        let (inbound_tx, inbound_rx) = mpsc::channel(LINK_QUEUE_CAPACITY);
+
+        let tls_connector = options.tls_acceptor.as_ref().map(|_| {
+            let mut root_cert_store = rustls::RootCertStore::empty();
+            root_cert_store.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+            let tls_config = rustls::ClientConfig::builder()
+                .with_root_certificates(root_cert_store)
+                .with_no_client_auth();
+            TlsConnector::from(Arc::new(tls_config))
+        });
+
+        if options.tcp_enabled {
+            // Use the port UDP actually bound, in case `port` was 0.
+            let tcp_addr = format!("0.0.0.0:{}", udp.local_addr()?.port());
+            let listener = TcpListener::bind(&tcp_addr).await?;
+            tokio::spawn(accept_loop(
+                listener,
+                options.tls_acceptor.clone(),
+                inbound_tx.clone(),
+            ));
+        }
+        tokio::spawn(udp_receive_loop(udp.clone(), inbound_tx.clone()));
+
+        Ok((
+            Arc::new(Self {
+                udp,
+                options,
+                tls_connector,
+                links: DashMap::new(),
+                inbound_tx,
+            }),
+            inbound_rx,
+        ))
+    }
+
+    /// Returns the local UDP address of the bus.
+    pub fn local_addr(&self) -> Result<SocketAddr, SpinelDBError> {
+        Ok(self.udp.local_addr()?)
+    }
+
+    /// Signs `message` and sends it to the bus address `target`, choosing the
+    /// transport from the message class and its encoded size.
+    pub async fn send(
+        &self,
+        message: GossipMessage,
+        target: SocketAddr,
+        password: &Option<String>,
+    ) {
+        let use_udp = self.prefers_udp(&message);
+        let Some(encoded) = encode(message, password) else {
+            return;
+        };
+        if use_udp && (encoded.len() <= UDP_MAX_PAYLOAD_SIZE || !self.options.tcp_enabled) {
+            if let Err(e) = self.udp.send_to(&encoded, &target).await {
+                warn!("Failed to send gossip datagram to {}: {}", target, e);
+            }
+        } else {
+            self.send_frame(encoded, target);
+        }
+    }
+
+    /// Like `send`, but resolves a node's announced `host:port` bus address first.
+    pub async fn send_to_node(
+        &self,
+        message: GossipMessage,
+        bus_addr: &str,
+        password: &Option<String>,
+    ) {
+        let target = match bus_addr.parse::<SocketAddr>() {
+            Ok(addr) => Some(addr),
+            Err(_) => tokio::net::lookup_host(bus_addr)
+                .await
+                .ok()
+                .and_then(|mut addrs| addrs.next()),
+        };
+        match target {
+            Some(target) => self.send(message, target, password).await,
+            None => warn!("Cannot resolve cluster bus address '{}'.", bus_addr),
+        }
+    }
+
+    /// Signs `message` and sends it back to where `origin` came from.
+    pub async fn reply(
+        &self,
+        message: GossipMessage,
+        origin: &BusOrigin,
+        password: &Option<String>,
+    ) {
+        match origin {
+            BusOrigin::Udp(addr) => self.send(message, *addr, password).await,
+            BusOrigin::Tcp { peer, reply } => {
+                if let Some(encoded) = encode(message, password)
+                    && reply.try_send(encoded).is_err()
+                {
+                    warn!("Dropping gossip reply to {}: link is closed or full.", peer);
+                }
+            }
+        }
+    }
+
+    /// Returns `true` if `message` should be sent over UDP when it fits in a datagram.
+    fn prefers_udp(&self, message: &GossipMessage) -> bool {
+        if !self.options.tcp_enabled {
+            return true;
+        }
+        self.options.udp_heartbeats
+            && matches!(
+                message,
+                GossipMessage::Meet { .. }
+                    | GossipMessage::Ping { .. }
+                    | GossipMessage::Pong { .. }
+            )
+    }
+
+    /// Queues an encoded frame on the persistent link to `target`, opening it if needed.
+    fn send_frame(&self, frame: Bytes, target: SocketAddr) {
+        let sender = self
+            .links
+            .entry(target)
+            .or_insert_with(|| self.open_link(target))
+            .clone();
+        if let Err(e) = sender.try_send(frame) {
+            match e {
+                mpsc::error::TrySendError::Full(_) => {
+                    warn!(
+                        "Cluster bus link to {} is backlogged. Dropping message.",
+                        target
+                    );
+                }
+                mpsc::error::TrySendError::Closed(frame) => {
+                    let sender = self.open_link(target);
+                    let _ = sender.try_send(frame);
+                    self.links.insert(target, sender);
+                }
+            }
+        }
+    }
+
+    /// Spawns the task that owns the outbound link to `target`.
+    fn open_link(&self, target: SocketAddr) -> mpsc::Sender<Bytes> {
+        let (tx, rx) = mpsc::channel(LINK_QUEUE_CAPACITY);
+        tokio::spawn(run_outbound_link(
+            target,
+            self.tls_connector.clone(),
+            rx,
+            tx.downgrade(),
+            self.inbound_tx.clone(),
+        ));
+        tx
+    }
+}
+
+/// Signs and encodes a message into a `SecureGossipMessage` envelope.
+fn encode(message: GossipMessage, password: &Option<String>) -> Option<Bytes> {
+    let secure_message = match SecureGossipMessage::new(message, password) {
+        Ok(m) => m,
+        Err(e) => {
+            warn!("Failed to sign gossip message: {}", e);
+            return None;
+        }
+    };
+    match bincode::encode_to_vec(&secure_message, config::standard()) {
+        Ok(encoded) => Some(Bytes::from(encoded)),
+        Err(e) => {
+            warn!("Failed to serialize gossip message: {}", e);
+            None
+        }
+    }
+}
+
+/// Decodes an envelope, without verifying its signature.
+fn decode(buf: &[u8], src: SocketAddr) -> Option<SecureGossipMessage> {
+    match bincode::decode_from_slice::<SecureGossipMessage, _>(buf, config::standard()) {
+        Ok((secure_msg, _)) => Some(secure_msg),
+        Err(e) => {
+            warn!(
+                "Failed to deserialize secure gossip message from {}: {}",
+                src, e
+            );
+            None
+        }
+    }
+}
+
+async fn udp_receive_loop(socket: Arc<UdpSocket>, inbound_tx: mpsc::Sender<InboundMessage>) {
+    let mut buf = vec![0; UDP_BUFFER_SIZE];
+    loop {
+        match socket.recv_from(&mut buf).await {
+            Ok((len, src)) => {
+                if let Some(msg) = decode(&buf[..len], src)
+                    && inbound_tx.send((msg, BusOrigin::Udp(src))).await.is_err()
+                {
+                    return;
+                }
+            }
+            Err(e) => warn!("Error receiving from cluster bus: {}", e),
+        }
+    }
+}
+
+/// A byte stream on the cluster bus, either plain TCP or TLS.
+trait BusStream: AsyncRead + AsyncWrite + Unpin + Send {}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> BusStream for T {}
+
+async fn accept_loop(
+    listener: TcpListener,
+    acceptor: Option<TlsAcceptor>,
+    inbound_tx: mpsc::Sender<InboundMessage>,
+) {
+    loop {
+        let (socket, peer) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                warn!("Error accepting cluster bus connection: {}", e);
+                continue;
+            }
+        };
+        let acceptor = acceptor.clone();
+        let inbound_tx = inbound_tx.clone();
+        tokio::spawn(async move {
+            let _ = socket.set_nodelay(true);
+            let stream: Box<dyn BusStream> = match acceptor {
+                Some(acceptor) => match acceptor.accept(socket).await {
+                    Ok(tls_stream) => Box::new(tls_stream),
+                    Err(e) => {
+                        warn!(
+                            "TLS handshake failed on cluster bus link from {}: {}",
+                            peer, e
+                        );
+                        return;
+                    }
+                },
+                None => Box::new(socket),
+            };
+            debug!("Accepted cluster bus link from {}", peer);
+            // Holding a sender keeps the link open until the peer disconnects.
+            let (reply_tx, mut reply_rx) = mpsc::channel(LINK_QUEUE_CAPACITY);
+            let mut pending = None;
+            let _ = drive_link(
+                stream,
+                peer,
+                &mut reply_rx,
+                &mut pending,
+                reply_tx.downgrade(),
+                &inbound_tx,
+            )
+            .await;
+            debug!("Cluster bus link from {} closed.", peer);
+        });
+    }
+}
+
+async fn run_outbound_link(
+    target: SocketAddr,
+    tls_connector: Option<TlsConnector>,
+    mut rx: mpsc::Receiver<Bytes>,
+    weak_tx: mpsc::WeakSender<Bytes>,
+    inbound_tx: mpsc::Sender<InboundMessage>,
+) {
+    let mut delay = INITIAL_RECONNECT_DELAY;
+    // A frame that was dequeued but not fully written, resent after reconnecting.
+    let mut pending: Option<Bytes> = None;
+    loop {
+        if pending.is_none() && rx.is_closed() && rx.is_empty() {
+            return;
+        }
+        match connect(target, tls_connector.as_ref()).await {
+            Ok(stream) => {
+                info!("Cluster bus link to {} established.", target);
+                delay = INITIAL_RECONNECT_DELAY;
+                match drive_link(
+                    stream,
+                    target,
+                    &mut rx,
+                    &mut pending,
+                    weak_tx.clone(),
+                    &inbound_tx,
+                )
+                .await
+                {
+                    Ok(()) => return,
+                    Err(e) => warn!("Cluster bus link to {} lost: {}", target, e),
+                }
+            }
+            Err(e) => debug!("Failed to connect cluster bus link to {}: {}", target, e),
+        }
+        time::sleep(delay).await;
+        delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+    }
+}
+
+async fn connect(
+    target: SocketAddr,
+    tls_connector: Option<&TlsConnector>,
+) -> Result<Box<dyn BusStream>, SpinelDBError> {
+    let socket = time::timeout(CONNECT_TIMEOUT, TcpStream::connect(target))
+        .await
+        .map_err(|_| SpinelDBError::Internal("connection timed out".into()))??;
+    let _ = socket.set_nodelay(true);
+    match tls_connector {
+        Some(connector) => {
+            let domain = rustls::pki_types::ServerName::from(target.ip());
+            let tls_stream = connector
+                .connect(domain, socket)
+                .await
+                .map_err(|e| SpinelDBError::Internal(format!("TLS handshake failed: {e}")))?;
+            Ok(Box::new(tls_stream))
+        }
+        None => Ok(Box::new(socket)),
+    }
+}
+
+/// Runs one connected link until it breaks. Frames from `rx` are written to the peer
+/// and frames from the peer are delivered to `inbound_tx`. Returns `Ok(())` if the
+/// link is no longer needed.
+async fn drive_link(
+    stream: Box<dyn BusStream>,
+    peer: SocketAddr,
+    rx: &mut mpsc::Receiver<Bytes>,
+    pending: &mut Option<Bytes>,
+    weak_tx: mpsc::WeakSender<Bytes>,
+    inbound_tx: &mpsc::Sender<InboundMessage>,
+) -> Result<(), SpinelDBError> {
+    let (mut reader, mut writer) = tokio::io::split(stream);
+
+    let write_loop = async {
+        loop {
+            let frame = match pending.take() {
+                Some(frame) => frame,
+                None => match rx.recv().await {
+                    Some(frame) => frame,
+                    None => return Ok(()),
+                },
+            };
+            if let Err(e) = write_frame(&mut writer, &frame).await {
+                *pending = Some(frame);
+                return Err(e);
+            }
+        }
+    };
+
+    let read_loop = async {
+        loop {
+            let frame = read_frame(&mut reader).await?;
+            let Some(msg) = decode(&frame, peer) else {
+                continue;
+            };
+            let Some(reply) = weak_tx.upgrade() else {
+                return Ok(());
+            };
+            if inbound_tx
+                .send((msg, BusOrigin::Tcp { peer, reply }))
+                .await
+                .is_err()
+            {
+                return Ok(());
+            }
+        }
+    };
+
+    tokio::select! {
+        result = write_loop => result,
+        result = read_loop => result,
+    }
+}
+
+async fn write_frame(
+    writer: &mut WriteHalf<Box<dyn BusStream>>,
+    frame: &[u8],
+) -> Result<(), SpinelDBError> {
+    writer.write_u32(frame.len() as u32).await?;
+    writer.write_all(frame).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+async fn read_frame(reader: &mut ReadHalf<Box<dyn BusStream>>) -> Result<Vec<u8>, SpinelDBError> {
+    let len = reader.read_u32().await? as usize;
+    if len > MAX_FRAME_SIZE {
+        return Err(SpinelDBError::Internal(format!(
+            "cluster bus frame of {len} bytes exceeds the limit"
+        )));
+    }
+    let mut buf = vec![0; len];
+    reader.read_exact(&mut buf).await?;
+    Ok(buf)
+}
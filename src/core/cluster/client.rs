@@ -5,6 +5,8 @@
 // Use the correct, refactored path for ClusterInfo and ClusterSubcommand.
 use crate::core::Command;
 use crate::core::commands::cluster::{ClusterInfo, ClusterSubcommand};
+use crate::core::commands::generic::client::ClientSubcommand;
+use crate::core::commands::generic::{Asking, Client, Del, Restore, Select};
 use crate::core::protocol::{RespFrame, RespFrameCodec};
 use anyhow::{Result, anyhow};
 use bytes::{Bytes, BytesMut};
@@ -18,6 +20,17 @@ use tokio_util::codec::{Decoder, Encoder};
 const CLIENT_CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
 const CLIENT_WRITE_TIMEOUT: Duration = Duration::from_secs(2);
 const CLIENT_READ_TIMEOUT: Duration = Duration::from_secs(3);
+/// How long to wait for a source node to finish migrating a slot atomically.
+const MIGRATE_SLOT_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// The state of a key sent to the destination of an atomic slot migration.
+#[derive(Debug, Clone)]
+pub struct SlotEntry {
+    pub key: Bytes,
+    /// The serialized value and TTL in milliseconds (0 for none), or `None` if the key
+    /// no longer exists and must be deleted on the destination.
+    pub value: Option<(Bytes, u64)>,
+}
 
 /// An internal client for sending commands to other nodes in the cluster.
 pub struct ClusterClient {
@@ -36,11 +49,47 @@ impl ClusterClient {
         })
     }
 
+    /// Sends `AUTH password`, for nodes that require a password.
+    pub async fn authenticate(&mut self, password: &str) -> Result<()> {
+        let frame = RespFrame::Array(vec![
+            RespFrame::BulkString("AUTH".into()),
+            RespFrame::BulkString(password.to_string().into()),
+        ]);
+        match self.send_and_receive(frame).await? {
+            RespFrame::SimpleString(s) if s == "OK" => Ok(()),
+            other => Err(anyhow!("Unexpected response to AUTH: {other:?}")),
+        }
+    }
+
+    /// Returns false if the peer closed the connection or sent unexpected data while
+    /// it was idle, so that the connection can no longer be reused.
+    pub fn is_reusable(&self) -> bool {
+        let mut buf = [0u8; 1];
+        matches!(
+            self.stream.try_read(&mut buf),
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock
+        )
+    }
+
     /// A generic method to send a single command frame and receive a single reply frame.
     async fn send_and_receive(&mut self, frame: RespFrame) -> Result<RespFrame> {
-        // 1. Encode the command into a byte buffer.
+        let mut replies = self.send_pipeline(vec![frame], CLIENT_READ_TIMEOUT).await?;
+        Ok(replies.remove(0))
+    }
+
+    /// Sends several command frames at once and receives one reply frame for each.
+    /// `read_timeout` applies to each read from the connection.
+    async fn send_pipeline(
+        &mut self,
+        frames: Vec<RespFrame>,
+        read_timeout: Duration,
+    ) -> Result<Vec<RespFrame>> {
+        // 1. Encode the commands into a byte buffer.
+        let expected = frames.len();
         let mut write_buf = BytesMut::new();
-        self.codec.encode(frame, &mut write_buf)?;
+        for frame in frames {
+            self.codec.encode(frame, &mut write_buf)?;
+        }
 
         // 2. Send the buffer to the target server with a write timeout.
         let write_fut = self.stream.write_all(&write_buf);
@@ -48,19 +97,22 @@ impl ClusterClient {
             .await
             .map_err(|_| anyhow!("Write timeout while sending command"))??;
 
-        // 3. Read the reply from the server in a loop.
+        // 3. Read the replies from the server in a loop.
+        let mut replies = Vec::with_capacity(expected);
         let mut read_buf = BytesMut::with_capacity(4096);
         loop {
+            // Decode as many full frames as the buffer holds.
+            while let Some(reply) = self.codec.decode(&mut read_buf)? {
+                replies.push(reply);
+                if replies.len() == expected {
+                    return Ok(replies);
+                }
+            }
             let read_fut = self.stream.read_buf(&mut read_buf);
-            match tokio::time::timeout(CLIENT_READ_TIMEOUT, read_fut).await {
+            match tokio::time::timeout(read_timeout, read_fut).await {
                 Ok(Ok(0)) => return Err(anyhow!("Connection closed by peer")),
-                Ok(Ok(_)) => {
-                    // Attempt to decode a full frame from the buffer.
-                    if let Some(reply) = self.codec.decode(&mut read_buf)? {
-                        return Ok(reply);
-                    }
-                    // If data is not yet complete, the loop continues.
-                }
+                // If data is not yet complete, the loop continues.
+                Ok(Ok(_)) => {}
                 Ok(Err(e)) => return Err(e.into()),
                 Err(_) => return Err(anyhow!("Read timeout while waiting for response")),
             }
@@ -80,6 +132,22 @@ impl ClusterClient {
         }
     }
 
+    /// Sends `INFO memory` and returns the `used_memory` the node reports.
+    pub async fn used_memory(&mut self) -> Result<u64> {
+        let frame = RespFrame::Array(vec![
+            RespFrame::BulkString("INFO".into()),
+            RespFrame::BulkString("memory".into()),
+        ]);
+        match self.send_and_receive(frame).await? {
+            RespFrame::BulkString(info) => String::from_utf8_lossy(&info)
+                .lines()
+                .find_map(|line| line.strip_prefix("used_memory:"))
+                .and_then(|value| value.trim().parse().ok())
+                .ok_or_else(|| anyhow!("INFO reply does not report used_memory")),
+            other => Err(anyhow!("Unexpected response to INFO: {other:?}")),
+        }
+    }
+
     /// Sends `CLUSTER GETKEYSINSLOT ...` and parses the resulting array of keys.
     pub async fn get_keys_in_slot(&mut self, slot: u16, count: usize) -> Result<Vec<Bytes>> {
         let frame = Command::Cluster(ClusterInfo {
@@ -131,4 +199,104 @@ impl ClusterClient {
             other => Err(anyhow!("Unexpected response to MIGRATE: {other:?}")),
         }
     }
+
+    /// Writes a batch of keys of a slot being imported into database `db_index`, in one
+    /// pipeline. Each key is preceded by `ASKING`, since the slot still belongs to the
+    /// source node.
+    pub async fn restore_slot_entries(
+        &mut self,
+        db_index: usize,
+        entries: Vec<SlotEntry>,
+    ) -> Result<()> {
+        let mut frames = Vec::with_capacity(entries.len() * 2 + 1);
+        frames.push(Command::Select(Select { db_index }).into());
+        for entry in entries {
+            frames.push(Command::Asking(Asking).into());
+            let command = match entry.value {
+                Some((serialized_value, ttl_ms)) => Command::Restore(Restore {
+                    key: entry.key,
+                    ttl_ms,
+                    serialized_value,
+                    replace: true,
+                }),
+                None => Command::Del(Del {
+                    keys: vec![entry.key],
+                }),
+            };
+            frames.push(command.into());
+        }
+
+        for reply in self.send_pipeline(frames, CLIENT_READ_TIMEOUT).await? {
+            match reply {
+                RespFrame::SimpleString(_) | RespFrame::Integer(_) => {}
+                other => {
+                    return Err(anyhow!(
+                        "Unexpected response while importing a slot: {other:?}"
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Sends the sub-requests of a fanned-out command in one pipeline and returns their
+    /// replies. Fan-out is disabled on the connection first, so that the receiving node
+    /// executes each sub-request itself, and database `db_index` is selected, as pooled
+    /// connections may have been used for another database before. If `asking` is set,
+    /// each sub-request is preceded by `ASKING`, to follow an `ASK` redirect.
+    pub async fn send_sub_requests(
+        &mut self,
+        db_index: usize,
+        commands: Vec<Command>,
+        asking: bool,
+    ) -> Result<Vec<RespFrame>> {
+        let mut frames = Vec::with_capacity(2 * commands.len() + 2);
+        frames.push(
+            Command::Client(Client {
+                subcommand: ClientSubcommand::CrossSlot(false),
+            })
+            .into(),
+        );
+        frames.push(Command::Select(Select { db_index }).into());
+        for command in commands {
+            if asking {
+                frames.push(Command::Asking(Asking).into());
+            }
+            frames.push(command.into());
+        }
+
+        let mut replies = self.send_pipeline(frames, CLIENT_READ_TIMEOUT).await?;
+        for (name, reply) in ["CLIENT CROSSSLOT", "SELECT"]
+            .into_iter()
+            .zip(replies.drain(..2))
+        {
+            if !matches!(reply, RespFrame::SimpleString(_)) {
+                return Err(anyhow!("Unexpected response to {name}: {reply:?}"));
+            }
+        }
+        if asking {
+            // Drop the reply to each `ASKING`, which precedes the reply to its command.
+            replies = replies.into_iter().skip(1).step_by(2).collect();
+        }
+        Ok(replies)
+    }
+
+    /// Sends `CLUSTER MIGRATESLOT` to the source node of a slot and waits for it to
+    /// finish. Returns the number of keys the source sent.
+    pub async fn migrate_slot(&mut self, slot: u16, dest_id: String) -> Result<i64> {
+        let frame = Command::Cluster(ClusterInfo {
+            subcommand: ClusterSubcommand::MigrateSlot { slot, dest_id },
+        })
+        .into();
+
+        let mut replies = self
+            .send_pipeline(vec![frame], MIGRATE_SLOT_TIMEOUT)
+            .await?;
+        match replies.remove(0) {
+            RespFrame::Integer(keys_sent) => Ok(keys_sent),
+            other => Err(anyhow!(
+                "Unexpected response to CLUSTER MIGRATESLOT: {other:?}"
+            )),
+        }
+    }
 }
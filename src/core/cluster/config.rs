@@ -45,6 +45,29 @@ pub struct ClusterConfig {
     /// NOT recommended for production. Use the external Warden process for safe failover.
     #[serde(default)]
     pub replica_initiated_failover: bool,
+
+    /// If `true`, messages that must not be lost (tag purges, config updates, failover
+    /// votes, fail reports and oversized pings) are sent over a persistent TCP link
+    /// to each peer on the bus port.
+    #[serde(default = "default_true")]
+    pub bus_tcp_enabled: bool,
+
+    /// If `true`, heartbeats that fit in a single datagram are sent over UDP as a
+    /// fast path. Has no effect when `bus_tcp_enabled` is `false`, as all traffic
+    /// then uses UDP.
+    #[serde(default = "default_true")]
+    pub bus_udp_heartbeats: bool,
+
+    /// If `true`, the TCP cluster bus links are encrypted with the server's `[tls]`
+    /// certificate. Requires `tls.enabled`.
+    #[serde(default)]
+    pub bus_tls: bool,
+
+    /// If `true`, new connections start with `CLIENT CROSSSLOT ON`, so `MGET`, `EXISTS`,
+    /// `DEL`, `UNLINK` and `DBSIZE` are fanned out to the nodes owning their keys
+    /// instead of failing with `CROSSSLOT`.
+    #[serde(default)]
+    pub cross_slot_fanout: bool,
 }
 
 impl Default for ClusterConfig {
@@ -59,6 +82,10 @@ impl Default for ClusterConfig {
             bus_port_offset: 10000,
             failover_quorum: 2, // A safe default for a minimal 3-master setup.
             replica_initiated_failover: false, // Default to OFF for production safety.
+            bus_tcp_enabled: true,
+            bus_udp_heartbeats: true,
+            bus_tls: false,
+            cross_slot_fanout: false,
         }
     }
 }
@@ -75,3 +102,6 @@ fn default_bus_port_offset() -> u16 {
 fn default_failover_quorum() -> usize {
     2
 }
+fn default_true() -> bool {
+    true
+}
@@ -1,25 +1,31 @@
 // src/core/cluster/failover.rs
 
 use crate::config::{ReplicationConfig, ReplicationPrimaryConfig};
-use crate::core::cluster::gossip::{GossipMessage, now_ms};
-use crate::core::cluster::secure_gossip::SecureGossipMessage;
+use crate::core::SpinelDBError;
+use crate::core::cluster::bus::{BusOrigin, ClusterBus};
+use crate::core::cluster::gossip::{GossipMessage, GossipTaskMessage, now_ms};
 use crate::core::cluster::state::NodeFlags;
 use crate::core::state::ServerState;
-use bincode::config;
 use rand::Rng;
 use std::sync::Arc;
 use std::sync::atomic::Ordering;
-use std::time::Duration;
-use tokio::net::UdpSocket;
+use std::time::{Duration, Instant};
 use tracing::{info, warn};
 
+/// How long each step of a manual failover may take before it is abandoned.
+const MANUAL_FAILOVER_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long a primary pauses writes for a manual failover. This covers both the
+/// catch-up and the election, after which writes resume even if the failover failed.
+const MANUAL_FAILOVER_PAUSE_MS: u64 = 2 * MANUAL_FAILOVER_TIMEOUT.as_millis() as u64;
+const MANUAL_FAILOVER_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
 /// The base delay before a replica initiates a failover election.
 /// A random delay is added to this to prevent multiple replicas from starting an election simultaneously.
 const FAILOVER_BASE_DELAY_MS: u64 = 500;
 
 /// This function is called periodically by the cluster cron job (`probe_tick` in gossip.rs).
 /// It checks if this node is a replica and if its master is in a failure state.
-pub async fn handle_failover_cron(state: &Arc<ServerState>, socket: &Arc<UdpSocket>) {
+pub async fn handle_failover_cron(state: &Arc<ServerState>, bus: &Arc<ClusterBus>) {
     // Check if the replica-initiated failover feature is enabled in the configuration.
     // If not, this function does nothing, making Warden the only failover mechanism.
     if !state.config.lock().await.cluster.replica_initiated_failover {
@@ -38,12 +44,12 @@ pub async fn handle_failover_cron(state: &Arc<ServerState>, socket: &Arc<UdpSock
             .get_flags()
             .intersects(NodeFlags::FAIL | NodeFlags::PFAIL)
     {
-        start_election(state, socket).await;
+        start_election(state, bus).await;
     }
 }
 
 /// Starts the election process for this replica to become a new master.
-async fn start_election(state: &Arc<ServerState>, socket: &Arc<UdpSocket>) {
+async fn start_election(state: &Arc<ServerState>, bus: &Arc<ClusterBus>) {
     let cluster = state
         .cluster
         .as_ref()
@@ -56,13 +62,7 @@ async fn start_election(state: &Arc<ServerState>, socket: &Arc<UdpSocket>) {
     }
 
     if let Some(my_master_id) = &cluster.get_my_config().node_info.replica_of {
-        let my_offset = state
-            .replication
-            .replica_info
-            .lock()
-            .await
-            .as_ref()
-            .map_or(0, |i| i.processed_offset);
+        let my_offset = replica_processed_offset(state).await;
 
         for entry in cluster.nodes.iter() {
             let other_node = &entry.value().node_info;
@@ -98,56 +98,70 @@ async fn start_election(state: &Arc<ServerState>, socket: &Arc<UdpSocket>) {
         return;
     }
 
+    let auth_request = begin_election(state).await;
+    let password = state.config.lock().await.password.clone();
+
+    let primaries: Vec<_> = cluster
+        .nodes
+        .iter()
+        .map(|entry| entry.value().node_info.clone())
+        .filter(|node| {
+            node.get_flags().contains(NodeFlags::PRIMARY)
+                && !node.get_flags().contains(NodeFlags::MYSELF)
+        })
+        .collect();
+    for node in primaries {
+        bus.send_to_node(auth_request.clone(), &node.bus_addr, &password)
+            .await;
+    }
+}
+
+/// Opens a new election epoch with this replica's own vote counted, and returns the
+/// vote request to send to the primaries.
+async fn begin_election(state: &Arc<ServerState>) -> GossipMessage {
+    let cluster = state
+        .cluster
+        .as_ref()
+        .expect("begin_election must run in cluster mode");
     let new_epoch = cluster.get_new_config_epoch();
     cluster
         .failover_auth_time
-        .store(now_unix_ms, Ordering::Relaxed);
+        .store(now_ms(), Ordering::Relaxed);
     cluster.failover_auth_count.store(1, Ordering::Relaxed); // Count own vote.
     cluster
         .failover_auth_epoch
         .store(new_epoch, Ordering::Relaxed);
 
-    let my_offset = state
-        .replication
-        .replica_info
-        .lock()
-        .await
-        .as_ref()
-        .map_or(0, |info| info.processed_offset);
+    let my_offset = replica_processed_offset(state).await;
 
     info!(
         "Starting a new election for epoch {} with offset {}",
         new_epoch, my_offset
     );
 
-    let password = &state.config.lock().await.password;
-
-    let auth_request = GossipMessage::FailoverAuthRequest {
+    GossipMessage::FailoverAuthRequest {
         sender_id: cluster.my_id.clone(),
         config_epoch: new_epoch,
         replication_offset: my_offset,
         timestamp_ms: now_ms(),
-    };
-
-    if let Ok(secure_request) = SecureGossipMessage::new(auth_request, password) {
-        let bincode_config = config::standard();
-        if let Ok(encoded_msg) = bincode::encode_to_vec(&secure_request, bincode_config) {
-            for entry in cluster.nodes.iter() {
-                let node = &entry.value().node_info;
-                if node.get_flags().contains(NodeFlags::PRIMARY)
-                    && !node.get_flags().contains(NodeFlags::MYSELF)
-                {
-                    let _ = socket.send_to(&encoded_msg, &node.bus_addr).await;
-                }
-            }
-        }
     }
 }
 
+/// Returns the replication offset this replica has applied so far.
+async fn replica_processed_offset(state: &Arc<ServerState>) -> u64 {
+    state
+        .replication
+        .replica_info
+        .lock()
+        .await
+        .as_ref()
+        .map_or(0, |info| info.processed_offset)
+}
+
 /// Handles a vote request from another replica that is running for election.
 pub async fn handle_auth_request(
     state: &Arc<ServerState>,
-    socket: &Arc<UdpSocket>,
+    bus: &Arc<ClusterBus>,
     candidate_id: String,
     candidate_epoch: u64,
     candidate_offset: u64,
@@ -189,7 +203,7 @@ pub async fn handle_auth_request(
             candidate_id, candidate_epoch
         );
 
-        let password = &state.config.lock().await.password;
+        let password = state.config.lock().await.password.clone();
 
         let ack_msg = GossipMessage::FailoverAuthAck {
             sender_id: cluster.my_id.clone(),
@@ -197,15 +211,12 @@ pub async fn handle_auth_request(
             timestamp_ms: now_ms(),
         };
 
-        if let Ok(secure_ack) = SecureGossipMessage::new(ack_msg, password) {
-            let bincode_config = config::standard();
-            if let Some(candidate_node) = cluster.nodes.get(&candidate_id)
-                && let Ok(encoded) = bincode::encode_to_vec(&secure_ack, bincode_config)
-            {
-                let _ = socket
-                    .send_to(&encoded, &candidate_node.node_info.bus_addr)
-                    .await;
-            }
+        let candidate_addr = cluster
+            .nodes
+            .get(&candidate_id)
+            .map(|candidate_node| candidate_node.node_info.bus_addr.clone());
+        if let Some(addr) = candidate_addr {
+            bus.send_to_node(ack_msg, &addr, &password).await;
         }
     } else {
         warn!(
@@ -250,6 +261,213 @@ pub async fn handle_auth_ack(state: &Arc<ServerState>, sender_id: String, ack_ep
     }
 }
 
+/// How a manual `CLUSTER FAILOVER` takes over from the primary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailoverMode {
+    /// Pauses writes on the primary, waits for this replica to reach the primary's
+    /// offset, then wins an election. No acknowledged write is lost.
+    Coordinated,
+    /// Skips the handshake with the primary, which may be unreachable, and starts an
+    /// election right away. Writes the replica has not received are lost.
+    Force,
+    /// Promotes this replica without a handshake or an election by bumping the config
+    /// epoch locally. For when a majority of primaries cannot vote.
+    Takeover,
+}
+
+/// Runs a manual failover started by `CLUSTER FAILOVER` on this replica. Returns once
+/// this node is the primary, or with an error if a step did not finish in time.
+pub async fn run_manual_failover(
+    state: &Arc<ServerState>,
+    mode: FailoverMode,
+) -> Result<(), SpinelDBError> {
+    let cluster = state
+        .cluster
+        .as_ref()
+        .expect("run_manual_failover must run in cluster mode");
+    let primary_bus_addr = {
+        let myself = cluster.get_my_config();
+        myself
+            .node_info
+            .replica_of
+            .as_ref()
+            .and_then(|primary_id| cluster.nodes.get(primary_id))
+            .map(|primary| primary.node_info.bus_addr.clone())
+    }
+    .ok_or_else(|| SpinelDBError::InvalidState("This node's primary is unknown".into()))?;
+
+    if mode == FailoverMode::Coordinated {
+        *cluster.manual_failover_primary_offset.write() = None;
+        let start = GossipMessage::ManualFailoverStart {
+            sender_id: cluster.my_id.clone(),
+            timestamp_ms: now_ms(),
+        };
+        send_to_gossip_task(
+            state,
+            GossipTaskMessage::SendToNode {
+                message: start,
+                bus_addr: primary_bus_addr,
+            },
+        );
+        info!("[MANUAL FAILOVER] Asked the primary to pause writes.");
+
+        let caught_up = wait_until(MANUAL_FAILOVER_TIMEOUT, || async {
+            let Some(primary_offset) = *cluster.manual_failover_primary_offset.read() else {
+                return false;
+            };
+            replica_processed_offset(state).await >= primary_offset
+        })
+        .await;
+        if !caught_up {
+            return Err(SpinelDBError::InvalidState(
+                "Manual failover timed out waiting to catch up with the primary".into(),
+            ));
+        }
+        info!("[MANUAL FAILOVER] Reached the primary's replication offset.");
+    }
+
+    if mode == FailoverMode::Takeover {
+        cluster
+            .failover_auth_epoch
+            .store(cluster.get_new_config_epoch(), Ordering::Relaxed);
+        promote_to_master_internal(state)
+            .await
+            .map_err(|e| SpinelDBError::Internal(format!("Failed to take over: {e}")))?;
+    } else {
+        let auth_request = begin_election(state).await;
+        send_to_gossip_task(state, GossipTaskMessage::Broadcast(auth_request));
+        let elected = wait_until(MANUAL_FAILOVER_TIMEOUT, || async { is_primary(state) }).await;
+        if !elected {
+            return Err(SpinelDBError::InvalidState(
+                "Manual failover timed out waiting for votes from the primaries".into(),
+            ));
+        }
+    }
+    info!(
+        "[MANUAL FAILOVER] Promoted to primary in epoch {}.",
+        cluster.get_my_config().node_info.config_epoch
+    );
+
+    // Announce the new configuration right away instead of waiting for the next
+    // gossip round, so the old primary steps down and clients are redirected.
+    let announcement = GossipMessage::Ping {
+        sender_id: cluster.my_id.clone(),
+        gossip_nodes: vec![cluster.get_my_config().node_info.clone()],
+        timestamp_ms: now_ms(),
+    };
+    send_to_gossip_task(state, GossipTaskMessage::Broadcast(announcement));
+    Ok(())
+}
+
+/// Handles a replica's request to pause writes for a manual failover, replying with
+/// this primary's replication offset once the pause is in place.
+pub async fn handle_manual_failover_start(
+    state: &Arc<ServerState>,
+    bus: &Arc<ClusterBus>,
+    origin: &BusOrigin,
+    sender_id: String,
+) {
+    let cluster = state
+        .cluster
+        .as_ref()
+        .expect("handle_manual_failover_start must run in cluster mode");
+    if !is_primary(state) {
+        return;
+    }
+    let is_my_replica = cluster
+        .nodes
+        .get(&sender_id)
+        .is_some_and(|node| node.node_info.replica_of.as_deref() == Some(&cluster.my_id));
+    if !is_my_replica {
+        warn!("Ignoring manual failover request from {sender_id}, which is not my replica.");
+        return;
+    }
+
+    cluster.pause_writes(now_ms() + MANUAL_FAILOVER_PAUSE_MS);
+    // Writes admitted before the pause may still be running. They propagate before
+    // they finish, so once none are left the offset covers every one of them.
+    let deadline = Instant::now() + MANUAL_FAILOVER_TIMEOUT;
+    if !cluster.wait_for_writes_in_flight(deadline).await {
+        warn!(
+            "Writes in progress did not finish in time for a manual failover by replica {}. Resuming writes.",
+            sender_id
+        );
+        cluster.resume_writes();
+        return;
+    }
+    let replication_offset = state
+        .replication
+        .current_write_offset(state, Some(deadline))
+        .await;
+    info!(
+        "Pausing writes for a manual failover by replica {} at offset {}.",
+        sender_id, replication_offset
+    );
+
+    let password = state.config.lock().await.password.clone();
+    let ack = GossipMessage::ManualFailoverAck {
+        sender_id: cluster.my_id.clone(),
+        replication_offset,
+        timestamp_ms: now_ms(),
+    };
+    bus.reply(ack, origin, &password).await;
+}
+
+/// Records the offset reported by this replica's primary for a manual failover.
+pub fn handle_manual_failover_ack(
+    state: &Arc<ServerState>,
+    sender_id: String,
+    replication_offset: u64,
+) {
+    let cluster = state
+        .cluster
+        .as_ref()
+        .expect("handle_manual_failover_ack must run in cluster mode");
+    let from_my_primary =
+        cluster.get_my_config().node_info.replica_of.as_deref() == Some(sender_id.as_str());
+    if from_my_primary {
+        *cluster.manual_failover_primary_offset.write() = Some(replication_offset);
+    }
+}
+
+/// Returns true if this node is currently a primary.
+fn is_primary(state: &Arc<ServerState>) -> bool {
+    state.cluster.as_ref().is_some_and(|cluster| {
+        cluster
+            .get_my_config()
+            .node_info
+            .get_flags()
+            .contains(NodeFlags::PRIMARY)
+    })
+}
+
+/// Polls `condition` until it holds, returning false if `timeout` elapses first.
+async fn wait_until<F, Fut>(timeout: Duration, mut condition: F) -> bool
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = bool>,
+{
+    let deadline = tokio::time::Instant::now() + timeout;
+    loop {
+        if condition().await {
+            return true;
+        }
+        if tokio::time::Instant::now() >= deadline {
+            return false;
+        }
+        tokio::time::sleep(MANUAL_FAILOVER_POLL_INTERVAL).await;
+    }
+}
+
+fn send_to_gossip_task(state: &Arc<ServerState>, message: GossipTaskMessage) {
+    if let Err(e) = state.cluster_gossip_tx.try_send(message) {
+        warn!(
+            "Failed to send manual failover message to gossip worker, it may be busy or shut down: {}",
+            e
+        );
+    }
+}
+
 use anyhow::Result;
 use tracing::error;
 
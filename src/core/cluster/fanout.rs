@@ -0,0 +1,328 @@
+// src/core/cluster/fanout.rs
+
+//! Implements server-side fan-out of multi-key commands whose keys span several slots.
+//!
+//! A client that sent `CLIENT CROSSSLOT ON` (or connected to a node with
+//! `cross_slot_fanout` enabled) may send `MGET`, `EXISTS`, `DEL` or `UNLINK` with keys
+//! in any slots, and `DBSIZE` counts the keys of the whole cluster. The receiving node
+//! splits the command into one sub-request per slot, sends each to the node owning the
+//! slot, and merges the replies as if a single node had executed the command.
+//! Connections to other nodes are authenticated with the password the client
+//! authenticated with, so that sub-requests run as the client's user, and kept in a
+//! `FanoutPool` for later sub-requests with the same password.
+
+use super::client::ClusterClient;
+use super::slot::get_slot;
+use super::state::ClusterState;
+use crate::core::commands::generic::{DbSize, Del, Exists, Unlink};
+use crate::core::commands::string::MGet;
+use crate::core::{Command, RespValue, SpinelDBError};
+use bytes::Bytes;
+use futures::future::try_join_all;
+use parking_lot::Mutex;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::net::SocketAddr;
+
+/// The maximum number of idle connections kept to each node.
+const MAX_IDLE_CONNECTIONS_PER_NODE: usize = 8;
+
+/// The address of a node and the password a connection to it authenticated with.
+type PoolKey = (SocketAddr, Option<String>);
+
+/// Idle connections to other nodes, reused by fanned-out commands. Connections are
+/// kept per node and per password they authenticated with, so that they are only
+/// reused on behalf of the same user.
+#[derive(Default)]
+pub struct FanoutPool {
+    idle: Mutex<HashMap<PoolKey, Vec<ClusterClient>>>,
+}
+
+impl FanoutPool {
+    /// Takes an idle connection to `addr`, or opens a new one and authenticates it
+    /// with `password`.
+    async fn checkout(
+        &self,
+        addr: SocketAddr,
+        password: Option<&str>,
+    ) -> anyhow::Result<ClusterClient> {
+        let key = (addr, password.map(str::to_string));
+        loop {
+            let client = self.idle.lock().get_mut(&key).and_then(Vec::pop);
+            match client {
+                Some(client) if client.is_reusable() => return Ok(client),
+                Some(_) => continue,
+                None => break,
+            }
+        }
+        let mut client = ClusterClient::connect(addr).await?;
+        if let Some(password) = password {
+            client.authenticate(password).await?;
+        }
+        Ok(client)
+    }
+
+    /// Returns a connection authenticated with `password` that completed its requests
+    /// to the pool.
+    fn checkin(&self, addr: SocketAddr, password: Option<&str>, client: ClusterClient) {
+        let mut idle = self.idle.lock();
+        let clients = idle
+            .entry((addr, password.map(str::to_string)))
+            .or_default();
+        if clients.len() < MAX_IDLE_CONNECTIONS_PER_NODE {
+            clients.push(client);
+        }
+    }
+}
+
+impl std::fmt::Debug for FanoutPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let idle: usize = self.idle.lock().values().map(Vec::len).sum();
+        f.debug_struct("FanoutPool").field("idle", &idle).finish()
+    }
+}
+
+/// A part of a fanned-out command, executed by the owner of the keys it contains.
+#[derive(Debug, Clone)]
+pub struct SubRequest {
+    /// The ID of the node that must execute the sub-request.
+    pub node_id: String,
+    pub command: Command,
+    /// The positions of the sub-request's keys in the original command.
+    pub positions: Vec<usize>,
+}
+
+/// Returns true if `command` must be fanned out: it is a command that supports it and
+/// either spans several slots or, for `DBSIZE`, concerns the whole cluster.
+pub fn should_fan_out(command: &Command, keys: &[Bytes]) -> bool {
+    match command {
+        Command::DbSize(_) => true,
+        Command::MGet(_) | Command::Exists(_) | Command::Del(_) | Command::Unlink(_) => {
+            let first_slot = keys.first().map(get_slot);
+            keys.iter().any(|key| Some(get_slot(key)) != first_slot)
+        }
+        _ => false,
+    }
+}
+
+/// Splits `command` into one sub-request per slot, or for `DBSIZE`, one per primary
+/// that owns slots.
+pub fn scatter(
+    cluster: &ClusterState,
+    command: &Command,
+) -> Result<Vec<SubRequest>, SpinelDBError> {
+    let keys = match command {
+        Command::MGet(cmd) => &cmd.keys,
+        Command::Exists(cmd) => &cmd.keys,
+        Command::Del(cmd) => &cmd.keys,
+        Command::Unlink(cmd) => &cmd.keys,
+        Command::DbSize(_) => {
+            let owners: BTreeSet<String> = cluster
+                .slots_map
+                .iter()
+                .filter_map(|owner| owner.read().clone())
+                .collect();
+            return Ok(owners
+                .into_iter()
+                .map(|node_id| SubRequest {
+                    node_id,
+                    command: Command::DbSize(DbSize),
+                    positions: Vec::new(),
+                })
+                .collect());
+        }
+        _ => {
+            return Err(SpinelDBError::Internal(format!(
+                "Command '{}' cannot be fanned out",
+                command.name()
+            )));
+        }
+    };
+
+    let mut by_slot: BTreeMap<u16, Vec<usize>> = BTreeMap::new();
+    for (position, key) in keys.iter().enumerate() {
+        by_slot.entry(get_slot(key)).or_default().push(position);
+    }
+
+    by_slot
+        .into_iter()
+        .map(|(slot, positions)| {
+            let node_id = cluster.slots_map[slot as usize]
+                .read()
+                .clone()
+                .ok_or_else(|| {
+                    SpinelDBError::ClusterDown(format!("Hash slot {slot} is not served"))
+                })?;
+            let slot_keys: Vec<Bytes> = positions.iter().map(|&i| keys[i].clone()).collect();
+            let command = match command {
+                Command::MGet(_) => Command::MGet(MGet { keys: slot_keys }),
+                Command::Exists(_) => Command::Exists(Exists { keys: slot_keys }),
+                Command::Del(_) => Command::Del(Del { keys: slot_keys }),
+                _ => Command::Unlink(Unlink { keys: slot_keys }),
+            };
+            Ok(SubRequest {
+                node_id,
+                command,
+                positions,
+            })
+        })
+        .collect()
+}
+
+/// Sends the sub-requests owned by other nodes, one pipeline per node, to all nodes
+/// concurrently, in database `db_index` and authenticating new connections with
+/// `password`. Sub-requests answered with an `ASK` or `MOVED` redirect are followed
+/// with `follow_redirects`. Returns the replies paired with the index of their
+/// sub-request.
+pub async fn send_remote(
+    cluster: &ClusterState,
+    requests: &[SubRequest],
+    db_index: usize,
+    password: Option<&str>,
+) -> Result<Vec<(usize, RespValue)>, SpinelDBError> {
+    let mut by_node: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (index, request) in requests.iter().enumerate() {
+        if request.node_id != cluster.my_id {
+            by_node.entry(&request.node_id).or_default().push(index);
+        }
+    }
+
+    let sends = by_node.into_iter().map(|(node_id, indices)| async move {
+        let addr: SocketAddr = cluster
+            .nodes
+            .get(node_id)
+            .and_then(|node| node.node_info.addr.parse().ok())
+            .ok_or_else(|| {
+                SpinelDBError::ClusterDown(format!("Address of node {node_id} is unknown"))
+            })?;
+        let replies =
+            send_to_node(cluster, addr, requests, &indices, db_index, false, password).await?;
+        Ok::<_, SpinelDBError>(indices.into_iter().zip(replies))
+    });
+    let mut replies: Vec<(usize, RespValue)> =
+        try_join_all(sends).await?.into_iter().flatten().collect();
+    follow_redirects(cluster, requests, &mut replies, db_index, password).await?;
+    Ok(replies)
+}
+
+/// Sends the sub-requests whose reply is an `ASK` or `MOVED` redirect, because their
+/// slot is being migrated or has just moved, once more to the node named in the
+/// redirect, and replaces the replies with the new ones. `replies` pairs the replies
+/// with the index of their sub-request.
+pub async fn follow_redirects(
+    cluster: &ClusterState,
+    requests: &[SubRequest],
+    replies: &mut [(usize, RespValue)],
+    db_index: usize,
+    password: Option<&str>,
+) -> Result<(), SpinelDBError> {
+    let mut redirected: HashMap<(SocketAddr, bool), Vec<usize>> = HashMap::new();
+    for (index, reply) in replies.iter() {
+        if let Some(target) = parse_redirect(reply) {
+            redirected.entry(target).or_default().push(*index);
+        }
+    }
+    let resends = redirected
+        .into_iter()
+        .map(|((addr, asking), indices)| async move {
+            let replies = send_to_node(
+                cluster, addr, requests, &indices, db_index, asking, password,
+            )
+            .await?;
+            Ok::<_, SpinelDBError>(indices.into_iter().zip(replies))
+        });
+    let resent: HashMap<usize, RespValue> =
+        try_join_all(resends).await?.into_iter().flatten().collect();
+    for (index, reply) in replies.iter_mut() {
+        if let Some(resent_reply) = resent.get(index) {
+            *reply = resent_reply.clone();
+        }
+    }
+    Ok(())
+}
+
+/// Sends the sub-requests at `indices` to the node at `addr` in one pipeline, over a
+/// pooled connection, and returns their replies in the same order.
+async fn send_to_node(
+    cluster: &ClusterState,
+    addr: SocketAddr,
+    requests: &[SubRequest],
+    indices: &[usize],
+    db_index: usize,
+    asking: bool,
+    password: Option<&str>,
+) -> Result<Vec<RespValue>, SpinelDBError> {
+    let commands = indices
+        .iter()
+        .map(|&i| requests[i].command.clone())
+        .collect();
+    let replies = async {
+        let mut client = cluster.fanout_pool.checkout(addr, password).await?;
+        let replies = client.send_sub_requests(db_index, commands, asking).await?;
+        cluster.fanout_pool.checkin(addr, password, client);
+        anyhow::Ok(replies)
+    }
+    .await
+    .map_err(|e| SpinelDBError::ClusterDown(format!("Could not reach node {addr}: {e}")))?;
+    Ok(replies.into_iter().map(Into::into).collect())
+}
+
+/// Returns the address named by an `ASK` or `MOVED` error reply, and whether the
+/// sub-request must be preceded by `ASKING` when it is sent there.
+fn parse_redirect(reply: &RespValue) -> Option<(SocketAddr, bool)> {
+    let RespValue::Error(message) = reply else {
+        return None;
+    };
+    let mut parts = message.split_whitespace();
+    let asking = match parts.next()? {
+        "ASK" => true,
+        "MOVED" => false,
+        _ => return None,
+    };
+    let _slot = parts.next()?;
+    let addr = parts.next()?.parse().ok()?;
+    Some((addr, asking))
+}
+
+/// Merges the replies to the sub-requests, given in the same order, into the reply to
+/// the original command. An error reply from any node fails the whole command.
+pub fn gather(
+    command: &Command,
+    requests: &[SubRequest],
+    replies: Vec<RespValue>,
+) -> Result<RespValue, SpinelDBError> {
+    if let Some(RespValue::Error(e)) = replies.iter().find(|r| matches!(r, RespValue::Error(_))) {
+        return Err(SpinelDBError::ClusterDown(format!(
+            "A sub-request of the fanned-out command failed: {e}"
+        )));
+    }
+
+    match command {
+        Command::MGet(cmd) => {
+            let mut values = vec![RespValue::Null; cmd.keys.len()];
+            for (request, reply) in requests.iter().zip(replies) {
+                let RespValue::Array(slot_values) = reply else {
+                    return Err(SpinelDBError::Internal(
+                        "Expected an array reply to a fanned-out MGET".into(),
+                    ));
+                };
+                for (&position, value) in request.positions.iter().zip(slot_values) {
+                    values[position] = value;
+                }
+            }
+            Ok(RespValue::Array(values))
+        }
+        _ => {
+            let mut total = 0;
+            for reply in replies {
+                let RespValue::Integer(count) = reply else {
+                    return Err(SpinelDBError::Internal(format!(
+                        "Expected an integer reply to a fanned-out {}",
+                        command.name().to_ascii_uppercase()
+                    )));
+                };
+                total += count;
+            }
+            Ok(RespValue::Integer(total))
+        }
+    }
+}
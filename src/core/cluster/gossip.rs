@@ -3,11 +3,16 @@
 //! Implements the cluster gossip protocol for node discovery, state propagation,
 //! and failure detection.
 
+use crate::core::Command;
+use crate::core::cluster::bus::{BusOptions, BusOrigin, ClusterBus};
 use crate::core::cluster::failover;
-use crate::core::cluster::secure_gossip::SecureGossipMessage;
 use crate::core::cluster::state::{ClusterNode, NodeFlags, NodeRuntimeState};
+use crate::core::commands::cache::Cache;
+use crate::core::commands::cache::cache_policy::{CachePolicyCmd, CachePolicySubcommand};
+use crate::core::commands::cache::command::CacheSubcommand;
+use crate::core::events::UnitOfWork;
 use crate::core::state::ServerState;
-use bincode::config;
+use crate::core::storage::cache_types::CachePolicy;
 use bytes::Bytes;
 use rand::seq::SliceRandom;
 use serde::{Deserialize, Serialize};
@@ -15,9 +20,9 @@ use std::net::SocketAddr;
 use std::sync::Arc;
 use std::sync::atomic::Ordering;
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
-use tokio::net::UdpSocket;
 use tokio::sync::{broadcast, mpsc};
 use tokio::time;
+use tokio_rustls::TlsAcceptor;
 use tracing::{debug, error, info, warn};
 use tracing_subscriber::filter::EnvFilter;
 
@@ -25,7 +30,6 @@ use tracing_subscriber::filter::EnvFilter;
 const GOSSIP_INTERVAL: Duration = Duration::from_secs(1);
 const PROBE_INTERVAL: Duration = Duration::from_millis(100);
 const GOSSIP_MAX_NODES_IN_PACKET: usize = 10;
-const UDP_BUFFER_SIZE: usize = 65535;
 
 /// The types of messages gossiped between nodes.
 #[derive(Serialize, Deserialize, bincode::Encode, bincode::Decode, Debug, Clone)]
@@ -67,6 +71,8 @@ pub enum GossipMessage {
     },
     PurgeTags {
         sender_id: String,
+        /// The database the tags were purged in.
+        db: usize,
         tags_with_epoch: Vec<(Vec<u8>, u64)>, // Tuple of (tag, epoch)
         timestamp_ms: u64,
     },
@@ -76,6 +82,25 @@ pub enum GossipMessage {
         value: String,
         timestamp_ms: u64,
     },
+    /// Shares a `CACHE.POLICY SET` (`policy_json` is the policy) or `DEL` (`None`).
+    CachePolicyUpdate {
+        sender_id: String,
+        name: String,
+        policy_json: Option<String>,
+        timestamp_ms: u64,
+    },
+    /// Sent by a replica to its primary to ask it to pause writes for a manual failover.
+    ManualFailoverStart {
+        sender_id: String,
+        timestamp_ms: u64,
+    },
+    /// The primary's reply to `ManualFailoverStart`, carrying its replication offset
+    /// after writes were paused.
+    ManualFailoverAck {
+        sender_id: String,
+        replication_offset: u64,
+        timestamp_ms: u64,
+    },
 }
 
 impl GossipMessage {
@@ -90,7 +115,10 @@ impl GossipMessage {
             | GossipMessage::FailReport { timestamp_ms, .. }
             | GossipMessage::Publish { timestamp_ms, .. }
             | GossipMessage::PurgeTags { timestamp_ms, .. }
-            | GossipMessage::ConfigUpdate { timestamp_ms, .. } => *timestamp_ms,
+            | GossipMessage::ConfigUpdate { timestamp_ms, .. }
+            | GossipMessage::CachePolicyUpdate { timestamp_ms, .. }
+            | GossipMessage::ManualFailoverStart { timestamp_ms, .. }
+            | GossipMessage::ManualFailoverAck { timestamp_ms, .. } => *timestamp_ms,
         }
     }
 }
@@ -105,6 +133,11 @@ pub enum GossipTaskMessage {
         message: GossipMessage,
         target: SocketAddr,
     },
+    /// Send a message to a known node by its bus address (used for CLUSTER FAILOVER).
+    SendToNode {
+        message: GossipMessage,
+        bus_addr: String,
+    },
 }
 
 /// Helper to get the current system time in milliseconds since the UNIX epoch.
@@ -119,67 +152,69 @@ pub fn now_ms() -> u64 {
 pub async fn run(
     state: Arc<ServerState>,
     bus_port: u16,
+    tls_acceptor: Option<TlsAcceptor>,
     mut shutdown_rx: broadcast::Receiver<()>,
     mut gossip_task_rx: mpsc::Receiver<GossipTaskMessage>,
 ) {
-    let addr = format!("0.0.0.0:{bus_port}");
-    let socket = match UdpSocket::bind(&addr).await {
-        Ok(s) => Arc::new(s),
+    let options = {
+        let config = state.config.lock().await;
+        BusOptions {
+            tcp_enabled: config.cluster.bus_tcp_enabled,
+            udp_heartbeats: config.cluster.bus_udp_heartbeats,
+            tls_acceptor: if config.cluster.bus_tls {
+                tls_acceptor
+            } else {
+                None
+            },
+        }
+    };
+    let transports = match (options.tcp_enabled, options.tls_acceptor.is_some()) {
+        (true, true) => "UDP and TCP (TLS)",
+        (true, false) => "UDP and TCP",
+        (false, _) => "UDP",
+    };
+    let (bus, mut inbound_rx) = match ClusterBus::bind(bus_port, options).await {
+        Ok(bound) => bound,
         Err(e) => {
-            error!("Failed to bind cluster bus on UDP port {}: {}", bus_port, e);
+            error!("Failed to bind cluster bus on port {}: {}", bus_port, e);
             return;
         }
     };
-    info!("Cluster bus listening on UDP port {}.", bus_port);
+    info!("Cluster bus listening on {} port {}.", transports, bus_port);
 
-    // Task for receiving messages from other nodes.
+    // Task for handling messages received from other nodes over either transport.
     let receiver_state = state.clone();
-    let receiver_socket = socket.clone();
+    let receiver_bus = bus.clone();
     tokio::spawn(async move {
-        let mut buf = [0; UDP_BUFFER_SIZE];
-        loop {
-            match receiver_socket.recv_from(&mut buf).await {
-                Ok((len, src)) => {
-                    let (password, node_timeout) = {
-                        let config = receiver_state.config.lock().await;
-                        (config.password.clone(), config.cluster.node_timeout)
-                    };
-                    let bincode_config = config::standard();
-                    match bincode::decode_from_slice::<SecureGossipMessage, _>(
-                        &buf[..len],
-                        bincode_config,
-                    ) {
-                        Ok((secure_msg, _)) => match secure_msg.verify(&password) {
-                            Ok(true) => {
-                                handle_gossip_message(
-                                    &receiver_state,
-                                    secure_msg.message,
-                                    &receiver_socket,
-                                    src,
-                                    node_timeout,
-                                )
-                                .await
-                            }
-                            Ok(false) => {
-                                warn!(
-                                    "Received gossip message with invalid signature from {}. Ignoring.",
-                                    src
-                                );
-                            }
-                            Err(e) => {
-                                warn!(
-                                    "Error verifying gossip message signature from {}: {}",
-                                    src, e
-                                );
-                            }
-                        },
-                        Err(e) => warn!(
-                            "Failed to deserialize secure gossip message from {}: {}",
-                            src, e
-                        ),
-                    }
+        while let Some((secure_msg, origin)) = inbound_rx.recv().await {
+            let (password, node_timeout) = {
+                let config = receiver_state.config.lock().await;
+                (config.password.clone(), config.cluster.node_timeout)
+            };
+            match secure_msg.verify(&password) {
+                Ok(true) => {
+                    handle_gossip_message(
+                        &receiver_state,
+                        secure_msg.message,
+                        &receiver_bus,
+                        origin,
+                        node_timeout,
+                    )
+                    .await
+                }
+                Ok(false) => {
+                    warn!(
+                        "Received gossip message with invalid signature from {}. Ignoring.",
+                        origin.addr()
+                    );
+                }
+                Err(e) => {
+                    warn!(
+                        "Error verifying gossip message signature from {}: {}",
+                        origin.addr(),
+                        e
+                    );
                 }
-                Err(e) => error!("Error receiving from cluster bus: {}", e),
             }
         }
     });
@@ -192,20 +227,25 @@ pub async fn run(
         tokio::select! {
             _ = shutdown_rx.recv() => { info!("Gossip worker shutting down."); return; }
             _ = gossip_tick.tick() => {
-                send_pings(&state, &socket).await;
+                send_pings(&state, &bus).await;
             }
             _ = probe_tick.tick() => {
-                 check_for_failed_nodes(&state, &socket).await;
+                 check_for_failed_nodes(&state, &bus).await;
                  check_quorum_and_self_fence(&state).await;
-                 failover::handle_failover_cron(&state, &socket).await;
+                 failover::handle_failover_cron(&state, &bus).await;
             }
             Some(task_message) = gossip_task_rx.recv() => {
                 match task_message {
                     GossipTaskMessage::Broadcast(message) => {
-                        broadcast_gossip_message(&state, &socket, message).await;
+                        broadcast_gossip_message(&state, &bus, message).await;
                     }
                     GossipTaskMessage::DirectSend { message, target } => {
-                        send_direct_gossip_message(&state, &socket, message, target).await;
+                        let password = state.config.lock().await.password.clone();
+                        bus.send(message, target, &password).await;
+                    }
+                    GossipTaskMessage::SendToNode { message, bus_addr } => {
+                        let password = state.config.lock().await.password.clone();
+                        bus.send_to_node(message, &bus_addr, &password).await;
                     }
                 }
             }
@@ -213,49 +253,28 @@ pub async fn run(
     }
 }
 
-/// Centralized helper to send a message to a single specific target.
-async fn send_direct_gossip_message(
-    state: &Arc<ServerState>,
-    socket: &Arc<UdpSocket>,
-    message: GossipMessage,
-    target: SocketAddr,
-) {
-    let password = &state.config.lock().await.password;
-    if let Ok(secure_message) = SecureGossipMessage::new(message, password) {
-        let bincode_config = config::standard();
-        if let Ok(encoded_msg) = bincode::encode_to_vec(&secure_message, bincode_config)
-            && let Err(e) = socket.send_to(&encoded_msg, &target).await
-        {
-            warn!("Failed to send direct gossip message to {}: {}", target, e);
-        }
-    }
-}
-
 /// Centralized helper to broadcast a gossip message to all other nodes in the cluster.
 async fn broadcast_gossip_message(
     state: &Arc<ServerState>,
-    socket: &Arc<UdpSocket>,
+    bus: &Arc<ClusterBus>,
     message: GossipMessage,
 ) {
     let cluster = state.cluster.as_ref().unwrap();
-    let password = &state.config.lock().await.password;
-    if let Ok(secure_message) = SecureGossipMessage::new(message, password) {
-        let bincode_config = config::standard();
-        if let Ok(encoded_msg) = bincode::encode_to_vec(&secure_message, bincode_config) {
-            for entry in cluster.nodes.iter() {
-                let node_info = &entry.value().node_info;
-                if !node_info
-                    .get_flags()
-                    .intersects(NodeFlags::MYSELF | NodeFlags::FAIL | NodeFlags::HANDSHAKE)
-                    && let Err(e) = socket.send_to(&encoded_msg, &node_info.bus_addr).await
-                {
-                    warn!(
-                        "Failed to send broadcast gossip message to {}: {}",
-                        node_info.bus_addr, e
-                    );
-                }
-            }
-        }
+    let password = state.config.lock().await.password.clone();
+    let targets: Vec<String> = cluster
+        .nodes
+        .iter()
+        .map(|entry| entry.value().node_info.clone())
+        .filter(|node_info| {
+            !node_info
+                .get_flags()
+                .intersects(NodeFlags::MYSELF | NodeFlags::FAIL | NodeFlags::HANDSHAKE)
+        })
+        .map(|node_info| node_info.bus_addr)
+        .collect();
+    for bus_addr in targets {
+        bus.send_to_node(message.clone(), &bus_addr, &password)
+            .await;
     }
 }
 
@@ -287,9 +306,9 @@ fn choose_nodes_to_ping(state: &Arc<ServerState>) -> Vec<NodeRuntimeState> {
         .collect()
 }
 
-async fn send_pings(state: &Arc<ServerState>, socket: &Arc<UdpSocket>) {
+async fn send_pings(state: &Arc<ServerState>, bus: &Arc<ClusterBus>) {
     let cluster = state.cluster.as_ref().unwrap();
-    let password = &state.config.lock().await.password;
+    let password = state.config.lock().await.password.clone();
 
     let replica_info = state.replication.replica_info.lock().await;
     if let Some(info) = replica_info.as_ref()
@@ -301,7 +320,6 @@ async fn send_pings(state: &Arc<ServerState>, socket: &Arc<UdpSocket>) {
 
     let chosen_nodes = choose_nodes_to_ping(state);
     for mut runtime_state in chosen_nodes {
-        let node_info = &runtime_state.node_info;
         let gossip_nodes = select_nodes_for_gossip(state);
         let ping_msg = GossipMessage::Ping {
             sender_id: cluster.my_id.clone(),
@@ -309,26 +327,17 @@ async fn send_pings(state: &Arc<ServerState>, socket: &Arc<UdpSocket>) {
             timestamp_ms: now_ms(),
         };
 
-        if let Ok(secure_ping_msg) = SecureGossipMessage::new(ping_msg, password) {
-            let bincode_config = config::standard();
-            if let Ok(encoded) = bincode::encode_to_vec(&secure_ping_msg, bincode_config) {
-                if let Err(e) = socket.send_to(&encoded, &node_info.bus_addr).await {
-                    error!("Failed to send PING to {}: {}", node_info.bus_addr, e);
-                } else {
-                    debug!("Sent PING to {}", node_info.bus_addr);
-                    runtime_state.ping_sent = Some(Instant::now());
-                    cluster.nodes.insert(node_info.id.clone(), runtime_state);
-                }
-            } else {
-                error!("Failed to serialize secure PING message");
-            }
-        } else {
-            error!("Failed to sign PING message");
-        }
+        let bus_addr = runtime_state.node_info.bus_addr.clone();
+        bus.send_to_node(ping_msg, &bus_addr, &password).await;
+        debug!("Sent PING to {}", bus_addr);
+        runtime_state.ping_sent = Some(Instant::now());
+        cluster
+            .nodes
+            .insert(runtime_state.node_info.id.clone(), runtime_state);
     }
 }
 
-async fn check_for_failed_nodes(state: &Arc<ServerState>, socket: &Arc<UdpSocket>) {
+async fn check_for_failed_nodes(state: &Arc<ServerState>, bus: &Arc<ClusterBus>) {
     let cluster = state.cluster.as_ref().unwrap();
     let (node_timeout, password) = {
         let config_guard = state.config.lock().await;
@@ -340,6 +349,7 @@ async fn check_for_failed_nodes(state: &Arc<ServerState>, socket: &Arc<UdpSocket
 
     cluster.clean_pfail_reports();
 
+    let mut newly_failed = Vec::new();
     for mut entry in cluster.nodes.iter_mut() {
         let node_id = entry.key().clone();
         let runtime_state = entry.value_mut();
@@ -360,27 +370,27 @@ async fn check_for_failed_nodes(state: &Arc<ServerState>, socket: &Arc<UdpSocket
         }
 
         if flags.contains(NodeFlags::PFAIL) && cluster.promote_pfail_to_fail(&node_id).await {
-            info!("Broadcasting FAIL report for node {}", node_id);
-            let fail_report_msg = GossipMessage::FailReport {
-                sender_id: cluster.my_id.clone(),
-                failed_node_id: node_id.clone(),
-                timestamp_ms: now_ms(),
-            };
+            newly_failed.push(node_id);
+        }
+    }
 
-            if let Ok(secure_report) = SecureGossipMessage::new(fail_report_msg, &password) {
-                let bincode_config = config::standard();
-                if let Ok(encoded_msg) = bincode::encode_to_vec(&secure_report, bincode_config) {
-                    for other_node_entry in cluster.nodes.iter() {
-                        let other_node_id = other_node_entry.key();
-                        let other_node_info = &other_node_entry.value().node_info;
-                        if other_node_id != &cluster.my_id && other_node_id != &node_id {
-                            let _ = socket
-                                .send_to(&encoded_msg, &other_node_info.bus_addr)
-                                .await;
-                        }
-                    }
-                }
-            }
+    for node_id in newly_failed {
+        info!("Broadcasting FAIL report for node {}", node_id);
+        let fail_report_msg = GossipMessage::FailReport {
+            sender_id: cluster.my_id.clone(),
+            failed_node_id: node_id.clone(),
+            timestamp_ms: now_ms(),
+        };
+
+        let targets: Vec<String> = cluster
+            .nodes
+            .iter()
+            .filter(|other| other.key() != &cluster.my_id && other.key() != &node_id)
+            .map(|other| other.value().node_info.bus_addr.clone())
+            .collect();
+        for bus_addr in targets {
+            bus.send_to_node(fail_report_msg.clone(), &bus_addr, &password)
+                .await;
         }
     }
 }
@@ -429,12 +439,13 @@ async fn check_quorum_and_self_fence(state: &Arc<ServerState>) {
 async fn handle_gossip_message(
     state: &Arc<ServerState>,
     msg: GossipMessage,
-    socket: &Arc<UdpSocket>,
-    src_addr: std::net::SocketAddr,
+    bus: &Arc<ClusterBus>,
+    origin: BusOrigin,
     node_timeout: u64,
 ) {
     let cluster = state.cluster.as_ref().unwrap();
-    let password = &state.config.lock().await.password;
+    let password = state.config.lock().await.password.clone();
+    let src_addr = origin.addr();
     let time_window = Duration::from_millis(node_timeout * 2).as_millis();
     let now = now_ms();
     let msg_ts = msg.timestamp();
@@ -465,19 +476,18 @@ async fn handle_gossip_message(
                 gossip_nodes,
                 timestamp_ms: now_ms(),
             };
-            if let Ok(secure_msg) = SecureGossipMessage::new(ping_msg, password) {
-                let bincode_config = config::standard();
-                if let Ok(encoded) = bincode::encode_to_vec(&secure_msg, bincode_config) {
-                    let _ = socket.send_to(&encoded, &src_addr).await;
-                }
-            }
+            bus.reply(ping_msg, &origin, &password).await;
         }
         GossipMessage::Ping {
             sender_id,
             gossip_nodes,
             ..
         } => {
-            if let Some(sender_runtime) = cluster.nodes.get(&sender_id) {
+            let sender_bus_addr = cluster
+                .nodes
+                .get(&sender_id)
+                .map(|sender_runtime| sender_runtime.node_info.bus_addr.clone());
+            if let Some(sender_bus_addr) = sender_bus_addr {
                 for received_node_info in gossip_nodes {
                     cluster.merge_node_info(received_node_info, state).await;
                 }
@@ -487,14 +497,8 @@ async fn handle_gossip_message(
                     gossip_nodes: my_gossip_nodes,
                     timestamp_ms: now_ms(),
                 };
-                if let Ok(secure_pong) = SecureGossipMessage::new(pong_msg, password) {
-                    let bincode_config = config::standard();
-                    if let Ok(encoded) = bincode::encode_to_vec(&secure_pong, bincode_config) {
-                        let _ = socket
-                            .send_to(&encoded, &sender_runtime.node_info.bus_addr)
-                            .await;
-                    }
-                }
+                bus.send_to_node(pong_msg, &sender_bus_addr, &password)
+                    .await;
             } else {
                 warn!(
                     "Received PING from unknown node ID {}. Responding with MEET logic.",
@@ -506,12 +510,7 @@ async fn handle_gossip_message(
                     gossip_nodes: my_gossip_nodes,
                     timestamp_ms: now_ms(),
                 };
-                if let Ok(secure_msg) = SecureGossipMessage::new(ping_msg, password) {
-                    let bincode_config = config::standard();
-                    if let Ok(encoded) = bincode::encode_to_vec(&secure_msg, bincode_config) {
-                        let _ = socket.send_to(&encoded, &src_addr).await;
-                    }
-                }
+                bus.reply(ping_msg, &origin, &password).await;
             }
         }
         GossipMessage::Pong {
@@ -542,14 +541,8 @@ async fn handle_gossip_message(
             replication_offset,
             ..
         } => {
-            failover::handle_auth_request(
-                state,
-                socket,
-                sender_id,
-                config_epoch,
-                replication_offset,
-            )
-            .await;
+            failover::handle_auth_request(state, bus, sender_id, config_epoch, replication_offset)
+                .await;
         }
         GossipMessage::FailoverAuthAck {
             sender_id,
@@ -558,6 +551,16 @@ async fn handle_gossip_message(
         } => {
             failover::handle_auth_ack(state, sender_id, config_epoch).await;
         }
+        GossipMessage::ManualFailoverStart { sender_id, .. } => {
+            failover::handle_manual_failover_start(state, bus, &origin, sender_id).await;
+        }
+        GossipMessage::ManualFailoverAck {
+            sender_id,
+            replication_offset,
+            ..
+        } => {
+            failover::handle_manual_failover_ack(state, sender_id, replication_offset);
+        }
         GossipMessage::FailReport {
             sender_id,
             failed_node_id,
@@ -585,6 +588,7 @@ async fn handle_gossip_message(
         }
         GossipMessage::PurgeTags {
             sender_id,
+            db,
             tags_with_epoch,
             ..
         } => {
@@ -599,7 +603,7 @@ async fn handle_gossip_message(
                 state
                     .cache
                     .tag_purge_epochs
-                    .entry(tag)
+                    .entry((db, tag))
                     .and_modify(|e| {
                         if epoch > *e {
                             *e = epoch;
@@ -639,6 +643,44 @@ async fn handle_gossip_message(
                 }
             }
         }
+        GossipMessage::CachePolicyUpdate {
+            sender_id,
+            name,
+            policy_json,
+            ..
+        } => {
+            if cluster.my_id == sender_id {
+                return;
+            }
+            debug!("Received CACHE.POLICY change for '{name}' from node {sender_id}");
+            let subcommand = match policy_json {
+                Some(json) => match serde_json::from_str::<CachePolicy>(&json) {
+                    Ok(policy) => {
+                        state.cache.set_policy(policy.clone()).await;
+                        CachePolicySubcommand::Set(Box::new(policy))
+                    }
+                    Err(e) => {
+                        warn!(
+                            "Ignoring malformed cache policy '{name}' from node {sender_id}: {e}"
+                        );
+                        return;
+                    }
+                },
+                None => {
+                    if state.cache.delete_policy(&name).await.is_none() {
+                        return;
+                    }
+                    CachePolicySubcommand::Del(name)
+                }
+            };
+            // Record the change in this node's AOF and pass it on to its replicas.
+            let command = Command::Cache(Cache {
+                subcommand: CacheSubcommand::Policy(CachePolicyCmd { subcommand }),
+            });
+            state
+                .event_bus
+                .publish(UnitOfWork::Command(Box::new(command)), state);
+        }
     }
 }
 
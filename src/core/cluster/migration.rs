@@ -0,0 +1,401 @@
+// src/core/cluster/migration.rs
+
+//! Implements atomic slot migration, used by `CLUSTER RESHARD ... ATOMIC`.
+//!
+//! Instead of moving keys one by one with `MIGRATE` while clients are redirected with
+//! `ASK`, the source node keeps serving the slot while it streams a copy of it to the
+//! destination. Keys written meanwhile are recorded by the shards of the database and
+//! replayed in catch-up rounds. Once few enough changes remain, writes to the slot are
+//! paused briefly, the last changes are sent, and ownership is handed over. Writes
+//! stay paused until the handover has completed or failed, so clients never see a slot
+//! that is split between two nodes.
+
+use super::client::{ClusterClient, SlotEntry};
+use super::slot::get_slot;
+use crate::core::cluster::NodeFlags;
+use crate::core::commands::generic::Del;
+use crate::core::database::Db;
+use crate::core::events::UnitOfWork;
+use crate::core::persistence::spldb;
+use crate::core::state::ServerState;
+use crate::core::{Command, SpinelDBError};
+use bytes::Bytes;
+use parking_lot::RwLock;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+use tracing::info;
+
+/// The number of keys sent to the destination in one pipelined batch.
+const MIGRATION_BATCH_SIZE: usize = 256;
+/// The maximum number of catch-up rounds before cutting over regardless.
+const MAX_CATCH_UP_ROUNDS: usize = 16;
+/// Cut over once no more than this many changed keys are left to send.
+const CUTOVER_MAX_PENDING_KEYS: usize = 128;
+/// The longest the cutover waits for writes to the slot that are already in progress.
+/// If they do not finish in time, the migration is abandoned and writes resume.
+const CUTOVER_DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Coordinates client writes with the cutover of an atomic slot migration.
+#[derive(Debug, Default)]
+pub struct SlotMigrationGate {
+    /// The slot being migrated atomically from this node, if any.
+    slot: RwLock<Option<u16>>,
+    /// True while writes to the slot are paused for the cutover. Only `finish` clears
+    /// it, once the slot has been handed over or the migration has been abandoned.
+    paused: AtomicBool,
+    /// Wakes writes waiting for the pause to end.
+    resumed: Notify,
+    /// The writes in progress that may touch the slot.
+    in_flight: Arc<InFlightWrites>,
+}
+
+/// Counts admitted writes that are still in progress, so that a pause of writes can
+/// wait for them to finish.
+#[derive(Debug, Default)]
+pub struct InFlightWrites {
+    count: AtomicUsize,
+    /// Notified when the last write in progress finishes.
+    drained: Notify,
+}
+
+impl InFlightWrites {
+    /// Returns the number of writes in progress.
+    pub fn count(&self) -> usize {
+        self.count.load(Ordering::SeqCst)
+    }
+
+    /// Waits until no writes are in progress. Returns false if some still were at
+    /// `deadline`.
+    pub async fn wait_drained(&self, deadline: Instant) -> bool {
+        loop {
+            let drained = self.drained.notified();
+            tokio::pin!(drained);
+            // Registers for the notification before checking, so that a write that
+            // finishes in between is not missed.
+            drained.as_mut().enable();
+            if self.count() == 0 {
+                return true;
+            }
+            if tokio::time::timeout_at(deadline.into(), drained)
+                .await
+                .is_err()
+            {
+                return self.count() == 0;
+            }
+        }
+    }
+}
+
+/// Marks an admitted write as in progress until dropped, so that a pause of writes
+/// can wait for it to finish.
+#[derive(Debug)]
+pub struct InFlightWrite {
+    in_flight: Arc<InFlightWrites>,
+}
+
+impl InFlightWrite {
+    /// Counts a write in `in_flight` for as long as the returned guard lives.
+    pub(super) fn new(in_flight: &Arc<InFlightWrites>) -> Self {
+        in_flight.count.fetch_add(1, Ordering::SeqCst);
+        Self {
+            in_flight: in_flight.clone(),
+        }
+    }
+}
+
+impl Drop for InFlightWrite {
+    fn drop(&mut self) {
+        if self.in_flight.count.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.in_flight.drained.notify_waiters();
+        }
+    }
+}
+
+impl SlotMigrationGate {
+    /// Returns the slot being migrated atomically, if any.
+    pub fn migrating_slot(&self) -> Option<u16> {
+        *self.slot.read()
+    }
+
+    fn start(&self, slot: u16) -> Result<(), SpinelDBError> {
+        let mut current = self.slot.write();
+        if let Some(other) = *current {
+            return Err(SpinelDBError::InvalidState(format!(
+                "Slot {other} is already being migrated atomically"
+            )));
+        }
+        *current = Some(slot);
+        Ok(())
+    }
+
+    fn finish(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+        *self.slot.write() = None;
+        self.resumed.notify_waiters();
+    }
+
+    /// Returns true while writes to the migrating slot are paused for the cutover.
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    /// Pauses new writes to the slot until `finish` and waits for those in progress to
+    /// finish. Returns false if they did not finish in time.
+    async fn pause(&self) -> bool {
+        let deadline = Instant::now() + CUTOVER_DRAIN_TIMEOUT;
+        self.paused.store(true, Ordering::SeqCst);
+        self.in_flight.wait_drained(deadline).await
+    }
+
+    /// Admits a write to `keys`, waiting while the migrating slot is paused. Writes
+    /// without keys, such as `EXEC`, may touch any slot and are treated as touching it.
+    /// Returns a guard if the write must be waited for by the cutover.
+    pub async fn enter(&self, keys: &[Bytes]) -> Option<InFlightWrite> {
+        loop {
+            let slot = self.migrating_slot()?;
+            if !keys.is_empty() && !keys.iter().any(|key| get_slot(key) == slot) {
+                return None;
+            }
+            if self.is_paused() {
+                let resumed = self.resumed.notified();
+                tokio::pin!(resumed);
+                resumed.as_mut().enable();
+                // `finish` may have run since the check above.
+                if self.is_paused() {
+                    resumed.await;
+                }
+                continue;
+            }
+            let guard = InFlightWrite::new(&self.in_flight);
+            // The pause may have started after the check above; if so, back off so
+            // the cutover does not wait for this write.
+            if self.is_paused() {
+                drop(guard);
+                continue;
+            }
+            return Some(guard);
+        }
+    }
+}
+
+/// Migrates `slot` from this node to `dest_id` atomically, as described in the module
+/// documentation. On success this node no longer owns the slot, its keys have been
+/// removed locally, and the number of keys sent is returned. On failure the slot stays
+/// on this node, and the partial copy on the destination is overwritten by a retry.
+pub async fn migrate_slot_atomically(
+    state: &Arc<ServerState>,
+    slot: u16,
+    dest_id: &str,
+) -> Result<usize, SpinelDBError> {
+    let cluster = state
+        .cluster
+        .as_ref()
+        .ok_or_else(|| SpinelDBError::InvalidState("Cluster mode is not enabled.".into()))?;
+    if !cluster.i_own_slot(slot) {
+        return Err(SpinelDBError::InvalidState(format!(
+            "I'm not the owner of hash slot {slot}"
+        )));
+    }
+    let dest_addr: SocketAddr = {
+        let dest = cluster.nodes.get(dest_id).ok_or_else(|| {
+            SpinelDBError::InvalidState(format!("Destination node {dest_id} not found"))
+        })?;
+        if !dest.node_info.get_flags().contains(NodeFlags::PRIMARY) {
+            return Err(SpinelDBError::InvalidState(
+                "The destination of a slot migration must be a primary".into(),
+            ));
+        }
+        dest.node_info.addr.parse().map_err(|_| {
+            SpinelDBError::InvalidState(format!("Invalid address for node {dest_id}"))
+        })?
+    };
+
+    // Keys of the slot may exist in any database, so all of them are migrated.
+    let dbs = &state.dbs;
+    cluster.slot_migration.start(slot)?;
+    for db in dbs {
+        db.begin_slot_tracking(slot).await;
+    }
+
+    let result = stream_and_cut_over(state, dbs, slot, dest_id, dest_addr).await;
+
+    cluster.slot_migration.finish();
+    for db in dbs {
+        db.end_slot_tracking(slot).await;
+    }
+
+    if result.is_ok() {
+        for db in dbs {
+            remove_migrated_keys(state, db, slot).await;
+        }
+    }
+    result
+}
+
+/// Copies the slot in every database to the destination, replays changes until few
+/// are left, then pauses writes to the slot and hands it over.
+async fn stream_and_cut_over(
+    state: &Arc<ServerState>,
+    dbs: &[Arc<Db>],
+    slot: u16,
+    dest_id: &str,
+    dest_addr: SocketAddr,
+) -> Result<usize, SpinelDBError> {
+    let cluster = state.cluster.as_ref().unwrap();
+    let mut client = ClusterClient::connect(dest_addr)
+        .await
+        .map_err(migration_error)?;
+
+    let mut keys = Vec::with_capacity(dbs.len());
+    for db in dbs {
+        keys.push(
+            db.get_keys_in_slot(slot, db.count_keys_in_slot(slot).await)
+                .await,
+        );
+    }
+    info!(
+        "[ATOMIC MIGRATION SLOT {}] Streaming {} keys to {}.",
+        slot,
+        keys.iter().map(Vec::len).sum::<usize>(),
+        dest_id
+    );
+    let mut sent = send_keys_of_all_dbs(&mut client, dbs, keys).await?;
+
+    let mut pending = vec![Vec::new(); dbs.len()];
+    for round in 1..=MAX_CATCH_UP_ROUNDS {
+        let mut changes = Vec::with_capacity(dbs.len());
+        for db in dbs {
+            changes.push(
+                db.take_slot_changes(slot)
+                    .await
+                    .into_iter()
+                    .collect::<Vec<_>>(),
+            );
+        }
+        let changed = changes.iter().map(Vec::len).sum::<usize>();
+        if changed <= CUTOVER_MAX_PENDING_KEYS || round == MAX_CATCH_UP_ROUNDS {
+            pending = changes;
+            break;
+        }
+        info!(
+            "[ATOMIC MIGRATION SLOT {}] Catch-up round {}: {} changed keys.",
+            slot, round, changed
+        );
+        sent += send_keys_of_all_dbs(&mut client, dbs, changes).await?;
+    }
+
+    if !cluster.slot_migration.pause().await {
+        return Err(SpinelDBError::MigrationError(
+            "Timed out waiting for writes to the slot to finish".into(),
+        ));
+    }
+    for (db, keys) in dbs.iter().zip(pending.iter_mut()) {
+        keys.extend(db.take_slot_changes(slot).await);
+        keys.sort();
+        keys.dedup();
+    }
+    info!(
+        "[ATOMIC MIGRATION SLOT {}] Writes paused. Sending the last {} changed keys.",
+        slot,
+        pending.iter().map(Vec::len).sum::<usize>()
+    );
+    sent += send_keys_of_all_dbs(&mut client, dbs, pending).await?;
+
+    client
+        .cluster_setslot(vec![
+            "SETSLOT".into(),
+            slot.to_string().into(),
+            "NODE".into(),
+            dest_id.to_string().into(),
+        ])
+        .await
+        .map_err(migration_error)?;
+    cluster.assign_slot(slot, dest_id)?;
+    cluster.save_config().await?;
+    info!(
+        "[ATOMIC MIGRATION SLOT {}] Handed over to {} after sending {} keys.",
+        slot, dest_id, sent
+    );
+    Ok(sent)
+}
+
+/// Sends the keys of each database, given in the order of `dbs`, to the same database
+/// on the destination. Returns the number of keys sent.
+async fn send_keys_of_all_dbs(
+    client: &mut ClusterClient,
+    dbs: &[Arc<Db>],
+    keys: Vec<Vec<Bytes>>,
+) -> Result<usize, SpinelDBError> {
+    let mut sent = 0;
+    for (db_index, (db, keys)) in dbs.iter().zip(keys).enumerate() {
+        sent += send_keys(client, db_index, db, keys).await?;
+    }
+    Ok(sent)
+}
+
+/// Sends the current state of `keys` in database `db_index` to the destination in
+/// batches. Keys that no longer exist are deleted there. Returns the number of keys
+/// sent.
+async fn send_keys(
+    client: &mut ClusterClient,
+    db_index: usize,
+    db: &Db,
+    keys: Vec<Bytes>,
+) -> Result<usize, SpinelDBError> {
+    let total = keys.len();
+    for batch in keys.chunks(MIGRATION_BATCH_SIZE) {
+        let mut entries = Vec::with_capacity(batch.len());
+        for key in batch {
+            entries.push(dump_key(db, key).await?);
+        }
+        client
+            .restore_slot_entries(db_index, entries)
+            .await
+            .map_err(migration_error)?;
+    }
+    Ok(total)
+}
+
+/// Serializes the current value of `key` for `RESTORE`.
+async fn dump_key(db: &Db, key: &Bytes) -> Result<SlotEntry, SpinelDBError> {
+    let guard = db.get_shard(db.get_shard_index(key)).entries.lock().await;
+    let value = match guard.peek(key).filter(|v| !v.is_expired()) {
+        Some(stored) => {
+            let payload = spldb::serialize_value(&stored.data).map_err(|e| {
+                SpinelDBError::MigrationError(format!("Failed to serialize key: {e}"))
+            })?;
+            // A TTL of 0 means no expiry for RESTORE, so round a key that is about to
+            // expire up to 1ms instead.
+            let ttl_ms = stored.remaining_ttl_ms().map_or(0, |ttl| ttl.max(1) as u64);
+            Some((payload, ttl_ms))
+        }
+        None => None,
+    };
+    Ok(SlotEntry {
+        key: key.clone(),
+        value,
+    })
+}
+
+/// Removes the keys of a slot that has been handed over, and propagates the removal
+/// to the AOF and replicas.
+async fn remove_migrated_keys(state: &Arc<ServerState>, db: &Db, slot: u16) {
+    loop {
+        let keys = db.get_keys_in_slot(slot, MIGRATION_BATCH_SIZE).await;
+        if keys.is_empty() {
+            return;
+        }
+        db.del(&keys).await;
+        state.event_bus.publish(
+            UnitOfWork::Command(Box::new(Command::Del(Del { keys }))),
+            state,
+        );
+    }
+}
+
+fn migration_error(e: anyhow::Error) -> SpinelDBError {
+    SpinelDBError::MigrationError(e.to_string())
+}
@@ -3,10 +3,14 @@
 //! This module contains all logic related to the cluster mode, including
 //! state management, the gossip protocol, failover, and slot handling.
 
+pub mod bus;
 pub mod client;
 pub mod config;
 pub mod failover;
+pub mod fanout;
 pub mod gossip;
+pub mod migration;
+pub mod rebalance;
 pub mod secure_gossip;
 pub mod slot;
 pub mod state;
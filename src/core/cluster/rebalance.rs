@@ -0,0 +1,295 @@
+// src/core/cluster/rebalance.rs
+
+//! Computes slot rebalancing plans for `CLUSTER REBALANCE` and tracks the progress
+//! of a running rebalance.
+//!
+//! Each participating primary is given a share of the assigned slots in proportion
+//! to its weight. Nodes holding more than their share donate their highest-numbered
+//! slots to nodes holding less, so the number of slots moved is exactly the total
+//! surplus, which is the minimum needed to reach the target distribution.
+//!
+//! With `BY MEMORY`, each node is instead given a share of the memory used by the
+//! cluster, and slots are weighed by the memory they are estimated to use.
+
+use crate::core::SpinelDBError;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A primary taking part in a rebalance.
+#[derive(Debug, Clone)]
+pub struct RebalanceNode {
+    pub id: String,
+    pub slots: BTreeSet<u16>,
+    /// The relative capacity of the node. A weight of 0 drains the node.
+    pub weight: f64,
+}
+
+/// What `CLUSTER REBALANCE` distributes across the nodes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RebalanceBy {
+    /// The slots, each counting the same.
+    #[default]
+    Slots,
+    /// The memory used by the nodes. Each slot is assumed to use the average memory
+    /// per slot of the node that holds it.
+    Memory,
+}
+
+/// A batch of slots to move from one node to another.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlotMove {
+    pub source: String,
+    pub destination: String,
+    pub slots: Vec<u16>,
+}
+
+/// The lifecycle of the most recent rebalance on this node.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RebalanceStatus {
+    #[default]
+    Idle,
+    Running,
+    Done,
+    Failed,
+}
+
+impl RebalanceStatus {
+    /// Returns the name reported by `CLUSTER INFO`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RebalanceStatus::Idle => "idle",
+            RebalanceStatus::Running => "running",
+            RebalanceStatus::Done => "done",
+            RebalanceStatus::Failed => "failed",
+        }
+    }
+}
+
+/// The progress of the most recent rebalance, reported by `CLUSTER INFO`.
+#[derive(Debug, Clone, Default)]
+pub struct RebalanceProgress {
+    pub status: RebalanceStatus,
+    pub moves_total: usize,
+    pub moves_done: usize,
+    pub slots_total: usize,
+    pub slots_moved: usize,
+    pub last_error: Option<String>,
+}
+
+/// Returns the number of slots each node should hold, in the order of `nodes`.
+fn expected_slot_counts(
+    nodes: &[RebalanceNode],
+    total_slots: usize,
+    total_weight: f64,
+) -> Vec<usize> {
+    let exact: Vec<f64> = nodes
+        .iter()
+        .map(|n| total_slots as f64 * n.weight / total_weight)
+        .collect();
+    let mut expected: Vec<usize> = exact.iter().map(|e| e.floor() as usize).collect();
+
+    // Hand out the slots lost to rounding, largest fractional part first.
+    let mut remainder = total_slots - expected.iter().sum::<usize>();
+    let mut order: Vec<usize> = (0..nodes.len()).collect();
+    order.sort_by(|&a, &b| {
+        let frac_a = exact[a] - exact[a].floor();
+        let frac_b = exact[b] - exact[b].floor();
+        frac_b
+            .total_cmp(&frac_a)
+            .then_with(|| nodes[a].id.cmp(&nodes[b].id))
+    });
+    for i in order {
+        if remainder == 0 {
+            break;
+        }
+        if nodes[i].weight > 0.0 {
+            expected[i] += 1;
+            remainder -= 1;
+        }
+    }
+    expected
+}
+
+/// Returns the sum of the weights of `nodes`, checking that they are valid.
+fn total_weight(nodes: &[RebalanceNode]) -> Result<f64, SpinelDBError> {
+    if nodes
+        .iter()
+        .any(|n| !n.weight.is_finite() || n.weight < 0.0)
+    {
+        return Err(SpinelDBError::InvalidState(
+            "Node weights must be non-negative numbers".into(),
+        ));
+    }
+    let total_weight: f64 = nodes.iter().map(|n| n.weight).sum();
+    if total_weight <= 0.0 {
+        return Err(SpinelDBError::InvalidState(
+            "At least one node must have a positive weight".into(),
+        ));
+    }
+    Ok(total_weight)
+}
+
+/// Computes the moves needed to distribute the slots of `nodes` by weight.
+///
+/// Returns no moves if every node is within `threshold_pct` percent of its share.
+/// A node with weight 0 that still holds slots is always considered unbalanced.
+pub fn plan(nodes: &[RebalanceNode], threshold_pct: f64) -> Result<Vec<SlotMove>, SpinelDBError> {
+    let total_weight = total_weight(nodes)?;
+    let total_slots: usize = nodes.iter().map(|n| n.slots.len()).sum();
+    let expected = expected_slot_counts(nodes, total_slots, total_weight);
+
+    let unbalanced = nodes.iter().zip(&expected).any(|(node, &target)| {
+        let current = node.slots.len();
+        if target == 0 {
+            return current > 0;
+        }
+        let deviation = (current as f64 - target as f64).abs() / target as f64 * 100.0;
+        deviation > threshold_pct
+    });
+    if !unbalanced {
+        return Ok(vec![]);
+    }
+
+    // Donors are sorted by surplus and receivers by deficit, largest first.
+    let mut donors: Vec<(usize, usize)> = Vec::new();
+    let mut receivers: Vec<(usize, usize)> = Vec::new();
+    for (i, (node, &target)) in nodes.iter().zip(&expected).enumerate() {
+        let current = node.slots.len();
+        if current > target {
+            donors.push((i, current - target));
+        } else if current < target {
+            receivers.push((i, target - current));
+        }
+    }
+    donors.sort_by(|a, b| {
+        b.1.cmp(&a.1)
+            .then_with(|| nodes[a.0].id.cmp(&nodes[b.0].id))
+    });
+    receivers.sort_by(|a, b| {
+        b.1.cmp(&a.1)
+            .then_with(|| nodes[a.0].id.cmp(&nodes[b.0].id))
+    });
+
+    let mut donor_slots: Vec<Vec<u16>> = nodes
+        .iter()
+        .map(|n| n.slots.iter().copied().collect())
+        .collect();
+    let mut moves = Vec::new();
+    let (mut d, mut r) = (0, 0);
+    while d < donors.len() && r < receivers.len() {
+        let (donor, surplus) = &mut donors[d];
+        let (receiver, deficit) = &mut receivers[r];
+        let count = (*surplus).min(*deficit);
+
+        let slots = &mut donor_slots[*donor];
+        let mut moved = slots.split_off(slots.len() - count);
+        moved.sort_unstable();
+        moves.push(SlotMove {
+            source: nodes[*donor].id.clone(),
+            destination: nodes[*receiver].id.clone(),
+            slots: moved,
+        });
+
+        *surplus -= count;
+        *deficit -= count;
+        if *surplus == 0 {
+            d += 1;
+        }
+        if *deficit == 0 {
+            r += 1;
+        }
+    }
+    Ok(moves)
+}
+
+/// Computes the moves needed to distribute the load of `nodes` by weight, where each
+/// slot of `nodes[i]` carries a load of `slot_loads[i]`, e.g. the memory it uses.
+///
+/// Returns no moves if every node is within `threshold_pct` percent of its share of
+/// the total load. Otherwise the slots of each overloaded node are moved one by one
+/// to the node furthest below its share, as long as neither node ends up on the other
+/// side of its share. Nodes with weight 0 are drained completely.
+pub fn plan_by_load(
+    nodes: &[RebalanceNode],
+    slot_loads: &[f64],
+    threshold_pct: f64,
+) -> Result<Vec<SlotMove>, SpinelDBError> {
+    let total_weight = total_weight(nodes)?;
+    if slot_loads.len() != nodes.len() || slot_loads.iter().any(|l| !l.is_finite() || *l < 0.0) {
+        return Err(SpinelDBError::InvalidState(
+            "Slot loads must be non-negative numbers".into(),
+        ));
+    }
+    let mut loads: Vec<f64> = nodes
+        .iter()
+        .zip(slot_loads)
+        .map(|(node, load)| node.slots.len() as f64 * load)
+        .collect();
+    let total_load: f64 = loads.iter().sum();
+    let targets: Vec<f64> = nodes
+        .iter()
+        .map(|n| total_load * n.weight / total_weight)
+        .collect();
+
+    let unbalanced = nodes.iter().enumerate().any(|(i, node)| {
+        if node.weight == 0.0 || targets[i] == 0.0 {
+            return node.weight == 0.0 && !node.slots.is_empty();
+        }
+        (loads[i] - targets[i]).abs() / targets[i] * 100.0 > threshold_pct
+    });
+    if !unbalanced {
+        return Ok(vec![]);
+    }
+
+    // The most overloaded nodes donate first.
+    let mut donors: Vec<usize> = (0..nodes.len())
+        .filter(|&i| {
+            loads[i] > targets[i] || (nodes[i].weight == 0.0 && !nodes[i].slots.is_empty())
+        })
+        .collect();
+    donors.sort_by(|&a, &b| {
+        (loads[b] - targets[b])
+            .total_cmp(&(loads[a] - targets[a]))
+            .then_with(|| nodes[a].id.cmp(&nodes[b].id))
+    });
+
+    let mut batches: BTreeMap<(usize, usize), Vec<u16>> = BTreeMap::new();
+    for donor in donors {
+        let drain = nodes[donor].weight == 0.0;
+        let load = slot_loads[donor];
+        // All slots of a node carry the same load, so the highest-numbered move first.
+        for &slot in nodes[donor].slots.iter().rev() {
+            let surplus = loads[donor] - targets[donor];
+            if !drain && surplus < load {
+                break;
+            }
+            let receiver = (0..nodes.len())
+                .filter(|&i| i != donor && nodes[i].weight > 0.0)
+                .max_by(|&a, &b| {
+                    (targets[a] - loads[a])
+                        .total_cmp(&(targets[b] - loads[b]))
+                        .then_with(|| nodes[b].id.cmp(&nodes[a].id))
+                });
+            let Some(receiver) = receiver else {
+                break;
+            };
+            if !drain && targets[receiver] - loads[receiver] < load {
+                break;
+            }
+            loads[donor] -= load;
+            loads[receiver] += load;
+            batches.entry((donor, receiver)).or_default().push(slot);
+        }
+    }
+
+    Ok(batches
+        .into_iter()
+        .map(|((donor, receiver), mut slots)| {
+            slots.sort_unstable();
+            SlotMove {
+                source: nodes[donor].id.clone(),
+                destination: nodes[receiver].id.clone(),
+                slots,
+            }
+        })
+        .collect())
+}
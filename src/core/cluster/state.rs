@@ -3,11 +3,16 @@
 //! Manages the shared state of the cluster, including node information,
 //! slot mappings, and failover status.
 
+use super::fanout::FanoutPool;
+use super::gossip::now_ms;
+use super::migration::{InFlightWrite, InFlightWrites, SlotMigrationGate};
+use super::rebalance::RebalanceProgress;
 use super::slot::NUM_SLOTS;
 use crate::config::{Config, IntoMutex, ReplicationConfig};
 use crate::core::SpinelDBError;
 use crate::core::state::ServerState;
 use bitflags::bitflags;
+use bytes::Bytes;
 use dashmap::DashMap;
 use dashmap::mapref::one::Ref;
 use parking_lot::RwLock;
@@ -17,6 +22,7 @@ use std::sync::Arc;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::{Duration, Instant};
 use tokio::fs;
+use tokio::sync::Notify;
 use tracing::{error, info, warn};
 
 /// The role of a node in the cluster.
@@ -102,6 +108,14 @@ pub struct SerializableClusterState {
     nodes: Vec<ClusterNode>,
 }
 
+/// Keeps a write admitted by `ClusterState::begin_write` counted as in progress, so
+/// that a manual failover or a slot migration cutover waits for it.
+#[derive(Debug)]
+pub struct ClusterWriteGuard {
+    _failover: InFlightWrite,
+    _slot: Option<InFlightWrite>,
+}
+
 /// `ClusterState` is the main container for all cluster-related information on this node.
 #[derive(Debug)]
 pub struct ClusterState {
@@ -123,6 +137,24 @@ pub struct ClusterState {
     pub failover_auth_count: AtomicU64,
     pub failover_auth_rank: AtomicU64,
     pub failover_auth_epoch: AtomicU64,
+    /// The progress of the most recent `CLUSTER REBALANCE` started on this node.
+    pub rebalance_progress: RwLock<RebalanceProgress>,
+    // --- Manual failover (`CLUSTER FAILOVER`) state ---
+    /// On a primary, the UNIX time in milliseconds until which writes are paused so a
+    /// replica can catch up before taking over. Zero when no pause is active.
+    pub manual_failover_pause_until: AtomicU64,
+    /// Wakes writes waiting for a manual failover pause to end.
+    pub manual_failover_resumed: Notify,
+    /// On a primary, the admitted writes that are still in progress. A manual failover
+    /// pause waits for these before reporting the replication offset.
+    pub manual_failover_in_flight: Arc<InFlightWrites>,
+    /// On a replica running a manual failover, the replication offset reported by its
+    /// primary once writes were paused.
+    pub manual_failover_primary_offset: RwLock<Option<u64>>,
+    /// Coordinates client writes with an atomic migration of a slot away from this node.
+    pub slot_migration: SlotMigrationGate,
+    /// Connections to other nodes used by fanned-out multi-key commands.
+    pub fanout_pool: FanoutPool,
 }
 
 impl ClusterState {
@@ -189,6 +221,13 @@ impl ClusterState {
             failover_auth_count: AtomicU64::new(0),
             failover_auth_rank: AtomicU64::new(0),
             failover_auth_epoch: AtomicU64::new(0),
+            rebalance_progress: RwLock::new(RebalanceProgress::default()),
+            manual_failover_pause_until: AtomicU64::new(0),
+            manual_failover_resumed: Notify::new(),
+            manual_failover_in_flight: Arc::default(),
+            manual_failover_primary_offset: RwLock::new(None),
+            slot_migration: SlotMigrationGate::default(),
+            fanout_pool: FanoutPool::default(),
         })
     }
 
@@ -283,6 +322,13 @@ impl ClusterState {
             failover_auth_count: AtomicU64::new(0),
             failover_auth_rank: AtomicU64::new(0),
             failover_auth_epoch: AtomicU64::new(s_state.current_epoch),
+            rebalance_progress: RwLock::new(RebalanceProgress::default()),
+            manual_failover_pause_until: AtomicU64::new(0),
+            manual_failover_resumed: Notify::new(),
+            manual_failover_in_flight: Arc::default(),
+            manual_failover_primary_offset: RwLock::new(None),
+            slot_migration: SlotMigrationGate::default(),
+            fanout_pool: FanoutPool::default(),
         })
     }
 
@@ -521,8 +567,15 @@ impl ClusterState {
     /// Generates a new, unique configuration epoch for this node.
     pub fn get_new_config_epoch(&self) -> u64 {
         let current = self.current_epoch.load(Ordering::Relaxed);
-        let my_epoch = self.get_my_config().node_info.config_epoch;
-        let new_epoch = current.max(my_epoch) + 1;
+        // The new epoch must be above every known node's epoch, so that the node it is
+        // used for wins any conflict over slots with their previous owner.
+        let max_known_epoch = self
+            .nodes
+            .iter()
+            .map(|entry| entry.value().node_info.config_epoch)
+            .max()
+            .unwrap_or(0);
+        let new_epoch = current.max(max_known_epoch) + 1;
         self.current_epoch.store(new_epoch, Ordering::Relaxed);
         new_epoch
     }
@@ -564,6 +617,10 @@ impl ClusterState {
                 .insert(new_runtime.node_info.id.clone(), new_runtime);
         }
 
+        if received_node.get_flags().contains(NodeFlags::PRIMARY) {
+            self.claim_slots_with_newer_epoch(&received_node);
+        }
+
         let my_config = self.get_my_config();
         if my_config.node_info.get_flags().contains(NodeFlags::PRIMARY)
             && received_node.get_flags().contains(NodeFlags::PRIMARY)
@@ -590,6 +647,24 @@ impl ClusterState {
         }
     }
 
+    /// Points the slots claimed by `node` at it when its config epoch is newer than
+    /// that of their current owner, as happens after a failover.
+    fn claim_slots_with_newer_epoch(&self, node: &ClusterNode) {
+        for &slot in &node.slots {
+            let owner_id = self.slots_map[slot as usize].read().clone();
+            let Some(owner_id) = owner_id.filter(|id| *id != node.id) else {
+                continue;
+            };
+            let owner_epoch = self
+                .nodes
+                .get(&owner_id)
+                .map_or(0, |owner| owner.node_info.config_epoch);
+            if node.config_epoch > owner_epoch {
+                *self.slots_map[slot as usize].write() = Some(node.id.clone());
+            }
+        }
+    }
+
     /// Handles the case where this node discovers a new primary with a higher epoch,
     /// triggering a self-demotion to a replica to prevent split-brain.
     async fn handle_epoch_conflict_and_reconfigure(
@@ -633,6 +708,8 @@ impl ClusterState {
 
         self.save_config().await?;
 
+        // Writes held back by a manual failover can now be redirected to the new primary.
+        self.resume_writes();
         state.set_quorum_loss_read_only(false, "Reconfiguring as a replica.");
 
         if state.replication_reconfigure_tx.send(()).is_err() {
@@ -652,6 +729,110 @@ impl ClusterState {
             .is_some_and(|id| *id == self.my_id)
     }
 
+    /// Checks if this node is a replica of the owner of a given slot, which allows it
+    /// to serve reads for that slot to `READONLY` clients, and its shard channels.
+    pub fn i_replicate_slot_owner(&self, slot: u16) -> bool {
+        let owner_id = self.slots_map[slot as usize].read();
+        let Some(owner_id) = owner_id.as_deref() else {
+            return false;
+        };
+        self.nodes
+            .get(&self.my_id)
+            .is_some_and(|myself| myself.node_info.replica_of.as_deref() == Some(owner_id))
+    }
+
+    /// Returns true while writes are paused for a manual failover.
+    pub fn is_write_paused(&self) -> bool {
+        self.manual_failover_pause_until.load(Ordering::SeqCst) > now_ms()
+    }
+
+    /// Waits until any write pause set for a manual failover has ended. The pause is
+    /// bounded by its deadline, so a failover that never completes cannot block writes
+    /// for longer than that.
+    pub async fn wait_for_write_pause(&self) {
+        loop {
+            let resumed = self.manual_failover_resumed.notified();
+            tokio::pin!(resumed);
+            // Registers for the notification before checking, so that a pause that
+            // ends in between is not missed.
+            resumed.as_mut().enable();
+            let until = self.manual_failover_pause_until.load(Ordering::SeqCst);
+            let now = now_ms();
+            if until <= now {
+                return;
+            }
+            let _ = tokio::time::timeout(Duration::from_millis(until - now), resumed).await;
+        }
+    }
+
+    /// Pauses writes for a manual failover until `until`, a UNIX time in milliseconds,
+    /// or until `resume_writes` is called.
+    pub fn pause_writes(&self, until: u64) {
+        self.manual_failover_pause_until
+            .store(until, Ordering::SeqCst);
+    }
+
+    /// Ends a manual failover pause and wakes the writes waiting for it.
+    pub fn resume_writes(&self) {
+        self.manual_failover_pause_until.store(0, Ordering::SeqCst);
+        self.manual_failover_resumed.notify_waiters();
+    }
+
+    /// Waits until every write admitted before a manual failover pause has finished,
+    /// including its propagation to replicas. Returns false if some were still in
+    /// progress at `deadline`.
+    pub async fn wait_for_writes_in_flight(&self, deadline: Instant) -> bool {
+        self.manual_failover_in_flight.wait_drained(deadline).await
+    }
+
+    /// Admits a write command to `keys`, waiting out a manual failover pause and the
+    /// cutover of an atomic slot migration. The returned guard must be held until the
+    /// command has been executed and propagated, except while a blocking command waits.
+    pub async fn begin_write(&self, keys: &[Bytes]) -> ClusterWriteGuard {
+        let failover = loop {
+            self.wait_for_write_pause().await;
+            let guard = InFlightWrite::new(&self.manual_failover_in_flight);
+            // The pause may have started after the wait above; if so, back off so that
+            // the failover does not report an offset this write could still move.
+            if !self.is_write_paused() {
+                break guard;
+            }
+        };
+        ClusterWriteGuard {
+            _failover: failover,
+            _slot: self.slot_migration.enter(keys).await,
+        }
+    }
+
+    /// Assigns `slot` to `new_owner_id` and clears any migration state for it.
+    pub fn assign_slot(&self, slot: u16, new_owner_id: &str) -> Result<(), SpinelDBError> {
+        if !self.nodes.contains_key(new_owner_id) {
+            return Err(SpinelDBError::InvalidState(format!(
+                "Node {new_owner_id} not found"
+            )));
+        }
+
+        // Clear migration state from all nodes for this slot
+        for mut node in self.nodes.iter_mut() {
+            node.node_info.migrating_slots.remove(&slot);
+            node.node_info.importing_slots.remove(&slot);
+        }
+
+        // Remove slot from old owner
+        if let Some(id) = { self.slots_map[slot as usize].read().clone() }
+            && let Some(mut old_owner) = self.nodes.get_mut(&id)
+        {
+            old_owner.node_info.slots.remove(&slot);
+        }
+
+        // Assign slot to new owner
+        if let Some(mut new_owner) = self.nodes.get_mut(new_owner_id) {
+            new_owner.node_info.slots.insert(slot);
+            *self.slots_map[slot as usize].write() = Some(new_owner_id.to_string());
+        }
+        Ok(())
+    }
+
     /// Returns the node that is responsible for a given slot.
     pub fn get_node_for_slot(&self, slot: u16) -> Option<Ref<'_, String, NodeRuntimeState>> {
         let owner_id = self.slots_map[slot as usize].read();
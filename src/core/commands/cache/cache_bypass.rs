@@ -75,7 +75,7 @@ impl ExecutableCommand for CacheBypass {
 
         // Call the fetch logic with the `bypass_store` flag set to true.
         let (outcome, _) = fetch_cmd
-            .fetch_from_origin(&ctx.state, true, target_ip, domain)
+            .fetch_from_origin(&ctx.state, ctx.db_index(), true, target_ip, domain)
             .await?;
 
         // Convert the fetch outcome into a single byte buffer for the client.
@@ -3,18 +3,24 @@
 //! Implements the `CACHE.FETCH` command, providing atomic, stampede-protected
 //! fetching of cacheable content from an origin server, with support for streaming large bodies.
 
+use super::helpers::{select_response_headers, surrogate_keys};
+use crate::connection::SessionState;
+use crate::core::cluster::state::ClusterWriteGuard;
 use crate::core::commands::cache::cache_get::CacheGet;
 use crate::core::commands::cache::cache_set::CacheSet;
+use crate::core::commands::cache::command::{Cache, CacheSubcommand};
 use crate::core::commands::command_spec::CommandSpec;
 use crate::core::commands::command_trait::{
     CommandFlags, ExecutableCommand, ParseCommand, WriteOutcome,
 };
 use crate::core::commands::helpers::{ArgParser, extract_bytes, validate_fetch_url};
 use crate::core::database::ExecutionContext;
+use crate::core::events::UnitOfWork;
 use crate::core::handler::command_router::RouteResponse;
+use crate::core::handler::pipeline::{cluster_redirect, state_check};
 use crate::core::protocol::RespFrame;
 use crate::core::state::ServerState;
-use crate::core::storage::cache_types::{CacheBody, ManifestState};
+use crate::core::storage::cache_types::{CacheBody, HttpMetadata, ManifestState};
 use crate::core::{Command, RespValue, SpinelDBError};
 use async_trait::async_trait;
 use bytes::Bytes;
@@ -37,6 +43,7 @@ pub enum FetchOutcome {
     /// The response body was large and has been streamed to a file on disk.
     OnDisk { path: PathBuf, size: u64 },
     /// The origin responded with a non-200 status, which has been negatively cached.
+    /// A `200` answer to a range request is reported this way too, but is not stored.
     Negative { status: u16, body: Option<Bytes> },
 }
 
@@ -51,6 +58,12 @@ pub struct CacheFetch {
     pub tags: Vec<Bytes>,
     pub vary: Option<Bytes>,
     pub headers: Option<Vec<(Bytes, Bytes)>>,
+    /// An inclusive byte range to request from the origin, which must answer
+    /// `206 Partial Content`. Set for slice fetches by the HTTP front end; never parsed.
+    pub range: Option<(u64, u64)>,
+    /// The validator sent as `If-Range` with `range`, so that the origin answers with
+    /// the whole object instead of a slice of a newer version. Never parsed.
+    pub if_range: Option<Bytes>,
 }
 
 /// Parses Cache-Control header to extract max-age and stale-while-revalidate.
@@ -183,22 +196,8 @@ impl CacheFetch {
         &self,
         ctx: &mut ExecutionContext<'a>,
     ) -> Result<RouteResponse, SpinelDBError> {
-        let (allowed_domains, allow_private) = {
-            let config = ctx.state.config.lock().await;
-            (
-                config.security.allowed_fetch_domains.clone(),
-                config.security.allow_private_fetch_ips,
-            )
-        };
-        let resolved_ips = validate_fetch_url(&self.url, &allowed_domains, allow_private).await?;
-        let target_ip = resolved_ips.first().cloned().ok_or_else(|| {
-            SpinelDBError::Internal("Validated URL did not return any IP addresses".to_string())
-        })?;
-
-        // Extract domain for Host header
-        let url_parsed = Url::parse(&self.url)
-            .map_err(|e| SpinelDBError::InvalidRequest(format!("Invalid URL: {e}")))?;
-        let domain = url_parsed.host_str().unwrap_or("").to_string();
+        let (target_ip, domain) = self.resolve_origin(&ctx.state).await?;
+        let db_index = ctx.db_index();
 
         // Bypass cache store and shared future logic for authorized requests.
         if self
@@ -212,7 +211,7 @@ impl CacheFetch {
                 self.url
             );
             let (outcome, _) = self
-                .fetch_from_origin(&ctx.state, true, target_ip, domain)
+                .fetch_from_origin(&ctx.state, db_index, true, target_ip, domain)
                 .await?;
             let body_bytes = match outcome {
                 FetchOutcome::InMemory(bytes) => bytes,
@@ -239,11 +238,119 @@ impl CacheFetch {
             return Ok(initial_response);
         }
 
-        // --- Cache Stampede Protection using a Shared Future ---
+        // Release the shard lock before the potentially long fetch. The leader stores
+        // the fetched content through its own lock on the same shard.
+        ctx.release_locks();
+
         let state = ctx.state.clone();
+        match self
+            .fetch_shared(&state, db_index, target_ip, domain, false)
+            .await?
+        {
+            FetchOutcome::InMemory(bytes) => {
+                Ok(RouteResponse::Single(RespValue::BulkString(bytes)))
+            }
+            FetchOutcome::OnDisk { path, size } => {
+                let permit = state
+                    .cache
+                    .on_disk_read_semaphore
+                    .clone()
+                    .acquire_owned()
+                    .await
+                    .map_err(|e| {
+                        SpinelDBError::Internal(format!(
+                            "Failed to acquire semaphore permit: {}",
+                            e
+                        ))
+                    })?;
+
+                let file = TokioFile::open(&path).await.map_err(|e| {
+                    SpinelDBError::Internal(format!("Failed to open cache file for streaming: {e}"))
+                })?;
+                let resp_header = format!("${size}\r\n").into_bytes();
+                Ok(RouteResponse::StreamBody {
+                    resp_header,
+                    file: file.take(size),
+                    _permit: permit,
+                })
+            }
+            FetchOutcome::Negative { status, body } => Err(SpinelDBError::InvalidState(format!(
+                "Origin responded with status {status}: {}",
+                String::from_utf8_lossy(&body.unwrap_or_default())
+            ))),
+        }
+    }
+
+    /// Validates the URL against the fetch security settings and resolves the origin's
+    /// IP address and host name. The fetch is pinned to the returned IP, which prevents
+    /// DNS rebinding between validation and the request.
+    pub async fn resolve_origin(
+        &self,
+        state: &Arc<ServerState>,
+    ) -> Result<(IpAddr, String), SpinelDBError> {
+        let (allowed_domains, allow_private) = {
+            let config = state.config.lock().await;
+            (
+                config.security.allowed_fetch_domains.clone(),
+                config.security.allow_private_fetch_ips,
+            )
+        };
+        let resolved_ips = validate_fetch_url(&self.url, &allowed_domains, allow_private).await?;
+        let target_ip = resolved_ips.first().cloned().ok_or_else(|| {
+            SpinelDBError::Internal("Validated URL did not return any IP addresses".to_string())
+        })?;
+
+        let url_parsed = Url::parse(&self.url)
+            .map_err(|e| SpinelDBError::InvalidRequest(format!("Invalid URL: {e}")))?;
+        let domain = url_parsed.host_str().unwrap_or("").to_string();
+        Ok((target_ip, domain))
+    }
+
+    /// Admits a store of this fetch that is issued outside of the command router, as
+    /// the router admits `CACHE.FETCH`: this node must own the key's slot, accept
+    /// writes and be below `maxmemory`. The returned admission must be held until the
+    /// store has been propagated.
+    pub async fn admit_store(
+        &self,
+        state: &Arc<ServerState>,
+        db_index: usize,
+    ) -> Result<Option<ClusterWriteGuard>, SpinelDBError> {
+        let keys = [self.key.clone()];
+        let admission = match &state.cluster {
+            Some(cluster_state) => Some(cluster_state.begin_write(&keys).await),
+            None => None,
+        };
+        let mut session = SessionState::new(false, false, false);
+        session.current_db_index = db_index;
+        cluster_redirect::check_redirection(state, &keys, &session, false).await?;
+        state_check::check_server_state(state, &self.as_command()).await?;
+        Ok(admission)
+    }
+
+    fn as_command(&self) -> Command {
+        Command::Cache(Cache {
+            subcommand: CacheSubcommand::Fetch(self.clone()),
+        })
+    }
+
+    /// Fetches from the origin through a per-key shared future, so that concurrent
+    /// misses for the same key result in a single origin request. The content is stored
+    /// in database `db_index`. Must be called without holding the key's shard lock.
+    ///
+    /// With `propagate`, the leader propagates the store to the AOF and replicas as
+    /// `CACHE.FETCH`. It is set by fetches issued outside of the command router.
+    pub async fn fetch_shared(
+        &self,
+        state: &Arc<ServerState>,
+        db_index: usize,
+        target_ip: IpAddr,
+        domain: String,
+        propagate: bool,
+    ) -> Result<FetchOutcome, SpinelDBError> {
         let key = self.key.clone();
+        let lock_key = (db_index, key.clone());
 
-        let future_to_await = match state.cache.fetch_locks.entry(key.clone()) {
+        let future_to_await = match state.cache.fetch_locks.entry(lock_key.clone()) {
             // Follower path: an origin fetch is already in progress.
             dashmap::mapref::entry::Entry::Occupied(occupied) => {
                 debug!(
@@ -267,13 +374,27 @@ impl CacheFetch {
                 let fetch_future: BoxFuture<'static, Result<FetchOutcome, Arc<SpinelDBError>>> =
                     async move {
                         match command_clone
-                            .fetch_from_origin(&state_clone, false, leader_ip, leader_domain)
+                            .fetch_from_origin(
+                                &state_clone,
+                                db_index,
+                                false,
+                                leader_ip,
+                                leader_domain,
+                            )
                             .await
                         {
                             Ok((outcome, write_outcome)) => {
                                 // The leader is responsible for updating the dirty keys counter.
                                 if let WriteOutcome::Write { keys_modified } = write_outcome {
                                     state_clone.persistence.increment_dirty_keys(keys_modified);
+                                    if propagate {
+                                        state_clone.event_bus.publish(
+                                            UnitOfWork::Command(Box::new(
+                                                command_clone.as_command(),
+                                            )),
+                                            &state_clone,
+                                        );
+                                    }
                                 }
                                 Ok(outcome)
                             }
@@ -292,67 +413,70 @@ impl CacheFetch {
         let fetch_result = future_to_await.await;
 
         // The operation is complete; remove the future from the map to prevent memory leaks.
-        state.cache.fetch_locks.remove(&key);
-
-        match fetch_result {
-            Ok(outcome) => {
-                // Each client constructs its own response from the shared outcome.
-                match outcome {
-                    FetchOutcome::InMemory(bytes) => {
-                        Ok(RouteResponse::Single(RespValue::BulkString(bytes)))
-                    }
-                    FetchOutcome::OnDisk { path, size } => {
-                        let permit = state
-                            .cache
-                            .on_disk_read_semaphore
-                            .clone()
-                            .acquire_owned()
-                            .await
-                            .map_err(|e| {
-                                SpinelDBError::Internal(format!(
-                                    "Failed to acquire semaphore permit: {}",
-                                    e
-                                ))
-                            })?;
-
-                        let file = TokioFile::open(&path).await.map_err(|e| {
-                            SpinelDBError::Internal(format!(
-                                "Failed to open cache file for streaming: {e}"
-                            ))
-                        })?;
-                        let resp_header = format!("${size}\r\n").into_bytes();
-                        Ok(RouteResponse::StreamBody {
-                            resp_header,
-                            file,
-                            _permit: permit,
-                        })
-                    }
-                    FetchOutcome::Negative { status, body } => {
-                        Err(SpinelDBError::InvalidState(format!(
-                            "Origin responded with status {status}: {}",
-                            String::from_utf8_lossy(&body.unwrap_or_default())
-                        )))
-                    }
-                }
-            }
-            Err(arc_err) => Err(SpinelDBError::clone(&*arc_err)),
-        }
+        state.cache.fetch_locks.remove(&lock_key);
+
+        fetch_result.map_err(|arc_err| SpinelDBError::clone(&*arc_err))
+    }
+
+    /// Reads the metadata stored for this fetch's variant in database `db_index`, e.g.
+    /// after a fetch completed.
+    pub async fn stored_metadata(
+        &self,
+        state: &Arc<ServerState>,
+        db_index: usize,
+    ) -> Result<HttpMetadata, SpinelDBError> {
+        let get_cmd = CacheGet {
+            key: self.key.clone(),
+            headers: self.headers.clone(),
+            ..Default::default()
+        };
+        let command = Command::Cache(crate::core::commands::cache::Cache {
+            subcommand: crate::core::commands::cache::command::CacheSubcommand::Get(
+                get_cmd.clone(),
+            ),
+        });
+        let db = state
+            .get_db(db_index)
+            .ok_or_else(|| SpinelDBError::Internal("Cache database not found".into()))?;
+        let mut ctx = ExecutionContext {
+            state: state.clone(),
+            locks: db.determine_locks_for_command(&command).await,
+            db: &db,
+            command: Some(command),
+            session_id: 0,
+            authenticated_user: None,
+            write_guard: None,
+        };
+        Ok(get_cmd
+            .variant_metadata(&mut ctx)?
+            .map(|(metadata, _)| metadata)
+            .unwrap_or_default())
     }
 
     /// Fetches from the origin, deciding whether to stream to disk or buffer in memory.
+    /// Unless `bypass_store` is set, the content is stored in database `db_index`.
     pub async fn fetch_from_origin(
         &self,
         server_state: &Arc<ServerState>,
+        db_index: usize,
         mut bypass_store: bool,
         resolved_ip: IpAddr,
         domain: String,
     ) -> Result<(FetchOutcome, WriteOutcome), SpinelDBError> {
-        let (streaming_threshold, cache_path, global_negative_ttl) = {
+        let (
+            streaming_threshold,
+            cache_path,
+            global_negative_ttl,
+            header_allowlist,
+            surrogate_key_headers,
+        ) = {
             let config = server_state.config.lock().await;
             (
                 config.cache.streaming_threshold_bytes,
                 config.cache.on_disk_path.clone(),
                 config.cache.negative_cache_ttl_seconds,
+                config.cache.stored_response_headers.clone(),
+                config.cache.surrogate_key_headers.clone(),
             )
         };
 
@@ -364,8 +488,9 @@ impl CacheFetch {
 
         let respect_origin = matched_policy.is_some_and(|p| p.respect_origin_headers);
         let policy_negative_ttl = matched_policy.and_then(|p| p.negative_ttl);
+        let use_surrogate_keys = matched_policy.is_some_and(|p| p.surrogate_keys);
 
-        server_state.cache.increment_misses();
+        server_state.cache.increment_misses(db_index);
 
         let url_parsed = Url::parse(&self.url)
             .map_err(|e| SpinelDBError::InvalidRequest(format!("Invalid URL: {e}")))?;
@@ -378,19 +503,70 @@ impl CacheFetch {
             .build()
             .map_err(|e| SpinelDBError::HttpClientError(e.to_string()))?;
 
-        let mut res = client
-            .get(&self.url)
-            .send()
-            .await
-            .map_err(|e| SpinelDBError::HttpClientError(e.to_string()))?;
+        let mut request = client.get(&self.url);
+        if let Some((start, end)) = self.range {
+            request = request.header(reqwest::header::RANGE, format!("bytes={start}-{end}"));
+            if let Some(validator) = &self.if_range {
+                request = request.header(reqwest::header::IF_RANGE, validator.as_ref());
+            }
+        }
+        // The slot is held until the body has been read.
+        let mut origin_permit = server_state
+            .cache
+            .acquire_origin(&self.url, matched_policy)
+            .await?;
+        let mut res = match request.send().await {
+            Ok(res) => res,
+            Err(e) => {
+                origin_permit.record(false);
+                return Err(SpinelDBError::HttpClientError(e.to_string()));
+            }
+        };
+        origin_permit.record(!res.status().is_server_error());
+
+        let mut response_headers = select_response_headers(res.headers(), &header_allowlist);
+        let mut tags = self.tags.clone();
+        let mut origin_keys = Vec::new();
+        if use_surrogate_keys {
+            for tag in surrogate_keys(res.headers(), &surrogate_key_headers) {
+                if !tags.contains(&tag) {
+                    tags.push(tag.clone());
+                    origin_keys.push(tag);
+                }
+            }
+        }
+        let expected_status = if self.range.is_some() {
+            // A slice is only usable with the origin's `Content-Range`.
+            if let Some(content_range) = res.headers().get(reqwest::header::CONTENT_RANGE) {
+                response_headers.push((
+                    Bytes::from_static(b"content-range"),
+                    Bytes::copy_from_slice(content_range.as_bytes()),
+                ));
+            }
+            if res.status() == reqwest::StatusCode::OK {
+                // The origin ignored the range, or the object changed since `if_range`.
+                return Ok((
+                    FetchOutcome::Negative {
+                        status: 200,
+                        body: None,
+                    },
+                    WriteOutcome::DidNotWrite,
+                ));
+            }
+            reqwest::StatusCode::PARTIAL_CONTENT
+        } else {
+            reqwest::StatusCode::OK
+        };
 
-        if res.status() != reqwest::StatusCode::OK {
+        if res.status() != expected_status {
             let status = res.status();
             let error_body = res.bytes().await.ok();
 
             let negative_ttl = policy_negative_ttl.unwrap_or(global_negative_ttl);
-            if !bypass_store && negative_ttl > 0 {
-                let db = server_state.get_db(0).unwrap();
+            if !bypass_store
+                && negative_ttl > 0
+                && let Some(db) = server_state.get_db(db_index)
+            {
                 let set_cmd_for_lock = Command::Cache(crate::core::commands::cache::Cache {
                     subcommand: crate::core::commands::cache::command::CacheSubcommand::Set(
                         CacheSet::default(),
@@ -403,13 +579,17 @@ impl CacheFetch {
                     command: Some(set_cmd_for_lock),
                     session_id: 0,
                     authenticated_user: None,
+                    write_guard: None,
                 };
                 let set_cmd_internal = CacheSet {
                     key: self.key.clone(),
                     ttl: Some(negative_ttl),
-                    tags: self.tags.clone(),
+                    tags: tags.clone(),
                     vary: self.vary.clone(),
                     headers: self.headers.clone(),
+                    status: Some(status.as_u16()),
+                    response_headers,
+                    surrogate_keys: origin_keys.clone(),
                     ..Default::default()
                 };
                 let _ = set_cmd_internal
@@ -452,6 +632,20 @@ impl CacheFetch {
                 swr_override = parsed_swr;
             }
         }
+        // `Surrogate-Control` is addressed to caches like this one and takes precedence.
+        if use_surrogate_keys
+            && let Some(sc_header) = headers
+                .get("surrogate-control")
+                .and_then(|v| v.to_str().ok())
+        {
+            let (parsed_ttl, parsed_swr) = parse_cache_control(sc_header);
+            if parsed_ttl.is_some() {
+                ttl_override = parsed_ttl;
+            }
+            if parsed_swr.is_some() {
+                swr_override = parsed_swr;
+            }
+        }
 
         if headers
             .get(reqwest::header::VARY)
@@ -480,7 +674,12 @@ impl CacheFetch {
 
             server_state
                 .cache
-                .log_manifest(self.key.clone(), ManifestState::Pending, final_path.clone())
+                .log_manifest(
+                    db_index,
+                    self.key.clone(),
+                    ManifestState::Pending,
+                    final_path.clone(),
+                )
                 .await?;
 
             let mut temp_file = OpenOptions::new()
@@ -535,13 +734,18 @@ impl CacheFetch {
             last_modified: headers
                 .get(reqwest::header::LAST_MODIFIED)
                 .map(|v| Bytes::from(v.as_bytes().to_vec())),
-            tags: self.tags.clone(),
+            tags,
             vary: self.vary.clone(),
             headers: self.headers.clone(),
+            status: Some(expected_status.as_u16()),
+            response_headers,
+            surrogate_keys: origin_keys,
             ..Default::default()
         };
 
-        let db = server_state.get_db(0).unwrap();
+        let db = server_state
+            .get_db(db_index)
+            .ok_or_else(|| SpinelDBError::Internal("Cache database not found".into()))?;
         let set_cmd_for_lock = Command::Cache(crate::core::commands::cache::Cache {
             subcommand: crate::core::commands::cache::command::CacheSubcommand::Set(
                 set_cmd_internal.clone(),
@@ -554,6 +758,7 @@ impl CacheFetch {
             command: Some(set_cmd_for_lock),
             session_id: 0,
             authenticated_user: None,
+            write_guard: None,
         };
 
         let (_, write_outcome) = set_cmd_internal
@@ -561,9 +766,14 @@ impl CacheFetch {
             .await?;
 
         if let CacheBody::OnDisk { path, .. } = final_cache_body {
+            let metadata = HttpMetadata {
+                status: set_cmd_internal.status,
+                headers: set_cmd_internal.response_headers.clone(),
+                ..Default::default()
+            };
             server_state
                 .cache
-                .log_manifest(set_cmd_internal.key.clone(), ManifestState::Committed, path)
+                .log_manifest_commit(db_index, set_cmd_internal.key.clone(), path, &metadata)
                 .await?;
         }
         Ok((final_outcome_for_client, write_outcome))
@@ -2,29 +2,34 @@
 
 //! Implements the `CACHE.GET` command, which retrieves a cached object.
 //! This implementation supports content variants via the `Vary` header,
-//! and advanced stale content serving strategies like stale-while-revalidate.
+//! advanced stale content serving strategies like stale-while-revalidate,
+//! byte ranges of cached bodies, and bodies encoded per `Accept-Encoding`.
 
-use super::helpers::calculate_variant_hash;
+use super::encoding::{self, Negotiation};
+use super::helpers::{
+    calculate_variant_hash, headers_to_resp, select_response_headers, surrogate_keys,
+};
 use crate::core::commands::cache::cache_set::apply_ttl_options;
 use crate::core::commands::command_spec::CommandSpec;
 use crate::core::commands::command_trait::{
     CommandFlags, ExecutableCommand, ParseCommand, WriteOutcome,
 };
-use crate::core::commands::helpers::{ArgParser, extract_bytes};
-use crate::core::database::ExecutionContext;
+use crate::core::commands::helpers::{ArgParser, extract_bytes, extract_string};
+use crate::core::database::{ExecutionContext, ExecutionLocks, tiering};
 use crate::core::handler::command_router::RouteResponse;
 use crate::core::protocol::RespFrame;
 use crate::core::state::ServerState;
-use crate::core::storage::cache_types::{CacheBody, CachePolicy};
+use crate::core::storage::cache_types::{CacheBody, CachePolicy, HttpMetadata};
 use crate::core::storage::data_types::{DataValue, StoredValue};
 use crate::core::{RespValue, SpinelDBError};
 use async_trait::async_trait;
 use bytes::Bytes;
 use reqwest::header::{HeaderMap, HeaderName, HeaderValue, IF_MODIFIED_SINCE, IF_NONE_MATCH};
+use std::io::{Read, SeekFrom};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::fs::File as TokioFile;
-use tokio::io::{AsyncReadExt, copy as tokio_copy};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, copy as tokio_copy};
 use tokio::sync::MutexGuard;
 use tracing::{debug, warn};
 use wildmatch::WildMatch;
@@ -38,6 +43,46 @@ pub struct CacheGet {
     pub if_none_match: Option<Bytes>,
     pub if_modified_since: Option<Bytes>,
     pub force_revalidate: bool,
+    /// If set, only this byte range of the body is returned.
+    pub range: Option<ByteRange>,
+    /// If true, fresh hits are returned as a bare body, streamed from disk where possible,
+    /// instead of the `[status, headers, body]` array. Set by the HTTP front end; never parsed.
+    pub raw: bool,
+}
+
+/// A byte range of a cached body, given as inclusive offsets like `GETRANGE`.
+/// Negative offsets count from the end of the body, so `-1` is its last byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRange {
+    pub start: i64,
+    pub end: i64,
+}
+
+impl ByteRange {
+    /// Resolves the range against a body of `len` bytes into absolute, inclusive
+    /// offsets. Returns `None` if the range selects no bytes.
+    pub fn resolve(&self, len: u64) -> Option<(u64, u64)> {
+        let len = len as i64;
+        let start = if self.start < 0 {
+            (len + self.start).max(0)
+        } else {
+            self.start
+        };
+        let end = if self.end < 0 {
+            len + self.end
+        } else {
+            self.end.min(len - 1)
+        };
+        (start < len && start <= end).then_some((start as u64, end as u64))
+    }
+
+    /// Formats the `Content-Range` value for a body of `len` bytes.
+    pub fn content_range(&self, len: u64) -> String {
+        match self.resolve(len) {
+            Some((start, end)) => format!("bytes {start}-{end}/{len}"),
+            None => format!("bytes */{len}"),
+        }
+    }
 }
 
 impl ParseCommand for CacheGet {
@@ -65,6 +110,22 @@ impl ParseCommand for CacheGet {
         if parser.match_flag("force-revalidate") {
             cmd.force_revalidate = true;
         }
+        if parser.match_flag("range") {
+            let remaining = parser.remaining_args();
+            if remaining.len() < 2 {
+                return Err(SpinelDBError::SyntaxError);
+            }
+            let offset = |frame: &RespFrame| -> Result<i64, SpinelDBError> {
+                extract_string(frame)?
+                    .parse()
+                    .map_err(|_| SpinelDBError::NotAnInteger)
+            };
+            cmd.range = Some(ByteRange {
+                start: offset(&remaining[0])?,
+                end: offset(&remaining[1])?,
+            });
+            parser = ArgParser::new(&remaining[2..]);
+        }
 
         if parser.match_flag("headers") {
             let remaining = parser.remaining_args();
@@ -139,20 +200,37 @@ impl CacheGet {
         ctx: &mut ExecutionContext<'a>,
     ) -> Result<RouteResponse, SpinelDBError> {
         let state = ctx.state.clone();
+        let db_index = ctx.db_index();
+        let negotiation = Negotiation::for_key(&state, &self.key).await;
+        if let Some(negotiation) = &negotiation
+            && !self.force_revalidate
+        {
+            self.prepare_rendition(ctx, negotiation).await?;
+        }
         let (_shard, guard) = ctx.get_single_shard_context_mut()?;
 
+        // A body file can be gone while the entry still references it, e.g. after a
+        // restart from a snapshot taken before the body was promoted to memory.
+        if self.drop_missing_on_disk_body(guard).await {
+            crate::core::metrics::CACHE_MISSES_TOTAL
+                .with_label_values(&["none"])
+                .inc();
+            state.cache.increment_misses(db_index);
+            return Ok(RouteResponse::NoOp);
+        }
+
         // Handle forced revalidation first.
         if self.force_revalidate {
-            return self.handle_force_revalidate(state, guard).await;
+            return self.handle_force_revalidate(state, db_index, guard).await;
         }
 
         // Check if the entry is valid (not expired and not invalidated by tags).
-        if !self.is_entry_valid(&state, guard) {
+        if !self.is_entry_valid(&state, db_index, guard) {
             guard.pop(&self.key);
             crate::core::metrics::CACHE_MISSES_TOTAL
                 .with_label_values(&["none"])
                 .inc();
-            state.cache.increment_misses();
+            state.cache.increment_misses(db_index);
             return Ok(RouteResponse::NoOp);
         }
 
@@ -164,21 +242,27 @@ impl CacheGet {
 
         // State 1: Fresh content.
         if entry_expiry.is_some_and(|exp| exp > now) {
-            return self.serve_fresh_content(state, guard).await;
+            return self
+                .serve_fresh_content(state, db_index, guard, negotiation.as_ref())
+                .await;
         }
 
         // State 2: Stale, but within the SWR window.
         if entry_swr_expiry.is_some_and(|exp| exp > now) {
-            return self.serve_stale_and_revalidate(state, guard).await;
+            return self
+                .serve_stale_and_revalidate(state, db_index, guard, negotiation.as_ref())
+                .await;
         }
 
         // State 3: Stale and past SWR, but within the grace window or revalidate requested.
         if self.revalidate_url.is_some() || entry_grace_expiry.is_some_and(|exp| exp > now) {
-            return self.serve_from_grace_or_revalidate(state, guard).await;
+            return self
+                .serve_from_grace_or_revalidate(state, db_index, guard, negotiation.as_ref())
+                .await;
         }
 
         // State 4: Expired completely.
-        state.cache.increment_misses();
+        state.cache.increment_misses(db_index);
         crate::core::metrics::CACHE_MISSES_TOTAL
             .with_label_values(&["none"])
             .inc();
@@ -190,9 +274,12 @@ impl CacheGet {
     async fn serve_fresh_content<'b>(
         &self,
         state: Arc<ServerState>,
+        db_index: usize,
         guard: &mut MutexGuard<'b, crate::core::database::ShardCache>,
+        negotiation: Option<&Negotiation>,
     ) -> Result<RouteResponse, SpinelDBError> {
         let entry = guard.get_mut(&self.key).unwrap();
+        let frequency = entry.lfu.counter;
         let DataValue::HttpCache {
             variants, vary_on, ..
         } = &mut entry.data
@@ -200,19 +287,27 @@ impl CacheGet {
             return Err(SpinelDBError::WrongType);
         };
         let variant_hash = calculate_variant_hash(vary_on, &self.headers);
-        let Some(variant) = variants.get_mut(&variant_hash) else {
-            state.cache.increment_misses();
+        if !variants.contains_key(&variant_hash) {
+            state.cache.increment_misses(db_index);
             crate::core::metrics::CACHE_MISSES_TOTAL
                 .with_label_values(&["none"])
                 .inc();
             return Ok(RouteResponse::NoOp);
+        }
+        let served_hash = self.select_variant(guard, variant_hash, negotiation, true);
+        let entry = guard.get_mut(&self.key).unwrap();
+        let DataValue::HttpCache { variants, .. } = &mut entry.data else {
+            return Err(SpinelDBError::WrongType);
         };
+        let variant = variants
+            .get_mut(&served_hash)
+            .ok_or_else(|| SpinelDBError::Internal("Selected cache variant is missing".into()))?;
         variant.last_accessed = Instant::now();
 
         if let CacheBody::Negative { status, body } = &variant.body {
             return Ok(RouteResponse::Single(RespValue::Array(vec![
                 RespValue::Integer(*status as i64),
-                RespValue::Array(vec![]),
+                headers_to_resp(variant.metadata.response_headers()),
                 RespValue::BulkString(body.clone().unwrap_or_default()),
             ])));
         }
@@ -228,12 +323,35 @@ impl CacheGet {
             return Ok(RouteResponse::NoOp); // 304 Not Modified
         }
 
-        state.cache.increment_hits();
+        state.cache.increment_hits(db_index);
         crate::core::metrics::CACHE_HITS_TOTAL
             .with_label_values(&["none"])
             .inc();
 
-        let body_response = Self::create_body_response(&state, &variant.body).await?;
+        // A hot body stored on disk is moved back to memory in the background.
+        if let CacheBody::OnDisk { path, .. } = &variant.body {
+            tiering::schedule_promotion(&state, db_index, &self.key, served_hash, path, frequency);
+        }
+
+        if self.raw {
+            return Self::create_body_response(&state, &variant.body, self.range).await;
+        }
+
+        let mut status = variant.metadata.status_code();
+        let mut headers = variant.metadata.response_headers();
+        if let Some(range) = self.range {
+            let len = variant.body.len() as u64;
+            status = if range.resolve(len).is_some() {
+                206
+            } else {
+                416
+            };
+            headers.push((
+                Bytes::from_static(b"content-range"),
+                Bytes::from(range.content_range(len)),
+            ));
+        }
+        let body_response = Self::create_body_response(&state, &variant.body, self.range).await?;
         let final_body = match body_response {
             RouteResponse::Single(RespValue::BulkString(bytes)) => bytes,
             RouteResponse::StreamBody { mut file, .. } => {
@@ -249,8 +367,8 @@ impl CacheGet {
         };
 
         Ok(RouteResponse::Single(RespValue::Array(vec![
-            RespValue::Integer(200),
-            RespValue::Array(vec![]),
+            RespValue::Integer(status as i64),
+            headers_to_resp(headers),
             RespValue::BulkString(final_body),
         ])))
     }
@@ -259,9 +377,11 @@ impl CacheGet {
     async fn serve_stale_and_revalidate<'b>(
         &self,
         state: Arc<ServerState>,
+        db_index: usize,
         guard: &mut MutexGuard<'b, crate::core::database::ShardCache>,
+        negotiation: Option<&Negotiation>,
     ) -> Result<RouteResponse, SpinelDBError> {
-        state.cache.increment_stale_hits();
+        state.cache.increment_stale_hits(db_index);
         let entry = guard.get_mut(&self.key).unwrap();
         let DataValue::HttpCache {
             variants, vary_on, ..
@@ -271,7 +391,7 @@ impl CacheGet {
         };
         let variant_hash = calculate_variant_hash(vary_on, &self.headers);
         let Some(variant) = variants.get_mut(&variant_hash) else {
-            state.cache.increment_misses();
+            state.cache.increment_misses(db_index);
             crate::core::metrics::CACHE_MISSES_TOTAL
                 .with_label_values(&["none"])
                 .inc();
@@ -279,10 +399,13 @@ impl CacheGet {
         };
         let revalidate_url_from_cache = variant.metadata.revalidate_url.clone();
         variant.last_accessed = Instant::now();
+        // Stale bodies are returned without headers, so they are only encoded for the
+        // HTTP front end, which reads the encoding from the variant's metadata.
+        let served_hash = self.select_variant(guard, variant_hash, negotiation, self.raw);
 
         if let Some(url) = self.revalidate_url.clone().or(revalidate_url_from_cache) {
             // --- LOGIC REVISED TO REMOVE UNNECESSARY LOOP ---
-            let is_leader = match state.cache.swr_locks.entry(self.key.clone()) {
+            let is_leader = match state.cache.swr_locks.entry((db_index, self.key.clone())) {
                 dashmap::mapref::entry::Entry::Occupied(mut entry) => {
                     if let Some(strong_lock) = entry.get().upgrade() {
                         strong_lock.try_lock().is_ok()
@@ -311,11 +434,14 @@ impl CacheGet {
                 let key_clone = self.key.clone();
                 let headers_clone = self.headers.clone();
                 tokio::spawn(async move {
-                    let db = state_clone.get_db(0).unwrap();
+                    let Some(db) = state_clone.get_db(db_index) else {
+                        return;
+                    };
                     let shard_index = db.get_shard_index(&key_clone);
                     let mut task_guard = db.get_shard(shard_index).entries.lock().await;
                     if let Err(e) = revalidate_and_update_cache(
                         state_clone,
+                        db_index,
                         key_clone,
                         url,
                         variant_hash,
@@ -329,14 +455,23 @@ impl CacheGet {
                 });
             }
         }
-        Self::create_body_response(&state, &variant.body).await
+        let entry = guard.get_mut(&self.key).unwrap();
+        let DataValue::HttpCache { variants, .. } = &entry.data else {
+            return Err(SpinelDBError::WrongType);
+        };
+        let variant = variants
+            .get(&served_hash)
+            .ok_or_else(|| SpinelDBError::Internal("Selected cache variant is missing".into()))?;
+        Self::create_body_response(&state, &variant.body, self.range).await
     }
 
     /// Serves content from its grace period after a failed revalidation attempt.
     async fn serve_from_grace_or_revalidate<'b>(
         &self,
         state: Arc<ServerState>,
+        db_index: usize,
         guard: &mut MutexGuard<'b, crate::core::database::ShardCache>,
+        negotiation: Option<&Negotiation>,
     ) -> Result<RouteResponse, SpinelDBError> {
         let (revalidate_url_from_cache, variant_hash) = {
             let entry = guard.peek(&self.key).unwrap();
@@ -365,6 +500,7 @@ impl CacheGet {
             })?;
         let reval_result = revalidate_and_update_cache(
             state.clone(),
+            db_index,
             self.key.clone(),
             url,
             variant_hash,
@@ -373,28 +509,40 @@ impl CacheGet {
         )
         .await;
 
-        let entry = guard.peek(&self.key).ok_or(SpinelDBError::KeyNotFound)?;
+        let entry = guard.get_mut(&self.key).ok_or(SpinelDBError::KeyNotFound)?;
+        let DataValue::HttpCache { variants, .. } = &entry.data else {
+            return Err(SpinelDBError::WrongType);
+        };
+        if !variants.contains_key(&variant_hash) {
+            return Err(SpinelDBError::Internal(
+                "Variant vanished after revalidation".into(),
+            ));
+        }
+        // A successful revalidation has replaced the body and dropped its renditions.
+        let served_hash = self.select_variant(guard, variant_hash, negotiation, self.raw);
+        let entry = guard.get_mut(&self.key).ok_or(SpinelDBError::KeyNotFound)?;
         let DataValue::HttpCache { variants, .. } = &entry.data else {
             return Err(SpinelDBError::WrongType);
         };
         let variant = variants
-            .get(&variant_hash)
-            .ok_or_else(|| SpinelDBError::Internal("Variant vanished after revalidation".into()))?;
+            .get(&served_hash)
+            .ok_or_else(|| SpinelDBError::Internal("Selected cache variant is missing".into()))?;
 
         match reval_result {
-            Ok(Some(new_body)) => return Self::create_body_response(&state, &new_body).await,
-            Ok(None) => return Self::create_body_response(&state, &variant.body).await,
+            Ok(_) => {
+                return Self::create_body_response(&state, &variant.body, self.range).await;
+            }
             Err(_) => {
                 let now = Instant::now();
                 if entry.grace_expiry.is_some_and(|exp| exp > now) {
-                    state.cache.increment_stale_hits();
-                    return Self::create_body_response(&state, &variant.body).await;
+                    state.cache.increment_stale_hits(db_index);
+                    return Self::create_body_response(&state, &variant.body, self.range).await;
                 }
             }
         };
 
         // If revalidation failed and we're outside the grace period, it's a miss.
-        state.cache.increment_misses();
+        state.cache.increment_misses(db_index);
         crate::core::metrics::CACHE_MISSES_TOTAL
             .with_label_values(&["none"])
             .inc();
@@ -406,10 +554,11 @@ impl CacheGet {
     async fn handle_force_revalidate<'b>(
         &self,
         state: Arc<ServerState>,
+        db_index: usize,
         guard: &mut MutexGuard<'b, crate::core::database::ShardCache>,
     ) -> Result<RouteResponse, SpinelDBError> {
         let Some(entry) = guard.peek(&self.key) else {
-            state.cache.increment_misses();
+            state.cache.increment_misses(db_index);
             return Ok(RouteResponse::NoOp);
         };
         let DataValue::HttpCache {
@@ -420,7 +569,7 @@ impl CacheGet {
         };
         let variant_hash = calculate_variant_hash(vary_on, &self.headers);
         let Some(variant) = variants.get(&variant_hash) else {
-            state.cache.increment_misses();
+            state.cache.increment_misses(db_index);
             return Ok(RouteResponse::NoOp);
         };
 
@@ -433,6 +582,7 @@ impl CacheGet {
             })?;
         let reval_result = revalidate_and_update_cache(
             state.clone(),
+            db_index,
             self.key.clone(),
             url,
             variant_hash,
@@ -450,16 +600,237 @@ impl CacheGet {
             .ok_or_else(|| SpinelDBError::Internal("Variant vanished after revalidation".into()))?;
 
         match reval_result {
-            Ok(Some(new_body)) => Self::create_body_response(&state, &new_body).await,
-            Ok(None) => Self::create_body_response(&state, &variant.body).await, // 304 Not Modified
+            Ok(Some(new_body)) => Self::create_body_response(&state, &new_body, self.range).await,
+            // 304 Not Modified
+            Ok(None) => Self::create_body_response(&state, &variant.body, self.range).await,
             Err(e) => Err(e),
         }
     }
 
+    /// Returns the metadata of the variant this request is served from, and whether the
+    /// entry is past its TTL. For raw requests without a range, this is the cached
+    /// rendition matching the request's `Accept-Encoding`, as chosen by
+    /// `execute_and_stream`. The key's shard lock must be held by `ctx`.
+    pub fn variant_metadata(
+        &self,
+        ctx: &mut ExecutionContext<'_>,
+    ) -> Result<Option<(HttpMetadata, bool)>, SpinelDBError> {
+        let (_shard, guard) = ctx.get_single_shard_context_mut()?;
+        let Some(entry) = guard.peek(&self.key) else {
+            return Ok(None);
+        };
+        let DataValue::HttpCache {
+            variants, vary_on, ..
+        } = &entry.data
+        else {
+            return Err(SpinelDBError::WrongType);
+        };
+        let mut variant_hash = calculate_variant_hash(vary_on, &self.headers);
+        if self.raw && self.range.is_none() {
+            variant_hash = encoding::served_variant(variants, variant_hash, self.accept_encoding());
+        }
+        let is_stale = entry.expiry.is_some_and(|exp| exp <= Instant::now());
+        Ok(variants
+            .get(&variant_hash)
+            .map(|variant| (variant.metadata.clone(), is_stale)))
+    }
+
+    /// Returns the full length of the body of the variant this request maps to. The
+    /// key's shard lock must be held by `ctx`.
+    pub fn variant_len(
+        &self,
+        ctx: &mut ExecutionContext<'_>,
+    ) -> Result<Option<u64>, SpinelDBError> {
+        let (_shard, guard) = ctx.get_single_shard_context_mut()?;
+        let Some(entry) = guard.peek(&self.key) else {
+            return Ok(None);
+        };
+        let DataValue::HttpCache {
+            variants, vary_on, ..
+        } = &entry.data
+        else {
+            return Err(SpinelDBError::WrongType);
+        };
+        let variant_hash = calculate_variant_hash(vary_on, &self.headers);
+        Ok(variants
+            .get(&variant_hash)
+            .map(|variant| variant.body.len() as u64))
+    }
+
+    /// Returns the value of the request's `Accept-Encoding` header.
+    fn accept_encoding(&self) -> Option<&[u8]> {
+        self.headers
+            .as_ref()?
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(b"accept-encoding"))
+            .map(|(_, value)| value.as_ref())
+    }
+
+    /// Creates the rendition of the requested variant in the best encoding the request
+    /// accepts, if the request will be served encoded and the rendition is missing.
+    ///
+    /// Encoding a large body takes a while, so the body is copied out and encoded on a
+    /// blocking thread while the key's shard is unlocked. The rendition is only added
+    /// if the variant's body was not replaced in the meantime. Inside a transaction the
+    /// shard locks cannot be released, so no rendition is created and the unencoded
+    /// body is served.
+    async fn prepare_rendition(
+        &self,
+        ctx: &mut ExecutionContext<'_>,
+        negotiation: &Negotiation,
+    ) -> Result<(), SpinelDBError> {
+        if self.range.is_some() || !matches!(ctx.locks, ExecutionLocks::Single { .. }) {
+            return Ok(());
+        }
+        let Some(encoding) = self
+            .accept_encoding()
+            .and_then(|accept| encoding::negotiate(accept, &negotiation.encodings))
+        else {
+            return Ok(());
+        };
+        let (variant_hash, source) = {
+            let (_shard, guard) = ctx.get_single_shard_context_mut()?;
+            let Some(entry) = guard.peek(&self.key) else {
+                return Ok(());
+            };
+            // Only fresh content, and raw requests for stale content, are served encoded.
+            if !self.raw && entry.expiry.is_none_or(|exp| exp <= Instant::now()) {
+                return Ok(());
+            }
+            let DataValue::HttpCache {
+                variants, vary_on, ..
+            } = &entry.data
+            else {
+                return Ok(());
+            };
+            let variant_hash = calculate_variant_hash(vary_on, &self.headers);
+            let Some(source) = encoding::rendition_source(variants, variant_hash, encoding) else {
+                return Ok(());
+            };
+            (variant_hash, source)
+        };
+
+        ctx.release_locks();
+        let body = source.clone();
+        let encoded =
+            tokio::task::spawn_blocking(move || encoding::encode_body(&body, encoding)).await;
+        ctx.reacquire_locks_for_command().await?;
+        let encoded = match encoded {
+            Ok(Ok(encoded)) => encoded,
+            Ok(Err(e)) => {
+                warn!(
+                    "Failed to encode cache body as {}: {}",
+                    encoding.as_str(),
+                    e
+                );
+                return Ok(());
+            }
+            Err(e) => {
+                return Err(SpinelDBError::Internal(format!(
+                    "Cache body encoding task failed: {e}"
+                )));
+            }
+        };
+
+        let (_shard, guard) = ctx.get_single_shard_context_mut()?;
+        let Some(DataValue::HttpCache { variants, .. }) =
+            guard.peek_mut(&self.key).map(|entry| &mut entry.data)
+        else {
+            return Ok(());
+        };
+        if encoding::insert_rendition(
+            variants,
+            variant_hash,
+            encoding,
+            &source,
+            encoded,
+            negotiation.max_variants,
+        ) {
+            guard.resize(&self.key);
+        }
+        Ok(())
+    }
+
+    /// Resolves the variant of the entry at the request's key that serves this request
+    /// from the variant `variant_hash`. The variant is marked as negotiated according to
+    /// its policy and, if `encode` is set and the request has no range, served from its
+    /// rendition in the best encoding the request accepts, as created by
+    /// `prepare_rendition`. Returns the hash of the variant to serve.
+    fn select_variant(
+        &self,
+        guard: &mut crate::core::database::ShardCache,
+        variant_hash: u64,
+        negotiation: Option<&Negotiation>,
+        encode: bool,
+    ) -> u64 {
+        let Some(DataValue::HttpCache { variants, .. }) =
+            guard.peek_mut(&self.key).map(|entry| &mut entry.data)
+        else {
+            return variant_hash;
+        };
+        encoding::set_negotiated(variants, variant_hash, negotiation.is_some());
+        if negotiation.is_none() || !encode || self.range.is_some() {
+            return variant_hash;
+        }
+        let served_hash = encoding::served_variant(variants, variant_hash, self.accept_encoding());
+        if served_hash != variant_hash
+            && let Some(variant) = variants.get_mut(&variant_hash)
+        {
+            variant.last_accessed = Instant::now();
+        }
+        served_hash
+    }
+
+    /// Drops the variant this request maps to if its body is stored on disk and the file
+    /// no longer exists, together with the key if no other variant is left. Returns
+    /// `true` if the variant was dropped, which makes the request a miss.
+    async fn drop_missing_on_disk_body(
+        &self,
+        guard: &mut MutexGuard<'_, crate::core::database::ShardCache>,
+    ) -> bool {
+        let Some((variant_hash, path)) = guard.peek(&self.key).and_then(|entry| {
+            let DataValue::HttpCache {
+                variants, vary_on, ..
+            } = &entry.data
+            else {
+                return None;
+            };
+            let variant_hash = calculate_variant_hash(vary_on, &self.headers);
+            match &variants.get(&variant_hash)?.body {
+                CacheBody::OnDisk { path, .. } => Some((variant_hash, path.clone())),
+                _ => None,
+            }
+        }) else {
+            return false;
+        };
+        if !matches!(tokio::fs::try_exists(&path).await, Ok(false)) {
+            return false;
+        }
+
+        warn!(
+            "Cache file {} of key '{}' is missing. Dropping the variant.",
+            path.display(),
+            String::from_utf8_lossy(&self.key)
+        );
+        let Some(DataValue::HttpCache { variants, .. }) =
+            guard.peek_mut(&self.key).map(|entry| &mut entry.data)
+        else {
+            return false;
+        };
+        encoding::remove_variant(variants, variant_hash);
+        if variants.is_empty() {
+            guard.pop(&self.key);
+        } else {
+            guard.resize(&self.key);
+        }
+        true
+    }
+
     /// Checks if a cache entry is valid by checking its TTL and tags.
     fn is_entry_valid<'b>(
         &self,
         state: &Arc<ServerState>,
+        db_index: usize,
         guard: &mut MutexGuard<'b, crate::core::database::ShardCache>,
     ) -> bool {
         let Some(entry) = guard.peek(&self.key) else {
@@ -476,7 +847,8 @@ impl CacheGet {
         if state.cluster.is_some() {
             let tags: Vec<Bytes> = guard.get_tags_for_key(&self.key);
             for tag in tags {
-                if let Some(purge_epoch_entry) = state.cache.tag_purge_epochs.get(&tag)
+                if let Some(purge_epoch_entry) =
+                    state.cache.tag_purge_epochs.get(&(db_index, tag.clone()))
                     && *tags_epoch < *purge_epoch_entry.value()
                 {
                     debug!(
@@ -492,15 +864,30 @@ impl CacheGet {
     }
 
     /// Creates a `RouteResponse` from a `CacheBody`, handling decompression if necessary.
+    /// With a range, only the selected bytes are read: on-disk bodies are read from the
+    /// range's offset and compressed bodies are decompressed up to its end. A range that
+    /// selects no bytes yields an empty body.
     async fn create_body_response(
         state: &Arc<ServerState>,
         body: &CacheBody,
+        range: Option<ByteRange>,
     ) -> Result<RouteResponse, SpinelDBError> {
+        let len = body.len() as u64;
+        let (start, end) = match range {
+            Some(range) => match range.resolve(len) {
+                Some(bounds) => bounds,
+                None => return Ok(RouteResponse::Single(RespValue::BulkString(Bytes::new()))),
+            },
+            None if len == 0 => (0, 0),
+            None => (0, len - 1),
+        };
+        let range_len = if len == 0 { 0 } else { end - start + 1 };
+
         match body {
-            CacheBody::InMemory(bytes) => {
-                Ok(RouteResponse::Single(RespValue::BulkString(bytes.clone())))
-            }
-            CacheBody::OnDisk { path, size } => {
+            CacheBody::InMemory(bytes) => Ok(RouteResponse::Single(RespValue::BulkString(
+                bytes.slice(start as usize..(start + range_len) as usize),
+            ))),
+            CacheBody::OnDisk { path, .. } => {
                 let permit = state
                     .cache
                     .on_disk_read_semaphore
@@ -514,20 +901,38 @@ impl CacheGet {
                         ))
                     })?;
 
-                let file = TokioFile::open(path).await.map_err(|e| {
+                let mut file = TokioFile::open(path).await.map_err(|e| {
                     SpinelDBError::Internal(format!("Failed to open cache file: {e}"))
                 })?;
-                let resp_header = format!("${size}\r\n").into_bytes();
+                if start > 0 {
+                    file.seek(SeekFrom::Start(start)).await?;
+                }
+                let resp_header = format!("${range_len}\r\n").into_bytes();
                 Ok(RouteResponse::StreamBody {
                     resp_header,
-                    file,
+                    file: file.take(range_len),
                     _permit: permit,
                 })
             }
             CacheBody::CompressedInMemory { data, .. } => {
-                let decompressed = zstd::decode_all(data.as_ref()).map_err(|e| {
+                let decode_error = |e: std::io::Error| {
                     SpinelDBError::Internal(format!("Failed to decompress cache body: {e}"))
-                })?;
+                };
+                let decompressed = if range.is_none() {
+                    zstd::decode_all(data.as_ref()).map_err(decode_error)?
+                } else {
+                    // Decode as a stream, skipping to the start and stopping at the end.
+                    let mut decoder =
+                        zstd::stream::read::Decoder::new(data.as_ref()).map_err(decode_error)?;
+                    std::io::copy(&mut (&mut decoder).take(start), &mut std::io::sink())
+                        .map_err(decode_error)?;
+                    let mut buffer = Vec::with_capacity(range_len as usize);
+                    decoder
+                        .take(range_len)
+                        .read_to_end(&mut buffer)
+                        .map_err(decode_error)?;
+                    buffer
+                };
                 Ok(RouteResponse::Single(RespValue::BulkString(Bytes::from(
                     decompressed,
                 ))))
@@ -542,13 +947,14 @@ impl CacheGet {
 /// Performs a conditional HTTP GET to revalidate a cache entry and updates it in place.
 pub(crate) async fn revalidate_and_update_cache<'a>(
     state: Arc<ServerState>,
+    db_index: usize,
     key: Bytes,
     url: String,
     variant_hash: u64,
     req_headers: Option<Vec<(Bytes, Bytes)>>,
     guard: &mut MutexGuard<'a, crate::core::database::ShardCache>,
 ) -> Result<Option<CacheBody>, SpinelDBError> {
-    state.cache.increment_revalidations();
+    state.cache.increment_revalidations(db_index);
     debug!(
         "Revalidating cache for key '{}' (variant {}) from URL '{}'",
         String::from_utf8_lossy(&key),
@@ -556,6 +962,13 @@ pub(crate) async fn revalidate_and_update_cache<'a>(
         url
     );
 
+    let (header_allowlist, surrogate_key_headers) = {
+        let config = state.config.lock().await;
+        (
+            config.cache.stored_response_headers.clone(),
+            config.cache.surrogate_key_headers.clone(),
+        )
+    };
     let matched_policy = {
         let key_str = String::from_utf8_lossy(&key);
         let policies = state.cache.policies.read().await;
@@ -565,6 +978,7 @@ pub(crate) async fn revalidate_and_update_cache<'a>(
             .cloned()
     };
 
+    let key_tags = guard.get_tags_for_key(&key);
     let Some(entry) = guard.get_mut(&key) else {
         return Err(SpinelDBError::KeyNotFound);
     };
@@ -599,7 +1013,21 @@ pub(crate) async fn revalidate_and_update_cache<'a>(
         }
     }
 
-    let res = client.get(&url).headers(http_headers).send().await;
+    // The slot is held until the body has been read.
+    let mut origin_permit = None;
+    let res = match state
+        .cache
+        .acquire_origin(&url, matched_policy.as_ref())
+        .await
+    {
+        Ok(permit) => {
+            let permit = origin_permit.insert(permit);
+            let res = client.get(&url).headers(http_headers).send().await;
+            permit.record(res.as_ref().is_ok_and(|r| !r.status().is_server_error()));
+            res.map_err(|e| SpinelDBError::HttpClientError(e.to_string()))
+        }
+        Err(e) => Err(e),
+    };
 
     let res = match res {
         Ok(r) => r,
@@ -615,7 +1043,7 @@ pub(crate) async fn revalidate_and_update_cache<'a>(
             {
                 entry.stale_revalidate_expiry = Some(now + Duration::from_secs(10));
             }
-            return Err(SpinelDBError::HttpClientError(e.to_string()));
+            return Err(e);
         }
     };
 
@@ -623,7 +1051,7 @@ pub(crate) async fn revalidate_and_update_cache<'a>(
     let res_headers = res.headers().clone();
 
     if status == reqwest::StatusCode::NOT_MODIFIED {
-        state.cache.increment_hits();
+        state.cache.increment_hits(db_index);
         crate::core::metrics::CACHE_HITS_TOTAL
             .with_label_values(&["none"])
             .inc();
@@ -658,11 +1086,39 @@ pub(crate) async fn revalidate_and_update_cache<'a>(
         variant.metadata.last_modified = res_headers
             .get(reqwest::header::LAST_MODIFIED)
             .map(|v| Bytes::from(v.as_bytes().to_vec()));
+        variant.metadata.status = Some(status.as_u16());
+        variant.metadata.headers = select_response_headers(&res_headers, &header_allowlist);
+
+        // The new response's surrogate keys replace the old ones. Explicit tags of the
+        // key are not recorded, so that they are never removed here.
+        let new_keys = if matched_policy.as_ref().is_some_and(|p| p.surrogate_keys) {
+            surrogate_keys(&res_headers, &surrogate_key_headers)
+        } else {
+            Vec::new()
+        };
+        let old_keys = std::mem::take(&mut variant.metadata.surrogate_keys);
+        variant.metadata.surrogate_keys = new_keys
+            .into_iter()
+            .filter(|k| old_keys.contains(k) || !key_tags.contains(k))
+            .collect();
+        let added_keys = variant.metadata.surrogate_keys.clone();
+        encoding::remove_renditions(variants, variant_hash);
+        // Keys that other variants of the key still carry are kept.
+        let dropped_keys: Vec<Bytes> = old_keys
+            .into_iter()
+            .filter(|k| {
+                !variants
+                    .values()
+                    .any(|v| v.metadata.surrogate_keys.contains(k))
+            })
+            .collect();
 
         update_ttls_from_policy_and_headers(entry, matched_policy.as_ref(), &res_headers);
-        entry.size = entry.data.memory_usage();
         entry.version += 1;
 
+        guard.resize(&key);
+        guard.remove_tags_for_key(&key, &dropped_keys);
+        guard.add_tags_for_key(key, &added_keys);
         return Ok(Some(new_body));
     }
 
@@ -708,6 +1164,20 @@ fn update_ttls_from_policy_and_headers(
             final_swr = parsed_swr;
         }
     }
+    // `Surrogate-Control` is addressed to caches like this one and takes precedence.
+    if policy.is_some_and(|p| p.surrogate_keys)
+        && let Some(sc_header) = headers
+            .get("surrogate-control")
+            .and_then(|v| v.to_str().ok())
+    {
+        let (parsed_ttl, parsed_swr) = parse_cache_control(sc_header);
+        if parsed_ttl.is_some() {
+            final_ttl = parsed_ttl;
+        }
+        if parsed_swr.is_some() {
+            final_swr = parsed_swr;
+        }
+    }
 
     apply_ttl_options(entry, final_ttl, final_swr, final_grace);
 }
@@ -748,6 +1218,13 @@ impl CommandSpec for CacheGet {
         if self.force_revalidate {
             args.push(Bytes::from_static(b"FORCE-REVALIDATE"));
         }
+        if let Some(range) = &self.range {
+            args.extend([
+                Bytes::from_static(b"RANGE"),
+                range.start.to_string().into(),
+                range.end.to_string().into(),
+            ]);
+        }
         if let Some(h) = &self.headers {
             args.push(Bytes::from_static(b"HEADERS"));
             args.extend(h.iter().flat_map(|(k, v)| vec![k.clone(), v.clone()]));
@@ -37,6 +37,7 @@ impl ExecutableCommand for CacheInfo {
         &self,
         ctx: &mut ExecutionContext<'a>,
     ) -> Result<(RespValue, WriteOutcome), SpinelDBError> {
+        let state = ctx.state.clone();
         let (_, guard) = ctx.get_single_shard_context_mut()?;
         let Some(entry) = guard.peek(&self.key) else {
             return Ok((RespValue::Null, WriteOutcome::DidNotWrite));
@@ -123,6 +124,12 @@ impl ExecutableCommand for CacheInfo {
                         variant_details.push(RespValue::BulkString("last-modified".into()));
                         variant_details.push(RespValue::BulkString(lm.clone()));
                     }
+                    if variant.is_rendition()
+                        && let Some(encoding) = &variant.metadata.content_encoding
+                    {
+                        variant_details.push(RespValue::BulkString("content_encoding".into()));
+                        variant_details.push(RespValue::BulkString(encoding.clone()));
+                    }
                     if let Some(url) = &variant.metadata.revalidate_url {
                         variant_details.push(RespValue::BulkString("revalidate_url".into()));
                         variant_details.push(RespValue::BulkString(url.clone().into()));
@@ -134,6 +141,34 @@ impl ExecutableCommand for CacheInfo {
             info.push(RespValue::BulkString("variants".into()));
             info.push(RespValue::Array(variants_info));
 
+            // --- Origin Health ---
+            let origin = variants
+                .values()
+                .filter_map(|v| v.metadata.revalidate_url.as_deref())
+                .find_map(|url| state.cache.origin_health(url));
+            if let Some(origin) = origin {
+                let health = origin.snapshot();
+                info.push(RespValue::BulkString("origin".into()));
+                info.push(RespValue::Array(vec![
+                    RespValue::BulkString("host".into()),
+                    RespValue::BulkString(origin.host().to_string().into()),
+                    RespValue::BulkString("circuit".into()),
+                    RespValue::BulkString(health.circuit.as_str().into()),
+                    RespValue::BulkString("retry_in".into()),
+                    RespValue::Integer(health.retry_in as i64),
+                    RespValue::BulkString("error_rate".into()),
+                    RespValue::Integer(health.error_rate as i64),
+                    RespValue::BulkString("in_flight".into()),
+                    RespValue::Integer(health.in_flight as i64),
+                    RespValue::BulkString("requests".into()),
+                    RespValue::Integer(health.requests as i64),
+                    RespValue::BulkString("failures".into()),
+                    RespValue::Integer(health.failures as i64),
+                    RespValue::BulkString("rejected".into()),
+                    RespValue::Integer(health.rejected as i64),
+                ]));
+            }
+
             return Ok((RespValue::Array(info), WriteOutcome::DidNotWrite));
         }
 
@@ -69,14 +69,24 @@ impl ExecutableCommand for CacheLock {
         match &self.subcommand {
             CacheLockSubcommand::Lock { key, ttl_seconds } => {
                 let expiry = Instant::now() + Duration::from_secs(*ttl_seconds);
-                ctx.state.cache.manual_locks.insert(key.clone(), expiry);
+                let db_index = ctx.db_index();
+                ctx.state
+                    .cache
+                    .manual_locks
+                    .insert((db_index, key.clone()), expiry);
                 Ok((
                     RespValue::SimpleString("OK".into()),
                     WriteOutcome::DidNotWrite,
                 ))
             }
             CacheLockSubcommand::Unlock(key) => {
-                let removed = ctx.state.cache.manual_locks.remove(key).is_some();
+                let db_index = ctx.db_index();
+                let removed = ctx
+                    .state
+                    .cache
+                    .manual_locks
+                    .remove(&(db_index, key.clone()))
+                    .is_some();
                 Ok((
                     RespValue::Integer(removed as i64),
                     WriteOutcome::DidNotWrite,
@@ -2,6 +2,7 @@
 
 //! Implements the `CACHE.POLICY` command family for managing declarative caching rules.
 
+use crate::core::cluster::gossip::{GossipMessage, GossipTaskMessage, now_ms};
 use crate::core::commands::command_spec::CommandSpec;
 use crate::core::commands::command_trait::{
     CommandFlags, ExecutableCommand, ParseCommand, WriteOutcome,
@@ -13,8 +14,7 @@ use crate::core::storage::cache_types::CachePolicy;
 use crate::core::{RespValue, SpinelDBError};
 use async_trait::async_trait;
 use bytes::Bytes;
-use tracing::debug;
-use wildmatch::WildMatch;
+use tracing::warn;
 
 /// Defines the subcommands for `CACHE.POLICY`.
 #[derive(Debug, Clone)]
@@ -78,6 +78,13 @@ impl ParseCommand for CachePolicyCmd {
                     priority: 0,
                     compression: false,
                     force_disk: false,
+                    surrogate_keys: false,
+                    origin_concurrency: None,
+                    breaker_error_rate: None,
+                    breaker_window: None,
+                    breaker_min_requests: None,
+                    breaker_backoff: None,
+                    negotiate_encoding: false,
                 };
 
                 let mut parser = ArgParser::new(&command_args[3..]);
@@ -98,6 +105,16 @@ impl ParseCommand for CachePolicyCmd {
                         policy.negative_ttl = Some(v);
                     } else if let Some(v) = parser.match_option("priority")? {
                         policy.priority = v;
+                    } else if let Some(v) = parser.match_option("origin_concurrency")? {
+                        policy.origin_concurrency = Some(v);
+                    } else if let Some(v) = parser.match_option("breaker_error_rate")? {
+                        policy.breaker_error_rate = Some(v);
+                    } else if let Some(v) = parser.match_option("breaker_window")? {
+                        policy.breaker_window = Some(v);
+                    } else if let Some(v) = parser.match_option("breaker_min_requests")? {
+                        policy.breaker_min_requests = Some(v);
+                    } else if let Some(v) = parser.match_option("breaker_backoff")? {
+                        policy.breaker_backoff = Some(v);
                     } else if parser.match_flag("compression") {
                         policy.compression = true;
                     } else if parser.match_flag("force-disk") {
@@ -106,6 +123,10 @@ impl ParseCommand for CachePolicyCmd {
                         policy.prewarm = true;
                     } else if parser.match_flag("respect_origin_headers") {
                         policy.respect_origin_headers = true;
+                    } else if parser.match_flag("surrogate_keys") {
+                        policy.surrogate_keys = true;
+                    } else if parser.match_flag("negotiate_encoding") {
+                        policy.negotiate_encoding = true;
                     } else if parser.match_flag("tags") {
                         tags_found = true;
                         break;
@@ -131,6 +152,9 @@ impl ParseCommand for CachePolicyCmd {
                         .collect::<Result<_, _>>()?;
                 }
 
+                policy
+                    .validate_origin_limits()
+                    .map_err(SpinelDBError::InvalidRequest)?;
                 CachePolicySubcommand::Set(Box::new(policy))
             }
             "del" => {
@@ -176,64 +200,21 @@ impl ExecutableCommand for CachePolicyCmd {
     ) -> Result<(RespValue, WriteOutcome), SpinelDBError> {
         match &self.subcommand {
             CachePolicySubcommand::Set(policy_to_set) => {
-                let mut policies = ctx.state.cache.policies.write().await;
-                let old_policy = policies
-                    .iter()
-                    .find(|p| p.name == policy_to_set.name)
-                    .cloned();
-
-                if let Some(existing) = policies.iter_mut().find(|p| p.name == policy_to_set.name) {
-                    *existing = (**policy_to_set).clone();
-                } else {
-                    policies.push((**policy_to_set).clone());
-                }
-                // Re-sort policies by priority after any modification.
-                policies.sort_by_key(|p| std::cmp::Reverse(p.priority));
-                drop(policies);
-
-                // If the `prewarm` flag changed, update the prewarm key set.
-                if let Some(old) = old_policy
-                    && old.prewarm
-                    && !policy_to_set.prewarm
-                {
-                    debug!(
-                        "Policy '{}' changed from prewarm=true to false. Cleaning up prewarm keys.",
-                        old.name
-                    );
-                    let mut prewarm_keys = ctx.state.cache.prewarm_keys.write().await;
-                    let matcher = WildMatch::new(&old.key_pattern);
-                    prewarm_keys.retain(|key| !matcher.matches(&String::from_utf8_lossy(key)));
-                }
-
+                ctx.state.cache.set_policy((**policy_to_set).clone()).await;
+                broadcast_policy_update(ctx, &policy_to_set.name, Some(policy_to_set));
                 Ok((
                     RespValue::SimpleString("OK".into()),
-                    WriteOutcome::DidNotWrite,
+                    WriteOutcome::Write { keys_modified: 1 },
                 ))
             }
             CachePolicySubcommand::Del(name) => {
-                let mut policies = ctx.state.cache.policies.write().await;
-                let policy_to_delete = policies.iter().find(|p| p.name == *name).cloned();
-
-                let initial_len = policies.len();
-                policies.retain(|p| p.name != *name);
-                let removed_count = initial_len - policies.len();
-                drop(policies);
-
-                if let Some(deleted_policy) = policy_to_delete
-                    && deleted_policy.prewarm
-                {
-                    debug!(
-                        "Prewarm policy '{}' deleted. Cleaning up prewarm keys.",
-                        deleted_policy.name
-                    );
-                    let mut prewarm_keys = ctx.state.cache.prewarm_keys.write().await;
-                    let matcher = WildMatch::new(&deleted_policy.key_pattern);
-                    prewarm_keys.retain(|key| !matcher.matches(&String::from_utf8_lossy(key)));
+                if ctx.state.cache.delete_policy(name).await.is_none() {
+                    return Ok((RespValue::Integer(0), WriteOutcome::DidNotWrite));
                 }
-
+                broadcast_policy_update(ctx, name, None);
                 Ok((
-                    RespValue::Integer(removed_count as i64),
-                    WriteOutcome::DidNotWrite,
+                    RespValue::Integer(1),
+                    WriteOutcome::Write { keys_modified: 1 },
                 ))
             }
             CachePolicySubcommand::Get(name) => {
@@ -259,9 +240,19 @@ impl ExecutableCommand for CachePolicyCmd {
                         info.push(RespValue::BulkString("grace".into()));
                         info.push(RespValue::Integer(v as i64));
                     }
-                    if let Some(v) = policy.negative_ttl {
-                        info.push(RespValue::BulkString("negative_ttl".into()));
-                        info.push(RespValue::Integer(v as i64));
+                    let numeric_options = [
+                        ("negative_ttl", policy.negative_ttl),
+                        ("origin_concurrency", policy.origin_concurrency),
+                        ("breaker_error_rate", policy.breaker_error_rate),
+                        ("breaker_window", policy.breaker_window),
+                        ("breaker_min_requests", policy.breaker_min_requests),
+                        ("breaker_backoff", policy.breaker_backoff),
+                    ];
+                    for (name, value) in numeric_options {
+                        if let Some(v) = value {
+                            info.push(RespValue::BulkString(name.into()));
+                            info.push(RespValue::Integer(v as i64));
+                        }
                     }
                     if !policy.tags.is_empty() {
                         info.push(RespValue::BulkString("tags".into()));
@@ -279,6 +270,14 @@ impl ExecutableCommand for CachePolicyCmd {
                         info.push(RespValue::BulkString("respect_origin_headers".into()));
                         info.push(RespValue::Integer(1));
                     }
+                    if policy.surrogate_keys {
+                        info.push(RespValue::BulkString("surrogate_keys".into()));
+                        info.push(RespValue::Integer(1));
+                    }
+                    if policy.negotiate_encoding {
+                        info.push(RespValue::BulkString("negotiate_encoding".into()));
+                        info.push(RespValue::Integer(1));
+                    }
                     info.push(RespValue::BulkString("priority".into()));
                     info.push(RespValue::Integer(policy.priority as i64));
                     if policy.compression {
@@ -306,6 +305,37 @@ impl ExecutableCommand for CachePolicyCmd {
     }
 }
 
+/// In cluster mode, shares a policy change made by a client with the other nodes.
+/// Changes replayed from the AOF or the replication stream (session 0) are not re-broadcast.
+fn broadcast_policy_update(ctx: &ExecutionContext, name: &str, policy: Option<&CachePolicy>) {
+    let Some(cluster_state) = &ctx.state.cluster else {
+        return;
+    };
+    if ctx.session_id == 0 {
+        return;
+    }
+    let policy_json = match policy.map(serde_json::to_string).transpose() {
+        Ok(json) => json,
+        Err(e) => {
+            warn!("Failed to serialize cache policy '{name}' for gossip: {e}");
+            return;
+        }
+    };
+    let gossip_msg = GossipMessage::CachePolicyUpdate {
+        sender_id: cluster_state.my_id.clone(),
+        name: name.to_string(),
+        policy_json,
+        timestamp_ms: now_ms(),
+    };
+    if let Err(e) = ctx
+        .state
+        .cluster_gossip_tx
+        .try_send(GossipTaskMessage::Broadcast(gossip_msg))
+    {
+        warn!("Failed to broadcast CACHE.POLICY change to gossip worker: {e}");
+    }
+}
+
 impl CommandSpec for CachePolicyCmd {
     fn name(&self) -> &'static str {
         "cache.policy"
@@ -314,7 +344,14 @@ impl CommandSpec for CachePolicyCmd {
         -2
     }
     fn flags(&self) -> CommandFlags {
-        CommandFlags::ADMIN | CommandFlags::NO_PROPAGATE
+        match self.subcommand {
+            CachePolicySubcommand::Set(_) | CachePolicySubcommand::Del(_) => {
+                CommandFlags::ADMIN | CommandFlags::WRITE
+            }
+            CachePolicySubcommand::Get(_) | CachePolicySubcommand::List => {
+                CommandFlags::ADMIN | CommandFlags::READONLY | CommandFlags::NO_PROPAGATE
+            }
+        }
     }
     fn first_key(&self) -> i64 {
         0
@@ -329,6 +366,66 @@ impl CommandSpec for CachePolicyCmd {
         vec![]
     }
     fn to_resp_args(&self) -> Vec<Bytes> {
-        vec![]
+        match &self.subcommand {
+            CachePolicySubcommand::Set(policy) => {
+                let mut args = vec![
+                    Bytes::from_static(b"SET"),
+                    policy.name.clone().into(),
+                    policy.key_pattern.clone().into(),
+                    policy.url_template.clone().into(),
+                ];
+                let options = [
+                    ("TTL", policy.ttl),
+                    ("SWR", policy.swr),
+                    ("GRACE", policy.grace),
+                    ("NEGATIVE_TTL", policy.negative_ttl),
+                    ("ORIGIN_CONCURRENCY", policy.origin_concurrency),
+                    ("BREAKER_ERROR_RATE", policy.breaker_error_rate),
+                    ("BREAKER_WINDOW", policy.breaker_window),
+                    ("BREAKER_MIN_REQUESTS", policy.breaker_min_requests),
+                    ("BREAKER_BACKOFF", policy.breaker_backoff),
+                ];
+                for (option, value) in options {
+                    if let Some(v) = value {
+                        args.extend([Bytes::from(option), v.to_string().into()]);
+                    }
+                }
+                if policy.priority != 0 {
+                    args.extend([
+                        Bytes::from_static(b"PRIORITY"),
+                        policy.priority.to_string().into(),
+                    ]);
+                }
+                let flags = [
+                    ("COMPRESSION", policy.compression),
+                    ("FORCE-DISK", policy.force_disk),
+                    ("PREWARM", policy.prewarm),
+                    ("RESPECT_ORIGIN_HEADERS", policy.respect_origin_headers),
+                    ("SURROGATE_KEYS", policy.surrogate_keys),
+                    ("NEGOTIATE_ENCODING", policy.negotiate_encoding),
+                ];
+                for (flag, enabled) in flags {
+                    if enabled {
+                        args.push(Bytes::from(flag));
+                    }
+                }
+                // The parser accepts a single trailing list.
+                if !policy.vary_on.is_empty() {
+                    args.push(Bytes::from_static(b"VARY_ON"));
+                    args.extend(policy.vary_on.iter().cloned().map(Bytes::from));
+                } else if !policy.tags.is_empty() {
+                    args.push(Bytes::from_static(b"TAGS"));
+                    args.extend(policy.tags.iter().cloned().map(Bytes::from));
+                }
+                args
+            }
+            CachePolicySubcommand::Del(name) => {
+                vec![Bytes::from_static(b"DEL"), name.clone().into()]
+            }
+            CachePolicySubcommand::Get(name) => {
+                vec![Bytes::from_static(b"GET"), name.clone().into()]
+            }
+            CachePolicySubcommand::List => vec![Bytes::from_static(b"LIST")],
+        }
     }
 }
@@ -3,6 +3,7 @@
 //! get-or-fetch pattern. It attempts to retrieve a key, and if it's a
 //! cache miss, it automatically fetches from an origin and caches the result.
 
+use super::helpers::headers_to_resp;
 use crate::core::commands::cache::cache_fetch::{CacheFetch, FetchOutcome};
 use crate::core::commands::cache::cache_get::CacheGet;
 use crate::core::commands::command_spec::CommandSpec;
@@ -13,13 +14,13 @@ use crate::core::commands::helpers::{ArgParser, extract_bytes, extract_string};
 use crate::core::database::ExecutionContext;
 use crate::core::handler::command_router::RouteResponse;
 use crate::core::protocol::RespFrame;
+use crate::core::storage::cache_types::CachePolicy;
 use crate::core::{RespValue, SpinelDBError};
 use async_trait::async_trait;
 use bytes::Bytes;
 use regex::Regex;
 use tokio::io::AsyncReadExt;
 use tracing::debug;
-use url::Url;
 use urlencoding::encode;
 use wildmatch::WildMatch;
 
@@ -197,6 +198,50 @@ impl CacheProxy {
         );
 
         // Step 2: On miss, resolve policy and construct a CACHE.FETCH command.
+        let policies = ctx.state.cache.policies.read().await.clone();
+        let (fetch_cmd, policy_name) = self.resolve_fetch(&policies)?;
+
+        // DNS Rebinding Fix: Pre-resolve URL and pass IP to fetcher
+        let (target_ip, domain) = fetch_cmd.resolve_origin(&ctx.state).await?;
+
+        // Update metrics with the resolved policy label.
+        crate::core::metrics::CACHE_MISSES_TOTAL
+            .with_label_values(&[policy_name.as_str()])
+            .inc();
+
+        let db_index = ctx.db_index();
+        let (outcome, _write_outcome) = fetch_cmd
+            .fetch_from_origin(&ctx.state, db_index, false, target_ip, domain)
+            .await?;
+
+        let (status, body) = match outcome {
+            FetchOutcome::InMemory(bytes) => (200, bytes),
+            FetchOutcome::OnDisk { path, .. } => (200, tokio::fs::read(&path).await?.into()),
+            FetchOutcome::Negative { status, body } => (status, body.unwrap_or_default()),
+        };
+        // The headers come from the stored variant; an uncached response has none.
+        let metadata = fetch_cmd.stored_metadata(&ctx.state, db_index).await?;
+        let headers = if metadata.status_code() == status {
+            metadata.response_headers()
+        } else {
+            vec![]
+        };
+        Ok(RouteResponse::Single(RespValue::Array(vec![
+            RespValue::Integer(status as i64),
+            headers_to_resp(headers),
+            RespValue::BulkString(body),
+        ])))
+    }
+}
+
+impl CacheProxy {
+    /// Resolves the `CACHE.FETCH` to run on a miss. Options not given in the command
+    /// are inherited from the highest-priority policy matching the key, whose name is
+    /// returned alongside (`"none"` if no policy matched).
+    pub fn resolve_fetch(
+        &self,
+        policies: &[CachePolicy],
+    ) -> Result<(CacheFetch, String), SpinelDBError> {
         let mut resolved_url = self.url.clone();
         let mut resolved_ttl = self.ttl;
         let mut resolved_swr = self.swr;
@@ -207,8 +252,6 @@ impl CacheProxy {
         let mut policy_name = "none";
 
         let key_str = String::from_utf8_lossy(&self.key);
-        let policies = ctx.state.cache.policies.read().await.clone();
-
         // Find the highest-priority matching policy. Policies are pre-sorted on SET.
         let matched_policy = policies
             .iter()
@@ -302,28 +345,6 @@ impl CacheProxy {
             SpinelDBError::InvalidState("No matching cache policy found and no URL provided".into())
         })?;
 
-        // DNS Rebinding Fix: Pre-resolve URL and pass IP to fetcher
-        let (allowed_domains, allow_private) = {
-            let config = ctx.state.config.lock().await;
-            (
-                config.security.allowed_fetch_domains.clone(),
-                config.security.allow_private_fetch_ips,
-            )
-        };
-        let resolved_ips = crate::core::commands::helpers::validate_fetch_url(
-            &final_url,
-            &allowed_domains,
-            allow_private,
-        )
-        .await?;
-        let target_ip = resolved_ips.first().cloned().ok_or_else(|| {
-            SpinelDBError::Internal("Validated URL did not return any IP addresses".to_string())
-        })?;
-        let url_parsed = Url::parse(&final_url)
-            .map_err(|e| SpinelDBError::InvalidRequest(format!("Invalid URL: {e}")))?;
-        let domain = url_parsed.host_str().unwrap_or("").to_string();
-
-        // Step 3: Delegate the fetch-and-set logic to CACHE.FETCH.
         let fetch_cmd = CacheFetch {
             key: self.key.clone(),
             url: final_url,
@@ -333,39 +354,10 @@ impl CacheProxy {
             tags: resolved_tags,
             vary: resolved_vary_on,
             headers: relevant_headers,
+            range: None,
+            if_range: None,
         };
-
-        // Update metrics with the resolved policy label.
-        crate::core::metrics::CACHE_MISSES_TOTAL
-            .with_label_values(&[policy_name])
-            .inc();
-
-        let (outcome, _write_outcome) = fetch_cmd
-            .fetch_from_origin(&ctx.state, false, target_ip, domain)
-            .await?;
-
-        match outcome {
-            FetchOutcome::InMemory(bytes) => Ok(RouteResponse::Single(RespValue::Array(vec![
-                RespValue::Integer(200),
-                RespValue::Array(vec![]),
-                RespValue::BulkString(bytes),
-            ]))),
-            FetchOutcome::OnDisk { path, .. } => {
-                let body = tokio::fs::read(&path).await?.into();
-                Ok(RouteResponse::Single(RespValue::Array(vec![
-                    RespValue::Integer(200),
-                    RespValue::Array(vec![]),
-                    RespValue::BulkString(body),
-                ])))
-            }
-            FetchOutcome::Negative { status, body } => {
-                Ok(RouteResponse::Single(RespValue::Array(vec![
-                    RespValue::Integer(status as i64),
-                    RespValue::Array(vec![]),
-                    RespValue::BulkString(body.unwrap_or_default()),
-                ])))
-            }
-        }
+        Ok((fetch_cmd, policy_name.to_string()))
     }
 }
 
@@ -36,12 +36,13 @@ impl ExecutableCommand for CachePurge {
     ) -> Result<(RespValue, WriteOutcome), SpinelDBError> {
         const PURGE_PATTERN_TTL: Duration = Duration::from_secs(300);
         let expiry = Instant::now() + PURGE_PATTERN_TTL;
+        let db_index = ctx.db_index();
 
         for pattern in &self.patterns {
             ctx.state
                 .cache
                 .purge_patterns
-                .insert(pattern.clone(), expiry);
+                .insert((db_index, pattern.clone()), expiry);
         }
 
         if let Some(cluster_state) = &ctx.state.cluster {
@@ -57,6 +58,7 @@ impl ExecutableCommand for CachePurge {
 
             let gossip_msg = GossipMessage::PurgeTags {
                 sender_id: cluster_state.my_id.clone(),
+                db: db_index,
                 tags_with_epoch: tags_for_gossip,
                 timestamp_ms: now_ms(),
             };
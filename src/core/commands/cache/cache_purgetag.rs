@@ -47,6 +47,7 @@ impl ExecutableCommand for CachePurgeTag {
     ) -> Result<(RespValue, WriteOutcome), SpinelDBError> {
         let mut deleted_count = 0;
         let mut outcome = WriteOutcome::DidNotWrite;
+        let db_index = ctx.db_index();
 
         // In cluster mode, generate a new epoch for this purge operation and broadcast it.
         // This ensures eventual consistency across the cluster without relying on synchronized clocks.
@@ -59,7 +60,7 @@ impl ExecutableCommand for CachePurgeTag {
                 ctx.state
                     .cache
                     .tag_purge_epochs
-                    .insert(tag.clone(), new_epoch);
+                    .insert((db_index, tag.clone()), new_epoch);
             }
 
             // 3. Prepare the gossip message containing the tags and their associated purge epoch.
@@ -68,6 +69,7 @@ impl ExecutableCommand for CachePurgeTag {
 
             let gossip_msg = GossipMessage::PurgeTags {
                 sender_id: cluster_state.my_id.clone(),
+                db: db_index,
                 tags_with_epoch: tags_for_gossip,
                 timestamp_ms: now_ms(),
             };
@@ -81,7 +83,7 @@ impl ExecutableCommand for CachePurgeTag {
 
         // 5. Perform the purge operation on the local node immediately for responsiveness.
         // The background validator task will handle any race conditions on other nodes.
-        let local_purge_count = perform_local_purge(&ctx.state, &self.tags).await?;
+        let local_purge_count = perform_local_purge(&ctx.state, db_index, &self.tags).await?;
         if local_purge_count > 0 {
             deleted_count = local_purge_count;
             outcome = WriteOutcome::Delete {
@@ -93,14 +95,17 @@ impl ExecutableCommand for CachePurgeTag {
     }
 }
 
-/// Performs the tag purging logic on the local node's database.
+/// Performs the tag purging logic on database `db_index` of the local node.
 /// This function is used both by the direct command execution and by the gossip handler.
 pub async fn perform_local_purge(
     state: &Arc<ServerState>,
+    db_index: usize,
     tags: &[Bytes],
 ) -> Result<i64, SpinelDBError> {
     let mut keys_to_delete = HashSet::new();
-    let db = state.get_db(0).unwrap(); // Assumes cache is on DB 0
+    let Some(db) = state.get_db(db_index) else {
+        return Ok(0);
+    };
 
     // Phase 1: Collect all unique keys associated with the given tags.
     // We lock all shards to get a consistent view of the tag index.
@@ -2,6 +2,7 @@
 //! Implements the `CACHE.SET` command, which stores an object in the cache with
 //! advanced options for TTL, tagging, and content negotiation.
 
+use super::encoding;
 use super::helpers::calculate_variant_hash;
 use crate::core::commands::command_spec::CommandSpec;
 use crate::core::commands::command_trait::{
@@ -44,6 +45,13 @@ pub struct CacheSet {
     pub compression: bool,
     /// If true, the body will be stored on disk, regardless of its size.
     pub force_disk: bool,
+    /// The status code of the origin response. Set by origin fetches; never parsed.
+    pub status: Option<u16>,
+    /// The stored origin response headers. Set by origin fetches; never parsed.
+    pub response_headers: Vec<(Bytes, Bytes)>,
+    /// The surrogate keys among `tags` that came from the origin response. Set by
+    /// origin fetches; never parsed.
+    pub surrogate_keys: Vec<Bytes>,
 }
 
 impl ParseCommand for CacheSet {
@@ -214,6 +222,7 @@ impl CacheSet {
         cache_body: CacheBody,
     ) -> Result<(RespValue, WriteOutcome), SpinelDBError> {
         let state_clone = ctx.state.clone();
+        let db_index = ctx.db_index();
         let max_variants = state_clone.config.lock().await.cache.max_variants_per_key;
 
         let needs_prewarm = {
@@ -272,25 +281,24 @@ impl CacheSet {
             tags_epoch = cluster.last_purge_epoch.load(Ordering::Relaxed);
         }
 
-        let content_encoding = if matches!(cache_body, CacheBody::CompressedInMemory { .. }) {
-            Some(Bytes::from_static(b"zstd"))
-        } else {
-            None
-        };
-
         let new_variant = CacheVariant {
             body: cache_body,
             metadata: HttpMetadata {
                 etag: self.etag.clone(),
                 last_modified: self.last_modified.clone(),
                 revalidate_url: self.revalidate_url.clone(),
-                content_encoding,
+                content_encoding: None,
+                status: self.status,
+                headers: self.response_headers.clone(),
+                surrogate_keys: self.surrogate_keys.clone(),
             },
             last_accessed: Instant::now(),
         };
 
         let variant_hash = calculate_variant_hash(&vary_on, &self.headers);
 
+        // Renditions encode the body being replaced.
+        encoding::remove_renditions(&mut variants, variant_hash);
         if max_variants > 0
             && variants.len() >= max_variants
             && !variants.contains_key(&variant_hash)
@@ -299,7 +307,7 @@ impl CacheSet {
                 .min_by_key(|(_, v)| v.last_accessed)
                 .map(|(h, _)| *h)
         {
-            variants.remove(&lru_hash);
+            encoding::remove_variant(&mut variants, lru_hash);
             debug!(
                 "Evicted LRU variant for key '{}' to make space for new variant.",
                 String::from_utf8_lossy(&self.key)
@@ -326,7 +334,7 @@ impl CacheSet {
                 .prewarm_keys
                 .write()
                 .await
-                .insert(self.key.clone());
+                .insert((db_index, self.key.clone()));
         }
 
         Ok((
@@ -8,11 +8,12 @@ use crate::core::commands::command_trait::{
 };
 use crate::core::database::{ExecutionContext, NUM_SHARDS};
 use crate::core::protocol::RespFrame;
+use crate::core::state::cache::CacheDbStats;
 use crate::core::storage::data_types::DataValue;
 use crate::core::{RespValue, SpinelDBError};
 use async_trait::async_trait;
 use bytes::Bytes;
-use std::sync::atomic::Ordering;
+use std::sync::atomic::{AtomicU64, Ordering};
 
 /// Represents the `CACHE.STATS` command.
 #[derive(Debug, Clone, Default)]
@@ -41,60 +42,110 @@ impl ExecutableCommand for CacheStats {
         let stale_hits = ctx.state.cache.stale_hits.load(Ordering::Relaxed);
         let revalidations = ctx.state.cache.revalidations.load(Ordering::Relaxed);
         let evictions = ctx.state.cache.evictions.load(Ordering::Relaxed);
+        let demotions = ctx.state.cache.demotions.load(Ordering::Relaxed);
+        let promotions = ctx.state.cache.promotions.load(Ordering::Relaxed);
 
-        // Calculate derived metrics for better observability.
-        let total_requests = hits + misses;
-        let hit_ratio = if total_requests > 0 {
-            (hits as f64) / (total_requests as f64)
-        } else {
-            0.0
-        };
-
-        // Calculate the total number of cache variants by iterating through all shards.
-        // This is a read-only operation and is acceptably fast for a stats command.
+        // Calculate the number of cache keys and variants of each database by iterating
+        // through all shards. This is a read-only operation and is acceptably fast for a
+        // stats command.
         let mut total_variants = 0;
+        let mut per_db_counts = Vec::with_capacity(ctx.state.dbs.len());
         for db in &ctx.state.dbs {
+            let (mut keys, mut variants_in_db) = (0, 0);
             for shard_index in 0..NUM_SHARDS {
                 let guard = db.get_shard(shard_index).entries.lock().await;
                 for entry in guard.iter() {
                     if let DataValue::HttpCache { variants, .. } = &entry.1.data {
-                        total_variants += variants.len();
+                        keys += 1;
+                        variants_in_db += variants.len();
                     }
                 }
             }
+            total_variants += variants_in_db;
+            per_db_counts.push((keys, variants_in_db));
         }
 
         // Get the number of active caching policies.
         let policies_count = ctx.state.cache.policies.read().await.len();
 
-        // Format the hit ratio for RESP output.
-        let mut buffer = ryu::Buffer::new();
-        let formatted_ratio = buffer.format(hit_ratio);
-
         // Assemble the final response array.
-        let stats = vec![
+        let mut stats = vec![
             RespValue::BulkString("hits".into()),
             RespValue::Integer(hits as i64),
             RespValue::BulkString("misses".into()),
             RespValue::Integer(misses as i64),
             RespValue::BulkString("hit_ratio".into()),
-            RespValue::BulkString(Bytes::copy_from_slice(formatted_ratio.as_bytes())),
+            format_hit_ratio(hits, misses),
             RespValue::BulkString("stale_hits".into()),
             RespValue::Integer(stale_hits as i64),
             RespValue::BulkString("revalidations".into()),
             RespValue::Integer(revalidations as i64),
             RespValue::BulkString("evictions".into()),
             RespValue::Integer(evictions as i64),
+            RespValue::BulkString("demotions".into()),
+            RespValue::Integer(demotions as i64),
+            RespValue::BulkString("promotions".into()),
+            RespValue::Integer(promotions as i64),
             RespValue::BulkString("total_variants".into()),
             RespValue::Integer(total_variants as i64),
             RespValue::BulkString("policies_count".into()),
             RespValue::Integer(policies_count as i64),
         ];
 
+        // Append a `dbN` section for every database that holds cache keys or has
+        // served cache requests.
+        for (db_index, (keys, variants)) in per_db_counts.into_iter().enumerate() {
+            let db_stats = ctx.state.cache.db_stats.get(&db_index);
+            if keys == 0 && db_stats.is_none() {
+                continue;
+            }
+            let load = |counter: fn(&CacheDbStats) -> &AtomicU64| {
+                db_stats
+                    .as_deref()
+                    .map_or(0, |stats| counter(stats).load(Ordering::Relaxed))
+            };
+            let (db_hits, db_misses) = (load(|s| &s.hits), load(|s| &s.misses));
+            stats.push(RespValue::BulkString(format!("db{db_index}").into()));
+            stats.push(RespValue::Array(vec![
+                RespValue::BulkString("hits".into()),
+                RespValue::Integer(db_hits as i64),
+                RespValue::BulkString("misses".into()),
+                RespValue::Integer(db_misses as i64),
+                RespValue::BulkString("hit_ratio".into()),
+                format_hit_ratio(db_hits, db_misses),
+                RespValue::BulkString("stale_hits".into()),
+                RespValue::Integer(load(|s| &s.stale_hits) as i64),
+                RespValue::BulkString("revalidations".into()),
+                RespValue::Integer(load(|s| &s.revalidations) as i64),
+                RespValue::BulkString("evictions".into()),
+                RespValue::Integer(load(|s| &s.evictions) as i64),
+                RespValue::BulkString("demotions".into()),
+                RespValue::Integer(load(|s| &s.demotions) as i64),
+                RespValue::BulkString("promotions".into()),
+                RespValue::Integer(load(|s| &s.promotions) as i64),
+                RespValue::BulkString("keys".into()),
+                RespValue::Integer(keys as i64),
+                RespValue::BulkString("total_variants".into()),
+                RespValue::Integer(variants as i64),
+            ]));
+        }
+
         Ok((RespValue::Array(stats), WriteOutcome::DidNotWrite))
     }
 }
 
+/// Calculates the ratio of hits to all requests and formats it for RESP output.
+fn format_hit_ratio(hits: u64, misses: u64) -> RespValue {
+    let total_requests = hits + misses;
+    let hit_ratio = if total_requests > 0 {
+        (hits as f64) / (total_requests as f64)
+    } else {
+        0.0
+    };
+    let mut buffer = ryu::Buffer::new();
+    RespValue::BulkString(Bytes::copy_from_slice(buffer.format(hit_ratio).as_bytes()))
+}
+
 impl CommandSpec for CacheStats {
     fn name(&self) -> &'static str {
         "cache.stats"
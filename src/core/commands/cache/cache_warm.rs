@@ -0,0 +1,569 @@
+// src/core/commands/cache/cache_warm.rs
+
+//! Implements the `CACHE.WARM` command, which populates the cache from a list of URLs
+//! ahead of client traffic, e.g. after a deploy or a cold start.
+//!
+//! Each URL is mapped back to the key it is cached under by matching it against the
+//! policy's `url_template`, and is then fetched in the background through the same
+//! path as a `CACHE.PROXY` miss. Keys that are already cached and fresh are skipped.
+
+use crate::core::commands::cache::cache_fetch::FetchOutcome;
+use crate::core::commands::cache::cache_proxy::CacheProxy;
+use crate::core::commands::command_spec::CommandSpec;
+use crate::core::commands::command_trait::{
+    CommandFlags, ExecutableCommand, ParseCommand, WriteOutcome,
+};
+use crate::core::commands::helpers::{ArgParser, extract_string};
+use crate::core::database::ExecutionContext;
+use crate::core::protocol::RespFrame;
+use crate::core::state::ServerState;
+use crate::core::state::warm::{WarmJob, WarmOutcome};
+use crate::core::storage::cache_types::CachePolicy;
+use crate::core::{RespValue, SpinelDBError};
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::StreamExt;
+use regex::Regex;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{debug, info};
+use wildmatch::WildMatch;
+
+/// The number of URLs fetched at the same time if `CONCURRENCY` is not given.
+const DEFAULT_WARM_CONCURRENCY: usize = 4;
+/// The lowest `RATE` accepted, in URLs per second.
+const MIN_WARM_RATE: f64 = 0.001;
+
+/// The options of a `CACHE.WARM <policy> FROM ...` run.
+#[derive(Debug, Clone, Default)]
+pub struct WarmRequest {
+    pub policy: String,
+    /// URLs, and paths of files on the server listing URLs.
+    pub sources: Vec<String>,
+    pub concurrency: usize,
+    /// The maximum number of URLs started per second.
+    pub rate: Option<f64>,
+}
+
+/// Defines the subcommands for `CACHE.WARM`.
+#[derive(Debug, Clone)]
+pub enum CacheWarmSubcommand {
+    /// Starts warming the keys of a policy.
+    Start(WarmRequest),
+    /// Reports the progress of all warmup jobs.
+    Status,
+    /// Stops the running warmup of a policy in the current database.
+    Cancel(String),
+}
+
+/// The main command struct for `CACHE.WARM`.
+#[derive(Debug, Clone)]
+pub struct CacheWarm {
+    pub subcommand: CacheWarmSubcommand,
+}
+
+impl Default for CacheWarm {
+    fn default() -> Self {
+        Self {
+            subcommand: CacheWarmSubcommand::Status,
+        }
+    }
+}
+
+/// Parses a `RATE` value given as `<n>` or `<n>/s`.
+fn parse_rate(value: &str) -> Result<f64, SpinelDBError> {
+    let number = value
+        .strip_suffix("/s")
+        .or_else(|| value.strip_suffix("/S"))
+        .unwrap_or(value);
+    match number.parse::<f64>() {
+        Ok(rate) if rate.is_finite() && rate >= MIN_WARM_RATE => Ok(rate),
+        _ => Err(SpinelDBError::InvalidRequest(format!(
+            "RATE must be at least {MIN_WARM_RATE} URLs per second"
+        ))),
+    }
+}
+
+impl ParseCommand for CacheWarm {
+    fn parse(args: &[RespFrame]) -> Result<Self, SpinelDBError> {
+        if args.is_empty() {
+            return Err(SpinelDBError::WrongArgumentCount("CACHE.WARM".to_string()));
+        }
+
+        // `CACHE.WARM <policy> FROM ...` starts a run; anything else is a subcommand, so
+        // policies named like one can still be warmed.
+        let is_start = args
+            .get(1)
+            .map(extract_string)
+            .transpose()?
+            .is_some_and(|s| s.eq_ignore_ascii_case("from"));
+        if !is_start {
+            let sub_str = extract_string(&args[0])?.to_ascii_lowercase();
+            let subcommand = match (sub_str.as_str(), args.len()) {
+                ("status", 1) => CacheWarmSubcommand::Status,
+                ("cancel", 2) => CacheWarmSubcommand::Cancel(extract_string(&args[1])?),
+                ("status", _) | ("cancel", _) => {
+                    return Err(SpinelDBError::WrongArgumentCount(format!(
+                        "CACHE.WARM {}",
+                        sub_str.to_ascii_uppercase()
+                    )));
+                }
+                _ => return Err(SpinelDBError::SyntaxError),
+            };
+            return Ok(CacheWarm { subcommand });
+        }
+
+        let mut request = WarmRequest {
+            policy: extract_string(&args[0])?,
+            concurrency: DEFAULT_WARM_CONCURRENCY,
+            ..Default::default()
+        };
+        let mut parser = ArgParser::new(&args[2..]);
+        while !parser.remaining_args().is_empty() {
+            if let Some(concurrency) = parser.match_option::<usize>("concurrency")? {
+                if concurrency == 0 {
+                    return Err(SpinelDBError::InvalidRequest(
+                        "CONCURRENCY must be greater than 0".into(),
+                    ));
+                }
+                request.concurrency = concurrency;
+            } else if let Some(rate) = parser.match_option::<String>("rate")? {
+                request.rate = Some(parse_rate(&rate)?);
+            } else {
+                request
+                    .sources
+                    .push(extract_string(&parser.remaining_args()[0])?);
+                parser = ArgParser::new(&parser.remaining_args()[1..]);
+            }
+        }
+        if request.sources.is_empty() {
+            return Err(SpinelDBError::WrongArgumentCount("CACHE.WARM".to_string()));
+        }
+
+        Ok(CacheWarm {
+            subcommand: CacheWarmSubcommand::Start(request),
+        })
+    }
+}
+
+#[async_trait]
+impl ExecutableCommand for CacheWarm {
+    async fn execute<'a>(
+        &self,
+        ctx: &mut ExecutionContext<'a>,
+    ) -> Result<(RespValue, WriteOutcome), SpinelDBError> {
+        match &self.subcommand {
+            CacheWarmSubcommand::Start(request) => {
+                let queued = start_warm(&ctx.state, ctx.db_index(), request).await?;
+                Ok((RespValue::Integer(queued as i64), WriteOutcome::DidNotWrite))
+            }
+            CacheWarmSubcommand::Status => {
+                let mut jobs: Vec<Arc<WarmJob>> = ctx
+                    .state
+                    .cache
+                    .warm_jobs
+                    .iter()
+                    .map(|job| job.value().clone())
+                    .collect();
+                jobs.sort_by(|a, b| (a.db_index, &a.policy).cmp(&(b.db_index, &b.policy)));
+                let status = jobs
+                    .iter()
+                    .map(|job| {
+                        let snapshot = job.snapshot();
+                        RespValue::Array(vec![
+                            RespValue::BulkString("policy".into()),
+                            RespValue::BulkString(job.policy.clone().into()),
+                            RespValue::BulkString("db".into()),
+                            RespValue::Integer(job.db_index as i64),
+                            RespValue::BulkString("state".into()),
+                            RespValue::BulkString(snapshot.state.as_str().into()),
+                            RespValue::BulkString("total".into()),
+                            RespValue::Integer(snapshot.total as i64),
+                            RespValue::BulkString("fetched".into()),
+                            RespValue::Integer(snapshot.fetched as i64),
+                            RespValue::BulkString("skipped".into()),
+                            RespValue::Integer(snapshot.skipped as i64),
+                            RespValue::BulkString("failed".into()),
+                            RespValue::Integer(snapshot.failed as i64),
+                            RespValue::BulkString("elapsed_ms".into()),
+                            RespValue::Integer(snapshot.elapsed.as_millis() as i64),
+                        ])
+                    })
+                    .collect();
+                Ok((RespValue::Array(status), WriteOutcome::DidNotWrite))
+            }
+            CacheWarmSubcommand::Cancel(policy) => {
+                let cancelled = ctx
+                    .state
+                    .cache
+                    .warm_jobs
+                    .get(&(ctx.db_index(), policy.clone()))
+                    .is_some_and(|job| job.cancel());
+                Ok((
+                    RespValue::Integer(cancelled as i64),
+                    WriteOutcome::DidNotWrite,
+                ))
+            }
+        }
+    }
+}
+
+/// Reads the URLs of `request`, registers a job for them and spawns it. Returns the
+/// number of URLs queued.
+async fn start_warm(
+    state: &Arc<ServerState>,
+    db_index: usize,
+    request: &WarmRequest,
+) -> Result<usize, SpinelDBError> {
+    let policy = state
+        .cache
+        .policies
+        .read()
+        .await
+        .iter()
+        .find(|p| p.name == request.policy)
+        .cloned()
+        .ok_or_else(|| {
+            SpinelDBError::InvalidRequest(format!("No cache policy named '{}'", request.policy))
+        })?;
+    let matcher = UrlKeyMatcher::new(&policy)?;
+
+    let mut urls = Vec::new();
+    for source in &request.sources {
+        if source.starts_with("http://") || source.starts_with("https://") {
+            urls.push(source.clone());
+        } else {
+            let contents = tokio::fs::read_to_string(source).await.map_err(|e| {
+                SpinelDBError::InvalidRequest(format!("Cannot read warmup source '{source}': {e}"))
+            })?;
+            urls.extend(parse_url_list(&contents));
+        }
+    }
+    let mut seen = HashSet::new();
+    urls.retain(|url| seen.insert(url.clone()));
+
+    let job_key = (db_index, policy.name.clone());
+    let job = Arc::new(WarmJob::new(
+        policy.name.clone(),
+        db_index,
+        urls.len() as u64,
+    ));
+    match state.cache.warm_jobs.entry(job_key) {
+        dashmap::mapref::entry::Entry::Occupied(mut occupied) => {
+            if occupied.get().is_running() {
+                return Err(SpinelDBError::InvalidState(format!(
+                    "A warmup of policy '{}' is already running",
+                    policy.name
+                )));
+            }
+            occupied.insert(job.clone());
+        }
+        dashmap::mapref::entry::Entry::Vacant(vacant) => {
+            vacant.insert(job.clone());
+        }
+    }
+
+    info!(
+        "Warming {} URLs for cache policy '{}' in db {}.",
+        urls.len(),
+        policy.name,
+        db_index
+    );
+    let queued = urls.len();
+    tokio::spawn(run_warm(
+        state.clone(),
+        job,
+        matcher,
+        urls,
+        request.concurrency,
+        request.rate,
+    ));
+    Ok(queued)
+}
+
+/// Warms `urls` with at most `concurrency` fetches in flight, starting no more than
+/// `rate` of them per second.
+async fn run_warm(
+    state: Arc<ServerState>,
+    job: Arc<WarmJob>,
+    matcher: UrlKeyMatcher,
+    urls: Vec<String>,
+    concurrency: usize,
+    rate: Option<f64>,
+) {
+    // The job is finished even if this task panics, so that the policy can be warmed
+    // again.
+    let finish = job.finish_on_drop();
+    let started = Instant::now();
+    let interval = rate.map(|rate| Duration::from_secs_f64(1.0 / rate));
+    let state = &state;
+    let job = &job;
+    let matcher = &matcher;
+
+    // No more URLs are started once the job is cancelled, and waits for a start slot
+    // end early, so that a cancelled job finishes promptly.
+    futures::stream::iter(urls.into_iter().enumerate())
+        .take_until(job.cancelled())
+        .for_each_concurrent(concurrency, |(i, url)| async move {
+            if let Some(interval) = interval {
+                let Some(start_at) = u32::try_from(i)
+                    .ok()
+                    .and_then(|i| interval.checked_mul(i))
+                    .and_then(|delay| started.checked_add(delay))
+                else {
+                    job.record(WarmOutcome::Failed);
+                    return;
+                };
+                tokio::select! {
+                    _ = tokio::time::sleep_until(start_at.into()) => {}
+                    _ = job.cancelled() => return,
+                }
+            }
+            if job.is_cancelled() {
+                return;
+            }
+            let outcome = warm_url(state, job.db_index, matcher, &url).await;
+            job.record(outcome);
+        })
+        .await;
+
+    drop(finish);
+    let snapshot = job.snapshot();
+    info!(
+        "Warmup of cache policy '{}' in db {} {}: {} fetched, {} skipped, {} failed.",
+        job.policy,
+        job.db_index,
+        snapshot.state.as_str(),
+        snapshot.fetched,
+        snapshot.skipped,
+        snapshot.failed
+    );
+}
+
+/// Fetches `url` into the key it maps to, unless that key is already cached and fresh.
+async fn warm_url(
+    state: &Arc<ServerState>,
+    db_index: usize,
+    matcher: &UrlKeyMatcher,
+    url: &str,
+) -> WarmOutcome {
+    let Some(key) = matcher.key_for(url) else {
+        debug!("Warmup URL '{url}' does not match the policy's URL template.");
+        return WarmOutcome::Failed;
+    };
+    let key = Bytes::from(key);
+    if is_fresh(state, db_index, &key).await {
+        return WarmOutcome::Skipped;
+    }
+
+    // The URL is fetched as listed, with the remaining options of the policy that
+    // matches the key, like a `CACHE.PROXY` miss.
+    let proxy = CacheProxy {
+        key,
+        url: Some(url.to_string()),
+        ..Default::default()
+    };
+    // The store is admitted and propagated as if it had gone through the command router.
+    let result = async {
+        let policies = state.cache.policies.read().await.clone();
+        let (fetch_cmd, _) = proxy.resolve_fetch(&policies)?;
+        let _admission = fetch_cmd.admit_store(state, db_index).await?;
+        let (target_ip, domain) = fetch_cmd.resolve_origin(state).await?;
+        fetch_cmd
+            .fetch_shared(state, db_index, target_ip, domain, true)
+            .await
+    }
+    .await;
+    match result {
+        Ok(FetchOutcome::InMemory(_) | FetchOutcome::OnDisk { .. }) => WarmOutcome::Fetched,
+        Ok(FetchOutcome::Negative { status, .. }) => {
+            debug!("Warmup of '{url}' failed: origin responded with status {status}.");
+            WarmOutcome::Failed
+        }
+        Err(e) => {
+            debug!("Warmup of '{url}' failed: {e}");
+            WarmOutcome::Failed
+        }
+    }
+}
+
+/// Returns `true` if `key` exists in database `db_index` and has not expired.
+async fn is_fresh(state: &Arc<ServerState>, db_index: usize, key: &Bytes) -> bool {
+    let Some(db) = state.get_db(db_index) else {
+        return false;
+    };
+    let guard = db.get_shard(db.get_shard_index(key)).entries.lock().await;
+    guard.peek(key).is_some_and(|entry| {
+        entry
+            .expiry
+            .is_none_or(|expiry| expiry > std::time::Instant::now())
+    })
+}
+
+/// Extracts the URLs from the contents of a warmup file: the `<loc>` entries of an XML
+/// sitemap, or otherwise one URL per line, ignoring blank lines and `#` comments.
+pub fn parse_url_list(contents: &str) -> Vec<String> {
+    if contents.contains("<loc>") {
+        let re_loc = Regex::new(r"<loc>\s*([^<]*?)\s*</loc>").unwrap();
+        return re_loc
+            .captures_iter(contents)
+            .map(|caps| unescape_xml(&caps[1]))
+            .filter(|url| !url.is_empty())
+            .collect();
+    }
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Replaces the XML entities allowed in sitemap URLs.
+fn unescape_xml(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Maps origin URLs back to cache keys by inverting a policy's interpolation: the
+/// `url_template` becomes a pattern whose `{N}` placeholders capture the values that
+/// fill the N-th `*` of the `key_pattern`.
+#[derive(Debug)]
+pub struct UrlKeyMatcher {
+    url_regex: Regex,
+    /// The placeholder number of each capture group of `url_regex`, in order.
+    placeholders: Vec<usize>,
+    key_pattern: String,
+}
+
+impl UrlKeyMatcher {
+    /// Builds the matcher for `policy`. Fails if its keys cannot be derived from URLs,
+    /// i.e. its URL template uses request headers or its key pattern contains `?`.
+    pub fn new(policy: &CachePolicy) -> Result<Self, SpinelDBError> {
+        if policy.url_template.contains("{hdr:") || policy.key_pattern.contains('?') {
+            return Err(SpinelDBError::InvalidRequest(format!(
+                "Keys of cache policy '{}' cannot be derived from URLs",
+                policy.name
+            )));
+        }
+
+        let re_placeholder = Regex::new(r"\{(\d+)\}").unwrap();
+        let template = &policy.url_template;
+        let mut pattern = String::from("^");
+        let mut placeholders = Vec::new();
+        let mut last = 0;
+        for caps in re_placeholder.captures_iter(template) {
+            let whole = caps.get(0).unwrap();
+            pattern.push_str(&regex::escape(&template[last..whole.start()]));
+            let number: usize = caps[1].parse().map_err(|_| SpinelDBError::SyntaxError)?;
+            if placeholders.contains(&number) {
+                pattern.push_str(".*?");
+            } else {
+                placeholders.push(number);
+                pattern.push_str("(.*?)");
+            }
+            last = whole.end();
+        }
+        pattern.push_str(&regex::escape(&template[last..]));
+        pattern.push('$');
+
+        Ok(Self {
+            url_regex: Regex::new(&pattern)
+                .map_err(|e| SpinelDBError::Internal(format!("Invalid URL pattern: {e}")))?,
+            placeholders,
+            key_pattern: policy.key_pattern.clone(),
+        })
+    }
+
+    /// Returns the key `url` is cached under, or `None` if the URL does not match the
+    /// template or leaves a wildcard of the key pattern unfilled.
+    pub fn key_for(&self, url: &str) -> Option<String> {
+        let caps = self.url_regex.captures(url)?;
+        let capture = |number: usize| {
+            let group = self.placeholders.iter().position(|n| *n == number)? + 1;
+            let value = caps.get(group)?.as_str();
+            Some(
+                urlencoding::decode(value)
+                    .map(|v| v.into_owned())
+                    .unwrap_or_else(|_| value.to_string()),
+            )
+        };
+
+        let mut key = String::with_capacity(self.key_pattern.len());
+        let mut wildcard = 0;
+        for c in self.key_pattern.chars() {
+            if c == '*' {
+                wildcard += 1;
+                key.push_str(&capture(wildcard)?);
+            } else {
+                key.push(c);
+            }
+        }
+        WildMatch::new(&self.key_pattern)
+            .matches(&key)
+            .then_some(key)
+    }
+}
+
+impl CommandSpec for CacheWarm {
+    fn name(&self) -> &'static str {
+        "cache.warm"
+    }
+
+    fn arity(&self) -> i64 {
+        -2
+    }
+
+    fn flags(&self) -> CommandFlags {
+        // Warmup reads files on the server, so it is restricted and not replicated.
+        match self.subcommand {
+            CacheWarmSubcommand::Start(_) | CacheWarmSubcommand::Cancel(_) => {
+                CommandFlags::ADMIN | CommandFlags::NO_PROPAGATE
+            }
+            CacheWarmSubcommand::Status => {
+                CommandFlags::ADMIN | CommandFlags::READONLY | CommandFlags::NO_PROPAGATE
+            }
+        }
+    }
+
+    fn first_key(&self) -> i64 {
+        0
+    }
+
+    fn last_key(&self) -> i64 {
+        0
+    }
+
+    fn step(&self) -> i64 {
+        0
+    }
+
+    fn get_keys(&self) -> Vec<Bytes> {
+        vec![]
+    }
+
+    fn to_resp_args(&self) -> Vec<Bytes> {
+        match &self.subcommand {
+            CacheWarmSubcommand::Start(request) => {
+                let mut args = vec![request.policy.clone().into(), Bytes::from_static(b"FROM")];
+                args.extend(request.sources.iter().cloned().map(Bytes::from));
+                args.push(Bytes::from_static(b"CONCURRENCY"));
+                args.push(request.concurrency.to_string().into());
+                if let Some(rate) = request.rate {
+                    args.push(Bytes::from_static(b"RATE"));
+                    args.push(rate.to_string().into());
+                }
+                args
+            }
+            CacheWarmSubcommand::Status => vec![Bytes::from_static(b"STATUS")],
+            CacheWarmSubcommand::Cancel(policy) => {
+                vec![Bytes::from_static(b"CANCEL"), policy.clone().into()]
+            }
+        }
+    }
+}
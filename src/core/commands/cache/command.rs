@@ -27,6 +27,7 @@ use super::cache_set::CacheSet;
 use super::cache_softpurge::CacheSoftPurge;
 use super::cache_softpurgetag::CacheSoftPurgeTag;
 use super::cache_stats::CacheStats;
+use super::cache_warm::CacheWarm;
 
 /// Enum to hold all possible parsed `CACHE` subcommands.
 #[derive(Debug, Clone)]
@@ -45,6 +46,7 @@ pub enum CacheSubcommand {
     Info(CacheInfo),
     SoftPurge(CacheSoftPurge),
     SoftPurgeTag(CacheSoftPurgeTag),
+    Warm(CacheWarm),
 }
 
 /// The main `Cache` command struct that holds a specific subcommand.
@@ -98,6 +100,7 @@ impl ParseCommand for Cache {
             "softpurgetag" => {
                 CacheSubcommand::SoftPurgeTag(CacheSoftPurgeTag::parse(command_args)?)
             }
+            "warm" => CacheSubcommand::Warm(CacheWarm::parse(command_args)?),
             _ => return Err(SpinelDBError::UnknownCommand(format!("CACHE {sub_str}"))),
         };
 
@@ -127,6 +130,7 @@ impl ExecutableCommand for Cache {
             CacheSubcommand::Info(cmd) => cmd.execute(ctx).await,
             CacheSubcommand::SoftPurge(cmd) => cmd.execute(ctx).await,
             CacheSubcommand::SoftPurgeTag(cmd) => cmd.execute(ctx).await,
+            CacheSubcommand::Warm(cmd) => cmd.execute(ctx).await,
         }
     }
 }
@@ -157,6 +161,7 @@ impl CommandSpec for Cache {
             CacheSubcommand::Info(cmd) => cmd.flags(),
             CacheSubcommand::SoftPurge(cmd) => cmd.flags(),
             CacheSubcommand::SoftPurgeTag(cmd) => cmd.flags(),
+            CacheSubcommand::Warm(cmd) => cmd.flags(),
         }
     }
 
@@ -174,7 +179,8 @@ impl CommandSpec for Cache {
             | CacheSubcommand::Stats(_)
             | CacheSubcommand::Policy(_)
             | CacheSubcommand::SoftPurgeTag(_)
-            | CacheSubcommand::Purge(_) => 0,
+            | CacheSubcommand::Purge(_)
+            | CacheSubcommand::Warm(_) => 0,
         }
     }
 
@@ -222,6 +228,7 @@ impl CommandSpec for Cache {
             CacheSubcommand::Info(cmd) => cmd.get_keys(),
             CacheSubcommand::SoftPurge(cmd) => cmd.get_keys(),
             CacheSubcommand::SoftPurgeTag(cmd) => cmd.get_keys(),
+            CacheSubcommand::Warm(cmd) => cmd.get_keys(),
         }
     }
 
@@ -297,6 +304,11 @@ impl CommandSpec for Cache {
                 args.extend(cmd.to_resp_args());
                 args
             }
+            CacheSubcommand::Warm(cmd) => {
+                let mut args = vec![Bytes::from_static(b"WARM")];
+                args.extend(cmd.to_resp_args());
+                args
+            }
         }
     }
 }
@@ -0,0 +1,303 @@
+// src/core/commands/cache/encoding.rs
+
+//! Serves cached bodies encoded for the client's `Accept-Encoding`.
+//!
+//! A body is cached once, unencoded. For policies with `NEGOTIATE_ENCODING`, the best
+//! encoding a request accepts is chosen from `cache.response_encodings`, and the body
+//! is encoded the first time that encoding is asked for. The result is kept as a
+//! rendition: an extra variant of the key whose hash is derived from the hash of the
+//! variant it encodes. Renditions count towards `max_variants_per_key` and are dropped
+//! whenever the variant they encode changes.
+
+use crate::core::database::tiering::is_same_body;
+use crate::core::state::ServerState;
+use crate::core::storage::cache_types::{
+    CacheBody, CacheVariant, ContentEncoding, IDENTITY_ENCODING, VariantMap,
+};
+use bytes::Bytes;
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::time::Instant;
+use wildmatch::WildMatch;
+
+/// The Brotli quality used for renditions. Bodies are encoded while the request waits,
+/// so a fast level is preferred over the best ratio.
+const BROTLI_QUALITY: u32 = 5;
+/// The Brotli window size (log2) used for renditions.
+const BROTLI_WINDOW: u32 = 22;
+
+/// The encodings a request may be served with, resolved from its key's policy.
+pub struct Negotiation {
+    /// The encodings from `cache.response_encodings`.
+    pub encodings: Vec<ContentEncoding>,
+    /// The `max_variants_per_key` limit, which renditions count towards.
+    pub max_variants: usize,
+}
+
+impl Negotiation {
+    /// Returns the negotiation for `key`, or `None` if the policy matching the key does
+    /// not enable `NEGOTIATE_ENCODING` or no encodings are configured.
+    pub async fn for_key(state: &ServerState, key: &[u8]) -> Option<Self> {
+        {
+            let policies = state.cache.policies.read().await;
+            if policies.is_empty() {
+                return None;
+            }
+            let key_str = String::from_utf8_lossy(key);
+            let policy = policies
+                .iter()
+                .find(|p| WildMatch::new(&p.key_pattern).matches(&key_str))?;
+            if !policy.negotiate_encoding {
+                return None;
+            }
+        }
+        let config = state.config.lock().await;
+        (!config.cache.response_encodings.is_empty()).then(|| Negotiation {
+            encodings: config.cache.response_encodings.clone(),
+            max_variants: config.cache.max_variants_per_key,
+        })
+    }
+}
+
+/// Chooses the encoding of `encodings` with the highest weight in the `Accept-Encoding`
+/// header. Ties are broken by the order of `ContentEncoding::ALL`. Returns `None` if
+/// the client accepts none of them or weights the unencoded body higher.
+pub fn negotiate(accept_encoding: &[u8], encodings: &[ContentEncoding]) -> Option<ContentEncoding> {
+    let accept_encoding = String::from_utf8_lossy(accept_encoding);
+    let weights: Vec<(&str, f32)> = accept_encoding
+        .split(',')
+        .filter_map(|part| {
+            let mut params = part.split(';');
+            let coding = params.next()?.trim();
+            if coding.is_empty() {
+                return None;
+            }
+            let q = params
+                .find_map(|param| {
+                    let (name, value) = param.split_once('=')?;
+                    name.trim()
+                        .eq_ignore_ascii_case("q")
+                        .then(|| value.trim().parse::<f32>().ok())
+                        .flatten()
+                })
+                .unwrap_or(1.0);
+            Some((coding, q.clamp(0.0, 1.0)))
+        })
+        .collect();
+    let weight = |coding: &str| {
+        weights
+            .iter()
+            .find(|(c, _)| c.eq_ignore_ascii_case(coding))
+            .map(|(_, q)| *q)
+    };
+    let wildcard = weight("*");
+    // The unencoded body is acceptable unless the client rules it out.
+    let identity = weight("identity").or(wildcard).unwrap_or(1.0);
+
+    let mut best: Option<(ContentEncoding, f32)> = None;
+    for encoding in ContentEncoding::ALL
+        .into_iter()
+        .filter(|e| encodings.contains(e))
+    {
+        let q = weight(encoding.as_str())
+            .or_else(|| {
+                (encoding == ContentEncoding::Gzip)
+                    .then(|| weight("x-gzip"))
+                    .flatten()
+            })
+            .or(wildcard)
+            .unwrap_or(0.0);
+        if q > 0.0 && best.is_none_or(|(_, best_q)| q > best_q) {
+            best = Some((encoding, q));
+        }
+    }
+    best.filter(|(_, q)| *q >= identity)
+        .map(|(encoding, _)| encoding)
+}
+
+/// Encodes `data` with `encoding`.
+pub fn encode(encoding: ContentEncoding, data: &[u8]) -> std::io::Result<Vec<u8>> {
+    match encoding {
+        ContentEncoding::Brotli => {
+            let mut writer =
+                brotli::CompressorWriter::new(Vec::new(), 4096, BROTLI_QUALITY, BROTLI_WINDOW);
+            writer.write_all(data)?;
+            Ok(writer.into_inner())
+        }
+        ContentEncoding::Zstd => zstd::encode_all(data, 0),
+        ContentEncoding::Gzip => {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(data)?;
+            encoder.finish()
+        }
+    }
+}
+
+/// Calculates the hash of the rendition of the variant `variant_hash` in `encoding`.
+pub fn rendition_hash(variant_hash: u64, encoding: ContentEncoding) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    "rendition".hash(&mut hasher);
+    variant_hash.hash(&mut hasher);
+    encoding.as_str().hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Returns `true` if renditions can be created from `variant`: a successful, unencoded
+/// in-memory body that the origin did not already encode.
+fn is_encodable(variant: &CacheVariant) -> bool {
+    !variant.is_rendition()
+        && matches!(
+            variant.body,
+            CacheBody::InMemory(_) | CacheBody::CompressedInMemory { .. }
+        )
+        && (200..300).contains(&variant.metadata.status_code())
+        && !variant
+            .metadata
+            .headers
+            .iter()
+            .any(|(name, _)| name.eq_ignore_ascii_case(b"content-encoding"))
+}
+
+/// Marks the variant `variant_hash` as served with `Vary: Accept-Encoding` if `enabled`
+/// and it can be encoded, and clears the mark otherwise.
+pub fn set_negotiated(variants: &mut VariantMap, variant_hash: u64, enabled: bool) {
+    let Some(variant) = variants.get_mut(&variant_hash) else {
+        return;
+    };
+    if variant.is_rendition() {
+        return;
+    }
+    let negotiated = enabled && is_encodable(variant);
+    variant.metadata.content_encoding = negotiated.then(|| Bytes::from_static(IDENTITY_ENCODING));
+}
+
+/// Returns the hash of the variant to serve for the variant `variant_hash`: its cached
+/// rendition that best matches `accept_encoding`, or the variant itself.
+pub fn served_variant(
+    variants: &VariantMap,
+    variant_hash: u64,
+    accept_encoding: Option<&[u8]>,
+) -> u64 {
+    let negotiated = variants
+        .get(&variant_hash)
+        .is_some_and(|v| v.metadata.content_encoding.is_some() && !v.is_rendition());
+    let Some(accept_encoding) = accept_encoding.filter(|_| negotiated) else {
+        return variant_hash;
+    };
+    let cached: Vec<ContentEncoding> = ContentEncoding::ALL
+        .into_iter()
+        .filter(|e| variants.contains_key(&rendition_hash(variant_hash, *e)))
+        .collect();
+    negotiate(accept_encoding, &cached).map_or(variant_hash, |e| rendition_hash(variant_hash, e))
+}
+
+/// Returns the body to encode for the rendition of the variant `variant_hash` in
+/// `encoding`, or `None` if the rendition exists or the variant cannot be encoded.
+/// The body shares its data with the variant, so copying it out is cheap.
+pub fn rendition_source(
+    variants: &VariantMap,
+    variant_hash: u64,
+    encoding: ContentEncoding,
+) -> Option<CacheBody> {
+    if variants.contains_key(&rendition_hash(variant_hash, encoding)) {
+        return None;
+    }
+    let variant = variants.get(&variant_hash)?;
+    is_encodable(variant).then(|| variant.body.clone())
+}
+
+/// Encodes `body`, a source returned by `rendition_source`, with `encoding`. This can
+/// take a while for large bodies, so it must not run while the key's shard is locked.
+pub fn encode_body(body: &CacheBody, encoding: ContentEncoding) -> std::io::Result<Bytes> {
+    match (body, encoding) {
+        // Compressed bodies are already stored as zstd.
+        (CacheBody::CompressedInMemory { data, .. }, ContentEncoding::Zstd) => Ok(data.clone()),
+        (CacheBody::CompressedInMemory { data, .. }, _) => zstd::decode_all(data.as_ref())
+            .and_then(|body| encode(encoding, &body))
+            .map(Bytes::from),
+        (CacheBody::InMemory(body), _) => encode(encoding, body).map(Bytes::from),
+        _ => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "Only in-memory bodies can be encoded",
+        )),
+    }
+}
+
+/// Adds `encoded`, the body `source` of the variant `variant_hash` encoded with
+/// `encoding`, as the variant's rendition. Nothing is added if the rendition was
+/// created in the meantime or the variant's body is no longer `source`. At
+/// `max_variants`, the least recently used other variant is evicted to make space.
+/// Returns `true` if a rendition was added.
+pub fn insert_rendition(
+    variants: &mut VariantMap,
+    variant_hash: u64,
+    encoding: ContentEncoding,
+    source: &CacheBody,
+    encoded: Bytes,
+    max_variants: usize,
+) -> bool {
+    let hash = rendition_hash(variant_hash, encoding);
+    if variants.contains_key(&hash) {
+        return false;
+    }
+    let Some(variant) = variants.get(&variant_hash) else {
+        return false;
+    };
+    if !is_same_body(&variant.body, source) || !is_encodable(variant) {
+        return false;
+    }
+
+    let mut metadata = variant.metadata.clone();
+    metadata.content_encoding = Some(Bytes::from_static(encoding.as_str().as_bytes()));
+    // Renditions are recreated from their variant rather than revalidated.
+    metadata.revalidate_url = None;
+    // An encoded body is not byte-for-byte identical to the unencoded one.
+    metadata.etag = metadata.etag.map(|etag| {
+        if etag.starts_with(b"W/") {
+            etag
+        } else {
+            Bytes::from([b"W/".as_slice(), &etag].concat())
+        }
+    });
+
+    if max_variants > 0 && variants.len() >= max_variants {
+        let lru_hash = variants
+            .iter()
+            .filter(|(h, _)| **h != variant_hash)
+            .min_by_key(|(_, v)| v.last_accessed)
+            .map(|(h, _)| *h);
+        match lru_hash {
+            Some(lru_hash) => remove_variant(variants, lru_hash),
+            None => return false,
+        }
+    }
+
+    variants.insert(
+        hash,
+        CacheVariant {
+            body: CacheBody::InMemory(encoded),
+            metadata,
+            last_accessed: Instant::now(),
+        },
+    );
+    true
+}
+
+/// Removes the renditions of the variant `variant_hash`.
+pub fn remove_renditions(variants: &mut VariantMap, variant_hash: u64) {
+    for encoding in ContentEncoding::ALL {
+        variants.remove(&rendition_hash(variant_hash, encoding));
+    }
+}
+
+/// Removes the variant `hash` together with its renditions.
+pub fn remove_variant(variants: &mut VariantMap, hash: u64) {
+    if let Some(variant) = variants.remove(&hash)
+        && !variant.is_rendition()
+    {
+        remove_renditions(variants, hash);
+    }
+}
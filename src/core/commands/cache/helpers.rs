@@ -2,6 +2,7 @@
 
 //! Contains shared helper functions for the CACHE.* command family.
 
+use crate::core::RespValue;
 use bytes::Bytes;
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
@@ -63,3 +64,56 @@ pub fn calculate_variant_hash(vary_on: &[Bytes], headers: &Option<Vec<(Bytes, By
     }
     hasher.finish()
 }
+
+/// Selects the origin response headers named in `allowlist` (case-insensitively),
+/// in the order the origin sent them.
+pub fn select_response_headers(
+    headers: &reqwest::header::HeaderMap,
+    allowlist: &[String],
+) -> Vec<(Bytes, Bytes)> {
+    headers
+        .iter()
+        .filter(|(name, _)| {
+            allowlist
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(name.as_str()))
+        })
+        .map(|(name, value)| {
+            (
+                Bytes::copy_from_slice(name.as_str().as_bytes()),
+                Bytes::copy_from_slice(value.as_bytes()),
+            )
+        })
+        .collect()
+}
+
+/// Encodes headers as the flat `[name, value, ...]` array used in the
+/// `[status, headers, body]` replies of `CACHE.GET` and `CACHE.PROXY`.
+pub fn headers_to_resp(headers: Vec<(Bytes, Bytes)>) -> RespValue {
+    RespValue::Array(
+        headers
+            .into_iter()
+            .flat_map(|(name, value)| [RespValue::BulkString(name), RespValue::BulkString(value)])
+            .collect(),
+    )
+}
+
+/// Collects the surrogate keys declared by the origin in the headers named in
+/// `header_names`. Values are split on whitespace and commas, so both Fastly's
+/// space-separated `Surrogate-Key` and the comma-separated `Cache-Tag` are supported.
+pub fn surrogate_keys(headers: &reqwest::header::HeaderMap, header_names: &[String]) -> Vec<Bytes> {
+    let mut keys: Vec<Bytes> = Vec::new();
+    for name in header_names {
+        for value in headers.get_all(name.as_str()) {
+            let Ok(value) = value.to_str() else {
+                continue;
+            };
+            for key in value.split(|c: char| c == ',' || c.is_ascii_whitespace()) {
+                if !key.is_empty() && !keys.iter().any(|k| k == key.as_bytes()) {
+                    keys.push(Bytes::copy_from_slice(key.as_bytes()));
+                }
+            }
+        }
+    }
+    keys
+}
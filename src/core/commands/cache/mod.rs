@@ -1,6 +1,7 @@
 // src/core/commands/cache/mod.rs
 
-mod helpers;
+pub(crate) mod encoding;
+pub(crate) mod helpers;
 
 // Modules for each subcommand
 pub mod cache_bypass;
@@ -16,6 +17,7 @@ pub mod cache_set;
 pub mod cache_softpurge;
 pub mod cache_softpurgetag;
 pub mod cache_stats;
+pub mod cache_warm;
 
 // Export the main dispatcher struct
 pub mod command;
@@ -14,16 +14,20 @@ pub async fn execute(
         .cluster
         .as_ref()
         .expect("CLUSTER ADDSLOTS must be run in cluster mode");
-    let mut my_runtime_state = cluster.nodes.get_mut(&cluster.my_id).unwrap();
 
-    for &slot in slots {
-        if slot >= NUM_SLOTS as u16 {
-            return Err(SpinelDBError::InvalidState(format!(
-                "Slot {slot} is out of range"
-            )));
+    if let Some(slot) = slots.iter().find(|&&slot| slot >= NUM_SLOTS as u16) {
+        return Err(SpinelDBError::InvalidState(format!(
+            "Slot {slot} is out of range"
+        )));
+    }
+
+    // The node map entry must be released before `save_config` reads the whole map.
+    {
+        let mut my_runtime_state = cluster.nodes.get_mut(&cluster.my_id).unwrap();
+        for &slot in slots {
+            *cluster.slots_map[slot as usize].write() = Some(cluster.my_id.clone());
+            my_runtime_state.node_info.slots.insert(slot);
         }
-        *cluster.slots_map[slot as usize].write() = Some(cluster.my_id.clone());
-        my_runtime_state.node_info.slots.insert(slot);
     }
 
     cluster.save_config().await?;
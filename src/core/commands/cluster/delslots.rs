@@ -0,0 +1,48 @@
+// src/core/commands/cluster/delslots.rs
+
+use crate::core::cluster::slot::NUM_SLOTS;
+use crate::core::commands::command_trait::WriteOutcome;
+use crate::core::database::ExecutionContext;
+use crate::core::{RespValue, SpinelDBError};
+
+/// Executes `CLUSTER DELSLOTS` and `CLUSTER DELSLOTSRANGE`, which make this node forget
+/// the owner of each slot. The slots become unassigned in this node's view.
+pub async fn execute(
+    ctx: &mut ExecutionContext<'_>,
+    slots: &[u16],
+) -> Result<(RespValue, WriteOutcome), SpinelDBError> {
+    let cluster = ctx
+        .state
+        .cluster
+        .as_ref()
+        .expect("CLUSTER DELSLOTS must be run in cluster mode");
+
+    // Validate every slot before changing anything, so the command is all-or-nothing.
+    for &slot in slots {
+        if slot >= NUM_SLOTS as u16 {
+            return Err(SpinelDBError::InvalidState(format!(
+                "Slot {slot} is out of range"
+            )));
+        }
+        if cluster.slots_map[slot as usize].read().is_none() {
+            return Err(SpinelDBError::InvalidState(format!(
+                "Slot {slot} is already unassigned"
+            )));
+        }
+    }
+
+    for &slot in slots {
+        if let Some(owner_id) = cluster.slots_map[slot as usize].write().take()
+            && let Some(mut owner) = cluster.nodes.get_mut(&owner_id)
+        {
+            owner.node_info.slots.remove(&slot);
+        }
+    }
+
+    cluster.save_config().await?;
+
+    Ok((
+        RespValue::SimpleString("OK".into()),
+        WriteOutcome::DidNotWrite,
+    ))
+}
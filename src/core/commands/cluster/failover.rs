@@ -0,0 +1,59 @@
+// src/core/commands/cluster/failover.rs
+
+//! Implements the `CLUSTER FAILOVER [FORCE | TAKEOVER]` command.
+//! This command promotes a replica to replace its primary on operator request.
+
+use crate::core::cluster::NodeFlags;
+use crate::core::cluster::failover::{self, FailoverMode};
+use crate::core::commands::command_trait::WriteOutcome;
+use crate::core::database::ExecutionContext;
+use crate::core::{RespValue, SpinelDBError};
+use tracing::error;
+
+/// Executes `CLUSTER FAILOVER`. The preconditions are checked here and the failover
+/// itself runs in a background task, so the reply only means that it has started.
+/// Its outcome is visible in `CLUSTER NODES` and the server log.
+pub async fn execute(
+    ctx: &mut ExecutionContext<'_>,
+    mode: FailoverMode,
+) -> Result<(RespValue, WriteOutcome), SpinelDBError> {
+    let cluster = ctx.state.cluster.as_ref().unwrap();
+
+    let primary_id = {
+        let myself = cluster.get_my_config();
+        if !myself.node_info.get_flags().contains(NodeFlags::REPLICA) {
+            return Err(SpinelDBError::InvalidState(
+                "You should send CLUSTER FAILOVER to a replica".into(),
+            ));
+        }
+        myself.node_info.replica_of.clone().ok_or_else(|| {
+            SpinelDBError::InvalidState("I'm a replica but my primary is unknown to me".into())
+        })?
+    };
+    let primary_flags = cluster
+        .nodes
+        .get(&primary_id)
+        .map(|primary| primary.node_info.get_flags())
+        .ok_or_else(|| {
+            SpinelDBError::InvalidState("I'm a replica but my primary is unknown to me".into())
+        })?;
+    if mode == FailoverMode::Coordinated
+        && primary_flags.intersects(NodeFlags::FAIL | NodeFlags::PFAIL)
+    {
+        return Err(SpinelDBError::InvalidState(
+            "Primary is down or failed, please use CLUSTER FAILOVER FORCE".into(),
+        ));
+    }
+
+    let state_for_task = ctx.state.clone();
+    ctx.state.critical_tasks.lock().await.spawn(async move {
+        if let Err(e) = failover::run_manual_failover(&state_for_task, mode).await {
+            error!("[MANUAL FAILOVER] Failed: {}", e);
+        }
+    });
+
+    Ok((
+        RespValue::SimpleString("OK".into()),
+        WriteOutcome::DidNotWrite,
+    ))
+}
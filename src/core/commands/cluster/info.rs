@@ -0,0 +1,87 @@
+// src/core/commands/cluster/info.rs
+
+use crate::core::cluster::NodeFlags;
+use crate::core::cluster::slot::NUM_SLOTS;
+use crate::core::commands::command_trait::WriteOutcome;
+use crate::core::database::ExecutionContext;
+use crate::core::{RespValue, SpinelDBError};
+use std::collections::HashMap;
+use std::sync::atomic::Ordering;
+
+/// Executes `CLUSTER INFO`, reporting slot coverage, node counts, epochs, and the
+/// progress of the most recent rebalance.
+pub async fn execute(
+    ctx: &mut ExecutionContext<'_>,
+) -> Result<(RespValue, WriteOutcome), SpinelDBError> {
+    let cluster = ctx.state.cluster.as_ref().unwrap();
+
+    let node_flags: HashMap<String, NodeFlags> = cluster
+        .nodes
+        .iter()
+        .map(|entry| (entry.key().clone(), entry.value().node_info.get_flags()))
+        .collect();
+
+    let (mut assigned, mut ok, mut pfail, mut fail) = (0, 0, 0, 0);
+    for slot in cluster.slots_map.iter() {
+        let Some(owner_id) = slot.read().clone() else {
+            continue;
+        };
+        assigned += 1;
+        match node_flags.get(&owner_id) {
+            Some(flags) if flags.contains(NodeFlags::FAIL) => fail += 1,
+            Some(flags) if flags.contains(NodeFlags::PFAIL) => pfail += 1,
+            Some(_) => ok += 1,
+            None => fail += 1,
+        }
+    }
+
+    let size = cluster
+        .nodes
+        .iter()
+        .filter(|entry| {
+            let node = &entry.value().node_info;
+            node.get_flags().contains(NodeFlags::PRIMARY) && !node.slots.is_empty()
+        })
+        .count();
+    let state = if assigned == NUM_SLOTS && fail == 0 {
+        "ok"
+    } else {
+        "fail"
+    };
+    let my_epoch = cluster.get_my_config().node_info.config_epoch;
+    let rebalance = cluster.rebalance_progress.read().clone();
+
+    let mut info = String::new();
+    info.push_str(&format!("cluster_state:{state}\r\n"));
+    info.push_str(&format!("cluster_slots_assigned:{assigned}\r\n"));
+    info.push_str(&format!("cluster_slots_ok:{ok}\r\n"));
+    info.push_str(&format!("cluster_slots_pfail:{pfail}\r\n"));
+    info.push_str(&format!("cluster_slots_fail:{fail}\r\n"));
+    info.push_str(&format!("cluster_known_nodes:{}\r\n", node_flags.len()));
+    info.push_str(&format!("cluster_size:{size}\r\n"));
+    info.push_str(&format!(
+        "cluster_current_epoch:{}\r\n",
+        cluster.current_epoch.load(Ordering::Relaxed)
+    ));
+    info.push_str(&format!("cluster_my_epoch:{my_epoch}\r\n"));
+    info.push_str(&format!(
+        "cluster_rebalance_status:{}\r\n",
+        rebalance.status.as_str()
+    ));
+    info.push_str(&format!(
+        "cluster_rebalance_moves:{}/{}\r\n",
+        rebalance.moves_done, rebalance.moves_total
+    ));
+    info.push_str(&format!(
+        "cluster_rebalance_slots:{}/{}\r\n",
+        rebalance.slots_moved, rebalance.slots_total
+    ));
+    if let Some(error) = &rebalance.last_error {
+        info.push_str(&format!("cluster_rebalance_error:{error}\r\n"));
+    }
+
+    Ok((
+        RespValue::BulkString(info.into()),
+        WriteOutcome::DidNotWrite,
+    ))
+}
@@ -0,0 +1,24 @@
+// src/core/commands/cluster/migrateslot.rs
+
+//! Implements the `CLUSTER MIGRATESLOT <slot> <destination-node-id>` command.
+//! It is sent by `CLUSTER RESHARD ... ATOMIC` to the source node of each slot.
+
+use crate::core::cluster::migration;
+use crate::core::commands::command_trait::WriteOutcome;
+use crate::core::database::ExecutionContext;
+use crate::core::{RespValue, SpinelDBError};
+
+/// Executes `CLUSTER MIGRATESLOT`. The slot is migrated atomically to the destination,
+/// which must already have it in the IMPORTING state. Replies with the number of keys
+/// sent once the destination owns the slot.
+pub async fn execute(
+    ctx: &mut ExecutionContext<'_>,
+    slot: u16,
+    dest_id: &str,
+) -> Result<(RespValue, WriteOutcome), SpinelDBError> {
+    let keys_sent = migration::migrate_slot_atomically(&ctx.state, slot, dest_id).await?;
+    Ok((
+        RespValue::Integer(keys_sent as i64),
+        WriteOutcome::DidNotWrite,
+    ))
+}
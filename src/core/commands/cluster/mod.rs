@@ -4,21 +4,30 @@
 
 // Declare all submodule files.
 mod addslots;
+mod delslots;
+mod failover;
 mod fix;
 mod forget;
 mod getkeysinslot;
+mod info;
 mod meet;
+mod migrateslot;
 mod nodes;
+mod rebalance;
 mod replicate;
 mod reshard;
 mod setslot;
+mod shards;
 mod slots;
 
+use crate::core::cluster::failover::FailoverMode;
+use crate::core::cluster::rebalance::RebalanceBy;
+use crate::core::cluster::slot::{self, NUM_SLOTS};
 use crate::core::commands::command_spec::CommandSpec;
 use crate::core::commands::command_trait::{
     CommandFlags, ExecutableCommand, ParseCommand, WriteOutcome,
 };
-use crate::core::commands::helpers::extract_string;
+use crate::core::commands::helpers::{extract_bytes, extract_string};
 use crate::core::database::ExecutionContext;
 use crate::core::protocol::RespFrame;
 use crate::core::{RespValue, SpinelDBError};
@@ -44,9 +53,143 @@ pub enum ClusterSubcommand {
         source_node_id: String,
         destination_node_id: String,
         slots: Vec<u16>,
+        atomic: bool,
     },
     Forget(String),
     Fix,
+    Rebalance {
+        weights: Vec<(String, f64)>,
+        threshold: f64,
+        simulate: bool,
+        atomic: bool,
+        by: RebalanceBy,
+    },
+    Shards,
+    KeySlot(Bytes),
+    CountKeysInSlot(u16),
+    DelSlots(Vec<u16>),
+    AddSlotsRange(Vec<(u16, u16)>),
+    DelSlotsRange(Vec<(u16, u16)>),
+    Info,
+    Failover(FailoverMode),
+    MigrateSlot {
+        slot: u16,
+        dest_id: String,
+    },
+}
+
+/// The default `CLUSTER REBALANCE` threshold: nodes within this percentage of their
+/// share of slots are considered balanced.
+const DEFAULT_REBALANCE_THRESHOLD: f64 = 2.0;
+
+/// Parses a list of slot numbers.
+fn parse_slots(args: &[RespFrame]) -> Result<Vec<u16>, SpinelDBError> {
+    args.iter()
+        .map(|f| {
+            extract_string(f)?
+                .parse::<u16>()
+                .map_err(|_| SpinelDBError::NotAnInteger)
+        })
+        .collect()
+}
+
+/// Parses a list of `start end` slot range pairs.
+fn parse_slot_ranges(args: &[RespFrame], cmd: &str) -> Result<Vec<(u16, u16)>, SpinelDBError> {
+    if args.is_empty() || !args.len().is_multiple_of(2) {
+        return Err(SpinelDBError::WrongArgumentCount(cmd.to_string()));
+    }
+    let slots = parse_slots(args)?;
+    slots
+        .chunks(2)
+        .map(|pair| {
+            if pair[0] > pair[1] {
+                Err(SpinelDBError::InvalidState(format!(
+                    "Start slot number {} is greater than end slot number {}",
+                    pair[0], pair[1]
+                )))
+            } else {
+                Ok((pair[0], pair[1]))
+            }
+        })
+        .collect()
+}
+
+/// Expands `start end` slot range pairs into a list of slots.
+fn expand_slot_ranges(ranges: &[(u16, u16)]) -> Vec<u16> {
+    ranges
+        .iter()
+        .flat_map(|&(start, end)| start..=end)
+        .collect()
+}
+
+/// Parses the options of `CLUSTER REBALANCE`.
+fn parse_rebalance(args: &[RespFrame]) -> Result<ClusterSubcommand, SpinelDBError> {
+    let mut weights = Vec::new();
+    let mut threshold = DEFAULT_REBALANCE_THRESHOLD;
+    let mut simulate = false;
+    let mut atomic = false;
+    let mut by = RebalanceBy::Slots;
+    let mut i = 0;
+    while i < args.len() {
+        match extract_string(&args[i])?.to_ascii_lowercase().as_str() {
+            "weight" => {
+                i += 1;
+                let start = i;
+                while i < args.len() {
+                    let arg = extract_string(&args[i])?;
+                    let Some((node, weight)) = arg.split_once('=') else {
+                        break;
+                    };
+                    let weight = weight
+                        .parse::<f64>()
+                        .map_err(|_| SpinelDBError::NotAFloat)?;
+                    if !weight.is_finite() || weight < 0.0 {
+                        return Err(SpinelDBError::NotAFloat);
+                    }
+                    weights.push((node.to_string(), weight));
+                    i += 1;
+                }
+                if i == start {
+                    return Err(SpinelDBError::SyntaxError);
+                }
+            }
+            "threshold" => {
+                let value = args.get(i + 1).ok_or(SpinelDBError::SyntaxError)?;
+                threshold = extract_string(value)?
+                    .parse::<f64>()
+                    .map_err(|_| SpinelDBError::NotAFloat)?;
+                if !threshold.is_finite() || threshold < 0.0 {
+                    return Err(SpinelDBError::NotAFloat);
+                }
+                i += 2;
+            }
+            "simulate" => {
+                simulate = true;
+                i += 1;
+            }
+            "atomic" => {
+                atomic = true;
+                i += 1;
+            }
+            "by" => {
+                let value = args.get(i + 1).ok_or(SpinelDBError::SyntaxError)?;
+                by = match extract_string(value)?.to_ascii_lowercase().as_str() {
+                    "slots" => RebalanceBy::Slots,
+                    "memory" => RebalanceBy::Memory,
+                    _ => return Err(SpinelDBError::SyntaxError),
+                };
+                i += 2;
+            }
+            _ => return Err(SpinelDBError::SyntaxError),
+        }
+    }
+    Ok(ClusterSubcommand::Rebalance {
+        weights,
+        threshold,
+        simulate,
+        atomic,
+        by,
+    })
 }
 
 /// An enum for the sub-options of the `CLUSTER SETSLOT` command.
@@ -149,7 +292,9 @@ impl ParseCommand for ClusterInfo {
                 }
                 let source_node_id = extract_string(&args[1])?;
                 let destination_node_id = extract_string(&args[2])?;
-                let slots: Vec<u16> = args[3..]
+                let atomic = extract_string(&args[3])?.eq_ignore_ascii_case("atomic");
+                let first_slot_arg = if atomic { 4 } else { 3 };
+                let slots: Vec<u16> = args[first_slot_arg..]
                     .iter()
                     .map(|f| {
                         extract_string(f)?
@@ -166,6 +311,24 @@ impl ParseCommand for ClusterInfo {
                     source_node_id,
                     destination_node_id,
                     slots,
+                    atomic,
+                }
+            }
+            "migrateslot" => {
+                if args.len() != 3 {
+                    return Err(SpinelDBError::WrongArgumentCount(
+                        "CLUSTER MIGRATESLOT".to_string(),
+                    ));
+                }
+                let slot = extract_string(&args[1])?.parse::<u16>()?;
+                if slot as usize >= NUM_SLOTS {
+                    return Err(SpinelDBError::InvalidState(format!(
+                        "Slot {slot} is out of range"
+                    )));
+                }
+                ClusterSubcommand::MigrateSlot {
+                    slot,
+                    dest_id: extract_string(&args[2])?,
                 }
             }
             "forget" => {
@@ -182,6 +345,57 @@ impl ParseCommand for ClusterInfo {
                 }
                 ClusterSubcommand::Fix
             }
+            "rebalance" => parse_rebalance(&args[1..])?,
+            "shards" => ClusterSubcommand::Shards,
+            "info" => ClusterSubcommand::Info,
+            "keyslot" => {
+                if args.len() != 2 {
+                    return Err(SpinelDBError::WrongArgumentCount(
+                        "CLUSTER KEYSLOT".to_string(),
+                    ));
+                }
+                ClusterSubcommand::KeySlot(extract_bytes(&args[1])?)
+            }
+            "countkeysinslot" => {
+                if args.len() != 2 {
+                    return Err(SpinelDBError::WrongArgumentCount(
+                        "CLUSTER COUNTKEYSINSLOT".to_string(),
+                    ));
+                }
+                ClusterSubcommand::CountKeysInSlot(extract_string(&args[1])?.parse::<u16>()?)
+            }
+            "delslots" => {
+                if args.len() < 2 {
+                    return Err(SpinelDBError::WrongArgumentCount(
+                        "CLUSTER DELSLOTS".to_string(),
+                    ));
+                }
+                ClusterSubcommand::DelSlots(parse_slots(&args[1..])?)
+            }
+            "failover" => {
+                let mode = match args.len() {
+                    1 => FailoverMode::Coordinated,
+                    2 => match extract_string(&args[1])?.to_ascii_lowercase().as_str() {
+                        "force" => FailoverMode::Force,
+                        "takeover" => FailoverMode::Takeover,
+                        _ => return Err(SpinelDBError::SyntaxError),
+                    },
+                    _ => {
+                        return Err(SpinelDBError::WrongArgumentCount(
+                            "CLUSTER FAILOVER".to_string(),
+                        ));
+                    }
+                };
+                ClusterSubcommand::Failover(mode)
+            }
+            "addslotsrange" => ClusterSubcommand::AddSlotsRange(parse_slot_ranges(
+                &args[1..],
+                "CLUSTER ADDSLOTSRANGE",
+            )?),
+            "delslotsrange" => ClusterSubcommand::DelSlotsRange(parse_slot_ranges(
+                &args[1..],
+                "CLUSTER DELSLOTSRANGE",
+            )?),
             _ => return Err(SpinelDBError::UnknownCommand(format!("CLUSTER {sub_str}"))),
         };
         Ok(ClusterInfo { subcommand })
@@ -221,9 +435,43 @@ impl ExecutableCommand for ClusterInfo {
                 source_node_id,
                 destination_node_id,
                 slots,
-            } => reshard::execute(ctx, source_node_id, destination_node_id, slots).await,
+                atomic,
+            } => reshard::execute(ctx, source_node_id, destination_node_id, slots, *atomic).await,
             ClusterSubcommand::Forget(node_id) => forget::execute(ctx, node_id).await,
             ClusterSubcommand::Fix => fix::execute(ctx).await,
+            ClusterSubcommand::Rebalance {
+                weights,
+                threshold,
+                simulate,
+                atomic,
+                by,
+            } => rebalance::execute(ctx, weights, *threshold, *simulate, *atomic, *by).await,
+            ClusterSubcommand::Shards => shards::execute(ctx).await,
+            ClusterSubcommand::Info => info::execute(ctx).await,
+            ClusterSubcommand::Failover(mode) => failover::execute(ctx, *mode).await,
+            ClusterSubcommand::MigrateSlot { slot, dest_id } => {
+                migrateslot::execute(ctx, *slot, dest_id).await
+            }
+            ClusterSubcommand::KeySlot(key) => Ok((
+                RespValue::Integer(slot::get_slot(key) as i64),
+                WriteOutcome::DidNotWrite,
+            )),
+            ClusterSubcommand::CountKeysInSlot(slot) => {
+                if *slot as usize >= NUM_SLOTS {
+                    return Err(SpinelDBError::InvalidState(format!(
+                        "Slot {slot} is out of range"
+                    )));
+                }
+                let count = ctx.db.count_keys_in_slot(*slot).await;
+                Ok((RespValue::Integer(count as i64), WriteOutcome::DidNotWrite))
+            }
+            ClusterSubcommand::DelSlots(slots) => delslots::execute(ctx, slots).await,
+            ClusterSubcommand::AddSlotsRange(ranges) => {
+                addslots::execute(ctx, &expand_slot_ranges(ranges)).await
+            }
+            ClusterSubcommand::DelSlotsRange(ranges) => {
+                delslots::execute(ctx, &expand_slot_ranges(ranges)).await
+            }
         }
     }
 }
@@ -289,12 +537,16 @@ impl CommandSpec for ClusterInfo {
                 source_node_id,
                 destination_node_id,
                 slots,
+                atomic,
             } => {
                 let mut args = vec![
                     "RESHARD".into(),
                     source_node_id.clone().into(),
                     destination_node_id.clone().into(),
                 ];
+                if *atomic {
+                    args.push("ATOMIC".into());
+                }
                 args.extend(slots.iter().map(|s| s.to_string().into()));
                 args
             }
@@ -302,6 +554,65 @@ impl CommandSpec for ClusterInfo {
                 vec!["FORGET".into(), node_id.clone().into()]
             }
             ClusterSubcommand::Fix => vec!["FIX".into()],
+            ClusterSubcommand::Rebalance {
+                weights,
+                threshold,
+                simulate,
+                atomic,
+                by,
+            } => {
+                let mut args = vec!["REBALANCE".into()];
+                if !weights.is_empty() {
+                    args.push("WEIGHT".into());
+                    args.extend(weights.iter().map(|(node, w)| format!("{node}={w}").into()));
+                }
+                args.extend(["THRESHOLD".into(), threshold.to_string().into()]);
+                if *simulate {
+                    args.push("SIMULATE".into());
+                }
+                if *atomic {
+                    args.push("ATOMIC".into());
+                }
+                if *by == RebalanceBy::Memory {
+                    args.extend(["BY".into(), "MEMORY".into()]);
+                }
+                args
+            }
+            ClusterSubcommand::Shards => vec!["SHARDS".into()],
+            ClusterSubcommand::Info => vec!["INFO".into()],
+            ClusterSubcommand::Failover(mode) => match mode {
+                FailoverMode::Coordinated => vec!["FAILOVER".into()],
+                FailoverMode::Force => vec!["FAILOVER".into(), "FORCE".into()],
+                FailoverMode::Takeover => vec!["FAILOVER".into(), "TAKEOVER".into()],
+            },
+            ClusterSubcommand::MigrateSlot { slot, dest_id } => vec![
+                "MIGRATESLOT".into(),
+                slot.to_string().into(),
+                dest_id.clone().into(),
+            ],
+            ClusterSubcommand::KeySlot(key) => vec!["KEYSLOT".into(), key.clone()],
+            ClusterSubcommand::CountKeysInSlot(slot) => {
+                vec!["COUNTKEYSINSLOT".into(), slot.to_string().into()]
+            }
+            ClusterSubcommand::DelSlots(slots) => {
+                let mut args = vec!["DELSLOTS".into()];
+                args.extend(slots.iter().map(|s| s.to_string().into()));
+                args
+            }
+            ClusterSubcommand::AddSlotsRange(ranges) => {
+                let mut args = vec!["ADDSLOTSRANGE".into()];
+                for (start, end) in ranges {
+                    args.extend([start.to_string().into(), end.to_string().into()]);
+                }
+                args
+            }
+            ClusterSubcommand::DelSlotsRange(ranges) => {
+                let mut args = vec!["DELSLOTSRANGE".into()];
+                for (start, end) in ranges {
+                    args.extend([start.to_string().into(), end.to_string().into()]);
+                }
+                args
+            }
         }
     }
 }
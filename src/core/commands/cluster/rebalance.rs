@@ -0,0 +1,200 @@
+// src/core/commands/cluster/rebalance.rs
+
+use super::reshard::run_reshard_orchestrator;
+use crate::core::cluster::client::ClusterClient;
+use crate::core::cluster::rebalance::{
+    self, RebalanceBy, RebalanceNode, RebalanceProgress, RebalanceStatus, SlotMove,
+};
+use crate::core::cluster::{ClusterNode, NodeFlags};
+use crate::core::commands::command_trait::WriteOutcome;
+use crate::core::database::ExecutionContext;
+use crate::core::state::ServerState;
+use crate::core::{RespValue, SpinelDBError};
+use std::sync::Arc;
+use tracing::{error, info};
+
+/// Executes `CLUSTER REBALANCE`. The plan is computed from the slot counts of all
+/// reachable primaries and the given weights. Unless `simulate` is set, it is then run
+/// move by move through the reshard orchestrator in a background task, with progress
+/// reported by `CLUSTER INFO`. With `atomic`, each slot is migrated atomically. With
+/// `by` set to `Memory`, the plan balances the memory used by the primaries instead of
+/// their slot counts. The reply lists the planned moves.
+pub async fn execute(
+    ctx: &mut ExecutionContext<'_>,
+    weights: &[(String, f64)],
+    threshold: f64,
+    simulate: bool,
+    atomic: bool,
+    by: RebalanceBy,
+) -> Result<(RespValue, WriteOutcome), SpinelDBError> {
+    let cluster = ctx.state.cluster.as_ref().unwrap();
+
+    let mut primaries: Vec<ClusterNode> = cluster
+        .nodes
+        .iter()
+        .map(|entry| entry.value().node_info.clone())
+        .filter(|n| {
+            let flags = n.get_flags();
+            flags.contains(NodeFlags::PRIMARY)
+                && !flags.intersects(NodeFlags::FAIL | NodeFlags::PFAIL | NodeFlags::HANDSHAKE)
+        })
+        .collect();
+    primaries.sort_by(|a, b| a.id.cmp(&b.id));
+    let mut nodes: Vec<RebalanceNode> = primaries
+        .iter()
+        .map(|n| RebalanceNode {
+            id: n.id.clone(),
+            slots: n.slots.clone(),
+            weight: 1.0,
+        })
+        .collect();
+
+    for (name, weight) in weights {
+        let matches: Vec<usize> = (0..nodes.len())
+            .filter(|&i| nodes[i].id.starts_with(name.as_str()))
+            .collect();
+        match matches.as_slice() {
+            [i] => nodes[*i].weight = *weight,
+            [] => {
+                return Err(SpinelDBError::InvalidState(format!(
+                    "No reachable primary matches node '{name}'"
+                )));
+            }
+            _ => {
+                return Err(SpinelDBError::InvalidState(format!(
+                    "Node '{name}' is ambiguous"
+                )));
+            }
+        }
+    }
+
+    let moves = match by {
+        RebalanceBy::Slots => rebalance::plan(&nodes, threshold)?,
+        RebalanceBy::Memory => {
+            let mut slot_loads = Vec::with_capacity(primaries.len());
+            for node in &primaries {
+                let used_memory = used_memory(&ctx.state, node).await?;
+                slot_loads.push(if node.slots.is_empty() {
+                    1.0
+                } else {
+                    (used_memory as f64 / node.slots.len() as f64).max(1.0)
+                });
+            }
+            rebalance::plan_by_load(&nodes, &slot_loads, threshold)?
+        }
+    };
+    let reply = RespValue::Array(
+        moves
+            .iter()
+            .map(|m| {
+                RespValue::BulkString(
+                    format!(
+                        "move {} slots from {} to {}",
+                        m.slots.len(),
+                        m.source,
+                        m.destination
+                    )
+                    .into(),
+                )
+            })
+            .collect(),
+    );
+    if simulate || moves.is_empty() {
+        return Ok((reply, WriteOutcome::DidNotWrite));
+    }
+
+    {
+        let mut progress = cluster.rebalance_progress.write();
+        if progress.status == RebalanceStatus::Running {
+            return Err(SpinelDBError::InvalidState(
+                "A cluster rebalance is already in progress".into(),
+            ));
+        }
+        *progress = RebalanceProgress {
+            status: RebalanceStatus::Running,
+            moves_total: moves.len(),
+            moves_done: 0,
+            slots_total: moves.iter().map(|m| m.slots.len()).sum(),
+            slots_moved: 0,
+            last_error: None,
+        };
+    }
+
+    let state_for_task = ctx.state.clone();
+    ctx.state
+        .critical_tasks
+        .lock()
+        .await
+        .spawn(run_rebalance(state_for_task, moves, atomic));
+
+    Ok((reply, WriteOutcome::DidNotWrite))
+}
+
+/// Returns the memory used by `node`, asking it with `INFO memory` unless it is this
+/// node.
+async fn used_memory(state: &Arc<ServerState>, node: &ClusterNode) -> Result<u64, SpinelDBError> {
+    let cluster = state.cluster.as_ref().unwrap();
+    if node.id == cluster.my_id {
+        return Ok(state
+            .dbs
+            .iter()
+            .map(|db| db.get_current_memory() as u64)
+            .sum());
+    }
+    let password = state.config.lock().await.password.clone();
+    let unreachable = |e: anyhow::Error| {
+        SpinelDBError::ClusterDown(format!(
+            "Could not read the memory used by node {}: {e}",
+            node.id
+        ))
+    };
+    let addr = node
+        .addr
+        .parse()
+        .map_err(|e: std::net::AddrParseError| unreachable(e.into()))?;
+    let mut client = ClusterClient::connect(addr).await.map_err(unreachable)?;
+    if let Some(password) = password {
+        client.authenticate(&password).await.map_err(unreachable)?;
+    }
+    client.used_memory().await.map_err(unreachable)
+}
+
+/// Runs each move of a rebalance plan in order, stopping at the first failure.
+async fn run_rebalance(state: Arc<ServerState>, moves: Vec<SlotMove>, atomic: bool) {
+    let Some(cluster) = state.cluster.as_ref() else {
+        return;
+    };
+    let total = moves.len();
+    for (i, slot_move) in moves.into_iter().enumerate() {
+        info!(
+            "[REBALANCE {}/{}] Moving {} slots from {} to {}.",
+            i + 1,
+            total,
+            slot_move.slots.len(),
+            slot_move.source,
+            slot_move.destination
+        );
+        let on_slot_migrated = |_slot: u16| {
+            cluster.rebalance_progress.write().slots_moved += 1;
+        };
+        if let Err(e) = run_reshard_orchestrator(
+            state.clone(),
+            slot_move.source,
+            slot_move.destination,
+            slot_move.slots,
+            atomic,
+            &on_slot_migrated,
+        )
+        .await
+        {
+            error!("[REBALANCE {}/{}] Failed: {}", i + 1, total, e);
+            let mut progress = cluster.rebalance_progress.write();
+            progress.status = RebalanceStatus::Failed;
+            progress.last_error = Some(e.to_string());
+            return;
+        }
+        cluster.rebalance_progress.write().moves_done += 1;
+    }
+    info!("[REBALANCE] Completed {} moves.", total);
+    cluster.rebalance_progress.write().status = RebalanceStatus::Done;
+}
@@ -27,6 +27,7 @@ pub async fn execute(
     source_node_id: &str,
     destination_node_id: &str,
     slots: &[u16],
+    atomic: bool,
 ) -> Result<(RespValue, WriteOutcome), SpinelDBError> {
     let state_for_task = ctx.state.clone();
     let source_clone = source_node_id.to_owned();
@@ -39,8 +40,15 @@ pub async fn execute(
             "Starting background resharding task: {:?} slots from {} to {}",
             slots_clone, source_clone, dest_clone
         );
-        if let Err(e) =
-            run_reshard_orchestrator(state_for_task, source_clone, dest_clone, slots_clone).await
+        if let Err(e) = run_reshard_orchestrator(
+            state_for_task,
+            source_clone,
+            dest_clone,
+            slots_clone,
+            atomic,
+            &|_| {},
+        )
+        .await
         {
             error!("Resharding task failed: {}", e);
         } else {
@@ -58,11 +66,16 @@ pub async fn execute(
 
 /// The main resharding orchestrator. It connects to all nodes and manages the
 /// multi-step process of migrating slots and keys using a connection pool.
-async fn run_reshard_orchestrator(
+/// With `atomic`, the source node migrates each slot as a whole instead, so that
+/// clients are never redirected with ASK. `on_slot_migrated` is called after each
+/// slot has been handed over.
+pub(super) async fn run_reshard_orchestrator(
     state: Arc<ServerState>,
     source_id: String,
     dest_id: String,
     slots: Vec<u16>,
+    atomic: bool,
+    on_slot_migrated: &(dyn Fn(u16) + Send + Sync),
 ) -> Result<(), anyhow::Error> {
     let cluster = state
         .cluster
@@ -161,84 +174,103 @@ async fn run_reshard_orchestrator(
             ])
             .await?;
 
-        // Temporarily borrow a client from the pool for admin commands.
-        let mut source_admin_client = client_rx.recv().await.unwrap();
-
-        info!(
-            "[RESHARD SLOT {}] Step 2/5: Setting slot to MIGRATING on source {}.",
-            slot, source_id
-        );
-        source_admin_client
-            .cluster_setslot(vec![
-                "SETSLOT".into(),
-                slot.to_string().into(),
-                "MIGRATING".into(),
-                dest_id.clone().into(),
-            ])
-            .await?;
+        if atomic {
+            // --- Step 3b (atomic): The source streams the slot and hands it over ---
+            info!(
+                "[RESHARD SLOT {}] Step 2/5: Migrating the slot atomically from {}.",
+                slot, source_id
+            );
+            let mut source_admin_client = client_rx.recv().await.unwrap();
+            let result = source_admin_client
+                .migrate_slot(slot, dest_id.clone())
+                .await;
+            let _ = client_tx.send(source_admin_client).await;
+            let keys_sent = result
+                .map_err(|e| anyhow!("Failed to migrate slot {slot}: {e}. Aborting reshard."))?;
+            info!(
+                "[RESHARD SLOT {}] Step 3/5: The source sent {} keys and handed over the slot.",
+                slot, keys_sent
+            );
+        } else {
+            // Temporarily borrow a client from the pool for admin commands.
+            let mut source_admin_client = client_rx.recv().await.unwrap();
 
-        // --- Step 3b: Migrate all keys in the slot concurrently ---
-        info!("[RESHARD SLOT {}] Step 3/5: Migrating keys...", slot);
-        loop {
-            let keys_to_move = source_admin_client
-                .get_keys_in_slot(slot, KEY_BATCH_SIZE)
+            info!(
+                "[RESHARD SLOT {}] Step 2/5: Setting slot to MIGRATING on source {}.",
+                slot, source_id
+            );
+            source_admin_client
+                .cluster_setslot(vec![
+                    "SETSLOT".into(),
+                    slot.to_string().into(),
+                    "MIGRATING".into(),
+                    dest_id.clone().into(),
+                ])
                 .await?;
-            if keys_to_move.is_empty() {
-                info!("[RESHARD SLOT {}] All keys have been migrated.", slot);
-                break;
-            }
 
-            // Create a future for each key migration.
-            let mut migration_tasks = Vec::new();
-            for key in keys_to_move {
-                // Acquire a client from the pool for this specific task.
-                let mut client = client_rx
-                    .recv()
-                    .await
-                    .ok_or_else(|| anyhow!("Client pool was closed unexpectedly"))?;
-                let tx = client_tx.clone();
-                let dest_node_clone = dest_node.clone();
-
-                let task = async move {
-                    debug!(
-                        "[RESHARD SLOT {}] Migrating key: {}",
-                        slot,
-                        String::from_utf8_lossy(&key)
-                    );
-                    let dest_host = dest_node_clone.addr.split(':').next().unwrap().to_string();
-                    let dest_port = dest_node_clone
-                        .addr
-                        .split(':')
-                        .next_back()
-                        .unwrap()
-                        .parse()?;
-
-                    let result = client.migrate_key(dest_host, dest_port, key, 0, 5000).await;
-
-                    // Return the client to the pool.
-                    let _ = tx.send(client).await;
-                    result
-                };
-                migration_tasks.push(task);
-            }
+            // --- Step 3b: Migrate all keys in the slot concurrently ---
+            info!("[RESHARD SLOT {}] Step 3/5: Migrating keys...", slot);
+            loop {
+                let keys_to_move = source_admin_client
+                    .get_keys_in_slot(slot, KEY_BATCH_SIZE)
+                    .await?;
+                if keys_to_move.is_empty() {
+                    info!("[RESHARD SLOT {}] All keys have been migrated.", slot);
+                    break;
+                }
+
+                // Create a future for each key migration.
+                let mut migration_tasks = Vec::new();
+                for key in keys_to_move {
+                    // Acquire a client from the pool for this specific task.
+                    let mut client = client_rx
+                        .recv()
+                        .await
+                        .ok_or_else(|| anyhow!("Client pool was closed unexpectedly"))?;
+                    let tx = client_tx.clone();
+                    let dest_node_clone = dest_node.clone();
+
+                    let task = async move {
+                        debug!(
+                            "[RESHARD SLOT {}] Migrating key: {}",
+                            slot,
+                            String::from_utf8_lossy(&key)
+                        );
+                        let dest_host = dest_node_clone.addr.split(':').next().unwrap().to_string();
+                        let dest_port = dest_node_clone
+                            .addr
+                            .split(':')
+                            .next_back()
+                            .unwrap()
+                            .parse()?;
+
+                        let result = client.migrate_key(dest_host, dest_port, key, 0, 5000).await;
+
+                        // Return the client to the pool.
+                        let _ = tx.send(client).await;
+                        result
+                    };
+                    migration_tasks.push(task);
+                }
 
-            // Wait for all migrations in the current batch to complete.
-            let results = join_all(migration_tasks).await;
-
-            // If any migration fails, abort the entire resharding process.
-            for result in results {
-                if let Err(e) = result {
-                    // Return the admin client to the pool before erroring out.
-                    let _ = client_tx.send(source_admin_client).await;
-                    return Err(anyhow!(
-                        "Failed to migrate a key in slot {slot}: {e}. Aborting reshard."
-                    ));
+                // Wait for all migrations in the current batch to complete.
+                let results = join_all(migration_tasks).await;
+
+                // If any migration fails, abort the entire resharding process.
+                for result in results {
+                    if let Err(e) = result {
+                        // Return the admin client to the pool before erroring out.
+                        let _ = client_tx.send(source_admin_client).await;
+                        return Err(anyhow!(
+                            "Failed to migrate a key in slot {slot}: {e}. Aborting reshard."
+                        ));
+                    }
                 }
             }
-        }
 
-        // Return the admin client to the source pool.
-        let _ = client_tx.send(source_admin_client).await;
+            // Return the admin client to the source pool.
+            let _ = client_tx.send(source_admin_client).await;
+        }
 
         // --- Step 3c: Finalize the slot ownership change across the cluster ---
         info!(
@@ -276,6 +308,7 @@ async fn run_reshard_orchestrator(
         );
         cluster.save_config().await?;
         info!("[RESHARD SLOT {}] Resharding complete for this slot.", slot);
+        on_slot_migrated(slot);
     }
 
     Ok(())
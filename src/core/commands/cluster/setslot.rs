@@ -37,30 +37,7 @@ pub async fn execute(
                 .importing_slots
                 .insert(slot, src_node_id.clone());
         }
-        SetSlotSubcommand::Node(new_owner_id) => {
-            // Clear migration state from all nodes for this slot
-            for mut node in cluster.nodes.iter_mut() {
-                node.node_info.migrating_slots.remove(&slot);
-                node.node_info.importing_slots.remove(&slot);
-            }
-
-            // Remove slot from old owner
-            if let Some(id) = { cluster.slots_map[slot as usize].read().clone() }
-                && let Some(mut old_owner) = cluster.nodes.get_mut(&id)
-            {
-                old_owner.node_info.slots.remove(&slot);
-            }
-
-            // Assign slot to new owner
-            if let Some(mut new_owner) = cluster.nodes.get_mut(new_owner_id) {
-                new_owner.node_info.slots.insert(slot);
-                *cluster.slots_map[slot as usize].write() = Some(new_owner_id.clone());
-            } else {
-                return Err(SpinelDBError::InvalidState(format!(
-                    "Node {new_owner_id} not found"
-                )));
-            }
-        }
+        SetSlotSubcommand::Node(new_owner_id) => cluster.assign_slot(slot, new_owner_id)?,
         SetSlotSubcommand::Stable => {
             let mut myself = cluster.nodes.get_mut(&cluster.my_id).unwrap();
             myself.node_info.migrating_slots.remove(&slot);
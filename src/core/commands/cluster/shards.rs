@@ -0,0 +1,91 @@
+// src/core/commands/cluster/shards.rs
+
+use crate::core::cluster::{ClusterNode, NodeFlags};
+use crate::core::commands::command_trait::WriteOutcome;
+use crate::core::database::ExecutionContext;
+use crate::core::{RespValue, SpinelDBError};
+use std::collections::BTreeMap;
+
+/// Executes `CLUSTER SHARDS`. Each shard is a primary and its replicas, reported with
+/// the slot ranges it serves and the endpoint, role, and health of every node.
+pub async fn execute(
+    ctx: &mut ExecutionContext<'_>,
+) -> Result<(RespValue, WriteOutcome), SpinelDBError> {
+    let cluster = ctx.state.cluster.as_ref().unwrap();
+    let nodes_view: BTreeMap<String, ClusterNode> = cluster
+        .nodes
+        .iter()
+        .map(|entry| (entry.key().clone(), entry.value().node_info.clone()))
+        .collect();
+
+    let mut shards = Vec::new();
+    for primary in nodes_view
+        .values()
+        .filter(|n| n.get_flags().contains(NodeFlags::PRIMARY))
+    {
+        let mut slot_ranges = Vec::new();
+        for (start, end) in slot_ranges_of(primary) {
+            slot_ranges.push(RespValue::Integer(start as i64));
+            slot_ranges.push(RespValue::Integer(end as i64));
+        }
+
+        let mut nodes = vec![format_shard_node(primary, "master")?];
+        for replica in nodes_view
+            .values()
+            .filter(|n| n.replica_of.as_deref() == Some(primary.id.as_str()))
+        {
+            nodes.push(format_shard_node(replica, "replica")?);
+        }
+
+        shards.push(RespValue::Array(vec![
+            RespValue::BulkString("slots".into()),
+            RespValue::Array(slot_ranges),
+            RespValue::BulkString("nodes".into()),
+            RespValue::Array(nodes),
+        ]));
+    }
+
+    Ok((RespValue::Array(shards), WriteOutcome::DidNotWrite))
+}
+
+/// Collapses a node's slots into contiguous `(start, end)` ranges.
+fn slot_ranges_of(node: &ClusterNode) -> Vec<(u16, u16)> {
+    let mut ranges: Vec<(u16, u16)> = Vec::new();
+    for &slot in &node.slots {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == slot => *end = slot,
+            _ => ranges.push((slot, slot)),
+        }
+    }
+    ranges
+}
+
+fn format_shard_node(node: &ClusterNode, role: &str) -> Result<RespValue, SpinelDBError> {
+    let (ip, port) = node
+        .addr
+        .rsplit_once(':')
+        .ok_or(SpinelDBError::Internal("Invalid node address".into()))?;
+    let port: i64 = port.parse()?;
+    let flags = node.get_flags();
+    let health = if flags.intersects(NodeFlags::FAIL | NodeFlags::PFAIL) {
+        "fail"
+    } else {
+        "online"
+    };
+    Ok(RespValue::Array(vec![
+        RespValue::BulkString("id".into()),
+        RespValue::BulkString(node.id.clone().into()),
+        RespValue::BulkString("port".into()),
+        RespValue::Integer(port),
+        RespValue::BulkString("ip".into()),
+        RespValue::BulkString(ip.to_string().into()),
+        RespValue::BulkString("endpoint".into()),
+        RespValue::BulkString(ip.to_string().into()),
+        RespValue::BulkString("role".into()),
+        RespValue::BulkString(role.to_string().into()),
+        RespValue::BulkString("replication-offset".into()),
+        RespValue::Integer(node.replication_offset as i64),
+        RespValue::BulkString("health".into()),
+        RespValue::BulkString(health.into()),
+    ]))
+}
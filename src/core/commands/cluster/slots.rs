@@ -74,10 +74,12 @@ fn format_slot_range(
     let mut node_infos = Vec::new();
     if let Some(primary) = nodes_view.get(master_id) {
         node_infos.push(format_node_info(primary)?);
-        for replica in nodes_view
-            .values()
-            .filter(|n| n.replica_of.as_deref() == Some(master_id))
-        {
+        // Replicas are listed so that READONLY clients can route reads to them,
+        // but only while they are reachable.
+        for replica in nodes_view.values().filter(|n| {
+            n.replica_of.as_deref() == Some(master_id)
+                && !n.get_flags().intersects(NodeFlags::FAIL | NodeFlags::PFAIL)
+        }) {
             node_infos.push(format_node_info(replica)?);
         }
     }
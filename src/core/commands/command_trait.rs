@@ -33,6 +33,9 @@ bitflags! {
         const MOVABLEKEYS    = 1 << 7;
         /// The command is a scripting command (e.g., `EVAL`).
         const SCRIPTING      = 1 << 8;
+        /// The command can neither be called from a script nor queued in a transaction,
+        /// e.g. because it blocks the client (`WAIT`).
+        const NO_SCRIPT      = 1 << 9;
     }
 }
 
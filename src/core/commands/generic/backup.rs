@@ -33,7 +33,8 @@ impl ExecutableCommand for Backup {
         &self,
         ctx: &mut ExecutionContext<'a>,
     ) -> Result<(RespValue, WriteOutcome), SpinelDBError> {
-        match spldb::save(&ctx.state.dbs, &self.path).await {
+        let policies = ctx.state.cache.policies.read().await.clone();
+        match spldb::save(&ctx.state.dbs, &policies, &self.path).await {
             Ok(_) => {
                 info!("Manual backup to '{}' completed successfully.", self.path);
                 Ok((
@@ -24,6 +24,8 @@ pub enum ClientSubcommand {
         lib_name: Option<String>,
         lib_ver: Option<String>,
     },
+    /// `CLIENT CROSSSLOT ON|OFF`, handled by the router as it changes session state.
+    CrossSlot(bool),
 }
 
 #[derive(Debug, Clone, Default)]
@@ -96,6 +98,18 @@ impl ParseCommand for Client {
                 }
                 ClientSubcommand::SetInfo { lib_name, lib_ver }
             }
+            "crossslot" => {
+                if args.len() != 2 {
+                    return Err(SpinelDBError::WrongArgumentCount(
+                        "CLIENT CROSSSLOT".to_string(),
+                    ));
+                }
+                match extract_string(&args[1])?.to_ascii_lowercase().as_str() {
+                    "on" => ClientSubcommand::CrossSlot(true),
+                    "off" => ClientSubcommand::CrossSlot(false),
+                    _ => return Err(SpinelDBError::SyntaxError),
+                }
+            }
             _ => return Err(SpinelDBError::UnknownCommand(format!("CLIENT {sub_str}"))),
         };
 
@@ -215,6 +229,10 @@ impl ExecutableCommand for Client {
                     ))
                 }
             }
+            // This subcommand changes connection state and is handled in `command_router`.
+            ClientSubcommand::CrossSlot(_) => Err(SpinelDBError::Internal(
+                "CLIENT CROSSSLOT should not be executed directly".into(),
+            )),
         }
     }
 }
@@ -261,6 +279,9 @@ impl CommandSpec for Client {
                     args.extend_from_slice(&["LIB-VER".into(), ver.clone().into()]);
                 }
             }
+            ClientSubcommand::CrossSlot(on) => {
+                args.extend_from_slice(&["CROSSSLOT".into(), if *on { "ON" } else { "OFF" }.into()])
+            }
         }
         args
     }
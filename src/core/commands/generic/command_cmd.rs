@@ -82,6 +82,9 @@ fn flags_to_resp_values(flags: CommandFlags) -> Vec<RespValue> {
     if flags.contains(CommandFlags::MOVABLEKEYS) {
         flag_values.push(RespValue::SimpleString("movablekeys".to_string()));
     }
+    if flags.contains(CommandFlags::NO_SCRIPT) {
+        flag_values.push(RespValue::SimpleString("noscript".to_string()));
+    }
     flag_values
 }
 
@@ -55,6 +55,7 @@ impl ExecutableCommand for Del {
         // Collect tasks to be performed after releasing the database locks.
         let mut post_lock_tasks: Vec<(Bytes, DataValue)> = Vec::new();
         let mut items_to_unlink: Vec<LazyFreeItem> = Vec::new();
+        let db_index = ctx.db_index();
 
         // --- Start of Locking Scope ---
         {
@@ -91,7 +92,7 @@ impl ExecutableCommand for Del {
 
                     if should_unlink {
                         // Send both key and value to the lazy-free manager.
-                        items_to_unlink.push((key.clone(), popped_value));
+                        items_to_unlink.push((db_index, key.clone(), popped_value));
                     }
                 }
             }
@@ -21,6 +21,17 @@ fn update_aggregated_outcome(current_outcome: &RwLock<WriteOutcome>, new_outcome
     *current = current.merge(new_outcome);
 }
 
+/// Rejects commands that cannot be called from a script.
+fn check_script_command(command: &Command) -> Result<(), SpinelDBError> {
+    if command.get_flags().contains(CommandFlags::NO_SCRIPT) {
+        return Err(SpinelDBError::InvalidState(format!(
+            "Command '{}' is not allowed from scripts",
+            command.name()
+        )));
+    }
+    Ok(())
+}
+
 /// Represents the EVAL command, which executes a Lua script.
 ///
 /// # WARNING: Transaction Usage
@@ -167,6 +178,7 @@ impl ExecutableCommand for Eval {
                                 resp_args.push(lua_value_to_resp_frame(val)?);
                             }
                             let command = Command::try_from(RespFrame::Array(resp_args))?;
+                            check_script_command(&command)?;
                             let mut temp_ctx = ExecutionContext {
                                 state,
                                 locks: db.determine_locks_for_command(&command).await,
@@ -174,6 +186,7 @@ impl ExecutableCommand for Eval {
                                 command: Some(command.clone()),
                                 session_id: call_session_id,
                                 authenticated_user: user,
+                                write_guard: None,
                             };
                             let (resp_val, outcome) = command.execute(&mut temp_ctx).await?;
                             update_aggregated_outcome(&aggregated_outcome, outcome);
@@ -200,6 +213,9 @@ impl ExecutableCommand for Eval {
                                 resp_args.push(lua_value_to_resp_frame(val)?);
                             }
                             let command = Command::try_from(RespFrame::Array(resp_args))?;
+                            if let Err(e) = check_script_command(&command) {
+                                return Ok(LuaValue::Table(lua_error_to_table(&lua, e)?));
+                            }
                             let mut temp_ctx = ExecutionContext {
                                 state,
                                 locks: db.determine_locks_for_command(&command).await,
@@ -207,6 +223,7 @@ impl ExecutableCommand for Eval {
                                 command: Some(command.clone()),
                                 session_id: pcall_session_id,
                                 authenticated_user: user,
+                                write_guard: None,
                             };
                             match command.execute(&mut temp_ctx).await {
                                 Ok((resp_val, outcome)) => {
@@ -10,12 +10,20 @@ use crate::core::protocol::RespFrame;
 use crate::core::{RespValue, SpinelDBError};
 use async_trait::async_trait;
 use bytes::Bytes;
+use std::sync::atomic::Ordering;
 use std::time::{SystemTime, UNIX_EPOCH};
 use tracing::warn;
 
 #[derive(Debug, Clone)]
 pub enum FailoverSubcommand {
-    Poison { run_id: String, ttl_secs: u64 },
+    Poison {
+        run_id: String,
+        ttl_secs: u64,
+    },
+    /// Fences this instance to a Warden failover epoch. Warden sends it before each
+    /// reconfiguration command, and an older epoch is refused so that a Warden that
+    /// lost a later election cannot undo the newer failover.
+    Epoch(u64),
 }
 
 #[derive(Debug, Clone, Default)]
@@ -46,6 +54,10 @@ impl ParseCommand for Failover {
                 let ttl_secs = extract_string(&args[2])?.parse()?;
                 FailoverSubcommand::Poison { run_id, ttl_secs }
             }
+            "epoch" => {
+                validate_arg_count(&args[1..], 1, "FAILOVER EPOCH")?;
+                FailoverSubcommand::Epoch(extract_string(&args[1])?.parse()?)
+            }
             _ => return Err(SpinelDBError::UnknownCommand("FAILOVER".to_string())),
         };
 
@@ -91,6 +103,31 @@ impl ExecutableCommand for Failover {
                     warn!("Failed to persist poisoned masters state to disk: {}", e);
                 }
 
+                Ok((
+                    RespValue::SimpleString("OK".into()),
+                    WriteOutcome::DidNotWrite,
+                ))
+            }
+            FailoverSubcommand::Epoch(epoch) => {
+                let replication = &ctx.state.replication;
+                // An equal epoch is accepted, as the leader reconnects for each command.
+                let previous = replication
+                    .failover_epoch
+                    .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |current| {
+                        (*epoch >= current).then_some(*epoch)
+                    })
+                    .map_err(|current| {
+                        SpinelDBError::InvalidState(format!(
+                            "Failover epoch {epoch} is older than the current epoch {current}"
+                        ))
+                    })?;
+
+                if previous != *epoch
+                    && let Err(e) = replication.save_poisoned_masters_to_disk()
+                {
+                    warn!("Failed to persist the failover epoch to disk: {}", e);
+                }
+
                 Ok((
                     RespValue::SimpleString("OK".into()),
                     WriteOutcome::DidNotWrite,
@@ -129,6 +166,7 @@ impl CommandSpec for Failover {
                 run_id.clone().into(),
                 ttl_secs.to_string().into(),
             ],
+            FailoverSubcommand::Epoch(epoch) => vec!["EPOCH".into(), epoch.to_string().into()],
         }
     }
 }
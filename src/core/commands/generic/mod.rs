@@ -36,6 +36,8 @@ pub mod publish;
 pub mod pubsub;
 pub mod punsubscribe;
 pub mod quit;
+pub mod readonly;
+pub mod readwrite;
 pub mod rename;
 pub mod renamenx;
 pub mod replconf;
@@ -47,13 +49,18 @@ pub mod select;
 pub mod shutdown;
 pub mod slowlog;
 pub mod sort;
+pub mod spublish;
+pub mod ssubscribe;
 pub mod subscribe;
+pub mod sunsubscribe;
 pub mod time;
 pub mod ttl;
 pub mod type_cmd;
 pub mod unlink;
 pub mod unsubscribe;
 pub mod unwatch;
+pub mod wait;
+pub mod waitaof;
 pub mod watch;
 
 // Re-export all command structs for easy access from the parent `commands` module.
@@ -92,6 +99,8 @@ pub use self::publish::Publish;
 pub use self::pubsub::PubSubInfo;
 pub use self::punsubscribe::PUnsubscribe;
 pub use self::quit::Quit;
+pub use self::readonly::ReadOnly;
+pub use self::readwrite::ReadWrite;
 pub use self::rename::Rename;
 pub use self::renamenx::RenameNx;
 pub use self::replconf::Replconf;
@@ -103,11 +112,16 @@ pub use self::select::Select;
 pub use self::shutdown::Shutdown;
 pub use self::slowlog::Slowlog;
 pub use self::sort::Sort;
+pub use self::spublish::SPublish;
+pub use self::ssubscribe::SSubscribe;
 pub use self::subscribe::Subscribe;
+pub use self::sunsubscribe::SUnsubscribe;
 pub use self::time::Time;
 pub use self::ttl::Ttl;
 pub use self::type_cmd::TypeInfo;
 pub use self::unlink::Unlink;
 pub use self::unsubscribe::Unsubscribe;
 pub use self::unwatch::Unwatch;
+pub use self::wait::Wait;
+pub use self::waitaof::WaitAof;
 pub use self::watch::Watch;
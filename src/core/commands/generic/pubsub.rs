@@ -17,6 +17,8 @@ pub enum PubSubSubcommand {
     Channels(Option<Bytes>),
     NumSub(Vec<Bytes>),
     NumPat,
+    ShardChannels(Option<Bytes>),
+    ShardNumSub(Vec<Bytes>),
 }
 
 impl Default for PubSubSubcommand {
@@ -63,6 +65,26 @@ impl ParseCommand for PubSubInfo {
                 }
                 PubSubSubcommand::NumPat
             }
+            "shardchannels" => {
+                if args.len() > 2 {
+                    return Err(SpinelDBError::WrongArgumentCount(
+                        "PUBSUB SHARDCHANNELS".to_string(),
+                    ));
+                }
+                let pattern = if args.len() > 1 {
+                    Some(extract_bytes(&args[1])?)
+                } else {
+                    None
+                };
+                PubSubSubcommand::ShardChannels(pattern)
+            }
+            "shardnumsub" => {
+                let channels = args[1..]
+                    .iter()
+                    .map(extract_bytes)
+                    .collect::<Result<_, _>>()?;
+                PubSubSubcommand::ShardNumSub(channels)
+            }
             _ => return Err(SpinelDBError::UnknownCommand(format!("PUBSUB {sub_str}"))),
         };
 
@@ -103,6 +125,27 @@ impl ExecutableCommand for PubSubInfo {
                 let count = pubsub.get_pattern_subscriber_count();
                 Ok((RespValue::Integer(count as i64), WriteOutcome::DidNotWrite))
             }
+            PubSubSubcommand::ShardChannels(pattern) => {
+                let channels: Vec<RespValue> = pubsub
+                    .get_all_shard_channels()
+                    .into_iter()
+                    .filter(|channel_name| match pattern {
+                        Some(p) => glob_match(p, channel_name),
+                        None => true,
+                    })
+                    .map(RespValue::BulkString)
+                    .collect();
+                Ok((RespValue::Array(channels), WriteOutcome::DidNotWrite))
+            }
+            PubSubSubcommand::ShardNumSub(channels) => {
+                let mut result = Vec::with_capacity(channels.len() * 2);
+                for channel_name in channels {
+                    let count = pubsub.get_shard_subscriber_count(channel_name);
+                    result.push(RespValue::BulkString(channel_name.clone()));
+                    result.push(RespValue::Integer(count as i64));
+                }
+                Ok((RespValue::Array(result), WriteOutcome::DidNotWrite))
+            }
         }
     }
 }
@@ -135,6 +178,8 @@ impl CommandSpec for PubSubInfo {
             PubSubSubcommand::Channels(_) => vec!["CHANNELS".into()],
             PubSubSubcommand::NumSub(_) => vec!["NUMSUB".into()],
             PubSubSubcommand::NumPat => vec!["NUMPAT".into()],
+            PubSubSubcommand::ShardChannels(_) => vec!["SHARDCHANNELS".into()],
+            PubSubSubcommand::ShardNumSub(_) => vec!["SHARDNUMSUB".into()],
         }
     }
 }
@@ -119,6 +119,7 @@ impl ExecutableCommand for Rename {
         };
 
         // After successfully moving the value, update the on-disk cache manifest if applicable.
+        let db_index = ctx.db_index();
         if let DataValue::HttpCache { variants, .. } = &value_to_move.data {
             for variant in variants.values() {
                 if let CacheBody::OnDisk { path, .. } = &variant.body {
@@ -126,6 +127,7 @@ impl ExecutableCommand for Rename {
                     ctx.state
                         .cache
                         .log_manifest(
+                            db_index,
                             self.source.clone(),
                             ManifestState::PendingDelete,
                             path.clone(),
@@ -133,10 +135,11 @@ impl ExecutableCommand for Rename {
                         .await?;
                     ctx.state
                         .cache
-                        .log_manifest(
+                        .log_manifest_commit(
+                            db_index,
                             self.destination.clone(),
-                            ManifestState::Committed,
                             path.clone(),
+                            &variant.metadata,
                         )
                         .await?;
                 }
@@ -149,15 +152,17 @@ impl ExecutableCommand for Rename {
             && val.size > auto_unlink_threshold
         {
             let state_clone = ctx.state.clone();
+            let db_index = ctx.db_index();
             let dest_key_clone = self.destination.clone();
             tokio::spawn(async move {
                 let send_timeout = Duration::from_secs(5);
                 if tokio::time::timeout(
                     send_timeout,
-                    state_clone
-                        .persistence
-                        .lazy_free_tx
-                        .send(vec![(dest_key_clone, val)]),
+                    state_clone.persistence.lazy_free_tx.send(vec![(
+                        db_index,
+                        dest_key_clone,
+                        val,
+                    )]),
                 )
                 .await
                 .is_err()
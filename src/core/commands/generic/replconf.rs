@@ -27,6 +27,31 @@ impl ParseCommand for Replconf {
         Ok(Replconf { args: str_args })
     }
 }
+impl Replconf {
+    /// Parses `REPLCONF ACK <offset> [FACK <aof-offset>]`, returning the acknowledged
+    /// replication offset and, if present, the offset fsynced to the replica's AOF.
+    pub fn ack_offsets(&self) -> Option<(u64, Option<u64>)> {
+        if !self.args.first()?.eq_ignore_ascii_case("ack") {
+            return None;
+        }
+        let offset = self.args.get(1)?.parse::<u64>().ok()?;
+        let aof_offset = match self.args.get(2) {
+            Some(flag) if flag.eq_ignore_ascii_case("fack") => {
+                self.args.get(3).and_then(|s| s.parse::<u64>().ok())
+            }
+            _ => None,
+        };
+        Some((offset, aof_offset))
+    }
+
+    /// Returns true if this is a `REPLCONF GETACK` request sent by a primary.
+    pub fn is_getack(&self) -> bool {
+        self.args
+            .first()
+            .is_some_and(|arg| arg.eq_ignore_ascii_case("getack"))
+    }
+}
+
 #[async_trait]
 impl ExecutableCommand for Replconf {
     async fn execute<'a>(
@@ -0,0 +1,90 @@
+// src/core/commands/generic/spublish.rs
+
+use crate::core::Command;
+use crate::core::commands::command_spec::CommandSpec;
+use crate::core::commands::command_trait::{
+    CommandFlags, ExecutableCommand, ParseCommand, WriteOutcome,
+};
+use crate::core::commands::helpers::{extract_bytes, validate_arg_count};
+use crate::core::database::ExecutionContext;
+use crate::core::events::UnitOfWork;
+use crate::core::protocol::RespFrame;
+use crate::core::{RespValue, SpinelDBError};
+use async_trait::async_trait;
+use bytes::Bytes;
+
+/// Publishes a message to a shard channel.
+///
+/// Unlike `PUBLISH`, the message is not broadcast to the whole cluster. The channel
+/// hashes to a slot, the router redirects the client to the slot's primary, and the
+/// message is delivered to local subscribers and to this node's replicas through the
+/// replication stream.
+#[derive(Debug, Clone, Default)]
+pub struct SPublish {
+    pub channel: Bytes,
+    pub message: Bytes,
+}
+
+impl ParseCommand for SPublish {
+    fn parse(args: &[RespFrame]) -> Result<Self, SpinelDBError> {
+        validate_arg_count(args, 2, "SPUBLISH")?;
+        Ok(SPublish {
+            channel: extract_bytes(&args[0])?,
+            message: extract_bytes(&args[1])?,
+        })
+    }
+}
+
+#[async_trait]
+impl ExecutableCommand for SPublish {
+    async fn execute<'a>(
+        &self,
+        ctx: &mut ExecutionContext<'a>,
+    ) -> Result<(RespValue, WriteOutcome), SpinelDBError> {
+        let local_receivers_count = ctx
+            .state
+            .pubsub
+            .publish_shard(&self.channel, self.message.clone());
+
+        // Replicas deliver the message to their own shard subscribers. The message is
+        // not written to the AOF, as it does not change the dataset.
+        ctx.state
+            .event_bus
+            .publish_to_replicas(UnitOfWork::Command(Box::new(Command::SPublish(
+                self.clone(),
+            ))));
+
+        Ok((
+            RespValue::Integer(local_receivers_count as i64),
+            WriteOutcome::DidNotWrite,
+        ))
+    }
+}
+
+impl CommandSpec for SPublish {
+    fn name(&self) -> &'static str {
+        "spublish"
+    }
+    fn arity(&self) -> i64 {
+        3
+    }
+    fn flags(&self) -> CommandFlags {
+        CommandFlags::PUBSUB | CommandFlags::NO_PROPAGATE
+    }
+    fn first_key(&self) -> i64 {
+        1
+    }
+    fn last_key(&self) -> i64 {
+        1
+    }
+    fn step(&self) -> i64 {
+        1
+    }
+    fn get_keys(&self) -> Vec<Bytes> {
+        // The channel is only a key for cluster routing. It never needs a shard lock.
+        vec![]
+    }
+    fn to_resp_args(&self) -> Vec<Bytes> {
+        vec![self.channel.clone(), self.message.clone()]
+    }
+}
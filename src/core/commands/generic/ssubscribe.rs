@@ -0,0 +1,68 @@
+// src/core/commands/generic/ssubscribe.rs
+
+use crate::core::commands::command_spec::CommandSpec;
+use crate::core::commands::command_trait::{
+    CommandFlags, ExecutableCommand, ParseCommand, WriteOutcome,
+};
+use crate::core::commands::helpers::extract_bytes;
+use crate::core::database::ExecutionContext;
+use crate::core::protocol::RespFrame;
+use crate::core::{RespValue, SpinelDBError};
+use async_trait::async_trait;
+use bytes::Bytes;
+
+#[derive(Debug, Clone, Default)]
+pub struct SSubscribe {
+    pub channels: Vec<Bytes>,
+}
+impl ParseCommand for SSubscribe {
+    fn parse(args: &[RespFrame]) -> Result<Self, SpinelDBError> {
+        if args.is_empty() {
+            return Err(SpinelDBError::WrongArgumentCount("SSUBSCRIBE".to_string()));
+        }
+        let channels = args
+            .iter()
+            .map(extract_bytes)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(SSubscribe { channels })
+    }
+}
+#[async_trait]
+impl ExecutableCommand for SSubscribe {
+    async fn execute<'a>(
+        &self,
+        _ctx: &mut ExecutionContext<'a>,
+    ) -> Result<(RespValue, WriteOutcome), SpinelDBError> {
+        // This command is handled by the connection router to switch to Pub/Sub mode.
+        Err(SpinelDBError::Internal(
+            "SSUBSCRIBE command should not be executed directly".into(),
+        ))
+    }
+}
+impl CommandSpec for SSubscribe {
+    fn name(&self) -> &'static str {
+        "ssubscribe"
+    }
+    fn arity(&self) -> i64 {
+        -2
+    }
+    fn flags(&self) -> CommandFlags {
+        CommandFlags::PUBSUB | CommandFlags::NO_PROPAGATE
+    }
+    fn first_key(&self) -> i64 {
+        1
+    }
+    fn last_key(&self) -> i64 {
+        -1
+    }
+    fn step(&self) -> i64 {
+        1
+    }
+    fn get_keys(&self) -> Vec<Bytes> {
+        // The channels are only keys for cluster routing. They never need shard locks.
+        vec![]
+    }
+    fn to_resp_args(&self) -> Vec<Bytes> {
+        self.channels.clone()
+    }
+}
@@ -44,6 +44,7 @@ impl ExecutableCommand for Unlink {
     ) -> Result<(RespValue, WriteOutcome), SpinelDBError> {
         let mut count = 0u64;
         let mut items_to_reclaim: Vec<LazyFreeItem> = Vec::new();
+        let db_index = ctx.db_index();
         let mut post_lock_tasks: Vec<(Bytes, DataValue)> = Vec::new();
 
         // --- Start of Locking Scope ---
@@ -67,7 +68,7 @@ impl ExecutableCommand for Unlink {
                         count += 1;
                         // Defer notification and reclamation to avoid holding locks across await points.
                         post_lock_tasks.push((key.clone(), popped_value.data.clone()));
-                        items_to_reclaim.push((key.clone(), popped_value));
+                        items_to_reclaim.push((db_index, key.clone(), popped_value));
                     }
                 }
             }
@@ -0,0 +1,109 @@
+// src/core/commands/generic/wait.rs
+
+//! Implements the `WAIT` command, which blocks the client until all previous write
+//! commands have been acknowledged by at least the given number of replicas.
+
+use crate::config::ReplicationConfig;
+use crate::core::commands::command_spec::CommandSpec;
+use crate::core::commands::command_trait::{
+    CommandFlags, ExecutableCommand, ParseCommand, WriteOutcome,
+};
+use crate::core::commands::helpers::{extract_string, validate_arg_count};
+use crate::core::database::ExecutionContext;
+use crate::core::protocol::RespFrame;
+use crate::core::{RespValue, SpinelDBError};
+use async_trait::async_trait;
+use bytes::Bytes;
+use std::time::{Duration, Instant};
+
+/// Represents the `WAIT numreplicas timeout` command.
+#[derive(Debug, Clone, Default)]
+pub struct Wait {
+    pub num_replicas: usize,
+    /// The timeout in milliseconds. `0` blocks until enough replicas acknowledge.
+    pub timeout_ms: u64,
+}
+
+/// Parses a `WAIT`/`WAITAOF` numeric argument, rejecting negative values.
+pub(crate) fn parse_wait_arg<T: std::str::FromStr>(frame: &RespFrame) -> Result<T, SpinelDBError> {
+    extract_string(frame)?
+        .parse::<T>()
+        .map_err(|_| SpinelDBError::NotAnInteger)
+}
+
+/// Converts a `WAIT`/`WAITAOF` timeout into an absolute deadline.
+pub(crate) fn wait_deadline(timeout_ms: u64) -> Option<Instant> {
+    (timeout_ms > 0).then(|| Instant::now() + Duration::from_millis(timeout_ms))
+}
+
+impl ParseCommand for Wait {
+    fn parse(args: &[RespFrame]) -> Result<Self, SpinelDBError> {
+        validate_arg_count(args, 2, "WAIT")?;
+        Ok(Wait {
+            num_replicas: parse_wait_arg(&args[0])?,
+            timeout_ms: parse_wait_arg(&args[1])?,
+        })
+    }
+}
+
+#[async_trait]
+impl ExecutableCommand for Wait {
+    async fn execute<'a>(
+        &self,
+        ctx: &mut ExecutionContext<'a>,
+    ) -> Result<(RespValue, WriteOutcome), SpinelDBError> {
+        if matches!(
+            ctx.state.config.lock().await.replication,
+            ReplicationConfig::Replica { .. }
+        ) {
+            return Err(SpinelDBError::InvalidState(
+                "WAIT cannot be used with replica instances.".into(),
+            ));
+        }
+
+        let deadline = wait_deadline(self.timeout_ms);
+        let replication = &ctx.state.replication;
+        let target_offset = replication.current_write_offset(&ctx.state, deadline).await;
+        let acked = replication
+            .wait_for_replica_acks(
+                &ctx.state.replica_states,
+                target_offset,
+                self.num_replicas,
+                false,
+                deadline,
+            )
+            .await;
+
+        Ok((RespValue::Integer(acked as i64), WriteOutcome::DidNotWrite))
+    }
+}
+
+impl CommandSpec for Wait {
+    fn name(&self) -> &'static str {
+        "wait"
+    }
+    fn arity(&self) -> i64 {
+        3
+    }
+    fn flags(&self) -> CommandFlags {
+        CommandFlags::NO_PROPAGATE | CommandFlags::NO_SCRIPT
+    }
+    fn first_key(&self) -> i64 {
+        0
+    }
+    fn last_key(&self) -> i64 {
+        0
+    }
+    fn step(&self) -> i64 {
+        0
+    }
+    fn get_keys(&self) -> Vec<Bytes> {
+        vec![]
+    }
+    fn to_resp_args(&self) -> Vec<Bytes> {
+        vec![
+            self.num_replicas.to_string().into(),
+            self.timeout_ms.to_string().into(),
+        ]
+    }
+}
@@ -0,0 +1,133 @@
+// src/core/commands/generic/waitaof.rs
+
+//! Implements the `WAITAOF` command, which blocks the client until all previous write
+//! commands have been fsynced to the local AOF and/or the AOFs of a number of replicas.
+
+use super::wait::{parse_wait_arg, wait_deadline};
+use crate::config::ReplicationConfig;
+use crate::core::commands::command_spec::CommandSpec;
+use crate::core::commands::command_trait::{
+    CommandFlags, ExecutableCommand, ParseCommand, WriteOutcome,
+};
+use crate::core::commands::helpers::validate_arg_count;
+use crate::core::database::ExecutionContext;
+use crate::core::protocol::RespFrame;
+use crate::core::{RespValue, SpinelDBError};
+use async_trait::async_trait;
+use bytes::Bytes;
+use std::sync::atomic::Ordering;
+
+/// Represents the `WAITAOF numlocal numreplicas timeout` command.
+#[derive(Debug, Clone, Default)]
+pub struct WaitAof {
+    pub num_local: usize,
+    pub num_replicas: usize,
+    /// The timeout in milliseconds. `0` blocks until both conditions are met.
+    pub timeout_ms: u64,
+}
+
+impl ParseCommand for WaitAof {
+    fn parse(args: &[RespFrame]) -> Result<Self, SpinelDBError> {
+        validate_arg_count(args, 3, "WAITAOF")?;
+        let num_local = parse_wait_arg(&args[0])?;
+        if num_local > 1 {
+            return Err(SpinelDBError::InvalidRequest(
+                "WAITAOF numlocal must be 0 or 1".into(),
+            ));
+        }
+        Ok(WaitAof {
+            num_local,
+            num_replicas: parse_wait_arg(&args[1])?,
+            timeout_ms: parse_wait_arg(&args[2])?,
+        })
+    }
+}
+
+#[async_trait]
+impl ExecutableCommand for WaitAof {
+    async fn execute<'a>(
+        &self,
+        ctx: &mut ExecutionContext<'a>,
+    ) -> Result<(RespValue, WriteOutcome), SpinelDBError> {
+        if matches!(
+            ctx.state.config.lock().await.replication,
+            ReplicationConfig::Replica { .. }
+        ) {
+            return Err(SpinelDBError::InvalidState(
+                "WAITAOF cannot be used with replica instances.".into(),
+            ));
+        }
+
+        let aof_enabled = ctx.state.event_bus.is_aof_enabled();
+        if self.num_local > 0 && !aof_enabled {
+            return Err(SpinelDBError::InvalidState(
+                "WAITAOF cannot be used when numlocal is set but appendonly is disabled.".into(),
+            ));
+        }
+
+        let deadline = wait_deadline(self.timeout_ms);
+        let persistence = &ctx.state.persistence;
+        let replication = &ctx.state.replication;
+        let local_target = persistence.aof_enqueued_seq.load(Ordering::SeqCst);
+
+        let local_wait = async {
+            if aof_enabled {
+                persistence.wait_for_aof_fsync(local_target, deadline).await
+            } else {
+                false
+            }
+        };
+        let replica_wait = async {
+            let target_offset = replication.current_write_offset(&ctx.state, deadline).await;
+            replication
+                .wait_for_replica_acks(
+                    &ctx.state.replica_states,
+                    target_offset,
+                    self.num_replicas,
+                    true,
+                    deadline,
+                )
+                .await
+        };
+        let (local_synced, replicas_acked) = tokio::join!(local_wait, replica_wait);
+
+        Ok((
+            RespValue::Array(vec![
+                RespValue::Integer(local_synced as i64),
+                RespValue::Integer(replicas_acked as i64),
+            ]),
+            WriteOutcome::DidNotWrite,
+        ))
+    }
+}
+
+impl CommandSpec for WaitAof {
+    fn name(&self) -> &'static str {
+        "waitaof"
+    }
+    fn arity(&self) -> i64 {
+        4
+    }
+    fn flags(&self) -> CommandFlags {
+        CommandFlags::NO_PROPAGATE | CommandFlags::NO_SCRIPT
+    }
+    fn first_key(&self) -> i64 {
+        0
+    }
+    fn last_key(&self) -> i64 {
+        0
+    }
+    fn step(&self) -> i64 {
+        0
+    }
+    fn get_keys(&self) -> Vec<Bytes> {
+        vec![]
+    }
+    fn to_resp_args(&self) -> Vec<Bytes> {
+        vec![
+            self.num_local.to_string().into(),
+            self.num_replicas.to_string().into(),
+            self.timeout_ms.to_string().into(),
+        ]
+    }
+}
@@ -32,7 +32,7 @@ impl ExecutableCommand for HLen {
     ) -> Result<(RespValue, WriteOutcome), SpinelDBError> {
         let (_, shard_cache_guard) = ctx.get_single_shard_context_mut()?;
 
-        let resp = if let Some(entry) = shard_cache_guard.get_mut(&self.key) {
+        let resp = if let Some(entry) = shard_cache_guard.get(&self.key) {
             if entry.is_expired() {
                 shard_cache_guard.pop(&self.key); // Hapus pasif
                 RespValue::Integer(0)
@@ -32,7 +32,7 @@ impl ExecutableCommand for HmGet {
     ) -> Result<(RespValue, WriteOutcome), SpinelDBError> {
         let (_, shard_cache_guard) = ctx.get_single_shard_context_mut()?;
         let mut responses = Vec::with_capacity(self.fields.len());
-        if let Some(entry) = shard_cache_guard.get_mut(&self.key) {
+        if let Some(entry) = shard_cache_guard.get(&self.key) {
             if !entry.is_expired() {
                 if let DataValue::Hash(hash) = &entry.data {
                     for field in &self.fields {
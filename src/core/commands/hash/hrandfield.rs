@@ -77,7 +77,7 @@ impl ExecutableCommand for HRandField {
         ctx: &mut ExecutionContext<'a>,
     ) -> Result<(RespValue, WriteOutcome), SpinelDBError> {
         let (_, shard_cache_guard) = ctx.get_single_shard_context_mut()?;
-        if let Some(entry) = shard_cache_guard.get_mut(&self.key) {
+        if let Some(entry) = shard_cache_guard.get(&self.key) {
             if entry.is_expired() {
                 shard_cache_guard.pop(&self.key);
                 return Ok((
@@ -38,7 +38,7 @@ impl ExecutableCommand for HStrLen {
         let (_, shard_cache_guard) = ctx.get_single_shard_context_mut()?;
 
         // Logika dirapikan dan semua nilai integer dibungkus dengan RespValue.
-        let length = if let Some(entry) = shard_cache_guard.get_mut(&self.key) {
+        let length = if let Some(entry) = shard_cache_guard.get(&self.key) {
             if entry.is_expired() {
                 // Kunci ada tapi kedaluwarsa, sama seperti tidak ada.
                 0
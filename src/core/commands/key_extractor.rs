@@ -68,6 +68,10 @@ pub fn extract_keys_from_command(
 
         "zrangestore" => extract_n_keys(args, 2, 1, 1),
 
+        // --- Shard channels hash to slots like keys, for cluster routing ---
+        "spublish" => extract_n_keys(args, 1, 1, 1),
+        "ssubscribe" => extract_up_to_n_keys(args, args.len()),
+
         "bitop" => extract_bitop_keys(args),
         "migrate" => extract_migrate_keys(args),
 
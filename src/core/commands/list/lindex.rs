@@ -36,7 +36,7 @@ impl ExecutableCommand for LIndex {
     ) -> Result<(RespValue, WriteOutcome), SpinelDBError> {
         // Use consistent helper and handle passive expiration.
         let (_, shard_cache_guard) = ctx.get_single_shard_context_mut()?;
-        if let Some(entry) = shard_cache_guard.get_mut(&self.key) {
+        if let Some(entry) = shard_cache_guard.get(&self.key) {
             if entry.is_expired() {
                 // Passively delete expired key.
                 shard_cache_guard.pop(&self.key);
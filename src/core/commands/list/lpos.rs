@@ -99,7 +99,7 @@ impl ExecutableCommand for LPos {
         let (_, shard_cache_guard) = ctx.get_single_shard_context_mut()?;
 
         // First, check if the key exists and is a list.
-        let Some(entry) = shard_cache_guard.get_mut(&self.key) else {
+        let Some(entry) = shard_cache_guard.get(&self.key) else {
             // If the key does not exist, return Null or an empty array depending on COUNT.
             return Ok((
                 if self.count.is_some() {
@@ -81,12 +81,19 @@ define_commands! {
         (PSubscribe, PSubscribe, generic),
         (Unsubscribe, Unsubscribe, generic),
         (PUnsubscribe, PUnsubscribe, generic),
+        (SPublish, SPublish, generic),
+        (SSubscribe, SSubscribe, generic),
+        (SUnsubscribe, SUnsubscribe, generic),
         (Watch, Watch, generic),
         (Unwatch, Unwatch, generic),
+        (Wait, Wait, generic),
+        (WaitAof, WaitAof, generic),
         (Replconf, Replconf, generic),
         (Psync, Psync, generic),
         (Info, Info, generic),
         (Asking, Asking, generic),
+        (ReadOnly, ReadOnly, generic),
+        (ReadWrite, ReadWrite, generic),
         (BgRewriteAof, BgRewriteAof, generic),
         (Sort, Sort, generic),
         (Exists, Exists, generic),
@@ -45,7 +45,7 @@ impl ExecutableCommand for HScan {
         ctx: &mut ExecutionContext<'a>,
     ) -> Result<(RespValue, WriteOutcome), SpinelDBError> {
         let (_, guard) = ctx.get_single_shard_context_mut()?;
-        let (cursor, items) = if let Some(entry) = guard.get_mut(&self.key) {
+        let (cursor, items) = if let Some(entry) = guard.get(&self.key) {
             if entry.is_expired() {
                 // Return an empty result if the key is expired.
                 (0, vec![])
@@ -33,18 +33,18 @@ impl ExecutableCommand for SMIsMember {
         ctx: &mut ExecutionContext<'a>,
     ) -> Result<(RespValue, WriteOutcome), SpinelDBError> {
         let (_, shard_cache_guard) = ctx.get_single_shard_context_mut()?;
-        let set_ref: Option<&HashSet<Bytes>> =
-            if let Some(entry) = shard_cache_guard.get_mut(&self.key) {
-                if entry.is_expired() {
-                    None
-                } else if let DataValue::Set(set) = &entry.data {
-                    Some(set)
-                } else {
-                    return Err(SpinelDBError::WrongType);
-                }
-            } else {
+        let set_ref: Option<&HashSet<Bytes>> = if let Some(entry) = shard_cache_guard.get(&self.key)
+        {
+            if entry.is_expired() {
                 None
-            };
+            } else if let DataValue::Set(set) = &entry.data {
+                Some(set)
+            } else {
+                return Err(SpinelDBError::WrongType);
+            }
+        } else {
+            None
+        };
 
         let results: Vec<RespValue> = self
             .members
@@ -53,7 +53,7 @@ impl ExecutableCommand for BitCount {
     ) -> Result<(RespValue, WriteOutcome), SpinelDBError> {
         // Use consistent helper and handle passive expiration.
         let (_, shard_cache_guard) = ctx.get_single_shard_context_mut()?;
-        let count = if let Some(entry) = shard_cache_guard.get_mut(&self.key) {
+        let count = if let Some(entry) = shard_cache_guard.get(&self.key) {
             if entry.is_expired() {
                 shard_cache_guard.pop(&self.key);
                 0
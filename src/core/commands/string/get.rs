@@ -35,7 +35,7 @@ impl ExecutableCommand for Get {
         let (_, shard_cache_guard) = ctx.get_single_shard_context_mut()?;
 
         // `get_mut` from the guard will automatically update LFU/LRU info.
-        if let Some(entry) = shard_cache_guard.get_mut(&self.key) {
+        if let Some(entry) = shard_cache_guard.get(&self.key) {
             if entry.is_expired() {
                 // To avoid borrowing issues, we get the key again to pop it.
                 let key_clone = self.key.clone();
@@ -10,6 +10,7 @@ use crate::core::storage::data_types::DataValue;
 use crate::core::{RespValue, SpinelDBError};
 use async_trait::async_trait;
 use bytes::Bytes;
+use std::collections::BTreeMap;
 
 #[derive(Debug, Clone, Default)]
 pub struct MGet {
@@ -30,32 +31,31 @@ impl ExecutableCommand for MGet {
         &self,
         ctx: &mut ExecutionContext<'a>,
     ) -> Result<(RespValue, WriteOutcome), SpinelDBError> {
-        let mut responses = Vec::with_capacity(self.keys.len());
-        if let ExecutionLocks::Multi { guards } = &ctx.locks {
-            for key in &self.keys {
-                let shard_index = ctx.db.get_shard_index(key);
-                let value = if let Some(guard) = guards.get(&shard_index) {
-                    if let Some(entry) = guard.peek(key) {
-                        if !entry.is_expired() {
-                            match &entry.data {
-                                DataValue::String(s) => RespValue::BulkString(s.clone()),
-                                _ => RespValue::Null,
-                            }
-                        } else {
-                            RespValue::Null
-                        }
-                    } else {
-                        RespValue::Null
-                    }
-                } else {
-                    RespValue::Null
-                };
-                responses.push(value);
+        let guards = match std::mem::replace(&mut ctx.locks, ExecutionLocks::None) {
+            ExecutionLocks::Multi { guards } => guards,
+            ExecutionLocks::Single { shard_index, guard } => {
+                let mut map = BTreeMap::new();
+                map.insert(shard_index, guard);
+                map
+            }
+            _ => {
+                return Err(SpinelDBError::Internal(
+                    "MGET requires appropriate lock (Single or Multi)".into(),
+                ));
             }
-        } else {
-            return Err(SpinelDBError::Internal(
-                "MGET requires multi-shard lock".into(),
-            ));
+        };
+
+        let mut responses = Vec::with_capacity(self.keys.len());
+        for key in &self.keys {
+            let shard_index = ctx.db.get_shard_index(key);
+            let value = match guards.get(&shard_index).and_then(|guard| guard.peek(key)) {
+                Some(entry) if !entry.is_expired() => match &entry.data {
+                    DataValue::String(s) => RespValue::BulkString(s.clone()),
+                    _ => RespValue::Null,
+                },
+                _ => RespValue::Null,
+            };
+            responses.push(value);
         }
         Ok((RespValue::Array(responses), WriteOutcome::DidNotWrite))
     }
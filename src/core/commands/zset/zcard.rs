@@ -31,7 +31,7 @@ impl ExecutableCommand for ZCard {
         ctx: &mut ExecutionContext<'a>,
     ) -> Result<(RespValue, WriteOutcome), SpinelDBError> {
         let (_, shard_cache_guard) = ctx.get_single_shard_context_mut()?;
-        let len = if let Some(entry) = shard_cache_guard.get_mut(&self.key) {
+        let len = if let Some(entry) = shard_cache_guard.get(&self.key) {
             if entry.is_expired() {
                 shard_cache_guard.pop(&self.key);
                 0
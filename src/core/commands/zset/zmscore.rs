@@ -33,8 +33,7 @@ impl ExecutableCommand for ZMScore {
         ctx: &mut ExecutionContext<'a>,
     ) -> Result<(RespValue, WriteOutcome), SpinelDBError> {
         let (_, shard_cache_guard) = ctx.get_single_shard_context_mut()?;
-        let zset_ref: Option<&SortedSet> = if let Some(entry) = shard_cache_guard.get_mut(&self.key)
-        {
+        let zset_ref: Option<&SortedSet> = if let Some(entry) = shard_cache_guard.get(&self.key) {
             if entry.is_expired() {
                 None
             } else if let DataValue::SortedSet(zset) = &entry.data {
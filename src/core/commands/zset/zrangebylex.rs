@@ -68,7 +68,7 @@ impl ExecutableCommand for ZRangeByLex {
     ) -> Result<(RespValue, WriteOutcome), SpinelDBError> {
         let (_, shard_cache_guard) = ctx.get_single_shard_context_mut()?;
 
-        let Some(entry) = shard_cache_guard.get_mut(&self.key) else {
+        let Some(entry) = shard_cache_guard.get(&self.key) else {
             return Ok((RespValue::Array(vec![]), WriteOutcome::DidNotWrite));
         };
 
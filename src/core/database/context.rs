@@ -6,6 +6,7 @@ use super::shard::{DbShard, ShardCache};
 use crate::core::Command;
 use crate::core::SpinelDBError;
 use crate::core::acl::user::AclUser;
+use crate::core::cluster::state::ClusterWriteGuard;
 use crate::core::commands::command_trait::CommandExt;
 use crate::core::state::ServerState;
 use bytes::Bytes;
@@ -24,11 +25,19 @@ pub struct ExecutionContext<'a> {
     pub session_id: u64,
     /// The ACL user associated with the session, for permission checks.
     pub authenticated_user: Option<Arc<AclUser>>,
+    /// The cluster write admission of a blocking command, which it releases while it
+    /// waits. Other commands are admitted by the router for their whole execution.
+    pub write_guard: Option<ClusterWriteGuard>,
 }
 
 // --- Implementations for ExecutionContext ---
 
 impl<'a> ExecutionContext<'a> {
+    /// Returns the index of the database the command is executed against.
+    pub fn db_index(&self) -> usize {
+        self.state.db_index_of(self.db)
+    }
+
     /// A helper function to get the shard and its lock from the context.
     pub fn get_single_shard_context_mut(
         &mut self,
@@ -95,6 +104,21 @@ impl<'a> ExecutionContext<'a> {
         self.locks = ExecutionLocks::None;
     }
 
+    /// Releases the cluster write admission before the command blocks, so that a manual
+    /// failover or slot migration cutover does not wait for a client that may stay
+    /// blocked indefinitely. Returns true if an admission was held.
+    pub fn release_write_admission(&mut self) -> bool {
+        self.write_guard.take().is_some()
+    }
+
+    /// Admits the command as a cluster write again once it has been woken and must
+    /// write. Locks must not be held, as the admission may wait out a pause.
+    pub async fn reacquire_write_admission(&mut self, keys: &[Bytes]) {
+        if let Some(cluster) = &self.state.cluster {
+            self.write_guard = Some(cluster.begin_write(keys).await);
+        }
+    }
+
     /// Re-acquires all necessary locks for the command currently in the context.
     pub async fn reacquire_locks_for_command(&mut self) -> Result<(), SpinelDBError> {
         let command = self.command.as_ref().ok_or_else(|| {
@@ -62,6 +62,14 @@ impl Db {
             .sum()
     }
 
+    /// Returns the number of cache keys in the database. O(1) complexity.
+    pub fn get_cache_key_count(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|s| s.cache_key_count.load(Ordering::Relaxed))
+            .sum()
+    }
+
     /// Gets a list of keys belonging to a specific cluster slot using the slot index.
     pub async fn get_keys_in_slot(&self, slot: u16, count: usize) -> Vec<Bytes> {
         let mut keys_in_slot = Vec::with_capacity(count);
@@ -91,6 +99,21 @@ impl Db {
         keys_in_slot
     }
 
+    /// Counts the unexpired keys that belong to a specific cluster hash slot.
+    pub async fn count_keys_in_slot(&self, slot: u16) -> usize {
+        let mut count = 0;
+        for shard in &self.shards {
+            let guard = shard.entries.lock().await;
+            if let Some(keys_for_slot) = guard.slot_index.get(&slot) {
+                count += keys_for_slot
+                    .iter()
+                    .filter(|key| guard.peek(key).is_some_and(|v| !v.is_expired()))
+                    .count();
+            }
+        }
+        count
+    }
+
     /// Gets a reference to a shard by its index.
     pub fn get_shard(&self, index: usize) -> &Arc<DbShard> {
         &self.shards[index]
@@ -111,21 +134,6 @@ impl Db {
             .sum()
     }
 
-    /// Collects all key-value pairs for synchronization.
-    pub async fn get_all_kvs_for_sync(&self) -> Vec<(Bytes, StoredValue)> {
-        let all_guards = self.lock_all_shards().await;
-        let mut all_kvs = Vec::new();
-        for mut guard in all_guards {
-            all_kvs.extend(
-                guard
-                    .iter_mut()
-                    .filter(|(_, value)| !value.is_expired())
-                    .map(|(key, value)| (key.clone(), value.clone())),
-            );
-        }
-        all_kvs
-    }
-
     /// Gets a random sample of keys that might be expired for active deletion.
     pub async fn get_expired_sample_keys(&self, sample_size: usize) -> Vec<Bytes> {
         let mut rng = rand::rngs::SmallRng::from_entropy();
@@ -54,10 +54,10 @@ impl Db {
     }
 
     /// A helper to check if an evicted value was a cache item and, if so,
-    /// increments the global cache eviction statistic.
-    fn handle_cache_eviction_stat(state: &Arc<ServerState>, value: &StoredValue) {
+    /// increments the cache eviction statistics of this database.
+    fn handle_cache_eviction_stat(&self, state: &Arc<ServerState>, value: &StoredValue) {
         if matches!(value.data, DataValue::HttpCache { .. }) {
-            state.cache.increment_evictions();
+            state.cache.increment_evictions(state.db_index_of(self));
         }
     }
 
@@ -84,7 +84,7 @@ impl Db {
         let mut guard = self.get_shard(shard_index).entries.lock().await;
 
         if let Some((key, value)) = guard.pop_lru() {
-            Self::handle_cache_eviction_stat(state, &value);
+            self.handle_cache_eviction_stat(state, &value);
             Self::notify_waiters_on_eviction(state, &key, &value);
             debug!(
                 "Evicted LRU key '{}' from shard {}",
@@ -116,7 +116,7 @@ impl Db {
         if let Some(key) = key_to_evict
             && let Some(value) = guard.pop(&key)
         {
-            Self::handle_cache_eviction_stat(state, &value);
+            self.handle_cache_eviction_stat(state, &value);
             Self::notify_waiters_on_eviction(state, &key, &value);
             debug!(
                 "Evicted RANDOM key '{}' (volatile_only: {}) from shard {}.",
@@ -134,7 +134,7 @@ impl Db {
         if let Some((key, shard_index)) = self.find_volatile_lru_candidate().await {
             let mut guard = self.get_shard(shard_index).entries.lock().await;
             if let Some(value) = guard.pop(&key) {
-                Self::handle_cache_eviction_stat(state, &value);
+                self.handle_cache_eviction_stat(state, &value);
                 Self::notify_waiters_on_eviction(state, &key, &value);
                 debug!(
                     "Evicted VOLATILE-LRU key '{}' from shard {}.",
@@ -186,7 +186,7 @@ impl Db {
         if let Some((key_to_evict, _, shard_index)) = best_candidate {
             let mut guard = self.get_shard(shard_index).entries.lock().await;
             if let Some(value) = guard.pop(&key_to_evict) {
-                Self::handle_cache_eviction_stat(state, &value);
+                self.handle_cache_eviction_stat(state, &value);
                 Self::notify_waiters_on_eviction(state, &key_to_evict, &value);
                 debug!(
                     "Evicted VOLATILE-TTL key '{}' from shard {}.",
@@ -224,7 +224,7 @@ impl Db {
         if let Some((key_to_evict, _, shard_index)) = best_candidate {
             let mut guard = self.get_shard(shard_index).entries.lock().await;
             if let Some(value) = guard.pop(&key_to_evict) {
-                Self::handle_cache_eviction_stat(state, &value);
+                self.handle_cache_eviction_stat(state, &value);
                 Self::notify_waiters_on_eviction(state, &key_to_evict, &value);
                 debug!(
                     "Evicted LFU key '{}' (volatile_only: {}) from shard {}.",
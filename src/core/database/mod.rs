@@ -9,6 +9,9 @@ pub mod core;
 pub mod eviction;
 pub mod locking;
 pub mod shard;
+pub mod slot_tracking;
+pub mod snapshot;
+pub mod tiering;
 pub mod transaction;
 pub mod zset;
 
@@ -16,3 +19,4 @@ pub use self::core::{Db, NUM_SHARDS, PopDirection, PushDirection};
 pub use context::ExecutionContext;
 pub use locking::ExecutionLocks;
 pub use shard::{DbShard, ShardCache};
+pub use snapshot::{Snapshot, SnapshotReader};
@@ -4,13 +4,13 @@
 //! storage units within a `Db`.
 
 use crate::core::cluster::slot::get_slot;
-use crate::core::storage::data_types::StoredValue;
+use crate::core::storage::data_types::{DataValue, StoredValue};
 use bytes::Bytes;
 use lru::LruCache;
 use std::collections::{HashMap, HashSet};
 use std::num::NonZeroUsize;
-use std::sync::Arc;
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Weak};
 use tokio::sync::Mutex;
 
 /// Default capacity for the LRU cache within each shard.
@@ -28,6 +28,8 @@ pub struct DbShard {
     pub current_memory: Arc<AtomicUsize>,
     /// An atomic counter for the total number of keys in this shard.
     pub key_count: Arc<AtomicUsize>,
+    /// An atomic counter for the number of cache keys (`HttpCache` values) in this shard.
+    pub cache_key_count: Arc<AtomicUsize>,
 }
 
 /// A `ShardCache` wraps the `LruCache` and manages associated metadata like
@@ -44,6 +46,28 @@ pub struct ShardCache {
     memory_counter: Arc<AtomicUsize>,
     /// A shared atomic counter for the shard's total key count.
     key_counter: Arc<AtomicUsize>,
+    /// A shared atomic counter for the shard's cache key count.
+    cache_key_counter: Arc<AtomicUsize>,
+    /// Copy-on-write state for the snapshots that have not finished reading this shard.
+    snapshots: Vec<ShardSnapshot>,
+    /// For each slot being migrated atomically, the keys modified since tracking began.
+    slot_changes: HashMap<u16, HashSet<Bytes>>,
+}
+
+/// The copy-on-write state of one in-progress snapshot for a single shard.
+///
+/// Before a key is modified for the first time after the snapshot began, its
+/// original value is preserved here. The snapshot reads preserved values instead of
+/// live ones, so its memory overhead grows with concurrent writes, not dataset size.
+#[derive(Debug)]
+struct ShardSnapshot {
+    id: u64,
+    /// Becomes dangling when the owning snapshot is dropped, so that an abandoned
+    /// snapshot stops preserving values.
+    owner: Weak<()>,
+    /// The value each modified key had when the snapshot began. `None` means the key
+    /// did not exist at that point.
+    preserved: HashMap<Bytes, Option<StoredValue>>,
 }
 
 impl DbShard {
@@ -52,14 +76,17 @@ impl DbShard {
         let lru_capacity = NonZeroUsize::new(DEFAULT_SHARD_LRU_CAPACITY).unwrap();
         let current_memory = Arc::new(AtomicUsize::new(0));
         let key_count = Arc::new(AtomicUsize::new(0));
+        let cache_key_count = Arc::new(AtomicUsize::new(0));
         Self {
             entries: Mutex::new(ShardCache::new(
                 lru_capacity,
                 current_memory.clone(),
                 key_count.clone(),
+                cache_key_count.clone(),
             )),
             current_memory,
             key_count,
+            cache_key_count,
         }
     }
 
@@ -81,6 +108,7 @@ impl ShardCache {
         capacity: NonZeroUsize,
         memory_counter: Arc<AtomicUsize>,
         key_counter: Arc<AtomicUsize>,
+        cache_key_counter: Arc<AtomicUsize>,
     ) -> Self {
         Self {
             store: LruCache::new(capacity),
@@ -88,16 +116,26 @@ impl ShardCache {
             slot_index: HashMap::new(),
             memory_counter,
             key_counter,
+            cache_key_counter,
+            snapshots: Vec::new(),
+            slot_changes: HashMap::new(),
         }
     }
 
     /// Puts a key-value pair into the cache, handling all memory and key count accounting.
     /// It returns the old value if the key already existed.
     pub fn put(&mut self, key: Bytes, mut value: StoredValue) -> Option<StoredValue> {
+        self.before_write(&key);
         value.size = value.data.memory_usage();
         let new_item_mem = key.len() + value.size;
+        if is_cache_value(&value) {
+            self.cache_key_counter.fetch_add(1, Ordering::Relaxed);
+        }
 
         let old_value = self.store.put(key.clone(), value);
+        if old_value.as_ref().is_some_and(is_cache_value) {
+            self.cache_key_counter.fetch_sub(1, Ordering::Relaxed);
+        }
 
         if let Some(ref old) = old_value {
             // Key existed, calculate the memory difference.
@@ -118,10 +156,14 @@ impl ShardCache {
 
     /// Removes a key from the cache, returning the value if the key was present.
     pub fn pop(&mut self, key: &Bytes) -> Option<StoredValue> {
+        self.before_write(key);
         if let Some(popped_value) = self.store.pop(key) {
             let mem_to_free = key.len() + popped_value.size;
             self.update_memory(-(mem_to_free as isize));
             self.key_counter.fetch_sub(1, Ordering::Relaxed);
+            if is_cache_value(&popped_value) {
+                self.cache_key_counter.fetch_sub(1, Ordering::Relaxed);
+            }
             self.remove_key_from_tags(key);
 
             let slot = get_slot(key);
@@ -140,10 +182,16 @@ impl ShardCache {
 
     /// Removes and returns the least recently used item from the cache.
     pub fn pop_lru(&mut self) -> Option<(Bytes, StoredValue)> {
+        if let Some(key) = self.store.peek_lru().map(|(k, _)| k.clone()) {
+            self.before_write(&key);
+        }
         if let Some((k, v)) = self.store.pop_lru() {
             let mem_to_free = k.len() + v.size;
             self.update_memory(-(mem_to_free as isize));
             self.key_counter.fetch_sub(1, Ordering::Relaxed);
+            if is_cache_value(&v) {
+                self.cache_key_counter.fetch_sub(1, Ordering::Relaxed);
+            }
             self.remove_key_from_tags(&k);
 
             let slot = get_slot(&k);
@@ -176,11 +224,20 @@ impl ShardCache {
         if self.store.is_empty() {
             return;
         }
-        self.store.clear();
+        // Every key of a slot being migrated is removed.
+        for (slot, changes) in &mut self.slot_changes {
+            if let Some(keys) = self.slot_index.get(slot) {
+                changes.extend(keys.iter().cloned());
+            }
+        }
+        let empty = LruCache::new(self.store.cap());
+        let cleared = std::mem::replace(&mut self.store, empty);
+        self.preserve_cleared_for_snapshots(cleared);
         self.tag_index.clear();
         self.slot_index.clear();
         self.memory_counter.store(0, Ordering::Relaxed);
         self.key_counter.store(0, Ordering::Relaxed);
+        self.cache_key_counter.store(0, Ordering::Relaxed);
     }
 
     /// Gets a mutable reference to a value, inserting a default if it doesn't exist.
@@ -188,6 +245,7 @@ impl ShardCache {
     where
         F: FnOnce() -> StoredValue,
     {
+        self.before_write(&key);
         if self.store.get(&key).is_none() {
             let new_value = f();
             self.put(key.clone(), new_value);
@@ -197,6 +255,7 @@ impl ShardCache {
 
     /// Gets a mutable reference to a value, updating its LFU/LRU metadata.
     pub fn get_mut(&mut self, key: &Bytes) -> Option<&mut StoredValue> {
+        self.before_write(key);
         if let Some(entry) = self.store.get_mut(key) {
             entry.update_lfu();
             return Some(entry);
@@ -217,14 +276,132 @@ impl ShardCache {
         self.store.peek(key)
     }
 
+    /// Gets a mutable reference to a value without updating its LFU/LRU metadata.
+    pub fn peek_mut(&mut self, key: &Bytes) -> Option<&mut StoredValue> {
+        self.before_write(key);
+        self.store.peek_mut(key)
+    }
+
+    /// Recalculates the size of a value that was modified in place and updates the
+    /// memory counter accordingly. Returns the change in size.
+    pub fn resize(&mut self, key: &Bytes) -> isize {
+        let Some(entry) = self.store.peek_mut(key) else {
+            return 0;
+        };
+        let new_size = entry.data.memory_usage();
+        let diff = new_size as isize - entry.size as isize;
+        entry.size = new_size;
+        self.update_memory(diff);
+        diff
+    }
+
     /// Returns an iterator over the key-value pairs in the shard.
     pub fn iter(&self) -> lru::Iter<'_, Bytes, StoredValue> {
         self.store.iter()
     }
 
-    /// Returns a mutable iterator over the key-value pairs in the shard.
-    pub fn iter_mut(&mut self) -> lru::IterMut<'_, Bytes, StoredValue> {
-        self.store.iter_mut()
+    /// Starts tracking writes for the snapshot `id`. Must be called for every shard
+    /// while all of them are locked, so that the snapshot is point-in-time consistent.
+    pub(super) fn begin_snapshot(&mut self, id: u64, owner: Weak<()>) {
+        self.snapshots.push(ShardSnapshot {
+            id,
+            owner,
+            preserved: HashMap::new(),
+        });
+    }
+
+    /// Stops tracking writes for the snapshot `id` and releases its preserved values.
+    pub(super) fn end_snapshot(&mut self, id: u64) {
+        self.snapshots.retain(|s| s.id != id);
+    }
+
+    /// Returns the value `key` had when the snapshot `id` began.
+    pub(super) fn snapshot_value(&self, id: u64, key: &Bytes) -> Option<&StoredValue> {
+        match self
+            .snapshots
+            .iter()
+            .find(|s| s.id == id)
+            .and_then(|s| s.preserved.get(key))
+        {
+            Some(preserved) => preserved.as_ref(),
+            None => self.store.peek(key),
+        }
+    }
+
+    /// Returns the preserved values of the snapshot `id`, which include keys that
+    /// existed when the snapshot began but have since been removed.
+    pub(super) fn snapshot_preserved(
+        &self,
+        id: u64,
+    ) -> impl Iterator<Item = (&Bytes, &StoredValue)> {
+        self.snapshots
+            .iter()
+            .filter(move |s| s.id == id)
+            .flat_map(|s| s.preserved.iter())
+            .filter_map(|(key, value)| value.as_ref().map(|v| (key, v)))
+    }
+
+    /// Starts recording the keys of `slot` that are modified, for an atomic slot migration.
+    pub(super) fn begin_slot_tracking(&mut self, slot: u16) {
+        self.slot_changes.entry(slot).or_default();
+    }
+
+    /// Stops recording modifications to the keys of `slot`.
+    pub(super) fn end_slot_tracking(&mut self, slot: u16) {
+        self.slot_changes.remove(&slot);
+    }
+
+    /// Returns the keys of `slot` modified since the last call, and resets the record.
+    pub(super) fn take_slot_changes(&mut self, slot: u16) -> HashSet<Bytes> {
+        self.slot_changes
+            .get_mut(&slot)
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
+    /// Called before any modification of `key`.
+    fn before_write(&mut self, key: &Bytes) {
+        self.preserve_for_snapshots(key);
+        if !self.slot_changes.is_empty()
+            && let Some(changes) = self.slot_changes.get_mut(&get_slot(key))
+        {
+            changes.insert(key.clone());
+        }
+    }
+
+    /// Preserves the current value of `key` for every snapshot that has not seen a
+    /// write to it yet.
+    fn preserve_for_snapshots(&mut self, key: &Bytes) {
+        if self.snapshots.is_empty() {
+            return;
+        }
+        self.snapshots.retain(|s| s.owner.strong_count() > 0);
+        for snapshot in &mut self.snapshots {
+            if !snapshot.preserved.contains_key(key) {
+                snapshot
+                    .preserved
+                    .insert(key.clone(), self.store.peek(key).cloned());
+            }
+        }
+    }
+
+    /// Hands the values removed by `clear` to the snapshots that have not seen a write
+    /// to them yet. The values are moved rather than cloned, unless several snapshots
+    /// need them.
+    fn preserve_cleared_for_snapshots(&mut self, cleared: LruCache<Bytes, StoredValue>) {
+        self.snapshots.retain(|s| s.owner.strong_count() > 0);
+        let Some((last, others)) = self.snapshots.split_last_mut() else {
+            return;
+        };
+        for (key, value) in cleared {
+            for snapshot in others.iter_mut() {
+                snapshot
+                    .preserved
+                    .entry(key.clone())
+                    .or_insert_with(|| Some(value.clone()));
+            }
+            last.preserved.entry(key).or_insert(Some(value));
+        }
     }
 
     /// Removes a key from all tag indexes it may be a part of.
@@ -249,6 +426,18 @@ impl ShardCache {
         }
     }
 
+    /// Removes a key from the indexes of the given tags.
+    pub fn remove_tags_for_key(&mut self, key: &Bytes, tags: &[Bytes]) {
+        for tag in tags {
+            if let Some(keys) = self.tag_index.get_mut(tag) {
+                keys.remove(key);
+                if keys.is_empty() {
+                    self.tag_index.remove(tag);
+                }
+            }
+        }
+    }
+
     /// Returns all tags associated with a given key.
     pub fn get_tags_for_key(&self, key: &Bytes) -> Vec<Bytes> {
         self.tag_index
@@ -263,3 +452,8 @@ impl ShardCache {
             .collect()
     }
 }
+
+/// Returns `true` if `value` is counted as a cache key.
+fn is_cache_value(value: &StoredValue) -> bool {
+    matches!(value.data, DataValue::HttpCache { .. })
+}
@@ -0,0 +1,36 @@
+// src/core/database/slot_tracking.rs
+
+//! Records which keys of a cluster slot are modified while the slot is being
+//! migrated atomically, so that those changes can be replayed on the destination.
+//!
+//! Tracking hooks into the same write path as snapshots: every modification of a key
+//! in a tracked slot adds the key to its shard's change set, whichever command made it.
+
+use super::core::Db;
+use bytes::Bytes;
+use std::collections::HashSet;
+
+impl Db {
+    /// Starts recording modifications to the keys of `slot` in every shard.
+    pub async fn begin_slot_tracking(&self, slot: u16) {
+        for shard in &self.shards {
+            shard.entries.lock().await.begin_slot_tracking(slot);
+        }
+    }
+
+    /// Stops recording modifications to the keys of `slot`.
+    pub async fn end_slot_tracking(&self, slot: u16) {
+        for shard in &self.shards {
+            shard.entries.lock().await.end_slot_tracking(slot);
+        }
+    }
+
+    /// Returns the keys of `slot` modified since tracking began or since the last call.
+    pub async fn take_slot_changes(&self, slot: u16) -> HashSet<Bytes> {
+        let mut changes = HashSet::new();
+        for shard in &self.shards {
+            changes.extend(shard.entries.lock().await.take_slot_changes(slot));
+        }
+        changes
+    }
+}
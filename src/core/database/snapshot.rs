@@ -0,0 +1,157 @@
+// src/core/database/snapshot.rs
+
+//! Implements fork-free, point-in-time snapshots of the keyspace.
+//!
+//! A snapshot is started by briefly locking every shard of every database and
+//! registering copy-on-write state on each of them. From then on, the first write to
+//! any key preserves that key's original value in its shard. The snapshot is then
+//! read shard by shard in small chunks under short locks, serving preserved values in
+//! place of live ones. Writers are only blocked for the duration of one chunk, and the
+//! memory overhead is proportional to the number of keys written while the snapshot
+//! is in progress rather than to the size of the dataset.
+
+use super::core::{Db, NUM_SHARDS};
+use crate::core::storage::data_types::StoredValue;
+use bytes::Bytes;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// The maximum number of keys read from a shard while its lock is held.
+const SNAPSHOT_CHUNK_SIZE: usize = 1024;
+
+/// A source of unique snapshot IDs, so that concurrent snapshots can coexist.
+static NEXT_SNAPSHOT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// A point-in-time view of a set of databases.
+///
+/// Dropping a snapshot before it has been fully read is safe: shards that were not
+/// read yet stop preserving values on their next write.
+#[derive(Debug)]
+pub struct Snapshot {
+    id: u64,
+    dbs: Vec<Arc<Db>>,
+    key_counts: Vec<usize>,
+    /// Shards hold a weak reference to this, which tells them the snapshot is alive.
+    _owner: Arc<()>,
+}
+
+impl Snapshot {
+    /// Begins a snapshot of `dbs`. All shards of all databases are locked at once, so
+    /// the snapshot is consistent across databases.
+    pub async fn begin(dbs: &[Arc<Db>]) -> Self {
+        Self::begin_with(dbs, || {}).await
+    }
+
+    /// Like `begin`, but calls `at_cut` while every shard is still locked. Writers
+    /// propagate their work before releasing their locks, so anything `at_cut` reads
+    /// from the propagation pipeline lines up exactly with the snapshot.
+    pub async fn begin_with(dbs: &[Arc<Db>], at_cut: impl FnOnce()) -> Self {
+        let id = NEXT_SNAPSHOT_ID.fetch_add(1, Ordering::Relaxed);
+        let owner = Arc::new(());
+
+        let mut all_guards = Vec::with_capacity(dbs.len());
+        for db in dbs {
+            all_guards.push(db.lock_all_shards().await);
+        }
+        for guards in all_guards.iter_mut() {
+            for guard in guards.iter_mut() {
+                guard.begin_snapshot(id, Arc::downgrade(&owner));
+            }
+        }
+        let key_counts = dbs.iter().map(|db| db.get_key_count()).collect();
+        at_cut();
+        drop(all_guards);
+
+        Self {
+            id,
+            dbs: dbs.to_vec(),
+            key_counts,
+            _owner: owner,
+        }
+    }
+
+    /// Returns the number of databases in the snapshot.
+    pub fn db_count(&self) -> usize {
+        self.dbs.len()
+    }
+
+    /// Returns the number of keys database `db_index` held when the snapshot began,
+    /// including keys that had expired but were not removed yet.
+    pub fn key_count(&self, db_index: usize) -> usize {
+        self.key_counts[db_index]
+    }
+
+    /// Returns a reader over the keys of database `db_index`.
+    pub fn reader(&self, db_index: usize) -> SnapshotReader<'_> {
+        SnapshotReader {
+            snapshot: self,
+            db: &self.dbs[db_index],
+            shard_index: 0,
+            keys: Vec::new(),
+            position: 0,
+            started: false,
+        }
+    }
+}
+
+/// Reads the keys of one database of a `Snapshot` in chunks.
+#[derive(Debug)]
+pub struct SnapshotReader<'a> {
+    snapshot: &'a Snapshot,
+    db: &'a Arc<Db>,
+    shard_index: usize,
+    /// The keys of the current shard at the time it was first locked, sorted.
+    keys: Vec<Bytes>,
+    position: usize,
+    started: bool,
+}
+
+impl SnapshotReader<'_> {
+    /// Calls `f` with the next chunk of unexpired keys and their values as of the
+    /// snapshot. The shard lock is held while `f` runs, so `f` should only serialize.
+    /// Returns `Ok(false)` once every shard of the database has been read.
+    pub async fn next_chunk<F, E>(&mut self, mut f: F) -> Result<bool, E>
+    where
+        F: FnMut(&Bytes, &StoredValue) -> Result<(), E>,
+    {
+        let id = self.snapshot.id;
+        while self.shard_index < NUM_SHARDS {
+            let mut guard = self.db.shards[self.shard_index].entries.lock().await;
+
+            if !self.started {
+                self.keys = guard.iter().map(|(key, _)| key.clone()).collect();
+                self.keys.sort_unstable();
+                self.position = 0;
+                self.started = true;
+            }
+
+            if self.position < self.keys.len() {
+                let end = (self.position + SNAPSHOT_CHUNK_SIZE).min(self.keys.len());
+                for key in &self.keys[self.position..end] {
+                    if let Some(value) = guard.snapshot_value(id, key)
+                        && !value.is_expired()
+                    {
+                        f(key, value)?;
+                    }
+                }
+                self.position = end;
+                return Ok(true);
+            }
+
+            // Keys that existed when the snapshot began but were removed before this
+            // shard was first locked are only left in the preserved values.
+            for (key, value) in guard.snapshot_preserved(id) {
+                if self.keys.binary_search(key).is_err() && !value.is_expired() {
+                    f(key, value)?;
+                }
+            }
+            guard.end_snapshot(id);
+            drop(guard);
+
+            self.shard_index += 1;
+            self.keys = Vec::new();
+            self.started = false;
+        }
+        Ok(false)
+    }
+}
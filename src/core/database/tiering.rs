@@ -0,0 +1,429 @@
+// src/core/database/tiering.rs
+
+//! Moves cache bodies between memory and disk when tiered caching (`cache.tiered`) is
+//! enabled. Under memory pressure, the in-memory bodies of a cold cache key are written
+//! to `on_disk_path` instead of the key being evicted. When a key with on-disk bodies
+//! becomes hot again, they are read back into memory. Every file written or released
+//! is logged to the on-disk cache manifest, whose size limit is enforced by the
+//! on-disk eviction task.
+
+use super::core::{Db, NUM_SHARDS};
+use crate::core::SpinelDBError;
+use crate::core::commands::cache::encoding;
+use crate::core::state::ServerState;
+use crate::core::storage::cache_types::{CacheBody, CacheVariant, HttpMetadata, ManifestState};
+use crate::core::storage::data_types::{DataValue, StoredValue};
+use bytes::Bytes;
+use rand::{Rng, SeedableRng};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use std::time::Instant;
+use tokio::fs::OpenOptions;
+use tokio::io::AsyncWriteExt;
+use tracing::{debug, warn};
+use uuid::Uuid;
+use wildmatch::WildMatch;
+
+/// The number of shards with demotable keys sampled when looking for a key to demote.
+const DEMOTION_SAMPLE_SHARDS: usize = 5;
+/// The number of least recently used entries of a shard inspected for demotion, of any
+/// type, so that the shard lock is held for a bounded time.
+const DEMOTION_SAMPLE_KEYS: usize = 16;
+/// Bodies are only promoted while memory use stays below this share of `maxmemory`,
+/// so that a promotion does not immediately cause another demotion.
+const PROMOTION_MEMORY_HEADROOM: f64 = 0.9;
+
+/// Returns `true` if the body of `variant` can be moved to disk.
+fn is_demotable(variant: &CacheVariant) -> bool {
+    !variant.is_rendition()
+        && match &variant.body {
+            CacheBody::InMemory(body) => !body.is_empty(),
+            CacheBody::CompressedInMemory { .. } => true,
+            _ => false,
+        }
+}
+
+/// Returns `true` if `value` is a cache entry with at least one demotable body.
+fn has_demotable_body(value: &StoredValue) -> bool {
+    matches!(
+        &value.data,
+        DataValue::HttpCache { variants, .. } if variants.values().any(is_demotable)
+    )
+}
+
+/// Returns `true` if `a` and `b` share the same in-memory data, i.e. the body was not
+/// replaced in between.
+pub(crate) fn is_same_body(a: &CacheBody, b: &CacheBody) -> bool {
+    match (a, b) {
+        (CacheBody::InMemory(a), CacheBody::InMemory(b))
+        | (
+            CacheBody::CompressedInMemory { data: a, .. },
+            CacheBody::CompressedInMemory { data: b, .. },
+        ) => a.as_ptr() == b.as_ptr() && a.len() == b.len(),
+        _ => false,
+    }
+}
+
+/// Returns `true` if the on-disk file of `variant` is `path`.
+fn is_stored_at(variant: &CacheVariant, path: &Path) -> bool {
+    matches!(&variant.body, CacheBody::OnDisk { path: p, .. } if p == path)
+}
+
+impl Db {
+    /// Frees memory by demoting the bodies of a cold cache key to disk if tiered caching
+    /// is enabled, and otherwise by evicting a key according to the eviction policy.
+    /// Returns `true` if something was demoted or evicted. Must be called without
+    /// holding a shard lock of this database. Since demotion writes and syncs files,
+    /// this is only called by the background eviction manager; the write path of the
+    /// command router calls `evict_one_key` directly.
+    pub async fn free_memory(&self, state: &Arc<ServerState>) -> bool {
+        let tier_path = {
+            let config = state.config.lock().await;
+            (config.cache.tiered && !config.cache.on_disk_path.is_empty())
+                .then(|| config.cache.on_disk_path.clone())
+        };
+        if let Some(cache_path) = tier_path
+            && self.get_cache_key_count() > 0
+            && self.demote_cold_cache_key(state, &cache_path).await > 0
+        {
+            return true;
+        }
+        self.evict_one_key(state).await
+    }
+
+    /// Moves the in-memory bodies of a cold cache key to files in `cache_path`. The key
+    /// is chosen among the least recently used keys of a few shards, preferring the
+    /// least frequently used one. Returns the number of bytes of memory freed.
+    pub async fn demote_cold_cache_key(&self, state: &Arc<ServerState>, cache_path: &str) -> usize {
+        let Some((key, shard_index)) = self.find_demotion_candidate().await else {
+            return 0;
+        };
+        let shard = self.get_shard(shard_index);
+
+        // Copy the bodies, so that the files are written without holding the lock.
+        let bodies: Vec<(u64, CacheBody, HttpMetadata)> = {
+            let guard = shard.entries.lock().await;
+            match guard.peek(&key).map(|value| &value.data) {
+                Some(DataValue::HttpCache { variants, .. }) => variants
+                    .iter()
+                    .filter(|(_, variant)| is_demotable(variant))
+                    .map(|(hash, variant)| (*hash, variant.body.clone(), variant.metadata.clone()))
+                    .collect(),
+                _ => return 0,
+            }
+        };
+
+        let db_index = state.db_index_of(self);
+        let mut written = Vec::with_capacity(bodies.len());
+        for (hash, body, metadata) in bodies {
+            match write_body_file(state, db_index, &key, cache_path, &body, &metadata).await {
+                Ok((path, size)) => written.push((hash, body, path, size)),
+                Err(e) => warn!(
+                    "Failed to demote a body of cache key '{}' to disk: {}",
+                    String::from_utf8_lossy(&key),
+                    e
+                ),
+            }
+        }
+        if written.is_empty() {
+            return 0;
+        }
+
+        let mut released = Vec::new();
+        let freed = {
+            let mut guard = shard.entries.lock().await;
+            match guard.peek_mut(&key).map(|value| &mut value.data) {
+                Some(DataValue::HttpCache { variants, .. }) => {
+                    for (hash, body, path, size) in written {
+                        match variants.get_mut(&hash) {
+                            Some(variant) if is_same_body(&variant.body, &body) => {
+                                variant.body = CacheBody::OnDisk { path, size };
+                                // Renditions are only kept for in-memory bodies.
+                                encoding::remove_renditions(variants, hash);
+                            }
+                            // The body was replaced or removed while it was written.
+                            _ => released.push(path),
+                        }
+                    }
+                }
+                _ => released.extend(written.into_iter().map(|(_, _, path, _)| path)),
+            }
+            -guard.resize(&key)
+        };
+
+        for path in released {
+            if let Err(e) = state
+                .cache
+                .log_manifest(db_index, key.clone(), ManifestState::PendingDelete, path)
+                .await
+            {
+                warn!("Failed to release an unused demoted cache file: {}", e);
+            }
+        }
+        if freed <= 0 {
+            return 0;
+        }
+        state.cache.increment_demotions(db_index);
+        debug!(
+            "Demoted cache key '{}' to disk, freeing {} bytes.",
+            String::from_utf8_lossy(&key),
+            freed
+        );
+        freed as usize
+    }
+
+    /// Finds the cache key to demote: the least frequently used of the cache keys with
+    /// in-memory bodies among the least recently used entries of a few shards, starting
+    /// at a random shard. Shards without cache keys are skipped without being locked.
+    async fn find_demotion_candidate(&self) -> Option<(Bytes, usize)> {
+        let mut rng = rand::rngs::SmallRng::from_entropy();
+        let first_shard = rng.gen_range(0..NUM_SHARDS);
+        let mut best: Option<(Bytes, u8, usize)> = None;
+        let mut sampled = 0;
+
+        for offset in 0..NUM_SHARDS {
+            let shard_index = (first_shard + offset) % NUM_SHARDS;
+            let shard = self.get_shard(shard_index);
+            if shard.cache_key_count.load(Ordering::Relaxed) == 0 {
+                continue;
+            }
+            let guard = shard.entries.lock().await;
+            // Iterate from the back (least recently used).
+            let Some((key, value)) = guard
+                .iter()
+                .rev()
+                .take(DEMOTION_SAMPLE_KEYS)
+                .filter(|(_, value)| has_demotable_body(value))
+                .min_by_key(|(_, value)| value.lfu.counter)
+            else {
+                continue;
+            };
+            if best
+                .as_ref()
+                .is_none_or(|(_, counter, _)| value.lfu.counter < *counter)
+            {
+                best = Some((key.clone(), value.lfu.counter, shard_index));
+            }
+            sampled += 1;
+            if sampled == DEMOTION_SAMPLE_SHARDS {
+                break;
+            }
+        }
+        best.map(|(key, _, shard_index)| (key, shard_index))
+    }
+
+    /// Returns when the variant of `key` stored in the on-disk file `path` was last
+    /// accessed, or `None` if no variant of the key is stored there.
+    pub async fn on_disk_body_last_access(&self, key: &Bytes, path: &Path) -> Option<Instant> {
+        let guard = self
+            .get_shard(self.get_shard_index(key))
+            .entries
+            .lock()
+            .await;
+        match &guard.peek(key)?.data {
+            DataValue::HttpCache { variants, .. } => variants
+                .values()
+                .find(|variant| is_stored_at(variant, path))
+                .map(|variant| variant.last_accessed),
+            _ => None,
+        }
+    }
+
+    /// Removes the variant of `key` stored in the on-disk file `path`, and the key if no
+    /// variants are left. Returns `true` if a variant was removed.
+    pub async fn remove_on_disk_body(&self, key: &Bytes, path: &Path) -> bool {
+        let mut guard = self
+            .get_shard(self.get_shard_index(key))
+            .entries
+            .lock()
+            .await;
+        let Some(DataValue::HttpCache { variants, .. }) = guard.peek(key).map(|value| &value.data)
+        else {
+            return false;
+        };
+        let Some(hash) = variants
+            .iter()
+            .find(|(_, variant)| is_stored_at(variant, path))
+            .map(|(hash, _)| *hash)
+        else {
+            return false;
+        };
+
+        let Some(DataValue::HttpCache { variants, .. }) =
+            guard.peek_mut(key).map(|value| &mut value.data)
+        else {
+            return false;
+        };
+        encoding::remove_variant(variants, hash);
+        if variants.is_empty() {
+            guard.pop(key);
+        } else {
+            guard.resize(key);
+        }
+        true
+    }
+}
+
+/// Writes `body` to a new file in `cache_path`, logging it to the manifest as pending
+/// and then as committed. Returns the path and size of the file.
+async fn write_body_file(
+    state: &Arc<ServerState>,
+    db_index: usize,
+    key: &Bytes,
+    cache_path: &str,
+    body: &CacheBody,
+    metadata: &HttpMetadata,
+) -> Result<(PathBuf, u64), SpinelDBError> {
+    let data = match body {
+        CacheBody::InMemory(data) => data.clone(),
+        // Files hold the body as served, so compressed bodies are decompressed.
+        CacheBody::CompressedInMemory { data, .. } => Bytes::from(zstd::decode_all(data.as_ref())?),
+        _ => {
+            return Err(SpinelDBError::Internal(
+                "Only in-memory cache bodies can be demoted".into(),
+            ));
+        }
+    };
+
+    tokio::fs::create_dir_all(cache_path).await?;
+    let path = PathBuf::from(cache_path).join(Uuid::new_v4().to_string());
+    state
+        .cache
+        .log_manifest(db_index, key.clone(), ManifestState::Pending, path.clone())
+        .await?;
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&path)
+        .await?;
+    file.write_all(&data).await?;
+    file.sync_all().await?;
+    state
+        .cache
+        .log_manifest_commit(db_index, key.clone(), path.clone(), metadata)
+        .await?;
+    Ok((path, data.len() as u64))
+}
+
+/// Moves the on-disk body `path` of the variant `variant_hash` of `key` back to memory
+/// in the background, if tiered caching is enabled and the key's access `frequency`
+/// has reached `cache.promotion_min_frequency`.
+pub fn schedule_promotion(
+    state: &Arc<ServerState>,
+    db_index: usize,
+    key: &Bytes,
+    variant_hash: u64,
+    path: &Path,
+    frequency: u8,
+) {
+    if !state.cache.promotions_in_flight.insert(path.to_path_buf()) {
+        return;
+    }
+    let state = state.clone();
+    let key = key.clone();
+    let path = path.to_path_buf();
+    tokio::spawn(async move {
+        if let Err(e) = promote(&state, db_index, &key, variant_hash, &path, frequency).await {
+            warn!(
+                "Failed to promote a body of cache key '{}' to memory: {}",
+                String::from_utf8_lossy(&key),
+                e
+            );
+        }
+        state.cache.promotions_in_flight.remove(&path);
+    });
+}
+
+/// Reads the on-disk body `path` into memory if it is eligible for promotion. Bodies at
+/// least `streaming_threshold_bytes` long, and bodies of keys whose policy sets
+/// `FORCE-DISK`, stay on disk.
+async fn promote(
+    state: &Arc<ServerState>,
+    db_index: usize,
+    key: &Bytes,
+    variant_hash: u64,
+    path: &Path,
+    frequency: u8,
+) -> Result<(), SpinelDBError> {
+    let (streaming_threshold, maxmemory) = {
+        let config = state.config.lock().await;
+        if !config.cache.tiered || frequency < config.cache.promotion_min_frequency {
+            return Ok(());
+        }
+        (config.cache.streaming_threshold_bytes, config.maxmemory)
+    };
+    let Some(db) = state.get_db(db_index) else {
+        return Ok(());
+    };
+
+    let size = tokio::fs::metadata(path).await?.len() as usize;
+    if size >= streaming_threshold {
+        return Ok(());
+    }
+    if let Some(maxmemory) = maxmemory.filter(|m| *m > 0) {
+        let used: usize = state.dbs.iter().map(|db| db.get_current_memory()).sum();
+        if (used + size) as f64 > maxmemory as f64 * PROMOTION_MEMORY_HEADROOM {
+            return Ok(());
+        }
+    }
+    {
+        let key_str = String::from_utf8_lossy(key);
+        let policies = state.cache.policies.read().await;
+        if policies
+            .iter()
+            .find(|p| WildMatch::new(&p.key_pattern).matches(&key_str))
+            .is_some_and(|p| p.force_disk)
+        {
+            return Ok(());
+        }
+    }
+
+    let body = {
+        let _permit = state
+            .cache
+            .on_disk_read_semaphore
+            .acquire()
+            .await
+            .map_err(|e| {
+                SpinelDBError::Internal(format!("Failed to acquire semaphore permit: {e}"))
+            })?;
+        Bytes::from(tokio::fs::read(path).await?)
+    };
+
+    let promoted = {
+        let mut guard = db.get_shard(db.get_shard_index(key)).entries.lock().await;
+        let promoted = match guard.peek_mut(key).map(|value| &mut value.data) {
+            Some(DataValue::HttpCache { variants, .. }) => match variants.get_mut(&variant_hash) {
+                Some(variant) if is_stored_at(variant, path) => {
+                    variant.body = CacheBody::InMemory(body);
+                    true
+                }
+                _ => false,
+            },
+            _ => false,
+        };
+        if promoted {
+            guard.resize(key);
+        }
+        promoted
+    };
+    if promoted {
+        state
+            .cache
+            .log_manifest(
+                db_index,
+                key.clone(),
+                ManifestState::PendingDelete,
+                path.to_path_buf(),
+            )
+            .await?;
+        state.cache.increment_promotions(db_index);
+        debug!(
+            "Promoted a body of cache key '{}' to memory.",
+            String::from_utf8_lossy(key)
+        );
+    }
+    Ok(())
+}
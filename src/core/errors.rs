@@ -101,6 +101,10 @@ pub enum SpinelDBError {
     #[error("Script timed out")]
     ScriptTimeout,
 
+    /// An origin is not accepting requests because its circuit breaker is open.
+    #[error("Origin unavailable: {0}")]
+    OriginUnavailable(String),
+
     // --- Cluster-specific errors ---
     /// A redirect error indicating that a key/slot has moved to a different node.
     #[error("MOVED {slot} {addr}")]
@@ -154,6 +158,7 @@ impl Clone for SpinelDBError {
             SpinelDBError::ConsumerGroupNotFound => SpinelDBError::ConsumerGroupNotFound,
             SpinelDBError::ReplicationLoopDetected => SpinelDBError::ReplicationLoopDetected,
             SpinelDBError::ScriptTimeout => SpinelDBError::ScriptTimeout,
+            SpinelDBError::OriginUnavailable(s) => SpinelDBError::OriginUnavailable(s.clone()),
             SpinelDBError::Moved { slot, addr } => SpinelDBError::Moved {
                 slot: *slot,
                 addr: addr.clone(),
@@ -189,6 +194,9 @@ impl PartialEq for SpinelDBError {
             (SpinelDBError::LockingError(s1), SpinelDBError::LockingError(s2)) => s1 == s2,
             (SpinelDBError::MigrationError(s1), SpinelDBError::MigrationError(s2)) => s1 == s2,
             (SpinelDBError::Internal(s1), SpinelDBError::Internal(s2)) => s1 == s2,
+            (SpinelDBError::OriginUnavailable(s1), SpinelDBError::OriginUnavailable(s2)) => {
+                s1 == s2
+            }
             (SpinelDBError::ClusterDown(s1), SpinelDBError::ClusterDown(s2)) => s1 == s2,
             (
                 SpinelDBError::Moved { slot: s1, addr: a1 },
@@ -7,6 +7,7 @@ use crate::core::Command;
 use crate::core::protocol::RespFrame;
 use crate::core::state::ServerState;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 use tokio::sync::{
     broadcast::{self, Sender as BroadcastSender},
     mpsc::{self, Sender as MpscSender, error::TrySendError},
@@ -93,6 +94,8 @@ pub struct EventBus {
     replication_sender: BroadcastSender<PropagatedWork>,
     /// An MPSC sender for AOF persistence (one-to-one).
     aof_sender: Option<MpscSender<PropagatedWork>>,
+    /// The number of work units successfully handed to the replication subscribers.
+    replication_published: AtomicU64,
 }
 
 impl EventBus {
@@ -110,6 +113,7 @@ impl EventBus {
         let bus = Self {
             replication_sender,
             aof_sender,
+            replication_published: AtomicU64::new(0),
         };
 
         (bus, aof_receiver)
@@ -122,31 +126,64 @@ impl EventBus {
         // Send to replication subscribers. It's okay if there are no active subscribers.
         if self.replication_sender.send(work.clone()).is_err() {
             debug!("Published a UnitOfWork with no active replication subscribers.");
+        } else {
+            self.replication_published.fetch_add(1, Ordering::SeqCst);
         }
 
-        if let Some(sender) = &self.aof_sender {
-            match sender.try_send(work) {
-                Ok(_) => {}
-                Err(TrySendError::Full(_)) => {
-                    let reason =
-                        "AOF channel is full. Persistence is lagging behind writes.".to_string();
-                    error!("{}", reason);
-                    state.set_read_only(true, &reason);
-                }
-                Err(TrySendError::Closed(_)) => {
-                    let reason = "AOF channel is closed. Persistence has stopped.".to_string();
-                    error!("{}", reason);
-                    state.set_read_only(true, &reason);
-                }
+        self.publish_to_aof(work.uow, state);
+    }
+
+    /// Publishes a `UnitOfWork` to the AOF only, bypassing replication. This is used by
+    /// replicas to persist the writes they apply from their primary.
+    pub fn publish_to_aof(&self, uow: UnitOfWork, state: &Arc<ServerState>) {
+        let Some(sender) = &self.aof_sender else {
+            return;
+        };
+        match sender.try_send(PropagatedWork { uow }) {
+            Ok(_) => {
+                state
+                    .persistence
+                    .aof_enqueued_seq
+                    .fetch_add(1, Ordering::SeqCst);
+            }
+            Err(TrySendError::Full(_)) => {
+                let reason =
+                    "AOF channel is full. Persistence is lagging behind writes.".to_string();
+                error!("{}", reason);
+                state.set_read_only(true, &reason);
+            }
+            Err(TrySendError::Closed(_)) => {
+                let reason = "AOF channel is closed. Persistence has stopped.".to_string();
+                error!("{}", reason);
+                state.set_read_only(true, &reason);
             }
         }
     }
 
+    /// Publishes a `UnitOfWork` to replication subscribers only, bypassing the AOF.
+    /// This is used for commands such as `SPUBLISH` that replicas must see but that
+    /// do not change the dataset.
+    pub fn publish_to_replicas(&self, uow: UnitOfWork) {
+        if self.replication_sender.send(PropagatedWork { uow }).is_ok() {
+            self.replication_published.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
     /// Provides a new receiver for a replication task to subscribe to updates.
     pub fn subscribe_for_replication(&self) -> broadcast::Receiver<PropagatedWork> {
         self.replication_sender.subscribe()
     }
 
+    /// Returns the number of work units delivered to replication subscribers so far.
+    pub fn replication_published(&self) -> u64 {
+        self.replication_published.load(Ordering::SeqCst)
+    }
+
+    /// Returns true if write commands are being persisted to the AOF.
+    pub fn is_aof_enabled(&self) -> bool {
+        self.aof_sender.is_some()
+    }
+
     /// Checks if the AOF channel has been closed.
     pub fn is_closed(&self) -> bool {
         self.aof_sender.as_ref().is_some_and(|s| s.is_closed())
@@ -35,6 +35,7 @@ pub async fn handle_auth(
                 {
                     session.is_authenticated = true;
                     session.authenticated_user = Some(user.clone().into());
+                    session.auth_password = Some(auth_cmd.password);
                     return Ok(RouteResponse::Single(RespValue::SimpleString("OK".into())));
                 }
             }
@@ -46,6 +47,7 @@ pub async fn handle_auth(
         // Legacy password authentication
         if *pass == auth_cmd.password {
             session.is_authenticated = true;
+            session.auth_password = Some(auth_cmd.password);
             Ok(RouteResponse::Single(RespValue::SimpleString("OK".into())))
         } else {
             tokio::time::sleep(std::time::Duration::from_millis(100)).await;
@@ -7,7 +7,6 @@ use crate::core::state::ServerState;
 use crate::core::{RespValue, SpinelDBError};
 use std::net::SocketAddr;
 use std::sync::Arc;
-use std::time::Instant;
 
 pub async fn handle_select(
     cmd: Select,
@@ -28,21 +27,47 @@ pub async fn handle_select(
     Ok(RouteResponse::Single(RespValue::SimpleString("OK".into())))
 }
 
+/// Handles `READONLY` and `READWRITE`, which toggle whether a cluster replica may
+/// serve this connection's reads.
+pub fn handle_readonly(
+    session: &mut SessionState,
+    state: &Arc<ServerState>,
+    readonly: bool,
+) -> Result<RouteResponse, SpinelDBError> {
+    if state.cluster.is_none() {
+        return Err(SpinelDBError::InvalidState(
+            "Cluster mode is not enabled.".into(),
+        ));
+    }
+    session.is_readonly = readonly;
+    Ok(RouteResponse::Single(RespValue::SimpleString("OK".into())))
+}
+
+/// Handles `CLIENT CROSSSLOT ON|OFF`, which controls whether multi-key commands
+/// spanning several slots are fanned out to the owning nodes.
+pub fn handle_cross_slot(
+    session: &mut SessionState,
+    state: &Arc<ServerState>,
+    enabled: bool,
+) -> Result<RouteResponse, SpinelDBError> {
+    if state.cluster.is_none() {
+        return Err(SpinelDBError::InvalidState(
+            "Cluster mode is not enabled.".into(),
+        ));
+    }
+    session.is_cross_slot = enabled;
+    Ok(RouteResponse::Single(RespValue::SimpleString("OK".into())))
+}
+
 pub async fn handle_replconf(
     cmd: &Replconf,
     state: &Arc<ServerState>,
     addr: &SocketAddr,
 ) -> Result<RouteResponse, SpinelDBError> {
-    if cmd
-        .args
-        .first()
-        .is_some_and(|a| a.eq_ignore_ascii_case("ack"))
-        && let Some(offset_str) = cmd.args.get(1)
-        && let Ok(offset) = offset_str.parse::<u64>()
-        && let Some(mut replica_state) = state.replica_states.get_mut(addr)
-    {
-        replica_state.value_mut().ack_offset = offset;
-        replica_state.value_mut().last_ack_time = Instant::now();
+    if let Some((offset, aof_offset)) = cmd.ack_offsets() {
+        state
+            .replication
+            .record_replica_ack(&state.replica_states, addr, offset, aof_offset);
     }
     Ok(RouteResponse::Single(RespValue::SimpleString("OK".into())))
 }
@@ -155,3 +155,72 @@ pub fn handle_punsubscribe(
     }
     Ok(RouteResponse::Multiple(responses))
 }
+
+/// Subscribes to shard channels. Cluster routing has already checked that all channels
+/// hash to the same slot, and that this node serves it.
+pub fn handle_ssubscribe(
+    channels: Vec<Bytes>,
+    session: &mut SessionState,
+    state: &Arc<ServerState>,
+    db: &Arc<Db>,
+    session_id: u64,
+) -> Result<RouteResponse, SpinelDBError> {
+    if session.is_in_transaction {
+        db.discard_transaction(session_id)?;
+        session.is_in_transaction = false;
+    }
+    if channels.is_empty() {
+        return Ok(RouteResponse::NoOp);
+    }
+    session.is_shard_subscribed = true;
+    let mut responses = Vec::with_capacity(channels.len());
+    for name in channels {
+        if session.subscribed_shard_channels.insert(name.clone()) {
+            let rx = state.pubsub.subscribe_shard(&name);
+            session
+                .pubsub_receivers
+                .push(SubscriptionReceiver::ShardChannel(name.clone(), rx));
+        }
+        responses.push(RespValue::Array(vec![
+            RespValue::BulkString("ssubscribe".into()),
+            RespValue::BulkString(name),
+            RespValue::Integer(session.subscribed_shard_channels.len() as i64),
+        ]));
+    }
+    Ok(RouteResponse::Multiple(responses))
+}
+
+pub fn handle_sunsubscribe(
+    channels: Vec<Bytes>,
+    session: &mut SessionState,
+) -> Result<RouteResponse, SpinelDBError> {
+    let to_process: Vec<Bytes> = if channels.is_empty() {
+        session.subscribed_shard_channels.iter().cloned().collect()
+    } else {
+        channels
+    };
+    let mut responses = Vec::new();
+    if to_process.is_empty() {
+        responses.push(RespValue::Array(vec![
+            RespValue::BulkString("sunsubscribe".into()),
+            RespValue::Null,
+            RespValue::Integer(0),
+        ]));
+    }
+    for name in to_process {
+        session.subscribed_shard_channels.remove(&name);
+        responses.push(RespValue::Array(vec![
+            RespValue::BulkString("sunsubscribe".into()),
+            RespValue::BulkString(name),
+            RespValue::Integer(session.subscribed_shard_channels.len() as i64),
+        ]));
+    }
+    session.pubsub_receivers.retain(|r| match r {
+        SubscriptionReceiver::ShardChannel(c, _) => session.subscribed_shard_channels.contains(c),
+        _ => true,
+    });
+    if session.subscribed_shard_channels.is_empty() {
+        session.is_shard_subscribed = false;
+    }
+    Ok(RouteResponse::Multiple(responses))
+}
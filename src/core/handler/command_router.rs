@@ -12,9 +12,12 @@ use super::pipeline::{acl_check, cluster_redirect, state_check};
 use super::safety_guard;
 use super::transaction_handler::TransactionHandler;
 use crate::connection::SessionState;
+use crate::core::cluster::fanout;
+use crate::core::cluster::state::ClusterWriteGuard;
 use crate::core::commands::cache::command::CacheSubcommand;
 use crate::core::commands::command_trait::{CommandExt, CommandFlags, WriteOutcome};
 use crate::core::commands::generic::Eval as EvalCmd;
+use crate::core::commands::generic::client::{Client, ClientSubcommand};
 use crate::core::commands::generic::script::ScriptSubcommand;
 use crate::core::commands::key_extractor;
 use crate::core::database::{Db, ExecutionContext};
@@ -28,6 +31,7 @@ use std::sync::Arc;
 use std::sync::atomic::Ordering;
 use std::time::Instant;
 use tokio::fs::File as TokioFile;
+use tokio::io::Take;
 use tokio::sync::OwnedSemaphorePermit;
 use tracing::error;
 use tracing::{Instrument, info_span};
@@ -38,10 +42,11 @@ pub enum RouteResponse {
     Single(RespValue),
     /// Multiple RESP values, sent sequentially. Used for commands like `SUBSCRIBE`.
     Multiple(Vec<RespValue>),
-    /// Streams a file body directly to the socket for high performance.
+    /// Streams a file body directly to the socket for high performance. The file is
+    /// positioned at the first byte to send and limited to the bytes to send.
     StreamBody {
         resp_header: Vec<u8>,
-        file: TokioFile,
+        file: Take<TokioFile>,
         _permit: OwnedSemaphorePermit,
     },
     /// No operation; no response should be sent to the client.
@@ -54,6 +59,8 @@ pub struct Router<'a> {
     session_id: u64,
     addr: SocketAddr,
     session: &'a mut SessionState,
+    /// The cluster write admission of the command being routed, if it is a write.
+    write_guard: Option<ClusterWriteGuard>,
 }
 
 impl<'a> Router<'a> {
@@ -69,6 +76,7 @@ impl<'a> Router<'a> {
             session_id,
             addr,
             session,
+            write_guard: None,
         }
     }
 
@@ -110,7 +118,7 @@ impl<'a> Router<'a> {
             client.id = %self.session_id,
         );
 
-        async move {
+        let result = async {
             let start_time = Instant::now();
             self.state.stats.increment_total_commands();
             metrics::COMMANDS_PROCESSED_TOTAL.inc();
@@ -124,13 +132,7 @@ impl<'a> Router<'a> {
                 return Ok(RouteResponse::Single(RespValue::SimpleString("OK".into())));
             }
 
-            // 2. Cluster Redirection Check: Return MOVED/ASK errors if the key is on another node.
-            cluster_redirect::check_redirection(&self.state, &keys_bytes, self.session).await?;
-            if self.session.is_asking {
-                self.session.is_asking = false; // ASKING is a one-shot command.
-            }
-
-            // 3. ACL Check: Verify user permissions.
+            // 2. ACL Check: Verify user permissions.
             acl_check::check_permissions(
                 &self.state,
                 self.session,
@@ -140,6 +142,44 @@ impl<'a> Router<'a> {
             )
             .await?;
 
+            // 3. Cluster Redirection Check: Return MOVED/ASK errors if the key is on another node.
+            // Writes first wait out a manual failover or slot migration cutover, so that they
+            // are redirected to the new owner once it has taken over. EXEC runs queued writes,
+            // and scripts may write. Unauthenticated clients are rejected without waiting.
+            let is_write = command
+                .get_flags()
+                .intersects(CommandFlags::WRITE | CommandFlags::SCRIPTING)
+                || matches!(command, Command::Exec);
+            self.write_guard = match &self.state.cluster {
+                Some(cluster_state) if is_write && self.session.is_authenticated => {
+                    Some(cluster_state.begin_write(&keys_bytes).await)
+                }
+                _ => None,
+            };
+            // A cross-slot client's multi-key commands are fanned out to the owning nodes
+            // instead, so they are never redirected.
+            let fan_out = self.state.cluster.is_some()
+                && self.session.is_cross_slot
+                && !self.session.is_in_transaction
+                && fanout::should_fan_out(&command, &keys_bytes);
+            // A replica serves reads for its primary's slots to clients that sent READONLY,
+            // and shard channel subscriptions, as SPUBLISH is replicated to it.
+            let replica_may_serve = (self.session.is_readonly
+                && command.get_flags().contains(CommandFlags::READONLY))
+                || matches!(command, Command::SSubscribe(_) | Command::SUnsubscribe(_));
+            if !fan_out {
+                cluster_redirect::check_redirection(
+                    &self.state,
+                    &keys_bytes,
+                    self.session,
+                    replica_may_serve,
+                )
+                .await?;
+            }
+            if self.session.is_asking {
+                self.session.is_asking = false; // ASKING is a one-shot command.
+            }
+
             // 4. Global State Check: Enforce read-only mode, min-replicas policy, etc.
             state_check::check_server_state(&self.state, &command).await?;
 
@@ -152,6 +192,8 @@ impl<'a> Router<'a> {
                 self.handle_unauthenticated(command).await
             } else if self.session.is_in_transaction {
                 self.handle_transaction_mode(command).await
+            } else if fan_out {
+                self.execute_fan_out(command).await
             } else {
                 self.handle_normal_command(command).await
             };
@@ -166,7 +208,9 @@ impl<'a> Router<'a> {
             result
         }
         .instrument(span)
-        .await
+        .await;
+        self.write_guard = None;
+        result
     }
 
     /// Handles commands when the session is not yet authenticated.
@@ -239,6 +283,15 @@ impl<'a> Router<'a> {
             Command::Select(cmd) => {
                 actions::connection::handle_select(cmd, self.session, &state, self.session_id).await
             }
+            Command::ReadOnly(_) => {
+                actions::connection::handle_readonly(self.session, &state, true)
+            }
+            Command::ReadWrite(_) => {
+                actions::connection::handle_readonly(self.session, &state, false)
+            }
+            Command::Client(Client {
+                subcommand: ClientSubcommand::CrossSlot(enabled),
+            }) => actions::connection::handle_cross_slot(self.session, &state, enabled),
 
             // Transaction control commands.
             Command::Multi => {
@@ -283,6 +336,16 @@ impl<'a> Router<'a> {
             Command::PUnsubscribe(cmd) => {
                 actions::pubsub::handle_punsubscribe(cmd.patterns, self.session)
             }
+            Command::SSubscribe(cmd) => actions::pubsub::handle_ssubscribe(
+                cmd.channels,
+                self.session,
+                &state,
+                &db,
+                self.session_id,
+            ),
+            Command::SUnsubscribe(cmd) => {
+                actions::pubsub::handle_sunsubscribe(cmd.channels, self.session)
+            }
 
             // Internal/Replication commands handled at the router level.
             Command::Replconf(ref cmd) => {
@@ -297,6 +360,64 @@ impl<'a> Router<'a> {
         }
     }
 
+    /// Executes a multi-key command spanning several slots by running a sub-request for
+    /// each slot on the node that owns it and merging the replies. Local sub-requests go
+    /// through the normal execution path, so writes are propagated as usual, unless
+    /// their slot is being migrated and their keys are no longer here, in which case
+    /// they follow the `ASK` redirect. Other nodes are asked as the session's user.
+    async fn execute_fan_out(&mut self, command: Command) -> Result<RouteResponse, SpinelDBError> {
+        let state = self.state.clone();
+        let cluster = state.cluster.as_ref().unwrap();
+        let db_index = self.session.current_db_index;
+        let db = state.get_db(db_index).unwrap();
+
+        let requests = fanout::scatter(cluster, &command)?;
+        let password = self.session.auth_password.clone();
+        let mut local = Vec::new();
+        let mut redirected = Vec::new();
+        for (index, request) in requests.iter().enumerate() {
+            if request.node_id != cluster.my_id {
+                continue;
+            }
+            let keys = request.command.get_keys();
+            match cluster_redirect::check_redirection(&state, &keys, self.session, false).await {
+                Ok(()) => local.push(index),
+                Err(e @ (SpinelDBError::Ask { .. } | SpinelDBError::Moved { .. })) => {
+                    redirected.push((index, RespValue::Error(e.to_string())))
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        let mut replies = vec![RespValue::Null; requests.len()];
+        let remote = fanout::send_remote(cluster, &requests, db_index, password.as_deref()).await?;
+        fanout::follow_redirects(
+            cluster,
+            &requests,
+            &mut redirected,
+            db_index,
+            password.as_deref(),
+        )
+        .await?;
+        for (index, reply) in remote.into_iter().chain(redirected) {
+            replies[index] = reply;
+        }
+        for index in local {
+            let request = &requests[index];
+            replies[index] = match self.execute_command(request.command.clone(), &db).await {
+                Ok(RouteResponse::Single(value)) => value,
+                Ok(_) => {
+                    return Err(SpinelDBError::Internal(
+                        "Unexpected response to a fanned-out sub-request".into(),
+                    ));
+                }
+                Err(e) => RespValue::Error(e.to_string()),
+            };
+        }
+
+        fanout::gather(&command, &requests, replies).map(RouteResponse::Single)
+    }
+
     /// Builds an `ExecutionContext` for a given command, acquiring the necessary locks.
     async fn build_exec_context(&self, command: &Command, db: &'a Arc<Db>) -> ExecutionContext<'a> {
         ExecutionContext {
@@ -306,6 +427,7 @@ impl<'a> Router<'a> {
             command: Some(command.clone()),
             session_id: self.session_id,
             authenticated_user: self.session.authenticated_user.clone(),
+            write_guard: None,
         }
     }
 
@@ -379,6 +501,8 @@ impl<'a> Router<'a> {
                     if total_memory < maxmem {
                         break;
                     }
+                    // Cold cache keys are only demoted to disk by the eviction manager,
+                    // so that a write never waits for a file to be written and synced.
                     if !db.evict_one_key(&self.state).await {
                         break;
                     }
@@ -386,8 +510,12 @@ impl<'a> Router<'a> {
             }
         }
 
-        // Build the execution context, which acquires the necessary locks.
+        // Build the execution context, which acquires the necessary locks. A blocking
+        // command takes over its write admission, to release it while it waits.
         let mut ctx = self.build_exec_context(&command, db).await;
+        if is_blocking(&command) {
+            ctx.write_guard = self.write_guard.take();
+        }
         let (resp_value, write_outcome) = command.execute(&mut ctx).await?;
 
         // If the command resulted in a write, handle propagation and statistics.
@@ -443,3 +571,16 @@ impl<'a> Router<'a> {
         Ok(RouteResponse::Single(resp_value))
     }
 }
+
+/// Returns true for the write commands that may block waiting for data.
+fn is_blocking(command: &Command) -> bool {
+    matches!(
+        command,
+        Command::BLPop(_)
+            | Command::BRPop(_)
+            | Command::BLMove(_)
+            | Command::BZPopMin(_)
+            | Command::BZPopMax(_)
+            | Command::XReadGroup(_)
+    )
+}
@@ -2,7 +2,7 @@
 
 // Declare the new actions submodule here, in the parent module file.
 mod actions;
-mod pipeline;
+pub(crate) mod pipeline;
 
 pub mod command_router;
 pub mod safety_guard;
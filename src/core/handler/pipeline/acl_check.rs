@@ -28,6 +28,12 @@ pub async fn check_permissions(
         return Err(SpinelDBError::NoPermission);
     }
 
+    // Shard channels are extracted like keys for cluster routing, but ACLs treat them
+    // as channels, not keys.
+    let keys_bytes = match command {
+        Command::SSubscribe(_) | Command::SPublish(_) => &[],
+        _ => keys_bytes,
+    };
     let keys_as_strings: Vec<String> = keys_bytes
         .iter()
         .map(|b| String::from_utf8_lossy(b).into_owned())
@@ -46,6 +52,12 @@ pub async fn check_permissions(
             .map(|b| String::from_utf8_lossy(b).into_owned())
             .collect(),
         Command::Publish(c) => vec![String::from_utf8_lossy(&c.channel).into_owned()],
+        Command::SSubscribe(c) => c
+            .channels
+            .iter()
+            .map(|b| String::from_utf8_lossy(b).into_owned())
+            .collect(),
+        Command::SPublish(c) => vec![String::from_utf8_lossy(&c.channel).into_owned()],
         _ => vec![],
     };
 
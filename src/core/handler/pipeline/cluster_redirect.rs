@@ -10,10 +10,13 @@ use bytes::Bytes;
 use std::sync::Arc;
 
 /// Checks if a command targeting specific keys should be redirected to another node.
+/// `replica_may_serve` allows a replica of the slot's owner to serve the command, e.g.
+/// a read sent by a `READONLY` client.
 pub async fn check_redirection(
     state: &Arc<ServerState>,
     keys: &[Bytes],
     session: &SessionState,
+    replica_may_serve: bool,
 ) -> Result<(), SpinelDBError> {
     let Some(cluster_state) = &state.cluster else {
         return Ok(());
@@ -68,6 +71,10 @@ pub async fn check_redirection(
         return Ok(());
     }
 
+    if replica_may_serve && cluster_state.i_replicate_slot_owner(first_slot) {
+        return Ok(());
+    }
+
     // Handle standard MOVED redirection if this node is not the slot owner.
     if let Some(owner_node) = cluster_state.get_node_for_slot(first_slot)
         && owner_node.node_info.id != *my_id
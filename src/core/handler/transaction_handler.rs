@@ -96,8 +96,9 @@ impl<'a> TransactionHandler<'a> {
                 }
             }
             _ if matches!(&command, Command::Watch(_))
-                || command.get_flags().contains(CommandFlags::TRANSACTION)
-                || command.get_flags().contains(CommandFlags::PUBSUB) =>
+                || command.get_flags().intersects(
+                    CommandFlags::TRANSACTION | CommandFlags::PUBSUB | CommandFlags::NO_SCRIPT,
+                ) =>
             {
                 tx_state.has_error = true;
                 return Ok(RespValue::Error(format!(
@@ -150,20 +151,14 @@ impl<'a> TransactionHandler<'a> {
             return Ok(RespValue::Array(vec![]));
         }
 
-        let (response, maybe_uow) = self.execute_transaction_atomically(tx_state).await?;
-
-        if let Some(uow) = maybe_uow {
-            self.state.event_bus.publish(uow, &self.state);
-        }
-
-        Ok(response)
+        self.execute_transaction_atomically(tx_state).await
     }
 
     /// The core logic for atomically executing a transaction.
     async fn execute_transaction_atomically(
         &mut self,
         tx_state: TransactionState,
-    ) -> Result<(RespValue, Option<UnitOfWork>), SpinelDBError> {
+    ) -> Result<RespValue, SpinelDBError> {
         let all_keys = self.collect_all_keys(&tx_state);
 
         // Perform cluster cross-slot check before acquiring locks.
@@ -193,15 +188,17 @@ impl<'a> TransactionHandler<'a> {
 
             // Check watched keys for modifications.
             if !self.check_watched_keys(&tx_state.watched_keys, &guards) {
-                return Ok((RespValue::NullArray, None)); // Abort transaction.
+                return Ok(RespValue::NullArray); // Abort transaction.
             }
 
             let (responses, write_commands, total_keys_changed, has_flush) = self
                 .execute_queued_commands(&tx_state.commands, &mut guards)
                 .await;
 
-            // Prepare the UnitOfWork for propagation if there were writes.
-            let maybe_uow = if !write_commands.is_empty() || has_flush {
+            // Propagate the transaction if there were writes. This happens before the
+            // locks are released, like for single commands, so that a snapshot never
+            // sees the writes without them having been enqueued for the AOF.
+            if !write_commands.is_empty() || has_flush {
                 if has_flush {
                     self.state
                         .persistence
@@ -213,15 +210,14 @@ impl<'a> TransactionHandler<'a> {
                         .increment_dirty_keys(total_keys_changed);
                 }
 
-                Some(UnitOfWork::Transaction(Box::new(TransactionData {
+                let uow = UnitOfWork::Transaction(Box::new(TransactionData {
                     all_commands: tx_state.commands,
                     write_commands,
-                })))
-            } else {
-                None
-            };
+                }));
+                self.state.event_bus.publish(uow, &self.state);
+            }
 
-            Ok((RespValue::Array(responses), maybe_uow))
+            Ok(RespValue::Array(responses))
         } // All locks are released here.
     }
 
@@ -326,6 +322,7 @@ impl<'a> TransactionHandler<'a> {
                 command: Some(command.clone()),
                 session_id: self.session_id,
                 authenticated_user: self.authenticated_user.clone(),
+                write_guard: None,
             };
 
             let result = command.execute(&mut ctx).await;
@@ -7,8 +7,8 @@
 
 use lazy_static::lazy_static;
 use prometheus::{
-    Counter, CounterVec, Gauge, Histogram, TextEncoder, register_counter, register_counter_vec,
-    register_gauge, register_histogram,
+    Counter, CounterVec, Gauge, GaugeVec, Histogram, TextEncoder, register_counter,
+    register_counter_vec, register_gauge, register_gauge_vec, register_histogram,
 };
 
 lazy_static! {
@@ -55,6 +55,25 @@ lazy_static! {
     /// The total number of cache items evicted due to memory pressure.
     pub static ref CACHE_EVICTIONS_TOTAL: Counter =
         register_counter!("spineldb_cache_evictions_total", "Total number of cache keys evicted.").unwrap();
+    /// The total number of cache items whose bodies were demoted to disk due to memory pressure.
+    pub static ref CACHE_DEMOTIONS_TOTAL: Counter =
+        register_counter!("spineldb_cache_demotions_total", "Total number of cache keys demoted to disk.").unwrap();
+    /// The total number of on-disk cache bodies promoted back to memory.
+    pub static ref CACHE_PROMOTIONS_TOTAL: Counter =
+        register_counter!("spineldb_cache_promotions_total", "Total number of cache bodies promoted to memory.").unwrap();
+    /// The total number of origin requests, labeled by origin host and outcome
+    /// (`success`, `failure`, or `rejected` by an open circuit breaker).
+    pub static ref CACHE_ORIGIN_REQUESTS_TOTAL: CounterVec =
+        register_counter_vec!("spineldb_cache_origin_requests_total", "Total number of origin requests, labeled by origin and outcome.", &["origin", "outcome"]).unwrap();
+
+
+    // --- Cache Origin Gauges ---
+    /// The number of requests currently in flight to each origin host.
+    pub static ref CACHE_ORIGIN_IN_FLIGHT: GaugeVec =
+        register_gauge_vec!("spineldb_cache_origin_in_flight", "Number of in-flight requests per origin.", &["origin"]).unwrap();
+    /// The circuit breaker state of each origin host.
+    pub static ref CACHE_ORIGIN_CIRCUIT_STATE: GaugeVec =
+        register_gauge_vec!("spineldb_cache_origin_circuit_state", "Circuit breaker state per origin (0 closed, 1 open, 2 half-open).", &["origin"]).unwrap();
 
 
     // --- Histograms ---
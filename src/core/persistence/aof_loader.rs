@@ -1,8 +1,10 @@
 // src/core/persistence/aof_loader.rs
 
-//! Implements the logic for loading data from an Append-Only File (AOF)
+//! Implements the logic for loading data from the multi-part Append-Only File (AOF)
 //! into memory when the server starts.
 
+use super::aof_manifest::{AofFileInfo, AofManifest};
+use super::spldb;
 use crate::core::commands::command_trait::CommandExt;
 use crate::core::commands::generic::Select;
 use crate::core::database::{ExecutionContext, ExecutionLocks};
@@ -13,13 +15,14 @@ use bytes::{Bytes, BytesMut};
 use std::mem;
 use std::path::Path;
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::fs::File as TokioFile;
 use tokio::io::{AsyncReadExt, BufReader};
 use tokio_util::codec::Decoder;
 use tracing::{debug, error, info, warn};
 
-/// `AofLoader` is responsible for reading an AOF file and replaying the commands
-/// to reconstruct the database state.
+/// `AofLoader` is responsible for reading the AOF manifest, loading the base file and
+/// replaying the incremental files to reconstruct the database state.
 pub struct AofLoader {
     config: crate::config::PersistenceConfig,
 }
@@ -29,28 +32,81 @@ impl AofLoader {
         Self { config }
     }
 
-    /// Loads the AOF file into the provided `ServerState`.
+    /// Loads the multi-part AOF into the provided `ServerState`.
     ///
-    /// It reads the AOF file in chunks, parses each RESP frame as a command,
-    /// and executes it to rebuild the in-memory state. It correctly handles
-    /// `SELECT` commands and `MULTI`/`EXEC` transaction blocks. This streaming
-    /// approach avoids loading the entire file into memory at once.
+    /// The base file is loaded first, directly from its SPLDB snapshot (or by replaying
+    /// it, for a base upgraded from a legacy single-file AOF). The incremental files are
+    /// then replayed in manifest order. Once loaded, the manifest is kept in the server
+    /// state for the AOF writer and the rewriter.
     pub async fn load_into(&self, state: &Arc<ServerState>) -> Result<(), SpinelDBError> {
         if !self.config.aof_enabled {
             return Ok(());
         }
 
-        let path = Path::new(&self.config.aof_path);
-        if !path.exists() {
-            info!(
-                "AOF file not found at '{}', starting with an empty state.",
-                self.config.aof_path
-            );
+        let manifest = AofManifest::load_or_create(&self.config).await?;
+        manifest.remove_unreferenced_files().await;
+        info!(
+            "Loading data from AOF manifest: {}",
+            manifest.manifest_path().display()
+        );
+
+        let start = Instant::now();
+        for info in manifest.files() {
+            self.load_file(state, &manifest, info).await?;
+        }
+        info!(
+            "AOF loaded {} file(s) in {:.2?}.",
+            manifest.files().count(),
+            start.elapsed()
+        );
+
+        *state.persistence.aof_manifest.lock().await = Some(manifest);
+        Ok(())
+    }
+
+    /// Loads a single file listed in the manifest.
+    async fn load_file(
+        &self,
+        state: &Arc<ServerState>,
+        manifest: &AofManifest,
+        info: &AofFileInfo,
+    ) -> Result<(), SpinelDBError> {
+        let path = manifest.path_of(info);
+        if info.is_spldb_base() {
+            info!("Loading AOF base snapshot: {}", path.display());
+            let data = Bytes::from(tokio::fs::read(&path).await?);
+            let policies = spldb::load_from_bytes(&data, &state.dbs)
+                .await
+                .map_err(|e| {
+                    SpinelDBError::AofError(format!(
+                        "Failed to load AOF base file '{}': {e}",
+                        path.display()
+                    ))
+                })?;
+            state.cache.replace_policies(policies).await;
             return Ok(());
         }
+        self.replay_resp_file(state, &path).await
+    }
 
-        info!("Loading data from AOF file: {}", self.config.aof_path);
-        let file = TokioFile::open(path).await?;
+    /// Replays a file of RESP commands.
+    ///
+    /// It reads the file in chunks, parses each RESP frame as a command,
+    /// and executes it to rebuild the in-memory state. It correctly handles
+    /// `SELECT` commands and `MULTI`/`EXEC` transaction blocks. This streaming
+    /// approach avoids loading the entire file into memory at once.
+    async fn replay_resp_file(
+        &self,
+        state: &Arc<ServerState>,
+        path: &Path,
+    ) -> Result<(), SpinelDBError> {
+        info!("Replaying AOF file: {}", path.display());
+        let file = TokioFile::open(path).await.map_err(|e| {
+            SpinelDBError::AofError(format!(
+                "Failed to open AOF file '{}' listed in the manifest: {e}",
+                path.display()
+            ))
+        })?;
         let mut reader = BufReader::new(file);
         let mut buffer = BytesMut::with_capacity(8192);
 
@@ -65,7 +121,10 @@ impl AofLoader {
             if reader.read_buf(&mut buffer).await? == 0 {
                 // End of file.
                 if !buffer.is_empty() {
-                    warn!("AOF file has trailing, incomplete data. Ignoring.");
+                    warn!(
+                        "AOF file {} has trailing, incomplete data. Ignoring. Run `spineldb check-aof --fix` to repair it.",
+                        path.display()
+                    );
                 }
                 break;
             }
@@ -129,6 +188,7 @@ impl AofLoader {
                                             command: Some(cmd.clone()),
                                             session_id: 0,
                                             authenticated_user: None,
+                                            write_guard: None,
                                         };
 
                                         if let Err(e) = cmd.execute(&mut ctx).await {
@@ -185,8 +245,9 @@ impl AofLoader {
         }
 
         info!(
-            "Successfully loaded {} commands/transactions from AOF.",
-            commands_loaded
+            "Successfully loaded {} commands/transactions from {}.",
+            commands_loaded,
+            path.display()
         );
         Ok(())
     }
@@ -211,6 +272,7 @@ impl AofLoader {
             command: Some(cmd.clone()),
             session_id: 0,
             authenticated_user: None,
+            write_guard: None,
         };
 
         if let Err(e) = cmd.execute(&mut ctx).await {
@@ -0,0 +1,390 @@
+// src/core/persistence/aof_manifest.rs
+
+//! Implements the manifest for the multi-part Append-Only File (AOF).
+//!
+//! Instead of a single, ever-growing file, the AOF is stored as a set of files in a
+//! dedicated directory:
+//!
+//! - A **base** file holding a full snapshot, written in SPLDB format by a rewrite.
+//!   An AOF upgraded from the legacy single-file layout keeps its RESP base instead.
+//! - One or more **incremental** files of RESP commands, appended to by the AOF writer.
+//! - A **manifest** listing the files above, in the order they must be loaded.
+//!
+//! The manifest is the single source of truth: it is always replaced atomically, so
+//! any file it does not reference can safely be removed.
+
+use crate::config::PersistenceConfig;
+use crate::core::SpinelDBError;
+use crate::core::state::ServerState;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::MappedMutexGuard;
+use tracing::{info, warn};
+
+/// The suffix of a base file written in SPLDB format.
+const BASE_SPLDB_SUFFIX: &str = "base.spldb";
+/// The suffix of a base file written as RESP commands (legacy upgrade).
+const BASE_RESP_SUFFIX: &str = "base.aof";
+/// The suffix of an incremental file.
+const INCR_SUFFIX: &str = "incr.aof";
+/// The prefix of temporary files created during rewrites and manifest updates.
+pub(crate) const TEMP_FILE_PREFIX: &str = "temp-";
+
+/// The role of a file listed in the manifest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AofFileType {
+    Base,
+    Incr,
+    /// A file that has been superseded by a rewrite and is pending deletion.
+    History,
+}
+
+impl AofFileType {
+    fn as_str(&self) -> &'static str {
+        match self {
+            AofFileType::Base => "b",
+            AofFileType::Incr => "i",
+            AofFileType::History => "h",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "b" => Some(AofFileType::Base),
+            "i" => Some(AofFileType::Incr),
+            "h" => Some(AofFileType::History),
+            _ => None,
+        }
+    }
+}
+
+/// A single file entry in the manifest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AofFileInfo {
+    pub file_name: String,
+    pub seq: u64,
+    pub file_type: AofFileType,
+}
+
+impl AofFileInfo {
+    /// Returns true if this is a base file stored in SPLDB format.
+    pub fn is_spldb_base(&self) -> bool {
+        self.file_type == AofFileType::Base && self.file_name.ends_with(BASE_SPLDB_SUFFIX)
+    }
+}
+
+impl fmt::Display for AofFileInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "file {} seq {} type {}",
+            self.file_name,
+            self.seq,
+            self.file_type.as_str()
+        )
+    }
+}
+
+/// The in-memory representation of the AOF manifest.
+#[derive(Debug, Clone)]
+pub struct AofManifest {
+    /// The directory containing the manifest and all AOF files.
+    dir: PathBuf,
+    /// The file name prefix shared by all AOF files, taken from `aof_path`.
+    prefix: String,
+    /// The current base file, if a rewrite has ever produced one.
+    pub base: Option<AofFileInfo>,
+    /// The incremental files, in the order they must be replayed.
+    /// The last one is the file the AOF writer is appending to.
+    pub incrs: Vec<AofFileInfo>,
+}
+
+impl AofManifest {
+    /// Returns the directory and file name prefix of the multi-part AOF for `config`.
+    pub fn location(config: &PersistenceConfig) -> Result<(PathBuf, String), SpinelDBError> {
+        let aof_path = Path::new(&config.aof_path);
+        let prefix = aof_path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .ok_or_else(|| SpinelDBError::AofError("Invalid AOF path".into()))?
+            .to_string();
+        let parent = aof_path.parent().unwrap_or_else(|| Path::new(""));
+        Ok((parent.join(&config.aof_dirname), prefix))
+    }
+
+    /// Reads the manifest from disk without creating or upgrading anything.
+    /// Returns `None` if no manifest exists yet.
+    pub async fn load(config: &PersistenceConfig) -> Result<Option<Self>, SpinelDBError> {
+        let (dir, prefix) = Self::location(config)?;
+        let manifest_path = dir.join(format!("{prefix}.manifest"));
+        let contents = match fs::read_to_string(&manifest_path).await {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let mut manifest = Self {
+            dir,
+            prefix,
+            base: None,
+            incrs: Vec::new(),
+        };
+        for info in Self::parse(&contents)? {
+            match info.file_type {
+                AofFileType::Base => {
+                    if manifest.base.is_some() {
+                        return Err(SpinelDBError::AofError(
+                            "AOF manifest lists more than one base file".into(),
+                        ));
+                    }
+                    manifest.base = Some(info);
+                }
+                AofFileType::Incr => manifest.incrs.push(info),
+                AofFileType::History => {}
+            }
+        }
+        Ok(Some(manifest))
+    }
+
+    /// Loads the manifest, creating the AOF directory and an initial manifest if needed.
+    ///
+    /// If no manifest exists but a legacy single-file AOF is found at `aof_path`, it is
+    /// moved into the AOF directory and becomes the (RESP) base file, so no data is lost.
+    /// A temporary file left next to it by a crashed legacy rewrite is removed, since the
+    /// rewrite never replaced the legacy AOF.
+    pub async fn load_or_create(config: &PersistenceConfig) -> Result<Self, SpinelDBError> {
+        if let Some(mut manifest) = Self::load(config).await? {
+            if manifest.incrs.is_empty() {
+                let incr = manifest.next_incr();
+                manifest.create_incr_file(&incr).await?;
+                manifest.incrs.push(incr);
+                manifest.persist().await?;
+            }
+            return Ok(manifest);
+        }
+
+        let (dir, prefix) = Self::location(config)?;
+        fs::create_dir_all(&dir).await?;
+        let mut manifest = Self {
+            dir,
+            prefix,
+            base: None,
+            incrs: Vec::new(),
+        };
+
+        let legacy_path = Path::new(&config.aof_path);
+        let legacy_temp_path =
+            legacy_path.with_file_name(format!("{TEMP_FILE_PREFIX}rewrite-{}", manifest.prefix));
+        match fs::remove_file(&legacy_temp_path).await {
+            Ok(()) => info!(
+                "Removed '{}' left by an interrupted legacy AOF rewrite.",
+                legacy_temp_path.display()
+            ),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        if fs::metadata(legacy_path).await.is_ok_and(|m| m.is_file()) {
+            let base = AofFileInfo {
+                file_name: format!("{}.1.{}", manifest.prefix, BASE_RESP_SUFFIX),
+                seq: 1,
+                file_type: AofFileType::Base,
+            };
+            info!(
+                "Upgrading legacy AOF file '{}' to a multi-part AOF in '{}'.",
+                legacy_path.display(),
+                manifest.dir.display()
+            );
+            fs::rename(legacy_path, manifest.path_of(&base)).await?;
+            manifest.base = Some(base);
+        }
+
+        let incr = manifest.next_incr();
+        manifest.create_incr_file(&incr).await?;
+        manifest.incrs.push(incr);
+        manifest.persist().await?;
+        info!(
+            "Created new AOF manifest at '{}'.",
+            manifest.manifest_path().display()
+        );
+        Ok(manifest)
+    }
+
+    /// Parses the textual manifest format: one `file <name> seq <n> type <b|i|h>` per line.
+    pub fn parse(contents: &str) -> Result<Vec<AofFileInfo>, SpinelDBError> {
+        let mut files = Vec::new();
+        for (line_no, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = || {
+                SpinelDBError::AofError(format!(
+                    "Invalid AOF manifest line {}: '{}'",
+                    line_no + 1,
+                    line
+                ))
+            };
+
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            let (mut file_name, mut seq, mut file_type) = (None, None, None);
+            for pair in parts.chunks(2) {
+                let [key, value] = pair else {
+                    return Err(invalid());
+                };
+                match *key {
+                    "file" => file_name = Some(value.to_string()),
+                    "seq" => seq = Some(value.parse::<u64>().map_err(|_| invalid())?),
+                    "type" => file_type = Some(AofFileType::parse(value).ok_or_else(invalid)?),
+                    // Unknown keys are ignored for forward compatibility.
+                    _ => {}
+                }
+            }
+
+            let file_name = file_name.ok_or_else(invalid)?;
+            if file_name.contains('/') || file_name.contains('\\') {
+                return Err(invalid());
+            }
+            files.push(AofFileInfo {
+                file_name,
+                seq: seq.ok_or_else(invalid)?,
+                file_type: file_type.ok_or_else(invalid)?,
+            });
+        }
+        Ok(files)
+    }
+
+    /// Returns the directory holding the multi-part AOF.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Returns the path of the manifest file.
+    pub fn manifest_path(&self) -> PathBuf {
+        self.dir.join(format!("{}.manifest", self.prefix))
+    }
+
+    /// Returns the full path of a file listed in the manifest.
+    pub fn path_of(&self, info: &AofFileInfo) -> PathBuf {
+        self.dir.join(&info.file_name)
+    }
+
+    /// Returns the path of the incremental file currently being appended to.
+    pub fn current_incr_path(&self) -> Option<PathBuf> {
+        self.incrs.last().map(|info| self.path_of(info))
+    }
+
+    /// Returns every file in load order: the base first, then the incremental files.
+    pub fn files(&self) -> impl Iterator<Item = &AofFileInfo> {
+        self.base.iter().chain(self.incrs.iter())
+    }
+
+    /// Builds the entry for the next incremental file. It is not added to the manifest.
+    pub fn next_incr(&self) -> AofFileInfo {
+        let seq = self.incrs.last().map_or(1, |info| info.seq + 1);
+        AofFileInfo {
+            file_name: format!("{}.{}.{}", self.prefix, seq, INCR_SUFFIX),
+            seq,
+            file_type: AofFileType::Incr,
+        }
+    }
+
+    /// Builds the entry for the next SPLDB base file. It is not added to the manifest.
+    pub fn next_base(&self) -> AofFileInfo {
+        let seq = self.base.as_ref().map_or(1, |info| info.seq + 1);
+        AofFileInfo {
+            file_name: format!("{}.{}.{}", self.prefix, seq, BASE_SPLDB_SUFFIX),
+            seq,
+            file_type: AofFileType::Base,
+        }
+    }
+
+    /// Returns a temporary path in the AOF directory for writing `info` before it is renamed.
+    pub fn temp_path_of(&self, info: &AofFileInfo) -> PathBuf {
+        self.dir
+            .join(format!("{TEMP_FILE_PREFIX}rewrite-{}", info.file_name))
+    }
+
+    /// Creates an empty incremental file, if it does not already exist.
+    async fn create_incr_file(&self, info: &AofFileInfo) -> Result<(), SpinelDBError> {
+        fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.path_of(info))
+            .await?;
+        Ok(())
+    }
+
+    /// Returns the combined size in bytes of all files listed in the manifest.
+    pub async fn total_size(&self) -> u64 {
+        let mut total = 0;
+        for info in self.files() {
+            if let Ok(metadata) = fs::metadata(self.path_of(info)).await {
+                total += metadata.len();
+            }
+        }
+        total
+    }
+
+    /// Atomically replaces the manifest on disk with the current in-memory state.
+    pub async fn persist(&self) -> Result<(), SpinelDBError> {
+        let contents: String = self.files().map(|info| format!("{info}\n")).collect();
+        let temp_path = self
+            .dir
+            .join(format!("{TEMP_FILE_PREFIX}{}.manifest", self.prefix));
+
+        let mut file = fs::File::create(&temp_path).await?;
+        file.write_all(contents.as_bytes()).await?;
+        file.sync_all().await?;
+        drop(file);
+
+        fs::rename(&temp_path, self.manifest_path()).await?;
+        // Make the rename itself durable.
+        if let Ok(dir) = fs::File::open(&self.dir).await {
+            let _ = dir.sync_all().await;
+        }
+        Ok(())
+    }
+
+    /// Removes files in the AOF directory that belong to this AOF but are not referenced
+    /// by the manifest, such as superseded files or leftovers from an interrupted rewrite.
+    pub async fn remove_unreferenced_files(&self) {
+        let Ok(mut entries) = fs::read_dir(&self.dir).await else {
+            return;
+        };
+        let manifest_name = format!("{}.manifest", self.prefix);
+        let temp_prefix = format!("{TEMP_FILE_PREFIX}rewrite-{}.", self.prefix);
+        let own_prefix = format!("{}.", self.prefix);
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+                continue;
+            };
+            let is_ours = name.starts_with(&temp_prefix)
+                || (name.starts_with(&own_prefix)
+                    && (name.ends_with(INCR_SUFFIX)
+                        || name.ends_with(BASE_SPLDB_SUFFIX)
+                        || name.ends_with(BASE_RESP_SUFFIX)));
+            if !is_ours || name == manifest_name || self.files().any(|f| f.file_name == name) {
+                continue;
+            }
+            match fs::remove_file(entry.path()).await {
+                Ok(_) => info!("Removed unreferenced AOF file '{}'.", name),
+                Err(e) => warn!("Failed to remove unreferenced AOF file '{}': {}", name, e),
+            }
+        }
+    }
+}
+
+/// Locks the server's AOF manifest, loading or creating it on first use.
+pub async fn lock_manifest(
+    state: &ServerState,
+) -> Result<MappedMutexGuard<'_, AofManifest>, SpinelDBError> {
+    let mut guard = state.persistence.aof_manifest.lock().await;
+    if guard.is_none() {
+        let config = state.config.lock().await.persistence.clone();
+        *guard = Some(AofManifest::load_or_create(&config).await?);
+    }
+    Ok(tokio::sync::MutexGuard::map(guard, |m| {
+        m.as_mut().expect("manifest was initialized above")
+    }))
+}
@@ -1,65 +1,108 @@
 // src/core/persistence/aof_rewriter.rs
 
-//! Implements the AOF (Append-Only File) rewrite logic.
+//! Implements the AOF (Append-Only File) rewrite logic for the multi-part AOF.
 //!
-//! The rewrite process creates a new, compact AOF file containing the minimal set
-//! of commands required to reconstruct the current database state. This is done
-//! in a background task to avoid blocking the main server loop, which is a critical
-//! design pattern for maintaining high performance and responsiveness. The process is
-//! carefully orchestrated to handle concurrent writes safely.
+//! A rewrite produces a new base file containing a snapshot of the current database
+//! state in SPLDB format. New writes are never buffered in memory: when the rewrite
+//! starts, the AOF writer is rotated to a fresh incremental file, and once the new base
+//! is on disk the manifest is switched over to reference only the new base and the
+//! incremental files that follow it. The superseded files are then deleted.
+//!
+//! The snapshot is written in a background task to avoid blocking the main server loop.
+//! Because the manifest is replaced atomically, a crash at any point leaves a loadable
+//! AOF behind: either the old base with all incremental files, or the new base with
+//! the incremental files written since the rewrite began.
 
+use super::aof_manifest::{AofFileInfo, lock_manifest};
+use super::spldb;
 use crate::core::commands::generic::Script as ScriptCmd;
 use crate::core::commands::generic::script::ScriptSubcommand;
-use crate::core::events::{PropagatedWork, UnitOfWork};
+use crate::core::database::Snapshot;
 use crate::core::protocol::RespFrame;
-use crate::core::state::ServerState;
-use crate::core::storage::data_types::StoredValue;
+use crate::core::state::{PendingIncrFile, ServerState};
 use crate::core::{Command, SpinelDBError};
 use bytes::Bytes;
 use std::collections::HashMap;
-use std::fs::File as StdFile;
-use std::io::Write;
-use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+use tokio::fs::{self, File as TokioFile};
+use tokio::io::{AsyncWriteExt, BufWriter};
 use tokio::task::JoinHandle;
 use tracing::{error, info, warn};
 
+/// How long a rewrite waits for the AOF writer to catch up with its snapshot.
+const AOF_DRAIN_TIMEOUT: Duration = Duration::from_secs(60);
+
 /// The main entry point for the AOF rewrite process.
 ///
 /// This function orchestrates the rewrite by:
-/// 1. Setting a flag to begin buffering incoming write commands in memory.
+/// 1. Setting the in-progress flag and rotating the AOF writer to a new incremental file
+///    at the exact point where the snapshot for the new base begins.
 /// 2. Spawning the I/O-intensive database snapshotting work onto a dedicated blocking thread.
-/// 3. Waiting for the blocking task to complete.
-/// 4. Appending the buffered commands to the newly created temporary AOF file.
-/// 5. Signaling the main AOF writer task to atomically switch to the new file.
-///
-/// This multi-stage process ensures that the server remains responsive during the rewrite
-/// and that no write operations are lost.
+/// 3. Waiting for the AOF writer to flush everything enqueued before that point.
+/// 4. Atomically switching the manifest to the new base file.
+/// 5. Deleting the base and incremental files that the new base supersedes.
 pub async fn rewrite_aof(state: Arc<ServerState>) {
     info!("AOF rewrite process started by worker task.");
 
-    let scripts_snapshot;
-    {
+    let (new_incr, snapshot, cut_seq) = {
         // Atomically acquire a lock and set the rewrite_in_progress flag.
-        // This is the critical step that diverts new write commands to an in-memory buffer.
         let mut rewrite_state_guard = state.persistence.aof_rewrite_state.lock().await;
         if rewrite_state_guard.is_in_progress {
             warn!("AOF rewrite requested, but one is already in progress. Aborting.");
             return;
         }
+
+        let (new_incr, path) = match rotate_incr_file(&state).await {
+            Ok(rotated) => rotated,
+            Err(e) => {
+                error!("AOF rewrite failed to rotate the incremental file: {}", e);
+                return;
+            }
+        };
+
+        // Writers publish their work before releasing their shard locks, so the number
+        // of work units enqueued while the snapshot holds every lock is exactly the set
+        // of writes the new base will contain. The AOF writer cannot pick up its next
+        // work unit while we hold the rewrite state lock, so it switches files precisely
+        // after the last of them and nothing lands in both the base and the new file.
+        //
+        // `SCRIPT LOAD` caches its script before publishing, so reading the scripts right
+        // after the cut sequence captures every load that the new file will not contain.
+        let mut cut_seq = 0;
+        let mut scripts_snapshot = HashMap::new();
+        let snapshot = Snapshot::begin_with(&state.dbs, || {
+            cut_seq = state.persistence.aof_enqueued_seq.load(Ordering::SeqCst);
+            scripts_snapshot = state.scripting.get_all_scripts();
+        })
+        .await;
+        if let Err(e) = write_scripts(&path, &scripts_snapshot).await {
+            error!(
+                "AOF rewrite failed to write scripts to the incremental file: {}",
+                e
+            );
+            return;
+        }
         rewrite_state_guard.is_in_progress = true;
-        // Take a consistent snapshot of all Lua scripts at the start of the rewrite.
-        scripts_snapshot = state.scripting.get_all_scripts();
-    }
-    info!("AOF rewrite state set to 'in_progress'. New commands will be buffered.");
+        rewrite_state_guard.pending_incr_file = Some(PendingIncrFile {
+            path,
+            after_seq: cut_seq,
+        });
+        (new_incr, snapshot, cut_seq)
+    };
+    info!(
+        "AOF rewrite state set to 'in_progress'. New commands are written to '{}'.",
+        new_incr.file_name
+    );
 
     let state_for_task = state.clone();
     // Spawn the I/O-heavy work onto a dedicated blocking thread to avoid starving the main Tokio runtime.
-    let rewrite_task: JoinHandle<Result<(), SpinelDBError>> =
+    let rewrite_task: JoinHandle<Result<AofFileInfo, SpinelDBError>> =
         tokio::task::spawn_blocking(move || {
             let rt = tokio::runtime::Handle::current();
             // We must block on the async part inside the blocking thread.
-            rt.block_on(async move { do_rewrite_blocking(state_for_task, scripts_snapshot).await })
+            rt.block_on(async move { write_new_base(&state_for_task, snapshot).await })
         });
 
     let rewrite_result = match rewrite_task.await {
@@ -69,188 +112,166 @@ pub async fn rewrite_aof(state: Arc<ServerState>) {
         ))),
     };
 
-    // Atomically take ownership of the buffered work that arrived during the rewrite.
-    let buffered_work = {
-        let mut rewrite_state_guard = state.persistence.aof_rewrite_state.lock().await;
-        std::mem::take(&mut rewrite_state_guard.buffer)
+    let rewrite_result = match rewrite_result {
+        Ok(new_base) => match drain_writer_to(&state, cut_seq).await {
+            Ok(()) => switch_to_new_base(&state, new_base, &new_incr).await,
+            Err(e) => Err(e),
+        },
+        Err(e) => Err(e),
     };
 
-    if let Err(e) = rewrite_result {
-        error!(
-            "AOF rewrite failed: {}. Server will enter read-only mode. Buffered commands will be drained to the old AOF file by the writer task.",
-            e
-        );
-        state.set_read_only(true, "AOF rewrite process failed");
-    } else {
-        info!("AOF rewrite temporary file created successfully.");
-        // Append the buffered commands to the newly created file.
-        if let Err(e) = append_buffered_work_to_temp_file(&state, buffered_work).await {
+    match rewrite_result {
+        Ok(new_size) => {
+            state
+                .persistence
+                .aof_last_rewrite_size
+                .store(new_size, Ordering::Relaxed);
+            info!(
+                "AOF rewrite process completed successfully. AOF size is now {} bytes.",
+                new_size
+            );
+        }
+        Err(e) => {
+            // The manifest still references the old base and every incremental file,
+            // including the new one, so no data is lost. Clean up and carry on.
             error!(
-                "Failed to append buffered work to new AOF file: {}. Aborting rewrite. Error: {}",
-                state.config.lock().await.persistence.aof_path,
+                "AOF rewrite failed: {}. The previous AOF files remain in use.",
                 e
             );
-            state.set_read_only(true, "AOF rewrite process failed during buffer append");
-        } else {
-            info!("AOF rewrite process completed successfully.");
+            if let Ok(manifest) = lock_manifest(&state).await {
+                manifest.remove_unreferenced_files().await;
+            }
         }
     }
 
-    // Signal the AOF writer task that the rewrite is complete (either in success or failure).
-    // The writer will then handle the atomic file rename and drain any remaining buffer.
+    state
+        .persistence
+        .aof_rewrite_state
+        .lock()
+        .await
+        .is_in_progress = false;
+
+    // Let the AOF writer know, so that it can close the previous incremental file even
+    // if no write has arrived since the rotation.
     if state.persistence.aof_rewrite_complete_tx.send(()).is_err() {
-        error!(
-            "Failed to send AOF rewrite completion signal. AOF writer might not switch to the new file or drain buffer."
-        );
-    } else {
-        info!("Sent AOF rewrite completion signal to the writer task.");
+        warn!("Failed to send AOF rewrite completion signal to the writer task.");
     }
 }
 
-/// The core blocking logic that iterates through the entire database and writes its
-/// state as a series of commands to a temporary file.
-async fn do_rewrite_blocking(
-    state: Arc<ServerState>,
-    scripts_snapshot: HashMap<String, Bytes>,
-) -> Result<(), SpinelDBError> {
-    let aof_path = state.config.lock().await.persistence.aof_path.clone();
-    let temp_file_path = get_temp_aof_path(&aof_path)?;
-    // Use standard blocking file I/O as this runs on a blocking thread.
-    let mut temp_file = StdFile::create(&temp_file_path)?;
-
-    info!(
-        "AOF rewrite: Writing database snapshot to temporary file: {:?}",
-        temp_file_path
-    );
-
-    // First, write all loaded Lua scripts to make the new AOF self-contained.
-    // This ensures EVALSHA commands can be replayed correctly.
-    if !scripts_snapshot.is_empty() {
-        info!(
-            "AOF rewrite: Writing {} scripts to the new AOF.",
-            scripts_snapshot.len()
-        );
-        let select_db_0: RespFrame =
-            Command::Select(crate::core::commands::generic::Select { db_index: 0 }).into();
-        temp_file.write_all(&select_db_0.encode_to_vec()?)?;
-        for script_body in scripts_snapshot.values() {
-            let script_load_cmd = Command::Script(ScriptCmd {
-                subcommand: ScriptSubcommand::Load(script_body.clone()),
-            });
-            let frame: RespFrame = script_load_cmd.into();
-            temp_file.write_all(&frame.encode_to_vec()?)?;
-        }
-    }
-
-    // Iterate through each database and shard to write its state.
-    for (db_index, db) in state.dbs.iter().enumerate() {
-        if db.get_key_count() > 0 {
-            // Write a SELECT command to switch to the correct database.
-            let select_cmd: RespFrame =
-                Command::Select(crate::core::commands::generic::Select { db_index }).into();
-            temp_file.write_all(&select_cmd.encode_to_vec()?)?;
+/// Creates the next incremental file and appends it to the manifest. The AOF writer
+/// only switches to it once the rewrite sets it as the pending file.
+async fn rotate_incr_file(
+    state: &ServerState,
+) -> Result<(AofFileInfo, std::path::PathBuf), SpinelDBError> {
+    let mut manifest = lock_manifest(state).await?;
+    let new_incr = manifest.next_incr();
+    let path = manifest.path_of(&new_incr);
 
-            // Iterate through each shard in the database.
-            for shard_index in 0..crate::core::database::NUM_SHARDS {
-                let shard = db.get_shard(shard_index);
-                let guard = shard.entries.lock().await;
+    TokioFile::create(&path).await?.sync_all().await?;
 
-                // Iterate through every key-value pair in the shard.
-                for (key, stored_value) in guard.iter() {
-                    // Skip keys that have already expired.
-                    if stored_value.is_expired() {
-                        continue;
-                    }
-                    // Convert the value back into a minimal set of construction commands.
-                    write_value_as_commands(&mut temp_file, key, stored_value)?;
-                }
-            }
-            info!("AOF rewrite: Snapshot of DB {} written.", db_index);
-        }
+    manifest.incrs.push(new_incr.clone());
+    if let Err(e) = manifest.persist().await {
+        manifest.incrs.pop();
+        let _ = fs::remove_file(&path).await;
+        return Err(e);
     }
-
-    // Ensure all buffered data is written to the OS.
-    temp_file.sync_all()?;
-    Ok(())
+    Ok((new_incr, path))
 }
 
-/// Appends commands that arrived during the rewrite to the new temporary AOF file.
-/// This is the final step before the atomic rename.
-async fn append_buffered_work_to_temp_file(
-    state: &Arc<ServerState>,
-    buffered_work: Vec<PropagatedWork>,
+/// Seeds the new incremental file with the Lua scripts cached at the rewrite's cut, so
+/// that `EVALSHA` commands in it can be replayed. The base file carries no scripts.
+async fn write_scripts(
+    path: &std::path::Path,
+    scripts_snapshot: &HashMap<String, Bytes>,
 ) -> Result<(), SpinelDBError> {
-    if buffered_work.is_empty() {
+    if scripts_snapshot.is_empty() {
         return Ok(());
     }
-
-    let aof_path = &state.config.lock().await.persistence.aof_path;
-    let temp_file_path = get_temp_aof_path(aof_path)?;
-    let mut temp_file = std::fs::OpenOptions::new()
-        .append(true)
-        .open(&temp_file_path)?;
-
     info!(
-        "AOF rewrite: Appending {} commands that arrived during snapshot to temp file.",
-        buffered_work.len()
+        "AOF rewrite: Writing {} scripts to the new incremental file.",
+        scripts_snapshot.len()
     );
-    for work_item in buffered_work {
-        // The scripts_snapshot is no longer needed here as EVALSHA is transformed upstream.
-        write_uow_to_file(&mut temp_file, work_item.uow)?;
+    let mut file = fs::OpenOptions::new().append(true).open(path).await?;
+    for script_body in scripts_snapshot.values() {
+        let script_load_cmd = Command::Script(ScriptCmd {
+            subcommand: ScriptSubcommand::Load(script_body.clone()),
+        });
+        let frame: RespFrame = script_load_cmd.into();
+        file.write_all(&frame.encode_to_vec()?).await?;
     }
-    temp_file.sync_all()?;
+    file.sync_all().await?;
     Ok(())
 }
 
-/// Serializes a single StoredValue into the minimal set of commands needed to recreate it.
-fn write_value_as_commands(
-    file: &mut StdFile,
-    key: &Bytes,
-    stored_value: &StoredValue,
-) -> Result<(), SpinelDBError> {
-    // Delegate the complex serialization logic to the StoredValue itself.
-    let commands = stored_value.to_construction_commands(key);
-    for cmd in commands {
-        let frame: RespFrame = cmd.into();
-        file.write_all(&frame.encode_to_vec()?)?;
-    }
-    Ok(())
+/// Writes `snapshot` to a new SPLDB base file.
+async fn write_new_base(
+    state: &Arc<ServerState>,
+    snapshot: Snapshot,
+) -> Result<AofFileInfo, SpinelDBError> {
+    let (new_base, temp_path, final_path) = {
+        let manifest = lock_manifest(state).await?;
+        let new_base = manifest.next_base();
+        let temp_path = manifest.temp_path_of(&new_base);
+        let final_path = manifest.path_of(&new_base);
+        (new_base, temp_path, final_path)
+    };
+
+    info!(
+        "AOF rewrite: Writing database snapshot to temporary file: {:?}",
+        temp_path
+    );
+    let mut writer = BufWriter::new(TokioFile::create(&temp_path).await?);
+    let policies = state.cache.policies.read().await.clone();
+    spldb::write_snapshot(&mut writer, &snapshot, &policies).await?;
+    drop(snapshot);
+    writer.flush().await?;
+    // Ensure all buffered data is durable before the file becomes visible.
+    writer.get_ref().sync_all().await?;
+    drop(writer);
+
+    fs::rename(&temp_path, &final_path).await?;
+    Ok(new_base)
 }
 
-/// Serializes a UnitOfWork (a single command or a transaction) to a file.
-fn write_uow_to_file(file: &mut StdFile, uow: UnitOfWork) -> Result<(), SpinelDBError> {
-    // The EVALSHA->EVAL transformation is now handled by the router/transaction handler,
-    // so this function can be simplified.
-    let frames_to_write: Vec<RespFrame> = match uow {
-        UnitOfWork::Transaction(tx_data) => {
-            let mut frames: Vec<RespFrame> = Vec::with_capacity(tx_data.all_commands.len() + 2);
-            frames.push(Command::Multi.into());
-            for cmd in tx_data.all_commands {
-                frames.push(cmd.into());
-            }
-            frames.push(Command::Exec.into());
-            frames
-        }
-        UnitOfWork::Command(cmd) => {
-            vec![(*cmd).into()]
-        }
-    };
-    for frame in frames_to_write {
-        file.write_all(&frame.encode_to_vec()?)?;
+/// Waits until the AOF writer has fsynced every work unit enqueued before the rewrite's
+/// snapshot began, so that the previous incremental files are complete before the
+/// manifest stops referencing them.
+async fn drain_writer_to(state: &ServerState, cut_seq: u64) -> Result<(), SpinelDBError> {
+    let deadline = Instant::now() + AOF_DRAIN_TIMEOUT;
+    if state
+        .persistence
+        .wait_for_aof_fsync(cut_seq, Some(deadline))
+        .await
+    {
+        Ok(())
+    } else {
+        Err(SpinelDBError::AofError(
+            "Timed out waiting for the AOF writer to reach the rewrite point".into(),
+        ))
     }
-    Ok(())
 }
 
-/// Generates the path for the temporary AOF file, e.g., "temp-rewrite-spineldb.aof".
-fn get_temp_aof_path(original_path: &str) -> Result<PathBuf, SpinelDBError> {
-    let path = Path::new(original_path);
-    let parent = path.parent().unwrap_or_else(|| Path::new("."));
-    let file_name = path
-        .file_name()
-        .ok_or_else(|| SpinelDBError::AofError("Invalid AOF path".into()))?;
-    let temp_file_name = format!(
-        "temp-rewrite-{}",
-        file_name.to_str().unwrap_or("spineldb.aof")
-    );
-    Ok(parent.join(temp_file_name))
+/// Points the manifest at the new base and the incremental files written since the
+/// rotation, then deletes the superseded files. Returns the new total AOF size.
+async fn switch_to_new_base(
+    state: &ServerState,
+    new_base: AofFileInfo,
+    new_incr: &AofFileInfo,
+) -> Result<u64, SpinelDBError> {
+    let mut manifest = lock_manifest(state).await?;
+    let Some(first_kept) = manifest.incrs.iter().position(|i| i == new_incr) else {
+        return Err(SpinelDBError::AofError(format!(
+            "Incremental file '{}' is no longer in the AOF manifest",
+            new_incr.file_name
+        )));
+    };
+
+    let mut updated = manifest.clone();
+    updated.base = Some(new_base);
+    updated.incrs.drain(..first_kept);
+    updated.persist().await?;
+    *manifest = updated;
+
+    manifest.remove_unreferenced_files().await;
+    Ok(manifest.total_size().await)
 }
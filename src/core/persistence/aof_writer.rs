@@ -3,17 +3,19 @@
 //! Implements the Append-Only File (AOF) writer task.
 //!
 //! This task is responsible for writing commands that modify the dataset to the
-//! AOF file on disk. It handles different `fsync` policies, a robust AOF rewrite
-//! process, and graceful shutdown to ensure data durability.
+//! current incremental file of the multi-part AOF. It handles different `fsync`
+//! policies, switching to a new incremental file when a rewrite rotates it, and
+//! graceful shutdown to ensure data durability.
 
+use super::aof_manifest::lock_manifest;
 use crate::config::AppendFsync;
 use crate::core::events::{PropagatedWork, UnitOfWork};
 use crate::core::protocol::RespFrame;
 use crate::core::state::ServerState;
 use crate::core::{Command, SpinelDBError};
 use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::sync::atomic::Ordering;
 use std::time::{Duration, Instant};
 use tokio::fs::{File as TokioFile, OpenOptions};
 use tokio::io::{AsyncWriteExt, BufWriter};
@@ -28,14 +30,18 @@ const AOF_RETRY_DELAY: Duration = Duration::from_secs(2);
 /// The main struct for the AOF writer background task.
 pub struct AofWriterTask {
     state: Arc<ServerState>,
-    /// A buffered writer to the AOF file to improve performance.
+    /// A buffered writer to the current incremental AOF file to improve performance.
     writer: BufWriter<TokioFile>,
+    /// The path of the incremental file currently being written.
+    current_path: PathBuf,
     /// Receives work units (commands/transactions) from the EventBus.
     aof_event_rx: mpsc::Receiver<PropagatedWork>,
     /// Receives requests for periodic fsyncing (for `appendfsync = everysec`).
     fsync_request_rx: mpsc::Receiver<()>,
     /// A watch receiver to get notified when an AOF rewrite process is complete.
     aof_rewrite_complete_rx: watch::Receiver<()>,
+    /// The number of work units received from the EventBus.
+    received_seq: u64,
 }
 
 impl AofWriterTask {
@@ -46,19 +52,22 @@ impl AofWriterTask {
         fsync_request_rx: mpsc::Receiver<()>,
         aof_rewrite_complete_rx: watch::Receiver<()>,
     ) -> Result<Self, SpinelDBError> {
-        let path = state.config.lock().await.persistence.aof_path.clone();
-        let file = OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(path)
-            .await?;
+        let path = lock_manifest(&state)
+            .await?
+            .current_incr_path()
+            .ok_or_else(|| {
+                SpinelDBError::AofError("AOF manifest has no incremental file".into())
+            })?;
+        let file = open_for_append(&path).await?;
 
         Ok(Self {
             state,
             writer: BufWriter::new(file),
+            current_path: path,
             aof_event_rx,
             fsync_request_rx,
             aof_rewrite_complete_rx,
+            received_seq: 0,
         })
     }
 
@@ -67,12 +76,12 @@ impl AofWriterTask {
         mut self,
         mut shutdown_rx: broadcast::Receiver<()>,
     ) -> Result<(), SpinelDBError> {
-        let config = self.state.config.lock().await;
+        let fsync_policy = self.state.config.lock().await.persistence.appendfsync;
         info!(
             "AOF writer task started. Writing to {}. Fsync policy: {:?}",
-            config.persistence.aof_path, config.persistence.appendfsync,
+            self.current_path.display(),
+            fsync_policy,
         );
-        drop(config);
 
         loop {
             tokio::select! {
@@ -83,6 +92,7 @@ impl AofWriterTask {
                     return Ok(());
                 }
                 Some(_) = self.fsync_request_rx.recv() => {
+                    self.switch_to_pending_incr_file().await?;
                     if let Err(e) = self.sync_to_disk().await {
                         error!("AOF fsync failed in periodic task: {}", e);
                     }
@@ -115,45 +125,22 @@ impl AofWriterTask {
     async fn drain_and_sync_all(&mut self) -> Result<(), SpinelDBError> {
         self.aof_event_rx.close();
         while let Some(work) = self.aof_event_rx.recv().await {
+            self.switch_to_pending_incr_file().await?;
+            self.received_seq += 1;
             if let Err(e) = self.write_uow_to_file(&work.uow, false).await {
                 warn!("Could not write pending AOF event during shutdown: {}", e);
             }
         }
-        self.drain_rewrite_buffer(false).await?;
         if let Err(e) = self.sync_to_disk().await {
             error!("Failed to sync AOF file on shutdown: {}", e);
         }
         Ok(())
     }
 
-    /// Handles a single work item by either buffering it or writing it to the AOF file.
+    /// Handles a single work item by writing it to the current incremental file.
     async fn handle_work_item(&mut self, work: PropagatedWork) -> Result<(), SpinelDBError> {
-        let mut rewrite_state = self.state.persistence.aof_rewrite_state.lock().await;
-        if rewrite_state.is_in_progress {
-            let limit = self
-                .state
-                .config
-                .lock()
-                .await
-                .persistence
-                .aof_rewrite_buffer_limit;
-            let work_size = work.estimated_size();
-
-            if rewrite_state.buffer_size + work_size > limit {
-                let err_msg = format!(
-                    "AOF rewrite buffer limit reached (limit: {} bytes, current: {} bytes, incoming: {} bytes). Aborting to prevent OOM.",
-                    limit, rewrite_state.buffer_size, work_size
-                );
-                error!("{}", err_msg);
-                self.state.set_read_only(true, "AOF rewrite buffer full");
-                return Err(SpinelDBError::AofError(err_msg));
-            }
-
-            rewrite_state.buffer.push(work);
-            rewrite_state.buffer_size += work_size;
-            return Ok(());
-        }
-        drop(rewrite_state);
+        self.switch_to_pending_incr_file().await?;
+        self.received_seq += 1;
 
         self.write_uow_to_file(&work.uow, true).await?;
 
@@ -161,78 +148,60 @@ impl AofWriterTask {
         if fsync_policy == AppendFsync::Always {
             self.sync_to_disk().await?;
         }
-        Ok(())
+        // Switch as soon as the rewrite's cut is reached rather than on the next write,
+        // so that a rewrite waiting for the previous file to be complete is not held up.
+        self.switch_to_pending_incr_file().await
     }
 
     /// Manages the transition after an AOF rewrite is finished.
     async fn handle_rewrite_completion(&mut self) -> Result<(), SpinelDBError> {
         info!("AOF rewrite completed signal received. Handling transition.");
-        let rewrite_succeeded = !self.state.is_read_only.load(Ordering::SeqCst);
-        let aof_path = self.state.config.lock().await.persistence.aof_path.clone();
-
-        self.drain_rewrite_buffer(rewrite_succeeded).await?;
-
-        if rewrite_succeeded {
-            if let Ok(metadata) = tokio::fs::metadata(&aof_path).await {
-                self.state
-                    .persistence
-                    .aof_last_rewrite_size
-                    .store(metadata.len(), Ordering::Relaxed);
-                info!("Updated aof_last_rewrite_size to {} bytes.", metadata.len());
-            } else {
-                warn!("Could not read metadata of new AOF file to update rewrite size.");
-            }
-        }
-
-        Ok(())
+        self.switch_to_pending_incr_file().await
     }
 
-    /// Drains the AOF rewrite buffer to the appropriate file.
-    async fn drain_rewrite_buffer(
-        &mut self,
-        switch_to_new_file: bool,
-    ) -> Result<(), SpinelDBError> {
-        if switch_to_new_file {
-            info!("AOF rewrite succeeded. Switching to new AOF file and draining buffer.");
-            self.writer.flush().await?;
-            self.writer.get_ref().sync_all().await.ok();
-
-            let path = &self.state.config.lock().await.persistence.aof_path;
-            let file = OpenOptions::new()
-                .create(true)
-                .append(true)
-                .open(path)
-                .await?;
-            self.writer = BufWriter::new(file);
-            info!("Successfully switched to the new AOF file: {}", path);
-        } else {
-            warn!("AOF rewrite failed or shutdown initiated. Draining buffer to OLD AOF file.");
-        }
-
-        let buffered_work = {
-            let mut rewrite_state = self.state.persistence.aof_rewrite_state.lock().await;
-            rewrite_state.is_in_progress = false;
-            rewrite_state.buffer_size = 0; // Reset the size counter
-            std::mem::take(&mut rewrite_state.buffer)
+    /// Switches to the incremental file that a rewrite has rotated to, if any, once
+    /// every work unit enqueued before the rewrite's snapshot has been written.
+    ///
+    /// The previous file is fsynced first, so everything written before the rotation
+    /// is durable before the rewrite can delete or supersede it.
+    async fn switch_to_pending_incr_file(&mut self) -> Result<(), SpinelDBError> {
+        let mut rewrite_state = self.state.persistence.aof_rewrite_state.lock().await;
+        let Some(pending) = rewrite_state
+            .pending_incr_file
+            .take_if(|pending| self.received_seq >= pending.after_seq)
+        else {
+            return Ok(());
         };
-        info!("AOF rewrite state unlocked. Normal writing can resume.");
+        let path = pending.path.clone();
 
-        if !buffered_work.is_empty() {
-            info!(
-                "Draining {} buffered commands from AOF rewrite process.",
-                buffered_work.len()
+        self.writer.flush().await?;
+        if let Err(e) = self.writer.get_ref().sync_all().await {
+            error!(
+                "Failed to fsync AOF file {} before switching: {}",
+                self.current_path.display(),
+                e
             );
-            for item in buffered_work {
-                if let Err(e) = self.write_uow_to_file(&item.uow, false).await {
-                    let err_msg = format!("Failed to write drained command to AOF: {e}.");
-                    error!("{}", err_msg);
-                    self.state.set_read_only(true, "AOF drain failure");
-                    return Err(SpinelDBError::AofError(err_msg));
-                }
-            }
-            self.sync_to_disk().await?;
-            info!("AOF rewrite buffer successfully drained.");
+            self.state.set_read_only(true, "AOF fsync failure");
+            return Err(e.into());
         }
+        self.publish_fsynced();
+
+        let file = match open_for_append(&path).await {
+            Ok(file) => file,
+            Err(e) => {
+                // Put the request back so the switch is retried on the next write.
+                rewrite_state.pending_incr_file = Some(pending);
+                return Err(e);
+            }
+        };
+        drop(rewrite_state);
+
+        self.writer = BufWriter::new(file);
+        self.current_path = path;
+        info!(
+            "Switched to new incremental AOF file: {}",
+            self.current_path.display()
+        );
         Ok(())
     }
 
@@ -307,17 +276,6 @@ impl AofWriterTask {
 
     /// Flushes the OS buffer to disk (`fsync`).
     async fn sync_to_disk(&mut self) -> Result<(), SpinelDBError> {
-        let is_rewriting = self
-            .state
-            .persistence
-            .aof_rewrite_state
-            .lock()
-            .await
-            .is_in_progress;
-        if is_rewriting {
-            return Ok(());
-        }
-
         let start_time = Instant::now();
 
         if let Err(e) = self.writer.get_ref().sync_all().await {
@@ -334,6 +292,33 @@ impl AofWriterTask {
         self.state
             .latency_monitor
             .add_sample("aof-fsync", vec![], latency);
+
+        self.publish_fsynced();
         Ok(())
     }
+
+    /// Records that everything received so far has been written and is now durable.
+    fn publish_fsynced(&self) {
+        let synced = self.received_seq;
+        self.state
+            .persistence
+            .aof_fsynced_seq
+            .send_if_modified(|seq| {
+                if *seq < synced {
+                    *seq = synced;
+                    true
+                } else {
+                    false
+                }
+            });
+    }
+}
+
+/// Opens an AOF file for appending, creating it if it does not exist.
+async fn open_for_append(path: &Path) -> Result<TokioFile, SpinelDBError> {
+    Ok(OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await?)
 }
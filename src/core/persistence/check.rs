@@ -0,0 +1,322 @@
+// src/core/persistence/check.rs
+
+//! Implements the offline `check-aof` and `check-spldb` tools.
+//!
+//! These run without starting a server. They validate every frame of an AOF and every
+//! record and the checksum of an SPLDB file, report the offset of the last valid
+//! record, and print per-command or per-type statistics. `check-aof --fix` truncates
+//! a damaged AOF to its last valid offset so that the node can be started again.
+
+use super::aof_manifest::{AofFileInfo, AofFileType, AofManifest};
+use super::spldb::{self, SpldbInspection};
+use crate::core::protocol::RespFrameCodec;
+use crate::core::{Command, SpinelDBError};
+use bytes::BytesMut;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tokio::fs::{self, File as TokioFile, OpenOptions};
+use tokio::io::{AsyncReadExt, BufReader};
+use tokio_util::codec::Decoder;
+
+/// The magic string at the start of every SPLDB file.
+const SPLDB_MAGIC: &[u8] = b"SPINELDB";
+
+/// The result of checking a single file of RESP commands.
+#[derive(Debug, Default)]
+pub struct AofFileCheck {
+    /// The size of the file in bytes.
+    pub file_size: u64,
+    /// The number of bytes up to the end of the last complete command or transaction.
+    pub valid_up_to: u64,
+    /// The number of commands and transactions found before `valid_up_to`.
+    pub commands: u64,
+    /// The number of occurrences of each command name.
+    pub per_command: BTreeMap<&'static str, u64>,
+    /// The first problem found in the file, if any.
+    pub error: Option<String>,
+}
+
+impl AofFileCheck {
+    pub fn is_valid(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Checks a file of RESP commands, frame by frame.
+///
+/// A `MULTI` block only counts as valid once its `EXEC` has been read, so truncating
+/// to `valid_up_to` never leaves a partial transaction behind.
+pub async fn check_resp_file(path: &Path) -> Result<AofFileCheck, SpinelDBError> {
+    let file = TokioFile::open(path).await?;
+    let mut report = AofFileCheck {
+        file_size: file.metadata().await?.len(),
+        ..Default::default()
+    };
+    let mut reader = BufReader::new(file);
+    let mut buffer = BytesMut::with_capacity(8192);
+    let mut codec = RespFrameCodec;
+
+    let mut offset: u64 = 0;
+    let mut tx_start: Option<u64> = None;
+    let mut tx_commands: Vec<&'static str> = Vec::new();
+
+    'read: loop {
+        if reader.read_buf(&mut buffer).await? == 0 {
+            if !buffer.is_empty() {
+                report.error = Some(format!(
+                    "{} bytes of incomplete data at offset {}",
+                    buffer.len(),
+                    offset
+                ));
+            }
+            break;
+        }
+
+        loop {
+            let before = buffer.len();
+            let frame = match codec.decode(&mut buffer) {
+                Ok(Some(frame)) => frame,
+                Ok(None) => break,
+                Err(e) => {
+                    report.error = Some(format!("Invalid RESP frame at offset {offset}: {e}"));
+                    break 'read;
+                }
+            };
+            let frame_start = offset;
+            offset += (before - buffer.len()) as u64;
+
+            let command = match Command::try_from(frame) {
+                Ok(command) => command,
+                Err(e) => {
+                    report.error = Some(format!("Invalid command at offset {frame_start}: {e}"));
+                    break 'read;
+                }
+            };
+
+            match command {
+                Command::Multi => {
+                    if tx_start.is_some() {
+                        report.error = Some(format!("Nested MULTI at offset {frame_start}"));
+                        break 'read;
+                    }
+                    tx_start = Some(frame_start);
+                }
+                Command::Exec | Command::Discard => {
+                    if tx_start.take().is_none() {
+                        report.error = Some(format!(
+                            "{} without MULTI at offset {frame_start}",
+                            command.name()
+                        ));
+                        break 'read;
+                    }
+                    if matches!(command, Command::Exec) {
+                        for name in tx_commands.drain(..) {
+                            *report.per_command.entry(name).or_default() += 1;
+                        }
+                        report.commands += 1;
+                    }
+                    tx_commands.clear();
+                    report.valid_up_to = offset;
+                }
+                cmd => {
+                    if tx_start.is_some() {
+                        tx_commands.push(cmd.name());
+                    } else {
+                        *report.per_command.entry(cmd.name()).or_default() += 1;
+                        report.commands += 1;
+                        report.valid_up_to = offset;
+                    }
+                }
+            }
+        }
+    }
+
+    if report.error.is_none()
+        && let Some(start) = tx_start
+    {
+        report.error = Some(format!("Unclosed MULTI at offset {start}"));
+    }
+    Ok(report)
+}
+
+/// Checks an SPLDB file.
+pub async fn check_spldb_file(path: &Path) -> Result<SpldbInspection, SpinelDBError> {
+    let data = fs::read(path).await?;
+    Ok(spldb::inspect(&data))
+}
+
+/// Truncates a file to `len` bytes and syncs it to disk.
+pub async fn truncate_file(path: &Path, len: u64) -> Result<(), SpinelDBError> {
+    let file = OpenOptions::new().write(true).open(path).await?;
+    file.set_len(len).await?;
+    file.sync_all().await?;
+    Ok(())
+}
+
+/// Runs the `check-aof` tool and prints its report.
+///
+/// `path` may be an AOF directory, a manifest, or a single AOF file (such as a legacy
+/// single-file AOF). Only the last file of a multi-part AOF can be repaired, since
+/// truncating any earlier file would lose the commands that follow it.
+/// Returns whether the AOF is valid after any fix was applied.
+pub async fn run_check_aof(path: &Path, fix: bool) -> Result<bool, SpinelDBError> {
+    let Some((dir, files)) = resolve_aof_files(path).await? else {
+        println!("Checking AOF file {}", path.display());
+        return check_and_report_resp_file(path, fix).await;
+    };
+
+    println!(
+        "Checking multi-part AOF in {} ({} file(s))",
+        dir.display(),
+        files.len()
+    );
+    let last = files.len().saturating_sub(1);
+    for (i, info) in files.iter().enumerate() {
+        let file_path = dir.join(&info.file_name);
+        println!();
+        let kind = match info.file_type {
+            AofFileType::Base => "base",
+            AofFileType::Incr => "incr",
+            AofFileType::History => "history",
+        };
+        println!("[{kind}] {}", file_path.display());
+        let is_valid = if info.is_spldb_base() {
+            print_spldb_report(&check_spldb_file(&file_path).await?)
+        } else {
+            check_and_report_resp_file(&file_path, fix && i == last).await?
+        };
+        if !is_valid {
+            if i != last && fix {
+                println!(
+                    "Only the last file of a multi-part AOF can be repaired. Restore this file from a backup."
+                );
+            }
+            return Ok(false);
+        }
+    }
+    println!();
+    println!("AOF is valid.");
+    Ok(true)
+}
+
+/// Runs the `check-spldb` tool and prints its report. Returns whether the file is valid.
+pub async fn run_check_spldb(path: &Path) -> Result<bool, SpinelDBError> {
+    println!("Checking SPLDB file {}", path.display());
+    Ok(print_spldb_report(&check_spldb_file(path).await?))
+}
+
+/// Finds the manifest for `path` and returns the AOF directory and its files in load
+/// order. Returns `None` if `path` is a single file rather than a multi-part AOF.
+async fn resolve_aof_files(
+    path: &Path,
+) -> Result<Option<(PathBuf, Vec<AofFileInfo>)>, SpinelDBError> {
+    let manifest_path = if fs::metadata(path).await?.is_dir() {
+        let mut entries = fs::read_dir(path).await?;
+        let mut found = None;
+        while let Some(entry) = entries.next_entry().await? {
+            if entry.file_name().to_string_lossy().ends_with(".manifest") {
+                found = Some(entry.path());
+                break;
+            }
+        }
+        found.ok_or_else(|| {
+            SpinelDBError::AofError(format!("No AOF manifest found in {}", path.display()))
+        })?
+    } else if path.extension().is_some_and(|ext| ext == "manifest") {
+        path.to_path_buf()
+    } else {
+        return Ok(None);
+    };
+
+    let contents = fs::read_to_string(&manifest_path).await?;
+    let listed = AofManifest::parse(&contents)?;
+    let mut files: Vec<AofFileInfo> = listed
+        .iter()
+        .filter(|f| f.file_type == AofFileType::Base)
+        .cloned()
+        .collect();
+    files.extend(
+        listed
+            .into_iter()
+            .filter(|f| f.file_type == AofFileType::Incr),
+    );
+    let dir = manifest_path
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+    Ok(Some((dir, files)))
+}
+
+/// Checks a single AOF file, which may hold RESP commands or an SPLDB snapshot,
+/// prints the report, and truncates it if `fix` is set.
+async fn check_and_report_resp_file(path: &Path, fix: bool) -> Result<bool, SpinelDBError> {
+    if starts_with_spldb_magic(path).await? {
+        return Ok(print_spldb_report(&check_spldb_file(path).await?));
+    }
+
+    let report = check_resp_file(path).await?;
+    println!(
+        "  {} command(s)/transaction(s), {} of {} bytes valid",
+        report.commands, report.valid_up_to, report.file_size
+    );
+    for (name, count) in &report.per_command {
+        println!("    {name}: {count}");
+    }
+
+    let Some(error) = &report.error else {
+        println!("  OK");
+        return Ok(true);
+    };
+    println!("  ERROR: {error}");
+    println!("  Last valid offset: {}", report.valid_up_to);
+    let discarded = report.file_size - report.valid_up_to;
+
+    if !fix {
+        println!(
+            "  Run with --fix to truncate the file to the last valid offset ({discarded} bytes would be discarded)."
+        );
+        return Ok(false);
+    }
+    truncate_file(path, report.valid_up_to).await?;
+    println!(
+        "  Fixed: truncated to {} bytes ({discarded} bytes discarded).",
+        report.valid_up_to
+    );
+    Ok(true)
+}
+
+/// Prints the report for an SPLDB file and returns whether it is valid.
+fn print_spldb_report(report: &SpldbInspection) -> bool {
+    for (key, value) in &report.aux_fields {
+        println!("  aux {key}: {value}");
+    }
+    println!(
+        "  {} key(s), {} with an expiry",
+        report.total_keys(),
+        report.keys_with_expiry
+    );
+    for (db, count) in &report.keys_per_db {
+        println!("    db{db}: {count}");
+    }
+    for (type_name, count) in &report.keys_per_type {
+        println!("    {type_name}: {count}");
+    }
+
+    match &report.error {
+        Some(error) => {
+            println!("  ERROR: {error}");
+            println!("  Last valid offset: {}", report.valid_up_to);
+            false
+        }
+        None => {
+            println!("  Checksum OK");
+            true
+        }
+    }
+}
+
+async fn starts_with_spldb_magic(path: &Path) -> Result<bool, SpinelDBError> {
+    let mut file = TokioFile::open(path).await?;
+    let mut magic = [0u8; SPLDB_MAGIC.len()];
+    Ok(file.read_exact(&mut magic).await.is_ok() && magic == SPLDB_MAGIC)
+}
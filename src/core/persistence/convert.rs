@@ -0,0 +1,67 @@
+// src/core/persistence/convert.rs
+
+//! Implements the offline `convert` tool, which converts snapshots between the SPLDB
+//! and Redis RDB formats without starting a server.
+//!
+//! The input format is detected from its magic bytes, and the output is written in
+//! the other format. This allows migrating a Redis `dump.rdb` into SpinelDB, and
+//! exporting SpinelDB data back to Redis for a rollback.
+
+use super::{rdb, spldb};
+use crate::core::SpinelDBError;
+use crate::core::database::Db;
+use bytes::Bytes;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::fs::{self, File as TokioFile};
+use tokio::io::{AsyncWriteExt, BufWriter};
+
+/// The number of databases available while converting. This matches the Redis default.
+const CONVERT_DATABASES: usize = 16;
+
+/// Runs the `convert` tool. Returns the number of keys skipped because their type
+/// cannot be represented in the output format.
+pub async fn run_convert(input: &Path, output: &Path) -> Result<u64, SpinelDBError> {
+    let data = Bytes::from(fs::read(input).await?);
+    let dbs: Vec<Arc<Db>> = (0..CONVERT_DATABASES)
+        .map(|_| Arc::new(Db::new()))
+        .collect();
+
+    let to_rdb = !rdb::is_rdb(&data);
+    if to_rdb {
+        println!("Converting SPLDB file {} to RDB", input.display());
+        spldb::load_from_bytes(&data, &dbs).await?;
+    } else {
+        println!("Converting RDB file {} to SPLDB", input.display());
+        rdb::load_from_bytes(&data, &dbs).await?;
+    }
+    drop(data);
+
+    for (db_index, db) in dbs.iter().enumerate() {
+        let keys = db.get_key_count();
+        if keys > 0 {
+            println!("  db{db_index}: {keys} key(s)");
+        }
+    }
+
+    // Write to a temporary file first so that a failed conversion never leaves a
+    // partial output file behind.
+    let temp_path = output.with_extension("convert.tmp");
+    let mut writer = BufWriter::new(TokioFile::create(&temp_path).await?);
+    let skipped = if to_rdb {
+        rdb::write_database(&mut writer, &dbs).await?
+    } else {
+        spldb::write_database(&mut writer, &dbs, &[]).await?;
+        0
+    };
+    writer.flush().await?;
+    writer.get_ref().sync_all().await?;
+    drop(writer);
+    fs::rename(&temp_path, output).await?;
+
+    if skipped > 0 {
+        println!("  Skipped {skipped} key(s) with types that have no Redis equivalent.");
+    }
+    println!("Wrote {}", output.display());
+    Ok(skipped)
+}
@@ -8,13 +8,18 @@
 
 // Declare the persistence sub-modules.
 mod aof_loader;
+pub mod aof_manifest;
 mod aof_rewriter;
 mod aof_writer;
+pub mod check;
+pub mod convert;
+pub mod rdb;
 pub mod spldb;
 pub mod spldb_saver;
 
 // Re-export the primary public types from the sub-modules.
 // This creates a clean public facade for the persistence system.
 pub use aof_loader::AofLoader;
+pub use aof_manifest::AofManifest;
 pub use aof_rewriter::rewrite_aof;
 pub use aof_writer::AofWriterTask;
@@ -0,0 +1,1151 @@
+// src/core/persistence/rdb.rs
+
+//! Implements import and export of Redis RDB files.
+//!
+//! Loading supports RDB versions 9 to 11 (Redis 5.0 to 7.2), including the compact
+//! encodings Redis uses on disk: ziplists, listpacks, intsets, zipmaps, quicklists,
+//! LZF-compressed strings and streams. Module types and functions cannot be
+//! represented in SpinelDB and are rejected or skipped.
+//!
+//! Exporting writes an RDB version 9 file that any Redis from 5.0 onward can load.
+//! Values use the plain (non-compact) encodings, which Redis converts on load.
+//! SpinelDB-specific types (JSON, HyperLogLog, Bloom filters and HTTP cache entries)
+//! have no Redis equivalent and are skipped.
+
+use crate::core::database::zset::SortedSet;
+use crate::core::database::{Db, Snapshot};
+use crate::core::storage::data_types::{DataValue, StoredValue};
+use crate::core::storage::stream::{
+    Consumer, ConsumerGroup, PendingEntryInfo, Stream, StreamEntry, StreamId,
+};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use crc::{CRC_64_REDIS, Crc};
+use indexmap::IndexMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::io::{self, Error, ErrorKind};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::io::AsyncWriteExt;
+use tracing::{info, warn};
+
+// --- RDB Constants ---
+pub const RDB_MAGIC: &[u8] = b"REDIS";
+const RDB_MIN_VERSION: u32 = 9;
+const RDB_MAX_VERSION: u32 = 11;
+const RDB_EXPORT_VERSION: &[u8] = b"0009";
+
+const RDB_OPCODE_FUNCTION2: u8 = 0xF5;
+const RDB_OPCODE_FUNCTION_PRE_GA: u8 = 0xF6;
+const RDB_OPCODE_MODULE_AUX: u8 = 0xF7;
+const RDB_OPCODE_IDLE: u8 = 0xF8;
+const RDB_OPCODE_FREQ: u8 = 0xF9;
+const RDB_OPCODE_AUX: u8 = 0xFA;
+const RDB_OPCODE_RESIZEDB: u8 = 0xFB;
+const RDB_OPCODE_EXPIRETIME_MS: u8 = 0xFC;
+const RDB_OPCODE_EXPIRETIME: u8 = 0xFD;
+const RDB_OPCODE_SELECTDB: u8 = 0xFE;
+const RDB_OPCODE_EOF: u8 = 0xFF;
+
+const RDB_TYPE_STRING: u8 = 0;
+const RDB_TYPE_LIST: u8 = 1;
+const RDB_TYPE_SET: u8 = 2;
+const RDB_TYPE_ZSET: u8 = 3;
+const RDB_TYPE_HASH: u8 = 4;
+const RDB_TYPE_ZSET_2: u8 = 5;
+const RDB_TYPE_MODULE_PRE_GA: u8 = 6;
+const RDB_TYPE_MODULE_2: u8 = 7;
+const RDB_TYPE_HASH_ZIPMAP: u8 = 9;
+const RDB_TYPE_LIST_ZIPLIST: u8 = 10;
+const RDB_TYPE_SET_INTSET: u8 = 11;
+const RDB_TYPE_ZSET_ZIPLIST: u8 = 12;
+const RDB_TYPE_HASH_ZIPLIST: u8 = 13;
+const RDB_TYPE_LIST_QUICKLIST: u8 = 14;
+const RDB_TYPE_STREAM_LISTPACKS: u8 = 15;
+const RDB_TYPE_HASH_LISTPACK: u8 = 16;
+const RDB_TYPE_ZSET_LISTPACK: u8 = 17;
+const RDB_TYPE_LIST_QUICKLIST_2: u8 = 18;
+const RDB_TYPE_STREAM_LISTPACKS_2: u8 = 19;
+const RDB_TYPE_SET_LISTPACK: u8 = 20;
+const RDB_TYPE_STREAM_LISTPACKS_3: u8 = 21;
+
+const RDB_ENC_INT8: u8 = 0;
+const RDB_ENC_INT16: u8 = 1;
+const RDB_ENC_INT32: u8 = 2;
+const RDB_ENC_LZF: u8 = 3;
+
+const QUICKLIST_NODE_CONTAINER_PLAIN: u64 = 1;
+const QUICKLIST_NODE_CONTAINER_PACKED: u64 = 2;
+
+const STREAM_ITEM_FLAG_DELETED: i64 = 1 << 0;
+const STREAM_ITEM_FLAG_SAMEFIELDS: i64 = 1 << 1;
+/// The maximum number of entries per listpack node when exporting a stream,
+/// matching the Redis default for `stream-node-max-entries`.
+const STREAM_NODE_MAX_ENTRIES: usize = 100;
+
+const CHECKSUM_ALGO: Crc<u64> = Crc::<u64>::new(&CRC_64_REDIS);
+
+/// Returns true if `data` starts with the Redis RDB magic string.
+pub fn is_rdb(data: &[u8]) -> bool {
+    data.starts_with(RDB_MAGIC)
+}
+
+// --- RDB Loader ---
+
+/// A single key read from an RDB file.
+pub struct RdbEntry {
+    pub db_index: usize,
+    pub key: Bytes,
+    pub value: StoredValue,
+}
+
+/// A parser that reads the keys of an RDB file one at a time.
+pub struct RdbParser {
+    cursor: Bytes,
+    version: u32,
+    db_index: usize,
+    expiry_ms: Option<u64>,
+}
+
+impl RdbParser {
+    /// Validates the header and checksum of an RDB file and prepares to read its keys.
+    pub fn new(data: &Bytes) -> io::Result<Self> {
+        if data.len() < RDB_MAGIC.len() + 4 || !is_rdb(data) {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "Invalid RDB magic string",
+            ));
+        }
+        let version = std::str::from_utf8(&data[RDB_MAGIC.len()..RDB_MAGIC.len() + 4])
+            .ok()
+            .and_then(|v| v.parse::<u32>().ok())
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "Invalid RDB version"))?;
+        if !(RDB_MIN_VERSION..=RDB_MAX_VERSION).contains(&version) {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "Unsupported RDB version {version} (supported: {RDB_MIN_VERSION} to {RDB_MAX_VERSION})"
+                ),
+            ));
+        }
+
+        if data.len() < RDB_MAGIC.len() + 4 + 9 {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "RDB file is too short for checksum.",
+            ));
+        }
+        let (data_part, checksum_part) = data.split_at(data.len() - 8);
+        let file_checksum = (&checksum_part[..]).get_u64_le();
+        // A zero checksum means the file was written with `rdbchecksum no`.
+        if file_checksum != 0 && CHECKSUM_ALGO.checksum(data_part) != file_checksum {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "RDB checksum mismatch. File may be corrupt.",
+            ));
+        }
+
+        Ok(Self {
+            cursor: data.slice(RDB_MAGIC.len() + 4..data.len() - 8),
+            version,
+            db_index: 0,
+            expiry_ms: None,
+        })
+    }
+
+    /// Returns the RDB version of the file.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Reads the next key, or returns `None` once the EOF opcode is reached.
+    /// Keys that have already expired are returned too, with an expiry in the past.
+    pub fn next_entry(&mut self) -> io::Result<Option<RdbEntry>> {
+        loop {
+            let opcode = read_u8(&mut self.cursor)?;
+            match opcode {
+                RDB_OPCODE_EOF => return Ok(None),
+                RDB_OPCODE_AUX => {
+                    read_string(&mut self.cursor)?;
+                    read_string(&mut self.cursor)?;
+                }
+                RDB_OPCODE_SELECTDB => {
+                    self.db_index = read_length(&mut self.cursor)? as usize;
+                }
+                RDB_OPCODE_RESIZEDB => {
+                    read_length(&mut self.cursor)?;
+                    read_length(&mut self.cursor)?;
+                }
+                RDB_OPCODE_EXPIRETIME_MS => {
+                    self.expiry_ms = Some(read_u64_le(&mut self.cursor)?);
+                }
+                RDB_OPCODE_EXPIRETIME => {
+                    self.expiry_ms = Some(u64::from(read_u32_le(&mut self.cursor)?) * 1000);
+                }
+                RDB_OPCODE_IDLE => {
+                    read_length(&mut self.cursor)?;
+                }
+                RDB_OPCODE_FREQ => {
+                    read_u8(&mut self.cursor)?;
+                }
+                RDB_OPCODE_FUNCTION2 => {
+                    read_string(&mut self.cursor)?;
+                    warn!("RDB file contains a Redis function library. Skipping.");
+                }
+                RDB_OPCODE_FUNCTION_PRE_GA | RDB_OPCODE_MODULE_AUX => {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        "RDB files with module data or pre-release functions are not supported",
+                    ));
+                }
+                value_type => {
+                    let key = read_string(&mut self.cursor)?;
+                    let data = read_value(&mut self.cursor, value_type)?;
+                    let mut value = StoredValue::new(data);
+                    value.expiry = self.expiry_ms.take().map(instant_from_unix_ms);
+                    return Ok(Some(RdbEntry {
+                        db_index: self.db_index,
+                        key,
+                        value,
+                    }));
+                }
+            }
+        }
+    }
+}
+
+/// Loads a full RDB file from a byte slice into the databases.
+pub async fn load_from_bytes(data: &Bytes, dbs: &[Arc<Db>]) -> io::Result<()> {
+    let mut parser = RdbParser::new(data)?;
+    info!(
+        "RDB checksum verified. Loading RDB version {}.",
+        parser.version()
+    );
+
+    // Clear all databases before loading data.
+    for db in dbs.iter() {
+        let guards = db.lock_all_shards().await;
+        for mut guard in guards {
+            guard.clear();
+        }
+    }
+
+    let mut loaded = 0u64;
+    while let Some(entry) = parser.next_entry()? {
+        if entry.value.is_expired() {
+            continue;
+        }
+        let Some(db) = dbs.get(entry.db_index) else {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("RDB contains out-of-range DB index {}", entry.db_index),
+            ));
+        };
+        db.insert_value_from_load(entry.key, entry.value).await;
+        loaded += 1;
+    }
+    info!("Loaded {} keys from RDB.", loaded);
+    Ok(())
+}
+
+/// Converts an absolute Unix time in milliseconds to an `Instant`.
+/// Times in the past map to the current instant, so the key is treated as expired.
+fn instant_from_unix_ms(ms: u64) -> Instant {
+    let expiry_time = UNIX_EPOCH + Duration::from_millis(ms);
+    let now = Instant::now();
+    match expiry_time.duration_since(SystemTime::now()) {
+        Ok(remaining) => now + remaining,
+        Err(_) => now,
+    }
+}
+
+/// Reads the value of a key of the given RDB type.
+fn read_value(cursor: &mut Bytes, value_type: u8) -> io::Result<DataValue> {
+    match value_type {
+        RDB_TYPE_STRING => Ok(DataValue::String(read_string(cursor)?)),
+        RDB_TYPE_LIST => {
+            let len = read_length(cursor)? as usize;
+            let mut list = VecDeque::with_capacity(capacity(len, cursor));
+            for _ in 0..len {
+                list.push_back(read_string(cursor)?);
+            }
+            Ok(DataValue::List(list))
+        }
+        RDB_TYPE_LIST_ZIPLIST => Ok(DataValue::List(
+            decode_ziplist(&read_string(cursor)?)?.into(),
+        )),
+        RDB_TYPE_LIST_QUICKLIST | RDB_TYPE_LIST_QUICKLIST_2 => {
+            let nodes = read_length(cursor)?;
+            let mut list = VecDeque::new();
+            for _ in 0..nodes {
+                if value_type == RDB_TYPE_LIST_QUICKLIST {
+                    list.extend(decode_ziplist(&read_string(cursor)?)?);
+                    continue;
+                }
+                match read_length(cursor)? {
+                    QUICKLIST_NODE_CONTAINER_PLAIN => list.push_back(read_string(cursor)?),
+                    QUICKLIST_NODE_CONTAINER_PACKED => {
+                        list.extend(decode_listpack(&read_string(cursor)?)?)
+                    }
+                    other => return Err(invalid(format!("Unknown quicklist container {other}"))),
+                }
+            }
+            Ok(DataValue::List(list))
+        }
+        RDB_TYPE_SET => {
+            let len = read_length(cursor)? as usize;
+            let mut set = HashSet::with_capacity(capacity(len, cursor));
+            for _ in 0..len {
+                set.insert(read_string(cursor)?);
+            }
+            Ok(DataValue::Set(set))
+        }
+        RDB_TYPE_SET_INTSET => Ok(DataValue::Set(
+            decode_intset(&read_string(cursor)?)?.into_iter().collect(),
+        )),
+        RDB_TYPE_SET_LISTPACK => Ok(DataValue::Set(
+            decode_listpack(&read_string(cursor)?)?
+                .into_iter()
+                .collect(),
+        )),
+        RDB_TYPE_ZSET | RDB_TYPE_ZSET_2 => {
+            let len = read_length(cursor)?;
+            let mut zset = SortedSet::new();
+            for _ in 0..len {
+                let member = read_string(cursor)?;
+                let score = if value_type == RDB_TYPE_ZSET_2 {
+                    f64::from_bits(read_u64_le(cursor)?)
+                } else {
+                    read_legacy_double(cursor)?
+                };
+                zset.add(score, member);
+            }
+            Ok(DataValue::SortedSet(zset))
+        }
+        RDB_TYPE_ZSET_ZIPLIST | RDB_TYPE_ZSET_LISTPACK => {
+            let blob = read_string(cursor)?;
+            let items = if value_type == RDB_TYPE_ZSET_ZIPLIST {
+                decode_ziplist(&blob)?
+            } else {
+                decode_listpack(&blob)?
+            };
+            let mut zset = SortedSet::new();
+            for pair in pairs(items)? {
+                zset.add(parse_f64(&pair.1)?, pair.0);
+            }
+            Ok(DataValue::SortedSet(zset))
+        }
+        RDB_TYPE_HASH => {
+            let len = read_length(cursor)? as usize;
+            let mut hash = IndexMap::with_capacity(capacity(len, cursor));
+            for _ in 0..len {
+                let field = read_string(cursor)?;
+                let value = read_string(cursor)?;
+                hash.insert(field, value);
+            }
+            Ok(DataValue::Hash(hash))
+        }
+        RDB_TYPE_HASH_ZIPMAP | RDB_TYPE_HASH_ZIPLIST | RDB_TYPE_HASH_LISTPACK => {
+            let blob = read_string(cursor)?;
+            let items = match value_type {
+                RDB_TYPE_HASH_ZIPMAP => decode_zipmap(&blob)?,
+                RDB_TYPE_HASH_ZIPLIST => decode_ziplist(&blob)?,
+                _ => decode_listpack(&blob)?,
+            };
+            Ok(DataValue::Hash(pairs(items)?.into_iter().collect()))
+        }
+        RDB_TYPE_STREAM_LISTPACKS | RDB_TYPE_STREAM_LISTPACKS_2 | RDB_TYPE_STREAM_LISTPACKS_3 => {
+            read_stream(cursor, value_type).map(DataValue::Stream)
+        }
+        RDB_TYPE_MODULE_PRE_GA | RDB_TYPE_MODULE_2 => Err(invalid(
+            "RDB files with module types are not supported".to_string(),
+        )),
+        other => Err(invalid(format!("Unknown RDB value type: {other}"))),
+    }
+}
+
+/// Reads a stream stored as a radix tree of listpacks.
+fn read_stream(cursor: &mut Bytes, value_type: u8) -> io::Result<Stream> {
+    let mut stream = Stream::new();
+
+    let nodes = read_length(cursor)?;
+    for _ in 0..nodes {
+        let node_key = read_string(cursor)?;
+        if node_key.len() != 16 {
+            return Err(invalid("Invalid stream node key".to_string()));
+        }
+        let master_id = stream_id_from_raw(&node_key);
+        let items = decode_listpack(&read_string(cursor)?)?;
+        read_stream_node(&items, master_id, &mut stream)?;
+    }
+
+    stream.length = read_length(cursor)?;
+    stream.last_generated_id = StreamId::new(read_length(cursor)?, read_length(cursor)?);
+    if value_type >= RDB_TYPE_STREAM_LISTPACKS_2 {
+        // First ID, max deleted entry ID and entries added. SpinelDB derives these.
+        for _ in 0..5 {
+            read_length(cursor)?;
+        }
+    }
+
+    let num_groups = read_length(cursor)?;
+    for _ in 0..num_groups {
+        let name = read_string(cursor)?;
+        let last_delivered_id = StreamId::new(read_length(cursor)?, read_length(cursor)?);
+        if value_type >= RDB_TYPE_STREAM_LISTPACKS_2 {
+            // Entries read, used by Redis for consumer group lag.
+            read_length(cursor)?;
+        }
+
+        let mut group_pel = BTreeMap::new();
+        let num_pending = read_length(cursor)?;
+        for _ in 0..num_pending {
+            let id = stream_id_from_raw(&read_raw(cursor, 16)?);
+            let delivery_time_ms = read_u64_le(cursor)?;
+            let delivery_count = read_length(cursor)?;
+            group_pel.insert(id, (delivery_time_ms, delivery_count));
+        }
+
+        let mut group = ConsumerGroup {
+            name: name.clone(),
+            last_delivered_id,
+            consumers: HashMap::new(),
+            pending_entries: BTreeMap::new(),
+            idle_index: BTreeSet::new(),
+        };
+        let num_consumers = read_length(cursor)?;
+        for _ in 0..num_consumers {
+            let consumer_name = read_string(cursor)?;
+            let seen_time_ms = read_u64_le(cursor)?;
+            if value_type >= RDB_TYPE_STREAM_LISTPACKS_3 {
+                // Active time.
+                read_u64_le(cursor)?;
+            }
+            let mut pending_ids = BTreeSet::new();
+            let num_consumer_pending = read_length(cursor)?;
+            for _ in 0..num_consumer_pending {
+                let id = stream_id_from_raw(&read_raw(cursor, 16)?);
+                let Some((delivery_time_ms, delivery_count)) = group_pel.get(&id).copied() else {
+                    return Err(invalid(
+                        "Stream consumer PEL entry is missing from the group PEL".to_string(),
+                    ));
+                };
+                group.pending_entries.insert(
+                    id,
+                    PendingEntryInfo {
+                        consumer_name: consumer_name.clone(),
+                        delivery_count,
+                        delivery_time_ms,
+                    },
+                );
+                group.idle_index.insert((delivery_time_ms, id));
+                pending_ids.insert(id);
+            }
+            group.consumers.insert(
+                consumer_name.clone(),
+                Consumer {
+                    name: consumer_name,
+                    seen_time_ms,
+                    pending_ids,
+                },
+            );
+        }
+        stream.groups.insert(name, group);
+    }
+    Ok(stream)
+}
+
+/// Reads the entries of a single stream listpack node.
+///
+/// A node starts with a master entry (`count`, `deleted`, the master field names and
+/// a `0` terminator). Each following entry holds flags, the ID as a delta from the
+/// master ID, its fields (omitted when they match the master fields), its values, and
+/// the number of listpack elements it used.
+fn read_stream_node(items: &[Bytes], master_id: StreamId, stream: &mut Stream) -> io::Result<()> {
+    let mut items = items.iter();
+    let count = next_int_from(&mut items)?;
+    let deleted = next_int_from(&mut items)?;
+    let num_master_fields = next_int_from(&mut items)?;
+    let mut master_fields = Vec::new();
+    for _ in 0..num_master_fields {
+        master_fields.push(next_item(&mut items)?);
+    }
+    if next_int_from(&mut items)? != 0 {
+        return Err(invalid(
+            "Invalid stream master entry terminator".to_string(),
+        ));
+    }
+
+    for _ in 0..(count + deleted) {
+        let flags = next_int_from(&mut items)?;
+        let ms = master_id
+            .timestamp_ms
+            .wrapping_add(next_int_from(&mut items)? as u64);
+        let seq = master_id
+            .sequence
+            .wrapping_add(next_int_from(&mut items)? as u64);
+        let mut fields = IndexMap::new();
+        if flags & STREAM_ITEM_FLAG_SAMEFIELDS != 0 {
+            for field in &master_fields {
+                fields.insert(field.clone(), next_item(&mut items)?);
+            }
+        } else {
+            let num_fields = next_int_from(&mut items)?;
+            for _ in 0..num_fields {
+                let field = next_item(&mut items)?;
+                fields.insert(field, next_item(&mut items)?);
+            }
+        }
+        // The lp-count element, used by Redis for backward iteration.
+        next_item(&mut items)?;
+
+        if flags & STREAM_ITEM_FLAG_DELETED == 0 {
+            let id = StreamId::new(ms, seq);
+            stream.entries.insert(id, StreamEntry { id, fields });
+        }
+    }
+    Ok(())
+}
+
+fn next_item<'a>(items: &mut impl Iterator<Item = &'a Bytes>) -> io::Result<Bytes> {
+    items
+        .next()
+        .cloned()
+        .ok_or_else(|| invalid("Truncated stream listpack".to_string()))
+}
+
+fn next_int_from<'a>(items: &mut impl Iterator<Item = &'a Bytes>) -> io::Result<i64> {
+    parse_i64(&next_item(items)?)
+}
+
+fn stream_id_from_raw(raw: &[u8]) -> StreamId {
+    let mut raw = raw;
+    StreamId::new(raw.get_u64(), raw.get_u64())
+}
+
+/// Groups a flat list of items into (field, value) pairs.
+fn pairs(items: Vec<Bytes>) -> io::Result<Vec<(Bytes, Bytes)>> {
+    if !items.len().is_multiple_of(2) {
+        return Err(invalid(
+            "Odd number of elements in an encoded hash or zset".to_string(),
+        ));
+    }
+    let mut iter = items.into_iter();
+    let mut result = Vec::with_capacity(iter.len() / 2);
+    while let (Some(a), Some(b)) = (iter.next(), iter.next()) {
+        result.push((a, b));
+    }
+    Ok(result)
+}
+
+// --- Compact Encodings ---
+
+/// Decodes a listpack, rendering integer elements as decimal strings.
+fn decode_listpack(blob: &[u8]) -> io::Result<Vec<Bytes>> {
+    let mut c = Bytes::copy_from_slice(blob);
+    if c.remaining() < 6 {
+        return Err(invalid("Listpack header is truncated".to_string()));
+    }
+    c.advance(6);
+    let mut items = Vec::new();
+    loop {
+        let b = read_u8(&mut c)?;
+        let (item, entry_len) = match b {
+            0xFF => return Ok(items),
+            _ if b & 0x80 == 0 => (int_bytes(i64::from(b & 0x7F)), 1),
+            _ if b & 0xC0 == 0x80 => {
+                let len = (b & 0x3F) as usize;
+                (read_raw(&mut c, len)?, 1 + len)
+            }
+            _ if b & 0xE0 == 0xC0 => {
+                let v = (i64::from(b & 0x1F) << 8) | i64::from(read_u8(&mut c)?);
+                (int_bytes(sign_extend(v, 13)), 2)
+            }
+            _ if b & 0xF0 == 0xE0 => {
+                let len = (((b & 0x0F) as usize) << 8) | read_u8(&mut c)? as usize;
+                (read_raw(&mut c, len)?, 2 + len)
+            }
+            0xF0 => {
+                let len = read_u32_le(&mut c)? as usize;
+                (read_raw(&mut c, len)?, 5 + len)
+            }
+            0xF1 => (int_bytes(read_int_le(&mut c, 2)?), 3),
+            0xF2 => (int_bytes(read_int_le(&mut c, 3)?), 4),
+            0xF3 => (int_bytes(read_int_le(&mut c, 4)?), 5),
+            0xF4 => (int_bytes(read_int_le(&mut c, 8)?), 9),
+            _ => return Err(invalid(format!("Invalid listpack encoding {b:#04x}"))),
+        };
+        read_raw(&mut c, listpack_backlen_size(entry_len))?;
+        items.push(item);
+    }
+}
+
+/// Decodes a ziplist, rendering integer elements as decimal strings.
+fn decode_ziplist(blob: &[u8]) -> io::Result<Vec<Bytes>> {
+    let mut c = Bytes::copy_from_slice(blob);
+    if c.remaining() < 10 {
+        return Err(invalid("Ziplist header is truncated".to_string()));
+    }
+    c.advance(10);
+    let mut items = Vec::new();
+    loop {
+        let prevlen = read_u8(&mut c)?;
+        if prevlen == 0xFF {
+            return Ok(items);
+        }
+        if prevlen == 0xFE {
+            read_raw(&mut c, 4)?;
+        }
+        let b = read_u8(&mut c)?;
+        let item = match b >> 6 {
+            0 => read_raw(&mut c, (b & 0x3F) as usize)?,
+            1 => {
+                let len = (((b & 0x3F) as usize) << 8) | read_u8(&mut c)? as usize;
+                read_raw(&mut c, len)?
+            }
+            2 => {
+                let len = read_raw(&mut c, 4)?.get_u32() as usize;
+                read_raw(&mut c, len)?
+            }
+            _ => match b {
+                0xC0 => int_bytes(read_int_le(&mut c, 2)?),
+                0xD0 => int_bytes(read_int_le(&mut c, 4)?),
+                0xE0 => int_bytes(read_int_le(&mut c, 8)?),
+                0xF0 => int_bytes(read_int_le(&mut c, 3)?),
+                0xFE => int_bytes(read_int_le(&mut c, 1)?),
+                0xF1..=0xFD => int_bytes(i64::from(b & 0x0F) - 1),
+                _ => return Err(invalid(format!("Invalid ziplist encoding {b:#04x}"))),
+            },
+        };
+        items.push(item);
+    }
+}
+
+/// Decodes an intset into decimal strings.
+fn decode_intset(blob: &[u8]) -> io::Result<Vec<Bytes>> {
+    let mut c = Bytes::copy_from_slice(blob);
+    let width = read_u32_le(&mut c)? as usize;
+    if !matches!(width, 2 | 4 | 8) {
+        return Err(invalid(format!("Invalid intset encoding {width}")));
+    }
+    let len = read_u32_le(&mut c)? as usize;
+    let mut items = Vec::with_capacity(capacity(len, &c));
+    for _ in 0..len {
+        items.push(int_bytes(read_int_le(&mut c, width)?));
+    }
+    Ok(items)
+}
+
+/// Decodes a zipmap (the pre-2.6 hash encoding) into a flat list of fields and values.
+fn decode_zipmap(blob: &[u8]) -> io::Result<Vec<Bytes>> {
+    let mut c = Bytes::copy_from_slice(blob);
+    read_u8(&mut c)?;
+    let mut items = Vec::new();
+    let read_len = |c: &mut Bytes| -> io::Result<Option<usize>> {
+        match read_u8(c)? {
+            0xFF => Ok(None),
+            0xFE => Ok(Some(read_u32_le(c)? as usize)),
+            len => Ok(Some(len as usize)),
+        }
+    };
+    while let Some(key_len) = read_len(&mut c)? {
+        items.push(read_raw(&mut c, key_len)?);
+        let value_len =
+            read_len(&mut c)?.ok_or_else(|| invalid("Zipmap is missing a value".to_string()))?;
+        let free = read_u8(&mut c)? as usize;
+        items.push(read_raw(&mut c, value_len)?);
+        read_raw(&mut c, free)?;
+    }
+    Ok(items)
+}
+
+/// Returns the number of bytes used to encode the back-length of a listpack entry.
+fn listpack_backlen_size(entry_len: usize) -> usize {
+    match entry_len {
+        0..=127 => 1,
+        128..16383 => 2,
+        16383..2097151 => 3,
+        2097151..268435455 => 4,
+        _ => 5,
+    }
+}
+
+fn sign_extend(value: i64, bits: u32) -> i64 {
+    let shift = 64 - bits;
+    (value << shift) >> shift
+}
+
+fn int_bytes(value: i64) -> Bytes {
+    Bytes::from(value.to_string())
+}
+
+/// The most output LZF can produce per input byte: a 3-byte back reference expands to
+/// at most 264 bytes.
+const LZF_MAX_EXPANSION: usize = 88;
+
+/// Decompresses an LZF-compressed string, as written by Redis with `rdbcompression yes`.
+fn lzf_decompress(input: &[u8], out_len: usize) -> io::Result<Bytes> {
+    // The length comes straight from the file, so check it against what the input can
+    // actually produce before trusting it, and let the buffer grow with the output.
+    if out_len > input.len().saturating_mul(LZF_MAX_EXPANSION) {
+        return Err(invalid(format!(
+            "LZF length {out_len} is too large for {} compressed bytes",
+            input.len()
+        )));
+    }
+    let mut out: Vec<u8> = Vec::with_capacity(out_len.min(input.len()));
+    let mut i = 0;
+    let truncated = || invalid("Truncated LZF data".to_string());
+    while i < input.len() {
+        let ctrl = input[i] as usize;
+        i += 1;
+        if ctrl < 32 {
+            let len = ctrl + 1;
+            let literal = input.get(i..i + len).ok_or_else(truncated)?;
+            out.extend_from_slice(literal);
+            i += len;
+        } else {
+            let mut len = ctrl >> 5;
+            if len == 7 {
+                len += *input.get(i).ok_or_else(truncated)? as usize;
+                i += 1;
+            }
+            let back = ((ctrl & 0x1F) << 8) + *input.get(i).ok_or_else(truncated)? as usize + 1;
+            i += 1;
+            if back > out.len() {
+                return Err(invalid("Invalid LZF back reference".to_string()));
+            }
+            let start = out.len() - back;
+            for k in 0..len + 2 {
+                out.push(out[start + k]);
+            }
+        }
+        if out.len() > out_len {
+            return Err(invalid("LZF data decompresses past its length".to_string()));
+        }
+    }
+    if out.len() != out_len {
+        return Err(invalid("LZF data has an unexpected length".to_string()));
+    }
+    Ok(Bytes::from(out))
+}
+
+// --- Primitive Readers ---
+
+fn invalid(message: String) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+fn eof() -> Error {
+    Error::new(ErrorKind::UnexpectedEof, "RDB data ended unexpectedly")
+}
+
+/// Caps a length read from the file, so that a corrupt length cannot cause a huge allocation.
+fn capacity(len: usize, cursor: &Bytes) -> usize {
+    len.min(cursor.remaining())
+}
+
+fn read_u8(c: &mut Bytes) -> io::Result<u8> {
+    if !c.has_remaining() {
+        return Err(eof());
+    }
+    Ok(c.get_u8())
+}
+
+fn read_u32_le(c: &mut Bytes) -> io::Result<u32> {
+    if c.remaining() < 4 {
+        return Err(eof());
+    }
+    Ok(c.get_u32_le())
+}
+
+fn read_u64_le(c: &mut Bytes) -> io::Result<u64> {
+    if c.remaining() < 8 {
+        return Err(eof());
+    }
+    Ok(c.get_u64_le())
+}
+
+fn read_raw(c: &mut Bytes, len: usize) -> io::Result<Bytes> {
+    if c.remaining() < len {
+        return Err(eof());
+    }
+    Ok(c.split_to(len))
+}
+
+/// Reads a signed little-endian integer of `width` bytes.
+fn read_int_le(c: &mut Bytes, width: usize) -> io::Result<i64> {
+    let raw = read_raw(c, width)?;
+    let mut value: i64 = 0;
+    for (i, byte) in raw.iter().enumerate() {
+        value |= i64::from(*byte) << (8 * i);
+    }
+    Ok(sign_extend(value, (width * 8) as u32))
+}
+
+/// Reads a length prefix. Returns the special encoding type instead if the length
+/// is an encoded string marker.
+fn read_length_or_encoding(c: &mut Bytes) -> io::Result<Result<u64, u8>> {
+    let first = read_u8(c)?;
+    match first >> 6 {
+        0 => Ok(Ok(u64::from(first & 0x3F))),
+        1 => Ok(Ok((u64::from(first & 0x3F) << 8) | u64::from(read_u8(c)?))),
+        2 => match first {
+            0x80 => Ok(Ok(u64::from(read_raw(c, 4)?.get_u32()))),
+            0x81 => Ok(Ok(read_raw(c, 8)?.get_u64())),
+            _ => Err(invalid(format!("Unknown RDB length encoding {first:#04x}"))),
+        },
+        _ => Ok(Err(first & 0x3F)),
+    }
+}
+
+fn read_length(c: &mut Bytes) -> io::Result<u64> {
+    read_length_or_encoding(c)?
+        .map_err(|_| invalid("Unexpected encoded string where a length was expected".to_string()))
+}
+
+/// Reads a string, which may be stored as an integer or LZF-compressed.
+fn read_string(c: &mut Bytes) -> io::Result<Bytes> {
+    match read_length_or_encoding(c)? {
+        Ok(len) => read_raw(c, len as usize),
+        Err(RDB_ENC_INT8) => Ok(int_bytes(read_int_le(c, 1)?)),
+        Err(RDB_ENC_INT16) => Ok(int_bytes(read_int_le(c, 2)?)),
+        Err(RDB_ENC_INT32) => Ok(int_bytes(read_int_le(c, 4)?)),
+        Err(RDB_ENC_LZF) => {
+            let compressed_len = read_length(c)? as usize;
+            let len = read_length(c)? as usize;
+            let compressed = read_raw(c, compressed_len)?;
+            lzf_decompress(&compressed, len)
+        }
+        Err(other) => Err(invalid(format!("Unknown RDB string encoding {other}"))),
+    }
+}
+
+/// Reads a score in the pre-RDB-8 textual double format.
+fn read_legacy_double(c: &mut Bytes) -> io::Result<f64> {
+    match read_u8(c)? {
+        253 => Ok(f64::NAN),
+        254 => Ok(f64::INFINITY),
+        255 => Ok(f64::NEG_INFINITY),
+        len => parse_f64(&read_raw(c, len as usize)?),
+    }
+}
+
+fn parse_f64(bytes: &[u8]) -> io::Result<f64> {
+    std::str::from_utf8(bytes)
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| invalid("Invalid floating point value".to_string()))
+}
+
+fn parse_i64(bytes: &Bytes) -> io::Result<i64> {
+    std::str::from_utf8(bytes)
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| invalid("Invalid integer value".to_string()))
+}
+
+// --- RDB Writer ---
+
+/// Writes the state of all databases into a writer in Redis RDB format.
+///
+/// Returns the number of keys that were skipped because their type has no Redis
+/// equivalent.
+pub async fn write_database<W>(writer: &mut W, dbs: &[Arc<Db>]) -> io::Result<u64>
+where
+    W: tokio::io::AsyncWrite + Unpin + Send,
+{
+    let mut buffer = BytesMut::with_capacity(8192);
+    let mut crc_digest = CHECKSUM_ALGO.digest();
+    let mut skipped = 0u64;
+
+    buffer.put_slice(RDB_MAGIC);
+    buffer.put_slice(RDB_EXPORT_VERSION);
+    for (key, value) in [
+        ("redis-bits", "64".to_string()),
+        (
+            "ctime",
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs()
+                .to_string(),
+        ),
+        ("spineldb-ver", env!("CARGO_PKG_VERSION").to_string()),
+    ] {
+        buffer.put_u8(RDB_OPCODE_AUX);
+        write_string(&mut buffer, key.as_bytes());
+        write_string(&mut buffer, value.as_bytes());
+    }
+
+    let snapshot = Snapshot::begin(dbs).await;
+    for db_index in 0..snapshot.db_count() {
+        let key_count = snapshot.key_count(db_index);
+        if key_count == 0 {
+            continue;
+        }
+        // The sizes in RESIZEDB are only hints, so the number of keys with an expiry
+        // is not counted up front.
+        buffer.put_u8(RDB_OPCODE_SELECTDB);
+        write_length(&mut buffer, db_index as u64);
+        buffer.put_u8(RDB_OPCODE_RESIZEDB);
+        write_length(&mut buffer, key_count as u64);
+        write_length(&mut buffer, 0);
+
+        let mut reader = snapshot.reader(db_index);
+        while reader
+            .next_chunk(|key, value| {
+                if !write_kv(&mut buffer, key, value) {
+                    warn!(
+                        "Skipping key '{}' in DB {}: its type has no Redis equivalent.",
+                        String::from_utf8_lossy(key),
+                        db_index
+                    );
+                    skipped += 1;
+                }
+                Ok::<(), io::Error>(())
+            })
+            .await?
+        {
+            if buffer.len() > 64 * 1024 {
+                crc_digest.update(&buffer);
+                writer.write_all(&buffer).await?;
+                buffer.clear();
+            }
+        }
+    }
+    drop(snapshot);
+
+    buffer.put_u8(RDB_OPCODE_EOF);
+    crc_digest.update(&buffer);
+    writer.write_all(&buffer).await?;
+    writer.write_u64_le(crc_digest.finalize()).await?;
+    writer.flush().await?;
+    Ok(skipped)
+}
+
+/// Writes a single key with its expiry. Returns false if the type cannot be exported.
+fn write_kv(buf: &mut BytesMut, key: &Bytes, value: &StoredValue) -> bool {
+    let mut value_buf = BytesMut::new();
+    let value_type = match &value.data {
+        DataValue::String(s) => {
+            write_string(&mut value_buf, s);
+            RDB_TYPE_STRING
+        }
+        DataValue::List(list) => {
+            write_length(&mut value_buf, list.len() as u64);
+            for item in list {
+                write_string(&mut value_buf, item);
+            }
+            RDB_TYPE_LIST
+        }
+        DataValue::Set(set) => {
+            write_length(&mut value_buf, set.len() as u64);
+            for member in set {
+                write_string(&mut value_buf, member);
+            }
+            RDB_TYPE_SET
+        }
+        DataValue::SortedSet(zset) => {
+            write_length(&mut value_buf, zset.len() as u64);
+            for entry in zset.iter() {
+                write_string(&mut value_buf, &entry.member);
+                value_buf.put_u64_le(entry.score.to_bits());
+            }
+            RDB_TYPE_ZSET_2
+        }
+        DataValue::Hash(hash) => {
+            write_length(&mut value_buf, hash.len() as u64);
+            for (field, val) in hash {
+                write_string(&mut value_buf, field);
+                write_string(&mut value_buf, val);
+            }
+            RDB_TYPE_HASH
+        }
+        DataValue::Stream(stream) => {
+            write_stream(&mut value_buf, stream);
+            RDB_TYPE_STREAM_LISTPACKS
+        }
+        DataValue::Json(_)
+        | DataValue::HyperLogLog(_)
+        | DataValue::BloomFilter(_)
+        | DataValue::HttpCache { .. } => return false,
+    };
+
+    if let Some(expiry) = value.expiry
+        && let Some(remaining) = expiry.checked_duration_since(Instant::now())
+        && let Ok(now) = SystemTime::now().duration_since(UNIX_EPOCH)
+    {
+        buf.put_u8(RDB_OPCODE_EXPIRETIME_MS);
+        buf.put_u64_le((now + remaining).as_millis() as u64);
+    }
+    buf.put_u8(value_type);
+    write_string(buf, key);
+    buf.put(value_buf);
+    true
+}
+
+/// Writes a stream in the `RDB_TYPE_STREAM_LISTPACKS` format.
+fn write_stream(buf: &mut BytesMut, stream: &Stream) {
+    let entries: Vec<&StreamEntry> = stream.entries.values().collect();
+    let nodes: Vec<&[&StreamEntry]> = entries.chunks(STREAM_NODE_MAX_ENTRIES).collect();
+    write_length(buf, nodes.len() as u64);
+    for node in nodes {
+        let master = node[0];
+        let mut raw_id = BytesMut::with_capacity(16);
+        put_raw_stream_id(&mut raw_id, master.id);
+        write_string(buf, &raw_id);
+        write_string(buf, &encode_stream_node(node));
+    }
+
+    write_length(buf, stream.length);
+    write_length(buf, stream.last_generated_id.timestamp_ms);
+    write_length(buf, stream.last_generated_id.sequence);
+
+    write_length(buf, stream.groups.len() as u64);
+    for group in stream.groups.values() {
+        write_string(buf, &group.name);
+        write_length(buf, group.last_delivered_id.timestamp_ms);
+        write_length(buf, group.last_delivered_id.sequence);
+
+        write_length(buf, group.pending_entries.len() as u64);
+        for (id, info) in &group.pending_entries {
+            put_raw_stream_id(buf, *id);
+            buf.put_u64_le(info.delivery_time_ms);
+            write_length(buf, info.delivery_count);
+        }
+
+        write_length(buf, group.consumers.len() as u64);
+        for consumer in group.consumers.values() {
+            write_string(buf, &consumer.name);
+            buf.put_u64_le(consumer.seen_time_ms);
+            write_length(buf, consumer.pending_ids.len() as u64);
+            for id in &consumer.pending_ids {
+                put_raw_stream_id(buf, *id);
+            }
+        }
+    }
+}
+
+/// Encodes the entries of one stream node as a listpack, using the fields of the
+/// first entry as the master fields.
+fn encode_stream_node(node: &[&StreamEntry]) -> Bytes {
+    let master = node[0];
+    let mut lp = ListpackWriter::default();
+    lp.push_int(node.len() as i64);
+    lp.push_int(0);
+    lp.push_int(master.fields.len() as i64);
+    for field in master.fields.keys() {
+        lp.push_str(field);
+    }
+    lp.push_int(0);
+
+    for entry in node {
+        let same_fields = entry.fields.len() == master.fields.len()
+            && entry.fields.keys().eq(master.fields.keys());
+        lp.push_int(if same_fields {
+            STREAM_ITEM_FLAG_SAMEFIELDS
+        } else {
+            0
+        });
+        lp.push_int(entry.id.timestamp_ms.wrapping_sub(master.id.timestamp_ms) as i64);
+        lp.push_int(entry.id.sequence.wrapping_sub(master.id.sequence) as i64);
+        if same_fields {
+            for value in entry.fields.values() {
+                lp.push_str(value);
+            }
+        } else {
+            lp.push_int(entry.fields.len() as i64);
+            for (field, value) in &entry.fields {
+                lp.push_str(field);
+                lp.push_str(value);
+            }
+        }
+        let mut lp_count = entry.fields.len() as i64 + 3;
+        if !same_fields {
+            lp_count += entry.fields.len() as i64 + 1;
+        }
+        lp.push_int(lp_count);
+    }
+    lp.finish()
+}
+
+fn put_raw_stream_id(buf: &mut BytesMut, id: StreamId) {
+    buf.put_u64(id.timestamp_ms);
+    buf.put_u64(id.sequence);
+}
+
+/// A minimal listpack encoder, used to export streams.
+#[derive(Default)]
+struct ListpackWriter {
+    body: BytesMut,
+    count: usize,
+}
+
+impl ListpackWriter {
+    fn push_int(&mut self, value: i64) {
+        let start = self.body.len();
+        if (0..=127).contains(&value) {
+            self.body.put_u8(value as u8);
+        } else {
+            self.body.put_u8(0xF4);
+            self.body.put_i64_le(value);
+        }
+        self.finish_entry(start);
+    }
+
+    fn push_str(&mut self, s: &[u8]) {
+        let start = self.body.len();
+        if s.len() < 64 {
+            self.body.put_u8(0x80 | s.len() as u8);
+        } else if s.len() < 4096 {
+            self.body.put_u8(0xE0 | (s.len() >> 8) as u8);
+            self.body.put_u8(s.len() as u8);
+        } else {
+            self.body.put_u8(0xF0);
+            self.body.put_u32_le(s.len() as u32);
+        }
+        self.body.put_slice(s);
+        self.finish_entry(start);
+    }
+
+    /// Appends the back-length of the entry that starts at `start`.
+    fn finish_entry(&mut self, start: usize) {
+        let len = self.body.len() - start;
+        let size = listpack_backlen_size(len);
+        for i in (0..size).rev() {
+            let group = ((len >> (7 * i)) & 0x7F) as u8;
+            self.body
+                .put_u8(if i == size - 1 { group } else { group | 0x80 });
+        }
+        self.count += 1;
+    }
+
+    fn finish(self) -> Bytes {
+        let total = 6 + self.body.len() + 1;
+        let mut out = BytesMut::with_capacity(total);
+        out.put_u32_le(total as u32);
+        out.put_u16_le(self.count.min(u16::MAX as usize) as u16);
+        out.put(self.body);
+        out.put_u8(0xFF);
+        out.freeze()
+    }
+}
+
+fn write_length(buf: &mut BytesMut, len: u64) {
+    if len < (1 << 6) {
+        buf.put_u8(len as u8);
+    } else if len < (1 << 14) {
+        buf.put_u16((len | (1 << 14)) as u16);
+    } else if len <= u32::MAX as u64 {
+        buf.put_u8(0x80);
+        buf.put_u32(len as u32);
+    } else {
+        buf.put_u8(0x81);
+        buf.put_u64(len);
+    }
+}
+
+fn write_string(buf: &mut BytesMut, s: &[u8]) {
+    write_length(buf, s.len() as u64);
+    buf.put_slice(s);
+}
@@ -6,10 +6,10 @@
 //! single values, used by the MIGRATE/RESTORE commands.
 
 use crate::core::SpinelDBError;
-use crate::core::database::Db;
 use crate::core::database::zset::SortedSet;
+use crate::core::database::{Db, Snapshot};
 use crate::core::state::ServerState;
-use crate::core::storage::cache_types::{CacheBody, CacheVariant, HttpMetadata};
+use crate::core::storage::cache_types::{CacheBody, CachePolicy, CacheVariant, HttpMetadata};
 use crate::core::storage::data_types::{DataValue, StoredValue};
 use crate::core::storage::stream::{
     ConsumerGroup, PendingEntryInfo, Stream, StreamEntry, StreamId,
@@ -48,6 +48,9 @@ const SPLDB_TYPE_HTTPCACHE: u8 = 7;
 const SPLDB_TYPE_HYPERLOGLOG: u8 = 8;
 const SPLDB_TYPE_BLOOMFILTER: u8 = 9;
 
+/// The AUX field under which each cache policy is stored, as JSON.
+const SPLDB_AUX_CACHE_POLICY: &[u8] = b"cache-policy";
+
 const CHECKSUM_ALGO: Crc<u64> = Crc::<u64>::new(&CRC_64_REDIS);
 
 // --- SPLDB Loader ---
@@ -94,7 +97,21 @@ impl SpldbLoader {
             spldb_bytes.len()
         );
 
-        if let Err(e) = load_from_bytes(&spldb_bytes, &state.dbs).await {
+        // A Redis RDB file can be used in place of an SPLDB file, to migrate from Redis.
+        let load_result = if super::rdb::is_rdb(&spldb_bytes) {
+            info!("File at {} is a Redis RDB file. Importing it.", path);
+            super::rdb::load_from_bytes(&spldb_bytes, &state.dbs).await
+        } else {
+            match load_from_bytes(&spldb_bytes, &state.dbs).await {
+                Ok(policies) => {
+                    state.cache.replace_policies(policies).await;
+                    Ok(())
+                }
+                Err(e) => Err(e),
+            }
+        };
+
+        if let Err(e) = load_result {
             if e.kind() == ErrorKind::InvalidData {
                 warn!(
                     "SPLDB file at {} is corrupt or in an incompatible format: {}. Backing it up and starting fresh.",
@@ -135,6 +152,8 @@ struct SpldbParser<'a> {
     dbs: &'a [Arc<Db>],
     current_db_index: usize,
     current_expiry: Option<Instant>,
+    /// Cache policies found in `cache-policy` AUX fields.
+    policies: Vec<CachePolicy>,
 }
 
 impl<'a> SpldbParser<'a> {
@@ -144,6 +163,7 @@ impl<'a> SpldbParser<'a> {
             dbs,
             current_db_index: 0,
             current_expiry: None,
+            policies: Vec::new(),
         }
     }
 
@@ -184,8 +204,14 @@ impl<'a> SpldbParser<'a> {
                     return Ok(());
                 }
                 SPLDB_OPCODE_AUX => {
-                    read_string(&mut self.cursor)?;
-                    read_string(&mut self.cursor)?;
+                    let key = read_string(&mut self.cursor)?;
+                    let value = read_string(&mut self.cursor)?;
+                    if key == SPLDB_AUX_CACHE_POLICY {
+                        match serde_json::from_slice::<CachePolicy>(&value) {
+                            Ok(policy) => self.policies.push(policy),
+                            Err(e) => warn!("Skipping unreadable cache policy in SPLDB: {e}"),
+                        }
+                    }
                 }
                 SPLDB_OPCODE_SELECTDB => {
                     let db_index = read_length_encoding(&mut self.cursor)? as usize;
@@ -202,7 +228,7 @@ impl<'a> SpldbParser<'a> {
                     read_length_encoding(&mut self.cursor)?;
                 }
                 SPLDB_OPCODE_EXPIRETIME_MS => {
-                    let ts_ms = self.cursor.get_u64_le();
+                    let ts_ms = read_u64_le(&mut self.cursor)?;
                     let expiry_time = UNIX_EPOCH + Duration::from_millis(ts_ms);
                     self.current_expiry = if let Ok(duration_from_now) =
                         expiry_time.duration_since(SystemTime::now())
@@ -244,7 +270,8 @@ impl<'a> SpldbParser<'a> {
 }
 
 /// Loads a full SPLDB file from a byte slice into the databases.
-pub async fn load_from_bytes(data: &Bytes, dbs: &[Arc<Db>]) -> io::Result<()> {
+/// Returns the cache policies stored in the file.
+pub async fn load_from_bytes(data: &Bytes, dbs: &[Arc<Db>]) -> io::Result<Vec<CachePolicy>> {
     if data.len() < 8 {
         return Err(Error::new(
             ErrorKind::InvalidData,
@@ -276,17 +303,38 @@ pub async fn load_from_bytes(data: &Bytes, dbs: &[Arc<Db>]) -> io::Result<()> {
     parser.parse_header()?;
     parser.parse_kv_pairs().await?;
 
-    Ok(())
+    Ok(parser.policies)
 }
 
 /// Streaming writes the state of all databases into a writer in SPLDB format.
 /// This implementation writes directly to the I/O sink without buffering the entire dataset in RAM.
-pub async fn save(dbs: &[Arc<Db>], path: &str) -> io::Result<()> {
+pub async fn save(dbs: &[Arc<Db>], policies: &[CachePolicy], path: &str) -> io::Result<()> {
     let mut file = tokio::fs::File::create(path).await?;
-    write_database(&mut file, dbs).await
+    write_database(&mut file, dbs, policies).await
 }
 
-pub async fn write_database<W>(writer: &mut W, dbs: &[Arc<Db>]) -> io::Result<()>
+/// Writes the databases and the cache policies to `writer` in SPLDB format.
+pub async fn write_database<W>(
+    writer: &mut W,
+    dbs: &[Arc<Db>],
+    policies: &[CachePolicy],
+) -> io::Result<()>
+where
+    W: tokio::io::AsyncWrite + Unpin + Send,
+{
+    // The snapshot is taken at a single point in time across all databases, and
+    // values are serialized straight from the shards without cloning the dataset.
+    let snapshot = Snapshot::begin(dbs).await;
+    write_snapshot(writer, &snapshot, policies).await
+}
+
+/// Writes an already started `snapshot` and the cache policies to `writer` in SPLDB
+/// format.
+pub async fn write_snapshot<W>(
+    writer: &mut W,
+    snapshot: &Snapshot,
+    policies: &[CachePolicy],
+) -> io::Result<()>
 where
     W: tokio::io::AsyncWrite + Unpin + Send,
 {
@@ -335,36 +383,41 @@ where
     write_string(&mut buffer, b"ctime");
     write_string(&mut buffer, &ctime.to_string().into_bytes());
 
+    // Cache policies are configuration rather than keyspace data, so they are stored
+    // as AUX fields, which older loaders skip.
+    for policy in policies {
+        let json = serde_json::to_vec(policy).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        buffer.put_u8(SPLDB_OPCODE_AUX);
+        write_string(&mut buffer, SPLDB_AUX_CACHE_POLICY);
+        write_string(&mut buffer, &json);
+    }
+
     flush_buffer(writer, &mut buffer, &mut crc_digest).await?;
 
     // --- Database Content ---
-    for (db_index, db) in dbs.iter().enumerate() {
-        // Snapshot keys. Note: This still loads all keys of a DB into memory vectors,
-        // but it's done per-DB and values are processed one by one.
-        // For extremely large DBs, locking shards individually would be even better but more complex.
-        let all_kvs = db.get_all_kvs_for_sync().await;
-        let valid_kvs: Vec<_> = all_kvs
-            .into_iter()
-            .filter(|(_, val)| !val.is_expired())
-            .collect();
-
-        if valid_kvs.is_empty() {
+    for db_index in 0..snapshot.db_count() {
+        let key_count = snapshot.key_count(db_index);
+        if key_count == 0 {
             continue;
         }
-
-        if db_index > 0 {
-            buffer.put_u8(SPLDB_OPCODE_SELECTDB);
-            write_length_encoding(&mut buffer, db_index as u64);
-        }
-
-        buffer.put_u8(SPLDB_OPCODE_RESIZEDB);
-        write_length_encoding(&mut buffer, valid_kvs.len() as u64);
-        write_length_encoding(&mut buffer, 0);
-
-        flush_buffer(writer, &mut buffer, &mut crc_digest).await?;
-
-        for (key, stored_value) in valid_kvs {
-            write_kv(&mut buffer, &key, &stored_value)?;
+        let mut header_written = false;
+        let mut reader = snapshot.reader(db_index);
+        while reader
+            .next_chunk(|key, stored_value| {
+                if !header_written {
+                    if db_index > 0 {
+                        buffer.put_u8(SPLDB_OPCODE_SELECTDB);
+                        write_length_encoding(&mut buffer, db_index as u64);
+                    }
+                    buffer.put_u8(SPLDB_OPCODE_RESIZEDB);
+                    write_length_encoding(&mut buffer, key_count as u64);
+                    write_length_encoding(&mut buffer, 0);
+                    header_written = true;
+                }
+                write_kv(&mut buffer, key, stored_value)
+            })
+            .await?
+        {
             // Flush periodically to keep memory usage low.
             if buffer.len() > 64 * 1024 {
                 flush_buffer(writer, &mut buffer, &mut crc_digest).await?;
@@ -490,6 +543,176 @@ fn read_length_encoding(cursor: &mut Bytes) -> io::Result<u64> {
     }
 }
 
+/// Caps a length read from the file, so that a corrupt length cannot cause a huge allocation.
+fn capacity(len: usize, cursor: &Bytes) -> usize {
+    len.min(cursor.remaining())
+}
+
+fn read_u8(cursor: &mut Bytes) -> io::Result<u8> {
+    if !cursor.has_remaining() {
+        return Err(Error::new(ErrorKind::UnexpectedEof, "Cannot read byte"));
+    }
+    Ok(cursor.get_u8())
+}
+
+fn read_u64_le(cursor: &mut Bytes) -> io::Result<u64> {
+    if cursor.remaining() < 8 {
+        return Err(Error::new(
+            ErrorKind::UnexpectedEof,
+            "Cannot read 64-bit integer",
+        ));
+    }
+    Ok(cursor.get_u64_le())
+}
+
+// --- Offline Inspection ---
+
+/// The result of walking an SPLDB file without loading it, used by `check-spldb`.
+#[derive(Debug, Default)]
+pub struct SpldbInspection {
+    /// The number of bytes up to and including the last complete record.
+    pub valid_up_to: usize,
+    /// True if the trailing checksum was found and matches the file contents.
+    pub checksum_ok: bool,
+    /// The first problem found in the file, if any.
+    pub error: Option<String>,
+    /// The auxiliary fields stored in the file.
+    pub aux_fields: Vec<(String, String)>,
+    /// The number of keys of each type.
+    pub keys_per_type: BTreeMap<&'static str, u64>,
+    /// The number of keys in each database.
+    pub keys_per_db: BTreeMap<usize, u64>,
+    /// The number of keys that have an expiry time.
+    pub keys_with_expiry: u64,
+}
+
+impl SpldbInspection {
+    /// Returns true if the file is complete and its checksum is valid.
+    pub fn is_valid(&self) -> bool {
+        self.error.is_none() && self.checksum_ok
+    }
+
+    pub fn total_keys(&self) -> u64 {
+        self.keys_per_db.values().sum()
+    }
+}
+
+/// Walks an SPLDB file record by record, validating every value and the trailing
+/// checksum, and collects key statistics. Unlike `load_from_bytes`, nothing is loaded
+/// and parsing does not stop at the first error without reporting how far it got.
+pub fn inspect(data: &[u8]) -> SpldbInspection {
+    let mut report = SpldbInspection::default();
+    if let Err(e) = inspect_records(data, &mut report) {
+        report.error = Some(e.to_string());
+    }
+    report
+}
+
+fn inspect_records(data: &[u8], report: &mut SpldbInspection) -> io::Result<()> {
+    let mut cursor = Bytes::copy_from_slice(data);
+    let offset = |cursor: &Bytes| data.len() - cursor.remaining();
+
+    if cursor.remaining() < SPLDB_MAGIC.len() + SPLDB_VERSION.len()
+        || cursor.split_to(SPLDB_MAGIC.len()) != SPLDB_MAGIC
+    {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "Invalid SPLDB magic string",
+        ));
+    }
+    cursor.advance(SPLDB_VERSION.len());
+    report.valid_up_to = offset(&cursor);
+
+    let mut db_index = 0;
+    let mut has_expiry = false;
+    loop {
+        if !cursor.has_remaining() {
+            return Err(Error::new(
+                ErrorKind::UnexpectedEof,
+                "SPLDB data ended without EOF opcode",
+            ));
+        }
+
+        match cursor.get_u8() {
+            SPLDB_OPCODE_EOF => {
+                let body_len = offset(&cursor);
+                let file_checksum = read_u64_le(&mut cursor).map_err(|_| {
+                    Error::new(ErrorKind::UnexpectedEof, "SPLDB checksum is missing")
+                })?;
+                report.checksum_ok = CHECKSUM_ALGO.checksum(&data[..body_len]) == file_checksum;
+                if !report.checksum_ok {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        "SPLDB checksum mismatch. File may be corrupt.",
+                    ));
+                }
+                report.valid_up_to = offset(&cursor);
+                if cursor.has_remaining() {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        format!(
+                            "{} bytes of unexpected data after the checksum",
+                            cursor.remaining()
+                        ),
+                    ));
+                }
+                return Ok(());
+            }
+            SPLDB_OPCODE_AUX => {
+                let key = read_string(&mut cursor)?;
+                let value = read_string(&mut cursor)?;
+                report.aux_fields.push((
+                    String::from_utf8_lossy(&key).to_string(),
+                    String::from_utf8_lossy(&value).to_string(),
+                ));
+            }
+            SPLDB_OPCODE_SELECTDB => {
+                db_index = read_length_encoding(&mut cursor)? as usize;
+            }
+            SPLDB_OPCODE_RESIZEDB => {
+                read_length_encoding(&mut cursor)?;
+                read_length_encoding(&mut cursor)?;
+            }
+            SPLDB_OPCODE_EXPIRETIME_MS => {
+                read_u64_le(&mut cursor)?;
+                has_expiry = true;
+                // The expiry belongs to the key that follows, so the record is not complete yet.
+                continue;
+            }
+            value_type => {
+                read_string(&mut cursor)?;
+                deserialize_single_value_data(&mut cursor, value_type)?;
+                *report
+                    .keys_per_type
+                    .entry(type_name(value_type))
+                    .or_default() += 1;
+                *report.keys_per_db.entry(db_index).or_default() += 1;
+                if std::mem::take(&mut has_expiry) {
+                    report.keys_with_expiry += 1;
+                }
+            }
+        }
+        report.valid_up_to = offset(&cursor);
+    }
+}
+
+/// Returns a human-readable name for an SPLDB value type.
+fn type_name(value_type: u8) -> &'static str {
+    match value_type {
+        SPLDB_TYPE_STRING => "string",
+        SPLDB_TYPE_LIST => "list",
+        SPLDB_TYPE_SET => "set",
+        SPLDB_TYPE_ZSET => "zset",
+        SPLDB_TYPE_HASH => "hash",
+        SPLDB_TYPE_STREAM => "stream",
+        SPLDB_TYPE_JSON => "json",
+        SPLDB_TYPE_HTTPCACHE => "httpcache",
+        SPLDB_TYPE_HYPERLOGLOG => "hyperloglog",
+        SPLDB_TYPE_BLOOMFILTER => "bloomfilter",
+        _ => "unknown",
+    }
+}
+
 // --- MIGRATE/RESTORE Helpers ---
 
 /// Serializes a single `DataValue` into SPLDB format, used by `MIGRATE`.
@@ -502,7 +725,7 @@ pub fn serialize_value(data: &DataValue) -> io::Result<Bytes> {
 /// Deserializes a single `StoredValue` from SPLDB format, used by `RESTORE`.
 pub fn deserialize_value(data: &Bytes) -> io::Result<StoredValue> {
     let mut cursor = data.clone();
-    let value_type = cursor.get_u8();
+    let value_type = read_u8(&mut cursor)?;
     let data_value = deserialize_single_value_data(&mut cursor, value_type)?;
     Ok(StoredValue::new(data_value))
 }
@@ -614,7 +837,7 @@ fn serialize_single_value_data(buf: &mut BytesMut, data: &DataValue) -> io::Resu
 
             let in_memory_variants: Vec<_> = variants
                 .iter()
-                .filter(|(_, v)| matches!(v.body, CacheBody::InMemory(_)))
+                .filter(|(_, v)| matches!(v.body, CacheBody::InMemory(_)) && !v.is_rendition())
                 .collect();
 
             write_length_encoding(buf, in_memory_variants.len() as u64);
@@ -637,6 +860,12 @@ fn serialize_single_value_data(buf: &mut BytesMut, data: &DataValue) -> io::Resu
                 if variant.metadata.revalidate_url.is_some() {
                     flags |= 1 << 2;
                 }
+                if variant.metadata.status.is_some() {
+                    flags |= 1 << 3;
+                }
+                if !variant.metadata.headers.is_empty() {
+                    flags |= 1 << 4;
+                }
                 buf.put_u8(flags);
 
                 if let Some(etag) = &variant.metadata.etag {
@@ -648,6 +877,16 @@ fn serialize_single_value_data(buf: &mut BytesMut, data: &DataValue) -> io::Resu
                 if let Some(url) = &variant.metadata.revalidate_url {
                     write_string(buf, url.as_bytes());
                 }
+                if let Some(status) = variant.metadata.status {
+                    write_length_encoding(buf, status as u64);
+                }
+                if !variant.metadata.headers.is_empty() {
+                    write_length_encoding(buf, variant.metadata.headers.len() as u64);
+                    for (name, value) in &variant.metadata.headers {
+                        write_string(buf, name);
+                        write_string(buf, value);
+                    }
+                }
             }
         }
     }
@@ -660,7 +899,7 @@ fn deserialize_single_value_data(cursor: &mut Bytes, value_type: u8) -> io::Resu
         SPLDB_TYPE_STRING => Ok(DataValue::String(read_string(cursor)?)),
         SPLDB_TYPE_LIST => {
             let len = read_length_encoding(cursor)? as usize;
-            let mut list = VecDeque::with_capacity(len);
+            let mut list = VecDeque::with_capacity(capacity(len, cursor));
             for _ in 0..len {
                 list.push_back(read_string(cursor)?);
             }
@@ -668,7 +907,7 @@ fn deserialize_single_value_data(cursor: &mut Bytes, value_type: u8) -> io::Resu
         }
         SPLDB_TYPE_SET => {
             let len = read_length_encoding(cursor)? as usize;
-            let mut set = HashSet::with_capacity(len);
+            let mut set = HashSet::with_capacity(capacity(len, cursor));
             for _ in 0..len {
                 set.insert(read_string(cursor)?);
             }
@@ -676,7 +915,7 @@ fn deserialize_single_value_data(cursor: &mut Bytes, value_type: u8) -> io::Resu
         }
         SPLDB_TYPE_HASH => {
             let len = read_length_encoding(cursor)? as usize;
-            let mut hash = IndexMap::with_capacity(len);
+            let mut hash = IndexMap::with_capacity(capacity(len, cursor));
             for _ in 0..len {
                 let field = read_string(cursor)?;
                 let value = read_string(cursor)?;
@@ -703,11 +942,11 @@ fn deserialize_single_value_data(cursor: &mut Bytes, value_type: u8) -> io::Resu
             let mut stream = Stream::new();
             let num_entries = read_length_encoding(cursor)? as usize;
             for _ in 0..num_entries {
-                let timestamp_ms = cursor.get_u64_le();
-                let sequence = cursor.get_u64_le();
+                let timestamp_ms = read_u64_le(cursor)?;
+                let sequence = read_u64_le(cursor)?;
                 let id = StreamId::new(timestamp_ms, sequence);
                 let num_fields = read_length_encoding(cursor)? as usize;
-                let mut fields = IndexMap::with_capacity(num_fields);
+                let mut fields = IndexMap::with_capacity(capacity(num_fields, cursor));
                 for _ in 0..num_fields {
                     let field = read_string(cursor)?;
                     let value = read_string(cursor)?;
@@ -715,14 +954,14 @@ fn deserialize_single_value_data(cursor: &mut Bytes, value_type: u8) -> io::Resu
                 }
                 stream.entries.insert(id, StreamEntry { id, fields });
             }
-            stream.length = cursor.get_u64_le();
-            stream.last_generated_id.timestamp_ms = cursor.get_u64_le();
-            stream.last_generated_id.sequence = cursor.get_u64_le();
+            stream.length = read_u64_le(cursor)?;
+            stream.last_generated_id.timestamp_ms = read_u64_le(cursor)?;
+            stream.last_generated_id.sequence = read_u64_le(cursor)?;
             let num_groups = read_length_encoding(cursor)? as usize;
             for _ in 0..num_groups {
                 let group_name = read_string(cursor)?;
-                let last_delivered_ts = cursor.get_u64_le();
-                let last_delivered_seq = cursor.get_u64_le();
+                let last_delivered_ts = read_u64_le(cursor)?;
+                let last_delivered_seq = read_u64_le(cursor)?;
                 let mut group = ConsumerGroup {
                     name: group_name.clone(),
                     last_delivered_id: StreamId::new(last_delivered_ts, last_delivered_seq),
@@ -732,10 +971,10 @@ fn deserialize_single_value_data(cursor: &mut Bytes, value_type: u8) -> io::Resu
                 };
                 let num_pending = read_length_encoding(cursor)? as usize;
                 for _ in 0..num_pending {
-                    let id = StreamId::new(cursor.get_u64_le(), cursor.get_u64_le());
+                    let id = StreamId::new(read_u64_le(cursor)?, read_u64_le(cursor)?);
                     let consumer_name = read_string(cursor)?;
-                    let delivery_count = cursor.get_u64_le();
-                    let delivery_time_ms = cursor.get_u64_le();
+                    let delivery_count = read_u64_le(cursor)?;
+                    let delivery_time_ms = read_u64_le(cursor)?;
                     group.pending_entries.insert(
                         id,
                         PendingEntryInfo {
@@ -749,11 +988,12 @@ fn deserialize_single_value_data(cursor: &mut Bytes, value_type: u8) -> io::Resu
                 let num_consumers = read_length_encoding(cursor)? as usize;
                 for _ in 0..num_consumers {
                     let consumer_name = read_string(cursor)?;
-                    let seen_time_ms = cursor.get_u64_le();
+                    let seen_time_ms = read_u64_le(cursor)?;
                     let num_pending_for_consumer = read_length_encoding(cursor)? as usize;
                     let mut pending_ids = BTreeSet::new();
                     for _ in 0..num_pending_for_consumer {
-                        pending_ids.insert(StreamId::new(cursor.get_u64_le(), cursor.get_u64_le()));
+                        pending_ids
+                            .insert(StreamId::new(read_u64_le(cursor)?, read_u64_le(cursor)?));
                     }
                     group.consumers.insert(
                         consumer_name.clone(),
@@ -799,18 +1039,18 @@ fn deserialize_single_value_data(cursor: &mut Bytes, value_type: u8) -> io::Resu
         }
         SPLDB_TYPE_HTTPCACHE => {
             let vary_len = read_length_encoding(cursor)? as usize;
-            let mut vary_on = Vec::with_capacity(vary_len);
+            let mut vary_on = Vec::with_capacity(capacity(vary_len, cursor));
             for _ in 0..vary_len {
                 vary_on.push(read_string(cursor)?);
             }
 
             let variants_len = read_length_encoding(cursor)? as usize;
-            let mut variants = HashMap::with_capacity(variants_len);
+            let mut variants = HashMap::with_capacity(capacity(variants_len, cursor));
             for _ in 0..variants_len {
-                let hash = cursor.get_u64_le();
+                let hash = read_u64_le(cursor)?;
                 let body = read_string(cursor)?;
 
-                let flags = cursor.get_u8();
+                let flags = read_u8(cursor)?;
                 let mut metadata = HttpMetadata::default();
                 if (flags & (1 << 0)) != 0 {
                     metadata.etag = Some(read_string(cursor)?);
@@ -822,6 +1062,17 @@ fn deserialize_single_value_data(cursor: &mut Bytes, value_type: u8) -> io::Resu
                     metadata.revalidate_url =
                         Some(String::from_utf8_lossy(&read_string(cursor)?).to_string());
                 }
+                if (flags & (1 << 3)) != 0 {
+                    metadata.status = Some(read_length_encoding(cursor)? as u16);
+                }
+                if (flags & (1 << 4)) != 0 {
+                    let headers_len = read_length_encoding(cursor)? as usize;
+                    for _ in 0..headers_len {
+                        let name = read_string(cursor)?;
+                        let value = read_string(cursor)?;
+                        metadata.headers.push((name, value));
+                    }
+                }
 
                 variants.insert(
                     hash,
@@ -848,8 +1099,8 @@ fn deserialize_single_value_data(cursor: &mut Bytes, value_type: u8) -> io::Resu
 /// Helper function to save only the data required for replication to bytes.
 /// Note: This is an alias for the same logic as full save for now,
 /// but kept separate if logic diverges.
-pub async fn save_to_bytes(dbs: &[Arc<Db>]) -> io::Result<Bytes> {
+pub async fn save_to_bytes(dbs: &[Arc<Db>], policies: &[CachePolicy]) -> io::Result<Bytes> {
     let mut buffer: Vec<u8> = Vec::new();
-    write_database(&mut buffer, dbs).await?;
+    write_database(&mut buffer, dbs, policies).await?;
     Ok(Bytes::from(buffer))
 }
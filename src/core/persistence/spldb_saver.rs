@@ -135,7 +135,8 @@ impl SpldbSaverTask {
         match file_result {
             Ok(file) => {
                 let mut writer = BufWriter::new(file);
-                if let Err(e) = spldb::write_database(&mut writer, &state.dbs).await {
+                let policies = state.cache.policies.read().await.clone();
+                if let Err(e) = spldb::write_database(&mut writer, &state.dbs, &policies).await {
                     let err_msg = format!("Failed to write SPLDB snapshot to temporary file: {e}");
                     error!("{}", err_msg);
                     *state.persistence.last_save_failure_time.lock().await =
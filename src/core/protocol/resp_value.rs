@@ -39,3 +39,20 @@ impl From<RespValue> for super::RespFrame {
         }
     }
 }
+
+/// Implements the conversion from a `RespFrame` received from another node to a `RespValue`.
+impl From<super::RespFrame> for RespValue {
+    fn from(frame: super::RespFrame) -> Self {
+        match frame {
+            super::RespFrame::SimpleString(s) => RespValue::SimpleString(s),
+            super::RespFrame::BulkString(b) => RespValue::BulkString(b),
+            super::RespFrame::Integer(i) => RespValue::Integer(i),
+            super::RespFrame::Array(arr) => {
+                RespValue::Array(arr.into_iter().map(Into::into).collect())
+            }
+            super::RespFrame::Null => RespValue::Null,
+            super::RespFrame::NullArray => RespValue::NullArray,
+            super::RespFrame::Error(s) => RespValue::Error(s),
+        }
+    }
+}
@@ -15,7 +15,7 @@ use tracing::{debug, warn};
 
 /// Manages a connection that is in Pub/Sub mode.
 /// In this mode, the connection can only receive messages and a limited
-/// set of commands (`(P)UNSUBSCRIBE`, `QUIT`).
+/// set of commands (`(P|S)UNSUBSCRIBE`, `QUIT`).
 pub struct PubSubModeHandler<'a, S: AsyncRead + AsyncWrite + Unpin> {
     framed: &'a mut Framed<S, RespFrameCodec>,
     shutdown_rx: &'a mut broadcast::Receiver<()>,
@@ -46,10 +46,12 @@ impl<'a, S: AsyncRead + AsyncWrite + Unpin> PubSubModeHandler<'a, S> {
             // If the client unsubscribes from all channels/patterns, exit Pub/Sub mode.
             if self.session.subscribed_channels.is_empty()
                 && self.session.subscribed_patterns.is_empty()
+                && self.session.subscribed_shard_channels.is_empty()
             {
                 debug!("No more subscriptions, exiting Pub/Sub mode.");
                 self.session.is_subscribed = false;
                 self.session.is_pattern_subscribed = false;
+                self.session.is_shard_subscribed = false;
                 return Ok(());
             }
 
@@ -110,6 +112,12 @@ impl<'a, S: AsyncRead + AsyncWrite + Unpin> PubSubModeHandler<'a, S> {
                         .pubsub_receivers
                         .push(SubscriptionReceiver::Pattern(pattern, new_rx));
                 }
+                SubscriptionReceiver::ShardChannel(name, _) => {
+                    let new_rx = self.state.pubsub.subscribe_shard(&name);
+                    self.session
+                        .pubsub_receivers
+                        .push(SubscriptionReceiver::ShardChannel(name, new_rx));
+                }
             }
         }
         debug!(
@@ -153,6 +161,14 @@ async fn receive_pubsub_message_static(
                         ])
                     })
                 }
+                // For shard channel subscriptions, format as `(smessage, channel_name, message_body)`.
+                SubscriptionReceiver::ShardChannel(name, rx) => rx.recv().await.map(|msg| {
+                    RespValue::Array(vec![
+                        RespValue::BulkString("smessage".into()),
+                        RespValue::BulkString(name.clone()),
+                        RespValue::BulkString(msg),
+                    ])
+                }),
             }
         }
         .boxed() // Box the future to create a homogenous type for `select_all`.
@@ -1,7 +1,7 @@
 // src/core/pubsub/mod.rs
 
 //! The core publish-subscribe (Pub/Sub) system.
-//! It manages channel and pattern subscriptions and message broadcasting.
+//! It manages channel, pattern, and shard channel subscriptions and message broadcasting.
 
 use crate::core::commands::scan::glob_match;
 use bytes::Bytes;
@@ -29,6 +29,9 @@ pub struct PubSubManager {
     channels: DashMap<Bytes, Arc<Sender<Bytes>>>,
     /// A map from a pattern to its broadcast sender for pattern-based subscriptions.
     pattern_channels: DashMap<Bytes, Arc<Sender<PMessage>>>,
+    /// A map from a shard channel to its broadcast sender. Shard channels are a separate
+    /// namespace: they hash to a cluster slot and are only served by the slot's shard.
+    shard_channels: DashMap<Bytes, Arc<Sender<Bytes>>>,
 }
 
 impl PubSubManager {
@@ -60,6 +63,15 @@ impl PubSubManager {
             .subscribe()
     }
 
+    /// Subscribes a client to a shard channel.
+    pub fn subscribe_shard(&self, channel_name: &Bytes) -> Receiver<Bytes> {
+        self.shard_channels
+            .entry(channel_name.clone())
+            .or_insert_with(|| Arc::new(broadcast::channel(CHANNEL_CAPACITY).0))
+            .value()
+            .subscribe()
+    }
+
     /// Unsubscribes a client from a channel.
     /// The actual removal of the broadcast sender (if it becomes empty) is handled
     /// by the `purge_empty_channels` background task.
@@ -98,6 +110,14 @@ impl PubSubManager {
         receivers
     }
 
+    /// Publishes a message to a shard channel. Pattern subscribers never receive shard
+    /// messages. Returns the number of clients that received the message.
+    pub fn publish_shard(&self, channel_name: &Bytes, message: Bytes) -> usize {
+        self.shard_channels
+            .get(channel_name)
+            .map_or(0, |channel| channel.send(message).unwrap_or(0))
+    }
+
     /// A maintenance task that removes channels and patterns that no longer have any subscribers.
     /// This prevents memory leaks from empty, unused channels.
     pub fn purge_empty_channels(&self) -> usize {
@@ -120,6 +140,15 @@ impl PubSubManager {
             }
         });
 
+        self.shard_channels.retain(|_channel_name, sender| {
+            if sender.receiver_count() == 0 {
+                purged_count += 1;
+                false
+            } else {
+                true
+            }
+        });
+
         if purged_count > 0 {
             debug!(
                 "Purged {} empty Pub/Sub channels and patterns.",
@@ -145,4 +174,19 @@ impl PubSubManager {
     pub fn get_pattern_subscriber_count(&self) -> usize {
         self.pattern_channels.len()
     }
+
+    /// Returns a list of all active shard channels.
+    pub fn get_all_shard_channels(&self) -> Vec<Bytes> {
+        self.shard_channels
+            .iter()
+            .map(|e| e.key().clone())
+            .collect()
+    }
+
+    /// Returns the number of subscribers for a specific shard channel.
+    pub fn get_shard_subscriber_count(&self, channel_name: &Bytes) -> usize {
+        self.shard_channels
+            .get(channel_name)
+            .map_or(0, |s| s.receiver_count())
+    }
 }
@@ -7,15 +7,18 @@
 
 use crate::core::Command;
 use crate::core::commands::generic::script::ScriptSubcommand;
-use crate::core::protocol::RespFrame;
+use crate::core::database::Snapshot;
+use crate::core::protocol::{RespFrame, RespFrameCodec};
 use crate::core::state::{ReplicaStateInfo, ReplicaSyncState, ServerState};
+use futures::StreamExt;
 use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::Instant;
 use tokio::fs::File as TokioFile;
 use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, BufWriter};
 use tokio::sync::{Mutex, broadcast};
-use tracing::{info, warn};
+use tokio_util::codec::FramedRead;
+use tracing::{debug, info, warn};
 
 /// `ReplicaHandler` manages the synchronization and command streaming process
 /// for a single connected replica. It is generic over the stream type `S`.
@@ -124,6 +127,7 @@ impl<S: AsyncRead + AsyncWrite + Unpin + Send + 'static> ReplicaHandler<S> {
             ReplicaStateInfo {
                 sync_state: ReplicaSyncState::AwaitingFullSync,
                 ack_offset: 0,
+                aof_ack_offset: 0,
                 last_ack_time: Instant::now(),
             },
         );
@@ -168,9 +172,21 @@ impl<S: AsyncRead + AsyncWrite + Unpin + Send + 'static> ReplicaHandler<S> {
     /// Sends a `+FULLRESYNC` response, streams the SPLDB snapshot, and sends cached scripts.
     async fn do_full_resync(&mut self) -> Result<u64, anyhow::Error> {
         let master_replid = &self.state.replication.replication_info.master_replid;
-        let master_repl_offset = self.state.replication.get_replication_offset();
 
-        // 1. Send FULLRESYNC header.
+        // 1. Take the point-in-time snapshot. Writers publish their work before releasing
+        // their shard locks, so the work units published at the cut are exactly the
+        // writes the snapshot contains, and the replica must be streamed everything after
+        // them. The backlog feeder assigns offsets asynchronously, so wait for it to
+        // reach that point rather than reading the offset directly.
+        let mut offset_at_cut = None;
+        let snapshot = Snapshot::begin_with(&self.state.dbs, || {
+            let published = self.state.event_bus.replication_published();
+            offset_at_cut = Some(self.state.replication.offset_after_units(published));
+        })
+        .await;
+        let master_repl_offset = offset_at_cut.expect("The snapshot cut always runs").await?;
+
+        // 2. Send FULLRESYNC header.
         let full_resync_response = format!("+FULLRESYNC {master_replid} {master_repl_offset}\r\n");
         self.stream
             .write_all(full_resync_response.as_bytes())
@@ -180,7 +196,7 @@ impl<S: AsyncRead + AsyncWrite + Unpin + Send + 'static> ReplicaHandler<S> {
             self.addr, master_repl_offset
         );
 
-        // 2. Generate and stream the SPLDB snapshot.
+        // 3. Serialize and stream the SPLDB snapshot.
         // We write to a temporary file first to avoid buffering the entire DB in memory,
         // then stream that file to the replica.
         let temp_path = format!("temp-repl-{}.spldb", self.addr.port());
@@ -191,7 +207,10 @@ impl<S: AsyncRead + AsyncWrite + Unpin + Send + 'static> ReplicaHandler<S> {
             "Generating SPLDB snapshot to temp file for replica {}...",
             self.addr
         );
-        crate::core::persistence::spldb::write_database(&mut buf_writer, &self.state.dbs).await?;
+        let policies = self.state.cache.policies.read().await.clone();
+        crate::core::persistence::spldb::write_snapshot(&mut buf_writer, &snapshot, &policies)
+            .await?;
+        drop(snapshot);
         buf_writer.flush().await?;
 
         // Get file size for the bulk string header.
@@ -208,7 +227,7 @@ impl<S: AsyncRead + AsyncWrite + Unpin + Send + 'static> ReplicaHandler<S> {
 
         info!("Finished streaming SPLDB file to replica {}.", self.addr);
 
-        // 3. Send cached Lua scripts.
+        // 4. Send cached Lua scripts.
         let all_scripts = self.state.scripting.get_all_scripts();
         if !all_scripts.is_empty() {
             info!(
@@ -231,65 +250,131 @@ impl<S: AsyncRead + AsyncWrite + Unpin + Send + 'static> ReplicaHandler<S> {
     }
 
     /// Enters a loop to stream live commands to a synchronized replica.
+    ///
+    /// The same loop reads `REPLCONF ACK` frames sent back by the replica and, when a
+    /// `WAIT`/`WAITAOF` caller asks for it, sends an out-of-band `REPLCONF GETACK`.
     async fn stream_live_updates(&mut self, mut last_known_offset: u64) {
         info!(
             "Replica {} is in sync. Streaming live updates from offset {}.",
             self.addr, last_known_offset
         );
 
-        let mut offset_receiver = self.state.replication_offset_receiver.clone();
-
-        loop {
-            if offset_receiver.changed().await.is_err() {
-                warn!(
-                    "Replication offset channel closed. Shutting down handler for {}.",
-                    self.addr
-                );
+        let state = self.state.clone();
+        let addr = self.addr;
+        let mut offset_receiver = state.replication_offset_receiver.clone();
+        let mut getack_receiver = state.replication.getack_notifier.subscribe();
+        let getack_frame = match RespFrame::Array(vec![
+            RespFrame::BulkString("REPLCONF".into()),
+            RespFrame::BulkString("GETACK".into()),
+            RespFrame::BulkString("*".into()),
+        ])
+        .encode_to_vec()
+        {
+            Ok(encoded) => encoded,
+            Err(e) => {
+                warn!("Failed to encode GETACK frame: {e}. Closing connection.");
                 return;
             }
+        };
 
-            let current_global_offset = *offset_receiver.borrow();
-            if last_known_offset >= current_global_offset {
-                continue;
-            }
+        let (reader, mut writer) = tokio::io::split(&mut self.stream);
+        let mut replica_frames = FramedRead::new(reader, RespFrameCodec);
 
-            if let Some(frames_with_offsets) = self
-                .state
-                .replication_backlog
-                .get_since(last_known_offset)
-                .await
-            {
-                if frames_with_offsets.is_empty() {
-                    last_known_offset = current_global_offset;
-                    continue;
-                }
+        loop {
+            tokio::select! {
+                changed = offset_receiver.changed() => {
+                    if changed.is_err() {
+                        warn!(
+                            "Replication offset channel closed. Shutting down handler for {}.",
+                            addr
+                        );
+                        return;
+                    }
+
+                    let current_global_offset = *offset_receiver.borrow();
+                    if last_known_offset >= current_global_offset {
+                        continue;
+                    }
+
+                    if let Some(frames_with_offsets) =
+                        state.replication_backlog.get_since(last_known_offset).await
+                    {
+                        if frames_with_offsets.is_empty() {
+                            last_known_offset = current_global_offset;
+                            continue;
+                        }
 
-                for (frame_offset, frame) in frames_with_offsets {
-                    match frame.encode_to_vec() {
-                        Ok(encoded) => {
-                            let frame_len = encoded.len() as u64;
-                            if self.stream.write_all(&encoded).await.is_err() {
-                                warn!(
-                                    "Failed to send update to replica {}. Connection lost.",
-                                    self.addr
-                                );
-                                return;
+                        for (frame_offset, frame) in frames_with_offsets {
+                            match frame.encode_to_vec() {
+                                Ok(encoded) => {
+                                    let frame_len = encoded.len() as u64;
+                                    if writer.write_all(&encoded).await.is_err() {
+                                        warn!(
+                                            "Failed to send update to replica {}. Connection lost.",
+                                            addr
+                                        );
+                                        return;
+                                    }
+                                    last_known_offset = frame_offset + frame_len;
+                                }
+                                Err(e) => {
+                                    warn!("Failed to encode frame: {e}. Closing connection.");
+                                    return;
+                                }
                             }
-                            last_known_offset = frame_offset + frame_len;
                         }
-                        Err(e) => {
-                            warn!("Failed to encode frame: {e}. Closing connection.");
+                    } else {
+                        warn!(
+                            "Lost position in backlog for replica {}. Forcing full resync.",
+                            addr
+                        );
+                        return;
+                    }
+                }
+                Ok(_) = getack_receiver.changed() => {
+                    // GETACK is out-of-band: replicas do not count it toward their offset.
+                    if writer.write_all(&getack_frame).await.is_err() {
+                        warn!("Failed to send GETACK to replica {}. Connection lost.", addr);
+                        return;
+                    }
+                }
+                frame = replica_frames.next() => {
+                    match frame {
+                        Some(Ok(frame)) => Self::handle_replica_frame(&state, &addr, frame),
+                        Some(Err(e)) => {
+                            warn!("Error reading from replica {}: {}. Closing connection.", addr, e);
+                            return;
+                        }
+                        None => {
+                            info!("Replica {} closed the replication link.", addr);
                             return;
                         }
                     }
                 }
-            } else {
-                warn!(
-                    "Lost position in backlog for replica {}. Forcing full resync.",
-                    self.addr
-                );
-                return;
             }
         }
     }
+
+    /// Processes a frame sent by the replica over the replication link.
+    /// Only `REPLCONF ACK` is expected; anything else is ignored.
+    fn handle_replica_frame(state: &Arc<ServerState>, addr: &SocketAddr, frame: RespFrame) {
+        match Command::try_from(frame) {
+            Ok(Command::Replconf(replconf)) => {
+                if let Some((offset, aof_offset)) = replconf.ack_offsets() {
+                    state.replication.record_replica_ack(
+                        &state.replica_states,
+                        addr,
+                        offset,
+                        aof_offset,
+                    );
+                }
+            }
+            Ok(other) => debug!(
+                "Ignoring unexpected command '{}' from replica {}.",
+                other.name(),
+                addr
+            ),
+            Err(e) => debug!("Ignoring unparsable frame from replica {}: {}", addr, e),
+        }
+    }
 }
@@ -68,6 +68,7 @@ async fn run_backlog_feeder(state: Arc<ServerState>, mut shutdown_rx: broadcast:
                             crate::core::events::UnitOfWork::Transaction(tx_data) => {
                                 // For replication, only propagate commands that actually modify data.
                                 if tx_data.write_commands.is_empty() {
+                                    state.replication.advance_feeder(1);
                                     continue;
                                 }
                                 // Wrap the write commands in MULTI/EXEC for atomic execution on replicas.
@@ -92,8 +93,12 @@ async fn run_backlog_feeder(state: Arc<ServerState>, mut shutdown_rx: broadcast:
                                 state.replication_backlog.add(command_offset, frame, frame_len as usize).await;
                             }
                         }
+                        // Only report progress once the offset covers this unit, so that
+                        // `WAIT` never targets an offset that excludes the caller's write.
+                        state.replication.advance_feeder(1);
                     },
                     Err(broadcast::error::RecvError::Lagged(n)) => {
+                        state.replication.advance_feeder(n);
                         warn!("Replication backlog feeder lagged. {} events were dropped. This may cause replicas to require a full resync.", n);
                     },
                     Err(broadcast::error::RecvError::Closed) => {
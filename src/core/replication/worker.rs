@@ -13,6 +13,8 @@ use crate::config::ReplicationConfig;
 use crate::core::commands::command_trait::{CommandExt, CommandFlags};
 use crate::core::commands::generic::Select;
 use crate::core::database::{ExecutionContext, ExecutionLocks};
+use crate::core::events::{TransactionData, UnitOfWork};
+use crate::core::persistence::rewrite_aof;
 use crate::core::persistence::spldb::load_from_bytes;
 use crate::core::protocol::{RespFrame, RespFrameCodec};
 use crate::core::state::{ReplicaInfo, ServerState};
@@ -20,8 +22,10 @@ use crate::core::{Command, SpinelDBError};
 use bytes::{Bytes, BytesMut};
 use futures::StreamExt;
 use rand::Rng;
+use std::collections::VecDeque;
 use std::pin::Pin;
 use std::sync::Arc;
+use std::sync::atomic::Ordering;
 use std::task::{Context, Poll};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::io::{
@@ -39,6 +43,8 @@ use tracing::{debug, error, info, warn};
 const INITIAL_RECONNECT_DELAY: Duration = Duration::from_secs(1);
 // The maximum delay for the exponential backoff reconnection strategy.
 const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
+// How often the replica proactively acknowledges its offset to the primary.
+const ACK_INTERVAL: Duration = Duration::from_secs(1);
 
 // An enum to abstract over different stream types (plain TCP or TLS).
 enum ReplicaStream {
@@ -109,6 +115,11 @@ pub struct ReplicaWorker {
     queued_tx_commands: Vec<Command>,
     /// Tracks the last known primary to detect configuration changes from failovers.
     last_known_primary: Mutex<Option<(String, u16)>>,
+    /// Pairs of (AOF work sequence, replication offset) not yet known to be fsynced locally.
+    /// Used to report the `FACK` offset consumed by `WAITAOF` on the primary.
+    pending_aof_offsets: VecDeque<(u64, u64)>,
+    /// The highest replication offset known to be fsynced to the local AOF.
+    aof_synced_offset: u64,
 }
 
 impl ReplicaWorker {
@@ -120,6 +131,8 @@ impl ReplicaWorker {
             is_in_transaction: false,
             queued_tx_commands: Vec::new(),
             last_known_primary: Mutex::new(None),
+            pending_aof_offsets: VecDeque::new(),
+            aof_synced_offset: 0,
         }
     }
 
@@ -266,6 +279,7 @@ impl ReplicaWorker {
             self.read_and_load_spldb(&mut buf_reader).await?;
             info!("Full resync successful. SPLDB loaded.");
             self.current_db_index = 0;
+            self.reset_aof_tracking().await;
             FramedRead::new(buf_reader.into_inner(), RespFrameCodec)
         } else {
             info!("Partial resync successful. Resuming command stream.");
@@ -285,14 +299,24 @@ impl ReplicaWorker {
         writer: Arc<Mutex<WriteHalf<ReplicaStream>>>,
     ) {
         info!("Now in sync mode, processing command stream from primary.");
-        while let Some(result) = framed_reader.next().await {
-            match self.handle_primary_frame(result, &writer).await {
-                Ok(_) => {}
-                Err(e) => {
-                    error!("Error handling frame from primary: {e}. Disconnecting.");
-                    self.is_in_transaction = false;
-                    self.queued_tx_commands.clear();
-                    break;
+        let mut ack_interval = tokio::time::interval(ACK_INTERVAL);
+        loop {
+            tokio::select! {
+                maybe_frame = framed_reader.next() => {
+                    let Some(result) = maybe_frame else {
+                        break;
+                    };
+                    if let Err(e) = self.handle_primary_frame(result, &writer).await {
+                        error!("Error handling frame from primary: {e}. Disconnecting.");
+                        self.is_in_transaction = false;
+                        self.queued_tx_commands.clear();
+                        break;
+                    }
+                }
+                _ = ack_interval.tick() => {
+                    // Periodic ACKs keep `last_ack_time` fresh for the primary's
+                    // `min_replicas_to_write` policy, even when no writes are flowing.
+                    self.send_ack(&writer).await;
                 }
             }
         }
@@ -308,11 +332,38 @@ impl ReplicaWorker {
         let command = Command::try_from(frame.clone())?;
         debug!("Received command from primary: {command:?}");
 
-        self.apply_command_or_transaction(command.clone(), writer)
-            .await?;
+        // `REPLCONF GETACK` is sent out-of-band by the primary and is not part of the
+        // replication stream, so it must not advance the processed offset.
+        let is_out_of_band = matches!(command, Command::Replconf(_));
 
-        if let Some(info) = self.state.replication.replica_info.lock().await.as_mut() {
-            info.processed_offset += frame_len;
+        self.apply_command_or_transaction(command, writer).await?;
+
+        if is_out_of_band {
+            return Ok(());
+        }
+
+        let processed_offset =
+            if let Some(info) = self.state.replication.replica_info.lock().await.as_mut() {
+                info.processed_offset += frame_len;
+                Some(info.processed_offset)
+            } else {
+                None
+            };
+
+        if let Some(offset) = processed_offset
+            && self.state.event_bus.is_aof_enabled()
+        {
+            let seq = self
+                .state
+                .persistence
+                .aof_enqueued_seq
+                .load(Ordering::SeqCst);
+            match self.pending_aof_offsets.back_mut() {
+                // Frames that did not produce AOF work become durable together with the
+                // last unit that did, so they can share its entry.
+                Some(last) if last.0 == seq => last.1 = offset,
+                _ => self.pending_aof_offsets.push_back((seq, offset)),
+            }
         }
 
         Ok(())
@@ -367,20 +418,10 @@ impl ReplicaWorker {
             return Ok(());
         }
 
-        if let Command::Replconf(ref r) = command
-            && r.args
-                .first()
-                .is_some_and(|arg| arg.eq_ignore_ascii_case("GETACK"))
-        {
-            let offset = self
-                .state
-                .replication
-                .replica_info
-                .lock()
-                .await
-                .as_ref()
-                .map_or(0, |i| i.processed_offset);
-            self.spawn_ack_task(writer.clone(), offset).await;
+        if let Command::Replconf(ref r) = command {
+            if r.is_getack() {
+                self.send_ack(writer).await;
+            }
             return Ok(());
         }
 
@@ -414,6 +455,7 @@ impl ReplicaWorker {
                 command: Some(command.clone()),
                 session_id: 0,
                 authenticated_user: None,
+                write_guard: None,
             };
             match command.execute(&mut ctx).await {
                 Ok(_) => {
@@ -434,6 +476,10 @@ impl ReplicaWorker {
             }
         }
         info!("Successfully applied transaction from primary.");
+        self.log_to_local_aof(UnitOfWork::Transaction(Box::new(TransactionData {
+            all_commands: commands.clone(),
+            write_commands: commands,
+        })));
         Ok(())
     }
 
@@ -443,6 +489,15 @@ impl ReplicaWorker {
             .get_db(self.current_db_index)
             .ok_or_else(|| SpinelDBError::Internal("Replica using invalid DB index".into()))?;
 
+        // Shard channel messages are delivered to this replica's subscribers. They do not
+        // change the dataset, so they are not logged to the local AOF.
+        if let Command::SPublish(ref cmd) = command {
+            self.state
+                .pubsub
+                .publish_shard(&cmd.channel, cmd.message.clone());
+            return Ok(());
+        }
+
         if !command.get_flags().contains(CommandFlags::WRITE) {
             return Ok(());
         }
@@ -455,6 +510,7 @@ impl ReplicaWorker {
             command: Some(command.clone()),
             session_id: 0,
             authenticated_user: None,
+            write_guard: None,
         };
 
         if let Err(e) = command.execute(&mut ctx).await {
@@ -466,6 +522,7 @@ impl ReplicaWorker {
             *self.state.replication.replica_info.lock().await = None;
             Err(SpinelDBError::ReplicationError(err_msg))
         } else {
+            self.log_to_local_aof(UnitOfWork::Command(Box::new(command)));
             Ok(())
         }
     }
@@ -482,12 +539,86 @@ impl ReplicaWorker {
         self.current_db_index = 0;
     }
 
-    async fn spawn_ack_task(&self, writer: Arc<Mutex<WriteHalf<ReplicaStream>>>, ack_offset: u64) {
+    /// Appends an applied write to this replica's own AOF, if AOF is enabled.
+    /// This is what makes the `FACK` offset reported to the primary truthful.
+    fn log_to_local_aof(&self, uow: UnitOfWork) {
+        self.state.event_bus.publish_to_aof(uow, &self.state);
+    }
+
+    /// Called after a full resync. The local AOF no longer matches the freshly loaded
+    /// dataset, so it is rewritten from the new snapshot before `FACK` advances again.
+    async fn reset_aof_tracking(&mut self) {
+        self.pending_aof_offsets.clear();
+        let processed_offset = self
+            .state
+            .replication
+            .replica_info
+            .lock()
+            .await
+            .as_ref()
+            .map_or(0, |i| i.processed_offset);
+        if self.state.event_bus.is_aof_enabled() {
+            let seq = self
+                .state
+                .persistence
+                .aof_enqueued_seq
+                .load(Ordering::SeqCst);
+            self.pending_aof_offsets.push_back((seq, processed_offset));
+            info!("Rewriting local AOF to match the snapshot received from the primary.");
+            tokio::spawn(rewrite_aof(self.state.clone()));
+        }
+    }
+
+    /// Returns the highest replication offset that is fsynced to the local AOF. Without
+    /// AOF there is nothing to fsync, so every write applied up to `processed_offset`
+    /// counts, as for `WAIT`.
+    fn current_aof_synced_offset(&mut self, processed_offset: u64) -> u64 {
+        if !self.state.event_bus.is_aof_enabled() {
+            return processed_offset;
+        }
+        let fsynced_seq = *self.state.persistence.aof_fsynced_seq.borrow();
+        while let Some(&(seq, offset)) = self.pending_aof_offsets.front() {
+            if seq > fsynced_seq {
+                break;
+            }
+            self.aof_synced_offset = offset;
+            self.pending_aof_offsets.pop_front();
+        }
+        if !self.pending_aof_offsets.is_empty() {
+            // Ask for an fsync so the next acknowledgment can report further progress.
+            let _ = self.state.persistence.aof_fsync_request_tx.try_send(());
+        }
+        self.aof_synced_offset
+    }
+
+    /// Sends `REPLCONF ACK <offset> FACK <aof-offset>` to the primary.
+    async fn send_ack(&mut self, writer: &Arc<Mutex<WriteHalf<ReplicaStream>>>) {
+        let offset = self
+            .state
+            .replication
+            .replica_info
+            .lock()
+            .await
+            .as_ref()
+            .map_or(0, |i| i.processed_offset);
+        let aof_offset = self.current_aof_synced_offset(offset);
+        self.spawn_ack_task(writer.clone(), offset, aof_offset)
+            .await;
+    }
+
+    async fn spawn_ack_task(
+        &self,
+        writer: Arc<Mutex<WriteHalf<ReplicaStream>>>,
+        ack_offset: u64,
+        aof_offset: u64,
+    ) {
         tokio::spawn(async move {
             let ack_cmd_frame = RespFrame::Array(vec![
                 RespFrame::BulkString("REPLCONF".into()),
                 RespFrame::BulkString("ACK".into()),
                 RespFrame::BulkString(ack_offset.to_string().into()),
+                RespFrame::BulkString("FACK".into()),
+                RespFrame::BulkString(aof_offset.to_string().into()),
             ]);
             if let Ok(encoded) = ack_cmd_frame.encode_to_vec() {
                 if let Err(e) = writer.lock().await.write_all(&encoded).await {
@@ -646,9 +777,11 @@ impl ReplicaWorker {
         spldb_bytes.resize(spldb_len, 0);
         reader.read_exact(&mut spldb_bytes).await?;
 
-        load_from_bytes(&spldb_bytes.freeze(), &self.state.dbs)
+        let policies = load_from_bytes(&spldb_bytes.freeze(), &self.state.dbs)
             .await
             .map_err(|e| SpinelDBError::ReplicationError(format!("SPLDB loading failed: {e}")))?;
+        // A full resync adopts the primary's cache policies along with its data.
+        self.state.cache.replace_policies(policies).await;
         info!("Finished loading SPLDB data from primary.");
         Ok(())
     }
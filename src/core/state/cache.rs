@@ -4,16 +4,19 @@
 
 use crate::core::commands::cache::cache_fetch::{CacheFetch, FetchOutcome};
 use crate::core::commands::cache::cache_set::CacheSet;
+use crate::core::commands::cache::helpers::select_response_headers;
 use crate::core::commands::command_trait::WriteOutcome;
 use crate::core::database::ExecutionContext;
 use crate::core::metrics;
 use crate::core::state::ServerState;
+use crate::core::state::origin::{OriginHealth, OriginLimits, OriginPermit};
+use crate::core::state::warm::WarmJob;
 use crate::core::storage::cache_types::{
-    CacheBody, CachePolicy, ManifestEntry, ManifestState, VariantMap,
+    CacheBody, CachePolicy, HttpMetadata, ManifestEntry, ManifestState, VariantMap,
 };
 use crate::core::{Command, SpinelDBError};
 use bytes::Bytes;
-use dashmap::DashMap;
+use dashmap::{DashMap, DashSet};
 use futures::future::{BoxFuture, Shared};
 use std::collections::HashSet;
 use std::path::PathBuf;
@@ -24,6 +27,7 @@ use tokio::fs::File as TokioFile;
 use tokio::io::{AsyncWriteExt, BufWriter};
 use tokio::sync::{Mutex, RwLock, Semaphore, mpsc};
 use tracing::{debug, warn};
+use wildmatch::WildMatch;
 
 /// The time window within which a cache variant is considered "hot" or popular,
 /// making it a candidate for proactive revalidation.
@@ -33,22 +37,47 @@ const CACHE_REVALIDATOR_HOT_VARIANT_WINDOW: Duration = Duration::from_secs(3600)
 /// This is the core of the cache stampede protection mechanism.
 pub type SharedFetch = Shared<BoxFuture<'static, Result<FetchOutcome, Arc<SpinelDBError>>>>;
 
+/// A cache key qualified by the index of the database it is stored in. Per-key cache
+/// state is tracked under these, so that tenants isolated by database never share it.
+pub type DbKey = (usize, Bytes);
+
 /// Represents a job sent to the background revalidation worker.
 #[derive(Debug)]
 pub struct RevalidationJob {
+    pub db_index: usize,
     pub key: Bytes,
     pub url: String,
     pub variant_hash: u64,
 }
 
+/// Returns the current UNIX time in seconds for a manifest entry.
+fn manifest_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Cache counters for a single database, reported by `CACHE.STATS`.
+#[derive(Debug, Default)]
+pub struct CacheDbStats {
+    pub hits: AtomicU64,
+    pub misses: AtomicU64,
+    pub stale_hits: AtomicU64,
+    pub revalidations: AtomicU64,
+    pub evictions: AtomicU64,
+    pub demotions: AtomicU64,
+    pub promotions: AtomicU64,
+}
+
 /// Holds all state and logic related to the Intelligent Cache feature.
 #[derive(Debug)]
 pub struct CacheState {
     /// Per-key shared futures to prevent cache stampedes on `CACHE.FETCH`.
-    pub fetch_locks: Arc<DashMap<Bytes, SharedFetch>>,
+    pub fetch_locks: Arc<DashMap<DbKey, SharedFetch>>,
     /// Per-key locks to prevent stampedes on stale-while-revalidate (SWR) background fetches.
     /// Uses Weak pointers to allow for automatic cleanup when no longer in use.
-    pub swr_locks: Arc<DashMap<Bytes, Weak<Mutex<()>>>>,
+    pub swr_locks: Arc<DashMap<DbKey, Weak<Mutex<()>>>>,
     /// Counter for cache hits.
     pub hits: AtomicU64,
     /// Counter for cache misses.
@@ -59,26 +88,40 @@ pub struct CacheState {
     pub revalidations: AtomicU64,
     /// Counter for cache keys evicted due to memory pressure.
     pub evictions: AtomicU64,
+    /// Counter for cache keys whose bodies were moved to disk due to memory pressure.
+    pub demotions: AtomicU64,
+    /// Counter for on-disk cache bodies moved back to memory.
+    pub promotions: AtomicU64,
+    /// The same counters broken down by database, created on first use.
+    pub db_stats: DashMap<usize, CacheDbStats>,
     /// Stores user-defined caching rules for declarative caching.
     pub policies: RwLock<Vec<CachePolicy>>,
     /// A set of keys that match a `prewarm` policy.
     /// This allows the revalidator to efficiently sample only relevant keys.
-    pub prewarm_keys: RwLock<HashSet<Bytes>>,
+    pub prewarm_keys: RwLock<HashSet<DbKey>>,
     /// A channel to send revalidation jobs to the dedicated worker.
     pub revalidation_tx: mpsc::Sender<RevalidationJob>,
     /// Stores the last known purge epoch for a given tag. This is a logical clock
     /// used to invalidate tagged content in a cluster without relying on synchronized time.
-    pub tag_purge_epochs: Arc<DashMap<Bytes, u64>>,
+    /// Key: Database index and tag.
+    pub tag_purge_epochs: Arc<DashMap<DbKey, u64>>,
     /// Stores patterns for lazy background purging via `CACHE.PURGE`.
-    /// Key: Glob pattern, Value: Time the purge was requested.
-    pub purge_patterns: Arc<DashMap<Bytes, Instant>>,
+    /// Key: Database index and glob pattern, Value: Time the purge was requested.
+    pub purge_patterns: Arc<DashMap<DbKey, Instant>>,
     /// Manually applied locks from `CACHE.LOCK`.
-    /// Key: Cache key, Value: Expiry time of the lock.
-    pub manual_locks: Arc<DashMap<Bytes, Instant>>,
+    /// Key: Database index and cache key, Value: Expiry time of the lock.
+    pub manual_locks: Arc<DashMap<DbKey, Instant>>,
     /// A synchronized writer for the on-disk cache manifest file.
     pub manifest_writer: Arc<Mutex<Option<BufWriter<TokioFile>>>>,
     /// A semaphore to limit concurrent file reads from the on-disk cache.
     pub on_disk_read_semaphore: Arc<Semaphore>,
+    /// The health of each origin host fetched from, keyed by `host:port`.
+    pub origins: DashMap<String, Arc<OriginHealth>>,
+    /// The most recent `CACHE.WARM` job for each policy, keyed by database index and
+    /// policy name.
+    pub warm_jobs: DashMap<(usize, String), Arc<WarmJob>>,
+    /// The on-disk cache files whose bodies are being promoted back to memory.
+    pub promotions_in_flight: DashSet<PathBuf>,
 }
 
 impl CacheState {
@@ -101,6 +144,9 @@ impl CacheState {
             stale_hits: AtomicU64::new(0),
             revalidations: AtomicU64::new(0),
             evictions: AtomicU64::new(0),
+            demotions: AtomicU64::new(0),
+            promotions: AtomicU64::new(0),
+            db_stats: DashMap::new(),
             policies: RwLock::new(Vec::new()),
             prewarm_keys: RwLock::new(HashSet::new()),
             revalidation_tx,
@@ -109,27 +155,66 @@ impl CacheState {
             manual_locks: Arc::new(DashMap::new()),
             manifest_writer: Arc::new(Mutex::new(None)),
             on_disk_read_semaphore: Arc::new(Semaphore::new(semaphore_permits)),
+            origins: DashMap::new(),
+            warm_jobs: DashMap::new(),
+            promotions_in_flight: DashSet::new(),
         }
     }
 
     /// Logs an entry to the on-disk cache manifest file.
     pub async fn log_manifest(
         &self,
+        db_index: usize,
         key: Bytes,
         state: ManifestState,
         path: PathBuf,
     ) -> Result<(), SpinelDBError> {
+        self.write_manifest_entry(ManifestEntry {
+            timestamp: manifest_timestamp(),
+            state,
+            path,
+            db: db_index,
+            key,
+            status: None,
+            headers: vec![],
+        })
+        .await
+    }
+
+    /// Logs a `Committed` manifest entry together with the status and headers of
+    /// the origin response stored in the file.
+    pub async fn log_manifest_commit(
+        &self,
+        db_index: usize,
+        key: Bytes,
+        path: PathBuf,
+        metadata: &HttpMetadata,
+    ) -> Result<(), SpinelDBError> {
+        let headers = metadata
+            .headers
+            .iter()
+            .map(|(name, value)| {
+                (
+                    String::from_utf8_lossy(name).into_owned(),
+                    String::from_utf8_lossy(value).into_owned(),
+                )
+            })
+            .collect();
+        self.write_manifest_entry(ManifestEntry {
+            timestamp: manifest_timestamp(),
+            state: ManifestState::Committed,
+            path,
+            db: db_index,
+            key,
+            status: metadata.status,
+            headers,
+        })
+        .await
+    }
+
+    async fn write_manifest_entry(&self, entry: ManifestEntry) -> Result<(), SpinelDBError> {
         let mut writer_guard = self.manifest_writer.lock().await;
         if let Some(writer) = writer_guard.as_mut() {
-            let entry = ManifestEntry {
-                timestamp: SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .unwrap_or_default()
-                    .as_secs(),
-                state,
-                path,
-                key,
-            };
             let mut line = serde_json::to_vec(&entry)?;
             line.push(b'\n');
             writer.write_all(&line).await?;
@@ -138,9 +223,102 @@ impl CacheState {
         Ok(())
     }
 
+    /// Inserts a policy or replaces the existing policy with the same name, keeping
+    /// the list ordered by priority. Returns the policy that was replaced, if any.
+    pub async fn set_policy(&self, policy: CachePolicy) -> Option<CachePolicy> {
+        let mut policies = self.policies.write().await;
+        let old_policy = match policies.iter_mut().find(|p| p.name == policy.name) {
+            Some(existing) => Some(std::mem::replace(existing, policy.clone())),
+            None => {
+                policies.push(policy.clone());
+                None
+            }
+        };
+        // Re-sort policies by priority after any modification.
+        policies.sort_by_key(|p| std::cmp::Reverse(p.priority));
+        drop(policies);
+
+        // If the `prewarm` flag was turned off, forget the keys it was tracking.
+        if let Some(old) = &old_policy
+            && old.prewarm
+            && !policy.prewarm
+        {
+            debug!(
+                "Policy '{}' changed from prewarm=true to false. Cleaning up prewarm keys.",
+                old.name
+            );
+            self.forget_prewarm_keys(&old.key_pattern).await;
+        }
+        old_policy
+    }
+
+    /// Removes the policy with the given name. Returns the removed policy, if any.
+    pub async fn delete_policy(&self, name: &str) -> Option<CachePolicy> {
+        let mut policies = self.policies.write().await;
+        let position = policies.iter().position(|p| p.name == name)?;
+        let deleted_policy = policies.remove(position);
+        drop(policies);
+
+        if deleted_policy.prewarm {
+            debug!(
+                "Prewarm policy '{}' deleted. Cleaning up prewarm keys.",
+                deleted_policy.name
+            );
+            self.forget_prewarm_keys(&deleted_policy.key_pattern).await;
+        }
+        Some(deleted_policy)
+    }
+
+    /// Replaces the whole policy list, e.g. with the policies of a loaded snapshot.
+    pub async fn replace_policies(&self, mut new_policies: Vec<CachePolicy>) {
+        new_policies.sort_by_key(|p| std::cmp::Reverse(p.priority));
+        *self.policies.write().await = new_policies;
+        self.prewarm_keys.write().await.clear();
+    }
+
+    /// Removes the keys matching `key_pattern` from the prewarm key set.
+    async fn forget_prewarm_keys(&self, key_pattern: &str) {
+        let matcher = WildMatch::new(key_pattern);
+        self.prewarm_keys
+            .write()
+            .await
+            .retain(|(_, key)| !matcher.matches(&String::from_utf8_lossy(key)));
+    }
+
+    /// Runs `update` on the counters of the given database.
+    fn with_db_stats(&self, db_index: usize, update: impl FnOnce(&CacheDbStats)) {
+        update(&self.db_stats.entry(db_index).or_default());
+    }
+
+    /// Waits for a request slot on the origin of `url`, applying the origin limits of
+    /// `policy`. Fails with `OriginUnavailable` while the origin's circuit breaker is open.
+    pub async fn acquire_origin(
+        &self,
+        url: &str,
+        policy: Option<&CachePolicy>,
+    ) -> Result<OriginPermit, SpinelDBError> {
+        let host = OriginHealth::host_of(url)
+            .ok_or_else(|| SpinelDBError::InvalidRequest(format!("Invalid URL: {url}")))?;
+        let origin = self
+            .origins
+            .entry(host.clone())
+            .or_insert_with(|| Arc::new(OriginHealth::new(host)))
+            .clone();
+        origin.acquire(&OriginLimits::from_policy(policy)).await
+    }
+
+    /// Returns the tracked health of the origin of `url`, if it has been fetched from.
+    pub fn origin_health(&self, url: &str) -> Option<Arc<OriginHealth>> {
+        let host = OriginHealth::host_of(url)?;
+        self.origins.get(&host).map(|origin| origin.clone())
+    }
+
     /// Atomically increments the counter for cache hits.
-    pub fn increment_hits(&self) {
+    pub fn increment_hits(&self, db_index: usize) {
         self.hits.fetch_add(1, Ordering::Relaxed);
+        self.with_db_stats(db_index, |stats| {
+            stats.hits.fetch_add(1, Ordering::Relaxed);
+        });
         // Use a "manual" label for hits not associated with a specific policy.
         metrics::CACHE_HITS_TOTAL
             .with_label_values(&["manual"])
@@ -148,8 +326,11 @@ impl CacheState {
     }
 
     /// Atomically increments the counter for cache misses.
-    pub fn increment_misses(&self) {
+    pub fn increment_misses(&self, db_index: usize) {
         self.misses.fetch_add(1, Ordering::Relaxed);
+        self.with_db_stats(db_index, |stats| {
+            stats.misses.fetch_add(1, Ordering::Relaxed);
+        });
         // Use a "manual" label for misses not associated with a specific policy.
         metrics::CACHE_MISSES_TOTAL
             .with_label_values(&["manual"])
@@ -157,30 +338,58 @@ impl CacheState {
     }
 
     /// Atomically increments the counter for stale cache hits.
-    pub fn increment_stale_hits(&self) {
+    pub fn increment_stale_hits(&self, db_index: usize) {
         self.stale_hits.fetch_add(1, Ordering::Relaxed);
+        self.with_db_stats(db_index, |stats| {
+            stats.stale_hits.fetch_add(1, Ordering::Relaxed);
+        });
     }
 
     /// Atomically increments the counter for successful revalidations.
-    pub fn increment_revalidations(&self) {
+    pub fn increment_revalidations(&self, db_index: usize) {
         self.revalidations.fetch_add(1, Ordering::Relaxed);
+        self.with_db_stats(db_index, |stats| {
+            stats.revalidations.fetch_add(1, Ordering::Relaxed);
+        });
     }
 
     /// Atomically increments the counter for cache evictions.
-    pub fn increment_evictions(&self) {
+    pub fn increment_evictions(&self, db_index: usize) {
         self.evictions.fetch_add(1, Ordering::Relaxed);
+        self.with_db_stats(db_index, |stats| {
+            stats.evictions.fetch_add(1, Ordering::Relaxed);
+        });
         metrics::CACHE_EVICTIONS_TOTAL.inc();
     }
 
+    /// Atomically increments the counter for cache demotions.
+    pub fn increment_demotions(&self, db_index: usize) {
+        self.demotions.fetch_add(1, Ordering::Relaxed);
+        self.with_db_stats(db_index, |stats| {
+            stats.demotions.fetch_add(1, Ordering::Relaxed);
+        });
+        metrics::CACHE_DEMOTIONS_TOTAL.inc();
+    }
+
+    /// Atomically increments the counter for cache promotions.
+    pub fn increment_promotions(&self, db_index: usize) {
+        self.promotions.fetch_add(1, Ordering::Relaxed);
+        self.with_db_stats(db_index, |stats| {
+            stats.promotions.fetch_add(1, Ordering::Relaxed);
+        });
+        metrics::CACHE_PROMOTIONS_TOTAL.inc();
+    }
+
     /// Performs an HTTP fetch to an origin server and updates the cache.
     /// This is a utility function used by background revalidation tasks.
     pub async fn fetch_from_origin(
         &self,
         server_state: &Arc<ServerState>,
+        db_index: usize,
         cmd: &CacheFetch,
         bypass_store: bool,
     ) -> Result<(Bytes, WriteOutcome), SpinelDBError> {
-        self.increment_misses();
+        self.increment_misses(db_index);
         let client = reqwest::Client::new();
         let res =
             client.get(&cmd.url).send().await.map_err(|e| {
@@ -229,10 +438,22 @@ impl CacheState {
             tags: cmd.tags.clone(),
             vary: cmd.vary.clone(),
             headers: cmd.headers.clone(),
+            status: Some(200),
+            response_headers: select_response_headers(
+                &headers,
+                &server_state
+                    .config
+                    .lock()
+                    .await
+                    .cache
+                    .stored_response_headers,
+            ),
             ..Default::default()
         };
 
-        let db = server_state.get_db(0).unwrap();
+        let db = server_state
+            .get_db(db_index)
+            .ok_or_else(|| SpinelDBError::Internal("Cache database not found".into()))?;
         let set_cmd_internal = set_cmd.clone();
 
         let set_command_for_lock = Command::Cache(crate::core::commands::cache::Cache {
@@ -246,6 +467,7 @@ impl CacheState {
             command: Some(set_command_for_lock),
             session_id: 0,
             authenticated_user: None,
+            write_guard: None,
         };
         let (_, write_outcome) = set_cmd_internal
             .execute_internal(&mut set_ctx, CacheBody::InMemory(body.clone()))
@@ -256,7 +478,12 @@ impl CacheState {
 
     /// Queues jobs for background, asynchronous revalidation for a cache key.
     /// This smart version only queues jobs for variants that have been accessed recently.
-    pub async fn trigger_smart_background_revalidation(&self, key: Bytes, variants: VariantMap) {
+    pub async fn trigger_smart_background_revalidation(
+        &self,
+        db_index: usize,
+        key: Bytes,
+        variants: VariantMap,
+    ) {
         let now = Instant::now();
         let jobs_to_queue: Vec<_> = variants
             .into_iter()
@@ -269,6 +496,7 @@ impl CacheState {
                         .revalidate_url
                         .clone()
                         .map(|url| RevalidationJob {
+                            db_index,
                             key: key.clone(),
                             url,
                             variant_hash: hash,
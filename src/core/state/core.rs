@@ -265,6 +265,15 @@ impl ServerState {
         self.dbs.get(db_index).cloned()
     }
 
+    /// Returns the index of `db`, which must be one of this server's databases.
+    /// Used by subsystems that hand work for a database over to background tasks.
+    pub fn db_index_of(&self, db: &Db) -> usize {
+        self.dbs
+            .iter()
+            .position(|candidate| std::ptr::eq(Arc::as_ptr(candidate), db))
+            .unwrap_or(0)
+    }
+
     /// Sets the server's read-only mode for administrative reasons.
     pub fn set_read_only(&self, value: bool, reason: &str) {
         if value {
@@ -6,9 +6,11 @@
 pub mod cache;
 mod client;
 mod core;
+pub mod origin;
 mod persistence;
 mod replication;
 mod stats;
+pub mod warm;
 
 pub use cache::CacheState;
 pub use client::*;
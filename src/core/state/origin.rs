@@ -0,0 +1,383 @@
+// src/core/state/origin.rs
+
+//! Tracks the health of the origin hosts the Intelligent Cache fetches from.
+//!
+//! Each host gets an optional concurrency limit and a circuit breaker. The breaker
+//! opens when the error rate over a sliding window crosses a policy's threshold, so
+//! that requests fail immediately and stale or grace content is served instead of
+//! waiting on a failing origin. While open, a single probe request is let through
+//! after a backoff that doubles with every failed probe.
+
+use crate::core::SpinelDBError;
+use crate::core::metrics;
+use crate::core::storage::cache_types::{
+    CachePolicy, MAX_BREAKER_BACKOFF_SECS, MAX_BREAKER_WINDOW_SECS, MAX_ORIGIN_CONCURRENCY,
+};
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tracing::{info, warn};
+use url::Url;
+
+/// The default length of the breaker's sliding window.
+const DEFAULT_WINDOW: Duration = Duration::from_secs(10);
+/// The default number of requests in the window before the breaker may open.
+const DEFAULT_MIN_REQUESTS: u64 = 5;
+/// The default time the breaker stays open before the first probe.
+const DEFAULT_BACKOFF: Duration = Duration::from_secs(1);
+/// The longest time the breaker stays open between probes.
+const MAX_BACKOFF: Duration = Duration::from_secs(MAX_BREAKER_BACKOFF_SECS);
+/// The longest sliding window of the breaker.
+const MAX_WINDOW: Duration = Duration::from_secs(MAX_BREAKER_WINDOW_SECS);
+
+/// The origin protection settings of a cache policy.
+#[derive(Debug, Clone)]
+pub struct OriginLimits {
+    /// The maximum number of concurrent requests to the host.
+    pub concurrency: Option<usize>,
+    /// The error rate (in percent) that opens the breaker. `None` disables it.
+    pub error_rate: Option<u64>,
+    pub window: Duration,
+    pub min_requests: u64,
+    pub backoff: Duration,
+}
+
+impl OriginLimits {
+    /// Returns the limits of `policy`, or no limits if no policy applies.
+    pub fn from_policy(policy: Option<&CachePolicy>) -> Self {
+        Self {
+            concurrency: policy
+                .and_then(|p| p.origin_concurrency)
+                .map(|n| n.min(MAX_ORIGIN_CONCURRENCY) as usize),
+            error_rate: policy.and_then(|p| p.breaker_error_rate),
+            window: policy
+                .and_then(|p| p.breaker_window)
+                .map_or(DEFAULT_WINDOW, |s| Duration::from_secs(s).min(MAX_WINDOW)),
+            min_requests: policy
+                .and_then(|p| p.breaker_min_requests)
+                .unwrap_or(DEFAULT_MIN_REQUESTS),
+            backoff: policy
+                .and_then(|p| p.breaker_backoff)
+                .map_or(DEFAULT_BACKOFF, |s| Duration::from_secs(s).min(MAX_BACKOFF)),
+        }
+    }
+}
+
+/// The state of an origin's circuit breaker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Requests flow normally.
+    Closed,
+    /// Requests are rejected until the backoff has elapsed.
+    Open,
+    /// A single probe request is in flight; other requests are rejected.
+    HalfOpen,
+}
+
+impl CircuitState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CircuitState::Closed => "closed",
+            CircuitState::Open => "open",
+            CircuitState::HalfOpen => "half-open",
+        }
+    }
+
+    fn gauge_value(&self) -> f64 {
+        match self {
+            CircuitState::Closed => 0.0,
+            CircuitState::Open => 1.0,
+            CircuitState::HalfOpen => 2.0,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Breaker {
+    state: CircuitState,
+    /// The time and success of each request in the sliding window.
+    outcomes: VecDeque<(Instant, bool)>,
+    /// When an open breaker lets the next probe through.
+    retry_at: Instant,
+    backoff: Duration,
+}
+
+/// A point-in-time view of an origin's health, reported by `CACHE.INFO`.
+#[derive(Debug, Clone)]
+pub struct OriginSnapshot {
+    pub circuit: CircuitState,
+    /// The error rate (in percent) over the breaker's current window.
+    pub error_rate: u64,
+    pub in_flight: u64,
+    /// Seconds until an open breaker lets the next probe through.
+    pub retry_in: u64,
+    pub requests: u64,
+    pub failures: u64,
+    pub rejected: u64,
+}
+
+/// The health of a single origin host.
+#[derive(Debug)]
+pub struct OriginHealth {
+    host: String,
+    breaker: Mutex<Breaker>,
+    /// The concurrency limit and its semaphore, replaced when the limit changes.
+    limiter: Mutex<Option<(usize, Arc<Semaphore>)>>,
+    in_flight: AtomicU64,
+    requests: AtomicU64,
+    failures: AtomicU64,
+    rejected: AtomicU64,
+}
+
+impl OriginHealth {
+    pub fn new(host: String) -> Self {
+        Self {
+            host,
+            breaker: Mutex::new(Breaker {
+                state: CircuitState::Closed,
+                outcomes: VecDeque::new(),
+                retry_at: Instant::now(),
+                backoff: DEFAULT_BACKOFF,
+            }),
+            limiter: Mutex::new(None),
+            in_flight: AtomicU64::new(0),
+            requests: AtomicU64::new(0),
+            failures: AtomicU64::new(0),
+            rejected: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the `host:port` that identifies the origin of `url`.
+    pub fn host_of(url: &str) -> Option<String> {
+        let url = Url::parse(url).ok()?;
+        Some(format!(
+            "{}:{}",
+            url.host_str()?,
+            url.port_or_known_default()?
+        ))
+    }
+
+    /// Returns the `host:port` of this origin.
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    /// Waits for a request slot on this origin. Fails immediately with
+    /// `OriginUnavailable` if the circuit breaker is open.
+    pub async fn acquire(
+        self: &Arc<Self>,
+        limits: &OriginLimits,
+    ) -> Result<OriginPermit, SpinelDBError> {
+        // Reject before queueing for a slot, so that requests don't wait on an open breaker.
+        if limits.error_rate.is_some() {
+            let blocked = {
+                let breaker = self.breaker.lock();
+                match breaker.state {
+                    CircuitState::Closed => false,
+                    CircuitState::Open => Instant::now() < breaker.retry_at,
+                    CircuitState::HalfOpen => true,
+                }
+            };
+            if blocked {
+                return Err(self.reject());
+            }
+        }
+        let slot = match limits.concurrency {
+            Some(limit) => Some(
+                self.semaphore(limit)
+                    .acquire_owned()
+                    .await
+                    .map_err(|e| SpinelDBError::Internal(e.to_string()))?,
+            ),
+            None => None,
+        };
+        let probe = self.admit(limits)?;
+
+        let in_flight = self.in_flight.fetch_add(1, Ordering::Relaxed) + 1;
+        metrics::CACHE_ORIGIN_IN_FLIGHT
+            .with_label_values(&[self.host.as_str()])
+            .set(in_flight as f64);
+        Ok(OriginPermit {
+            origin: self.clone(),
+            limits: limits.clone(),
+            probe,
+            recorded: false,
+            _slot: slot,
+        })
+    }
+
+    /// Checks the breaker, letting a probe through once an open breaker's backoff
+    /// has elapsed. Returns whether the request is that probe.
+    fn admit(&self, limits: &OriginLimits) -> Result<bool, SpinelDBError> {
+        if limits.error_rate.is_none() {
+            return Ok(false);
+        }
+        let mut breaker = self.breaker.lock();
+        match breaker.state {
+            CircuitState::Closed => Ok(false),
+            CircuitState::Open if Instant::now() >= breaker.retry_at => {
+                self.set_state(&mut breaker, CircuitState::HalfOpen);
+                Ok(true)
+            }
+            CircuitState::Open | CircuitState::HalfOpen => {
+                drop(breaker);
+                Err(self.reject())
+            }
+        }
+    }
+
+    fn reject(&self) -> SpinelDBError {
+        self.rejected.fetch_add(1, Ordering::Relaxed);
+        metrics::CACHE_ORIGIN_REQUESTS_TOTAL
+            .with_label_values(&[self.host.as_str(), "rejected"])
+            .inc();
+        SpinelDBError::OriginUnavailable(format!(
+            "circuit breaker for origin '{}' is open",
+            self.host
+        ))
+    }
+
+    fn semaphore(&self, limit: usize) -> Arc<Semaphore> {
+        let mut limiter = self.limiter.lock();
+        match limiter.as_ref() {
+            Some((current, semaphore)) if *current == limit => semaphore.clone(),
+            _ => {
+                let semaphore = Arc::new(Semaphore::new(limit.max(1)));
+                *limiter = Some((limit, semaphore.clone()));
+                semaphore
+            }
+        }
+    }
+
+    fn record(&self, success: bool, probe: bool, limits: &OriginLimits) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        if !success {
+            self.failures.fetch_add(1, Ordering::Relaxed);
+        }
+        metrics::CACHE_ORIGIN_REQUESTS_TOTAL
+            .with_label_values(&[
+                self.host.as_str(),
+                if success { "success" } else { "failure" },
+            ])
+            .inc();
+
+        let now = Instant::now();
+        let mut breaker = self.breaker.lock();
+        if probe {
+            if success {
+                info!("Origin '{}' recovered. Closing circuit breaker.", self.host);
+                breaker.outcomes.clear();
+                breaker.backoff = limits.backoff;
+                self.set_state(&mut breaker, CircuitState::Closed);
+            } else {
+                breaker.backoff = (breaker.backoff * 2).min(MAX_BACKOFF);
+                self.open(&mut breaker, now);
+            }
+            return;
+        }
+
+        breaker.outcomes.push_back((now, success));
+        while breaker
+            .outcomes
+            .front()
+            .is_some_and(|(at, _)| now.duration_since(*at) > limits.window)
+        {
+            breaker.outcomes.pop_front();
+        }
+
+        let Some(threshold) = limits.error_rate else {
+            return;
+        };
+        let total = breaker.outcomes.len() as u64;
+        let failures = breaker.outcomes.iter().filter(|(_, ok)| !ok).count() as u64;
+        if breaker.state == CircuitState::Closed
+            && total >= limits.min_requests
+            && failures * 100 >= threshold * total
+        {
+            warn!(
+                "Origin '{}' failed {failures} of {total} requests. Opening circuit breaker.",
+                self.host
+            );
+            breaker.outcomes.clear();
+            breaker.backoff = limits.backoff;
+            self.open(&mut breaker, now);
+        }
+    }
+
+    fn open(&self, breaker: &mut Breaker, now: Instant) {
+        breaker.retry_at = now
+            .checked_add(breaker.backoff)
+            .unwrap_or(now + MAX_BACKOFF);
+        self.set_state(breaker, CircuitState::Open);
+    }
+
+    fn set_state(&self, breaker: &mut Breaker, state: CircuitState) {
+        breaker.state = state;
+        metrics::CACHE_ORIGIN_CIRCUIT_STATE
+            .with_label_values(&[self.host.as_str()])
+            .set(state.gauge_value());
+    }
+
+    /// Returns the current health of this origin.
+    pub fn snapshot(&self) -> OriginSnapshot {
+        let breaker = self.breaker.lock();
+        let total = breaker.outcomes.len() as u64;
+        let failures = breaker.outcomes.iter().filter(|(_, ok)| !ok).count() as u64;
+        OriginSnapshot {
+            circuit: breaker.state,
+            error_rate: (failures * 100).checked_div(total).unwrap_or(0),
+            in_flight: self.in_flight.load(Ordering::Relaxed),
+            retry_in: match breaker.state {
+                CircuitState::Open => breaker
+                    .retry_at
+                    .saturating_duration_since(Instant::now())
+                    .as_secs(),
+                _ => 0,
+            },
+            requests: self.requests.load(Ordering::Relaxed),
+            failures: self.failures.load(Ordering::Relaxed),
+            rejected: self.rejected.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// A request slot on an origin. The outcome of the request must be reported with
+/// [`OriginPermit::record`]; the slot is released when the permit is dropped.
+#[derive(Debug)]
+pub struct OriginPermit {
+    origin: Arc<OriginHealth>,
+    limits: OriginLimits,
+    probe: bool,
+    recorded: bool,
+    _slot: Option<OwnedSemaphorePermit>,
+}
+
+impl OriginPermit {
+    /// Records whether the origin answered the request successfully.
+    pub fn record(&mut self, success: bool) {
+        if !self.recorded {
+            self.recorded = true;
+            self.origin.record(success, self.probe, &self.limits);
+        }
+    }
+}
+
+impl Drop for OriginPermit {
+    fn drop(&mut self) {
+        // A probe that was abandoned before completing lets the next request probe again.
+        if self.probe && !self.recorded {
+            let mut breaker = self.origin.breaker.lock();
+            if breaker.state == CircuitState::HalfOpen {
+                breaker.retry_at = Instant::now();
+                self.origin.set_state(&mut breaker, CircuitState::Open);
+            }
+        }
+        let in_flight = self.origin.in_flight.fetch_sub(1, Ordering::Relaxed) - 1;
+        metrics::CACHE_ORIGIN_IN_FLIGHT
+            .with_label_values(&[self.origin.host.as_str()])
+            .set(in_flight as f64);
+    }
+}
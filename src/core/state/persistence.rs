@@ -2,12 +2,13 @@
 
 //! Contains state definitions related to data persistence (AOF/SPLDB).
 
-use crate::core::events::PropagatedWork;
+use crate::core::persistence::aof_manifest::AofManifest;
 use crate::core::tasks::lazy_free::LazyFreeItem;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::time::Instant;
-use tokio::sync::{Mutex, mpsc};
+use tokio::sync::{Mutex, mpsc, watch};
 use tokio::task::JoinHandle;
 
 /// Holds the state for an in-progress AOF rewrite operation.
@@ -15,10 +16,19 @@ use tokio::task::JoinHandle;
 pub struct AofRewriteState {
     /// True if an AOF rewrite is currently active.
     pub is_in_progress: bool,
-    /// Buffers write commands that arrive while the rewrite is in progress.
-    pub buffer: Vec<PropagatedWork>,
-    /// The estimated total size of the buffered commands in bytes.
-    pub buffer_size: usize,
+    /// Set by the rewriter when it rotates to a new incremental file. The AOF writer
+    /// switches to this file once it has written every work unit before the cut.
+    pub pending_incr_file: Option<PendingIncrFile>,
+}
+
+/// An incremental file that the AOF writer has yet to switch to.
+#[derive(Debug)]
+pub struct PendingIncrFile {
+    pub path: PathBuf,
+    /// The number of work units enqueued when the rewrite's snapshot began. These are
+    /// covered by the new base, so they belong to the previous incremental file and
+    /// everything after them belongs to this one.
+    pub after_seq: u64,
 }
 
 /// Holds all state and channels related to persistence.
@@ -28,6 +38,8 @@ pub struct PersistenceState {
     pub is_saving_spldb: Arc<AtomicBool>,
     /// The state of the AOF rewrite process, protected by a Mutex.
     pub aof_rewrite_state: Arc<Mutex<AofRewriteState>>,
+    /// The manifest of the multi-part AOF. `None` until it is first loaded.
+    pub aof_manifest: Mutex<Option<AofManifest>>,
     /// A handle to the spawned AOF rewrite task, if any.
     pub aof_rewrite_handle: Arc<Mutex<Option<JoinHandle<()>>>>,
     /// A handle to the spawned BGSAVE task, if any.
@@ -49,6 +61,10 @@ pub struct PersistenceState {
     /// The size of the AOF file at the end of the last successful rewrite.
     /// Used by the auto-rewrite manager to calculate growth percentage.
     pub aof_last_rewrite_size: Arc<AtomicU64>,
+    /// The number of work units handed to the AOF writer so far.
+    pub aof_enqueued_seq: AtomicU64,
+    /// The number of work units the AOF writer has durably fsynced. Used by `WAITAOF`.
+    pub aof_fsynced_seq: watch::Sender<u64>,
 }
 
 impl PersistenceState {
@@ -61,6 +77,7 @@ impl PersistenceState {
         Self {
             is_saving_spldb: Arc::new(AtomicBool::new(false)),
             aof_rewrite_state: Arc::new(Mutex::new(AofRewriteState::default())),
+            aof_manifest: Mutex::new(None),
             aof_rewrite_handle: Arc::new(Mutex::new(None)),
             bgsave_handle: Arc::new(Mutex::new(None)),
             dirty_keys_counter: Arc::new(AtomicU64::new(0)),
@@ -71,6 +88,8 @@ impl PersistenceState {
             aof_rewrite_complete_tx,
             lazy_free_tx,
             aof_last_rewrite_size: Arc::new(AtomicU64::new(0)),
+            aof_enqueued_seq: AtomicU64::new(0),
+            aof_fsynced_seq: watch::channel(0).0,
         }
     }
 
@@ -89,4 +108,29 @@ impl PersistenceState {
     pub fn get_lazy_free_errors(&self) -> u64 {
         self.lazy_free_queue_full_errors.load(Ordering::Relaxed)
     }
+
+    /// Returns true if every work unit enqueued so far has been fsynced to the AOF.
+    pub fn is_aof_fully_synced(&self) -> bool {
+        *self.aof_fsynced_seq.borrow() >= self.aof_enqueued_seq.load(Ordering::SeqCst)
+    }
+
+    /// Waits until the AOF writer has fsynced at least `target_seq` work units, or until
+    /// `deadline` passes. An fsync is requested up front so that `everysec` and `no`
+    /// policies do not delay the caller unnecessarily. Returns true if the target was reached.
+    pub async fn wait_for_aof_fsync(&self, target_seq: u64, deadline: Option<Instant>) -> bool {
+        let mut fsynced_rx = self.aof_fsynced_seq.subscribe();
+        if *fsynced_rx.borrow_and_update() >= target_seq {
+            return true;
+        }
+        let _ = self.aof_fsync_request_tx.try_send(());
+
+        let reached = fsynced_rx.wait_for(|seq| *seq >= target_seq);
+        match deadline {
+            Some(deadline) => matches!(
+                tokio::time::timeout_at(deadline.into(), reached).await,
+                Ok(Ok(_))
+            ),
+            None => reached.await.is_ok(),
+        }
+    }
 }
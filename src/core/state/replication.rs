@@ -5,14 +5,20 @@
 use crate::core::SpinelDBError;
 use crate::core::state::ServerState;
 use dashmap::DashMap;
+use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
+use std::net::SocketAddr;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::{oneshot, watch};
 use tracing::{info, warn};
 
+/// How often a waiting `WAIT`/`WAITAOF` re-asks replicas for their offsets.
+const GETACK_RESEND_INTERVAL: Duration = Duration::from_millis(100);
+
 /// The synchronization state of a replica connected to this primary.
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ReplicaSyncState {
@@ -29,6 +35,8 @@ pub struct ReplicaStateInfo {
     pub sync_state: ReplicaSyncState,
     /// The last replication offset acknowledged by the replica.
     pub ack_offset: u64,
+    /// The last replication offset the replica reported as fsynced to its own AOF (`FACK`).
+    pub aof_ack_offset: u64,
     /// The timestamp of the last acknowledgment received from the replica.
     pub last_ack_time: Instant,
 }
@@ -51,11 +59,13 @@ pub struct ReplicaInfo {
     pub processed_offset: u64,
 }
 
-/// A serializable struct for persisting the poisoned masters map.
+/// A serializable struct for persisting the poisoned masters map and failover epoch.
 #[derive(Serialize, Deserialize)]
 struct PoisonedMastersSerializable {
     /// Key: run_id, Value: expiry UNIX timestamp in seconds.
     entries: HashMap<String, u64>,
+    #[serde(default)]
+    failover_epoch: u64,
 }
 
 /// The central struct holding all replication-related state.
@@ -70,6 +80,18 @@ pub struct ReplicationState {
     /// connecting to a demoted (stale) primary.
     /// Key: Master run_id, Value: UNIX timestamp (seconds) for the poison entry's expiry.
     pub poisoned_masters: Arc<DashMap<String, u64>>,
+    /// The highest Warden failover epoch this server has been reconfigured in, set with
+    /// `FAILOVER EPOCH`. Reconfiguration from Wardens of older epochs is refused.
+    pub failover_epoch: AtomicU64,
+    /// Bumped every time a replica acknowledges an offset, waking up `WAIT`/`WAITAOF` callers.
+    pub ack_notifier: watch::Sender<u64>,
+    /// Bumped to ask every replica handler to send `REPLCONF GETACK` to its replica.
+    pub getack_notifier: watch::Sender<u64>,
+    /// The number of work units the backlog feeder has consumed from the event bus.
+    /// Used to ensure a `WAIT` targets an offset that includes the caller's own writes.
+    pub feeder_progress: watch::Sender<u64>,
+    /// Callers of `offset_after_units` waiting for the feeder to reach a number of work units.
+    feeder_waiters: Mutex<Vec<(u64, oneshot::Sender<u64>)>>,
 }
 
 impl ReplicationState {
@@ -85,10 +107,15 @@ impl ReplicationState {
             },
             replica_info: tokio::sync::Mutex::new(None),
             poisoned_masters: Arc::new(DashMap::new()),
+            failover_epoch: AtomicU64::new(0),
+            ack_notifier: watch::channel(0).0,
+            getack_notifier: watch::channel(0).0,
+            feeder_progress: watch::channel(0).0,
+            feeder_waiters: Mutex::new(Vec::new()),
         }
     }
 
-    /// Saves the current state of poisoned masters to a JSON file.
+    /// Saves the current state of poisoned masters and the failover epoch to a JSON file.
     pub fn save_poisoned_masters_to_disk(&self) -> Result<(), SpinelDBError> {
         info!("Saving poisoned masters state to disk.");
         let now_unix_secs = SystemTime::now()
@@ -104,7 +131,8 @@ impl ReplicationState {
             .map(|entry| (entry.key().clone(), *entry.value()))
             .collect();
 
-        if entries.is_empty() {
+        let failover_epoch = self.failover_epoch.load(Ordering::SeqCst);
+        if entries.is_empty() && failover_epoch == 0 {
             // If there are no entries, remove the file if it exists.
             if fs::metadata(Self::POISONED_MASTERS_FILE).is_ok() {
                 fs::remove_file(Self::POISONED_MASTERS_FILE)?;
@@ -112,7 +140,10 @@ impl ReplicationState {
             return Ok(());
         }
 
-        let serializable = PoisonedMastersSerializable { entries };
+        let serializable = PoisonedMastersSerializable {
+            entries,
+            failover_epoch,
+        };
         let json_data = serde_json::to_string(&serializable)?;
 
         // Atomically write the file by first writing to a temp file and then renaming.
@@ -129,6 +160,8 @@ impl ReplicationState {
             Ok(json_data) => {
                 match serde_json::from_str::<PoisonedMastersSerializable>(&json_data) {
                     Ok(deserialized) => {
+                        self.failover_epoch
+                            .store(deserialized.failover_epoch, Ordering::SeqCst);
                         let now_unix_secs = SystemTime::now()
                             .duration_since(UNIX_EPOCH)
                             .unwrap_or_default()
@@ -205,4 +238,149 @@ impl ReplicationState {
         }
         Ok(())
     }
+
+    /// Records a `REPLCONF ACK` received from a replica and wakes up any waiting clients.
+    pub fn record_replica_ack(
+        &self,
+        replica_states: &DashMap<SocketAddr, ReplicaStateInfo>,
+        addr: &SocketAddr,
+        offset: u64,
+        aof_offset: Option<u64>,
+    ) {
+        if let Some(mut replica_state) = replica_states.get_mut(addr) {
+            let info = replica_state.value_mut();
+            info.ack_offset = offset;
+            if let Some(aof_offset) = aof_offset {
+                info.aof_ack_offset = aof_offset;
+            }
+            info.last_ack_time = Instant::now();
+        } else {
+            return;
+        }
+        self.ack_notifier.send_modify(|n| *n = n.wrapping_add(1));
+    }
+
+    /// Asks all connected replicas to report their current offsets as soon as possible.
+    pub fn request_replica_acks(&self) {
+        self.getack_notifier.send_modify(|n| *n = n.wrapping_add(1));
+    }
+
+    /// Returns the replication offset that covers every write published so far.
+    ///
+    /// Writes reach the backlog asynchronously through the feeder task, so this first
+    /// waits (bounded by `deadline`) until the feeder has consumed everything that the
+    /// event bus had published at the time of the call.
+    pub async fn current_write_offset(
+        &self,
+        server_state: &ServerState,
+        deadline: Option<Instant>,
+    ) -> u64 {
+        let published = server_state.event_bus.replication_published();
+        let mut progress_rx = self.feeder_progress.subscribe();
+        let caught_up = progress_rx.wait_for(|fed| *fed >= published);
+        match deadline {
+            Some(deadline) => {
+                let _ = tokio::time::timeout_at(deadline.into(), caught_up).await;
+            }
+            None => {
+                let _ = caught_up.await;
+            }
+        }
+        self.get_replication_offset()
+    }
+
+    /// Returns the replication offset right after the first `units` work units published
+    /// on the event bus, once the backlog feeder has consumed them.
+    ///
+    /// Unlike `current_write_offset`, the result does not cover later work units, so it
+    /// can be paired with a snapshot. This must be called while no writer can publish,
+    /// e.g. at a snapshot cut, so that the feeder cannot already be past `units`.
+    pub fn offset_after_units(&self, units: u64) -> oneshot::Receiver<u64> {
+        let (tx, rx) = oneshot::channel();
+        let mut waiters = self.feeder_waiters.lock();
+        if *self.feeder_progress.borrow() >= units {
+            let _ = tx.send(self.get_replication_offset());
+        } else {
+            waiters.push((units, tx));
+        }
+        rx
+    }
+
+    /// Records that the backlog feeder has consumed `units` more work units and hands
+    /// the current offset to the `offset_after_units` callers it has caught up with.
+    pub fn advance_feeder(&self, units: u64) {
+        let mut waiters = self.feeder_waiters.lock();
+        let mut fed = 0;
+        self.feeder_progress.send_modify(|n| {
+            *n += units;
+            fed = *n;
+        });
+        if waiters.is_empty() {
+            return;
+        }
+        let offset = self.get_replication_offset();
+        let (ready, pending): (Vec<_>, Vec<_>) =
+            waiters.drain(..).partition(|(target, _)| *target <= fed);
+        *waiters = pending;
+        for (_, tx) in ready {
+            let _ = tx.send(offset);
+        }
+    }
+
+    /// Counts the online replicas whose acknowledged offset has reached `target_offset`.
+    /// If `aof` is true, the offset the replica reported as fsynced to its AOF is used instead.
+    pub fn count_acked_replicas(
+        replica_states: &DashMap<SocketAddr, ReplicaStateInfo>,
+        target_offset: u64,
+        aof: bool,
+    ) -> usize {
+        replica_states
+            .iter()
+            .filter(|entry| {
+                let info = entry.value();
+                let acked = if aof {
+                    info.aof_ack_offset
+                } else {
+                    info.ack_offset
+                };
+                info.sync_state == ReplicaSyncState::Online && acked >= target_offset
+            })
+            .count()
+    }
+
+    /// Waits until at least `num_replicas` replicas have acknowledged `target_offset`,
+    /// or until `deadline` passes. Returns the number of replicas that acknowledged it.
+    ///
+    /// This never blocks the runtime: it parks on the ACK notification channel and
+    /// periodically re-sends `GETACK` so that idle replicas report promptly.
+    pub async fn wait_for_replica_acks(
+        &self,
+        replica_states: &DashMap<SocketAddr, ReplicaStateInfo>,
+        target_offset: u64,
+        num_replicas: usize,
+        aof: bool,
+        deadline: Option<Instant>,
+    ) -> usize {
+        let mut ack_rx = self.ack_notifier.subscribe();
+        loop {
+            let acked = Self::count_acked_replicas(replica_states, target_offset, aof);
+            if acked >= num_replicas || deadline.is_some_and(|d| Instant::now() >= d) {
+                return acked;
+            }
+
+            self.request_replica_acks();
+            let mut wake_at = Instant::now() + GETACK_RESEND_INTERVAL;
+            if let Some(deadline) = deadline {
+                wake_at = wake_at.min(deadline);
+            }
+            tokio::select! {
+                res = ack_rx.changed() => {
+                    if res.is_err() {
+                        return Self::count_acked_replicas(replica_states, target_offset, aof);
+                    }
+                }
+                _ = tokio::time::sleep_until(wake_at.into()) => {}
+            }
+        }
+    }
 }
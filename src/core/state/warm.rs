@@ -0,0 +1,162 @@
+// src/core/state/warm.rs
+
+//! Tracks the progress of cache warmups started with `CACHE.WARM`.
+//!
+//! A warmup fetches a list of URLs into the cache in the background. Its job records
+//! how many URLs have been fetched, skipped because they were already cached, or have
+//! failed, and can be cancelled while it runs.
+
+use parking_lot::Mutex;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+/// The result of warming a single URL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarmOutcome {
+    /// The URL was fetched and stored.
+    Fetched,
+    /// The URL's key was already cached and fresh.
+    Skipped,
+    /// The URL did not map to a key, could not be fetched, or the origin answered
+    /// with an error.
+    Failed,
+}
+
+/// The state of a warmup job.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarmState {
+    Running,
+    Done,
+    Cancelled,
+}
+
+impl WarmState {
+    /// Returns the name of the state as reported by `CACHE.WARM STATUS`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WarmState::Running => "running",
+            WarmState::Done => "done",
+            WarmState::Cancelled => "cancelled",
+        }
+    }
+}
+
+/// The progress of a single `CACHE.WARM` run for a policy.
+#[derive(Debug)]
+pub struct WarmJob {
+    pub policy: String,
+    pub db_index: usize,
+    pub total: u64,
+    started: Instant,
+    fetched: AtomicU64,
+    skipped: AtomicU64,
+    failed: AtomicU64,
+    cancelled: CancellationToken,
+    finished_at: Mutex<Option<Instant>>,
+}
+
+/// A point-in-time view of a warmup job.
+#[derive(Debug, Clone)]
+pub struct WarmSnapshot {
+    pub state: WarmState,
+    pub total: u64,
+    pub fetched: u64,
+    pub skipped: u64,
+    pub failed: u64,
+    /// The time the job has been running, or ran for if it has finished.
+    pub elapsed: Duration,
+}
+
+impl WarmJob {
+    /// Creates a running job that warms `total` URLs.
+    pub fn new(policy: String, db_index: usize, total: u64) -> Self {
+        Self {
+            policy,
+            db_index,
+            total,
+            started: Instant::now(),
+            fetched: AtomicU64::new(0),
+            skipped: AtomicU64::new(0),
+            failed: AtomicU64::new(0),
+            cancelled: CancellationToken::new(),
+            finished_at: Mutex::new(None),
+        }
+    }
+
+    /// Records the outcome of warming one URL.
+    pub fn record(&self, outcome: WarmOutcome) {
+        let counter = match outcome {
+            WarmOutcome::Fetched => &self.fetched,
+            WarmOutcome::Skipped => &self.skipped,
+            WarmOutcome::Failed => &self.failed,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Asks the job to stop. URLs that are already being fetched are completed.
+    /// Returns `false` if the job had already finished.
+    pub fn cancel(&self) -> bool {
+        if !self.is_running() {
+            return false;
+        }
+        self.cancelled.cancel();
+        true
+    }
+
+    /// Returns `true` if the job has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.is_cancelled()
+    }
+
+    /// Completes when the job is cancelled.
+    pub async fn cancelled(&self) {
+        self.cancelled.cancelled().await
+    }
+
+    /// Marks the job as finished.
+    pub fn finish(&self) {
+        self.finished_at.lock().get_or_insert_with(Instant::now);
+    }
+
+    /// Returns a guard that marks the job as finished when it is dropped.
+    pub fn finish_on_drop(self: &Arc<Self>) -> FinishGuard {
+        FinishGuard(self.clone())
+    }
+
+    /// Returns `true` until the job has finished.
+    pub fn is_running(&self) -> bool {
+        self.finished_at.lock().is_none()
+    }
+
+    /// Returns the current progress of the job.
+    pub fn snapshot(&self) -> WarmSnapshot {
+        let finished_at = *self.finished_at.lock();
+        let state = match finished_at {
+            None => WarmState::Running,
+            Some(_) if self.is_cancelled() => WarmState::Cancelled,
+            Some(_) => WarmState::Done,
+        };
+        WarmSnapshot {
+            state,
+            total: self.total,
+            fetched: self.fetched.load(Ordering::Relaxed),
+            skipped: self.skipped.load(Ordering::Relaxed),
+            failed: self.failed.load(Ordering::Relaxed),
+            elapsed: finished_at
+                .unwrap_or_else(Instant::now)
+                .saturating_duration_since(self.started),
+        }
+    }
+}
+
+/// Marks a warmup job as finished when dropped, also when its task unwinds.
+#[derive(Debug)]
+pub struct FinishGuard(Arc<WarmJob>);
+
+impl Drop for FinishGuard {
+    fn drop(&mut self) {
+        self.0.finish();
+    }
+}
@@ -47,8 +47,20 @@ pub struct HttpMetadata {
     pub last_modified: Option<Bytes>,
     /// The URL used to fetch/revalidate this content, essential for proactive revalidation.
     pub revalidate_url: Option<String>,
-    /// The content encoding used if the body is compressed (e.g., "zstd").
+    /// The content encoding the body is served with. Set to `identity` on variants
+    /// whose responses vary on `Accept-Encoding` but are served unencoded, and to the
+    /// encoding of encoded renditions (e.g. `br`). Not persisted.
     pub content_encoding: Option<Bytes>,
+    /// The status code of the origin response. `None` for content stored with
+    /// `CACHE.SET`, which is served as `200`.
+    pub status: Option<u16>,
+    /// The origin response headers named in `cache.stored_response_headers`, in the
+    /// order the origin sent them. ETag and Last-Modified are kept in their own fields.
+    pub headers: Vec<(Bytes, Bytes)>,
+    /// The surrogate keys the origin tagged the body with, other than the tags given
+    /// explicitly. Revalidation replaces them with the keys of the new response. Not
+    /// persisted.
+    pub surrogate_keys: Vec<Bytes>,
 }
 
 impl HttpMetadata {
@@ -58,7 +70,79 @@ impl HttpMetadata {
         let lm_size = self.last_modified.as_ref().map_or(0, |b| b.len());
         let url_size = self.revalidate_url.as_ref().map_or(0, |s| s.len());
         let encoding_size = self.content_encoding.as_ref().map_or(0, |b| b.len());
-        etag_size + lm_size + url_size + encoding_size
+        let headers_size: usize = self.headers.iter().map(|(n, v)| n.len() + v.len()).sum();
+        let surrogate_keys_size: usize = self.surrogate_keys.iter().map(|k| k.len()).sum();
+        etag_size + lm_size + url_size + encoding_size + headers_size + surrogate_keys_size
+    }
+
+    /// Returns the status code to serve this content with.
+    pub fn status_code(&self) -> u16 {
+        self.status.unwrap_or(200)
+    }
+
+    /// Returns the headers to serve this content with: the stored origin headers
+    /// followed by the encoding headers and the ETag and Last-Modified validators.
+    pub fn response_headers(&self) -> Vec<(Bytes, Bytes)> {
+        let mut headers = self.headers.clone();
+        headers.extend(self.encoding_headers());
+        if let Some(etag) = &self.etag {
+            headers.push((Bytes::from_static(b"etag"), etag.clone()));
+        }
+        if let Some(last_modified) = &self.last_modified {
+            headers.push((Bytes::from_static(b"last-modified"), last_modified.clone()));
+        }
+        headers
+    }
+
+    /// Returns the `Content-Encoding` and `Vary` headers of content whose responses
+    /// vary on `Accept-Encoding`, or nothing otherwise.
+    pub fn encoding_headers(&self) -> Vec<(Bytes, Bytes)> {
+        let Some(encoding) = &self.content_encoding else {
+            return Vec::new();
+        };
+        let mut headers = Vec::with_capacity(2);
+        if encoding.as_ref() != IDENTITY_ENCODING {
+            headers.push((Bytes::from_static(b"content-encoding"), encoding.clone()));
+        }
+        headers.push((
+            Bytes::from_static(b"vary"),
+            Bytes::from_static(b"accept-encoding"),
+        ));
+        headers
+    }
+}
+
+/// The `content_encoding` of variants that are served unencoded to clients whose
+/// `Accept-Encoding` is not satisfied by any of their renditions.
+pub const IDENTITY_ENCODING: &[u8] = b"identity";
+
+/// An encoding that cached bodies can be served with, negotiated from the request's
+/// `Accept-Encoding` header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ContentEncoding {
+    #[serde(rename = "br")]
+    Brotli,
+    #[serde(rename = "zstd")]
+    Zstd,
+    #[serde(rename = "gzip")]
+    Gzip,
+}
+
+impl ContentEncoding {
+    /// All encodings, in the order preferred when a client weights several equally.
+    pub const ALL: [ContentEncoding; 3] = [
+        ContentEncoding::Brotli,
+        ContentEncoding::Zstd,
+        ContentEncoding::Gzip,
+    ];
+
+    /// Returns the name of the encoding as used in `Content-Encoding`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ContentEncoding::Brotli => "br",
+            ContentEncoding::Zstd => "zstd",
+            ContentEncoding::Gzip => "gzip",
+        }
     }
 }
 
@@ -74,6 +158,18 @@ pub struct CacheVariant {
     pub last_accessed: Instant,
 }
 
+impl CacheVariant {
+    /// Returns `true` if this variant is an encoded rendition of another variant,
+    /// created for a client's `Accept-Encoding`. Renditions are never persisted or
+    /// revalidated; they are recreated from the variant they encode.
+    pub fn is_rendition(&self) -> bool {
+        self.metadata
+            .content_encoding
+            .as_ref()
+            .is_some_and(|e| e.as_ref() != IDENTITY_ENCODING)
+    }
+}
+
 /// A map from a variant hash to the actual cached variant data.
 /// The hash is generated from the values of the headers specified in `Vary`.
 pub type VariantMap = HashMap<u64, CacheVariant>;
@@ -125,6 +221,75 @@ pub struct CachePolicy {
     /// If true, forces items to be stored on disk, even if smaller than the streaming threshold.
     #[serde(default)]
     pub force_disk: bool,
+    /// If true, tags declared by the origin in the configured surrogate key headers
+    /// (e.g. `Surrogate-Key`) are added to cached items, and `Surrogate-Control`
+    /// overrides the TTL and SWR.
+    #[serde(default)]
+    pub surrogate_keys: bool,
+    /// The maximum number of concurrent requests to this policy's origin host
+    /// (at most `MAX_ORIGIN_CONCURRENCY`).
+    #[serde(default)]
+    pub origin_concurrency: Option<u64>,
+    /// The error rate (in percent) over the breaker window that opens the origin's
+    /// circuit breaker. The breaker is disabled if unset.
+    #[serde(default)]
+    pub breaker_error_rate: Option<u64>,
+    /// The length in seconds of the breaker's sliding window (default 10, at most a day).
+    #[serde(default)]
+    pub breaker_window: Option<u64>,
+    /// The number of requests in the window before the breaker may open (default 5).
+    #[serde(default)]
+    pub breaker_min_requests: Option<u64>,
+    /// The seconds an open breaker waits before probing the origin (default 1).
+    /// The wait doubles after each failed probe, up to five minutes (at most 300).
+    #[serde(default)]
+    pub breaker_backoff: Option<u64>,
+    /// If true, bodies are served encoded with the best of `cache.response_encodings`
+    /// that the client's `Accept-Encoding` allows. Encoded renditions are cached as
+    /// variants of the key.
+    #[serde(default)]
+    pub negotiate_encoding: bool,
+}
+
+/// The largest `origin_concurrency` a cache policy may set.
+pub const MAX_ORIGIN_CONCURRENCY: u64 = 1 << 20;
+/// The longest breaker window, in seconds, a cache policy may set.
+pub const MAX_BREAKER_WINDOW_SECS: u64 = 86_400;
+/// The longest breaker backoff, in seconds. Doubled backoffs are capped at it too.
+pub const MAX_BREAKER_BACKOFF_SECS: u64 = 300;
+
+impl CachePolicy {
+    /// Checks that the origin protection settings of the policy are within bounds.
+    /// The error names the offending `CACHE.POLICY SET` option.
+    pub fn validate_origin_limits(&self) -> Result<(), String> {
+        if let Some(v) = self.origin_concurrency
+            && !(1..=MAX_ORIGIN_CONCURRENCY).contains(&v)
+        {
+            return Err(format!(
+                "ORIGIN_CONCURRENCY must be between 1 and {MAX_ORIGIN_CONCURRENCY}"
+            ));
+        }
+        if let Some(v) = self.breaker_error_rate
+            && !(1..=100).contains(&v)
+        {
+            return Err("BREAKER_ERROR_RATE must be between 1 and 100".into());
+        }
+        if let Some(v) = self.breaker_window
+            && !(1..=MAX_BREAKER_WINDOW_SECS).contains(&v)
+        {
+            return Err(format!(
+                "BREAKER_WINDOW must be between 1 and {MAX_BREAKER_WINDOW_SECS} seconds"
+            ));
+        }
+        if let Some(v) = self.breaker_backoff
+            && !(1..=MAX_BREAKER_BACKOFF_SECS).contains(&v)
+        {
+            return Err(format!(
+                "BREAKER_BACKOFF must be between 1 and {MAX_BREAKER_BACKOFF_SECS} seconds"
+            ));
+        }
+        Ok(())
+    }
 }
 
 /// The persistent state of an on-disk cache file, logged in the manifest.
@@ -147,6 +312,16 @@ pub struct ManifestEntry {
     pub state: ManifestState,
     /// The path to the on-disk cache file.
     pub path: PathBuf,
+    /// The index of the database the key is stored in. Entries logged before databases
+    /// were recorded belong to database 0.
+    #[serde(default)]
+    pub db: usize,
     /// The key associated with this file, used for eviction.
     pub key: Bytes,
+    /// The status code of the origin response, logged with `Committed` entries.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    /// The stored origin response headers, logged with `Committed` entries.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub headers: Vec<(String, String)>,
 }
@@ -298,7 +298,8 @@ impl StoredValue {
                             .map(|d| d.as_secs())
                     });
 
-                for variant in variants.values() {
+                // Renditions are recreated from the variants they encode.
+                for variant in variants.values().filter(|v| !v.is_rendition()) {
                     let body_bytes = match &variant.body {
                         CacheBody::InMemory(bytes) => bytes.clone(),
                         // On-disk and negative caches are not persisted via AOF/SPLDB commands.
@@ -328,6 +329,9 @@ impl StoredValue {
                                 CacheBody::CompressedInMemory { .. }
                             ),
                             force_disk: false, // This state is transient and not stored this way.
+                            status: variant.metadata.status,
+                            response_headers: variant.metadata.headers.clone(),
+                            surrogate_keys: vec![],
                         }),
                     }));
                 }
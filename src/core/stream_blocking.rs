@@ -111,8 +111,9 @@ impl StreamBlockerManager {
             ctx.session_id, keys
         );
 
-        // --- Phase 2: Release locks and enter blocking wait ---
+        // --- Phase 2: Release locks and the write admission, and enter blocking wait ---
         ctx.release_locks();
+        let was_admitted = ctx.release_write_admission();
         debug!(
             "Session {}: Locks released. Awaiting notification.",
             ctx.session_id
@@ -129,6 +130,11 @@ impl StreamBlockerManager {
         // If woken up, re-acquire locks and verify that the stream has actually changed.
         // This prevents spurious wakeups from causing a client to re-read old data.
         if matches!(block_result, StreamBlockerResult::Woken) {
+            // `XREADGROUP` writes to the consumer group once woken, so it is admitted as a
+            // write again before the locks are taken.
+            if was_admitted {
+                ctx.reacquire_write_admission(keys).await;
+            }
             if ctx.reacquire_locks_for_command().await.is_err() {
                 return StreamBlockerResult::TimedOut; // Assume failure if locks can't be reacquired
             }
@@ -178,15 +184,18 @@ impl StreamBlockerManager {
 
         // In cluster mode, use a "lazy polling" loop to handle slot migrations.
         const POLLING_TIMEOUT: Duration = Duration::from_millis(500);
-        let deadline = Instant::now() + wait_timeout;
+        // A timeout of 0 blocks indefinitely, so it may have no deadline.
+        let deadline = Instant::now().checked_add(wait_timeout);
         let my_slot = get_slot(&keys[0]); // All keys must be in the same slot.
 
         loop {
             let now = Instant::now();
-            if now >= deadline {
+            let time_left = deadline.map_or(POLLING_TIMEOUT, |deadline| {
+                deadline.saturating_duration_since(now)
+            });
+            if time_left.is_zero() {
                 return StreamBlockerResult::TimedOut;
             }
-            let time_left = deadline - now;
             let current_timeout = POLLING_TIMEOUT.min(time_left);
 
             match timeout(current_timeout, &mut *rx).await {
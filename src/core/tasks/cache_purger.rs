@@ -6,17 +6,19 @@
 //! patterns submitted via the `CACHE.PURGE` command. It operates incrementally
 //! to avoid blocking the server for extended periods.
 
+use std::collections::BTreeMap;
 use std::sync::Arc;
 use std::time::Duration;
 
-use bytes::Bytes;
 use tokio::sync::broadcast;
 use tracing::{info, warn};
 use wildmatch::WildMatch;
 
 use crate::core::commands::Command;
 use crate::core::commands::command_trait::CommandExt;
+use crate::core::database::Db;
 use crate::core::state::ServerState;
+use crate::core::state::cache::DbKey;
 
 /// The interval at which the lazy cache purger runs its cycle.
 const CACHE_PURGE_INTERVAL: Duration = Duration::from_secs(1);
@@ -52,12 +54,12 @@ impl CachePurgerTask {
 
     /// Performs a single purge cycle.
     ///
-    /// It takes a batch of patterns from the purge queue, scans the entire keyspace
-    /// incrementally, and collects any keys that match one of the patterns. Finally,
-    /// it deletes the matched keys using `UNLINK`.
+    /// It takes a batch of patterns from the purge queue and, for each database they
+    /// were submitted in, scans its keyspace incrementally and collects any keys that
+    /// match one of its patterns. Finally, it deletes the matched keys using `UNLINK`.
     async fn perform_purge_cycle(&self) {
         // Take a small batch of purge patterns to process in this cycle.
-        let patterns_to_purge: Vec<Bytes> = self
+        let patterns_to_purge: Vec<DbKey> = self
             .state
             .cache
             .purge_patterns
@@ -71,12 +73,28 @@ impl CachePurgerTask {
         }
 
         // Pre-compile glob patterns for efficiency within the scan loop.
-        let matchers: Vec<_> = patterns_to_purge
-            .iter()
-            .map(|p| WildMatch::new(&String::from_utf8_lossy(p)))
-            .collect();
+        let mut matchers_by_db: BTreeMap<usize, Vec<WildMatch>> = BTreeMap::new();
+        for (db_index, pattern) in &patterns_to_purge {
+            matchers_by_db
+                .entry(*db_index)
+                .or_default()
+                .push(WildMatch::new(&String::from_utf8_lossy(pattern)));
+        }
 
-        let db = self.state.get_db(0).unwrap(); // Assumes cache is on DB 0.
+        for (db_index, matchers) in matchers_by_db {
+            if let Some(db) = self.state.get_db(db_index) {
+                self.purge_matching_keys(&db, &matchers).await;
+            }
+        }
+
+        // Remove the processed patterns from the queue.
+        for pattern in patterns_to_purge {
+            self.state.cache.purge_patterns.remove(&pattern);
+        }
+    }
+
+    /// Scans the keyspace of `db` and unlinks every key matching one of `matchers`.
+    async fn purge_matching_keys(&self, db: &Db, matchers: &[WildMatch]) {
         let mut keys_to_delete = Vec::new();
         let mut cursor = 0;
 
@@ -87,7 +105,7 @@ impl CachePurgerTask {
             for key in keys {
                 let key_str = String::from_utf8_lossy(&key);
                 // Check each key against the batch of patterns.
-                for matcher in &matchers {
+                for matcher in matchers {
                     if matcher.matches(&key_str) {
                         keys_to_delete.push(key.clone());
                         break; // Move to the next key once a match is found.
@@ -109,19 +127,15 @@ impl CachePurgerTask {
             let mut unlink_ctx = crate::core::database::ExecutionContext {
                 state: self.state.clone(),
                 locks: db.determine_locks_for_command(&unlink_cmd).await,
-                db: &db,
+                db,
                 command: Some(unlink_cmd.clone()),
                 session_id: 0, // Internal operation, no session ID.
                 authenticated_user: None,
+                write_guard: None,
             };
             if let Err(e) = unlink_cmd.execute(&mut unlink_ctx).await {
                 warn!("Cache purger failed to unlink keys: {}", e);
             }
         }
-
-        // Remove the processed patterns from the queue.
-        for pattern in patterns_to_purge {
-            self.state.cache.purge_patterns.remove(&pattern);
-        }
     }
 }
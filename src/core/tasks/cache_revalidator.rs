@@ -5,13 +5,12 @@
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
-use bytes::Bytes;
 use tokio::sync::{broadcast, mpsc};
 use tracing::{info, warn};
 
 use crate::core::commands::cache::cache_get::revalidate_and_update_cache;
 use crate::core::state::ServerState;
-use crate::core::state::cache::RevalidationJob;
+use crate::core::state::cache::{DbKey, RevalidationJob};
 use crate::core::storage::data_types::DataValue;
 
 /// A task responsible for performing background cache revalidations,
@@ -33,13 +32,23 @@ impl CacheRevalidationWorker {
                     // Spawn each revalidation job as a separate task to allow for concurrent fetches.
                     tokio::spawn(async move {
                         // The spawned task must acquire its own lock on the relevant shard.
-                        let db = state_clone.get_db(0).unwrap();
+                        let Some(db) = state_clone.get_db(job.db_index) else {
+                            warn!("Dropping cache revalidation job for unknown database {}", job.db_index);
+                            return;
+                        };
                         let shard_index = db.get_shard_index(&job.key);
                         let mut guard = db.get_shard(shard_index).entries.lock().await;
 
-                        if let Err(e) =
-                            revalidate_and_update_cache(state_clone, job.key, job.url, job.variant_hash, None, &mut guard)
-                                .await
+                        if let Err(e) = revalidate_and_update_cache(
+                            state_clone,
+                            job.db_index,
+                            job.key,
+                            job.url,
+                            job.variant_hash,
+                            None,
+                            &mut guard,
+                        )
+                        .await
                         {
                             warn!("Proactive cache revalidation failed: {}", e);
                         }
@@ -93,19 +102,13 @@ impl CacheRevalidator {
 
     /// Performs a single cycle of sampling and revalidating cache keys.
     async fn perform_revalidation_cycle(&self) {
-        let db = if let Some(db) = self.state.dbs.first() {
-            db
-        } else {
-            return;
-        };
-
         let prewarm_keys_guard = self.state.cache.prewarm_keys.read().await;
         if prewarm_keys_guard.is_empty() {
             return;
         }
 
         // Take a small, random sample of keys to check in this cycle.
-        let sample: Vec<Bytes> = prewarm_keys_guard
+        let sample: Vec<DbKey> = prewarm_keys_guard
             .iter()
             .take(CACHE_REVALIDATOR_SAMPLE_SIZE)
             .cloned()
@@ -113,7 +116,10 @@ impl CacheRevalidator {
 
         drop(prewarm_keys_guard);
 
-        for key in sample {
+        for (db_index, key) in sample {
+            let Some(db) = self.state.get_db(db_index) else {
+                continue;
+            };
             let shard_index = db.get_shard_index(&key);
             let guard = db.get_shard(shard_index).entries.lock().await;
 
@@ -134,7 +140,11 @@ impl CacheRevalidator {
 
                             self.state
                                 .cache
-                                .trigger_smart_background_revalidation(key_clone, variants_clone)
+                                .trigger_smart_background_revalidation(
+                                    db_index,
+                                    key_clone,
+                                    variants_clone,
+                                )
                                 .await;
                         }
                     }
@@ -142,7 +152,12 @@ impl CacheRevalidator {
             } else {
                 // The key exists in the prewarm set but not in the database,
                 // so it was likely deleted. Remove it from the prewarm set.
-                self.state.cache.prewarm_keys.write().await.remove(&key);
+                self.state
+                    .cache
+                    .prewarm_keys
+                    .write()
+                    .await
+                    .remove(&(db_index, key));
             }
         }
     }
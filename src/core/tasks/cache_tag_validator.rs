@@ -1,5 +1,6 @@
 // src/core/tasks/cache_tag_validator.rs
 
+use crate::core::database::Db;
 use crate::core::state::ServerState;
 use crate::core::storage::data_types::DataValue;
 use std::sync::Arc;
@@ -41,7 +42,14 @@ impl CacheTagValidatorTask {
     }
 
     async fn perform_validation_cycle(&self) {
-        let db = self.state.get_db(0).unwrap();
+        for (db_index, db) in self.state.dbs.iter().enumerate() {
+            self.validate_sample(db_index, db).await;
+        }
+    }
+
+    /// Deletes sampled cache entries of database `db_index` that were stored before a
+    /// purge of one of their tags.
+    async fn validate_sample(&self, db_index: usize, db: &Db) {
         let sample = db.get_random_keys(VALIDATOR_SAMPLE_SIZE).await;
         if sample.is_empty() {
             return;
@@ -57,7 +65,11 @@ impl CacheTagValidatorTask {
             {
                 let tags_for_key = guard.get_tags_for_key(&key);
                 for tag in tags_for_key {
-                    if let Some(latest_purge_epoch) = self.state.cache.tag_purge_epochs.get(&tag)
+                    if let Some(latest_purge_epoch) = self
+                        .state
+                        .cache
+                        .tag_purge_epochs
+                        .get(&(db_index, tag.clone()))
                         && *tags_epoch < *latest_purge_epoch.value()
                     {
                         debug!(
@@ -9,7 +9,8 @@ use tracing::{debug, info, warn};
 use crate::config::EvictionPolicy;
 use crate::core::state::ServerState;
 
-/// A task responsible for proactive memory eviction.
+/// A task responsible for proactive memory eviction. With tiered caching, it is also
+/// the only place where cold cache keys are demoted to disk.
 pub struct EvictionManager {
     state: Arc<ServerState>,
 }
@@ -117,7 +118,7 @@ impl EvictionManager {
 
             let mut evicted_in_pass = false;
             for db in &self.state.dbs {
-                if db.get_key_count() > 0 && db.evict_one_key(&self.state).await {
+                if db.get_key_count() > 0 && db.free_memory(&self.state).await {
                     evicted_in_pass = true;
                 }
             }
@@ -10,8 +10,9 @@ use std::sync::Arc;
 use tokio::sync::{broadcast, mpsc};
 use tracing::{debug, info, warn};
 
-/// The type of item sent to the lazy-free channel: a tuple of the key and its value.
-pub type LazyFreeItem = (Bytes, StoredValue);
+/// The type of item sent to the lazy-free channel: a tuple of the index of the
+/// database the key was removed from, the key and its value.
+pub type LazyFreeItem = (usize, Bytes, StoredValue);
 
 /// A task responsible for asynchronous value deallocation, triggered by `UNLINK`
 /// or `DEL` on large items. It also handles deleting on-disk cache files.
@@ -50,7 +51,7 @@ impl LazyFreeManager {
     /// Processes a vector of items marked for lazy freeing.
     async fn process_items(&self, items_to_free: Vec<LazyFreeItem>) {
         let items_len = items_to_free.len();
-        for (key, mut value) in items_to_free {
+        for (db_index, key, mut value) in items_to_free {
             // Check if the value is an HttpCache item with on-disk variants.
             if let DataValue::HttpCache { variants, .. } = &mut value.data {
                 for variant in variants.values_mut() {
@@ -59,7 +60,12 @@ impl LazyFreeManager {
                         if let Err(e) = self
                             .state
                             .cache
-                            .log_manifest(key.clone(), ManifestState::PendingDelete, path.clone())
+                            .log_manifest(
+                                db_index,
+                                key.clone(),
+                                ManifestState::PendingDelete,
+                                path.clone(),
+                            )
                             .await
                         {
                             warn!(
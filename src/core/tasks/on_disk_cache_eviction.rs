@@ -5,6 +5,7 @@
 use crate::core::state::ServerState;
 use crate::core::storage::cache_types::{ManifestEntry, ManifestState};
 use anyhow::Result;
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
@@ -52,32 +53,47 @@ impl OnDiskCacheEvictionTask {
     }
 
     /// Performs a single eviction cycle if the cache size exceeds the limit.
-    async fn perform_eviction_cycle(&self, max_disk_size: u64) -> Result<()> {
+    /// Files no longer referenced by a key are evicted first, then the files of the
+    /// least recently accessed variants. The variants of evicted files are removed.
+    pub async fn perform_eviction_cycle(&self, max_disk_size: u64) -> Result<()> {
         let manifest_path = get_manifest_path(&self.state).await?;
         if !manifest_path.exists() {
             return Ok(());
         }
 
-        // Lock the manifest writer to prevent concurrent writes during eviction checks.
-        let _writer_guard = self.state.cache.manifest_writer.lock().await;
-
-        let manifest_file = TokioFile::open(&manifest_path).await?;
-        let mut reader = BufReader::new(manifest_file);
-        let mut line = String::new();
+        // The latest entry of each file determines its state.
+        let mut latest: HashMap<PathBuf, ManifestEntry> = HashMap::new();
+        {
+            // Lock the manifest writer so that no entry is read half-written. The lock is
+            // released before files are logged for deletion.
+            let _writer_guard = self.state.cache.manifest_writer.lock().await;
+            let manifest_file = TokioFile::open(&manifest_path).await?;
+            let mut reader = BufReader::new(manifest_file);
+            let mut line = String::new();
+            while reader.read_line(&mut line).await? > 0 {
+                if let Ok(entry) = serde_json::from_str::<ManifestEntry>(&line) {
+                    latest.insert(entry.path.clone(), entry);
+                }
+                line.clear();
+            }
+        }
 
-        let mut committed_entries: Vec<ManifestEntry> = Vec::new();
+        // Collect all committed files and their total size.
+        let mut candidates = Vec::new();
         let mut total_size = 0;
-
-        // Read the manifest to get a list of all committed files and their total size.
-        while reader.read_line(&mut line).await? > 0 {
-            if let Ok(entry) = serde_json::from_str::<ManifestEntry>(&line)
-                && entry.state == ManifestState::Committed
-                && let Ok(metadata) = tokio::fs::metadata(&entry.path).await
-            {
-                total_size += metadata.len();
-                committed_entries.push(entry);
+        for entry in latest.into_values() {
+            if entry.state != ManifestState::Committed {
+                continue;
             }
-            line.clear();
+            let Ok(metadata) = tokio::fs::metadata(&entry.path).await else {
+                continue;
+            };
+            let last_access = match self.state.get_db(entry.db) {
+                Some(db) => db.on_disk_body_last_access(&entry.key, &entry.path).await,
+                None => None,
+            };
+            total_size += metadata.len();
+            candidates.push((last_access, metadata.len(), entry));
         }
 
         if total_size <= max_disk_size {
@@ -89,28 +105,33 @@ impl OnDiskCacheEvictionTask {
             total_size, max_disk_size
         );
 
-        // Sort by timestamp to find the least recently used files.
-        committed_entries.sort_by_key(|e| e.timestamp);
+        // Unreferenced files (`None`) sort first, then the least recently accessed ones.
+        candidates.sort_by_key(|(last_access, _, entry)| (*last_access, entry.timestamp));
 
         let mut size_to_free = total_size - max_disk_size;
         let mut evicted_count = 0;
 
         // Evict files until the size is under the quota.
-        for entry in committed_entries {
+        for (_, file_size, entry) in candidates {
             if size_to_free == 0 {
                 break;
             }
 
-            let file_size = match tokio::fs::metadata(&entry.path).await {
-                Ok(m) => m.len(),
-                Err(_) => continue,
-            };
-
             // Log the file for deletion. The GC task will perform the actual file removal.
             self.state
                 .cache
-                .log_manifest(entry.key, ManifestState::PendingDelete, entry.path)
+                .log_manifest(
+                    entry.db,
+                    entry.key.clone(),
+                    ManifestState::PendingDelete,
+                    entry.path.clone(),
+                )
                 .await?;
+            if let Some(db) = self.state.get_db(entry.db)
+                && db.remove_on_disk_body(&entry.key, &entry.path).await
+            {
+                self.state.cache.increment_evictions(entry.db);
+            }
 
             size_to_free = size_to_free.saturating_sub(file_size);
             evicted_count += 1;
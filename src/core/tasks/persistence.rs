@@ -5,8 +5,9 @@ use std::sync::atomic::Ordering;
 use std::time::Duration;
 
 use tokio::sync::broadcast;
-use tracing::info;
+use tracing::{info, warn};
 
+use crate::core::persistence::aof_manifest::lock_manifest;
 use crate::core::persistence::rewrite_aof;
 use crate::core::state::ServerState;
 
@@ -40,13 +41,11 @@ impl AofRewriteManager {
             return;
         }
 
-        if let Ok(metadata) =
-            tokio::fs::metadata(&self.state.config.lock().await.persistence.aof_path).await
-        {
+        if let Some(size) = self.current_aof_size().await {
             self.state
                 .persistence
                 .aof_last_rewrite_size
-                .store(metadata.len(), Ordering::Relaxed);
+                .store(size, Ordering::Relaxed);
         }
 
         info!(
@@ -86,10 +85,8 @@ impl AofRewriteManager {
             return;
         }
 
-        let aof_path = &self.state.config.lock().await.persistence.aof_path;
-        let current_size = match tokio::fs::metadata(aof_path).await {
-            Ok(metadata) => metadata.len(),
-            Err(_) => return,
+        let Some(current_size) = self.current_aof_size().await else {
+            return;
         };
 
         let last_size = self
@@ -124,6 +121,17 @@ impl AofRewriteManager {
         }
     }
 
+    /// Returns the combined size of the base and incremental AOF files.
+    async fn current_aof_size(&self) -> Option<u64> {
+        match lock_manifest(&self.state).await {
+            Ok(manifest) => Some(manifest.total_size().await),
+            Err(e) => {
+                warn!("Could not read the AOF manifest to check its size: {}", e);
+                None
+            }
+        }
+    }
+
     /// Spawns the `rewrite_aof` function in a new task.
     async fn trigger_rewrite(&self) {
         let state_clone = self.state.clone();
@@ -71,6 +71,65 @@ impl WardenClient {
         }
     }
 
+    /// Sends `AUTH` with `password`, which a peer Warden requires before it votes.
+    pub async fn authenticate(&mut self, password: &str) -> Result<()> {
+        let frame = RespFrame::Array(vec![
+            RespFrame::BulkString("AUTH".into()),
+            RespFrame::BulkString(password.to_string().into()),
+        ]);
+        match self.send_and_receive(frame).await? {
+            RespFrame::SimpleString(_) => Ok(()),
+            reply => Err(anyhow!("Unexpected AUTH reply: {reply:?}")),
+        }
+    }
+
+    /// Sends `FAILOVER EPOCH`, after which the instance refuses reconfiguration from
+    /// Wardens of older epochs. Fails if a newer failover already reconfigured it.
+    pub async fn fence_epoch(&mut self, epoch: u64) -> Result<()> {
+        let frame = RespFrame::Array(vec![
+            RespFrame::BulkString("FAILOVER".into()),
+            RespFrame::BulkString("EPOCH".into()),
+            RespFrame::BulkString(epoch.to_string().into()),
+        ]);
+        match self.send_and_receive(frame).await? {
+            RespFrame::SimpleString(_) => Ok(()),
+            RespFrame::Error(e) => Err(anyhow!("Epoch {epoch} was refused: {e}")),
+            reply => Err(anyhow!("Unexpected FAILOVER EPOCH reply: {reply:?}")),
+        }
+    }
+
+    /// Asks a peer Warden to vote for `candidate` in `epoch` with
+    /// `SENTINEL IS-MASTER-DOWN-BY-ADDR`. Returns the peer's `(leader, leader_epoch)`.
+    pub async fn request_vote(
+        &mut self,
+        master_addr: SocketAddr,
+        epoch: u64,
+        candidate: &str,
+    ) -> Result<(String, u64)> {
+        let frame = RespFrame::Array(vec![
+            RespFrame::BulkString("SENTINEL".into()),
+            RespFrame::BulkString("IS-MASTER-DOWN-BY-ADDR".into()),
+            RespFrame::BulkString(master_addr.ip().to_string().into()),
+            RespFrame::BulkString(master_addr.port().to_string().into()),
+            RespFrame::BulkString(epoch.to_string().into()),
+            RespFrame::BulkString(candidate.to_string().into()),
+        ]);
+        match self.send_and_receive(frame).await? {
+            RespFrame::Array(parts) => match parts.as_slice() {
+                [
+                    _,
+                    RespFrame::BulkString(leader),
+                    RespFrame::Integer(leader_epoch),
+                ] => Ok((
+                    String::from_utf8_lossy(leader).to_string(),
+                    *leader_epoch as u64,
+                )),
+                _ => Err(anyhow!("Unexpected vote reply: {parts:?}")),
+            },
+            reply => Err(anyhow!("Unexpected vote reply: {reply:?}")),
+        }
+    }
+
     /// Sends an `INFO replication` command and expects a bulk string response.
     pub async fn info_replication(&mut self) -> Result<String> {
         let frame = RespFrame::Array(vec![
@@ -0,0 +1,615 @@
+// src/core/warden/commands.rs
+
+//! Implements the commands answered by the Warden listener: `PING`, `INFO` and the
+//! Sentinel-compatible `SENTINEL` subcommands. `AUTH` and the Pub/Sub commands change
+//! the state of the connection and are handled by the listener itself.
+
+use super::config::MonitoredMaster;
+use super::failover;
+use super::state::{FailoverState, GlobalWardenState, MasterState, MasterStatus};
+use crate::core::commands::scan::glob_match;
+use crate::core::protocol::{RespFrame, RespValue};
+use parking_lot::Mutex;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// The result of a command. Errors hold the complete error line, e.g. `ERR ...`.
+type CommandResult = Result<RespValue, String>;
+
+/// An option of a monitored master that can be changed with `SENTINEL SET`.
+enum MasterOption {
+    DownAfter(Duration),
+    FailoverTimeout(Duration),
+    Quorum(usize),
+}
+
+/// Whether `args` is a `SENTINEL` subcommand that changes the Warden's state, including
+/// its epoch and vote. When the Warden has a password, the listener only accepts these
+/// after `AUTH`, which peer Wardens send with the same password.
+pub fn requires_auth(args: &[RespFrame]) -> bool {
+    let is = |arg: Option<&RespFrame>, names: &[&str]| {
+        matches!(arg, Some(RespFrame::BulkString(bytes))
+            if names.iter().any(|name| bytes.eq_ignore_ascii_case(name.as_bytes())))
+    };
+    is(args.first(), &["sentinel"])
+        && is(
+            args.get(1),
+            &[
+                "failover",
+                "monitor",
+                "remove",
+                "set",
+                "reset",
+                "is-master-down-by-addr",
+            ],
+        )
+}
+
+/// Parses and processes a single command received by the Warden.
+pub async fn process_warden_command(
+    args: &[RespFrame],
+    state: &Arc<GlobalWardenState>,
+) -> RespFrame {
+    let mut str_args = Vec::with_capacity(args.len());
+    for arg in args {
+        let RespFrame::BulkString(bytes) = arg else {
+            return RespFrame::Error("ERR invalid command format".to_string());
+        };
+        str_args.push(String::from_utf8_lossy(bytes).to_string());
+    }
+    let Some(cmd) = str_args.first() else {
+        return RespFrame::Error("ERR invalid command format".to_string());
+    };
+
+    let result = match cmd.to_ascii_lowercase().as_str() {
+        // PING is a simple health check.
+        "ping" => Ok(RespValue::SimpleString("PONG".into())),
+        "info" if str_args.len() <= 2 => Ok(info(state, str_args.get(1))),
+        "info" => Err(wrong_arity("info")),
+        // SENTINEL commands are the main API. We keep the "SENTINEL" name for client compatibility.
+        "sentinel" => sentinel(&str_args[1..], state).await,
+        _ => Err(format!("ERR Unknown command '{cmd}'")),
+    };
+    match result {
+        Ok(value) => value.into(),
+        Err(e) => RespFrame::Error(e),
+    }
+}
+
+/// Dispatches a `SENTINEL` subcommand. `args` starts with the subcommand name.
+async fn sentinel(args: &[String], state: &Arc<GlobalWardenState>) -> CommandResult {
+    let Some(subcommand) = args.first() else {
+        return Err(wrong_arity("sentinel"));
+    };
+    let subcommand = subcommand.to_ascii_lowercase();
+    let expect_args = |count: usize| {
+        if args.len() == count {
+            Ok(())
+        } else {
+            Err(wrong_arity(&format!("sentinel {subcommand}")))
+        }
+    };
+
+    match subcommand.as_str() {
+        // The most important command for clients: get the current master's address.
+        "get-master-addr-by-name" => {
+            expect_args(2)?;
+            Ok(state
+                .masters
+                .get(&args[1])
+                .map_or(RespValue::Null, |entry| {
+                    let master = entry.value().lock();
+                    // Return the address as a [ip, port] array, which is the standard SpinelDB format.
+                    RespValue::Array(vec![
+                        RespValue::BulkString(master.addr.ip().to_string().into()),
+                        RespValue::Integer(master.addr.port() as i64),
+                    ])
+                }))
+        }
+        "masters" => {
+            expect_args(1)?;
+            let mut masters: Vec<_> = state
+                .masters
+                .iter()
+                .map(|entry| entry.value().clone())
+                .collect();
+            masters.sort_by_key(|master| master.lock().config.name.clone());
+            Ok(RespValue::Array(
+                masters
+                    .iter()
+                    .map(|master| master_fields(&master.lock()))
+                    .collect(),
+            ))
+        }
+        "master" => {
+            expect_args(2)?;
+            Ok(master_fields(&find_master(state, &args[1])?.lock()))
+        }
+        "replicas" | "slaves" => {
+            expect_args(2)?;
+            Ok(replica_fields(&find_master(state, &args[1])?.lock()))
+        }
+        "sentinels" => {
+            expect_args(2)?;
+            Ok(peer_fields(&find_master(state, &args[1])?.lock()))
+        }
+        "ckquorum" => {
+            expect_args(2)?;
+            check_quorum(&find_master(state, &args[1])?.lock())
+        }
+        "failover" => {
+            expect_args(2)?;
+            force_failover(state, find_master(state, &args[1])?).await
+        }
+        "reset" => {
+            expect_args(2)?;
+            Ok(RespValue::Integer(reset_masters(state, &args[1])))
+        }
+        "monitor" => {
+            expect_args(5)?;
+            monitor(state, &args[1], &args[2], &args[3], &args[4]).await
+        }
+        "remove" => {
+            expect_args(2)?;
+            remove(state, &args[1]).await
+        }
+        "set" => {
+            if args.len() < 4 || !args.len().is_multiple_of(2) {
+                return Err(wrong_arity("sentinel set"));
+            }
+            set_options(state, &args[1], &args[2..]).await
+        }
+        "is-master-down-by-addr" => {
+            expect_args(5)?;
+            vote(state, &args[1], &args[2], &args[3], &args[4]).await
+        }
+        "myid" => {
+            expect_args(1)?;
+            Ok(RespValue::BulkString(state.my_run_id.clone().into()))
+        }
+        _ => Err(format!("ERR Unknown sentinel subcommand '{subcommand}'")),
+    }
+}
+
+fn wrong_arity(command: &str) -> String {
+    format!("ERR wrong number of arguments for '{command}' command")
+}
+
+/// Looks up a monitored master. The state is cloned out of the map so that no map
+/// guard is held while it is locked.
+fn find_master(state: &GlobalWardenState, name: &str) -> Result<Arc<Mutex<MasterState>>, String> {
+    state
+        .masters
+        .get(name)
+        .map(|entry| entry.value().clone())
+        .ok_or_else(|| "ERR No such master with that name".to_string())
+}
+
+/// Builds a flat array of field names and values, as Sentinel returns them.
+fn fields(pairs: Vec<(&str, String)>) -> RespValue {
+    RespValue::Array(
+        pairs
+            .into_iter()
+            .flat_map(|(name, value)| {
+                [
+                    RespValue::BulkString(name.to_string().into()),
+                    RespValue::BulkString(value.into()),
+                ]
+            })
+            .collect(),
+    )
+}
+
+fn master_fields(master: &MasterState) -> RespValue {
+    let mut flags = match master.status {
+        MasterStatus::Ok => "master",
+        MasterStatus::Sdown => "master,s_down",
+        MasterStatus::Odown => "master,s_down,o_down",
+    }
+    .to_string();
+    if master.failover_state != FailoverState::None {
+        flags.push_str(",failover_in_progress");
+    }
+    let failover_state = match master.failover_state {
+        FailoverState::None => "none",
+        FailoverState::Wait => "wait_start",
+        FailoverState::Vote => "vote",
+        FailoverState::Start => "start",
+        FailoverState::SelectReplica => "select_slave",
+        FailoverState::PromoteReplica => "promote_slave",
+    };
+
+    let mut pairs = vec![
+        ("name", master.config.name.clone()),
+        ("ip", master.addr.ip().to_string()),
+        ("port", master.addr.port().to_string()),
+        ("runid", master.run_id.clone()),
+        ("flags", flags),
+    ];
+    if let Some(down_since) = master.primary_state.down_since {
+        pairs.push(("s-down-time", down_since.elapsed().as_millis().to_string()));
+    }
+    pairs.extend([
+        ("num-slaves", master.replicas.len().to_string()),
+        ("num-other-sentinels", master.peers.len().to_string()),
+        ("quorum", master.config.quorum.to_string()),
+        (
+            "down-after-milliseconds",
+            master.config.down_after.as_millis().to_string(),
+        ),
+        (
+            "failover-timeout",
+            master.config.failover_timeout.as_millis().to_string(),
+        ),
+        ("config-epoch", master.master_epoch.to_string()),
+        ("failover-state", failover_state.to_string()),
+    ]);
+    if let Some(port) = master.config.proxy_port {
+        pairs.push(("proxy-port", port.to_string()));
+    }
+    fields(pairs)
+}
+
+fn replica_fields(master: &MasterState) -> RespValue {
+    let mut replicas: Vec<_> = master
+        .replicas
+        .iter()
+        .map(|entry| entry.value().clone())
+        .collect();
+    replicas.sort_by_key(|replica| replica.addr);
+    RespValue::Array(
+        replicas
+            .into_iter()
+            .map(|replica| {
+                let flags = if replica.down_since.is_some() {
+                    "slave,s_down"
+                } else {
+                    "slave"
+                };
+                fields(vec![
+                    ("name", replica.addr.to_string()),
+                    ("ip", replica.addr.ip().to_string()),
+                    ("port", replica.addr.port().to_string()),
+                    ("runid", replica.run_id.clone()),
+                    ("flags", flags.to_string()),
+                    ("master-host", master.addr.ip().to_string()),
+                    ("master-port", master.addr.port().to_string()),
+                    ("slave-repl-offset", replica.replication_offset.to_string()),
+                ])
+            })
+            .collect(),
+    )
+}
+
+fn peer_fields(master: &MasterState) -> RespValue {
+    let mut peers: Vec<_> = master.peers.values().collect();
+    peers.sort_by(|a, b| a.run_id.cmp(&b.run_id));
+    RespValue::Array(
+        peers
+            .into_iter()
+            .map(|peer| {
+                fields(vec![
+                    ("name", peer.run_id.clone()),
+                    ("ip", peer.addr.ip().to_string()),
+                    ("port", peer.addr.port().to_string()),
+                    ("runid", peer.run_id.clone()),
+                    ("flags", "sentinel".to_string()),
+                    (
+                        "last-hello-message",
+                        peer.last_hello_received.elapsed().as_millis().to_string(),
+                    ),
+                ])
+            })
+            .collect(),
+    )
+}
+
+/// Checks whether enough Wardens are reachable to agree on a failover of the master,
+/// and to elect the Warden that performs it.
+fn check_quorum(master: &MasterState) -> CommandResult {
+    let hello_timeout = master.config.hello_interval * 5;
+    let usable = master
+        .peers
+        .values()
+        .filter(|peer| peer.last_hello_received.elapsed() < hello_timeout)
+        .count()
+        + 1;
+    if usable < master.config.quorum {
+        return Err(format!(
+            "NOQUORUM {usable} usable Wardens. Not enough available Wardens to reach the specified quorum for this master"
+        ));
+    }
+    if usable < master.required_votes() {
+        return Err(format!(
+            "NOAUTH {usable} usable Wardens. Not enough available Wardens to reach the majority and authorize a failover"
+        ));
+    }
+    Ok(RespValue::SimpleString(format!(
+        "OK {usable} usable Wardens. Quorum and failover authorization can be reached"
+    )))
+}
+
+/// Starts a failover as if the master were down, without asking other Wardens to agree.
+async fn force_failover(
+    state: &Arc<GlobalWardenState>,
+    master_arc: Arc<Mutex<MasterState>>,
+) -> CommandResult {
+    {
+        let mut master = master_arc.lock();
+        if master.failover_state != FailoverState::None {
+            return Err("INPROG Failover already in progress".to_string());
+        }
+        if !master
+            .replicas
+            .iter()
+            .any(|replica| replica.down_since.is_none())
+        {
+            return Err("NOGOODSLAVE No suitable replica to promote".to_string());
+        }
+
+        info!(
+            "Starting a failover for master '{}' on request.",
+            master.config.name
+        );
+        let Some(epoch) = master.config_epoch.checked_add(1) else {
+            return Err("ERR The epoch of the master cannot be raised any further".to_string());
+        };
+        master.record_vote(state.my_run_id.clone(), epoch);
+        master.failover_state = FailoverState::Start;
+        master.failover_start_time = Some(Instant::now());
+        state.publish_event("+try-failover", master.event_details());
+    }
+    save_config(state).await;
+
+    tokio::spawn(failover::start_failover(master_arc, state.clone()));
+    Ok(RespValue::SimpleString("OK".into()))
+}
+
+/// Resets every master whose name matches `pattern`. Returns how many were reset.
+fn reset_masters(state: &GlobalWardenState, pattern: &str) -> i64 {
+    let mut count = 0;
+    for entry in state.masters.iter() {
+        if glob_match(pattern.as_bytes(), entry.key().as_bytes()) {
+            let mut master = entry.value().lock();
+            master.reset();
+            state.publish_event("+reset-master", master.event_details());
+            count += 1;
+        }
+    }
+    count
+}
+
+async fn monitor(
+    state: &Arc<GlobalWardenState>,
+    name: &str,
+    ip: &str,
+    port: &str,
+    quorum: &str,
+) -> CommandResult {
+    let port: u16 = port
+        .parse()
+        .map_err(|_| "ERR Invalid IP address or port specified".to_string())?;
+    if format!("{ip}:{port}").parse::<SocketAddr>().is_err() {
+        return Err("ERR Invalid IP address or port specified".to_string());
+    }
+    let quorum: usize = quorum
+        .parse()
+        .ok()
+        .filter(|&quorum| quorum > 0)
+        .ok_or_else(|| "ERR Quorum must be 1 or greater.".to_string())?;
+    if state.masters.contains_key(name) {
+        return Err("ERR Duplicated master name".to_string());
+    }
+
+    super::start_monitor(
+        state,
+        MonitoredMaster::new(name.to_string(), ip.to_string(), port, quorum),
+    );
+    state.publish_event(
+        "+monitor",
+        format!("master {name} {ip} {port} quorum {quorum}"),
+    );
+    save_config(state).await;
+    Ok(RespValue::SimpleString("OK".into()))
+}
+
+async fn remove(state: &GlobalWardenState, name: &str) -> CommandResult {
+    let Some((_, master)) = state.masters.remove(name) else {
+        return Err("ERR No such master with that name".to_string());
+    };
+    if let Some((_, monitor_task)) = state.monitors.remove(name) {
+        monitor_task.abort();
+    }
+    info!("Stopped monitoring master '{}'.", name);
+    state.publish_event("-monitor", master.lock().event_details());
+    save_config(state).await;
+    Ok(RespValue::SimpleString("OK".into()))
+}
+
+/// Applies `SENTINEL SET` options. All options are validated before any is applied.
+async fn set_options(state: &GlobalWardenState, name: &str, args: &[String]) -> CommandResult {
+    let master_arc = find_master(state, name)?;
+    let mut options = Vec::with_capacity(args.len() / 2);
+    for pair in args.chunks(2) {
+        let (option, value) = (&pair[0], &pair[1]);
+        let invalid = || format!("ERR Invalid argument '{value}' for SENTINEL SET '{option}'");
+        let positive = || {
+            value
+                .parse::<u64>()
+                .ok()
+                .filter(|&n| n > 0)
+                .ok_or_else(invalid)
+        };
+        let parsed = match option.to_ascii_lowercase().as_str() {
+            "down-after-milliseconds" => {
+                MasterOption::DownAfter(Duration::from_millis(positive()?))
+            }
+            "failover-timeout" => MasterOption::FailoverTimeout(Duration::from_millis(positive()?)),
+            "quorum" => MasterOption::Quorum(positive()? as usize),
+            _ => return Err(format!("ERR Unknown option '{option}' for SENTINEL SET")),
+        };
+        options.push((parsed, option, value));
+    }
+
+    {
+        let mut master = master_arc.lock();
+        for (parsed, option, value) in options {
+            match parsed {
+                MasterOption::DownAfter(down_after) => master.config.down_after = down_after,
+                MasterOption::FailoverTimeout(timeout) => master.config.failover_timeout = timeout,
+                MasterOption::Quorum(quorum) => master.config.quorum = quorum,
+            }
+            state.publish_event(
+                "+set",
+                format!("{} {} {}", master.event_details(), option, value),
+            );
+        }
+    }
+    save_config(state).await;
+    Ok(RespValue::SimpleString("OK".into()))
+}
+
+/// Answers a vote request from a candidate Warden, like `SENTINEL
+/// IS-MASTER-DOWN-BY-ADDR` in Sentinel. The reply is `[down, leader, leader_epoch]`:
+/// whether this Warden sees the master as down, and the candidate it voted for in its
+/// latest epoch. A `candidate` of `*` only asks for the down state.
+///
+/// A vote is granted once per epoch, and only while this Warden also sees the master
+/// as down. It is written to `warden.toml` before the reply is sent. Epochs too far
+/// ahead of this Warden's are refused.
+async fn vote(
+    state: &GlobalWardenState,
+    ip: &str,
+    port: &str,
+    epoch: &str,
+    candidate: &str,
+) -> CommandResult {
+    let addr: SocketAddr = format!("{ip}:{port}")
+        .parse()
+        .map_err(|_| "ERR Invalid IP address or port specified".to_string())?;
+    let epoch: u64 = epoch.parse().map_err(|_| "ERR Invalid epoch".to_string())?;
+    let Some(master_arc) = state
+        .masters
+        .iter()
+        .find(|entry| entry.value().lock().addr == addr)
+        .map(|entry| entry.value().clone())
+    else {
+        return Ok(vote_reply(false, None, 0));
+    };
+
+    let (reply, changed) = {
+        let mut master = master_arc.lock();
+        if !master.is_plausible_epoch(epoch) {
+            return Err(format!(
+                "ERR Epoch {epoch} is too far ahead of the current epoch {}",
+                master.config_epoch
+            ));
+        }
+        let is_down = master.primary_state.down_since.is_some();
+        let mut changed = false;
+        if epoch > master.config_epoch {
+            // Another Warden is in a newer epoch; an election of our own is stale.
+            master.config_epoch = epoch;
+            if master.failover_state == FailoverState::Vote {
+                master.reset_failover_state();
+            }
+            changed = true;
+        }
+        if is_down && candidate != "*" && epoch > master.last_voted_epoch {
+            info!(
+                "Voting for {} in epoch {} for master '{}'.",
+                candidate, epoch, master.config.name
+            );
+            master.record_vote(candidate.to_string(), epoch);
+            state.publish_event(
+                "+vote-for-leader",
+                format!("{} {} {}", master.event_details(), candidate, epoch),
+            );
+            changed = true;
+        }
+        let reply = vote_reply(
+            is_down,
+            master.voted_for.as_deref(),
+            master.last_voted_epoch,
+        );
+        (reply, changed)
+    };
+
+    if changed {
+        state
+            .save_config()
+            .await
+            .map_err(|e| format!("ERR Failed to persist the vote: {e}"))?;
+    }
+    Ok(reply)
+}
+
+fn vote_reply(is_down: bool, leader: Option<&str>, leader_epoch: u64) -> RespValue {
+    RespValue::Array(vec![
+        RespValue::Integer(is_down as i64),
+        RespValue::BulkString(leader.unwrap_or("*").to_string().into()),
+        RespValue::Integer(leader_epoch as i64),
+    ])
+}
+
+/// Writes a configuration change back to `warden.toml`. The change is already in
+/// effect, so a failure is only logged.
+async fn save_config(state: &GlobalWardenState) {
+    if let Err(e) = state.save_config().await {
+        warn!("Failed to rewrite the Warden configuration file: {}", e);
+    }
+}
+
+/// Builds the `INFO` reply. `section` selects `server` or `sentinel`; all sections are
+/// returned by default.
+fn info(state: &GlobalWardenState, section: Option<&String>) -> RespValue {
+    let section = section.map(|s| s.to_ascii_lowercase());
+    let wants = |name: &str| {
+        section
+            .as_deref()
+            .is_none_or(|s| s == name || matches!(s, "all" | "default" | "everything"))
+    };
+
+    let mut sections = Vec::new();
+    if wants("server") {
+        sections.push(format!(
+            "# Server\r\nrun_id:{}\r\ntcp_port:{}\r\nwarden_mode:yes\r\n",
+            state.my_run_id,
+            state.my_announce_addr.port()
+        ));
+    }
+    if wants("sentinel") {
+        let mut masters: Vec<_> = state
+            .masters
+            .iter()
+            .map(|entry| entry.value().clone())
+            .collect();
+        masters.sort_by_key(|master| master.lock().config.name.clone());
+
+        let mut sentinel = format!(
+            "# Sentinel\r\nsentinel_masters:{}\r\nsentinel_tilt:0\r\nsentinel_running_scripts:0\r\nsentinel_scripts_queue_length:0\r\n",
+            masters.len()
+        );
+        for (i, master) in masters.iter().enumerate() {
+            let master = master.lock();
+            let status = match master.status {
+                MasterStatus::Ok => "ok",
+                MasterStatus::Sdown => "sdown",
+                MasterStatus::Odown => "odown",
+            };
+            sentinel.push_str(&format!(
+                "master{}:name={},status={},address={},slaves={},sentinels={}\r\n",
+                i,
+                master.config.name,
+                status,
+                master.addr,
+                master.replicas.len(),
+                master.peers.len() + 1
+            ));
+        }
+        sections.push(sentinel);
+    }
+    RespValue::BulkString(sections.join("\r\n").into())
+}
@@ -1,11 +1,14 @@
 // src/core/warden/config.rs
 
 use anyhow::Result;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::net::SocketAddr;
+use std::path::Path;
 use std::time::Duration;
 use tokio::fs;
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct WardenConfig {
     #[serde(default = "default_host")]
     pub host: String,
@@ -13,12 +16,23 @@ pub struct WardenConfig {
     #[serde(default = "default_port")]
     pub port: u16,
 
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub announce_ip: Option<String>,
 
+    /// If set, clients must `AUTH` with this password before sending the `SENTINEL`
+    /// subcommands that change the Warden's state.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+
+    /// The run ID of this Warden. It is written to the file on first start, so that
+    /// the votes this Warden cast are still attributed to it after a restart.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub my_id: Option<String>,
+
     pub masters: Vec<MonitoredMaster>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct MonitoredMaster {
     pub name: String,
     pub ip: String,
@@ -33,6 +47,69 @@ pub struct MonitoredMaster {
 
     #[serde(with = "humantime_serde", default = "default_hello_interval")]
     pub hello_interval: Duration,
+
+    /// If set, the Warden listens on this port and proxies connections to the current
+    /// primary, giving clients that are not Sentinel-aware a stable endpoint.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy_port: Option<u16>,
+
+    /// The latest election epoch this Warden has seen for the master. Written back by
+    /// the Warden.
+    #[serde(default)]
+    pub current_epoch: u64,
+
+    /// The epoch of the failover that made `ip` and `port` the master. Written back
+    /// by the Warden.
+    #[serde(default)]
+    pub master_epoch: u64,
+
+    /// The epoch of the last vote this Warden cast for the master. Written back by
+    /// the Warden before the vote is sent, so it never votes twice in one epoch.
+    #[serde(default)]
+    pub leader_epoch: u64,
+
+    /// The run ID of the Warden voted for in `leader_epoch`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub leader: Option<String>,
+
+    /// The other Wardens known to monitor the master. Written back by the Warden. They
+    /// stay counted towards the majority of an election even while they are silent,
+    /// until `SENTINEL RESET`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub known_wardens: Vec<KnownWarden>,
+}
+
+/// Another Warden monitoring the same master.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct KnownWarden {
+    pub run_id: String,
+    pub addr: SocketAddr,
+}
+
+/// The `down_after` of a master added with `SENTINEL MONITOR`.
+const DEFAULT_DOWN_AFTER: Duration = Duration::from_secs(30);
+/// The `failover_timeout` of a master added with `SENTINEL MONITOR`.
+const DEFAULT_FAILOVER_TIMEOUT: Duration = Duration::from_secs(180);
+
+impl MonitoredMaster {
+    /// Creates the configuration of a master added at runtime, with default timeouts.
+    pub fn new(name: String, ip: String, port: u16, quorum: usize) -> Self {
+        Self {
+            name,
+            ip,
+            port,
+            quorum,
+            down_after: DEFAULT_DOWN_AFTER,
+            failover_timeout: DEFAULT_FAILOVER_TIMEOUT,
+            hello_interval: default_hello_interval(),
+            proxy_port: None,
+            current_epoch: 0,
+            master_epoch: 0,
+            leader_epoch: 0,
+            leader: None,
+            known_wardens: Vec::new(),
+        }
+    }
 }
 
 fn default_host() -> String {
@@ -53,4 +130,23 @@ impl WardenConfig {
         let config: WardenConfig = toml::from_str(&content)?;
         Ok(config)
     }
+
+    /// Writes the configuration to `path`, replacing the file atomically. The file and
+    /// its directory are synced before returning, as a vote must be on disk before it
+    /// is sent. This blocks, so async callers run it with `spawn_blocking`.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = toml::to_string(self)?;
+        let temp_path = path.with_extension("toml.tmp");
+        let mut file = std::fs::File::create(&temp_path)?;
+        file.write_all(content.as_bytes())?;
+        file.sync_all()?;
+        std::fs::rename(&temp_path, path)?;
+        // The rename is only durable once the directory entry is on disk.
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        std::fs::File::open(dir)?.sync_all()?;
+        Ok(())
+    }
 }
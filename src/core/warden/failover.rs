@@ -1,9 +1,12 @@
 // src/core/warden/failover.rs
 
 //! Contains the core logic for performing an automated failover orchestrated by a Warden leader.
+//!
+//! Every instance is fenced with `FAILOVER EPOCH` before it is reconfigured, so a
+//! Warden that was elected in an older epoch cannot undo a newer failover.
 
 use super::client::WardenClient;
-use super::state::{FailoverState, MasterState, MasterStatus};
+use super::state::{FailoverState, GlobalWardenState, MasterState, MasterStatus};
 use crate::core::protocol::RespFrame;
 use parking_lot::Mutex;
 use std::cmp::Ordering;
@@ -13,8 +16,12 @@ use std::sync::Arc;
 use tokio::time::{Duration, Instant, sleep};
 use tracing::{debug, error, info, warn};
 
-/// The main entry point for the failover process.
-pub async fn start_failover(state_arc: Arc<Mutex<MasterState>>) {
+/// The main entry point for the failover process. Its outcome is announced on the
+/// Warden's event channels.
+pub async fn start_failover(
+    state_arc: Arc<Mutex<MasterState>>,
+    global_state: Arc<GlobalWardenState>,
+) {
     let (master_name, old_master_addr, old_master_runid) = {
         let state = state_arc.lock();
         (state.config.name.clone(), state.addr, state.run_id.clone())
@@ -22,7 +29,7 @@ pub async fn start_failover(state_arc: Arc<Mutex<MasterState>>) {
     info!("Starting failover process for master '{}'", master_name);
 
     // --- Step 1: Select the best replica to promote ---
-    let candidate_addr = {
+    let (candidate_addr, epoch) = {
         let mut state = state_arc.lock();
         if state.failover_state != FailoverState::Start {
             warn!("start_failover called but state is not 'Start'. Aborting.");
@@ -35,6 +42,7 @@ pub async fn start_failover(state_arc: Arc<Mutex<MasterState>>) {
                 "No suitable replica found to promote for master '{}'. Aborting failover.",
                 state.config.name
             );
+            global_state.publish_event("-failover-abort-no-good-slave", state.event_details());
             state.reset_failover_state();
             return;
         }
@@ -42,7 +50,7 @@ pub async fn start_failover(state_arc: Arc<Mutex<MasterState>>) {
         let candidate_addr = candidate.unwrap();
         state.failover_state = FailoverState::PromoteReplica;
         state.promotion_candidate = Some(candidate_addr);
-        candidate_addr
+        (candidate_addr, state.config_epoch)
     };
 
     info!(
@@ -68,7 +76,16 @@ pub async fn start_failover(state_arc: Arc<Mutex<MasterState>>) {
                 RespFrame::BulkString("NO".into()),
                 RespFrame::BulkString("ONE".into()),
             ]);
-            client.send_and_receive(cmd).await.is_ok()
+            match client.fence_epoch(epoch).await {
+                Ok(()) => client.send_and_receive(cmd).await.is_ok(),
+                Err(e) => {
+                    warn!(
+                        "Candidate {} refused epoch {}: {}",
+                        candidate_addr, epoch, e
+                    );
+                    false
+                }
+            }
         }
         Err(e) => {
             error!(
@@ -114,7 +131,11 @@ pub async fn start_failover(state_arc: Arc<Mutex<MasterState>>) {
                 RespFrame::BulkString(new_master_ip_str.clone().into()),
                 RespFrame::BulkString(new_master_port_str.clone().into()),
             ]);
-            if let Err(e) = client.send_and_receive(cmd).await {
+            let result = match client.fence_epoch(epoch).await {
+                Ok(()) => client.send_and_receive(cmd).await.map(|_| ()),
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
                 warn!(
                     "Failed to send REPLICAOF to old master {}: {}. It may be unreachable.",
                     old_master_addr, e
@@ -137,8 +158,21 @@ pub async fn start_failover(state_arc: Arc<Mutex<MasterState>>) {
     // --- Step 5: Update the Warden's internal state with the new primary information ---
     let failover_timeout = {
         let mut state = state_arc.lock();
+        global_state.publish_event("+failover-end", state.event_details());
+        global_state.publish_event(
+            "+switch-master",
+            format!(
+                "{} {} {} {} {}",
+                master_name,
+                old_master_addr.ip(),
+                old_master_addr.port(),
+                candidate_addr.ip(),
+                candidate_addr.port()
+            ),
+        );
         state.status = MasterStatus::Ok;
         state.addr = candidate_addr;
+        state.master_epoch = epoch;
         state.run_id = new_master_runid.clone();
         state.primary_state.down_since = None;
         state.last_failover_time = std::time::Instant::now();
@@ -156,6 +190,12 @@ pub async fn start_failover(state_arc: Arc<Mutex<MasterState>>) {
 
         state.config.failover_timeout
     };
+    if let Err(e) = global_state.save_config().await {
+        warn!(
+            "Failed to persist the new master of '{}': {}",
+            master_name, e
+        );
+    }
 
     // --- Step 6: Spawn a task to reconfigure all other replicas ---
     info!(
@@ -169,6 +209,7 @@ pub async fn start_failover(state_arc: Arc<Mutex<MasterState>>) {
         new_master_runid,
         old_master_runid,
         failover_timeout,
+        epoch,
     ));
 
     info!(
@@ -229,6 +270,7 @@ pub async fn run_post_failover_reconfiguration(
     new_master_runid: String,
     old_master_runid: String,
     timeout: Duration,
+    epoch: u64,
 ) {
     let start_time = Instant::now();
     let mut interval = tokio::time::interval(Duration::from_secs(5));
@@ -260,6 +302,7 @@ pub async fn run_post_failover_reconfiguration(
                 new_master_addr,
                 &new_master_runid,
                 &old_master_runid,
+                epoch,
             )
             .await;
 
@@ -303,8 +346,10 @@ async fn reconfigure_and_verify_one_replica(
     new_master_addr: SocketAddr,
     new_master_runid: &str,
     old_master_runid: &str,
+    epoch: u64,
 ) -> anyhow::Result<bool> {
     let mut client = WardenClient::connect(replica_addr).await?;
+    client.fence_epoch(epoch).await?;
 
     // 1. Send the REPLICAOF command
     let replicaof_cmd = RespFrame::Array(vec![
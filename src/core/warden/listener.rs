@@ -1,14 +1,20 @@
 // src/core/warden/listener.rs
 
 //! Implements the TCP listener for the Warden, allowing clients and other
-//! Wardens to query its state using a subset of the SpinelDB Sentinel command API.
+//! Wardens to query its state using the Sentinel-compatible command API, and to
+//! subscribe to its event channels with `SUBSCRIBE` and `PSUBSCRIBE`.
 
+use super::commands::{process_warden_command, requires_auth};
+use crate::core::commands::scan::glob_match;
 use crate::core::protocol::{RespFrame, RespFrameCodec, RespValue};
-use crate::core::warden::state::GlobalWardenState;
+use crate::core::warden::state::{GlobalWardenState, WardenEvent};
 use anyhow::Result;
 use futures::{SinkExt, StreamExt};
+use std::collections::HashSet;
 use std::sync::Arc;
 use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
 use tokio_util::codec::Framed;
 use tracing::{info, warn};
 
@@ -37,14 +43,92 @@ pub async fn run_listener(port: u16, state: Arc<GlobalWardenState>) -> Result<()
     }
 }
 
+/// The event channels and patterns a connection is subscribed to.
+#[derive(Default)]
+struct Subscriptions {
+    channels: HashSet<String>,
+    patterns: HashSet<String>,
+    /// Created on the first subscription, so idle connections do not buffer events.
+    receiver: Option<broadcast::Receiver<WardenEvent>>,
+}
+
+impl Subscriptions {
+    fn count(&self) -> usize {
+        self.channels.len() + self.patterns.len()
+    }
+
+    /// Builds the messages to deliver for `event`: one for a channel subscription and
+    /// one per matching pattern.
+    fn messages_for(&self, event: &WardenEvent) -> Vec<RespFrame> {
+        let bulk = |s: &str| RespValue::BulkString(s.to_string().into());
+        let mut messages = Vec::new();
+        if self.channels.contains(&event.channel) {
+            messages.push(
+                RespValue::Array(vec![
+                    bulk("message"),
+                    bulk(&event.channel),
+                    bulk(&event.message),
+                ])
+                .into(),
+            );
+        }
+        for pattern in &self.patterns {
+            if glob_match(pattern.as_bytes(), event.channel.as_bytes()) {
+                messages.push(
+                    RespValue::Array(vec![
+                        bulk("pmessage"),
+                        bulk(pattern),
+                        bulk(&event.channel),
+                        bulk(&event.message),
+                    ])
+                    .into(),
+                );
+            }
+        }
+        messages
+    }
+}
+
+/// Waits for the next event if the connection is subscribed, or forever otherwise.
+async fn next_event(receiver: &mut Option<broadcast::Receiver<WardenEvent>>) -> WardenEvent {
+    let Some(receiver) = receiver else {
+        return std::future::pending().await;
+    };
+    loop {
+        match receiver.recv().await {
+            Ok(event) => return event,
+            Err(RecvError::Lagged(skipped)) => {
+                warn!(
+                    "Warden subscriber lagged behind; {} events dropped.",
+                    skipped
+                );
+            }
+            // The sender lives as long as the Warden, so this only happens on shutdown.
+            Err(RecvError::Closed) => return std::future::pending().await,
+        }
+    }
+}
+
 /// Handles a single client connection, reading commands and sending responses.
 async fn handle_connection(socket: TcpStream, state: Arc<GlobalWardenState>) -> Result<()> {
     // Wrap the TCP stream with our RESP codec to handle frame encoding/decoding.
     let mut framed = Framed::new(socket, RespFrameCodec);
+    let mut subscriptions = Subscriptions::default();
+    let mut authenticated = state.password.is_none();
 
-    // Process frames from the client in a loop.
-    // The `StreamExt::next` method is used to asynchronously get the next item.
-    while let Some(result) = framed.next().await {
+    loop {
+        let result = tokio::select! {
+            result = framed.next() => match result {
+                Some(result) => result,
+                None => break,
+            },
+            event = next_event(&mut subscriptions.receiver) => {
+                for message in subscriptions.messages_for(&event) {
+                    framed.send(message).await?;
+                }
+                continue;
+            }
+        };
         let frame = match result {
             Ok(frame) => frame,
             Err(e) => {
@@ -54,76 +138,152 @@ async fn handle_connection(socket: TcpStream, state: Arc<GlobalWardenState>) ->
         };
 
         // We expect commands to be in the form of a RESP Array.
-        if let RespFrame::Array(args) = frame {
-            let response = process_warden_command(&args, &state);
-            // The `SinkExt::send` method is used to asynchronously send a response.
-            if let Err(e) = framed.send(response).await {
-                warn!("Error sending response to warden client: {}", e);
-                break;
+        let responses = if let RespFrame::Array(args) = frame {
+            if let Some(response) = handle_auth_command(&args, &state, &mut authenticated).await {
+                vec![response]
+            } else if !authenticated && requires_auth(&args) {
+                vec![RespFrame::Error(
+                    "NOAUTH Authentication required.".to_string(),
+                )]
+            } else {
+                match handle_pubsub_command(&args, &state, &mut subscriptions) {
+                    Some(responses) => responses,
+                    None => vec![process_warden_command(&args, &state).await],
+                }
             }
         } else {
-            let error_response = RespFrame::Error(
+            vec![RespFrame::Error(
                 "ERR invalid command format. Commands must be RESP arrays.".to_string(),
-            );
-            if let Err(e) = framed.send(error_response).await {
-                warn!("Error sending error response to warden client: {}", e);
-                break;
+            )]
+        };
+        for response in responses {
+            if let Err(e) = framed.send(response).await {
+                warn!("Error sending response to warden client: {}", e);
+                return Ok(());
             }
         }
     }
     Ok(())
 }
 
-/// Parses and processes a single command received by the Warden.
-fn process_warden_command(args: &[RespFrame], state: &Arc<GlobalWardenState>) -> RespFrame {
-    // Use .first() for a more idiomatic and safer way to get the first element.
-    let Some(RespFrame::BulkString(cmd_bytes)) = args.first() else {
-        return RespFrame::Error("ERR invalid command format".to_string());
+/// Handles `AUTH password`, which allows the connection to change the Warden's state.
+/// Returns `None` for any other command.
+async fn handle_auth_command(
+    args: &[RespFrame],
+    state: &GlobalWardenState,
+    authenticated: &mut bool,
+) -> Option<RespFrame> {
+    let Some(RespFrame::BulkString(cmd)) = args.first() else {
+        return None;
+    };
+    if !cmd.eq_ignore_ascii_case(b"auth") {
+        return None;
+    }
+    let [_, RespFrame::BulkString(password)] = args else {
+        return Some(RespFrame::Error(
+            "ERR wrong number of arguments for 'auth' command".to_string(),
+        ));
+    };
+    let Some(expected) = &state.password else {
+        return Some(RespFrame::Error(
+            "ERR Client sent AUTH, but no password is set".to_string(),
+        ));
     };
+    if password.as_ref() == expected.as_bytes() {
+        *authenticated = true;
+        Some(RespFrame::SimpleString("OK".to_string()))
+    } else {
+        // Add a delay on failure to slow down guessing.
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        Some(RespFrame::Error("WRONGPASS invalid password".to_string()))
+    }
+}
 
-    // PING is a simple health check.
-    if cmd_bytes.eq_ignore_ascii_case(b"ping") {
-        return RespValue::SimpleString("PONG".into()).into();
+/// Handles `SUBSCRIBE`, `PSUBSCRIBE`, `UNSUBSCRIBE` and `PUNSUBSCRIBE`, which change the
+/// subscriptions of the connection. Returns `None` for any other command.
+fn handle_pubsub_command(
+    args: &[RespFrame],
+    state: &GlobalWardenState,
+    subscriptions: &mut Subscriptions,
+) -> Option<Vec<RespFrame>> {
+    let Some(RespFrame::BulkString(cmd)) = args.first() else {
+        return None;
+    };
+    let cmd = String::from_utf8_lossy(cmd).to_ascii_lowercase();
+    if !matches!(
+        cmd.as_str(),
+        "subscribe" | "psubscribe" | "unsubscribe" | "punsubscribe"
+    ) {
+        return None;
+    }
+    let names: Vec<String> = args[1..]
+        .iter()
+        .filter_map(|arg| match arg {
+            RespFrame::BulkString(bytes) => Some(String::from_utf8_lossy(bytes).to_string()),
+            _ => None,
+        })
+        .collect();
+    if names.len() != args.len() - 1 {
+        return Some(vec![RespFrame::Error(
+            "ERR invalid command format".to_string(),
+        )]);
+    }
+    let is_pattern = cmd.starts_with('p');
+    let is_unsubscribe = cmd.contains("unsubscribe");
+    if names.is_empty() && !is_unsubscribe {
+        return Some(vec![RespFrame::Error(format!(
+            "ERR wrong number of arguments for '{cmd}' command"
+        ))]);
     }
 
-    // SENTINEL commands are the main API. We keep the "SENTINEL" name for client compatibility.
-    if cmd_bytes.eq_ignore_ascii_case(b"sentinel") {
-        let Some(RespFrame::BulkString(subcmd_bytes)) = args.get(1) else {
-            return RespFrame::Error("ERR unknown sentinel subcommand".to_string());
+    let names = if names.is_empty() {
+        // Unsubscribing without arguments removes every subscription of that kind.
+        let set = if is_pattern {
+            &subscriptions.patterns
+        } else {
+            &subscriptions.channels
         };
+        let mut all: Vec<String> = set.iter().cloned().collect();
+        all.sort();
+        all
+    } else {
+        names
+    };
 
-        // The most important command for clients: get the current master's address.
-        if subcmd_bytes.eq_ignore_ascii_case(b"get-master-addr-by-name") {
-            let Some(RespFrame::BulkString(master_name_bytes)) = args.get(2) else {
-                return RespFrame::Error(
-                    "ERR wrong number of arguments for 'sentinel get-master-addr-by-name'"
-                        .to_string(),
-                );
-            };
-
-            let master_name = String::from_utf8_lossy(master_name_bytes);
-
-            // Look up the master's state in the global map.
-            return if let Some(master_state_entry) = state.masters.get(&*master_name) {
-                // Lock the specific master's state to read its current address.
-                let master_state = master_state_entry.value().lock();
-
-                // Return the address as a [ip, port] array, which is the standard SpinelDB format.
-                RespValue::Array(vec![
-                    RespValue::BulkString(master_state.addr.ip().to_string().into()),
-                    RespValue::Integer(master_state.addr.port() as i64),
-                ])
-                .into()
-            } else {
-                // If the master name is unknown, return Null.
-                RespFrame::Null
-            };
+    let mut replies = Vec::with_capacity(names.len().max(1));
+    for name in &names {
+        let set = if is_pattern {
+            &mut subscriptions.patterns
+        } else {
+            &mut subscriptions.channels
+        };
+        if is_unsubscribe {
+            set.remove(name);
+        } else {
+            set.insert(name.clone());
         }
+        replies.push(subscription_reply(&cmd, Some(name), subscriptions.count()));
+    }
+    if replies.is_empty() {
+        // Unsubscribing from nothing still gets one reply, as in SpinelDB.
+        replies.push(subscription_reply(&cmd, None, subscriptions.count()));
     }
 
-    // If the command is not recognized, return an error.
-    RespFrame::Error(format!(
-        "ERR Unknown command '{}'",
-        String::from_utf8_lossy(cmd_bytes)
-    ))
+    if subscriptions.count() == 0 {
+        subscriptions.receiver = None;
+    } else if subscriptions.receiver.is_none() {
+        subscriptions.receiver = Some(state.events.subscribe());
+    }
+    Some(replies)
+}
+
+fn subscription_reply(kind: &str, name: Option<&str>, count: usize) -> RespFrame {
+    RespValue::Array(vec![
+        RespValue::BulkString(kind.to_string().into()),
+        name.map_or(RespValue::Null, |name| {
+            RespValue::BulkString(name.to_string().into())
+        }),
+        RespValue::Integer(count as i64),
+    ])
+    .into()
 }
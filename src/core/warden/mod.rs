@@ -7,26 +7,30 @@
 //! - Detecting when a primary instance is down (Subjective Down and Objective Down).
 //! - Coordinating with other Warden instances to reach a quorum.
 //! - Triggering and managing an automated failover process to promote a replica to a new primary.
-//! - Providing an API for clients to query the current address of a master.
+//! - Providing a Sentinel-compatible API for clients to query and manage the monitored
+//!   masters, and Pub/Sub channels announcing failovers.
+//! - Optionally proxying client connections to the current primary of each master.
 
 use anyhow::{Result, anyhow};
-use dashmap::DashMap;
 use parking_lot::Mutex;
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::task::JoinSet;
 use tracing::{error, info, warn};
 
 // Declare all sub-modules that make up the Warden functionality.
 pub mod client;
+pub mod commands;
 pub mod config;
 pub mod failover;
 pub mod listener;
+pub mod proxy;
 pub mod state;
 pub mod worker;
 
 // Import the necessary structs and functions from our sub-modules.
-use self::config::WardenConfig;
+use self::config::{MonitoredMaster, WardenConfig};
 use self::listener::run_listener;
 use self::state::{GlobalWardenState, MasterState};
 use self::worker::MasterMonitor;
@@ -45,12 +49,18 @@ pub async fn run(config_path: &str) -> Result<()> {
     // A JoinSet to manage all spawned asynchronous tasks.
     let mut tasks: JoinSet<Result<()>> = JoinSet::new();
 
-    // Generate a unique 40-character hexadecimal run ID for this Warden instance.
-    // This is used to identify this Warden when communicating with others.
-    let mut runid_bytes = [0u8; 20];
-    getrandom::fill(&mut runid_bytes)
-        .map_err(|e| anyhow::anyhow!("Failed to generate random run ID: {e}"))?;
-    let my_run_id = hex::encode(runid_bytes);
+    // Reuse the run ID saved in the config file, or generate a unique 40-character
+    // hexadecimal one on first start. It identifies this Warden to the others, and its
+    // votes must remain attributed to it across restarts.
+    let my_run_id = match &config.my_id {
+        Some(my_id) => my_id.clone(),
+        None => {
+            let mut runid_bytes = [0u8; 20];
+            getrandom::fill(&mut runid_bytes)
+                .map_err(|e| anyhow::anyhow!("Failed to generate random run ID: {e}"))?;
+            hex::encode(runid_bytes)
+        }
+    };
     info!("Warden run ID: {}", my_run_id);
 
     // Determine the address this Warden should announce to its peers.
@@ -63,41 +73,20 @@ pub async fn run(config_path: &str) -> Result<()> {
 
     // Create the global, shared state for the Warden.
     // This state is wrapped in an Arc to be shared safely across all tasks.
-    let global_state = Arc::new(GlobalWardenState {
-        my_run_id,
-        masters: DashMap::new(),
-    });
+    let global_state = Arc::new(
+        GlobalWardenState::new(my_run_id, my_announce_addr)
+            .with_host(config.host.clone())
+            .with_password(config.password.clone())
+            .with_config_file(PathBuf::from(config_path), config.clone()),
+    );
 
     // Iterate through each master configuration and spawn a dedicated monitor task for it.
     for master_config in config.masters {
-        let name = master_config.name.clone();
-        info!(
-            "Initializing monitor for master '{}' at {}:{}",
-            &name, &master_config.ip, &master_config.port
-        );
-
-        // Create the specific state for this master, protected by a Mutex.
-        let master_state = Arc::new(Mutex::new(MasterState::from(master_config)));
-
-        // Insert this master's state into the global state map.
-        global_state
-            .masters
-            .insert(name.clone(), master_state.clone());
-
-        // Create and spawn the monitor task.
-        let monitor =
-            MasterMonitor::new(name, master_state, global_state.clone(), my_announce_addr);
-
-        // Store master name before moving the monitor into the async block.
-        let master_name_for_log = monitor.master_name().to_string();
-        tasks.spawn(async move {
-            monitor.run().await;
-            // This task should run forever, if it exits it's a critical issue.
-            // Return an error to signal this.
-            Err(anyhow!(
-                "MasterMonitor for {master_name_for_log} exited unexpectedly."
-            ))
-        });
+        start_monitor(&global_state, master_config);
+    }
+    // Write the run ID back on first start.
+    if config.my_id.is_none() {
+        global_state.save_config().await?;
     }
 
     // Spawn the TCP listener task. This allows other clients or Wardens
@@ -126,3 +115,37 @@ pub async fn run(config_path: &str) -> Result<()> {
     // If the loop exits, it means all tasks have stopped, which is a server-level error.
     Err(anyhow!("All Warden tasks have terminated. Shutting down."))
 }
+
+/// Starts monitoring a master, either from `warden.toml` or `SENTINEL MONITOR`. The
+/// monitor task runs until the master is removed with `SENTINEL REMOVE`.
+pub fn start_monitor(global_state: &Arc<GlobalWardenState>, master_config: MonitoredMaster) {
+    let name = master_config.name.clone();
+    info!(
+        "Initializing monitor for master '{}' at {}:{}",
+        &name, &master_config.ip, &master_config.port
+    );
+
+    // Create the specific state for this master, protected by a Mutex.
+    let master_state = Arc::new(Mutex::new(MasterState::from(master_config)));
+
+    // Insert this master's state into the global state map.
+    global_state
+        .masters
+        .insert(name.clone(), master_state.clone());
+
+    let monitor = MasterMonitor::new(
+        name.clone(),
+        master_state,
+        global_state.clone(),
+        global_state.my_announce_addr,
+    );
+    let task = tokio::spawn(async move {
+        monitor.run().await;
+        // This task should run forever, if it exits it's a critical issue.
+        error!(
+            "MasterMonitor for {} exited unexpectedly.",
+            monitor.master_name()
+        );
+    });
+    global_state.monitors.insert(name, task.abort_handle());
+}
@@ -0,0 +1,151 @@
+// src/core/warden/proxy.rs
+
+//! Implements the client-facing proxy of a monitored master.
+//!
+//! When a master has a `proxy_port`, the Warden listens on that port and forwards each
+//! connection to the current primary, as tracked in `MasterState.addr`. Connections
+//! are dropped on `+switch-master`, so clients that reconnect reach the new primary.
+//! This gives applications using plain clients a stable endpoint across failovers.
+
+use super::state::{GlobalWardenState, MasterState, WardenEvent};
+use parking_lot::Mutex;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinSet;
+use tokio::time;
+use tracing::{debug, info, warn};
+
+/// How long to wait before retrying to bind the proxy port.
+const BIND_RETRY_DELAY: Duration = Duration::from_secs(5);
+/// How long to wait for the primary to accept a proxied connection.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Runs the proxy for a master until the task is aborted. Aborting it also closes all
+/// proxied connections.
+pub async fn run_proxy(
+    port: u16,
+    master_name: String,
+    state: Arc<Mutex<MasterState>>,
+    global_state: Arc<GlobalWardenState>,
+) {
+    let listener = loop {
+        match TcpListener::bind((global_state.host.as_str(), port)).await {
+            Ok(listener) => break listener,
+            Err(e) => {
+                warn!(
+                    "Failed to bind proxy port {} for master '{}': {}. Retrying in {:?}...",
+                    port, master_name, e, BIND_RETRY_DELAY
+                );
+                time::sleep(BIND_RETRY_DELAY).await;
+            }
+        }
+    };
+    info!(
+        "Proxy for master '{}' listening on port {}",
+        master_name, port
+    );
+
+    // Connections live in this set, so that they end with the proxy.
+    let mut connections = JoinSet::new();
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((socket, addr)) => {
+                    // Subscribe before reading the address, so that a switch between
+                    // the two is not missed.
+                    let events = global_state.events.subscribe();
+                    let primary_addr = state.lock().addr;
+                    connections.spawn(proxy_connection(
+                        socket,
+                        addr,
+                        primary_addr,
+                        master_name.clone(),
+                        state.clone(),
+                        events,
+                    ));
+                }
+                Err(e) => warn!("Failed to accept proxy connection for '{}': {}", master_name, e),
+            },
+            // Reap finished connections so the set does not grow without bound.
+            Some(_) = connections.join_next(), if !connections.is_empty() => {}
+        }
+    }
+}
+
+/// Forwards one client connection to `primary_addr` until either side closes it or
+/// the master is switched.
+async fn proxy_connection(
+    mut client: TcpStream,
+    client_addr: SocketAddr,
+    primary_addr: SocketAddr,
+    master_name: String,
+    state: Arc<Mutex<MasterState>>,
+    mut events: broadcast::Receiver<WardenEvent>,
+) {
+    let mut primary = match time::timeout(CONNECT_TIMEOUT, TcpStream::connect(primary_addr)).await {
+        Ok(Ok(stream)) => stream,
+        Ok(Err(e)) => {
+            debug!(
+                "Proxy for '{}' could not reach primary {}: {}",
+                master_name, primary_addr, e
+            );
+            return;
+        }
+        Err(_) => {
+            debug!(
+                "Proxy for '{}' timed out connecting to primary {}",
+                master_name, primary_addr
+            );
+            return;
+        }
+    };
+
+    tokio::select! {
+        result = tokio::io::copy_bidirectional(&mut client, &mut primary) => {
+            if let Err(e) = result {
+                debug!("Proxied connection from {} ended: {}", client_addr, e);
+            }
+        }
+        _ = wait_for_switch(&mut events, &master_name, &state, primary_addr) => {
+            info!(
+                "Closing proxied connection from {} to old primary {} of '{}'.",
+                client_addr, primary_addr, master_name
+            );
+        }
+    }
+}
+
+/// Waits until `master_name` no longer points at `primary_addr`.
+async fn wait_for_switch(
+    events: &mut broadcast::Receiver<WardenEvent>,
+    master_name: &str,
+    state: &Mutex<MasterState>,
+    primary_addr: SocketAddr,
+) {
+    loop {
+        match events.recv().await {
+            // The address is updated under the same lock the event is published
+            // under, so it is current once the lock is available. Checking it ignores
+            // a switch to the primary this connection already uses.
+            Ok(event) => {
+                if event.channel == "+switch-master"
+                    && event.message.split(' ').next() == Some(master_name)
+                    && state.lock().addr != primary_addr
+                {
+                    return;
+                }
+            }
+            // Events were missed, so check the address directly.
+            Err(RecvError::Lagged(_)) => {
+                if state.lock().addr != primary_addr {
+                    return;
+                }
+            }
+            Err(RecvError::Closed) => return std::future::pending().await,
+        }
+    }
+}
@@ -4,13 +4,21 @@
 //! of the monitored SpinelDB instances and the failover process.
 
 use super::client::WardenClient;
-use super::config::MonitoredMaster;
+use super::config::{KnownWarden, MonitoredMaster, WardenConfig};
 use dashmap::DashMap;
 use parking_lot::Mutex;
 use std::collections::{HashMap, HashSet};
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+use tokio::task::AbortHandle;
+
+/// The number of events buffered for each subscriber before it starts missing them.
+const EVENT_CHANNEL_CAPACITY: usize = 1024;
+/// How far another Warden's epoch may be ahead of this Warden's to be adopted.
+const MAX_EPOCH_LEAD: u64 = 1 << 20;
 
 /// Represents the perceived status of a master instance.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -92,14 +100,19 @@ pub struct MasterState {
     pub primary_state: InstanceState,
     /// A map of all known replicas for this master, keyed by their address.
     pub replicas: DashMap<SocketAddr, InstanceState>,
-    /// A map of other Warden instances monitoring this same master.
-    /// Key: Warden's run_id.
+    /// A map of other Warden instances monitoring this same master. Peers are never
+    /// dropped for being silent, so that a partition or the loss of the master cannot
+    /// shrink the majority needed to win an election. Key: Warden's run_id.
     pub peers: HashMap<String, WardenPeerState>,
     /// The client used for Pub/Sub communication with the master.
     /// Wrapped in a Mutex to allow reconnection if the connection drops.
     pub pubsub_client: Mutex<Option<WardenClient>>,
-    /// The configuration epoch, incremented on each successful failover.
+    /// The election epoch of the master, raised by each election and adopted from
+    /// other Wardens that are ahead. Persisted in `warden.toml`.
     pub config_epoch: u64,
+    /// The epoch of the failover that made `addr` the master. Other Wardens adopt the
+    /// address announced with the highest master epoch.
+    pub master_epoch: u64,
     /// The current state of the failover process for this master.
     pub failover_state: FailoverState,
     /// The time when the current failover process began.
@@ -113,6 +126,11 @@ pub struct MasterState {
     pub last_failover_time: Instant,
     /// The last epoch this Warden has cast a vote for, preventing duplicate voting.
     pub last_voted_epoch: u64,
+    /// The run ID of the Warden this Warden voted for in `last_voted_epoch`.
+    pub voted_for: Option<String>,
+    /// The earliest time this Warden may start another election after losing one.
+    /// Randomized, so that candidates of a split vote do not retry in lockstep.
+    pub next_election_time: Instant,
     /// [BARU] A set of replica addresses that still need to be reconfigured after a failover.
     /// This state is persisted across Warden restarts (in memory).
     pub replicas_pending_reconfiguration: HashSet<SocketAddr>,
@@ -124,6 +142,30 @@ impl MasterState {
         let addr: SocketAddr = format!("{}:{}", config.ip, config.port)
             .parse()
             .expect("Invalid master address in config");
+        let (config_epoch, master_epoch, last_voted_epoch, voted_for) = (
+            config.current_epoch,
+            config.master_epoch,
+            config.leader_epoch,
+            config.leader.clone(),
+        );
+        // Wardens known before a restart are counted, but not usable until they are
+        // heard from again.
+        let hello_timeout = config.hello_interval * 5;
+        let last_hello_received = Instant::now()
+            .checked_sub(hello_timeout)
+            .unwrap_or_else(Instant::now);
+        let peers = config
+            .known_wardens
+            .iter()
+            .map(|known| {
+                let peer = WardenPeerState {
+                    run_id: known.run_id.clone(),
+                    addr: known.addr,
+                    last_hello_received,
+                };
+                (known.run_id.clone(), peer)
+            })
+            .collect();
         Self {
             config,
             status: MasterStatus::Ok,
@@ -131,16 +173,19 @@ impl MasterState {
             run_id: "?".to_string(),
             primary_state: InstanceState::new(addr),
             replicas: DashMap::new(),
-            peers: HashMap::new(),
+            peers,
             pubsub_client: Mutex::new(None),
-            config_epoch: 0,
+            config_epoch,
+            master_epoch,
             failover_state: FailoverState::None,
             failover_start_time: None,
             promotion_candidate: None,
             votes: HashMap::new(),
             // Initialize with a time far in the past to allow the first failover immediately.
             last_failover_time: Instant::now() - Duration::from_secs(3600 * 24),
-            last_voted_epoch: 0,
+            last_voted_epoch,
+            voted_for,
+            next_election_time: Instant::now(),
             // [BARU] Initialize the new set.
             replicas_pending_reconfiguration: HashSet::new(),
         }
@@ -155,6 +200,81 @@ impl MasterState {
         // [BARU] Also clear any pending reconfiguration tasks.
         self.replicas_pending_reconfiguration.clear();
     }
+
+    /// The number of votes needed to win an election: the quorum, and at least a
+    /// majority of all known Wardens, so that two partitions cannot both elect a leader.
+    /// Silent Wardens are still known, so the majority does not shrink when they stop
+    /// sending hellos.
+    pub fn required_votes(&self) -> usize {
+        let wardens = self.peers.len() + 1;
+        self.config.quorum.max(wardens / 2 + 1)
+    }
+
+    /// Returns true if `epoch`, announced by another Warden, is close enough to this
+    /// Warden's epoch to be adopted. Elections raise the epoch one at a time, so a much
+    /// larger lead can only come from a misbehaving client.
+    pub fn is_plausible_epoch(&self, epoch: u64) -> bool {
+        epoch <= self.config_epoch.saturating_add(MAX_EPOCH_LEAD)
+    }
+
+    /// Records a vote for `candidate` in `epoch`, and moves this Warden to that epoch.
+    pub fn record_vote(&mut self, candidate: String, epoch: u64) {
+        self.last_voted_epoch = epoch;
+        self.voted_for = Some(candidate);
+        self.config_epoch = self.config_epoch.max(epoch);
+    }
+
+    /// Returns the configuration to write back to `warden.toml`, with the current
+    /// address and epochs of the master and the Wardens known to monitor it.
+    pub fn persisted_config(&self) -> MonitoredMaster {
+        let mut known_wardens: Vec<KnownWarden> = self
+            .peers
+            .values()
+            .map(|peer| KnownWarden {
+                run_id: peer.run_id.clone(),
+                addr: peer.addr,
+            })
+            .collect();
+        known_wardens.sort_by(|a, b| a.run_id.cmp(&b.run_id));
+        MonitoredMaster {
+            ip: self.addr.ip().to_string(),
+            port: self.addr.port(),
+            current_epoch: self.config_epoch,
+            master_epoch: self.master_epoch,
+            leader_epoch: self.last_voted_epoch,
+            leader: self.voted_for.clone(),
+            known_wardens,
+            ..self.config.clone()
+        }
+    }
+
+    /// Forgets the replicas, peer Wardens and failure state of the master, as
+    /// `SENTINEL RESET` does. The monitor rediscovers the replicas and peers.
+    pub fn reset(&mut self) {
+        self.status = MasterStatus::Ok;
+        self.primary_state.down_since = None;
+        self.replicas.clear();
+        self.peers.clear();
+        self.reset_failover_state();
+    }
+
+    /// Returns the `master <name> <ip> <port>` part of an event about this master.
+    pub fn event_details(&self) -> String {
+        format!(
+            "master {} {} {}",
+            self.config.name,
+            self.addr.ip(),
+            self.addr.port()
+        )
+    }
+}
+
+/// An event published on one of the Warden's Pub/Sub channels, named after the event
+/// as in Sentinel (e.g. `+switch-master`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WardenEvent {
+    pub channel: String,
+    pub message: String,
 }
 
 /// The top-level, globally shared state for the entire Warden process.
@@ -162,6 +282,87 @@ impl MasterState {
 pub struct GlobalWardenState {
     /// The unique run ID of this Warden instance.
     pub my_run_id: String,
+    /// The address this Warden announces to its peers.
+    pub my_announce_addr: SocketAddr,
+    /// The host the Warden's proxies listen on, from `host` in `warden.toml`.
+    pub host: String,
+    /// The password required to change the Warden's state, if any.
+    pub password: Option<String>,
     /// A thread-safe map from a master's name to its `MasterState`.
     pub masters: DashMap<String, Arc<Mutex<MasterState>>>,
+    /// The monitor task of each master, aborted when the master is removed.
+    pub monitors: DashMap<String, AbortHandle>,
+    /// Delivers events to clients subscribed to the Warden's event channels.
+    pub events: broadcast::Sender<WardenEvent>,
+    /// The `warden.toml` this Warden was started from, rewritten by `save_config`.
+    config_file: Option<(PathBuf, WardenConfig)>,
+    /// Serializes writes of the configuration file.
+    save_lock: tokio::sync::Mutex<()>,
+}
+
+impl GlobalWardenState {
+    /// Creates the state of a Warden that does not monitor any master yet.
+    pub fn new(my_run_id: String, my_announce_addr: SocketAddr) -> Self {
+        Self {
+            my_run_id,
+            my_announce_addr,
+            host: "0.0.0.0".to_string(),
+            password: None,
+            masters: DashMap::new(),
+            monitors: DashMap::new(),
+            events: broadcast::channel(EVENT_CHANNEL_CAPACITY).0,
+            config_file: None,
+            save_lock: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Makes the Warden's proxies listen on `host` instead of all interfaces.
+    pub fn with_host(mut self, host: String) -> Self {
+        self.host = host;
+        self
+    }
+
+    /// Requires clients to `AUTH` with `password` before changing the Warden's state.
+    pub fn with_password(mut self, password: Option<String>) -> Self {
+        self.password = password;
+        self
+    }
+
+    /// Makes `save_config` write the Warden's state back to the configuration file at
+    /// `path`, which was loaded as `config`.
+    pub fn with_config_file(mut self, path: PathBuf, config: WardenConfig) -> Self {
+        self.config_file = Some((path, config));
+        self
+    }
+
+    /// Writes the monitored masters, their epochs and this Warden's votes to the
+    /// configuration file, if there is one, and waits until it is on disk. Must not be
+    /// called while holding the lock of a master.
+    pub async fn save_config(&self) -> anyhow::Result<()> {
+        let Some((path, config)) = &self.config_file else {
+            return Ok(());
+        };
+        let _guard = self.save_lock.lock().await;
+        let mut masters: Vec<MonitoredMaster> = self
+            .masters
+            .iter()
+            .map(|entry| entry.value().lock().persisted_config())
+            .collect();
+        masters.sort_by(|a, b| a.name.cmp(&b.name));
+        let config = WardenConfig {
+            my_id: Some(self.my_run_id.clone()),
+            masters,
+            ..config.clone()
+        };
+        let path = path.clone();
+        tokio::task::spawn_blocking(move || config.save(&path)).await?
+    }
+
+    /// Publishes an event to subscribed clients. It is dropped if there are none.
+    pub fn publish_event(&self, channel: &str, message: String) {
+        let _ = self.events.send(WardenEvent {
+            channel: channel.to_string(),
+            message,
+        });
+    }
 }
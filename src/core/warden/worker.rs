@@ -4,6 +4,7 @@
 
 use super::client::WardenClient;
 use super::failover;
+use super::proxy;
 use super::state::{
     FailoverState, GlobalWardenState, InstanceState, MasterState, MasterStatus, WardenPeerState,
 };
@@ -20,17 +21,21 @@ use tracing::{debug, error, info, warn};
 
 /// The Pub/Sub channel used by Wardens to announce their presence.
 pub(super) const HELLO_CHANNEL: &str = "__warden__:hello";
-/// The Pub/Sub channel used for failover voting.
-pub(super) const VOTES_CHANNEL: &str = "__warden__:votes";
+/// The longest a candidate waits for votes before giving up on an election.
+const MAX_ELECTION_TIMEOUT: Duration = Duration::from_secs(10);
 
 /// The payload of a `HELLO` message broadcast by a Warden instance.
 #[derive(Serialize, Deserialize, Debug)]
 struct HelloMessage {
     addr: SocketAddr,
     run_id: String,
+    /// The latest election epoch of the sender.
     epoch: u64,
     master_name: String,
     master_addr: SocketAddr,
+    /// The epoch of the failover that made `master_addr` the master.
+    #[serde(default)]
+    master_epoch: u64,
 }
 
 /// A cloneable struct that runs the monitoring loops for a single master.
@@ -64,13 +69,18 @@ impl MasterMonitor {
 
     /// The main entry point for the monitor, which spawns its sub-tasks.
     pub async fn run(&self) {
-        let (tick_interval, info_interval, hello_interval) = {
+        let (tick_interval, info_interval, hello_interval, proxy_port) = {
             let state = self.state.lock();
             let down_after = state.config.down_after;
             let tick_interval = (down_after / 3).max(Duration::from_secs(1));
             let info_interval = (down_after * 2).max(Duration::from_secs(10));
             let hello_interval = state.config.hello_interval;
-            (tick_interval, info_interval, hello_interval)
+            (
+                tick_interval,
+                info_interval,
+                hello_interval,
+                state.config.proxy_port,
+            )
         };
 
         info!(
@@ -83,6 +93,14 @@ impl MasterMonitor {
         tasks.spawn(self.clone().run_tick_loop(tick_interval));
         tasks.spawn(self.clone().run_info_loop(info_interval));
         tasks.spawn(self.clone().run_pubsub_loop(hello_interval));
+        if let Some(port) = proxy_port {
+            tasks.spawn(proxy::run_proxy(
+                port,
+                self.master_name.clone(),
+                self.state.clone(),
+                self.global_state.clone(),
+            ));
+        }
 
         if let Some(res) = tasks.join_next().await {
             error!(
@@ -155,18 +173,14 @@ impl MasterMonitor {
         }
     }
 
-    /// Establishes a connection to the master and subscribes to Warden channels.
+    /// Establishes a connection to the master and subscribes to the hello channel.
     async fn connect_and_subscribe(&self, master_addr: SocketAddr) -> anyhow::Result<WardenClient> {
         let mut client = WardenClient::connect(master_addr).await?;
         let cmd = RespFrame::Array(vec![
             RespFrame::BulkString("SUBSCRIBE".into()),
             RespFrame::BulkString(HELLO_CHANNEL.into()),
-            RespFrame::BulkString(VOTES_CHANNEL.into()),
         ]);
-
-        for _ in 0..3 {
-            client.send_and_receive(cmd.clone()).await?;
-        }
+        client.send_and_receive(cmd).await?;
         Ok(client)
     }
 
@@ -197,11 +211,8 @@ impl MasterMonitor {
 
     /// Dispatches a Pub/Sub message to the appropriate handler.
     async fn process_management_message(&self, channel: &Bytes, payload: &Bytes) {
-        let channel_str = String::from_utf8_lossy(channel);
-        if channel_str == HELLO_CHANNEL {
-            self.process_hello_message(payload);
-        } else if channel_str == VOTES_CHANNEL {
-            self.process_vote_message(payload).await;
+        if channel.as_ref() == HELLO_CHANNEL.as_bytes() {
+            self.process_hello_message(payload).await;
         }
     }
 
@@ -220,13 +231,14 @@ impl MasterMonitor {
 
     /// Constructs and publishes this Warden's `HelloMessage`.
     async fn publish_hello_message(&self) -> anyhow::Result<()> {
-        let (my_epoch, my_runid, master_name, master_addr) = {
+        let (my_epoch, my_runid, master_name, master_addr, master_epoch) = {
             let state = self.state.lock();
             (
                 state.config_epoch,
                 self.global_state.my_run_id.clone(),
                 state.config.name.clone(),
                 state.addr,
+                state.master_epoch,
             )
         };
         let hello_payload = HelloMessage {
@@ -235,6 +247,7 @@ impl MasterMonitor {
             epoch: my_epoch,
             master_name,
             master_addr,
+            master_epoch,
         };
         let message = serde_json::to_string(&hello_payload)?;
         self.publish_message(HELLO_CHANNEL.to_string(), message)
@@ -242,7 +255,7 @@ impl MasterMonitor {
     }
 
     /// Processes a `HelloMessage` received from a peer Warden.
-    fn process_hello_message(&self, payload: &Bytes) {
+    async fn process_hello_message(&self, payload: &Bytes) {
         let Ok(hello) = serde_json::from_slice::<HelloMessage>(payload) else {
             return;
         };
@@ -251,18 +264,78 @@ impl MasterMonitor {
             return;
         }
 
+        if self.record_hello(&hello)
+            && let Err(e) = self.global_state.save_config().await
+        {
+            warn!(
+                "Failed to persist the state learned from Warden {}: {}",
+                hello.run_id, e
+            );
+        }
+    }
+
+    /// Records a peer's hello and adopts its newer epochs. Returns `true` if the
+    /// persisted state changed.
+    fn record_hello(&self, hello: &HelloMessage) -> bool {
         let mut state = self.state.lock();
+        // A newly discovered Warden, or one that moved, is persisted so that it stays
+        // counted towards the majority across restarts.
+        let mut changed = state
+            .peers
+            .get(&hello.run_id)
+            .is_none_or(|peer| peer.addr != hello.addr);
         let peer_entry =
             state
                 .peers
                 .entry(hello.run_id.clone())
                 .or_insert_with(|| WardenPeerState {
-                    run_id: hello.run_id,
+                    run_id: hello.run_id.clone(),
                     addr: hello.addr,
                     last_hello_received: Instant::now(),
                 });
         peer_entry.last_hello_received = Instant::now();
-        debug!("Received hello from peer warden {}", peer_entry.run_id);
+        peer_entry.addr = hello.addr;
+        debug!("Received hello from peer warden {}", hello.run_id);
+
+        // A peer in a newer epoch makes any election of ours stale.
+        if hello.epoch > state.config_epoch {
+            state.config_epoch = hello.epoch;
+            if state.failover_state == FailoverState::Vote {
+                state.reset_failover_state();
+            }
+            changed = true;
+        }
+        // A peer that saw a later failover knows the current master.
+        if hello.master_epoch > state.master_epoch && state.failover_state == FailoverState::None {
+            if hello.master_addr != state.addr {
+                info!(
+                    "Master '{}' moved from {} to {} in epoch {}, as announced by Warden {}.",
+                    self.master_name,
+                    state.addr,
+                    hello.master_addr,
+                    hello.master_epoch,
+                    hello.run_id
+                );
+                self.global_state.publish_event(
+                    "+switch-master",
+                    format!(
+                        "{} {} {} {} {}",
+                        self.master_name,
+                        state.addr.ip(),
+                        state.addr.port(),
+                        hello.master_addr.ip(),
+                        hello.master_addr.port()
+                    ),
+                );
+                state.addr = hello.master_addr;
+                state.primary_state = InstanceState::new(hello.master_addr);
+                state.status = MasterStatus::Ok;
+                state.replicas.remove(&hello.master_addr);
+            }
+            state.master_epoch = hello.master_epoch;
+            changed = true;
+        }
+        changed
     }
 
     /// Checks if the master is subjectively down (SDOWN).
@@ -291,6 +364,8 @@ impl MasterMonitor {
                     state.config.name, state.addr
                 );
                 state.status = MasterStatus::Sdown;
+                self.global_state
+                    .publish_event("+sdown", state.event_details());
             }
         } else {
             let mut state = self.state.lock();
@@ -300,6 +375,14 @@ impl MasterMonitor {
                     state.config.name, master_addr
                 );
                 state.primary_state.down_since = None;
+                if state.status == MasterStatus::Odown {
+                    self.global_state
+                        .publish_event("-odown", state.event_details());
+                }
+                if state.status != MasterStatus::Ok {
+                    self.global_state
+                        .publish_event("-sdown", state.event_details());
+                }
                 state.status = MasterStatus::Ok;
                 state.reset_failover_state();
             }
@@ -342,7 +425,14 @@ impl MasterMonitor {
 
     /// Checks if a post-failover reconfiguration was interrupted and resumes it if necessary.
     async fn resume_interrupted_reconfiguration(&self) {
-        let (is_pending, new_master_addr, new_master_runid, old_master_runid, failover_timeout) = {
+        let (
+            is_pending,
+            new_master_addr,
+            new_master_runid,
+            old_master_runid,
+            failover_timeout,
+            epoch,
+        ) = {
             let state = self.state.lock();
             if state.failover_state == FailoverState::None
                 && !state.replicas_pending_reconfiguration.is_empty()
@@ -353,6 +443,7 @@ impl MasterMonitor {
                     state.run_id.clone(),
                     state.primary_state.run_id.clone(),
                     state.config.failover_timeout,
+                    state.config_epoch,
                 )
             } else {
                 (
@@ -361,6 +452,7 @@ impl MasterMonitor {
                     String::new(),
                     String::new(),
                     Duration::default(),
+                    0,
                 )
             }
         };
@@ -376,6 +468,7 @@ impl MasterMonitor {
                 new_master_runid,
                 old_master_runid,
                 failover_timeout,
+                epoch,
             ));
         }
     }
@@ -383,13 +476,11 @@ impl MasterMonitor {
     /// Checks if a failover leader election should be started.
     async fn check_failover_status(&self) {
         let (should_check_election, master_down, quorum) = {
-            let mut state = self.state.lock();
-            let hello_timeout = state.config.hello_interval * 5;
-            state
-                .peers
-                .retain(|_, peer| peer.last_hello_received.elapsed() < hello_timeout);
+            let state = self.state.lock();
             (
-                state.status == MasterStatus::Sdown && state.failover_state == FailoverState::None,
+                state.status != MasterStatus::Ok
+                    && state.failover_state == FailoverState::None
+                    && Instant::now() >= state.next_election_time,
                 state.primary_state.down_since.is_some(),
                 state.config.quorum,
             )
@@ -399,18 +490,18 @@ impl MasterMonitor {
             return;
         }
 
-        let can_reach_quorum = {
+        let (can_reach_quorum, total_wardens_seen) = {
             let state = self.state.lock();
             let total_wardens_seen = state.peers.len() + 1;
 
             if total_wardens_seen < quorum {
                 warn!(
-                    "Master '{}' is SDOWN, but this Warden can only see {}/{} required peers. Deferring failover election.",
+                    "Master '{}' is SDOWN, but this Warden only knows {}/{} required Wardens. Deferring failover election.",
                     self.master_name, total_wardens_seen, quorum
                 );
-                false
+                (false, total_wardens_seen)
             } else {
-                true
+                (true, total_wardens_seen)
             }
         };
 
@@ -418,135 +509,191 @@ impl MasterMonitor {
             return;
         }
 
-        let mut state = self.state.lock();
-        if state.status != MasterStatus::Sdown || state.failover_state != FailoverState::None {
-            return;
-        }
-
-        info!(
-            "Master '{}' is subjectively down (SDOWN). Starting leader election.",
-            state.config.name
-        );
-        state.failover_state = FailoverState::Vote;
-        state.config_epoch += 1;
-        state.last_voted_epoch = state.config_epoch;
-        state.votes.clear();
-        state
-            .votes
-            .insert(self.global_state.my_run_id.clone(), Instant::now());
-        let vote_req_msg = format!(
-            "VOTE-REQUEST:{}:{}:{}",
-            self.master_name, self.global_state.my_run_id, state.config_epoch
-        );
-        drop(state);
-
-        let self_clone = self.clone();
-        tokio::spawn(async move {
-            if let Err(e) = self_clone
-                .publish_message(VOTES_CHANNEL.to_string(), vote_req_msg)
-                .await
-            {
-                warn!("Failed to broadcast VOTE-REQUEST: {}", e);
+        let (epoch, master_addr, peers) = {
+            let mut state = self.state.lock();
+            if state.status == MasterStatus::Ok || state.failover_state != FailoverState::None {
+                return;
+            }
+            // Enough Wardens are reachable to agree on the failure.
+            if state.status == MasterStatus::Sdown {
+                state.status = MasterStatus::Odown;
+                self.global_state.publish_event(
+                    "+odown",
+                    format!(
+                        "{} #quorum {}/{}",
+                        state.event_details(),
+                        total_wardens_seen,
+                        quorum
+                    ),
+                );
             }
-        });
-    }
 
-    /// Checks the results of an ongoing leader election.
-    async fn check_election_status(&self) {
-        let (in_vote_state, quorum, failover_timeout) = {
-            let state = self.state.lock();
-            (
-                state.failover_state == FailoverState::Vote,
-                state.config.quorum,
-                state.config.failover_timeout,
-            )
+            // Become a candidate in a new epoch, voting for ourselves first.
+            let Some(epoch) = state.config_epoch.checked_add(1) else {
+                warn!(
+                    "The epoch of master '{}' cannot be raised any further. Not starting an election.",
+                    state.config.name
+                );
+                return;
+            };
+            info!(
+                "Master '{}' is down. Starting leader election for epoch {}.",
+                state.config.name, epoch
+            );
+            state.record_vote(self.global_state.my_run_id.clone(), epoch);
+            state.failover_state = FailoverState::Vote;
+            state.failover_start_time = Some(Instant::now());
+            state.votes.clear();
+            state
+                .votes
+                .insert(self.global_state.my_run_id.clone(), Instant::now());
+            self.global_state
+                .publish_event("+new-epoch", format!("{} {}", state.event_details(), epoch));
+            let peers: Vec<(String, SocketAddr)> = state
+                .peers
+                .values()
+                .map(|peer| (peer.run_id.clone(), peer.addr))
+                .collect();
+            (epoch, state.addr, peers)
         };
 
-        if !in_vote_state {
+        // Our own vote must be on disk before we ask for others.
+        if let Err(e) = self.global_state.save_config().await {
+            warn!(
+                "Failed to persist the vote for epoch {}: {}. Aborting election.",
+                epoch, e
+            );
+            self.state.lock().reset_failover_state();
             return;
         }
 
+        for (peer_id, peer_addr) in peers {
+            tokio::spawn(
+                self.clone()
+                    .request_vote(peer_id, peer_addr, master_addr, epoch),
+            );
+        }
+    }
+
+    /// Asks a peer Warden for its vote in `epoch`, and counts the vote if granted.
+    async fn request_vote(
+        self,
+        peer_id: String,
+        peer_addr: SocketAddr,
+        master_addr: SocketAddr,
+        epoch: u64,
+    ) {
+        let my_run_id = &self.global_state.my_run_id;
+        let reply = async {
+            let mut client = WardenClient::connect(peer_addr).await?;
+            if let Some(password) = &self.global_state.password {
+                client.authenticate(password).await?;
+            }
+            client.request_vote(master_addr, epoch, my_run_id).await
+        }
+        .await;
+        let (leader, leader_epoch) = match reply {
+            Ok(reply) => reply,
+            Err(e) => {
+                debug!("Vote request to Warden {} failed: {}", peer_id, e);
+                return;
+            }
+        };
+
         let mut state = self.state.lock();
-        if state.votes.len() >= quorum {
+        if !state.is_plausible_epoch(leader_epoch) {
+            warn!(
+                "Ignoring epoch {} from Warden {}, which is too far ahead of epoch {}.",
+                leader_epoch, peer_id, state.config_epoch
+            );
+        } else if leader_epoch > state.config_epoch {
+            // The peer has seen a newer epoch, so this election is stale.
             info!(
-                "Won leader election for master '{}' with {} votes (quorum {}).",
-                self.master_name,
-                state.votes.len(),
-                quorum
+                "Warden {} is in epoch {}, ahead of our election for master '{}'.",
+                peer_id, leader_epoch, self.master_name
             );
-            if state.last_failover_time.elapsed() < failover_timeout {
-                warn!(
-                    "Failover for '{}' already happened recently. Aborting and waiting for timeout.",
-                    self.master_name
-                );
+            state.config_epoch = leader_epoch;
+            if state.failover_state == FailoverState::Vote {
                 state.reset_failover_state();
-                return;
             }
-
-            state.failover_state = FailoverState::Start;
-            state.failover_start_time = Some(Instant::now());
+        } else if &leader == my_run_id
+            && leader_epoch == epoch
+            && state.config_epoch == epoch
+            && state.failover_state == FailoverState::Vote
+        {
             info!(
-                "Leader is starting failover process for master '{}'.",
-                self.master_name
+                "Received vote from {} for master {} in epoch {}",
+                peer_id, self.master_name, epoch
             );
-            tokio::spawn(failover::start_failover(self.state.clone()));
+            state.votes.insert(peer_id, Instant::now());
         }
     }
 
-    /// Processes a vote-related message from a peer Warden.
-    async fn process_vote_message(&self, payload: &Bytes) {
-        let payload_str = String::from_utf8_lossy(payload);
-        let msg_parts: Vec<&str> = payload_str.split(':').collect();
-        if msg_parts.len() < 4 {
+    /// Checks the results of an ongoing leader election.
+    async fn check_election_status(&self) {
+        let mut state = self.state.lock();
+        if state.failover_state != FailoverState::Vote {
             return;
         }
+        let required_votes = state.required_votes();
+        let failover_timeout = state.config.failover_timeout;
 
-        let msg_type = msg_parts[0];
-        let master_name = msg_parts[1];
-        let candidate_id = msg_parts[2];
-        let epoch: u64 = msg_parts[3].parse().unwrap_or(0);
-
-        if master_name != self.master_name {
+        if state.votes.len() < required_votes {
+            let election_timeout = failover_timeout.min(MAX_ELECTION_TIMEOUT);
+            if state
+                .failover_start_time
+                .is_some_and(|start| start.elapsed() > election_timeout)
+            {
+                warn!(
+                    "Lost leader election for master '{}' in epoch {} with {}/{} votes.",
+                    self.master_name,
+                    state.config_epoch,
+                    state.votes.len(),
+                    required_votes
+                );
+                self.global_state
+                    .publish_event("-failover-abort-not-elected", state.event_details());
+                state.reset_failover_state();
+                // Retry after a random delay, so that candidates of a split vote do
+                // not run into each other again.
+                state.next_election_time =
+                    Instant::now() + election_timeout.mul_f64(1.0 + rand::random::<f64>());
+            }
             return;
         }
 
-        if msg_type == "VOTE-REQUEST" {
-            let mut state = self.state.lock();
-            if epoch > state.last_voted_epoch {
-                info!(
-                    "Voting for {} in epoch {} for master {}",
-                    candidate_id, epoch, self.master_name
-                );
-                state.last_voted_epoch = epoch;
-
-                let my_run_id = self.global_state.my_run_id.clone();
-                let ack_msg = format!("VOTE-ACK:{master_name}:{my_run_id}:{candidate_id}:{epoch}");
-                drop(state);
-
-                let self_clone = self.clone();
-                tokio::spawn(async move {
-                    if let Err(e) = self_clone
-                        .publish_message(VOTES_CHANNEL.to_string(), ack_msg)
-                        .await
-                    {
-                        warn!("Failed to broadcast VOTE-ACK: {}", e);
-                    }
-                });
-            }
-        } else if msg_type == "VOTE-ACK"
-            && msg_parts.len() == 5
-            && msg_parts[3] == self.global_state.my_run_id
-        {
-            let voter_id = candidate_id;
-            let mut state = self.state.lock();
-            if state.failover_state == FailoverState::Vote && epoch == state.config_epoch {
-                info!(
-                    "Received vote from {} for master {}",
-                    voter_id, self.master_name
-                );
-                state.votes.insert(voter_id.to_string(), Instant::now());
-            }
+        info!(
+            "Won leader election for master '{}' in epoch {} with {} votes (required {}).",
+            self.master_name,
+            state.config_epoch,
+            state.votes.len(),
+            required_votes
+        );
+        self.global_state.publish_event(
+            "+elected-leader",
+            format!("{} {}", state.event_details(), state.config_epoch),
+        );
+        if state.last_failover_time.elapsed() < failover_timeout {
+            warn!(
+                "Failover for '{}' already happened recently. Aborting and waiting for timeout.",
+                self.master_name
+            );
+            state.reset_failover_state();
+            return;
         }
+
+        state.failover_state = FailoverState::Start;
+        state.failover_start_time = Some(Instant::now());
+        info!(
+            "Leader is starting failover process for master '{}'.",
+            self.master_name
+        );
+        self.global_state
+            .publish_event("+try-failover", state.event_details());
+        tokio::spawn(failover::start_failover(
+            self.state.clone(),
+            self.global_state.clone(),
+        ));
     }
 
     /// Periodically polls the master for its `INFO REPLICATION` output.
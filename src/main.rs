@@ -4,8 +4,10 @@
 
 use anyhow::Result;
 use spineldb::config::Config;
+use spineldb::core::persistence::{check, convert};
 use spineldb::server;
 use std::env;
+use std::path::Path;
 use std::sync::Arc;
 use tracing::{error, info};
 use tracing_subscriber::{filter::EnvFilter, prelude::*, reload};
@@ -32,6 +34,48 @@ async fn run_app() -> Result<()> {
         return Ok(());
     }
 
+    // Handle the offline file check subcommands. These never start a server.
+    if let Some(tool @ ("check-aof" | "check-spldb")) = args.get(1).map(String::as_str) {
+        let usage = match tool {
+            "check-aof" => "spineldb check-aof [--fix] <aof-dir|manifest|file>",
+            _ => "spineldb check-spldb <file>",
+        };
+        let fix = args[2..].iter().any(|arg| arg == "--fix");
+        let paths: Vec<&String> = args[2..].iter().filter(|arg| *arg != "--fix").collect();
+        if paths.len() != 1 || (fix && tool == "check-spldb") {
+            eprintln!("Usage: {usage}");
+            std::process::exit(1);
+        }
+
+        let path = Path::new(paths[0].as_str());
+        let result = if tool == "check-aof" {
+            check::run_check_aof(path, fix).await
+        } else {
+            check::run_check_spldb(path).await
+        };
+        match result {
+            Ok(true) => return Ok(()),
+            Ok(false) => std::process::exit(1),
+            Err(e) => {
+                eprintln!("Failed to check \"{}\": {e}", path.display());
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // Handle the offline snapshot conversion subcommand.
+    if args.get(1).is_some_and(|arg| arg == "convert") {
+        let [input, output] = &args[2..] else {
+            eprintln!("Usage: spineldb convert <input.rdb|input.spldb> <output>");
+            std::process::exit(1);
+        };
+        if let Err(e) = convert::run_convert(Path::new(input), Path::new(output)).await {
+            eprintln!("Failed to convert \"{input}\": {e}");
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     // Check if the --warden flag is present to start in Warden mode.
     if args.len() > 1 && args[1] == "--warden" {
         // --- Warden Mode ---
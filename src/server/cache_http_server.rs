@@ -0,0 +1,853 @@
+// src/server/cache_http_server.rs
+
+//! An optional HTTP front end for the Intelligent Cache.
+//!
+//! Each request is mapped to a cache key and answered from the cache with the same
+//! fresh, stale-while-revalidate and grace handling as `CACHE.GET`, including `304 Not
+//! Modified` replies to conditional requests. Misses are resolved through the matching
+//! policy's `url_template` and fetched with the stampede-protected fetch of
+//! `CACHE.FETCH`. On-disk bodies are streamed straight from their files.
+//!
+//! Single byte ranges are answered with `206 Partial Content`, unless an `If-Range`
+//! validator no longer matches. A range request that misses is fetched from the origin
+//! in fixed-size slices, each cached under its own key, so that seeking in a large
+//! object only transfers the slices it touches. Slices are checked against the
+//! validator of the first slice read, so that one response never mixes versions.
+
+use crate::config::CacheHttpConfig;
+use crate::core::commands::cache::Cache;
+use crate::core::commands::cache::cache_fetch::{CacheFetch, FetchOutcome};
+use crate::core::commands::cache::cache_get::{ByteRange, CacheGet};
+use crate::core::commands::cache::cache_proxy::CacheProxy;
+use crate::core::commands::cache::command::CacheSubcommand;
+use crate::core::database::ExecutionContext;
+use crate::core::handler::command_router::RouteResponse;
+use crate::core::state::ServerState;
+use crate::core::storage::cache_types::{CachePolicy, HttpMetadata};
+use crate::core::{Command, RespValue, SpinelDBError};
+use axum::Router;
+use axum::body::Body;
+use axum::extract::Request;
+use axum::http::header::{
+    ACCEPT_RANGES, AUTHORIZATION, CONNECTION, CONTENT_LENGTH, CONTENT_RANGE, ETAG, HOST, HeaderMap,
+    HeaderName, HeaderValue, IF_MODIFIED_SINCE, IF_NONE_MATCH, IF_RANGE, LAST_MODIFIED, RANGE,
+    TRANSFER_ENCODING,
+};
+use axum::http::{Method, StatusCode};
+use axum::response::{IntoResponse, Response};
+use bytes::Bytes;
+use futures::StreamExt;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::fs::File as TokioFile;
+use tokio::io::{AsyncReadExt, Take};
+use tokio::sync::{OwnedSemaphorePermit, broadcast};
+use tokio_util::io::ReaderStream;
+use tracing::{debug, error, info};
+use wildmatch::WildMatch;
+
+/// The response header reporting how a request was served.
+const X_CACHE: HeaderName = HeaderName::from_static("x-cache");
+
+/// Stored origin headers that are never replayed, because they describe the framing
+/// of the original response rather than its content.
+const FRAMING_HEADERS: [HeaderName; 3] = [CONTENT_LENGTH, TRANSFER_ENCODING, CONNECTION];
+
+/// The body of a response served by the front end.
+enum CachedBody {
+    InMemory(Bytes),
+    /// An on-disk body. The permit counts the open file against `on_disk_max_open_files`
+    /// until the body has been sent.
+    OnDisk {
+        file: Take<TokioFile>,
+        size: u64,
+        permit: OwnedSemaphorePermit,
+    },
+    /// A body assembled from cached slices, which are read as the body is sent.
+    Slices {
+        body: Body,
+        size: u64,
+    },
+}
+
+/// A response produced from the cache or from the origin.
+struct CachedResponse {
+    status: StatusCode,
+    body: CachedBody,
+    metadata: HttpMetadata,
+    /// The `Content-Range` of a `206` or `416` response.
+    content_range: Option<String>,
+    /// The value of the `X-Cache` header: `HIT`, `STALE`, `MISS` or `BYPASS`.
+    cache_status: &'static str,
+}
+
+impl CachedResponse {
+    /// Builds the HTTP response, answering `304 Not Modified` if the request's
+    /// validators match the cached content.
+    fn into_http(self, request_headers: &HeaderMap) -> Response {
+        let is_success = matches!(self.status, StatusCode::OK | StatusCode::PARTIAL_CONTENT);
+        let not_modified = is_success && is_not_modified(request_headers, &self.metadata);
+
+        let mut response = if not_modified {
+            StatusCode::NOT_MODIFIED.into_response()
+        } else {
+            match self.body {
+                CachedBody::InMemory(bytes) => (self.status, bytes).into_response(),
+                CachedBody::OnDisk { file, size, permit } => {
+                    // The permit moves into the stream and is released when it ends.
+                    let stream = ReaderStream::new(file).map(move |chunk| {
+                        let _ = &permit;
+                        chunk
+                    });
+                    let mut response = (self.status, Body::from_stream(stream)).into_response();
+                    response
+                        .headers_mut()
+                        .insert(CONTENT_LENGTH, HeaderValue::from(size));
+                    response
+                }
+                CachedBody::Slices { body, size } => {
+                    let mut response = (self.status, body).into_response();
+                    response
+                        .headers_mut()
+                        .insert(CONTENT_LENGTH, HeaderValue::from(size));
+                    response
+                }
+            }
+        };
+
+        let headers = response.headers_mut();
+        // Stored headers replace the defaults set above, such as the content type. The
+        // framing of the response is decided here, not by the origin.
+        let stored: Vec<_> = self
+            .metadata
+            .headers
+            .iter()
+            .filter_map(|(name, value)| {
+                Some((
+                    HeaderName::from_bytes(name).ok()?,
+                    HeaderValue::from_bytes(value).ok()?,
+                ))
+            })
+            .filter(|(name, _)| !FRAMING_HEADERS.contains(name))
+            .collect();
+        for (name, _) in &stored {
+            headers.remove(name);
+        }
+        for (name, value) in stored {
+            headers.append(name, value);
+        }
+        for (name, value) in self.metadata.encoding_headers() {
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(&name),
+                HeaderValue::from_bytes(&value),
+            ) {
+                headers.append(name, value);
+            }
+        }
+        headers.insert(X_CACHE, HeaderValue::from_static(self.cache_status));
+        if is_success {
+            headers.insert(ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+        }
+        if let Some(value) = self
+            .content_range
+            .and_then(|range| HeaderValue::from_str(&range).ok())
+        {
+            headers.insert(CONTENT_RANGE, value);
+        }
+        for (name, value) in [
+            (ETAG, &self.metadata.etag),
+            (LAST_MODIFIED, &self.metadata.last_modified),
+        ] {
+            if let Some(value) = value.as_ref().and_then(|v| HeaderValue::from_bytes(v).ok()) {
+                headers.insert(name, value);
+            }
+        }
+        response
+    }
+}
+
+/// Checks the request's `If-None-Match` or, failing that, `If-Modified-Since` header
+/// against the cached validators.
+fn is_not_modified(request_headers: &HeaderMap, metadata: &HttpMetadata) -> bool {
+    if let Some(if_none_match) = request_headers.get(IF_NONE_MATCH) {
+        let Some(etag) = &metadata.etag else {
+            return false;
+        };
+        // ETags are compared weakly, as required for `If-None-Match`.
+        let strip_weak = |tag: &[u8]| -> Vec<u8> {
+            let tag = tag.trim_ascii();
+            tag.strip_prefix(b"W/").unwrap_or(tag).to_vec()
+        };
+        let etag = strip_weak(etag);
+        return if_none_match
+            .as_bytes()
+            .split(|&b| b == b',')
+            .any(|tag| tag.trim_ascii() == b"*" || strip_weak(tag) == etag);
+    }
+
+    let parse_date = |value: &[u8]| {
+        std::str::from_utf8(value)
+            .ok()
+            .and_then(|s| httpdate::parse_http_date(s).ok())
+    };
+    let if_modified_since = request_headers
+        .get(IF_MODIFIED_SINCE)
+        .and_then(|v| parse_date(v.as_bytes()));
+    let last_modified = metadata.last_modified.as_deref().and_then(parse_date);
+    matches!((if_modified_since, last_modified), (Some(since), Some(modified)) if modified <= since)
+}
+
+/// Returns the strong validator identifying the version of a cached object: its ETag,
+/// unless it is weak, or else its `Last-Modified` date.
+fn strong_validator(metadata: &HttpMetadata) -> Option<Bytes> {
+    metadata
+        .etag
+        .as_ref()
+        .filter(|etag| !etag.starts_with(b"W/"))
+        .or(metadata.last_modified.as_ref())
+        .cloned()
+}
+
+/// Checks the request's `If-Range` header, if any, against the cached validators. A
+/// range whose `If-Range` does not match is ignored and the whole object is served.
+fn if_range_matches(request_headers: &HeaderMap, metadata: &HttpMetadata) -> bool {
+    let Some(if_range) = request_headers.get(IF_RANGE) else {
+        return true;
+    };
+    let if_range = if_range.as_bytes().trim_ascii();
+    if if_range.starts_with(b"\"") || if_range.starts_with(b"W/") {
+        // ETags are compared strongly, so a weak ETag never matches.
+        return !if_range.starts_with(b"W/")
+            && metadata
+                .etag
+                .as_deref()
+                .is_some_and(|etag| etag.trim_ascii() == if_range);
+    }
+    let parse_date = |value: &[u8]| {
+        std::str::from_utf8(value)
+            .ok()
+            .and_then(|s| httpdate::parse_http_date(s).ok())
+    };
+    matches!(
+        (parse_date(if_range), metadata.last_modified.as_deref().and_then(parse_date)),
+        (Some(date), Some(modified)) if date == modified
+    )
+}
+
+/// Parses a `Range` header holding a single byte range. Other ranges, including
+/// multiple ranges, are ignored and answered with the whole body.
+fn parse_range(request_headers: &HeaderMap) -> Option<ByteRange> {
+    let spec = request_headers
+        .get(RANGE)?
+        .to_str()
+        .ok()?
+        .trim()
+        .strip_prefix("bytes=")?;
+    let (start, end) = spec.split_once('-')?;
+    let (start, end) = (start.trim(), end.trim());
+    if start.is_empty() {
+        // A suffix range selects the last `n` bytes.
+        let suffix: i64 = end.parse().ok().filter(|&n| n > 0)?;
+        return Some(ByteRange {
+            start: -suffix,
+            end: -1,
+        });
+    }
+    let start: i64 = start.parse().ok()?;
+    let end: i64 = if end.is_empty() {
+        -1
+    } else {
+        end.parse().ok()?
+    };
+    (end == -1 || end >= start).then_some(ByteRange { start, end })
+}
+
+/// Returns the key a slice of the object at `key` is cached under.
+fn slice_key(key: &Bytes, index: u64) -> Bytes {
+    Bytes::from([key.as_ref(), format!(":slice:{index}").as_bytes()].concat())
+}
+
+/// Reads the full object size from a stored `Content-Range` header.
+fn content_range_total(metadata: &HttpMetadata) -> Option<u64> {
+    let (_, value) = metadata
+        .headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(b"content-range"))?;
+    let (_, total) = std::str::from_utf8(value).ok()?.rsplit_once('/')?;
+    total.trim().parse().ok()
+}
+
+/// Maps a request to its cache key.
+fn request_key(config: &CacheHttpConfig, request: &Request) -> Bytes {
+    let mut key = config.key_prefix.clone();
+    if config.include_host
+        && let Some(host) = request
+            .headers()
+            .get(HOST)
+            .and_then(|h| h.to_str().ok())
+            .or_else(|| request.uri().host())
+    {
+        key.push_str(host);
+    }
+    key.push_str(
+        request
+            .uri()
+            .path_and_query()
+            .map_or("/", |path_and_query| path_and_query.as_str()),
+    );
+    Bytes::from(key)
+}
+
+/// Builds an execution context holding the shard lock of `cmd`'s key.
+async fn get_context<'a>(
+    state: &Arc<ServerState>,
+    db: &'a crate::core::database::Db,
+    cmd: &CacheGet,
+) -> ExecutionContext<'a> {
+    let command = Command::Cache(Cache {
+        subcommand: CacheSubcommand::Get(cmd.clone()),
+    });
+    ExecutionContext {
+        state: state.clone(),
+        locks: db.determine_locks_for_command(&command).await,
+        db,
+        command: Some(command),
+        session_id: 0,
+        authenticated_user: None,
+        write_guard: None,
+    }
+}
+
+/// Looks the request up in the cache. Stale content is served and revalidated exactly
+/// as `CACHE.GET` does. Returns `None` on a miss.
+async fn lookup(
+    state: &Arc<ServerState>,
+    db_index: usize,
+    key: &Bytes,
+    headers: &[(Bytes, Bytes)],
+    range: Option<ByteRange>,
+) -> Result<Option<CachedResponse>, SpinelDBError> {
+    let get_cmd = CacheGet {
+        key: key.clone(),
+        headers: Some(headers.to_vec()),
+        range,
+        raw: true,
+        ..Default::default()
+    };
+    let db = state
+        .get_db(db_index)
+        .ok_or_else(|| SpinelDBError::Internal("Cache database not found".into()))?;
+    let mut ctx = get_context(state, &db, &get_cmd).await;
+
+    // Bodies come without a status; it is read from the stored metadata below.
+    let (status, body) = match get_cmd.execute_and_stream(&mut ctx).await? {
+        RouteResponse::NoOp => return Ok(None),
+        RouteResponse::Single(RespValue::BulkString(bytes)) => (None, CachedBody::InMemory(bytes)),
+        // Negatively cached responses keep the `[status, headers, body]` form.
+        RouteResponse::Single(RespValue::Array(parts)) => match parts.as_slice() {
+            [RespValue::Integer(status), _, RespValue::BulkString(body)] => {
+                (Some(*status as u16), CachedBody::InMemory(body.clone()))
+            }
+            _ => {
+                return Err(SpinelDBError::Internal(
+                    "Unexpected cached response layout".into(),
+                ));
+            }
+        },
+        RouteResponse::StreamBody { file, _permit, .. } => {
+            let size = file.limit();
+            (
+                None,
+                CachedBody::OnDisk {
+                    file,
+                    size,
+                    permit: _permit,
+                },
+            )
+        }
+        _ => {
+            return Err(SpinelDBError::Internal(
+                "Unexpected response from stream-aware GET logic".into(),
+            ));
+        }
+    };
+
+    let (metadata, is_stale) = get_cmd.variant_metadata(&mut ctx)?.unwrap_or_default();
+    // Negatively cached responses are served whole.
+    let (status, content_range) = match (status, range) {
+        (Some(status), _) => (status, None),
+        (None, Some(range)) => {
+            let len = get_cmd.variant_len(&mut ctx)?.unwrap_or_default();
+            let status = match range.resolve(len) {
+                Some(_) => StatusCode::PARTIAL_CONTENT,
+                None => StatusCode::RANGE_NOT_SATISFIABLE,
+            };
+            (status.as_u16(), Some(range.content_range(len)))
+        }
+        (None, None) => (metadata.status_code(), None),
+    };
+    Ok(Some(CachedResponse {
+        status: StatusCode::from_u16(status).unwrap_or(StatusCode::BAD_GATEWAY),
+        body,
+        metadata,
+        content_range,
+        cache_status: if is_stale { "STALE" } else { "HIT" },
+    }))
+}
+
+/// Fetches a missed request from the origin of its policy. Concurrent misses for the
+/// same key share one origin request.
+async fn fetch(
+    state: &Arc<ServerState>,
+    db_index: usize,
+    key: &Bytes,
+    headers: &[(Bytes, Bytes)],
+    policies: &[CachePolicy],
+    bypass_store: bool,
+) -> Result<CachedResponse, SpinelDBError> {
+    let proxy_cmd = CacheProxy {
+        key: key.clone(),
+        headers: Some(headers.to_vec()),
+        ..Default::default()
+    };
+    let (fetch_cmd, policy_name) = proxy_cmd.resolve_fetch(policies)?;
+    fetch_resolved(state, db_index, &fetch_cmd, &policy_name, bypass_store).await
+}
+
+/// Runs the origin fetch resolved for a missed request.
+async fn fetch_resolved(
+    state: &Arc<ServerState>,
+    db_index: usize,
+    fetch_cmd: &CacheFetch,
+    policy_name: &str,
+    bypass_store: bool,
+) -> Result<CachedResponse, SpinelDBError> {
+    // Stores are admitted and propagated as if the fetch had gone through the command
+    // router. Slices are not propagated, since `CACHE.FETCH` cannot express their range.
+    let _admission = if bypass_store {
+        None
+    } else {
+        fetch_cmd.admit_store(state, db_index).await?
+    };
+    let (target_ip, domain) = fetch_cmd.resolve_origin(state).await?;
+
+    crate::core::metrics::CACHE_MISSES_TOTAL
+        .with_label_values(&[policy_name])
+        .inc();
+
+    let outcome = if bypass_store {
+        fetch_cmd
+            .fetch_from_origin(state, db_index, true, target_ip, domain)
+            .await?
+            .0
+    } else {
+        fetch_cmd
+            .fetch_shared(
+                state,
+                db_index,
+                target_ip,
+                domain,
+                fetch_cmd.range.is_none(),
+            )
+            .await?
+    };
+
+    // Range fetches only succeed with a partial response.
+    let ok = if fetch_cmd.range.is_some() {
+        StatusCode::PARTIAL_CONTENT
+    } else {
+        StatusCode::OK
+    };
+    let (status, body) = match outcome {
+        FetchOutcome::InMemory(bytes) => (ok, CachedBody::InMemory(bytes)),
+        FetchOutcome::OnDisk { path, size } => {
+            let permit = state
+                .cache
+                .on_disk_read_semaphore
+                .clone()
+                .acquire_owned()
+                .await
+                .map_err(|e| {
+                    SpinelDBError::Internal(format!("Failed to acquire semaphore permit: {e}"))
+                })?;
+            let file = TokioFile::open(&path).await.map_err(|e| {
+                SpinelDBError::Internal(format!("Failed to open cache file for streaming: {e}"))
+            })?;
+            let file = file.take(size);
+            (ok, CachedBody::OnDisk { file, size, permit })
+        }
+        FetchOutcome::Negative { status, body } => (
+            StatusCode::from_u16(status).unwrap_or(StatusCode::BAD_GATEWAY),
+            CachedBody::InMemory(body.unwrap_or_default()),
+        ),
+    };
+
+    let metadata = if bypass_store {
+        HttpMetadata::default()
+    } else {
+        fetch_cmd.stored_metadata(state, db_index).await?
+    };
+    Ok(CachedResponse {
+        status,
+        body,
+        metadata,
+        content_range: None,
+        cache_status: if bypass_store { "BYPASS" } else { "MISS" },
+    })
+}
+
+/// A slice of an object, cached under its own key.
+struct Slice {
+    data: Bytes,
+    /// The size of the whole object.
+    total: u64,
+    metadata: HttpMetadata,
+    cache_status: &'static str,
+}
+
+/// An object whose ranges are served from slices cached under their own keys.
+#[derive(Clone)]
+struct SlicedObject {
+    state: Arc<ServerState>,
+    db_index: usize,
+    key: Bytes,
+    headers: Vec<(Bytes, Bytes)>,
+    policies: Vec<CachePolicy>,
+    slice_size: u64,
+}
+
+impl SlicedObject {
+    /// Reads slice `index` from the cache, fetching it from the origin with a range
+    /// request if it is missing. If `validator` is given, a cached slice of another
+    /// version is fetched again, with `validator` as `If-Range`.
+    ///
+    /// Returns `None` if the origin answered with the whole object: it does not
+    /// support range requests, or the object no longer matches `validator`.
+    async fn read_slice(
+        &self,
+        index: u64,
+        validator: Option<&Bytes>,
+    ) -> Result<Option<Slice>, SpinelDBError> {
+        let slice_key = slice_key(&self.key, index);
+        let is_current = |cached: &CachedResponse| {
+            validator.is_none_or(|v| strong_validator(&cached.metadata).as_ref() == Some(v))
+        };
+        let cached = match lookup(&self.state, self.db_index, &slice_key, &self.headers, None)
+            .await?
+        {
+            Some(cached) if is_current(&cached) => cached,
+            _ => {
+                // The origin URL is resolved from the object's key, not the slice's.
+                let proxy_cmd = CacheProxy {
+                    key: self.key.clone(),
+                    headers: Some(self.headers.clone()),
+                    ..Default::default()
+                };
+                let (mut fetch_cmd, policy_name) = proxy_cmd.resolve_fetch(&self.policies)?;
+                fetch_cmd.key = slice_key;
+                fetch_cmd.range =
+                    Some((index * self.slice_size, (index + 1) * self.slice_size - 1));
+                fetch_cmd.if_range = validator.cloned();
+                fetch_resolved(&self.state, self.db_index, &fetch_cmd, &policy_name, false).await?
+            }
+        };
+
+        if cached.status == StatusCode::OK {
+            return Ok(None);
+        }
+        if cached.status != StatusCode::PARTIAL_CONTENT {
+            return Err(SpinelDBError::InvalidState(format!(
+                "Origin answered a slice request with status {}",
+                cached.status
+            )));
+        }
+        if !is_current(&cached) {
+            return Err(SpinelDBError::InvalidState(format!(
+                "Slice {index} is from another version of the object"
+            )));
+        }
+        let total = content_range_total(&cached.metadata).ok_or_else(|| {
+            SpinelDBError::InvalidState("Cached slice has no Content-Range".into())
+        })?;
+        let data = match cached.body {
+            CachedBody::InMemory(bytes) => bytes,
+            CachedBody::OnDisk { mut file, .. } => {
+                let mut buffer = Vec::new();
+                file.read_to_end(&mut buffer).await?;
+                Bytes::from(buffer)
+            }
+            CachedBody::Slices { .. } => {
+                return Err(SpinelDBError::Internal("Nested slice body".into()));
+            }
+        };
+        Ok(Some(Slice {
+            data,
+            total,
+            metadata: cached.metadata,
+            cache_status: cached.cache_status,
+        }))
+    }
+
+    /// Reads a slice after the first, which must still carry `validator`.
+    async fn read_next_slice(&self, index: u64, validator: &Bytes) -> Result<Slice, SpinelDBError> {
+        self.read_slice(index, Some(validator))
+            .await?
+            .ok_or_else(|| {
+                SpinelDBError::InvalidState("The object changed while slicing it".into())
+            })
+    }
+
+    /// Answers a range request that missed from the cached slices. Only the slices
+    /// covering the range are read, the first before the response is sent and the
+    /// rest while its body is streamed. Every slice must carry the strong validator
+    /// of the first one read; if a later slice no longer does, the body is cut short.
+    ///
+    /// Returns `None` if the object cannot be sliced: the origin answered the first
+    /// slice request with the whole object, or the object has no strong validator.
+    async fn fetch_range(self, range: ByteRange) -> Result<Option<CachedResponse>, SpinelDBError> {
+        let slice_size = self.slice_size;
+        // The object's size is learnt from its first slice read. Suffix ranges need it
+        // before the slice holding their start is known.
+        let guess = if range.start >= 0 {
+            range.start as u64 / slice_size
+        } else {
+            0
+        };
+        let Some(mut first) = self.read_slice(guess, None).await? else {
+            return Ok(None);
+        };
+        let Some(validator) = strong_validator(&first.metadata) else {
+            return Ok(None);
+        };
+        let content_range = Some(range.content_range(first.total));
+        let Some((start, end)) = range.resolve(first.total) else {
+            return Ok(Some(CachedResponse {
+                status: StatusCode::RANGE_NOT_SATISFIABLE,
+                body: CachedBody::InMemory(Bytes::new()),
+                metadata: first.metadata,
+                content_range,
+                cache_status: first.cache_status,
+            }));
+        };
+        let first_index = start / slice_size;
+        if first_index != guess {
+            first = self.read_next_slice(first_index, &validator).await?;
+        }
+
+        // Cuts the part of slice `index` that lies within the range.
+        let cut = move |index: u64, data: Bytes| {
+            let offset = index * slice_size;
+            let from = start.saturating_sub(offset).min(data.len() as u64);
+            let to = (end + 1 - offset).min(data.len() as u64).max(from);
+            data.slice(from as usize..to as usize)
+        };
+        let head = cut(first_index, first.data);
+        let rest = futures::stream::iter(first_index + 1..=end / slice_size).then(move |index| {
+            let (object, validator) = (self.clone(), validator.clone());
+            async move {
+                object
+                    .read_next_slice(index, &validator)
+                    .await
+                    .map(|slice| cut(index, slice.data))
+                    .map_err(std::io::Error::other)
+            }
+        });
+        let body = Body::from_stream(futures::stream::once(async move { Ok(head) }).chain(rest));
+
+        Ok(Some(CachedResponse {
+            status: StatusCode::PARTIAL_CONTENT,
+            body: CachedBody::Slices {
+                body,
+                size: end - start + 1,
+            },
+            metadata: first.metadata,
+            content_range,
+            cache_status: first.cache_status,
+        }))
+    }
+}
+
+/// Answers a range request that missed. The range is served from slices of the
+/// object if slicing is enabled and the object can be sliced. Otherwise the whole
+/// object is fetched and the range is served from it.
+async fn fetch_missed_range(
+    state: &Arc<ServerState>,
+    db_index: usize,
+    key: &Bytes,
+    headers: &[(Bytes, Bytes)],
+    policies: &[CachePolicy],
+    range: ByteRange,
+) -> Result<CachedResponse, SpinelDBError> {
+    let slice_size = state.config.lock().await.cache.http.slice_size_bytes;
+    if slice_size > 0 {
+        let object = SlicedObject {
+            state: state.clone(),
+            db_index,
+            key: key.clone(),
+            headers: headers.to_vec(),
+            policies: policies.to_vec(),
+            slice_size,
+        };
+        match object.fetch_range(range).await? {
+            Some(cached) => return Ok(cached),
+            None => debug!(
+                "Key '{}' cannot be sliced, fetching the whole object",
+                String::from_utf8_lossy(key)
+            ),
+        }
+    }
+
+    let fetched = fetch(state, db_index, key, headers, policies, false).await?;
+    if fetched.status != StatusCode::OK {
+        return Ok(fetched);
+    }
+    // The object has been stored, so the range is read from the cache.
+    Ok(
+        match lookup(state, db_index, key, headers, Some(range)).await? {
+            Some(cached) => CachedResponse {
+                cache_status: fetched.cache_status,
+                ..cached
+            },
+            None => fetched,
+        },
+    )
+}
+
+/// Answers a request from the cache, fetching it from the origin on a miss.
+async fn lookup_or_fetch(
+    state: &Arc<ServerState>,
+    db_index: usize,
+    key: &Bytes,
+    headers: &[(Bytes, Bytes)],
+    policies: &[CachePolicy],
+    range: Option<ByteRange>,
+) -> Result<CachedResponse, SpinelDBError> {
+    if let Some(cached) = lookup(state, db_index, key, headers, range).await? {
+        return Ok(cached);
+    }
+    match range {
+        Some(range) => fetch_missed_range(state, db_index, key, headers, policies, range).await,
+        None => fetch(state, db_index, key, headers, policies, false).await,
+    }
+}
+
+/// Maps an error to a plain-text HTTP response.
+fn error_response(e: SpinelDBError) -> Response {
+    let status = match e {
+        SpinelDBError::InvalidRequest(_) => StatusCode::BAD_REQUEST,
+        SpinelDBError::OriginUnavailable(_)
+        | SpinelDBError::ReadOnly(_)
+        | SpinelDBError::MaxMemoryReached
+        | SpinelDBError::ClusterDown(_) => StatusCode::SERVICE_UNAVAILABLE,
+        // Another node owns the key's slot.
+        SpinelDBError::Moved { .. } | SpinelDBError::Ask { .. } => StatusCode::MISDIRECTED_REQUEST,
+        _ => StatusCode::BAD_GATEWAY,
+    };
+    (status, format!("{e}\n")).into_response()
+}
+
+/// Handles a single request to the cache front end.
+async fn cache_handler(state: Arc<ServerState>, request: Request) -> Response {
+    if request.method() != Method::GET && request.method() != Method::HEAD {
+        return (
+            StatusCode::METHOD_NOT_ALLOWED,
+            [(axum::http::header::ALLOW, "GET, HEAD")],
+        )
+            .into_response();
+    }
+
+    let (key, db_index) = {
+        let config = state.config.lock().await;
+        (
+            request_key(&config.cache.http, &request),
+            config.cache.http.db,
+        )
+    };
+    let key_str = String::from_utf8_lossy(&key).into_owned();
+    let policies = state.cache.policies.read().await.clone();
+    if !policies
+        .iter()
+        .any(|p| WildMatch::new(&p.key_pattern).matches(&key_str))
+    {
+        debug!("No cache policy matches HTTP request for key '{}'", key_str);
+        return (
+            StatusCode::NOT_FOUND,
+            "No cache policy matches this request\n",
+        )
+            .into_response();
+    }
+
+    let headers: Vec<(Bytes, Bytes)> = request
+        .headers()
+        .iter()
+        .map(|(name, value)| {
+            (
+                Bytes::copy_from_slice(name.as_str().as_bytes()),
+                Bytes::copy_from_slice(value.as_bytes()),
+            )
+        })
+        .collect();
+
+    // Authorized requests are private to their client, so they are neither served
+    // from nor stored in the cache. Their ranges are ignored.
+    let result = if request.headers().contains_key(AUTHORIZATION) {
+        fetch(&state, db_index, &key, &headers, &policies, true).await
+    } else {
+        let range = parse_range(request.headers());
+        match lookup_or_fetch(&state, db_index, &key, &headers, &policies, range).await {
+            Ok(cached)
+                if range.is_some()
+                    && matches!(
+                        cached.status,
+                        StatusCode::PARTIAL_CONTENT | StatusCode::RANGE_NOT_SATISFIABLE
+                    )
+                    && !if_range_matches(request.headers(), &cached.metadata) =>
+            {
+                lookup_or_fetch(&state, db_index, &key, &headers, &policies, None).await
+            }
+            result => result,
+        }
+    };
+
+    match result {
+        Ok(cached) => cached.into_http(request.headers()),
+        Err(e) => {
+            debug!("Cache HTTP request for key '{}' failed: {}", key_str, e);
+            error_response(e)
+        }
+    }
+}
+
+/// Runs the HTTP front end of the Intelligent Cache.
+pub async fn run_cache_http_server(
+    state: Arc<ServerState>,
+    mut shutdown_rx: broadcast::Receiver<()>,
+) {
+    let port = {
+        let config = state.config.lock().await;
+        config.cache.http.port
+    };
+
+    let app = Router::new().fallback(move |request: Request| cache_handler(state.clone(), request));
+
+    let addr = SocketAddr::from(([0, 0, 0, 0], port));
+    info!("Cache HTTP server listening on http://{}", addr);
+
+    let listener = match tokio::net::TcpListener::bind(addr).await {
+        Ok(l) => l,
+        Err(e) => {
+            error!("Failed to bind cache HTTP server on port {}: {}", port, e);
+            return;
+        }
+    };
+
+    if let Err(e) = axum::serve(listener, app)
+        .with_graceful_shutdown(async move {
+            shutdown_rx.recv().await.ok();
+            info!("Cache HTTP server shutting down.");
+        })
+        .await
+    {
+        error!("Cache HTTP server failed: {}", e);
+    }
+}
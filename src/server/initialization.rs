@@ -18,7 +18,7 @@ use tokio::net::TcpListener;
 use tokio::sync::broadcast;
 use tokio::task::JoinSet;
 use tokio_rustls::{TlsAcceptor, rustls};
-use tracing::{error, info, warn};
+use tracing::{info, warn};
 use tracing_subscriber::{filter::EnvFilter, reload};
 
 /// Initializes all server components before starting the main loop.
@@ -217,35 +217,6 @@ async fn load_persistence_data(server_state: &Arc<ServerState>) -> Result<()> {
         }
     }
 
-    // Check for leftover AOF temp files from a previous crashed rewrite.
-    let aof_path_str = &config.persistence.aof_path;
-    let aof_path = std::path::Path::new(aof_path_str);
-    if let Some(parent_dir) = aof_path.parent()
-        && parent_dir.exists()
-        && let Ok(entries) = std::fs::read_dir(parent_dir)
-    {
-        for entry in entries.flatten() {
-            if let Some(file_name) = entry.file_name().to_str()
-                && file_name.starts_with("temp-rewrite-")
-                && file_name.ends_with(
-                    aof_path
-                        .file_name()
-                        .unwrap_or_default()
-                        .to_str()
-                        .unwrap_or_default(),
-                )
-            {
-                let msg = format!(
-                    "FATAL: Found leftover AOF temp file '{}' from a previous crashed rewrite. Server is exiting to prevent data loss. Please manually inspect the files and restore the correct one by renaming it to '{}'.",
-                    entry.path().display(),
-                    aof_path.display()
-                );
-                error!("{}", msg);
-                return Err(anyhow!(msg));
-            }
-        }
-    }
-
     if config.persistence.aof_enabled {
         let aof_loader = AofLoader::new(config.persistence.clone());
         aof_loader.load_into(server_state).await?;
@@ -256,5 +227,16 @@ async fn load_persistence_data(server_state: &Arc<ServerState>) -> Result<()> {
         info!("No persistence method enabled. Starting with an empty state.");
     }
     info!("Persistence data loaded successfully.");
+
+    // Policies declared in the config file take precedence over stored ones.
+    for policy in &config.cache.policies {
+        server_state.cache.set_policy(policy.clone()).await;
+    }
+    if !config.cache.policies.is_empty() {
+        info!(
+            "Installed {} cache policies from the config file.",
+            config.cache.policies.len()
+        );
+    }
     Ok(())
 }
@@ -6,6 +6,7 @@ use std::sync::Arc;
 use tracing_subscriber::{filter::EnvFilter, reload};
 
 // Deklarasikan sub-modul dengan nama baru
+pub mod cache_http_server;
 mod connection_loop;
 mod context;
 mod initialization;
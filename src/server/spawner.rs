@@ -2,6 +2,7 @@
 
 //! Spawns all of the server's long-running background tasks.
 
+use super::cache_http_server;
 use super::context::ServerContext;
 use super::metrics_server;
 use crate::config::{AppendFsync, ReplicationConfig};
@@ -62,6 +63,17 @@ pub async fn spawn_all(ctx: &mut ServerContext) -> Result<()> {
         info!("Prometheus metrics server is disabled in the configuration.");
     }
 
+    // --- Cache HTTP Front End ---
+    if config_clone.cache.http.enabled {
+        let cache_http_state = server_state.clone();
+        let shutdown_rx_cache_http = shutdown_tx.subscribe();
+        background_tasks.spawn(async move {
+            cache_http_server::run_cache_http_server(cache_http_state, shutdown_rx_cache_http)
+                .await;
+            Ok(())
+        });
+    }
+
     // --- Core Maintenance Tasks ---
     if let ReplicationConfig::Primary(_) = &config_clone.replication {
         let ttl_manager = TtlManager::new(server_state.dbs.clone());
@@ -236,6 +248,7 @@ pub async fn spawn_all(ctx: &mut ServerContext) -> Result<()> {
     if config_clone.cluster.enabled {
         let state_clone = server_state.clone();
         let shutdown_rx_cluster = shutdown_tx.subscribe();
+        let bus_tls_acceptor = ctx.acceptor.clone();
         background_tasks.spawn(async move {
             let bus_port = {
                 let config_guard = state_clone.config.lock().await;
@@ -247,6 +260,7 @@ pub async fn spawn_all(ctx: &mut ServerContext) -> Result<()> {
             cluster::gossip::run(
                 state_clone,
                 bus_port,
+                bus_tls_acceptor,
                 shutdown_rx_cluster,
                 server_init.cluster_gossip_rx,
             )
@@ -0,0 +1,1143 @@
+// tests/integration/cache_http_test.rs
+
+//! Integration tests for the HTTP front end of the Intelligent Cache
+//! Tests: hits and misses, conditional requests, stampede protection, on-disk streaming,
+//! stored origin headers, range requests and sliced fetches, surrogate keys, origin
+//! concurrency limits and circuit breaking, content encoding negotiation, and the
+//! admission and propagation of stores
+
+use super::test_helpers::TestContext;
+use axum::Router;
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::routing::get;
+use bytes::Bytes;
+use spineldb::config::{Config, ReplicationConfig};
+use spineldb::core::commands::cache::command::{Cache, CacheSubcommand};
+use spineldb::core::events::UnitOfWork;
+use spineldb::core::persistence::spldb;
+use spineldb::core::protocol::RespFrame;
+use spineldb::core::{Command, RespValue};
+use spineldb::server::cache_http_server::run_cache_http_server;
+use std::collections::HashMap;
+use std::io::Read;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+use tokio::time::{Duration, sleep};
+
+/// The body served by the origin for `/large/{id}`, above the test's streaming threshold.
+const LARGE_BODY: &str = "a body that is larger than the streaming threshold of the test";
+
+/// The body served by the origin for `/media/{id}`, which supports range requests.
+const MEDIA_BODY: &str = "0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+/// The body served by the origin for `/text/{id}`, which is served encoded. It is
+/// below the test's streaming threshold, as only in-memory bodies are encoded.
+const TEXT_BODY: &str = "Lorem ipsum dolor sit amet";
+
+/// Counts origin requests per path.
+type Hits = Arc<Mutex<HashMap<String, usize>>>;
+
+fn free_port() -> u16 {
+    let probe = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    probe.local_addr().unwrap().port()
+}
+
+fn count(hits: &Hits, path: &str) -> usize {
+    hits.lock().unwrap().get(path).copied().unwrap_or(0)
+}
+
+/// Starts an origin that serves `/items/{id}` and `/large/{id}` slowly, so that
+/// concurrent misses overlap, `/media/{id}` with range support and an ETag holding
+/// the count of `version /media/{id}`, and `/tagged/{id}`
+/// with surrogate keys and a one-second `Surrogate-Control` max-age, and `/text/{id}`.
+/// Range requests are counted as `{path} {range}`. Returns its port and request counter.
+async fn spawn_origin() -> (u16, Hits) {
+    async fn item(Path(id): Path<String>, State(hits): State<Hits>) -> (HeaderMap, String) {
+        *hits
+            .lock()
+            .unwrap()
+            .entry(format!("/items/{id}"))
+            .or_default() += 1;
+        sleep(Duration::from_millis(200)).await;
+        let mut headers = HeaderMap::new();
+        headers.insert("etag", format!("\"v-{id}\"").parse().unwrap());
+        headers.insert(
+            "last-modified",
+            "Wed, 21 Oct 2015 07:28:00 GMT".parse().unwrap(),
+        );
+        headers.insert("access-control-allow-origin", "*".parse().unwrap());
+        headers.insert("x-origin-internal", "secret".parse().unwrap());
+        (headers, format!("item-{id}"))
+    }
+
+    async fn large(Path(id): Path<String>, State(hits): State<Hits>) -> &'static str {
+        *hits
+            .lock()
+            .unwrap()
+            .entry(format!("/large/{id}"))
+            .or_default() += 1;
+        LARGE_BODY
+    }
+
+    async fn media(
+        Path(id): Path<String>,
+        State(hits): State<Hits>,
+        request_headers: HeaderMap,
+    ) -> (StatusCode, HeaderMap, &'static str) {
+        let range = request_headers
+            .get("range")
+            .and_then(|value| value.to_str().ok()?.strip_prefix("bytes="))
+            .and_then(|spec| spec.split_once('-'))
+            .map(|(start, end)| {
+                let start: usize = start.parse().unwrap();
+                let end = end.parse::<usize>().unwrap().min(MEDIA_BODY.len() - 1);
+                (start, end)
+            });
+        let path = match range {
+            Some((start, end)) => format!("/media/{id} {start}-{end}"),
+            None => format!("/media/{id}"),
+        };
+        let etag = format!(
+            "\"m-{id}-{}\"",
+            count(&hits, &format!("version /media/{id}"))
+        );
+        *hits.lock().unwrap().entry(path).or_default() += 1;
+
+        let mut headers = HeaderMap::new();
+        headers.insert("content-type", "video/mp4".parse().unwrap());
+        headers.insert("etag", etag.parse().unwrap());
+        // A range of another version is answered with the whole object.
+        let range = range.filter(|_| {
+            request_headers
+                .get("if-range")
+                .is_none_or(|if_range| if_range.as_bytes() == etag.as_bytes())
+        });
+        match range {
+            Some((start, end)) => {
+                let content_range = format!("bytes {start}-{end}/{}", MEDIA_BODY.len());
+                headers.insert("content-range", content_range.parse().unwrap());
+                (
+                    StatusCode::PARTIAL_CONTENT,
+                    headers,
+                    &MEDIA_BODY[start..=end],
+                )
+            }
+            None => (StatusCode::OK, headers, MEDIA_BODY),
+        }
+    }
+
+    async fn tagged(Path(id): Path<String>, State(hits): State<Hits>) -> (HeaderMap, String) {
+        *hits
+            .lock()
+            .unwrap()
+            .entry(format!("/tagged/{id}"))
+            .or_default() += 1;
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "surrogate-key",
+            format!("product-{id} catalog").parse().unwrap(),
+        );
+        headers.insert("cache-tag", format!("brand-{id},catalog").parse().unwrap());
+        headers.insert("cache-control", "max-age=600".parse().unwrap());
+        let surrogate_control = if id == "short" {
+            "max-age=1"
+        } else {
+            "max-age=600"
+        };
+        headers.insert("surrogate-control", surrogate_control.parse().unwrap());
+        (headers, format!("tagged-{id}"))
+    }
+
+    async fn text(Path(id): Path<String>, State(hits): State<Hits>) -> (HeaderMap, &'static str) {
+        *hits
+            .lock()
+            .unwrap()
+            .entry(format!("/text/{id}"))
+            .or_default() += 1;
+        let mut headers = HeaderMap::new();
+        headers.insert("etag", format!("\"t-{id}\"").parse().unwrap());
+        headers.insert("content-type", "text/plain".parse().unwrap());
+        (headers, TEXT_BODY)
+    }
+
+    let hits = Hits::default();
+    let app = Router::new()
+        .route("/items/{id}", get(item))
+        .route("/large/{id}", get(large))
+        .route("/media/{id}", get(media))
+        .route("/tagged/{id}", get(tagged))
+        .route("/text/{id}", get(text))
+        .with_state(hits.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    (port, hits)
+}
+
+async fn set_policy(ctx: &TestContext, name: &str, pattern: &str, url: &str, extra: &[&str]) {
+    let mut args = vec![
+        RespFrame::BulkString(Bytes::from_static(b"CACHE")),
+        RespFrame::BulkString(Bytes::from_static(b"POLICY")),
+        RespFrame::BulkString(Bytes::from_static(b"SET")),
+        RespFrame::BulkString(Bytes::from(name.to_string())),
+        RespFrame::BulkString(Bytes::from(pattern.to_string())),
+        RespFrame::BulkString(Bytes::from(url.to_string())),
+    ];
+    args.extend(
+        extra
+            .iter()
+            .map(|arg| RespFrame::BulkString(Bytes::from(arg.to_string()))),
+    );
+    ctx.execute(Command::try_from(RespFrame::Array(args)).unwrap())
+        .await
+        .unwrap();
+}
+
+/// Starts a server with the cache front end and an origin with policies for
+/// `/items/*`, `/large/*`, `/media/*`, `/tagged/*` (with `SURROGATE_KEYS`) and `/text/*`
+/// (with `NEGOTIATE_ENCODING`). Returns the front end's base URL and the origin's counter.
+async fn setup(disk_dir: &std::path::Path) -> (TestContext, String, Hits, broadcast::Sender<()>) {
+    let (origin_port, hits) = spawn_origin().await;
+
+    let mut config = Config::default();
+    config.databases = 1;
+    config.persistence.aof_enabled = false;
+    config.persistence.spldb_enabled = false;
+    config.security.allow_private_fetch_ips = true;
+    config.cache.streaming_threshold_bytes = 32;
+    config.cache.on_disk_path = disk_dir.to_string_lossy().into_owned();
+    config.cache.http.enabled = true;
+    config.cache.http.port = free_port();
+    config.cache.http.slice_size_bytes = 16;
+    let http_port = config.cache.http.port;
+    let ctx = TestContext::with_config(config).await;
+
+    set_policy(
+        &ctx,
+        "items",
+        "/items/*",
+        &format!("http://127.0.0.1:{origin_port}/items/{{1}}"),
+        &["TTL", "60"],
+    )
+    .await;
+    set_policy(
+        &ctx,
+        "large",
+        "/large/*",
+        &format!("http://127.0.0.1:{origin_port}/large/{{1}}"),
+        &["TTL", "60"],
+    )
+    .await;
+    set_policy(
+        &ctx,
+        "media",
+        "/media/*",
+        &format!("http://127.0.0.1:{origin_port}/media/{{1}}"),
+        &["TTL", "60"],
+    )
+    .await;
+    set_policy(
+        &ctx,
+        "tagged",
+        "/tagged/*",
+        &format!("http://127.0.0.1:{origin_port}/tagged/{{1}}"),
+        &["TTL", "60", "SURROGATE_KEYS", "TAGS", "tagged"],
+    )
+    .await;
+    set_policy(
+        &ctx,
+        "text",
+        "/text/*",
+        &format!("http://127.0.0.1:{origin_port}/text/{{1}}"),
+        &["TTL", "60", "NEGOTIATE_ENCODING"],
+    )
+    .await;
+
+    let (shutdown_tx, shutdown_rx) = broadcast::channel(1);
+    tokio::spawn(run_cache_http_server(ctx.state.clone(), shutdown_rx));
+
+    let base = format!("http://127.0.0.1:{http_port}");
+    for _ in 0..50 {
+        if reqwest::get(&base).await.is_ok() {
+            break;
+        }
+        sleep(Duration::from_millis(20)).await;
+    }
+    (ctx, base, hits, shutdown_tx)
+}
+
+fn x_cache(response: &reqwest::Response) -> String {
+    response.headers()["x-cache"].to_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn test_cache_http_miss_then_hit() {
+    let dir = tempfile::tempdir().unwrap();
+    let (_ctx, base, hits, _shutdown) = setup(dir.path()).await;
+
+    let response = reqwest::get(format!("{base}/items/1")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(x_cache(&response), "MISS");
+    assert_eq!(response.headers()["etag"], "\"v-1\"");
+    assert_eq!(response.text().await.unwrap(), "item-1");
+
+    let response = reqwest::get(format!("{base}/items/1")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(x_cache(&response), "HIT");
+    assert_eq!(response.headers()["etag"], "\"v-1\"");
+    assert_eq!(
+        response.headers()["last-modified"],
+        "Wed, 21 Oct 2015 07:28:00 GMT"
+    );
+    assert_eq!(response.text().await.unwrap(), "item-1");
+
+    assert_eq!(count(&hits, "/items/1"), 1);
+}
+
+#[tokio::test]
+async fn test_cache_http_conditional_requests() {
+    let dir = tempfile::tempdir().unwrap();
+    let (_ctx, base, _hits, _shutdown) = setup(dir.path()).await;
+    let client = reqwest::Client::new();
+    let url = format!("{base}/items/2");
+    client.get(&url).send().await.unwrap();
+
+    let response = client
+        .get(&url)
+        .header("if-none-match", "\"other\", W/\"v-2\"")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(response.headers()["etag"], "\"v-2\"");
+    assert!(response.text().await.unwrap().is_empty());
+
+    let response = client
+        .get(&url)
+        .header("if-none-match", "\"other\"")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = client
+        .get(&url)
+        .header("if-modified-since", "Thu, 22 Oct 2015 07:28:00 GMT")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+    let response = client
+        .get(&url)
+        .header("if-modified-since", "Tue, 20 Oct 2015 07:28:00 GMT")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_cache_http_concurrent_misses_share_one_fetch() {
+    let dir = tempfile::tempdir().unwrap();
+    let (_ctx, base, hits, _shutdown) = setup(dir.path()).await;
+
+    let requests = (0..5).map(|_| {
+        let url = format!("{base}/items/3");
+        tokio::spawn(async move { reqwest::get(url).await.unwrap().text().await.unwrap() })
+    });
+    for body in futures::future::join_all(requests).await {
+        assert_eq!(body.unwrap(), "item-3");
+    }
+    assert_eq!(count(&hits, "/items/3"), 1);
+}
+
+#[tokio::test]
+async fn test_cache_http_streams_on_disk_bodies() {
+    let dir = tempfile::tempdir().unwrap();
+    let (_ctx, base, hits, _shutdown) = setup(dir.path()).await;
+
+    for expected in ["MISS", "HIT"] {
+        let response = reqwest::get(format!("{base}/large/1")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(x_cache(&response), expected);
+        assert_eq!(
+            response.headers()["content-length"],
+            LARGE_BODY.len().to_string().as_str()
+        );
+        assert_eq!(response.text().await.unwrap(), LARGE_BODY);
+    }
+    assert_eq!(count(&hits, "/large/1"), 1);
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+#[tokio::test]
+async fn test_cache_http_serves_stale_content_in_swr_window() {
+    let dir = tempfile::tempdir().unwrap();
+    let (ctx, base, hits, _shutdown) = setup(dir.path()).await;
+    let origin = reqwest::Url::parse(&ctx.state.cache.policies.read().await[0].url_template)
+        .unwrap()
+        .port()
+        .unwrap();
+    set_policy(
+        &ctx,
+        "items",
+        "/items/*",
+        &format!("http://127.0.0.1:{origin}/items/{{1}}"),
+        &["TTL", "1", "SWR", "60"],
+    )
+    .await;
+
+    reqwest::get(format!("{base}/items/4")).await.unwrap();
+    sleep(Duration::from_millis(1100)).await;
+
+    let response = reqwest::get(format!("{base}/items/4")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(x_cache(&response), "STALE");
+    assert_eq!(response.text().await.unwrap(), "item-4");
+
+    // The stale hit revalidates in the background.
+    sleep(Duration::from_millis(500)).await;
+    assert_eq!(count(&hits, "/items/4"), 2);
+}
+
+#[tokio::test]
+async fn test_cache_http_rejects_unmatched_requests() {
+    let dir = tempfile::tempdir().unwrap();
+    let (_ctx, base, hits, _shutdown) = setup(dir.path()).await;
+    let client = reqwest::Client::new();
+
+    let response = client.get(format!("{base}/other")).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = client.post(format!("{base}/items/5")).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+
+    // Authorized requests bypass the cache.
+    for _ in 0..2 {
+        let response = client
+            .get(format!("{base}/items/5"))
+            .header("authorization", "Bearer token")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(x_cache(&response), "BYPASS");
+    }
+    assert_eq!(count(&hits, "/items/5"), 2);
+}
+
+/// Runs `CACHE.GET` for `key` and returns the `[status, headers, body]` reply.
+async fn cache_get(ctx: &TestContext, key: &str) -> (i64, Vec<(String, String)>, Bytes) {
+    let command = Command::try_from(RespFrame::Array(vec![
+        RespFrame::BulkString(Bytes::from_static(b"CACHE")),
+        RespFrame::BulkString(Bytes::from_static(b"GET")),
+        RespFrame::BulkString(Bytes::from(key.to_string())),
+    ]))
+    .unwrap();
+    let RespValue::Array(parts) = ctx.execute(command).await.unwrap() else {
+        panic!("Expected [status, headers, body]");
+    };
+    let [
+        RespValue::Integer(status),
+        RespValue::Array(headers),
+        RespValue::BulkString(body),
+    ] = parts.as_slice()
+    else {
+        panic!("Unexpected CACHE.GET reply: {parts:?}");
+    };
+    let text = |value: &RespValue| match value {
+        RespValue::BulkString(bytes) => String::from_utf8_lossy(bytes).into_owned(),
+        other => panic!("Expected a header bulk string, got {other:?}"),
+    };
+    let headers = headers
+        .chunks(2)
+        .map(|pair| (text(&pair[0]), text(&pair[1])))
+        .collect();
+    (*status, headers, body.clone())
+}
+
+#[tokio::test]
+async fn test_cache_http_replays_stored_origin_headers() {
+    let dir = tempfile::tempdir().unwrap();
+    let (ctx, base, hits, _shutdown) = setup(dir.path()).await;
+
+    for (path, expected) in [
+        ("items/6", "MISS"),
+        ("items/6", "HIT"),
+        ("large/6", "MISS"),
+        ("large/6", "HIT"),
+    ] {
+        let response = reqwest::get(format!("{base}/{path}")).await.unwrap();
+        assert_eq!(x_cache(&response), expected);
+        assert_eq!(
+            response.headers()["content-type"],
+            "text/plain; charset=utf-8"
+        );
+        // Headers outside the allowlist are not stored.
+        assert!(!response.headers().contains_key("x-origin-internal"));
+    }
+    let response = reqwest::get(format!("{base}/items/6")).await.unwrap();
+    assert_eq!(response.headers()["access-control-allow-origin"], "*");
+    assert_eq!(count(&hits, "/items/6"), 1);
+
+    let (status, headers, body) = cache_get(&ctx, "/items/6").await;
+    assert_eq!(status, 200);
+    assert_eq!(body, Bytes::from_static(b"item-6"));
+    for header in [
+        ("content-type", "text/plain; charset=utf-8"),
+        ("access-control-allow-origin", "*"),
+        ("etag", "\"v-6\""),
+    ] {
+        assert!(
+            headers.contains(&(header.0.to_string(), header.1.to_string())),
+            "missing {header:?} in {headers:?}"
+        );
+    }
+
+    // The stored status and headers survive an SPLDB round trip.
+    let data = spldb::save_to_bytes(&ctx.state.dbs, &[]).await.unwrap();
+    let restarted = TestContext::new().await;
+    spldb::load_from_bytes(&data, &restarted.state.dbs)
+        .await
+        .unwrap();
+    assert_eq!(cache_get(&restarted, "/items/6").await.1, headers);
+}
+
+/// Sends a GET request for `path` with a `Range` header.
+async fn get_range(base: &str, path: &str, range: &str) -> reqwest::Response {
+    reqwest::Client::new()
+        .get(format!("{base}/{path}"))
+        .header("range", range)
+        .send()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_cache_http_serves_ranges_of_cached_bodies() {
+    let dir = tempfile::tempdir().unwrap();
+    let (_ctx, base, hits, _shutdown) = setup(dir.path()).await;
+    let size = LARGE_BODY.len();
+
+    // The whole object is cached first, so ranges are read from its file.
+    reqwest::get(format!("{base}/large/7")).await.unwrap();
+    for (range, content_range, body) in [
+        ("bytes=2-5", format!("bytes 2-5/{size}"), &LARGE_BODY[2..=5]),
+        (
+            "bytes=-4",
+            format!("bytes {}-{}/{size}", size - 4, size - 1),
+            &LARGE_BODY[size - 4..],
+        ),
+        (
+            "bytes=10-",
+            format!("bytes 10-{}/{size}", size - 1),
+            &LARGE_BODY[10..],
+        ),
+    ] {
+        let response = get_range(&base, "large/7", range).await;
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(x_cache(&response), "HIT");
+        assert_eq!(response.headers()["accept-ranges"], "bytes");
+        assert_eq!(response.headers()["content-range"], content_range.as_str());
+        assert_eq!(
+            response.headers()["content-length"],
+            body.len().to_string().as_str()
+        );
+        assert_eq!(response.text().await.unwrap(), body);
+    }
+
+    let response = get_range(&base, "large/7", "bytes=1000-2000").await;
+    assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+    assert_eq!(
+        response.headers()["content-range"],
+        format!("bytes */{size}").as_str()
+    );
+
+    // An in-memory body is served the same way.
+    reqwest::get(format!("{base}/items/7")).await.unwrap();
+    let response = get_range(&base, "items/7", "bytes=0-3").await;
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(response.text().await.unwrap(), "item");
+
+    assert_eq!(count(&hits, "/large/7"), 1);
+    assert_eq!(count(&hits, "/items/7"), 1);
+}
+
+#[tokio::test]
+async fn test_cache_http_fetches_missed_ranges_as_slices() {
+    let dir = tempfile::tempdir().unwrap();
+    let (_ctx, base, hits, _shutdown) = setup(dir.path()).await;
+
+    // Bytes 20-40 span the second and third 16-byte slices.
+    let response = get_range(&base, "media/8", "bytes=20-40").await;
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(x_cache(&response), "MISS");
+    assert_eq!(response.headers()["content-type"], "video/mp4");
+    assert_eq!(response.headers()["content-range"], "bytes 20-40/62");
+    assert_eq!(response.text().await.unwrap(), &MEDIA_BODY[20..=40]);
+    assert_eq!(count(&hits, "/media/8 16-31"), 1);
+    assert_eq!(count(&hits, "/media/8 32-47"), 1);
+    assert_eq!(count(&hits, "/media/8 0-15"), 0);
+    assert_eq!(count(&hits, "/media/8"), 0);
+
+    // A seek within the cached slices is served without the origin.
+    let response = get_range(&base, "media/8", "bytes=16-47").await;
+    assert_eq!(x_cache(&response), "HIT");
+    assert_eq!(response.text().await.unwrap(), &MEDIA_BODY[16..=47]);
+    assert_eq!(count(&hits, "/media/8 16-31"), 1);
+
+    // A suffix range fetches the last slice, which the origin truncates.
+    let response = get_range(&base, "media/8", "bytes=-5").await;
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(response.headers()["content-range"], "bytes 57-61/62");
+    assert_eq!(response.text().await.unwrap(), &MEDIA_BODY[57..]);
+    assert_eq!(count(&hits, "/media/8 48-61"), 1);
+}
+
+#[tokio::test]
+async fn test_cache_http_checks_slices_against_the_first_slice_version() {
+    let dir = tempfile::tempdir().unwrap();
+    let (_ctx, base, hits, _shutdown) = setup(dir.path()).await;
+    let bump_version = || {
+        *hits
+            .lock()
+            .unwrap()
+            .entry("version /media/10".into())
+            .or_default() += 1
+    };
+
+    get_range(&base, "media/10", "bytes=16-31").await;
+    bump_version();
+
+    // The cached second slice is of an older version than the first, so it is fetched
+    // again.
+    let response = get_range(&base, "media/10", "bytes=0-31").await;
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(response.headers()["etag"], "\"m-10-1\"");
+    assert_eq!(response.text().await.unwrap(), &MEDIA_BODY[..=31]);
+    assert_eq!(count(&hits, "/media/10 16-31"), 2);
+
+    // A slice missing after the object changed again cannot be served, so the
+    // response is cut short instead of mixing versions.
+    bump_version();
+    let response = get_range(&base, "media/10", "bytes=0-47").await;
+    assert_eq!(response.headers()["etag"], "\"m-10-1\"");
+    assert!(response.text().await.is_err());
+    assert_eq!(count(&hits, "/media/10 32-47"), 1);
+    assert_eq!(count(&hits, "/media/10"), 0);
+}
+
+#[tokio::test]
+async fn test_cache_http_honors_if_range() {
+    let dir = tempfile::tempdir().unwrap();
+    let (_ctx, base, hits, _shutdown) = setup(dir.path()).await;
+    reqwest::get(format!("{base}/items/11")).await.unwrap();
+
+    let get = |if_range: &'static str| {
+        reqwest::Client::new()
+            .get(format!("{base}/items/11"))
+            .header("range", "bytes=0-3")
+            .header("if-range", if_range)
+            .send()
+    };
+    let response = get("\"v-11\"").await.unwrap();
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(response.text().await.unwrap(), "item");
+    let response = get("Wed, 21 Oct 2015 07:28:00 GMT").await.unwrap();
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+
+    // A range of another version is ignored, and the whole object is served.
+    for if_range in ["\"v-old\"", "W/\"v-11\"", "Thu, 22 Oct 2015 07:28:00 GMT"] {
+        let response = get(if_range).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers().get("content-range").is_none());
+        assert_eq!(response.text().await.unwrap(), "item-11");
+    }
+    assert_eq!(count(&hits, "/items/11"), 1);
+}
+
+#[tokio::test]
+async fn test_cache_http_falls_back_to_whole_object_without_origin_ranges() {
+    let dir = tempfile::tempdir().unwrap();
+    let (_ctx, base, hits, _shutdown) = setup(dir.path()).await;
+
+    // The `/large` origin ignores ranges, so the whole object is fetched and cached.
+    let response = get_range(&base, "large/9", "bytes=0-6").await;
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(x_cache(&response), "MISS");
+    assert_eq!(response.text().await.unwrap(), &LARGE_BODY[..=6]);
+
+    let response = reqwest::get(format!("{base}/large/9")).await.unwrap();
+    assert_eq!(x_cache(&response), "HIT");
+    assert_eq!(response.text().await.unwrap(), LARGE_BODY);
+    assert_eq!(count(&hits, "/large/9"), 2);
+}
+
+async fn purge_tags(ctx: &TestContext, tags: &[&str]) -> RespValue {
+    let mut args = vec![
+        RespFrame::BulkString(Bytes::from_static(b"CACHE")),
+        RespFrame::BulkString(Bytes::from_static(b"PURGETAG")),
+    ];
+    args.extend(
+        tags.iter()
+            .map(|tag| RespFrame::BulkString(Bytes::from(tag.to_string()))),
+    );
+    ctx.execute(Command::try_from(RespFrame::Array(args)).unwrap())
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_cache_http_purges_by_origin_surrogate_keys() {
+    let dir = tempfile::tempdir().unwrap();
+    let (ctx, base, hits, _shutdown) = setup(dir.path()).await;
+
+    for id in ["1", "2"] {
+        reqwest::get(format!("{base}/tagged/{id}")).await.unwrap();
+    }
+
+    // Keys from both headers are registered, alongside the policy's own tags.
+    for tag in ["product-1", "brand-1"] {
+        assert_eq!(purge_tags(&ctx, &[tag]).await, RespValue::Integer(1));
+        let response = reqwest::get(format!("{base}/tagged/1")).await.unwrap();
+        assert_eq!(x_cache(&response), "MISS");
+    }
+    assert_eq!(count(&hits, "/tagged/1"), 3);
+    let response = reqwest::get(format!("{base}/tagged/2")).await.unwrap();
+    assert_eq!(x_cache(&response), "HIT");
+
+    purge_tags(&ctx, &["catalog"]).await;
+    for id in ["1", "2"] {
+        let response = reqwest::get(format!("{base}/tagged/{id}")).await.unwrap();
+        assert_eq!(x_cache(&response), "MISS");
+    }
+    purge_tags(&ctx, &["tagged"]).await;
+    let response = reqwest::get(format!("{base}/tagged/2")).await.unwrap();
+    assert_eq!(x_cache(&response), "MISS");
+    assert_eq!(count(&hits, "/tagged/2"), 3);
+}
+
+#[tokio::test]
+async fn test_cache_http_respects_surrogate_control_max_age() {
+    let dir = tempfile::tempdir().unwrap();
+    let (_ctx, base, hits, _shutdown) = setup(dir.path()).await;
+
+    reqwest::get(format!("{base}/tagged/short")).await.unwrap();
+    reqwest::get(format!("{base}/tagged/long")).await.unwrap();
+    sleep(Duration::from_millis(1100)).await;
+
+    // `Surrogate-Control: max-age=1` overrides both the policy TTL and `Cache-Control`.
+    let response = reqwest::get(format!("{base}/tagged/short")).await.unwrap();
+    assert_eq!(x_cache(&response), "MISS");
+    let response = reqwest::get(format!("{base}/tagged/long")).await.unwrap();
+    assert_eq!(x_cache(&response), "HIT");
+    assert_eq!(count(&hits, "/tagged/short"), 2);
+    assert_eq!(count(&hits, "/tagged/long"), 1);
+}
+
+/// An origin whose `/flaky/{id}` fails with 500 while `down` is set, and whose
+/// `/slow/{id}` records the highest number of concurrent requests it served.
+#[derive(Default)]
+struct FlakyOrigin {
+    down: AtomicBool,
+    hits: AtomicUsize,
+    in_flight: AtomicUsize,
+    max_in_flight: AtomicUsize,
+}
+
+async fn spawn_flaky_origin() -> (u16, Arc<FlakyOrigin>) {
+    async fn flaky(
+        Path(id): Path<String>,
+        State(origin): State<Arc<FlakyOrigin>>,
+    ) -> (StatusCode, String) {
+        origin.hits.fetch_add(1, Ordering::SeqCst);
+        if origin.down.load(Ordering::SeqCst) {
+            (StatusCode::INTERNAL_SERVER_ERROR, "down".to_string())
+        } else {
+            (StatusCode::OK, format!("flaky-{id}"))
+        }
+    }
+
+    async fn slow(Path(id): Path<String>, State(origin): State<Arc<FlakyOrigin>>) -> String {
+        let in_flight = origin.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        origin.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+        sleep(Duration::from_millis(100)).await;
+        origin.in_flight.fetch_sub(1, Ordering::SeqCst);
+        format!("slow-{id}")
+    }
+
+    let origin = Arc::new(FlakyOrigin::default());
+    let app = Router::new()
+        .route("/flaky/{id}", get(flaky))
+        .route("/slow/{id}", get(slow))
+        .with_state(origin.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    (port, origin)
+}
+
+/// Returns the `origin` section of `CACHE.INFO key` as field/value pairs.
+async fn origin_info(ctx: &TestContext, key: &str) -> HashMap<String, RespValue> {
+    let command = Command::try_from(RespFrame::Array(vec![
+        RespFrame::BulkString(Bytes::from_static(b"CACHE")),
+        RespFrame::BulkString(Bytes::from_static(b"INFO")),
+        RespFrame::BulkString(Bytes::from(key.to_string())),
+    ]))
+    .unwrap();
+    let RespValue::Array(info) = ctx.execute(command).await.unwrap() else {
+        panic!("Expected CACHE.INFO to return an array");
+    };
+    let Some([_, RespValue::Array(origin)]) = info
+        .chunks(2)
+        .find(|pair| pair[0] == RespValue::BulkString(Bytes::from_static(b"origin")))
+    else {
+        panic!("Expected an origin section in {info:?}");
+    };
+    origin
+        .chunks(2)
+        .map(|pair| match &pair[0] {
+            RespValue::BulkString(name) => {
+                (String::from_utf8_lossy(name).into_owned(), pair[1].clone())
+            }
+            other => panic!("Expected a field name, got {other:?}"),
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_cache_http_circuit_breaker_serves_grace_content() {
+    let dir = tempfile::tempdir().unwrap();
+    let (ctx, base, _hits, _shutdown) = setup(dir.path()).await;
+    let (port, origin) = spawn_flaky_origin().await;
+    set_policy(
+        &ctx,
+        "flaky",
+        "/flaky/*",
+        &format!("http://127.0.0.1:{port}/flaky/{{1}}"),
+        &[
+            "TTL",
+            "1",
+            "GRACE",
+            "60",
+            "BREAKER_ERROR_RATE",
+            "50",
+            "BREAKER_MIN_REQUESTS",
+            "3",
+            "BREAKER_BACKOFF",
+            "2",
+        ],
+    )
+    .await;
+
+    reqwest::get(format!("{base}/flaky/1")).await.unwrap();
+    origin.down.store(true, Ordering::SeqCst);
+    for id in ["2", "3"] {
+        let response = reqwest::get(format!("{base}/flaky/{id}")).await.unwrap();
+        assert_eq!(response.status(), 500);
+    }
+    assert_eq!(origin.hits.load(Ordering::SeqCst), 3);
+
+    // Two of three requests failed: the breaker is open and the origin is no longer asked.
+    let response = reqwest::get(format!("{base}/flaky/4")).await.unwrap();
+    assert_eq!(response.status(), 503);
+    sleep(Duration::from_millis(1100)).await;
+    let response = reqwest::get(format!("{base}/flaky/1")).await.unwrap();
+    assert_eq!(x_cache(&response), "STALE");
+    assert_eq!(response.text().await.unwrap(), "flaky-1");
+    assert_eq!(origin.hits.load(Ordering::SeqCst), 3);
+
+    let info = origin_info(&ctx, "/flaky/1").await;
+    assert_eq!(
+        info["host"],
+        RespValue::BulkString(format!("127.0.0.1:{port}").into())
+    );
+    assert_eq!(info["circuit"], RespValue::BulkString("open".into()));
+    assert_eq!(info["failures"], RespValue::Integer(2));
+    assert_eq!(info["rejected"], RespValue::Integer(2));
+    let metrics = spineldb::core::metrics::gather_metrics();
+    assert!(metrics.contains(&format!(
+        "spineldb_cache_origin_circuit_state{{origin=\"127.0.0.1:{port}\"}} 1"
+    )));
+
+    // After the backoff, a successful probe closes the breaker.
+    origin.down.store(false, Ordering::SeqCst);
+    sleep(Duration::from_millis(1000)).await;
+    let response = reqwest::get(format!("{base}/flaky/5")).await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        origin_info(&ctx, "/flaky/5").await["circuit"],
+        RespValue::BulkString("closed".into())
+    );
+}
+
+#[tokio::test]
+async fn test_cache_http_circuit_breaker_backs_off_after_failed_probes() {
+    let dir = tempfile::tempdir().unwrap();
+    let (ctx, base, _hits, _shutdown) = setup(dir.path()).await;
+    let (port, origin) = spawn_flaky_origin().await;
+    set_policy(
+        &ctx,
+        "flaky",
+        "/flaky/*",
+        &format!("http://127.0.0.1:{port}/flaky/{{1}}"),
+        &[
+            "BREAKER_ERROR_RATE",
+            "100",
+            "BREAKER_MIN_REQUESTS",
+            "1",
+            "BREAKER_BACKOFF",
+            "1",
+        ],
+    )
+    .await;
+    origin.down.store(true, Ordering::SeqCst);
+
+    let status = |path: String| async move { reqwest::get(path).await.unwrap().status() };
+    assert_eq!(status(format!("{base}/flaky/1")).await, 500);
+    assert_eq!(status(format!("{base}/flaky/2")).await, 503);
+
+    // The probe after one second fails, which doubles the backoff to two seconds.
+    sleep(Duration::from_millis(1100)).await;
+    assert_eq!(status(format!("{base}/flaky/3")).await, 500);
+    sleep(Duration::from_millis(1100)).await;
+    assert_eq!(status(format!("{base}/flaky/4")).await, 503);
+    assert_eq!(origin.hits.load(Ordering::SeqCst), 2);
+
+    origin.down.store(false, Ordering::SeqCst);
+    sleep(Duration::from_millis(1000)).await;
+    assert_eq!(status(format!("{base}/flaky/5")).await, 200);
+    assert_eq!(status(format!("{base}/flaky/6")).await, 200);
+}
+
+#[tokio::test]
+async fn test_cache_http_limits_concurrent_origin_requests() {
+    let dir = tempfile::tempdir().unwrap();
+    let (ctx, base, _hits, _shutdown) = setup(dir.path()).await;
+    let (port, origin) = spawn_flaky_origin().await;
+    set_policy(
+        &ctx,
+        "slow",
+        "/slow/*",
+        &format!("http://127.0.0.1:{port}/slow/{{1}}"),
+        &["TTL", "60", "ORIGIN_CONCURRENCY", "2"],
+    )
+    .await;
+
+    let requests = (0..6).map(|id| reqwest::get(format!("{base}/slow/{id}")));
+    for response in futures::future::join_all(requests).await {
+        assert_eq!(response.unwrap().status(), 200);
+    }
+    assert_eq!(origin.max_in_flight.load(Ordering::SeqCst), 2);
+}
+
+/// Requests `path` with the given `Accept-Encoding` header.
+async fn get_encoded(base: &str, path: &str, accept_encoding: &str) -> reqwest::Response {
+    reqwest::Client::new()
+        .get(format!("{base}{path}"))
+        .header("accept-encoding", accept_encoding)
+        .send()
+        .await
+        .unwrap()
+}
+
+/// Returns the `Content-Encoding` of a response and its decoded body.
+async fn decoded_body(response: reqwest::Response) -> (Option<String>, String) {
+    let encoding = response
+        .headers()
+        .get("content-encoding")
+        .map(|v| v.to_str().unwrap().to_string());
+    let body = response.bytes().await.unwrap();
+    let mut decoded = String::new();
+    match encoding.as_deref() {
+        Some("gzip") => {
+            flate2::read::GzDecoder::new(body.as_ref())
+                .read_to_string(&mut decoded)
+                .unwrap();
+        }
+        Some("br") => {
+            brotli::Decompressor::new(body.as_ref(), 4096)
+                .read_to_string(&mut decoded)
+                .unwrap();
+        }
+        Some("zstd") => {
+            decoded = String::from_utf8(zstd::decode_all(body.as_ref()).unwrap()).unwrap();
+        }
+        Some(other) => panic!("Unexpected content encoding {other}"),
+        None => decoded = String::from_utf8(body.to_vec()).unwrap(),
+    }
+    (encoding, decoded)
+}
+
+#[tokio::test]
+async fn test_cache_http_negotiates_content_encoding() {
+    let dir = tempfile::tempdir().unwrap();
+    let (ctx, base, hits, _shutdown) = setup(dir.path()).await;
+
+    // The miss is served as fetched from the origin.
+    let response = get_encoded(&base, "/text/1", "gzip").await;
+    assert_eq!(x_cache(&response), "MISS");
+    assert_eq!(decoded_body(response).await, (None, TEXT_BODY.to_string()));
+
+    let cases = [
+        ("gzip", Some("gzip")),
+        ("gzip;q=0.5, br", Some("br")),
+        ("zstd", Some("zstd")),
+        // Equally weighted encodings are chosen in the server's order.
+        ("gzip, zstd, br", Some("br")),
+        ("*", Some("br")),
+        ("gzip;q=0", None),
+        ("identity, gzip;q=0.5", None),
+        ("compress", None),
+    ];
+    for (accept_encoding, expected) in cases {
+        let response = get_encoded(&base, "/text/1", accept_encoding).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(x_cache(&response), "HIT");
+        assert_eq!(response.headers()["vary"], "accept-encoding");
+        assert_eq!(response.headers()["content-type"], "text/plain");
+        let etag = response.headers()["etag"].to_str().unwrap().to_string();
+        let (encoding, body) = decoded_body(response).await;
+        assert_eq!(
+            encoding.as_deref(),
+            expected,
+            "Accept-Encoding: {accept_encoding}"
+        );
+        assert_eq!(body, TEXT_BODY);
+        // Encoded bodies only match the origin's validator weakly.
+        match expected {
+            Some(_) => assert_eq!(etag, "W/\"t-1\""),
+            None => assert_eq!(etag, "\"t-1\""),
+        }
+    }
+
+    // A conditional request is answered for the encoded body too.
+    let response = reqwest::Client::new()
+        .get(format!("{base}/text/1"))
+        .header("accept-encoding", "gzip")
+        .header("if-none-match", "W/\"t-1\"")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(response.headers()["content-encoding"], "gzip");
+
+    // Ranges are served from the unencoded body.
+    let response = reqwest::Client::new()
+        .get(format!("{base}/text/1"))
+        .header("accept-encoding", "gzip")
+        .header("range", "bytes=0-4")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    assert!(response.headers().get("content-encoding").is_none());
+    assert_eq!(response.text().await.unwrap(), "Lorem");
+
+    // The body and one rendition per encoding are cached; the origin was asked once.
+    assert_eq!(count(&hits, "/text/1"), 1);
+    let RespValue::Array(info) = ctx
+        .execute(
+            Command::try_from(RespFrame::Array(vec![
+                RespFrame::BulkString(Bytes::from_static(b"CACHE")),
+                RespFrame::BulkString(Bytes::from_static(b"INFO")),
+                RespFrame::BulkString(Bytes::from_static(b"/text/1")),
+            ]))
+            .unwrap(),
+        )
+        .await
+        .unwrap()
+    else {
+        panic!("Expected CACHE.INFO to return an array");
+    };
+    let variants_count = info
+        .chunks(2)
+        .find(|pair| pair[0] == RespValue::BulkString(Bytes::from_static(b"variants_count")))
+        .map(|pair| pair[1].clone());
+    assert_eq!(variants_count, Some(RespValue::Integer(4)));
+}
+
+#[tokio::test]
+async fn test_cache_http_does_not_encode_for_other_policies() {
+    let dir = tempfile::tempdir().unwrap();
+    let (_ctx, base, _hits, _shutdown) = setup(dir.path()).await;
+
+    for _ in 0..2 {
+        let response = get_encoded(&base, "/items/enc", "gzip, br").await;
+        assert!(response.headers().get("content-encoding").is_none());
+        assert!(response.headers().get("vary").is_none());
+        assert_eq!(response.text().await.unwrap(), "item-enc");
+    }
+}
+
+#[tokio::test]
+async fn test_cache_http_propagates_stores() {
+    let dir = tempfile::tempdir().unwrap();
+    let (ctx, base, _hits, _shutdown) = setup(dir.path()).await;
+    let mut replication_rx = ctx.state.event_bus.subscribe_for_replication();
+
+    let response = reqwest::get(format!("{base}/items/replicated"))
+        .await
+        .unwrap();
+    assert_eq!(x_cache(&response), "MISS");
+
+    // The store reaches the AOF and replicas as the fetch that made it.
+    let work = replication_rx.recv().await.unwrap();
+    match work.uow {
+        UnitOfWork::Command(command) => match *command {
+            Command::Cache(Cache {
+                subcommand: CacheSubcommand::Fetch(fetch),
+            }) => {
+                assert_eq!(fetch.key, Bytes::from_static(b"/items/replicated"));
+                assert!(fetch.url.ends_with("/items/replicated"));
+            }
+            other => panic!("Expected CACHE.FETCH, got {other:?}"),
+        },
+        other => panic!("Expected a single command, got {other:?}"),
+    }
+
+    // Hits are not propagated.
+    let response = reqwest::get(format!("{base}/items/replicated"))
+        .await
+        .unwrap();
+    assert_eq!(x_cache(&response), "HIT");
+    assert!(replication_rx.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn test_cache_http_serves_only_hits_on_a_replica() {
+    let dir = tempfile::tempdir().unwrap();
+    let (ctx, base, hits, _shutdown) = setup(dir.path()).await;
+
+    let response = reqwest::get(format!("{base}/items/1")).await.unwrap();
+    assert_eq!(x_cache(&response), "MISS");
+
+    ctx.state.config.lock().await.replication = ReplicationConfig::Replica {
+        primary_host: "127.0.0.1".into(),
+        primary_port: 6379,
+        tls_enabled: false,
+    };
+
+    // Cached content is still served, but misses cannot be stored on a replica.
+    let response = reqwest::get(format!("{base}/items/1")).await.unwrap();
+    assert_eq!(x_cache(&response), "HIT");
+    let response = reqwest::get(format!("{base}/items/2")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(count(&hits, "/items/2"), 0);
+}
+
+#[tokio::test]
+async fn test_cache_http_refuses_misses_above_maxmemory() {
+    let dir = tempfile::tempdir().unwrap();
+    let (ctx, base, hits, _shutdown) = setup(dir.path()).await;
+
+    let response = reqwest::get(format!("{base}/items/1")).await.unwrap();
+    assert_eq!(x_cache(&response), "MISS");
+    ctx.state.config.lock().await.maxmemory = Some(1);
+
+    let response = reqwest::get(format!("{base}/items/2")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(count(&hits, "/items/2"), 0);
+}
@@ -5,6 +5,7 @@
 
 use super::test_helpers::TestContext;
 use bytes::Bytes;
+use spineldb::config::Config;
 use spineldb::core::Command;
 use spineldb::core::RespValue;
 use spineldb::core::protocol::RespFrame;
@@ -344,6 +345,58 @@ async fn test_cache_get_conditional_if_none_match() {
     }
 }
 
+#[tokio::test]
+async fn test_cache_get_range() {
+    let ctx = TestContext::new().await;
+
+    for (key, extra) in [("key_range", None), ("key_range_zstd", Some("COMPRESSION"))] {
+        let mut args = vec![
+            RespFrame::BulkString(Bytes::from_static(b"CACHE")),
+            RespFrame::BulkString(Bytes::from_static(b"SET")),
+            RespFrame::BulkString(Bytes::from(key)),
+            RespFrame::BulkString(Bytes::from("0123456789")),
+            RespFrame::BulkString(Bytes::from_static(b"TTL")),
+            RespFrame::BulkString(Bytes::from("60")),
+        ];
+        args.extend(extra.map(|flag| RespFrame::BulkString(Bytes::from(flag))));
+        ctx.execute(Command::try_from(RespFrame::Array(args)).unwrap())
+            .await
+            .unwrap();
+
+        for (start, end, status, content_range, body) in [
+            ("2", "5", 206, "bytes 2-5/10", "2345"),
+            ("-3", "-1", 206, "bytes 7-9/10", "789"),
+            ("8", "100", 206, "bytes 8-9/10", "89"),
+            ("10", "20", 416, "bytes */10", ""),
+        ] {
+            let get_cmd = Command::try_from(RespFrame::Array(vec![
+                RespFrame::BulkString(Bytes::from_static(b"CACHE")),
+                RespFrame::BulkString(Bytes::from_static(b"GET")),
+                RespFrame::BulkString(Bytes::from(key)),
+                RespFrame::BulkString(Bytes::from_static(b"RANGE")),
+                RespFrame::BulkString(Bytes::from(start)),
+                RespFrame::BulkString(Bytes::from(end)),
+            ]))
+            .unwrap();
+
+            let result = ctx.execute(get_cmd).await.unwrap();
+            let RespValue::Array(elements) = result else {
+                panic!("Expected Array [status, headers, body], got {result:?}");
+            };
+            assert_eq!(elements[0], RespValue::Integer(status));
+            let RespValue::Array(headers) = &elements[1] else {
+                panic!("Expected headers as Array, got {:?}", elements[1]);
+            };
+            assert!(headers.windows(2).any(|pair| pair
+                == [
+                    RespValue::BulkString(Bytes::from_static(b"content-range")),
+                    RespValue::BulkString(Bytes::from(content_range)),
+                ]));
+            assert_eq!(elements[2], RespValue::BulkString(Bytes::from(body)));
+        }
+    }
+}
+
 // ===== CACHE.STATS Tests =====
 
 #[tokio::test]
@@ -1085,3 +1138,969 @@ async fn test_cache_policy_applies_to_keys() {
         _ => panic!("Expected Array [status, headers, body], got {:?}", result),
     }
 }
+
+#[tokio::test]
+async fn test_cache_policies_declared_in_config_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.toml");
+    std::fs::write(
+        &path,
+        r#"
+[[cache.policies]]
+name = "products"
+key_pattern = "/products/*"
+url_template = "https://api.example.com/products/{1}"
+ttl = 300
+tags = ["products"]
+
+[[cache.policies]]
+name = "static"
+key_pattern = "/static/*"
+url_template = "https://cdn.example.com/{1}"
+priority = 10
+force_disk = true
+"#,
+    )
+    .unwrap();
+
+    let config = Config::from_file(path.to_str().unwrap()).unwrap();
+    let policies = &config.cache.policies;
+    assert_eq!(policies.len(), 2);
+    assert_eq!(policies[0].name, "products");
+    assert_eq!(policies[0].ttl, Some(300));
+    assert_eq!(policies[0].tags, vec!["products"]);
+    assert_eq!(policies[1].priority, 10);
+    assert!(policies[1].force_disk);
+
+    // Policy names must be unique.
+    let mut contents = std::fs::read_to_string(&path).unwrap();
+    contents = contents.replace("name = \"static\"", "name = \"products\"");
+    std::fs::write(&path, contents).unwrap();
+    let err = Config::from_file(path.to_str().unwrap()).unwrap_err();
+    assert!(err.to_string().contains("duplicate policy name"));
+
+    // Origin protection settings are bounded like in CACHE.POLICY SET.
+    contents = std::fs::read_to_string(&path).unwrap();
+    contents = contents
+        .replace(
+            "name = \"products\"\nkey_pattern = \"/static/*\"",
+            "name = \"static\"\nkey_pattern = \"/static/*\"",
+        )
+        .replace(
+            "force_disk = true",
+            "force_disk = true\nbreaker_backoff = 100000",
+        );
+    std::fs::write(&path, contents).unwrap();
+    let err = Config::from_file(path.to_str().unwrap()).unwrap_err();
+    assert!(err.to_string().contains("BREAKER_BACKOFF"), "{err}");
+}
+
+// ===== Database Isolation Tests =====
+
+/// Returns a context that executes commands against database `db_index` of `ctx`'s server.
+fn in_db(ctx: &TestContext, db_index: usize) -> TestContext {
+    TestContext {
+        state: ctx.state.clone(),
+        db: ctx.state.get_db(db_index).unwrap(),
+        db_index,
+    }
+}
+
+async fn cache_cmd(ctx: &TestContext, args: &[&str]) -> RespValue {
+    let mut frames = vec![RespFrame::BulkString(Bytes::from_static(b"CACHE"))];
+    frames.extend(
+        args.iter()
+            .map(|arg| RespFrame::BulkString(Bytes::from(arg.to_string()))),
+    );
+    ctx.execute(Command::try_from(RespFrame::Array(frames)).unwrap())
+        .await
+        .unwrap()
+}
+
+/// Returns the body of a `CACHE.GET` reply, or `None` on a miss.
+async fn cached_body(ctx: &TestContext, key: &str) -> Option<Bytes> {
+    match cache_cmd(ctx, &["GET", key]).await {
+        RespValue::BulkString(body) => Some(body),
+        RespValue::Array(parts) => match &parts[2] {
+            RespValue::BulkString(body) => Some(body.clone()),
+            other => panic!("Expected body as BulkString, got {other:?}"),
+        },
+        RespValue::Null => None,
+        other => panic!("Unexpected CACHE.GET reply: {other:?}"),
+    }
+}
+
+/// Starts an origin whose `/version` body counts the requests it has served, and
+/// `/large` which serves a 64-byte body. Returns its port.
+async fn spawn_counting_origin() -> u16 {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let served = Arc::new(AtomicUsize::new(0));
+    let app = axum::Router::new()
+        .route(
+            "/version",
+            axum::routing::get(move || {
+                let served = served.clone();
+                async move { format!("v{}", served.fetch_add(1, Ordering::SeqCst) + 1) }
+            }),
+        )
+        .route("/large", axum::routing::get(|| async { "x".repeat(64) }));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    port
+}
+
+fn multi_db_config() -> Config {
+    let mut config = Config::default();
+    config.databases = 4;
+    config.persistence.aof_enabled = false;
+    config.persistence.spldb_enabled = false;
+    config.security.allow_private_fetch_ips = true;
+    config
+}
+
+#[tokio::test]
+async fn test_cache_purgetag_is_scoped_to_database() {
+    let ctx = TestContext::with_config(multi_db_config()).await;
+    let db3 = in_db(&ctx, 3);
+
+    for (db, value) in [(&ctx, "in-db0"), (&db3, "in-db3")] {
+        cache_cmd(db, &["SET", "page", value, "TTL", "60", "TAGS", "pages"]).await;
+    }
+
+    assert_eq!(
+        cache_cmd(&db3, &["PURGETAG", "pages"]).await,
+        RespValue::Integer(1)
+    );
+    assert_eq!(cached_body(&db3, "page").await, None);
+    assert_eq!(
+        cached_body(&ctx, "page").await,
+        Some(Bytes::from_static(b"in-db0"))
+    );
+}
+
+#[tokio::test]
+async fn test_cache_revalidates_in_selected_database() {
+    let ctx = TestContext::with_config(multi_db_config()).await;
+    let db3 = in_db(&ctx, 3);
+    let url = format!("http://127.0.0.1:{}/version", spawn_counting_origin().await);
+
+    let fetched = cache_cmd(&db3, &["FETCH", "version", &url, "TTL", "1", "SWR", "60"]).await;
+    assert_eq!(fetched, RespValue::BulkString(Bytes::from_static(b"v1")));
+    assert_eq!(cached_body(&ctx, "version").await, None);
+
+    // The stale hit is revalidated in the background, in database 3.
+    sleep(Duration::from_millis(1100)).await;
+    assert_eq!(
+        cached_body(&db3, "version").await,
+        Some(Bytes::from_static(b"v1"))
+    );
+    sleep(Duration::from_millis(300)).await;
+    assert_eq!(
+        cached_body(&db3, "version").await,
+        Some(Bytes::from_static(b"v2"))
+    );
+
+    let RespValue::Array(stats) = cache_cmd(&ctx, &["STATS"]).await else {
+        panic!("Expected CACHE.STATS to return an array");
+    };
+    let section = |name: &str| {
+        stats
+            .chunks(2)
+            .find(|pair| pair[0] == RespValue::BulkString(Bytes::from(name.to_string())))
+            .map(|pair| pair[1].clone())
+    };
+    let Some(RespValue::Array(db3_stats)) = section("db3") else {
+        panic!("Expected a db3 section in {stats:?}");
+    };
+    let field = |name: &str| {
+        db3_stats
+            .chunks(2)
+            .find(|pair| pair[0] == RespValue::BulkString(Bytes::from(name.to_string())))
+            .map(|pair| pair[1].clone())
+            .unwrap()
+    };
+    assert_eq!(field("keys"), RespValue::Integer(1));
+    assert!(matches!(field("revalidations"), RespValue::Integer(n) if n >= 1));
+    assert!(matches!(field("stale_hits"), RespValue::Integer(n) if n >= 1));
+    assert!(section("db1").is_none());
+}
+
+#[tokio::test]
+async fn test_cache_manifest_records_database() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut config = multi_db_config();
+    config.cache.streaming_threshold_bytes = 32;
+    config.cache.on_disk_path = dir.path().to_string_lossy().into_owned();
+    let ctx = TestContext::with_config(config).await;
+    let manifest_path = dir.path().join("spineldb-cache.manifest");
+    let manifest_file = tokio::fs::File::create(&manifest_path).await.unwrap();
+    *ctx.state.cache.manifest_writer.lock().await = Some(tokio::io::BufWriter::new(manifest_file));
+    let db2 = in_db(&ctx, 2);
+    let url = format!("http://127.0.0.1:{}/large", spawn_counting_origin().await);
+
+    cache_cmd(&db2, &["FETCH", "large", &url, "TTL", "60"]).await;
+
+    let manifest = std::fs::read_to_string(&manifest_path).unwrap();
+    let entries: Vec<serde_json::Value> = manifest
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert!(!entries.is_empty());
+    for entry in entries {
+        assert_eq!(entry["db"], 2, "unexpected manifest entry {entry}");
+    }
+}
+
+#[tokio::test]
+async fn test_cache_revalidation_replaces_surrogate_keys() {
+    use axum::http::HeaderMap;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // Each response is tagged with its generation and with `catalog`.
+    let served = Arc::new(AtomicUsize::new(0));
+    let app = axum::Router::new().route(
+        "/product",
+        axum::routing::get(move || {
+            let served = served.clone();
+            async move {
+                let generation = served.fetch_add(1, Ordering::SeqCst) + 1;
+                let mut headers = HeaderMap::new();
+                headers.insert(
+                    "surrogate-key",
+                    format!("gen-{generation} catalog").parse().unwrap(),
+                );
+                (headers, format!("v{generation}"))
+            }
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let ctx = TestContext::with_config(multi_db_config()).await;
+    cache_cmd(
+        &ctx,
+        &[
+            "POLICY",
+            "SET",
+            "products",
+            "product:*",
+            &format!("http://127.0.0.1:{port}/{{1}}"),
+            "TTL",
+            "1",
+            "SWR",
+            "60",
+            "SURROGATE_KEYS",
+            "TAGS",
+            "products",
+        ],
+    )
+    .await;
+    cache_cmd(&ctx, &["PROXY", "product:product"]).await;
+
+    // The stale hit is revalidated in the background.
+    sleep(Duration::from_millis(1100)).await;
+    cached_body(&ctx, "product:product").await;
+    sleep(Duration::from_millis(300)).await;
+    assert_eq!(
+        cached_body(&ctx, "product:product").await,
+        Some(Bytes::from_static(b"v2"))
+    );
+
+    // The first response's own key is gone; the shared key and the policy's tag remain.
+    assert_eq!(
+        cache_cmd(&ctx, &["PURGETAG", "gen-1"]).await,
+        RespValue::Integer(0)
+    );
+    assert_eq!(
+        cache_cmd(&ctx, &["PURGETAG", "catalog"]).await,
+        RespValue::Integer(1)
+    );
+    cache_cmd(&ctx, &["PROXY", "product:product"]).await;
+    assert_eq!(
+        cache_cmd(&ctx, &["PURGETAG", "products"]).await,
+        RespValue::Integer(1)
+    );
+}
+
+// ===== Content Encoding Tests =====
+
+/// Runs `CACHE.GET key HEADERS Accept-Encoding <accept_encoding>` and returns the
+/// reply's `Content-Encoding` header and body.
+async fn get_with_accept_encoding(
+    ctx: &TestContext,
+    key: &str,
+    accept_encoding: &str,
+) -> (Option<Bytes>, Bytes) {
+    let RespValue::Array(parts) = cache_cmd(
+        ctx,
+        &["GET", key, "HEADERS", "Accept-Encoding", accept_encoding],
+    )
+    .await
+    else {
+        panic!("Expected [status, headers, body]");
+    };
+    let (RespValue::Array(headers), RespValue::BulkString(body)) = (&parts[1], &parts[2]) else {
+        panic!("Unexpected CACHE.GET reply: {parts:?}");
+    };
+    let encoding = headers
+        .chunks(2)
+        .find(|pair| pair[0] == RespValue::BulkString(Bytes::from_static(b"content-encoding")))
+        .map(|pair| match &pair[1] {
+            RespValue::BulkString(value) => value.clone(),
+            other => panic!("Expected a header value, got {other:?}"),
+        });
+    (encoding, body.clone())
+}
+
+fn gunzip(body: &[u8]) -> String {
+    use std::io::Read;
+    let mut decoded = String::new();
+    flate2::read::GzDecoder::new(body)
+        .read_to_string(&mut decoded)
+        .unwrap();
+    decoded
+}
+
+fn variants_count(info: &RespValue) -> RespValue {
+    let RespValue::Array(info) = info else {
+        panic!("Expected CACHE.INFO to return an array");
+    };
+    info.chunks(2)
+        .find(|pair| pair[0] == RespValue::BulkString(Bytes::from_static(b"variants_count")))
+        .map(|pair| pair[1].clone())
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_cache_get_encodes_bodies_for_negotiating_policies() {
+    let mut config = multi_db_config();
+    config.cache.max_variants_per_key = 2;
+    let ctx = TestContext::with_config(config).await;
+    cache_cmd(
+        &ctx,
+        &[
+            "POLICY",
+            "SET",
+            "encoded",
+            "enc:*",
+            "http://example.com/{1}",
+            "NEGOTIATE_ENCODING",
+        ],
+    )
+    .await;
+    cache_cmd(&ctx, &["SET", "enc:page", "first body", "TTL", "60"]).await;
+
+    let (encoding, body) = get_with_accept_encoding(&ctx, "enc:page", "gzip").await;
+    assert_eq!(encoding, Some(Bytes::from_static(b"gzip")));
+    assert_eq!(gunzip(&body), "first body");
+
+    // Replacing the body drops its renditions.
+    cache_cmd(&ctx, &["SET", "enc:page", "second body", "TTL", "60"]).await;
+    let (encoding, body) = get_with_accept_encoding(&ctx, "enc:page", "gzip").await;
+    assert_eq!(encoding, Some(Bytes::from_static(b"gzip")));
+    assert_eq!(gunzip(&body), "second body");
+
+    // Renditions count towards max_variants_per_key and the body is never evicted.
+    let (encoding, _) = get_with_accept_encoding(&ctx, "enc:page", "br").await;
+    assert_eq!(encoding, Some(Bytes::from_static(b"br")));
+    assert_eq!(
+        variants_count(&cache_cmd(&ctx, &["INFO", "enc:page"]).await),
+        RespValue::Integer(2)
+    );
+    let (encoding, body) = get_with_accept_encoding(&ctx, "enc:page", "identity").await;
+    assert_eq!(encoding, None);
+    assert_eq!(body, Bytes::from_static(b"second body"));
+}
+
+#[tokio::test]
+async fn test_cache_get_in_transaction_serves_unencoded_body() {
+    let ctx = TestContext::with_config(multi_db_config()).await;
+    cache_cmd(
+        &ctx,
+        &[
+            "POLICY",
+            "SET",
+            "encoded",
+            "enc:*",
+            "http://example.com/{1}",
+            "NEGOTIATE_ENCODING",
+        ],
+    )
+    .await;
+    cache_cmd(&ctx, &["SET", "enc:page", "body", "TTL", "60"]).await;
+
+    // The shard locks are held for the whole transaction, so the body is not encoded.
+    ctx.execute(Command::Multi).await.unwrap();
+    cache_cmd(
+        &ctx,
+        &["GET", "enc:page", "HEADERS", "Accept-Encoding", "gzip"],
+    )
+    .await;
+    let RespValue::Array(replies) = ctx.execute(Command::Exec).await.unwrap() else {
+        panic!("Expected EXEC to return an array");
+    };
+    let RespValue::Array(parts) = &replies[0] else {
+        panic!("Unexpected CACHE.GET reply: {replies:?}");
+    };
+    assert_eq!(parts[2], RespValue::BulkString(Bytes::from_static(b"body")));
+    assert_eq!(
+        variants_count(&cache_cmd(&ctx, &["INFO", "enc:page"]).await),
+        RespValue::Integer(1)
+    );
+
+    let (encoding, body) = get_with_accept_encoding(&ctx, "enc:page", "gzip").await;
+    assert_eq!(encoding, Some(Bytes::from_static(b"gzip")));
+    assert_eq!(gunzip(&body), "body");
+}
+
+#[tokio::test]
+async fn test_rendition_memory_is_released_with_the_key() {
+    let ctx = TestContext::with_config(multi_db_config()).await;
+    cache_cmd(
+        &ctx,
+        &[
+            "POLICY",
+            "SET",
+            "encoded",
+            "enc:*",
+            "http://example.com/{1}",
+            "NEGOTIATE_ENCODING",
+        ],
+    )
+    .await;
+    let used_memory = || ctx.db.get_current_memory();
+    let empty = used_memory();
+    cache_cmd(
+        &ctx,
+        &["SET", "enc:page", &"body ".repeat(100), "TTL", "60"],
+    )
+    .await;
+    let stored = used_memory();
+
+    let (encoding, _) = get_with_accept_encoding(&ctx, "enc:page", "gzip").await;
+    assert_eq!(encoding, Some(Bytes::from_static(b"gzip")));
+    assert!(used_memory() > stored, "the rendition was not counted");
+
+    ctx.del(&["enc:page"]).await.unwrap();
+    assert_eq!(used_memory(), empty);
+}
+
+// ===== Warmup Tests =====
+
+/// Starts an origin serving `page <id>` at `/pages/<id>`. Returns its port.
+async fn spawn_page_origin() -> u16 {
+    let app =
+        axum::Router::new().route(
+            "/pages/{id}",
+            axum::routing::get(
+                |axum::extract::Path(id): axum::extract::Path<String>| async move {
+                    format!("page {id}")
+                },
+            ),
+        );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    port
+}
+
+/// Waits for the warmup of `policy` to finish and returns its `CACHE.WARM STATUS` fields.
+async fn finished_warmup(ctx: &TestContext, policy: &str) -> Vec<(String, RespValue)> {
+    for _ in 0..100 {
+        let RespValue::Array(jobs) = cache_cmd(ctx, &["WARM", "STATUS"]).await else {
+            panic!("Expected CACHE.WARM STATUS to return an array");
+        };
+        let fields: Vec<(String, RespValue)> = jobs
+            .into_iter()
+            .filter_map(|job| match job {
+                RespValue::Array(fields) => Some(
+                    fields
+                        .chunks(2)
+                        .map(|pair| match &pair[0] {
+                            RespValue::BulkString(name) => {
+                                (String::from_utf8_lossy(name).into_owned(), pair[1].clone())
+                            }
+                            other => panic!("Expected a field name, got {other:?}"),
+                        })
+                        .collect::<Vec<_>>(),
+                ),
+                _ => None,
+            })
+            .find(|fields| {
+                fields.contains(&(
+                    "policy".to_string(),
+                    RespValue::BulkString(Bytes::from(policy.to_string())),
+                ))
+            })
+            .expect("No warmup job for the policy");
+        if !fields.contains(&(
+            "state".to_string(),
+            RespValue::BulkString(Bytes::from_static(b"running")),
+        )) {
+            return fields;
+        }
+        sleep(Duration::from_millis(50)).await;
+    }
+    panic!("Warmup of '{policy}' did not finish");
+}
+
+fn warmup_field(fields: &[(String, RespValue)], name: &str) -> RespValue {
+    fields
+        .iter()
+        .find(|(field, _)| field == name)
+        .map(|(_, value)| value.clone())
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_cache_warm_from_sitemap() {
+    let port = spawn_page_origin().await;
+    let ctx = TestContext::with_config(multi_db_config()).await;
+    cache_cmd(
+        &ctx,
+        &[
+            "POLICY",
+            "SET",
+            "pages",
+            "page:*",
+            &format!("http://127.0.0.1:{port}/pages/{{1}}"),
+            "TTL",
+            "60",
+        ],
+    )
+    .await;
+    cache_cmd(&ctx, &["SET", "page:cached", "already here", "TTL", "60"]).await;
+
+    let mut sitemap = tempfile::NamedTempFile::new().unwrap();
+    use std::io::Write;
+    write!(
+        sitemap,
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+  <url><loc>http://127.0.0.1:{port}/pages/home</loc></url>
+  <url><loc>http://127.0.0.1:{port}/pages/about</loc></url>
+  <url><loc>http://127.0.0.1:{port}/pages/cached</loc></url>
+  <url><loc>http://127.0.0.1:{port}/other/page</loc></url>
+  <url><loc>http://127.0.0.1:{port}/pages/home</loc></url>
+</urlset>"#
+    )
+    .unwrap();
+
+    assert_eq!(
+        cache_cmd(
+            &ctx,
+            &[
+                "WARM",
+                "pages",
+                "FROM",
+                sitemap.path().to_str().unwrap(),
+                "CONCURRENCY",
+                "2",
+            ],
+        )
+        .await,
+        RespValue::Integer(4)
+    );
+
+    let status = finished_warmup(&ctx, "pages").await;
+    assert_eq!(
+        warmup_field(&status, "state"),
+        RespValue::BulkString(Bytes::from_static(b"done"))
+    );
+    assert_eq!(warmup_field(&status, "total"), RespValue::Integer(4));
+    assert_eq!(warmup_field(&status, "fetched"), RespValue::Integer(2));
+    assert_eq!(warmup_field(&status, "skipped"), RespValue::Integer(1));
+    assert_eq!(warmup_field(&status, "failed"), RespValue::Integer(1));
+
+    assert_eq!(
+        cached_body(&ctx, "page:home").await,
+        Some(Bytes::from_static(b"page home"))
+    );
+    assert_eq!(
+        cached_body(&ctx, "page:about").await,
+        Some(Bytes::from_static(b"page about"))
+    );
+    assert_eq!(
+        cached_body(&ctx, "page:cached").await,
+        Some(Bytes::from_static(b"already here"))
+    );
+}
+
+#[tokio::test]
+async fn test_cache_warm_from_url_list_and_arguments() {
+    let port = spawn_page_origin().await;
+    let ctx = TestContext::with_config(multi_db_config()).await;
+    let db2 = in_db(&ctx, 2);
+    cache_cmd(
+        &db2,
+        &[
+            "POLICY",
+            "SET",
+            "pages",
+            "page:*",
+            &format!("http://127.0.0.1:{port}/pages/{{1}}"),
+            "TTL",
+            "60",
+        ],
+    )
+    .await;
+
+    let mut list = tempfile::NamedTempFile::new().unwrap();
+    use std::io::Write;
+    writeln!(
+        list,
+        "# Landing pages\n\nhttp://127.0.0.1:{port}/pages/one\n"
+    )
+    .unwrap();
+
+    assert_eq!(
+        cache_cmd(
+            &db2,
+            &[
+                "WARM",
+                "pages",
+                "FROM",
+                list.path().to_str().unwrap(),
+                &format!("http://127.0.0.1:{port}/pages/two"),
+                "RATE",
+                "50/s",
+            ],
+        )
+        .await,
+        RespValue::Integer(2)
+    );
+
+    let status = finished_warmup(&db2, "pages").await;
+    assert_eq!(warmup_field(&status, "db"), RespValue::Integer(2));
+    assert_eq!(warmup_field(&status, "fetched"), RespValue::Integer(2));
+    assert_eq!(
+        cached_body(&db2, "page:two").await,
+        Some(Bytes::from_static(b"page two"))
+    );
+    assert_eq!(cached_body(&ctx, "page:two").await, None);
+
+    // A finished warmup can no longer be cancelled.
+    assert_eq!(
+        cache_cmd(&db2, &["WARM", "CANCEL", "pages"]).await,
+        RespValue::Integer(0)
+    );
+}
+
+#[tokio::test]
+async fn test_cache_warm_does_not_store_on_a_replica() {
+    let port = spawn_page_origin().await;
+    let ctx = TestContext::with_config(multi_db_config()).await;
+    cache_cmd(
+        &ctx,
+        &[
+            "POLICY",
+            "SET",
+            "pages",
+            "page:*",
+            &format!("http://127.0.0.1:{port}/pages/{{1}}"),
+            "TTL",
+            "60",
+        ],
+    )
+    .await;
+    ctx.state.config.lock().await.replication = spineldb::config::ReplicationConfig::Replica {
+        primary_host: "127.0.0.1".into(),
+        primary_port: 6379,
+        tls_enabled: false,
+    };
+
+    cache_cmd(
+        &ctx,
+        &[
+            "WARM",
+            "pages",
+            "FROM",
+            &format!("http://127.0.0.1:{port}/pages/home"),
+        ],
+    )
+    .await;
+    let status = finished_warmup(&ctx, "pages").await;
+    assert_eq!(warmup_field(&status, "fetched"), RespValue::Integer(0));
+    assert_eq!(warmup_field(&status, "failed"), RespValue::Integer(1));
+    assert_eq!(cached_body(&ctx, "page:home").await, None);
+}
+
+#[tokio::test]
+async fn test_cache_warm_cancel_stops_a_rate_limited_warmup() {
+    let port = spawn_page_origin().await;
+    let ctx = TestContext::with_config(multi_db_config()).await;
+    cache_cmd(
+        &ctx,
+        &[
+            "POLICY",
+            "SET",
+            "pages",
+            "page:*",
+            &format!("http://127.0.0.1:{port}/pages/{{1}}"),
+            "TTL",
+            "60",
+        ],
+    )
+    .await;
+
+    let mut list = tempfile::NamedTempFile::new().unwrap();
+    use std::io::Write;
+    for id in 0..100 {
+        writeln!(list, "http://127.0.0.1:{port}/pages/{id}").unwrap();
+    }
+    cache_cmd(
+        &ctx,
+        &[
+            "WARM",
+            "pages",
+            "FROM",
+            list.path().to_str().unwrap(),
+            "RATE",
+            "1",
+        ],
+    )
+    .await;
+    sleep(Duration::from_millis(100)).await;
+    assert_eq!(
+        cache_cmd(&ctx, &["WARM", "CANCEL", "pages"]).await,
+        RespValue::Integer(1)
+    );
+
+    // The job finishes without waiting out its schedule, and can be started again.
+    let status = finished_warmup(&ctx, "pages").await;
+    assert_eq!(
+        warmup_field(&status, "state"),
+        RespValue::BulkString(Bytes::from_static(b"cancelled"))
+    );
+    assert_eq!(warmup_field(&status, "fetched"), RespValue::Integer(1));
+    assert_eq!(
+        cache_cmd(
+            &ctx,
+            &["WARM", "pages", "FROM", list.path().to_str().unwrap()],
+        )
+        .await,
+        RespValue::Integer(100)
+    );
+}
+
+#[tokio::test]
+async fn test_cache_warm_rejects_unknown_policies() {
+    let ctx = TestContext::new().await;
+    let command = Command::try_from(RespFrame::Array(
+        ["CACHE", "WARM", "missing", "FROM", "http://example.com/"]
+            .iter()
+            .map(|arg| RespFrame::BulkString(Bytes::from(arg.to_string())))
+            .collect(),
+    ))
+    .unwrap();
+    assert!(ctx.execute(command).await.is_err());
+}
+
+// ===== Tiered Storage Tests =====
+
+/// Returns a config with tiered caching to a temporary directory. Bodies are promoted
+/// once their key's access frequency reaches `promotion_min_frequency`.
+fn tiered_config(dir: &tempfile::TempDir, promotion_min_frequency: u8) -> Config {
+    let mut config = multi_db_config();
+    config.cache.on_disk_path = dir.path().to_string_lossy().into_owned();
+    config.cache.tiered = true;
+    config.cache.promotion_min_frequency = promotion_min_frequency;
+    config
+}
+
+/// Returns the global `CACHE.STATS` counter `name`.
+async fn cache_stat(ctx: &TestContext, name: &str) -> i64 {
+    let RespValue::Array(stats) = cache_cmd(ctx, &["STATS"]).await else {
+        panic!("Expected CACHE.STATS to return an array");
+    };
+    match stats
+        .chunks(2)
+        .find(|pair| pair[0] == RespValue::BulkString(Bytes::from(name.to_string())))
+    {
+        Some([_, RespValue::Integer(value)]) => *value,
+        other => panic!("Expected an integer '{name}' field, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_cache_tiered_demotes_cold_bodies_to_disk() {
+    let dir = tempfile::tempdir().unwrap();
+    let ctx = TestContext::with_config(tiered_config(&dir, u8::MAX)).await;
+    let manifest_path = dir.path().join("spineldb-cache.manifest");
+    let manifest_file = tokio::fs::File::create(&manifest_path).await.unwrap();
+    *ctx.state.cache.manifest_writer.lock().await = Some(tokio::io::BufWriter::new(manifest_file));
+    let body = "x".repeat(4096);
+
+    cache_cmd(&ctx, &["SET", "page", &body, "TTL", "60"]).await;
+    let memory_before = ctx.db.get_current_memory();
+
+    assert!(ctx.db.free_memory(&ctx.state).await);
+    assert_eq!(cache_stat(&ctx, "demotions").await, 1);
+    assert_eq!(cache_stat(&ctx, "evictions").await, 0);
+    assert!(ctx.db.get_current_memory() + 4096 <= memory_before);
+
+    // The body is served from disk, and the file is committed to the manifest.
+    assert_eq!(cached_body(&ctx, "page").await, Some(Bytes::from(body)));
+    let manifest = std::fs::read_to_string(&manifest_path).unwrap();
+    let committed: Vec<serde_json::Value> = manifest
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .filter(|entry| entry["state"] == "Committed")
+        .collect();
+    assert_eq!(committed.len(), 1);
+    assert!(std::path::Path::new(committed[0]["path"].as_str().unwrap()).exists());
+}
+
+#[tokio::test]
+async fn test_cache_tiered_write_path_evicts_without_demoting() {
+    use spineldb::config::EvictionPolicy;
+    use spineldb::connection::SessionState;
+    use spineldb::core::handler::command_router::Router;
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut config = tiered_config(&dir, u8::MAX);
+    config.maxmemory = Some(1);
+    config.maxmemory_policy = EvictionPolicy::AllkeysLru;
+    let ctx = TestContext::with_config(config).await;
+    cache_cmd(&ctx, &["SET", "page", &"x".repeat(4096), "TTL", "60"]).await;
+
+    // A write above maxmemory frees memory by evicting, which samples random shards and
+    // may miss the key, but never demotes it. Demotion is left to the background
+    // eviction manager.
+    let mut session = SessionState {
+        is_authenticated: true,
+        is_in_transaction: false,
+        is_asking: false,
+        is_readonly: false,
+        is_cross_slot: false,
+        is_subscribed: false,
+        is_pattern_subscribed: false,
+        is_shard_subscribed: false,
+        subscribed_channels: Default::default(),
+        subscribed_patterns: Default::default(),
+        subscribed_shard_channels: Default::default(),
+        pubsub_receivers: Vec::new(),
+        current_db_index: 0,
+        authenticated_user: None,
+        auth_password: None,
+    };
+    let frames = ["DEL", "other"]
+        .into_iter()
+        .map(|arg| RespFrame::BulkString(Bytes::from_static(arg.as_bytes())))
+        .collect();
+    let command = Command::try_from(RespFrame::Array(frames)).unwrap();
+    Router::new(
+        ctx.state.clone(),
+        1,
+        "127.0.0.1:50000".parse().unwrap(),
+        &mut session,
+    )
+    .route(command)
+    .await
+    .unwrap();
+
+    assert_eq!(cache_stat(&ctx, "demotions").await, 0);
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+}
+
+#[tokio::test]
+async fn test_cache_tiered_promotes_hot_bodies_to_memory() {
+    let dir = tempfile::tempdir().unwrap();
+    let ctx = TestContext::with_config(tiered_config(&dir, 0)).await;
+    let body = "y".repeat(4096);
+
+    cache_cmd(&ctx, &["SET", "page", &body, "TTL", "60"]).await;
+    assert!(ctx.db.free_memory(&ctx.state).await);
+    let demoted_memory = ctx.db.get_current_memory();
+
+    assert_eq!(
+        cached_body(&ctx, "page").await,
+        Some(Bytes::from(body.clone()))
+    );
+    for _ in 0..100 {
+        if cache_stat(&ctx, "promotions").await == 1 {
+            break;
+        }
+        sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(cache_stat(&ctx, "promotions").await, 1);
+    assert!(ctx.db.get_current_memory() >= demoted_memory + 4096);
+    assert_eq!(cached_body(&ctx, "page").await, Some(Bytes::from(body)));
+}
+
+#[tokio::test]
+async fn test_cache_tiered_missing_body_file_is_a_miss() {
+    let dir = tempfile::tempdir().unwrap();
+    let ctx = TestContext::with_config(tiered_config(&dir, u8::MAX)).await;
+    cache_cmd(&ctx, &["SET", "page", &"z".repeat(4096), "TTL", "60"]).await;
+    assert!(ctx.db.free_memory(&ctx.state).await);
+    assert_eq!(cache_stat(&ctx, "demotions").await, 1);
+
+    // The entry outlives its file, as after a restart from a snapshot taken before the
+    // body was promoted and the file deleted.
+    for file in std::fs::read_dir(dir.path()).unwrap() {
+        std::fs::remove_file(file.unwrap().path()).unwrap();
+    }
+    assert_eq!(cached_body(&ctx, "page").await, None);
+    assert_eq!(ctx.exists(&["page"]).await.unwrap(), RespValue::Integer(0));
+}
+
+#[tokio::test]
+async fn test_cache_tiered_disk_budget_evicts_least_recently_used_bodies() {
+    use spineldb::core::tasks::on_disk_cache_eviction::OnDiskCacheEvictionTask;
+
+    let dir = tempfile::tempdir().unwrap();
+    let ctx = TestContext::with_config(tiered_config(&dir, u8::MAX)).await;
+    let manifest_path = dir.path().join("spineldb-cache.manifest");
+    let manifest_file = tokio::fs::File::create(&manifest_path).await.unwrap();
+    *ctx.state.cache.manifest_writer.lock().await = Some(tokio::io::BufWriter::new(manifest_file));
+
+    cache_cmd(&ctx, &["SET", "old", &"a".repeat(100), "TTL", "60"]).await;
+    cache_cmd(&ctx, &["SET", "new", &"b".repeat(100), "TTL", "60"]).await;
+    assert!(ctx.db.free_memory(&ctx.state).await);
+    assert!(ctx.db.free_memory(&ctx.state).await);
+    assert_eq!(cache_stat(&ctx, "demotions").await, 2);
+
+    // Accessing "new" leaves "old" as the least recently used body on disk.
+    sleep(Duration::from_millis(10)).await;
+    assert!(cached_body(&ctx, "new").await.is_some());
+
+    OnDiskCacheEvictionTask::new(ctx.state.clone())
+        .perform_eviction_cycle(150)
+        .await
+        .unwrap();
+    assert_eq!(cache_stat(&ctx, "evictions").await, 1);
+    assert_eq!(cached_body(&ctx, "old").await, None);
+    assert_eq!(
+        cached_body(&ctx, "new").await,
+        Some(Bytes::from("b".repeat(100)))
+    );
+}
+
+#[tokio::test]
+async fn test_cache_tiered_skips_databases_without_cache_keys() {
+    let dir = tempfile::tempdir().unwrap();
+    let ctx = TestContext::with_config(tiered_config(&dir, u8::MAX)).await;
+
+    for i in 0..64 {
+        ctx.set(&format!("plain:{i}"), "value").await.unwrap();
+    }
+    assert_eq!(ctx.db.get_cache_key_count(), 0);
+    let cache_path = dir.path().to_string_lossy().into_owned();
+    assert_eq!(
+        ctx.db.demote_cold_cache_key(&ctx.state, &cache_path).await,
+        0
+    );
+
+    // Cache keys are counted as they are stored, replaced and deleted.
+    cache_cmd(&ctx, &["SET", "page", &"x".repeat(1024), "TTL", "60"]).await;
+    cache_cmd(&ctx, &["SET", "page", &"y".repeat(1024), "TTL", "60"]).await;
+    assert_eq!(ctx.db.get_cache_key_count(), 1);
+    ctx.del(&["page"]).await.unwrap();
+    assert_eq!(ctx.db.get_cache_key_count(), 0);
+}
@@ -4,13 +4,30 @@
 
 use super::test_helpers::TestContext;
 use bytes::Bytes;
+use futures::future::BoxFuture;
+use futures::{SinkExt, StreamExt};
 use spineldb::config::Config;
+use spineldb::connection::SessionState;
 use spineldb::core::Command;
 use spineldb::core::RespValue;
 use spineldb::core::SpinelDBError;
-use spineldb::core::cluster::slot::NUM_SLOTS;
-use spineldb::core::protocol::RespFrame;
+use spineldb::core::cluster::bus::{BusOptions, BusOrigin, ClusterBus};
+use spineldb::core::cluster::failover::{self, FailoverMode};
+use spineldb::core::cluster::gossip::{GossipMessage, now_ms};
+use spineldb::core::cluster::slot::{self, NUM_SLOTS};
+use spineldb::core::cluster::{ClusterNode, NodeFlags, NodeRuntimeState};
+use spineldb::core::commands::command_trait::CommandExt;
+use spineldb::core::database::ExecutionContext;
+use spineldb::core::handler::command_router::{RouteResponse, Router};
+use spineldb::core::protocol::{RespFrame, RespFrameCodec};
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tempfile::TempDir;
+use tokio::net::TcpListener;
+use tokio_util::codec::Framed;
 
 /// Helper to create a test context with cluster mode enabled
 async fn create_cluster_context() -> (TestContext, TempDir) {
@@ -299,3 +316,1992 @@ async fn test_cluster_command_without_cluster_mode() {
         _ => panic!("Expected InvalidState error, got {:?}", result),
     }
 }
+
+// ===== READONLY Replica Read Tests =====
+
+/// Adds a node to the cluster view of `ctx`, assigning it `slots`.
+fn add_node(
+    ctx: &TestContext,
+    id: &str,
+    port: u16,
+    flags: NodeFlags,
+    replica_of: Option<&str>,
+    slots: &[u16],
+) {
+    let cluster = ctx.state.cluster.as_ref().unwrap();
+    let mut node_info = ClusterNode {
+        id: id.to_string(),
+        addr: format!("127.0.0.1:{port}"),
+        bus_addr: format!("127.0.0.1:{}", port + 10000),
+        flags_raw: 0,
+        replica_of: replica_of.map(str::to_string),
+        slots: slots.iter().copied().collect(),
+        config_epoch: 1,
+        replication_offset: 0,
+        migrating_slots: Default::default(),
+        importing_slots: Default::default(),
+    };
+    node_info.set_flags(flags);
+    for &slot in slots {
+        *cluster.slots_map[slot as usize].write() = Some(id.to_string());
+    }
+    cluster.nodes.insert(
+        id.to_string(),
+        NodeRuntimeState {
+            node_info,
+            ping_sent: None,
+            pong_received: None,
+            pfail_reports: HashMap::new(),
+        },
+    );
+}
+
+/// Turns the local node into a replica of `primary_id`.
+fn make_myself_replica_of(ctx: &TestContext, primary_id: &str) {
+    let cluster = ctx.state.cluster.as_ref().unwrap();
+    let mut myself = cluster.nodes.get_mut(&cluster.my_id).unwrap();
+    myself.node_info.replica_of = Some(primary_id.to_string());
+    myself
+        .node_info
+        .set_flags(NodeFlags::MYSELF | NodeFlags::REPLICA);
+}
+
+#[tokio::test]
+async fn test_replica_can_serve_reads_only_for_its_primary_slots() {
+    let (ctx, _temp_dir) = create_cluster_context().await;
+    let primary_id = "a".repeat(40);
+    let other_id = "b".repeat(40);
+    add_node(&ctx, &primary_id, 7001, NodeFlags::PRIMARY, None, &[100]);
+    add_node(&ctx, &other_id, 7002, NodeFlags::PRIMARY, None, &[200]);
+
+    let cluster = ctx.state.cluster.as_ref().unwrap();
+    assert!(!cluster.i_replicate_slot_owner(100));
+
+    make_myself_replica_of(&ctx, &primary_id);
+    assert!(cluster.i_replicate_slot_owner(100));
+    assert!(!cluster.i_replicate_slot_owner(200));
+    assert!(!cluster.i_replicate_slot_owner(300), "unassigned slot");
+}
+
+/// Returns the session of an authenticated client that has not sent any commands.
+fn new_session() -> SessionState {
+    SessionState {
+        is_authenticated: true,
+        is_in_transaction: false,
+        is_asking: false,
+        is_readonly: false,
+        is_cross_slot: false,
+        is_subscribed: false,
+        is_pattern_subscribed: false,
+        is_shard_subscribed: false,
+        subscribed_channels: HashSet::new(),
+        subscribed_patterns: HashSet::new(),
+        subscribed_shard_channels: HashSet::new(),
+        pubsub_receivers: Vec::new(),
+        current_db_index: 0,
+        authenticated_user: None,
+        auth_password: None,
+    }
+}
+
+/// Routes a command through the command router, as a connection of `session` would.
+/// Returns the response if it is a single value.
+async fn route(
+    ctx: &TestContext,
+    session: &mut SessionState,
+    args: &[&str],
+) -> Result<Option<RespValue>, SpinelDBError> {
+    let frames = args
+        .iter()
+        .map(|arg| RespFrame::BulkString(Bytes::from(arg.to_string())))
+        .collect();
+    let command = Command::try_from(RespFrame::Array(frames))?;
+    Router::new(
+        ctx.state.clone(),
+        1,
+        "127.0.0.1:50000".parse().unwrap(),
+        session,
+    )
+    .route(command)
+    .await
+    .map(|response| match response {
+        RouteResponse::Single(value) => Some(value),
+        _ => None,
+    })
+}
+
+#[tokio::test]
+async fn test_replica_serves_shard_subscriptions_for_its_primary_slots() {
+    let (ctx, _temp_dir) = create_cluster_context().await;
+    let primary_id = "a".repeat(40);
+    let other_id = "b".repeat(40);
+    let channel = "{room}:a";
+    let other_channel = "{other}:a";
+    add_node(
+        &ctx,
+        &primary_id,
+        7001,
+        NodeFlags::PRIMARY,
+        None,
+        &[slot::get_slot(&Bytes::from(channel))],
+    );
+    add_node(
+        &ctx,
+        &other_id,
+        7002,
+        NodeFlags::PRIMARY,
+        None,
+        &[slot::get_slot(&Bytes::from(other_channel))],
+    );
+    let mut session = new_session();
+
+    // A node that does not replicate the owner redirects the subscriber.
+    let result = route(&ctx, &mut session, &["SSUBSCRIBE", channel]).await;
+    assert!(
+        matches!(result, Err(SpinelDBError::Moved { .. })),
+        "got {result:?}"
+    );
+
+    // A replica of the owner serves it, without READONLY, and delivers the messages
+    // published on the owner, which reach it through replication.
+    make_myself_replica_of(&ctx, &primary_id);
+    let result = route(&ctx, &mut session, &["SSUBSCRIBE", channel]).await;
+    assert!(result.is_ok(), "got {result:?}");
+    assert!(session.is_shard_subscribed);
+    assert_eq!(
+        ctx.state
+            .pubsub
+            .publish_shard(&Bytes::from(channel), Bytes::from("hello")),
+        1
+    );
+
+    let result = route(&ctx, &mut session, &["SSUBSCRIBE", other_channel]).await;
+    assert!(
+        matches!(result, Err(SpinelDBError::Moved { .. })),
+        "got {result:?}"
+    );
+
+    let result = route(&ctx, &mut session, &["SUNSUBSCRIBE", channel]).await;
+    assert!(result.is_ok(), "got {result:?}");
+    assert!(session.subscribed_shard_channels.is_empty());
+}
+
+#[tokio::test]
+async fn test_cluster_slots_reports_reachable_replicas() {
+    let (ctx, _temp_dir) = create_cluster_context().await;
+    let primary_id = "a".repeat(40);
+    let replica_id = "c".repeat(40);
+    let failed_replica_id = "d".repeat(40);
+    add_node(
+        &ctx,
+        &primary_id,
+        7001,
+        NodeFlags::PRIMARY,
+        None,
+        &[0, 1, 2],
+    );
+    add_node(
+        &ctx,
+        &replica_id,
+        7003,
+        NodeFlags::REPLICA,
+        Some(&primary_id),
+        &[],
+    );
+    add_node(
+        &ctx,
+        &failed_replica_id,
+        7004,
+        NodeFlags::REPLICA | NodeFlags::FAIL,
+        Some(&primary_id),
+        &[],
+    );
+
+    let result = execute_cluster(&ctx, "SLOTS", vec![]).await.unwrap();
+    let RespValue::Array(ranges) = result else {
+        panic!("Expected array from CLUSTER SLOTS");
+    };
+    assert_eq!(ranges.len(), 1);
+    let RespValue::Array(range) = &ranges[0] else {
+        panic!("Expected slot range array");
+    };
+    assert_eq!(range[0], RespValue::Integer(0));
+    assert_eq!(range[1], RespValue::Integer(2));
+    // Start, end, the primary and one reachable replica.
+    assert_eq!(range.len(), 4);
+    let RespValue::Array(replica) = &range[3] else {
+        panic!("Expected replica endpoint array");
+    };
+    assert_eq!(replica[1], RespValue::Integer(7003));
+    assert_eq!(replica[2], RespValue::BulkString(replica_id.into()));
+}
+
+// ===== CLUSTER Operability Subcommand Tests =====
+
+#[tokio::test]
+async fn test_cluster_keyslot() {
+    let (ctx, _temp_dir) = create_cluster_context().await;
+
+    let result = execute_cluster(&ctx, "KEYSLOT", vec!["somekey"])
+        .await
+        .unwrap();
+    let expected = slot::get_slot(&Bytes::from_static(b"somekey"));
+    assert_eq!(result, RespValue::Integer(expected as i64));
+
+    // Keys sharing a hash tag map to the same slot.
+    let a = execute_cluster(&ctx, "KEYSLOT", vec!["{user1000}.following"]).await;
+    let b = execute_cluster(&ctx, "KEYSLOT", vec!["{user1000}.followers"]).await;
+    assert_eq!(a.unwrap(), b.unwrap());
+}
+
+#[tokio::test]
+async fn test_cluster_countkeysinslot() {
+    let (ctx, _temp_dir) = create_cluster_context().await;
+    ctx.set("{tag}a", "1").await.unwrap();
+    ctx.set("{tag}b", "2").await.unwrap();
+    ctx.set("other", "3").await.unwrap();
+
+    let RespValue::Integer(slot) = execute_cluster(&ctx, "KEYSLOT", vec!["{tag}a"])
+        .await
+        .unwrap()
+    else {
+        panic!("Expected integer slot");
+    };
+    let slot = slot.to_string();
+    let result = execute_cluster(&ctx, "COUNTKEYSINSLOT", vec![&slot])
+        .await
+        .unwrap();
+    assert_eq!(result, RespValue::Integer(2));
+
+    let result = execute_cluster(&ctx, "COUNTKEYSINSLOT", vec!["16384"]).await;
+    assert!(result.is_err(), "Slot out of range should fail");
+}
+
+#[tokio::test]
+async fn test_cluster_addslotsrange_and_delslotsrange() {
+    let (ctx, _temp_dir) = create_cluster_context().await;
+    let cluster = ctx.state.cluster.as_ref().unwrap();
+
+    execute_cluster(&ctx, "ADDSLOTSRANGE", vec!["0", "9", "20", "29"])
+        .await
+        .unwrap();
+    assert_eq!(cluster.get_my_config().node_info.slots.len(), 20);
+    assert!(cluster.i_own_slot(25));
+
+    execute_cluster(&ctx, "DELSLOTSRANGE", vec!["5", "9"])
+        .await
+        .unwrap();
+    execute_cluster(&ctx, "DELSLOTS", vec!["20"]).await.unwrap();
+    assert_eq!(cluster.get_my_config().node_info.slots.len(), 14);
+    assert!(!cluster.i_own_slot(7));
+    assert!(cluster.slots_map[20].read().is_none());
+
+    // Deleting an unassigned slot fails and changes nothing.
+    let result = execute_cluster(&ctx, "DELSLOTS", vec!["21", "20"]).await;
+    assert!(result.is_err());
+    assert!(cluster.i_own_slot(21));
+
+    let result = execute_cluster(&ctx, "ADDSLOTSRANGE", vec!["10", "5"]).await;
+    assert!(result.is_err(), "Start greater than end should fail");
+    let result = execute_cluster(&ctx, "ADDSLOTSRANGE", vec!["10"]).await;
+    assert!(result.is_err(), "Unpaired range should fail");
+}
+
+fn info_field(info: &RespValue, field: &str) -> String {
+    let RespValue::BulkString(text) = info else {
+        panic!("Expected bulk string from CLUSTER INFO");
+    };
+    String::from_utf8_lossy(text)
+        .lines()
+        .find_map(|line| line.strip_prefix(&format!("{field}:")).map(str::to_string))
+        .unwrap_or_else(|| panic!("Missing field {field}"))
+}
+
+#[tokio::test]
+async fn test_cluster_info() {
+    let (ctx, _temp_dir) = create_cluster_context().await;
+
+    let info = execute_cluster(&ctx, "INFO", vec![]).await.unwrap();
+    assert_eq!(info_field(&info, "cluster_state"), "fail");
+    assert_eq!(info_field(&info, "cluster_slots_assigned"), "0");
+    assert_eq!(info_field(&info, "cluster_known_nodes"), "1");
+    assert_eq!(info_field(&info, "cluster_rebalance_status"), "idle");
+
+    let last = (NUM_SLOTS - 1).to_string();
+    execute_cluster(&ctx, "ADDSLOTSRANGE", vec!["0", &last])
+        .await
+        .unwrap();
+    let info = execute_cluster(&ctx, "INFO", vec![]).await.unwrap();
+    assert_eq!(info_field(&info, "cluster_state"), "ok");
+    assert_eq!(info_field(&info, "cluster_slots_ok"), NUM_SLOTS.to_string());
+    assert_eq!(info_field(&info, "cluster_size"), "1");
+}
+
+#[tokio::test]
+async fn test_cluster_shards_reports_slot_ranges_and_replicas() {
+    let (ctx, _temp_dir) = create_cluster_context().await;
+    let primary_id = "a".repeat(40);
+    let replica_id = "c".repeat(40);
+    add_node(
+        &ctx,
+        &primary_id,
+        7001,
+        NodeFlags::PRIMARY,
+        None,
+        &[0, 1, 2, 10],
+    );
+    add_node(
+        &ctx,
+        &replica_id,
+        7003,
+        NodeFlags::REPLICA | NodeFlags::PFAIL,
+        Some(&primary_id),
+        &[],
+    );
+
+    let RespValue::Array(shards) = execute_cluster(&ctx, "SHARDS", vec![]).await.unwrap() else {
+        panic!("Expected array from CLUSTER SHARDS");
+    };
+    // The local node is an empty primary shard of its own.
+    assert_eq!(shards.len(), 2);
+    let shard = shards
+        .iter()
+        .find(|shard| {
+            let RespValue::Array(fields) = shard else {
+                return false;
+            };
+            fields[1]
+                == RespValue::Array(vec![
+                    RespValue::Integer(0),
+                    RespValue::Integer(2),
+                    RespValue::Integer(10),
+                    RespValue::Integer(10),
+                ])
+        })
+        .expect("Shard of the primary not found");
+    let RespValue::Array(fields) = shard else {
+        unreachable!()
+    };
+    let RespValue::Array(nodes) = &fields[3] else {
+        panic!("Expected node list");
+    };
+    assert_eq!(nodes.len(), 2);
+    let RespValue::Array(replica) = &nodes[1] else {
+        panic!("Expected replica fields");
+    };
+    assert_eq!(replica[1], RespValue::BulkString(replica_id.into()));
+    assert_eq!(replica[3], RespValue::Integer(7003));
+    assert_eq!(replica[9], RespValue::BulkString("replica".into()));
+    assert_eq!(replica[13], RespValue::BulkString("fail".into()));
+}
+
+#[tokio::test]
+async fn test_cluster_rebalance_simulate_plans_without_moving() {
+    let (ctx, _temp_dir) = create_cluster_context().await;
+    let cluster = ctx.state.cluster.as_ref().unwrap();
+    let my_id = cluster.my_id.clone();
+    let empty_id = "e".repeat(40);
+    let last = (NUM_SLOTS - 1).to_string();
+    execute_cluster(&ctx, "ADDSLOTSRANGE", vec!["0", &last])
+        .await
+        .unwrap();
+    add_node(&ctx, &empty_id, 7002, NodeFlags::PRIMARY, None, &[]);
+
+    let result = execute_cluster(&ctx, "REBALANCE", vec!["SIMULATE"])
+        .await
+        .unwrap();
+    assert_eq!(
+        result,
+        RespValue::Array(vec![RespValue::BulkString(
+            format!("move 8192 slots from {my_id} to {empty_id}").into()
+        )])
+    );
+    assert_eq!(cluster.get_my_config().node_info.slots.len(), NUM_SLOTS);
+
+    // Weights are matched by node ID prefix.
+    let weight = format!("{}=3", &empty_id[..8]);
+    let result = execute_cluster(&ctx, "REBALANCE", vec!["WEIGHT", &weight, "SIMULATE"])
+        .await
+        .unwrap();
+    assert_eq!(
+        result,
+        RespValue::Array(vec![RespValue::BulkString(
+            format!("move 12288 slots from {my_id} to {empty_id}").into()
+        )])
+    );
+
+    let result = execute_cluster(&ctx, "REBALANCE", vec!["WEIGHT", "nope=1", "SIMULATE"]).await;
+    assert!(result.is_err(), "Unknown node in WEIGHT should fail");
+    let result = execute_cluster(&ctx, "REBALANCE", vec!["THRESHOLD"]).await;
+    assert!(result.is_err(), "THRESHOLD without a value should fail");
+}
+
+// ===== CLUSTER FAILOVER Tests =====
+
+/// Polls `condition` for up to five seconds.
+async fn eventually(condition: impl Fn() -> bool) -> bool {
+    for _ in 0..500 {
+        if condition() {
+            return true;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    false
+}
+
+fn i_am_primary(ctx: &TestContext) -> bool {
+    let cluster = ctx.state.cluster.as_ref().unwrap();
+    cluster
+        .get_my_config()
+        .node_info
+        .get_flags()
+        .contains(NodeFlags::PRIMARY)
+}
+
+#[tokio::test]
+async fn test_cluster_failover_rejects_primaries_and_bad_options() {
+    let (ctx, _temp_dir) = create_cluster_context().await;
+
+    let result = execute_cluster(&ctx, "FAILOVER", vec![]).await;
+    assert!(result.is_err(), "FAILOVER on a primary should fail");
+    let result = execute_cluster(&ctx, "FAILOVER", vec!["TAKEOVER"]).await;
+    assert!(
+        result.is_err(),
+        "FAILOVER TAKEOVER on a primary should fail"
+    );
+
+    let result = execute_cluster(&ctx, "FAILOVER", vec!["NOW"]).await;
+    assert!(matches!(result, Err(SpinelDBError::SyntaxError)));
+    let result = execute_cluster(&ctx, "FAILOVER", vec!["FORCE", "TAKEOVER"]).await;
+    assert!(matches!(result, Err(SpinelDBError::WrongArgumentCount(_))));
+}
+
+#[tokio::test]
+async fn test_cluster_failover_requires_force_when_primary_is_down() {
+    let (ctx, _temp_dir) = create_cluster_context().await;
+    let primary_id = "a".repeat(40);
+    add_node(
+        &ctx,
+        &primary_id,
+        7001,
+        NodeFlags::PRIMARY | NodeFlags::FAIL,
+        None,
+        &[0],
+    );
+    make_myself_replica_of(&ctx, &primary_id);
+
+    let result = execute_cluster(&ctx, "FAILOVER", vec![]).await;
+    assert!(
+        matches!(result, Err(SpinelDBError::InvalidState(ref msg)) if msg.contains("FORCE")),
+        "got {result:?}"
+    );
+}
+
+#[tokio::test]
+async fn test_cluster_failover_takeover_promotes_without_election() {
+    let (ctx, _temp_dir) = create_cluster_context().await;
+    let cluster = ctx.state.cluster.as_ref().unwrap();
+    let primary_id = "a".repeat(40);
+    add_node(
+        &ctx,
+        &primary_id,
+        7001,
+        NodeFlags::PRIMARY,
+        None,
+        &[0, 1, 2],
+    );
+    make_myself_replica_of(&ctx, &primary_id);
+
+    let result = execute_cluster(&ctx, "FAILOVER", vec!["TAKEOVER"])
+        .await
+        .unwrap();
+    assert_eq!(result, RespValue::SimpleString("OK".into()));
+
+    assert!(
+        eventually(|| i_am_primary(&ctx)).await,
+        "takeover timed out"
+    );
+    let myself = cluster.get_my_config().node_info.clone();
+    assert_eq!(myself.replica_of, None);
+    assert_eq!(
+        myself.slots.iter().copied().collect::<Vec<_>>(),
+        vec![0, 1, 2]
+    );
+    assert!(
+        myself.config_epoch > 1,
+        "takeover must bump the config epoch"
+    );
+    assert!(cluster.i_own_slot(1));
+    assert!(
+        cluster
+            .nodes
+            .get(&primary_id)
+            .unwrap()
+            .node_info
+            .slots
+            .is_empty()
+    );
+}
+
+#[tokio::test]
+async fn test_coordinated_failover_waits_for_primary_offset_then_election() {
+    let (ctx, _temp_dir) = create_cluster_context().await;
+    let cluster = ctx.state.cluster.as_ref().unwrap();
+    let primary_id = "a".repeat(40);
+    add_node(&ctx, &primary_id, 7001, NodeFlags::PRIMARY, None, &[0]);
+    make_myself_replica_of(&ctx, &primary_id);
+    let epoch_before = cluster.failover_auth_epoch.load(Ordering::Relaxed);
+
+    let state = ctx.state.clone();
+    let task = tokio::spawn(async move {
+        failover::run_manual_failover(&state, FailoverMode::Coordinated).await
+    });
+
+    // No election may start before the primary has paused and reported its offset.
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(
+        cluster.failover_auth_epoch.load(Ordering::Relaxed),
+        epoch_before
+    );
+    failover::handle_manual_failover_ack(&ctx.state, primary_id.clone(), 0);
+
+    assert!(
+        eventually(|| cluster.failover_auth_epoch.load(Ordering::Relaxed) > epoch_before).await,
+        "election did not start after catching up"
+    );
+    let election_epoch = cluster.failover_auth_epoch.load(Ordering::Relaxed);
+    failover::handle_auth_ack(&ctx.state, primary_id.clone(), election_epoch).await;
+
+    task.await.unwrap().unwrap();
+    assert!(i_am_primary(&ctx));
+    assert!(cluster.i_own_slot(0));
+}
+
+#[tokio::test]
+async fn test_primary_pauses_writes_for_its_replica_and_reports_offset() {
+    let (ctx, _temp_dir) = create_cluster_context().await;
+    let cluster = ctx.state.cluster.as_ref().unwrap();
+    let replica_id = "b".repeat(40);
+    let stranger_id = "c".repeat(40);
+    add_node(
+        &ctx,
+        &replica_id,
+        7002,
+        NodeFlags::REPLICA,
+        Some(&cluster.my_id),
+        &[],
+    );
+    add_node(&ctx, &stranger_id, 7003, NodeFlags::PRIMARY, None, &[]);
+
+    let (bus, _rx) = ClusterBus::bind(0, BusOptions::default()).await.unwrap();
+    let (replica_bus, mut replica_rx) = ClusterBus::bind(0, BusOptions::default()).await.unwrap();
+    let replica_addr = SocketAddr::from(([127, 0, 0, 1], replica_bus.local_addr().unwrap().port()));
+    let origin = BusOrigin::Udp(replica_addr);
+
+    failover::handle_manual_failover_start(&ctx.state, &bus, &origin, stranger_id).await;
+    assert_eq!(
+        cluster.manual_failover_pause_until.load(Ordering::Relaxed),
+        0
+    );
+
+    failover::handle_manual_failover_start(&ctx.state, &bus, &origin, replica_id).await;
+    assert!(cluster.manual_failover_pause_until.load(Ordering::Relaxed) > now_ms());
+    let (envelope, _) = tokio::time::timeout(Duration::from_secs(5), replica_rx.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    match envelope.message {
+        GossipMessage::ManualFailoverAck {
+            sender_id,
+            replication_offset,
+            ..
+        } => {
+            assert_eq!(sender_id, cluster.my_id);
+            assert_eq!(
+                replication_offset,
+                ctx.state.replication.get_replication_offset()
+            );
+        }
+        other => panic!("expected ManualFailoverAck, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_primary_reports_offset_only_after_writes_in_flight_finish() {
+    let (ctx, _temp_dir) = create_cluster_context().await;
+    let cluster = ctx.state.cluster.as_ref().unwrap();
+    let replica_id = "b".repeat(40);
+    add_node(
+        &ctx,
+        &replica_id,
+        7002,
+        NodeFlags::REPLICA,
+        Some(&cluster.my_id),
+        &[],
+    );
+
+    let (bus, _rx) = ClusterBus::bind(0, BusOptions::default()).await.unwrap();
+    let (replica_bus, mut replica_rx) = ClusterBus::bind(0, BusOptions::default()).await.unwrap();
+    let replica_addr = SocketAddr::from(([127, 0, 0, 1], replica_bus.local_addr().unwrap().port()));
+    let origin = BusOrigin::Udp(replica_addr);
+
+    // A write admitted before the pause is still running when the replica asks.
+    let in_flight = cluster.begin_write(&[Bytes::from_static(b"key")]).await;
+    let state = ctx.state.clone();
+    let pause = tokio::spawn(async move {
+        failover::handle_manual_failover_start(&state, &bus, &origin, replica_id).await;
+    });
+
+    assert!(eventually(|| cluster.is_write_paused()).await);
+    assert!(
+        tokio::time::timeout(Duration::from_millis(200), replica_rx.recv())
+            .await
+            .is_err(),
+        "the offset was reported while a write was still in progress"
+    );
+    // New writes wait out the pause instead of being admitted.
+    assert!(
+        tokio::time::timeout(
+            Duration::from_millis(50),
+            cluster.begin_write(&[Bytes::from_static(b"other")])
+        )
+        .await
+        .is_err()
+    );
+
+    drop(in_flight);
+    let (envelope, _) = tokio::time::timeout(Duration::from_secs(5), replica_rx.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(matches!(
+        envelope.message,
+        GossipMessage::ManualFailoverAck { .. }
+    ));
+    pause.await.unwrap();
+}
+
+/// Starts `command` in the background with a cluster write admission, as the router
+/// does for blocking commands, and returns once it holds the admission.
+async fn spawn_admitted(
+    ctx: &TestContext,
+    command: Command,
+) -> tokio::task::JoinHandle<Result<RespValue, SpinelDBError>> {
+    let state = ctx.state.clone();
+    let db = ctx.db.clone();
+    let keys = command.get_keys();
+    let cluster = ctx.state.cluster.as_ref().unwrap();
+    let write_guard = cluster.begin_write(&keys).await;
+    tokio::spawn(async move {
+        let mut exec_ctx = ExecutionContext {
+            state,
+            locks: db.determine_locks_for_command(&command).await,
+            db: &db,
+            command: Some(command.clone()),
+            session_id: 1,
+            authenticated_user: None,
+            write_guard: Some(write_guard),
+        };
+        command.execute(&mut exec_ctx).await.map(|(value, _)| value)
+    })
+}
+
+#[tokio::test]
+async fn test_client_blocked_in_blpop_does_not_hold_up_failover() {
+    let (ctx, _temp_dir) = create_cluster_context().await;
+    let cluster = ctx.state.cluster.as_ref().unwrap();
+    let slot = slot::get_slot(&Bytes::from_static(b"queue"));
+    execute_cluster(&ctx, "ADDSLOTS", vec![&slot.to_string()])
+        .await
+        .unwrap();
+    let replica_id = "b".repeat(40);
+    add_node(
+        &ctx,
+        &replica_id,
+        7002,
+        NodeFlags::REPLICA,
+        Some(&cluster.my_id),
+        &[],
+    );
+
+    let blpop = Command::try_from(RespFrame::Array(vec![
+        RespFrame::BulkString("BLPOP".into()),
+        RespFrame::BulkString("queue".into()),
+        RespFrame::BulkString("0".into()),
+    ]))
+    .unwrap();
+    let blocked = spawn_admitted(&ctx, blpop).await;
+    // Give the client time to find the list empty and block.
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let (bus, _rx) = ClusterBus::bind(0, BusOptions::default()).await.unwrap();
+    let (replica_bus, mut replica_rx) = ClusterBus::bind(0, BusOptions::default()).await.unwrap();
+    let replica_addr = SocketAddr::from(([127, 0, 0, 1], replica_bus.local_addr().unwrap().port()));
+    let origin = BusOrigin::Udp(replica_addr);
+    let state = ctx.state.clone();
+    tokio::spawn(async move {
+        failover::handle_manual_failover_start(&state, &bus, &origin, replica_id).await;
+    });
+
+    let (envelope, _) = tokio::time::timeout(Duration::from_secs(2), replica_rx.recv())
+        .await
+        .expect("the offset was not reported while a client was blocked")
+        .unwrap();
+    assert!(matches!(
+        envelope.message,
+        GossipMessage::ManualFailoverAck { .. }
+    ));
+    assert!(!blocked.is_finished());
+    blocked.abort();
+}
+
+#[tokio::test]
+async fn test_write_pause_ends_at_its_deadline() {
+    let (ctx, _temp_dir) = create_cluster_context().await;
+    let cluster = ctx.state.cluster.as_ref().unwrap();
+
+    cluster
+        .manual_failover_pause_until
+        .store(now_ms() + 200, Ordering::Relaxed);
+    let started = std::time::Instant::now();
+    cluster.wait_for_write_pause().await;
+    assert!(started.elapsed() >= Duration::from_millis(150));
+
+    cluster
+        .manual_failover_pause_until
+        .store(0, Ordering::Relaxed);
+    let started = std::time::Instant::now();
+    cluster.wait_for_write_pause().await;
+    assert!(started.elapsed() < Duration::from_millis(50));
+}
+
+#[tokio::test]
+async fn test_write_pause_wakes_writes_when_it_ends() {
+    let (ctx, _temp_dir) = create_cluster_context().await;
+    let cluster = ctx.state.cluster.as_ref().unwrap();
+
+    cluster.pause_writes(now_ms() + 10_000);
+    let state = ctx.state.clone();
+    let waiter = tokio::spawn(async move {
+        let _write = state
+            .cluster
+            .as_ref()
+            .unwrap()
+            .begin_write(&[Bytes::from_static(b"key")])
+            .await;
+    });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(!waiter.is_finished());
+
+    // The write is admitted as soon as the pause ends, not at its deadline.
+    cluster.resume_writes();
+    tokio::time::timeout(Duration::from_secs(1), waiter)
+        .await
+        .unwrap()
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_unauthenticated_write_is_not_held_by_write_pause() {
+    let (ctx, _temp_dir) = create_cluster_context().await;
+    let cluster = ctx.state.cluster.as_ref().unwrap();
+    cluster.pause_writes(now_ms() + 10_000);
+
+    let mut session = new_session();
+    session.is_authenticated = false;
+    let result = tokio::time::timeout(
+        Duration::from_secs(1),
+        route(&ctx, &mut session, &["SET", "key", "value"]),
+    )
+    .await
+    .expect("an unauthenticated write waited for the pause");
+    assert!(matches!(result, Err(SpinelDBError::AuthRequired)));
+    assert_eq!(cluster.manual_failover_in_flight.count(), 0);
+}
+
+#[tokio::test]
+async fn test_gossip_from_newer_primary_reassigns_its_slots() {
+    let (ctx, _temp_dir) = create_cluster_context().await;
+    let cluster = ctx.state.cluster.as_ref().unwrap();
+    let old_primary_id = "a".repeat(40);
+    let new_primary_id = "b".repeat(40);
+    add_node(&ctx, &old_primary_id, 7001, NodeFlags::PRIMARY, None, &[10]);
+    add_node(
+        &ctx,
+        &new_primary_id,
+        7002,
+        NodeFlags::REPLICA,
+        Some(&old_primary_id),
+        &[],
+    );
+
+    let mut promoted = cluster
+        .nodes
+        .get(&new_primary_id)
+        .unwrap()
+        .node_info
+        .clone();
+    promoted.set_flags(NodeFlags::PRIMARY);
+    promoted.replica_of = None;
+    promoted.slots = [10].into_iter().collect();
+
+    // A claim with an epoch that is not newer than the owner's is ignored.
+    cluster.merge_node_info(promoted.clone(), &ctx.state).await;
+    assert_eq!(
+        cluster.get_node_for_slot(10).unwrap().node_info.id,
+        old_primary_id
+    );
+
+    promoted.config_epoch = 2;
+    cluster.merge_node_info(promoted, &ctx.state).await;
+    assert_eq!(
+        cluster.get_node_for_slot(10).unwrap().node_info.id,
+        new_primary_id
+    );
+}
+
+mod rebalance_plan {
+    use spineldb::core::cluster::rebalance::{RebalanceNode, plan, plan_by_load};
+
+    fn node(id: &str, slots: std::ops::Range<u16>, weight: f64) -> RebalanceNode {
+        RebalanceNode {
+            id: id.to_string(),
+            slots: slots.collect(),
+            weight,
+        }
+    }
+
+    #[test]
+    fn test_plan_is_empty_when_balanced_within_threshold() {
+        let nodes = [node("a", 0..100, 1.0), node("b", 100..201, 1.0)];
+        assert!(plan(&nodes, 2.0).unwrap().is_empty());
+        assert!(!plan(&nodes, 0.0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_plan_moves_only_the_surplus() {
+        let nodes = [
+            node("a", 0..300, 1.0),
+            node("b", 300..300, 1.0),
+            node("c", 300..300, 1.0),
+        ];
+        let moves = plan(&nodes, 2.0).unwrap();
+        assert_eq!(moves.len(), 2);
+        assert!(
+            moves
+                .iter()
+                .all(|m| m.source == "a" && m.slots.len() == 100)
+        );
+        let total: usize = moves.iter().map(|m| m.slots.len()).sum();
+        assert_eq!(total, 200);
+        // The highest slots are moved first.
+        assert!(moves.iter().flat_map(|m| &m.slots).all(|&s| s >= 100));
+    }
+
+    #[test]
+    fn test_plan_drains_zero_weight_nodes() {
+        let nodes = [node("a", 0..10, 0.0), node("b", 10..20, 1.0)];
+        let moves = plan(&nodes, 50.0).unwrap();
+        assert_eq!(moves.len(), 1);
+        assert_eq!(moves[0].source, "a");
+        assert_eq!(moves[0].destination, "b");
+        assert_eq!(moves[0].slots, (0..10).collect::<Vec<u16>>());
+    }
+
+    #[test]
+    fn test_plan_rejects_all_zero_weights() {
+        let nodes = [node("a", 0..10, 0.0)];
+        assert!(plan(&nodes, 2.0).is_err());
+    }
+
+    #[test]
+    fn test_plan_by_load_with_equal_loads_matches_slot_counts() {
+        let nodes = [node("a", 0..300, 1.0), node("b", 300..300, 1.0)];
+        let moves = plan_by_load(&nodes, &[1.0, 1.0], 2.0).unwrap();
+        assert_eq!(moves.len(), 1);
+        assert_eq!(moves[0].source, "a");
+        assert_eq!(moves[0].slots, (150..300).collect::<Vec<u16>>());
+    }
+
+    #[test]
+    fn test_plan_by_load_moves_slots_off_the_heavier_node() {
+        // Both nodes hold 100 slots, but the slots of "a" use three times the memory.
+        let nodes = [node("a", 0..100, 1.0), node("b", 100..200, 1.0)];
+        assert!(plan(&nodes, 2.0).unwrap().is_empty());
+        let moves = plan_by_load(&nodes, &[3.0, 1.0], 2.0).unwrap();
+        assert_eq!(moves.len(), 1);
+        assert_eq!(moves[0].source, "a");
+        assert_eq!(moves[0].destination, "b");
+        // Of the 400 units in total, "a" keeps 67 slots (201) and "b" gets 199.
+        assert_eq!(moves[0].slots, (67..100).collect::<Vec<u16>>());
+    }
+
+    #[test]
+    fn test_plan_by_load_respects_the_threshold() {
+        let nodes = [node("a", 0..100, 1.0), node("b", 100..200, 1.0)];
+        assert!(plan_by_load(&nodes, &[1.05, 1.0], 10.0).unwrap().is_empty());
+        assert!(!plan_by_load(&nodes, &[1.05, 1.0], 1.0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_plan_by_load_drains_zero_weight_nodes() {
+        let nodes = [node("a", 0..10, 0.0), node("b", 10..20, 1.0)];
+        let moves = plan_by_load(&nodes, &[5.0, 1.0], 50.0).unwrap();
+        assert_eq!(moves.len(), 1);
+        assert_eq!(moves[0].destination, "b");
+        assert_eq!(moves[0].slots, (0..10).collect::<Vec<u16>>());
+    }
+
+    #[test]
+    fn test_plan_by_load_rejects_invalid_loads() {
+        let nodes = [node("a", 0..10, 1.0), node("b", 10..20, 1.0)];
+        assert!(plan_by_load(&nodes, &[1.0], 2.0).is_err());
+        assert!(plan_by_load(&nodes, &[1.0, -1.0], 2.0).is_err());
+        assert!(plan_by_load(&nodes, &[1.0, f64::NAN], 2.0).is_err());
+    }
+}
+
+// ===== Cluster Bus Transport Tests =====
+
+mod bus {
+    use spineldb::core::cluster::ClusterNode;
+    use spineldb::core::cluster::bus::{BusOptions, BusOrigin, ClusterBus, InboundMessage};
+    use spineldb::core::cluster::gossip::{GossipMessage, now_ms};
+    use std::net::SocketAddr;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+
+    const PASSWORD: Option<&str> = Some("bus-secret");
+
+    fn password() -> Option<String> {
+        PASSWORD.map(str::to_string)
+    }
+
+    async fn bind_bus(
+        tcp_enabled: bool,
+        udp_heartbeats: bool,
+    ) -> (Arc<ClusterBus>, mpsc::Receiver<InboundMessage>, SocketAddr) {
+        let options = BusOptions {
+            tcp_enabled,
+            udp_heartbeats,
+            tls_acceptor: None,
+        };
+        let (bus, rx) = ClusterBus::bind(0, options).await.unwrap();
+        let port = bus.local_addr().unwrap().port();
+        (bus, rx, SocketAddr::from(([127, 0, 0, 1], port)))
+    }
+
+    async fn recv(rx: &mut mpsc::Receiver<InboundMessage>) -> InboundMessage {
+        tokio::time::timeout(Duration::from_secs(10), rx.recv())
+            .await
+            .expect("timed out waiting for a bus message")
+            .expect("bus closed")
+    }
+
+    fn purge_tags() -> GossipMessage {
+        GossipMessage::PurgeTags {
+            sender_id: "node-a".to_string(),
+            db: 0,
+            tags_with_epoch: vec![(b"tag".to_vec(), 7)],
+            timestamp_ms: now_ms(),
+        }
+    }
+
+    fn ping(gossip_node_count: usize) -> GossipMessage {
+        let gossip_nodes = (0..gossip_node_count)
+            .map(|i| ClusterNode {
+                id: format!("{i:040}"),
+                addr: format!("10.0.0.{}:7000", i % 250),
+                bus_addr: format!("10.0.0.{}:17000", i % 250),
+                flags_raw: 0,
+                replica_of: None,
+                slots: (0..16).collect(),
+                config_epoch: i as u64,
+                replication_offset: 0,
+                migrating_slots: Default::default(),
+                importing_slots: Default::default(),
+            })
+            .collect();
+        GossipMessage::Ping {
+            sender_id: "node-a".to_string(),
+            gossip_nodes,
+            timestamp_ms: now_ms(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reliable_messages_use_tcp_with_hmac_envelope() {
+        let (bus_a, _rx_a, _) = bind_bus(true, true).await;
+        let (_bus_b, mut rx_b, addr_b) = bind_bus(true, true).await;
+
+        bus_a.send(purge_tags(), addr_b, &password()).await;
+
+        let (secure_msg, origin) = recv(&mut rx_b).await;
+        assert!(matches!(origin, BusOrigin::Tcp { .. }));
+        assert!(secure_msg.verify(&password()).unwrap());
+        assert!(!secure_msg.verify(&Some("wrong".to_string())).unwrap());
+        assert!(matches!(
+            secure_msg.message,
+            GossipMessage::PurgeTags { ref tags_with_epoch, .. } if tags_with_epoch[0].1 == 7
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_small_heartbeats_use_udp_fast_path() {
+        let (bus_a, _rx_a, _) = bind_bus(true, true).await;
+        let (_bus_b, mut rx_b, addr_b) = bind_bus(true, true).await;
+
+        bus_a.send(ping(0), addr_b, &password()).await;
+
+        let (secure_msg, origin) = recv(&mut rx_b).await;
+        assert!(matches!(origin, BusOrigin::Udp(_)));
+        assert!(matches!(secure_msg.message, GossipMessage::Ping { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_oversized_heartbeats_fall_back_to_tcp() {
+        let (bus_a, _rx_a, _) = bind_bus(true, true).await;
+        let (_bus_b, mut rx_b, addr_b) = bind_bus(true, true).await;
+
+        bus_a.send(ping(200), addr_b, &password()).await;
+
+        let (secure_msg, origin) = recv(&mut rx_b).await;
+        assert!(matches!(origin, BusOrigin::Tcp { .. }));
+        match secure_msg.message {
+            GossipMessage::Ping { gossip_nodes, .. } => assert_eq!(gossip_nodes.len(), 200),
+            other => panic!("Expected PING, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_heartbeats_use_tcp_when_udp_fast_path_is_disabled() {
+        let (bus_a, _rx_a, _) = bind_bus(true, false).await;
+        let (_bus_b, mut rx_b, addr_b) = bind_bus(true, false).await;
+
+        bus_a.send(ping(0), addr_b, &password()).await;
+
+        let (_, origin) = recv(&mut rx_b).await;
+        assert!(matches!(origin, BusOrigin::Tcp { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_everything_uses_udp_when_tcp_is_disabled() {
+        let (bus_a, _rx_a, _) = bind_bus(false, true).await;
+        let (_bus_b, mut rx_b, addr_b) = bind_bus(false, true).await;
+
+        bus_a.send(purge_tags(), addr_b, &password()).await;
+
+        let (_, origin) = recv(&mut rx_b).await;
+        assert!(matches!(origin, BusOrigin::Udp(_)));
+    }
+
+    #[tokio::test]
+    async fn test_replies_travel_back_over_the_same_tcp_link() {
+        let (bus_a, mut rx_a, _) = bind_bus(true, false).await;
+        let (bus_b, mut rx_b, addr_b) = bind_bus(true, false).await;
+
+        bus_a
+            .send(
+                GossipMessage::Meet {
+                    timestamp_ms: now_ms(),
+                },
+                addr_b,
+                &password(),
+            )
+            .await;
+        let (_, origin) = recv(&mut rx_b).await;
+        bus_b.reply(ping(0), &origin, &password()).await;
+
+        let (secure_msg, origin) = recv(&mut rx_a).await;
+        assert!(matches!(origin, BusOrigin::Tcp { peer, .. } if peer == addr_b));
+        assert!(matches!(secure_msg.message, GossipMessage::Ping { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_messages_queued_while_peer_is_down_are_delivered() {
+        // Reserve a free port, then release it so the peer can bind it later.
+        let port = std::net::UdpSocket::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let addr_b = SocketAddr::from(([127, 0, 0, 1], port));
+
+        let (bus_a, _rx_a, _) = bind_bus(true, true).await;
+        bus_a.send(purge_tags(), addr_b, &password()).await;
+        tokio::time::sleep(Duration::from_millis(300)).await;
+
+        let options = BusOptions {
+            tcp_enabled: true,
+            udp_heartbeats: true,
+            tls_acceptor: None,
+        };
+        let (_bus_b, mut rx_b) = ClusterBus::bind(port, options).await.unwrap();
+
+        let (secure_msg, _) = recv(&mut rx_b).await;
+        assert!(matches!(
+            secure_msg.message,
+            GossipMessage::PurgeTags { .. }
+        ));
+    }
+}
+
+// ===== Fake Cluster Node =====
+
+type Hook = Box<dyn FnOnce() -> BoxFuture<'static, ()> + Send>;
+
+/// Serves another cluster node over TCP, one connection at a time. `ASKING` and `CLIENT`
+/// commands are acknowledged, `CLUSTER` commands are also recorded, and everything
+/// else is executed on `dest`. `hook` runs once, before the first `RESTORE` is answered,
+/// and `cluster_hook` runs once, before the first `CLUSTER` command is answered.
+async fn spawn_fake_node(
+    dest: Arc<TestContext>,
+    hook: Option<Hook>,
+    cluster_hook: Option<Hook>,
+) -> (SocketAddr, Arc<Mutex<Vec<Vec<String>>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let cluster_commands = Arc::new(Mutex::new(Vec::new()));
+    let recorded = cluster_commands.clone();
+    tokio::spawn(async move {
+        let mut hook = hook;
+        let mut cluster_hook = cluster_hook;
+        while let Ok((socket, _)) = listener.accept().await {
+            let mut framed = Framed::new(socket, RespFrameCodec);
+            let mut selected = None;
+            while let Some(Ok(frame)) = framed.next().await {
+                let args: Vec<String> = match &frame {
+                    RespFrame::Array(items) => items
+                        .iter()
+                        .map(|item| match item {
+                            RespFrame::BulkString(b) => String::from_utf8_lossy(b).to_string(),
+                            other => format!("{other:?}"),
+                        })
+                        .collect(),
+                    _ => continue,
+                };
+                let name = args[0].to_ascii_uppercase();
+                let reply = if name == "ASKING" || name == "CLIENT" {
+                    RespFrame::SimpleString("OK".into())
+                } else if name == "SELECT" {
+                    // Run later commands in the selected database of `dest`, if it has it.
+                    let db_index: usize = args[1].parse().unwrap();
+                    selected = dest.state.get_db(db_index).map(|db| TestContext {
+                        state: dest.state.clone(),
+                        db,
+                        db_index,
+                    });
+                    RespFrame::SimpleString("OK".into())
+                } else if name == "CLUSTER" {
+                    if let Some(hook) = cluster_hook.take() {
+                        hook().await;
+                    }
+                    recorded.lock().unwrap().push(args[1..].to_vec());
+                    RespFrame::SimpleString("OK".into())
+                } else {
+                    if name == "RESTORE"
+                        && let Some(hook) = hook.take()
+                    {
+                        hook().await;
+                    }
+                    let command = Command::try_from(frame).unwrap();
+                    match selected.as_ref().unwrap_or(&dest).execute(command).await {
+                        Ok(value) => value.into(),
+                        Err(e) => RespFrame::Error(e.to_string()),
+                    }
+                };
+                if framed.send(reply).await.is_err() {
+                    break;
+                }
+            }
+        }
+    });
+    (addr, cluster_commands)
+}
+
+// ===== Atomic Slot Migration Tests =====
+
+mod atomic_migration {
+    use super::*;
+    use spineldb::core::cluster::migration;
+    use spineldb::core::commands::cluster::{ClusterInfo, ClusterSubcommand};
+
+    const SLOT_KEY: &str = "{mig}";
+
+    async fn source_owning_slot() -> (Arc<TestContext>, TempDir, u16) {
+        let (ctx, temp_dir) = create_cluster_context().await;
+        let slot = slot::get_slot(&Bytes::from_static(SLOT_KEY.as_bytes()));
+        execute_cluster(&ctx, "ADDSLOTS", vec![&slot.to_string()])
+            .await
+            .unwrap();
+        (Arc::new(ctx), temp_dir, slot)
+    }
+
+    fn key(name: &str) -> String {
+        format!("{SLOT_KEY}{name}")
+    }
+
+    #[tokio::test]
+    async fn test_migrate_slot_atomically_moves_keys_and_ownership() {
+        let (source, _temp_dir, slot) = source_owning_slot().await;
+        let dest = Arc::new(TestContext::new().await);
+        let (addr, cluster_commands) = spawn_fake_node(dest.clone(), None, None).await;
+        let dest_id = "d".repeat(40);
+        add_node(
+            &source,
+            &dest_id,
+            addr.port(),
+            NodeFlags::PRIMARY,
+            None,
+            &[],
+        );
+
+        source.set(&key("a"), "1").await.unwrap();
+        source.set(&key("b"), "2").await.unwrap();
+        source.set("elsewhere", "3").await.unwrap();
+
+        let sent = migration::migrate_slot_atomically(&source.state, slot, &dest_id)
+            .await
+            .unwrap();
+        assert_eq!(sent, 2);
+
+        assert_eq!(
+            dest.get(&key("a")).await.unwrap(),
+            RespValue::BulkString("1".into())
+        );
+        assert_eq!(
+            dest.get(&key("b")).await.unwrap(),
+            RespValue::BulkString("2".into())
+        );
+        assert_eq!(source.get(&key("a")).await.unwrap(), RespValue::Null);
+        assert_eq!(
+            source.get("elsewhere").await.unwrap(),
+            RespValue::BulkString("3".into())
+        );
+
+        let cluster = source.state.cluster.as_ref().unwrap();
+        assert!(!cluster.i_own_slot(slot));
+        assert_eq!(
+            cluster.slots_map[slot as usize].read().as_deref(),
+            Some(dest_id.as_str())
+        );
+        assert_eq!(cluster.slot_migration.migrating_slot(), None);
+        assert_eq!(
+            *cluster_commands.lock().unwrap(),
+            vec![vec![
+                "SETSLOT".to_string(),
+                slot.to_string(),
+                "NODE".to_string(),
+                dest_id.clone()
+            ]]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_migrate_slot_atomically_moves_keys_of_every_database() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = Config::default();
+        config.databases = 2;
+        config.persistence.aof_enabled = false;
+        config.persistence.spldb_enabled = false;
+        config.cluster.enabled = true;
+        config.cluster.config_file = temp_dir.path().join("nodes.conf").to_string_lossy().into();
+        let source = TestContext::with_config(config.clone()).await;
+        let slot = slot::get_slot(&Bytes::from_static(SLOT_KEY.as_bytes()));
+        execute_cluster(&source, "ADDSLOTS", vec![&slot.to_string()])
+            .await
+            .unwrap();
+        config.cluster.enabled = false;
+        let dest = Arc::new(TestContext::with_config(config).await);
+        let (addr, _) = spawn_fake_node(dest.clone(), None, None).await;
+        let dest_id = "d".repeat(40);
+        add_node(
+            &source,
+            &dest_id,
+            addr.port(),
+            NodeFlags::PRIMARY,
+            None,
+            &[],
+        );
+        let in_db = |ctx: &TestContext, db_index: usize| TestContext {
+            state: ctx.state.clone(),
+            db: ctx.state.get_db(db_index).unwrap(),
+            db_index,
+        };
+
+        source.set(&key("a"), "db0").await.unwrap();
+        in_db(&source, 1).set(&key("b"), "db1").await.unwrap();
+
+        let sent = migration::migrate_slot_atomically(&source.state, slot, &dest_id)
+            .await
+            .unwrap();
+        assert_eq!(sent, 2);
+
+        assert_eq!(
+            dest.get(&key("a")).await.unwrap(),
+            RespValue::BulkString("db0".into())
+        );
+        assert_eq!(dest.get(&key("b")).await.unwrap(), RespValue::Null);
+        assert_eq!(
+            in_db(&dest, 1).get(&key("b")).await.unwrap(),
+            RespValue::BulkString("db1".into())
+        );
+        assert_eq!(
+            in_db(&source, 1).get(&key("b")).await.unwrap(),
+            RespValue::Null
+        );
+    }
+
+    #[tokio::test]
+    async fn test_migrate_slot_atomically_replays_writes_made_while_streaming() {
+        let (source, _temp_dir, slot) = source_owning_slot().await;
+        let dest = Arc::new(TestContext::new().await);
+        let writer = source.clone();
+        let hook: Hook = Box::new(move || {
+            Box::pin(async move {
+                writer.set(&key("a"), "changed").await.unwrap();
+                writer.del(&[&key("b")]).await.unwrap();
+                writer.set(&key("c"), "new").await.unwrap();
+            })
+        });
+        let (addr, _) = spawn_fake_node(dest.clone(), Some(hook), None).await;
+        let dest_id = "d".repeat(40);
+        add_node(
+            &source,
+            &dest_id,
+            addr.port(),
+            NodeFlags::PRIMARY,
+            None,
+            &[],
+        );
+
+        source.set(&key("a"), "1").await.unwrap();
+        source.set(&key("b"), "2").await.unwrap();
+
+        migration::migrate_slot_atomically(&source.state, slot, &dest_id)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            dest.get(&key("a")).await.unwrap(),
+            RespValue::BulkString("changed".into())
+        );
+        assert_eq!(dest.get(&key("b")).await.unwrap(), RespValue::Null);
+        assert_eq!(
+            dest.get(&key("c")).await.unwrap(),
+            RespValue::BulkString("new".into())
+        );
+        assert_eq!(source.get(&key("c")).await.unwrap(), RespValue::Null);
+    }
+
+    #[tokio::test]
+    async fn test_client_blocked_in_blpop_does_not_hold_up_cutover() {
+        let (source, _temp_dir, slot) = source_owning_slot().await;
+        let dest = Arc::new(TestContext::new().await);
+        let blocked = Arc::new(Mutex::new(None));
+        let (client, blocked_clone) = (source.clone(), blocked.clone());
+        // A client blocks on a key of the slot while the slot is being streamed.
+        let hook: Hook = Box::new(move || {
+            Box::pin(async move {
+                let blpop = Command::try_from(RespFrame::Array(vec![
+                    RespFrame::BulkString("BLPOP".into()),
+                    RespFrame::BulkString(key("queue").into()),
+                    RespFrame::BulkString("0".into()),
+                ]))
+                .unwrap();
+                *blocked_clone.lock().unwrap() = Some(spawn_admitted(&client, blpop).await);
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            })
+        });
+        let (addr, _) = spawn_fake_node(dest.clone(), Some(hook), None).await;
+        let dest_id = "d".repeat(40);
+        add_node(
+            &source,
+            &dest_id,
+            addr.port(),
+            NodeFlags::PRIMARY,
+            None,
+            &[],
+        );
+        source.set(&key("a"), "1").await.unwrap();
+
+        let result = tokio::time::timeout(
+            Duration::from_secs(2),
+            migration::migrate_slot_atomically(&source.state, slot, &dest_id),
+        )
+        .await
+        .expect("the cutover waited for the blocked client");
+        assert!(result.is_ok());
+        let cluster = source.state.cluster.as_ref().unwrap();
+        assert!(!cluster.i_own_slot(slot));
+        blocked.lock().unwrap().take().unwrap().abort();
+    }
+
+    #[tokio::test]
+    async fn test_writes_stay_paused_until_the_slot_is_handed_over() {
+        let (source, _temp_dir, slot) = source_owning_slot().await;
+        let dest = Arc::new(TestContext::new().await);
+        let observer = source.clone();
+        let paused_during_setslot = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let observed = paused_during_setslot.clone();
+        let cluster_hook: Hook = Box::new(move || {
+            Box::pin(async move {
+                let cluster = observer.state.cluster.as_ref().unwrap();
+                // The handover is slow, and writes to the slot must keep waiting for it.
+                let blocked = tokio::time::timeout(
+                    Duration::from_millis(200),
+                    cluster.slot_migration.enter(&[Bytes::from(key("a"))]),
+                )
+                .await
+                .is_err();
+                observed.store(
+                    blocked && cluster.slot_migration.is_paused(),
+                    Ordering::SeqCst,
+                );
+            })
+        });
+        let (addr, _) = spawn_fake_node(dest.clone(), None, Some(cluster_hook)).await;
+        let dest_id = "d".repeat(40);
+        add_node(
+            &source,
+            &dest_id,
+            addr.port(),
+            NodeFlags::PRIMARY,
+            None,
+            &[],
+        );
+        source.set(&key("a"), "1").await.unwrap();
+
+        migration::migrate_slot_atomically(&source.state, slot, &dest_id)
+            .await
+            .unwrap();
+
+        assert!(paused_during_setslot.load(Ordering::SeqCst));
+        let cluster = source.state.cluster.as_ref().unwrap();
+        assert!(!cluster.slot_migration.is_paused());
+        assert!(
+            cluster
+                .slot_migration
+                .enter(&[Bytes::from(key("a"))])
+                .await
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_migrate_slot_atomically_requires_owned_slot_and_primary_destination() {
+        let (source, _temp_dir, slot) = source_owning_slot().await;
+        let replica_id = "r".repeat(40);
+        add_node(&source, &replica_id, 7001, NodeFlags::REPLICA, None, &[]);
+
+        let result = migration::migrate_slot_atomically(&source.state, slot, &replica_id).await;
+        assert!(result.is_err(), "destination must be a primary");
+        let result = migration::migrate_slot_atomically(&source.state, slot, "unknown").await;
+        assert!(result.is_err(), "destination must be known");
+        let result = migration::migrate_slot_atomically(&source.state, slot + 1, &replica_id).await;
+        assert!(result.is_err(), "slot must be owned by this node");
+
+        let cluster = source.state.cluster.as_ref().unwrap();
+        assert_eq!(cluster.slot_migration.migrating_slot(), None);
+        assert!(cluster.i_own_slot(slot));
+    }
+
+    #[tokio::test]
+    async fn test_failed_migration_keeps_slot_and_keys() {
+        let (source, _temp_dir, slot) = source_owning_slot().await;
+        // Reserve a port and close it again so that connecting fails.
+        let port = TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let dest_id = "d".repeat(40);
+        add_node(&source, &dest_id, port, NodeFlags::PRIMARY, None, &[]);
+        source.set(&key("a"), "1").await.unwrap();
+
+        let result = migration::migrate_slot_atomically(&source.state, slot, &dest_id).await;
+        assert!(matches!(result, Err(SpinelDBError::MigrationError(_))));
+
+        let cluster = source.state.cluster.as_ref().unwrap();
+        assert!(cluster.i_own_slot(slot));
+        assert_eq!(cluster.slot_migration.migrating_slot(), None);
+        assert_eq!(
+            source.get(&key("a")).await.unwrap(),
+            RespValue::BulkString("1".into())
+        );
+        // Writes to the slot are no longer tracked once the migration is over.
+        source.set(&key("b"), "2").await.unwrap();
+        assert!(source.db.take_slot_changes(slot).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_slot_tracking_records_only_keys_in_tracked_slot() {
+        let (source, _temp_dir, slot) = source_owning_slot().await;
+        source.db.begin_slot_tracking(slot).await;
+
+        source.set(&key("a"), "1").await.unwrap();
+        source.set("elsewhere", "2").await.unwrap();
+        source.del(&[&key("b")]).await.unwrap();
+
+        let changes = source.db.take_slot_changes(slot).await;
+        assert!(changes.contains(&Bytes::from(key("a"))));
+        assert!(!changes.contains(&Bytes::from_static(b"elsewhere")));
+        assert!(source.db.take_slot_changes(slot).await.is_empty());
+
+        source.db.end_slot_tracking(slot).await;
+        source.set(&key("a"), "3").await.unwrap();
+        assert!(source.db.take_slot_changes(slot).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_writes_pass_the_gate_when_no_migration_is_active() {
+        let (source, _temp_dir, _slot) = source_owning_slot().await;
+        let cluster = source.state.cluster.as_ref().unwrap();
+        assert!(
+            cluster
+                .slot_migration
+                .enter(&[Bytes::from(key("a"))])
+                .await
+                .is_none()
+        );
+        assert!(cluster.slot_migration.enter(&[]).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_reshard_and_migrateslot_parse_atomic_options() {
+        let (ctx, _temp_dir) = create_cluster_context().await;
+
+        let result = execute_cluster(&ctx, "MIGRATESLOT", vec!["100"]).await;
+        assert!(result.is_err(), "MIGRATESLOT needs a destination");
+        let invalid_slot = NUM_SLOTS.to_string();
+        let result = execute_cluster(&ctx, "MIGRATESLOT", vec![&invalid_slot, "x"]).await;
+        assert!(result.is_err(), "slot out of range");
+        let result = execute_cluster(&ctx, "MIGRATESLOT", vec!["100", "x"]).await;
+        assert!(result.is_err(), "slot not owned");
+
+        let result = execute_cluster(&ctx, "RESHARD", vec!["a", "b", "ATOMIC"]).await;
+        assert!(result.is_err(), "RESHARD ATOMIC still needs slots");
+        let result = execute_cluster(&ctx, "REBALANCE", vec!["ATOMIC", "SIMULATE"]).await;
+        assert!(result.is_ok(), "REBALANCE accepts ATOMIC: {result:?}");
+        let result = execute_cluster(&ctx, "REBALANCE", vec!["BY", "MEMORY", "SIMULATE"]).await;
+        assert!(result.is_ok(), "REBALANCE accepts BY MEMORY: {result:?}");
+        let result = execute_cluster(&ctx, "REBALANCE", vec!["BY", "KEYS"]).await;
+        assert!(result.is_err(), "REBALANCE rejects an unknown BY");
+
+        let frame = RespFrame::Array(
+            ["CLUSTER", "RESHARD", "a", "b", "ATOMIC", "1", "2"]
+                .into_iter()
+                .map(|arg| RespFrame::BulkString(Bytes::from_static(arg.as_bytes())))
+                .collect(),
+        );
+        match Command::try_from(frame).unwrap() {
+            Command::Cluster(ClusterInfo {
+                subcommand: ClusterSubcommand::Reshard { slots, atomic, .. },
+            }) => {
+                assert!(atomic);
+                assert_eq!(slots, vec![1, 2]);
+            }
+            other => panic!("Expected CLUSTER RESHARD, got {other:?}"),
+        }
+    }
+}
+
+// ===== Cross-Slot Fan-Out Tests =====
+
+mod fanout {
+    use super::*;
+    use spineldb::core::cluster::fanout;
+    use spineldb::core::commands::generic::{DbSize, Del};
+    use spineldb::core::commands::string::MGet;
+
+    fn keys(names: &[&str]) -> Vec<Bytes> {
+        names
+            .iter()
+            .map(|name| Bytes::from(name.to_string()))
+            .collect()
+    }
+
+    /// Creates a cluster where this node owns the slot of `{a}` and a fake node owning
+    /// the slot of `{b}` serves `remote`.
+    async fn two_node_cluster() -> (TestContext, TempDir, Arc<TestContext>) {
+        let (ctx, temp_dir) = create_cluster_context().await;
+        let local_slot = slot::get_slot(&Bytes::from_static(b"{a}"));
+        let remote_slot = slot::get_slot(&Bytes::from_static(b"{b}"));
+        execute_cluster(&ctx, "ADDSLOTS", vec![&local_slot.to_string()])
+            .await
+            .unwrap();
+        let remote = Arc::new(TestContext::new().await);
+        let (addr, _) = spawn_fake_node(remote.clone(), None, None).await;
+        add_node(
+            &ctx,
+            &"b".repeat(40),
+            addr.port(),
+            NodeFlags::PRIMARY,
+            None,
+            &[remote_slot],
+        );
+        (ctx, temp_dir, remote)
+    }
+
+    /// Runs a fanned-out command the way the router does, executing local
+    /// sub-requests on `ctx`.
+    async fn run(ctx: &TestContext, command: Command) -> Result<RespValue, SpinelDBError> {
+        let cluster = ctx.state.cluster.as_ref().unwrap();
+        let requests = fanout::scatter(cluster, &command)?;
+        let mut replies = vec![RespValue::Null; requests.len()];
+        for (index, reply) in fanout::send_remote(cluster, &requests, 0, None).await? {
+            replies[index] = reply;
+        }
+        for (index, request) in requests.iter().enumerate() {
+            if request.node_id == cluster.my_id {
+                replies[index] = ctx.execute(request.command.clone()).await?;
+            }
+        }
+        fanout::gather(&command, &requests, replies)
+    }
+
+    #[test]
+    fn test_only_supported_commands_spanning_slots_are_fanned_out() {
+        let cross = keys(&["{a}1", "{b}1"]);
+        let same = keys(&["{a}1", "{a}2"]);
+        let mget = |keys: &[Bytes]| {
+            Command::MGet(MGet {
+                keys: keys.to_vec(),
+            })
+        };
+        assert!(fanout::should_fan_out(&mget(&cross), &cross));
+        assert!(!fanout::should_fan_out(&mget(&same), &same));
+        assert!(fanout::should_fan_out(&Command::DbSize(DbSize), &[]));
+        let del = Command::Del(Del {
+            keys: cross.clone(),
+        });
+        assert!(fanout::should_fan_out(&del, &cross));
+        let get = Command::try_from(RespFrame::Array(vec![
+            RespFrame::BulkString("GET".into()),
+            RespFrame::BulkString("{a}1".into()),
+        ]))
+        .unwrap();
+        assert!(!fanout::should_fan_out(&get, &keys(&["{a}1"])));
+    }
+
+    #[tokio::test]
+    async fn test_scatter_groups_keys_by_slot_owner() {
+        let (ctx, _temp_dir, _remote) = two_node_cluster().await;
+        let cluster = ctx.state.cluster.as_ref().unwrap();
+        let command = Command::MGet(MGet {
+            keys: keys(&["{a}1", "{b}1", "{a}2"]),
+        });
+
+        let requests = fanout::scatter(cluster, &command).unwrap();
+        assert_eq!(requests.len(), 2);
+        let local = requests
+            .iter()
+            .find(|r| r.node_id == cluster.my_id)
+            .unwrap();
+        assert_eq!(local.positions, vec![0, 2]);
+        let remote = requests
+            .iter()
+            .find(|r| r.node_id == "b".repeat(40))
+            .unwrap();
+        assert_eq!(remote.positions, vec![1]);
+
+        let unserved = Command::MGet(MGet {
+            keys: keys(&["{a}1", "{c}1"]),
+        });
+        assert!(matches!(
+            fanout::scatter(cluster, &unserved),
+            Err(SpinelDBError::ClusterDown(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_fanned_out_commands_merge_replies_in_key_order() {
+        let (ctx, _temp_dir, remote) = two_node_cluster().await;
+        ctx.set("{a}1", "local1").await.unwrap();
+        ctx.set("{a}2", "local2").await.unwrap();
+        remote.set("{b}1", "remote1").await.unwrap();
+
+        let mget = Command::MGet(MGet {
+            keys: keys(&["{a}1", "{b}1", "{b}2", "{a}2"]),
+        });
+        assert_eq!(
+            run(&ctx, mget).await.unwrap(),
+            RespValue::Array(vec![
+                RespValue::BulkString("local1".into()),
+                RespValue::BulkString("remote1".into()),
+                RespValue::Null,
+                RespValue::BulkString("local2".into()),
+            ])
+        );
+
+        assert_eq!(
+            run(&ctx, Command::DbSize(DbSize)).await.unwrap(),
+            RespValue::Integer(3)
+        );
+
+        let del = Command::Del(Del {
+            keys: keys(&["{a}1", "{b}1", "{b}2"]),
+        });
+        assert_eq!(run(&ctx, del).await.unwrap(), RespValue::Integer(2));
+        assert_eq!(remote.get("{b}1").await.unwrap(), RespValue::Null);
+        assert_eq!(ctx.get("{a}1").await.unwrap(), RespValue::Null);
+    }
+
+    #[tokio::test]
+    async fn test_fan_out_fails_when_a_node_is_unreachable() {
+        let (ctx, _temp_dir) = create_cluster_context().await;
+        let port = TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let remote_slot = slot::get_slot(&Bytes::from_static(b"{b}"));
+        add_node(
+            &ctx,
+            &"b".repeat(40),
+            port,
+            NodeFlags::PRIMARY,
+            None,
+            &[remote_slot],
+        );
+
+        let result = run(&ctx, Command::DbSize(DbSize)).await;
+        assert!(matches!(result, Err(SpinelDBError::ClusterDown(_))));
+    }
+
+    #[tokio::test]
+    async fn test_fan_out_authenticates_and_reuses_connections() {
+        let (ctx, _temp_dir) = create_cluster_context().await;
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let accepted = Arc::new(Mutex::new(0));
+        let accepted_clone = accepted.clone();
+        // A node that requires `AUTH secret` before answering `DBSIZE`.
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                *accepted_clone.lock().unwrap() += 1;
+                tokio::spawn(async move {
+                    let mut framed = Framed::new(socket, RespFrameCodec);
+                    let mut authenticated = false;
+                    while let Some(Ok(RespFrame::Array(args))) = framed.next().await {
+                        let reply = match (&args[0], args.get(1)) {
+                            (RespFrame::BulkString(name), Some(RespFrame::BulkString(pass)))
+                                if name.as_ref() == b"AUTH" =>
+                            {
+                                authenticated = pass.as_ref() == b"secret";
+                                RespFrame::SimpleString("OK".into())
+                            }
+                            _ if !authenticated => {
+                                RespFrame::Error("NOAUTH Authentication required".into())
+                            }
+                            (RespFrame::BulkString(name), _)
+                                if name.as_ref() == b"CLIENT" || name.as_ref() == b"SELECT" =>
+                            {
+                                RespFrame::SimpleString("OK".into())
+                            }
+                            _ => RespFrame::Integer(7),
+                        };
+                        if framed.send(reply).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+        let remote_slot = slot::get_slot(&Bytes::from_static(b"{b}"));
+        add_node(
+            &ctx,
+            &"b".repeat(40),
+            port,
+            NodeFlags::PRIMARY,
+            None,
+            &[remote_slot],
+        );
+        let cluster = ctx.state.cluster.as_ref().unwrap();
+        let requests = fanout::scatter(cluster, &Command::DbSize(DbSize)).unwrap();
+
+        let result = fanout::send_remote(cluster, &requests, 0, None).await;
+        assert!(matches!(result, Err(SpinelDBError::ClusterDown(_))));
+
+        for _ in 0..3 {
+            let replies = fanout::send_remote(cluster, &requests, 0, Some("secret"))
+                .await
+                .unwrap();
+            assert_eq!(replies, vec![(0, RespValue::Integer(7))]);
+        }
+        // The failed connection was dropped; the authenticated one was reused.
+        assert_eq!(*accepted.lock().unwrap(), 2);
+
+        // Connections are only reused with the password they authenticated with.
+        let result = fanout::send_remote(cluster, &requests, 0, None).await;
+        assert!(matches!(result, Err(SpinelDBError::ClusterDown(_))));
+        assert_eq!(*accepted.lock().unwrap(), 3);
+
+        // The router authenticates as the client did, whatever the node's password.
+        let mut session = new_session();
+        session.is_cross_slot = true;
+        session.auth_password = Some("secret".into());
+        let result = route(&ctx, &mut session, &["DBSIZE"]).await;
+        assert_eq!(result.unwrap(), Some(RespValue::Integer(7)));
+        session.auth_password = None;
+        let result = route(&ctx, &mut session, &["DBSIZE"]).await;
+        assert!(matches!(result, Err(SpinelDBError::ClusterDown(_))));
+    }
+
+    #[tokio::test]
+    async fn test_local_sub_requests_follow_ask_redirects() {
+        let (ctx, _temp_dir, remote) = two_node_cluster().await;
+        let local_slot = slot::get_slot(&Bytes::from_static(b"{a}"));
+        let remote_id = "b".repeat(40);
+        let cluster = ctx.state.cluster.as_ref().unwrap();
+        cluster
+            .nodes
+            .get_mut(&cluster.my_id)
+            .unwrap()
+            .node_info
+            .migrating_slots
+            .insert(local_slot, remote_id);
+        // `{a}1` has already been migrated to the node serving `{b}`.
+        remote.set("{a}1", "moved").await.unwrap();
+        remote.set("{b}1", "remote").await.unwrap();
+
+        let mut session = new_session();
+        session.is_cross_slot = true;
+        let result = route(&ctx, &mut session, &["MGET", "{a}1", "{b}1"]).await;
+        assert_eq!(
+            result.unwrap(),
+            Some(RespValue::Array(vec![
+                RespValue::BulkString("moved".into()),
+                RespValue::BulkString("remote".into()),
+            ]))
+        );
+
+        // Keys still here are served locally.
+        ctx.set("{a}1", "local").await.unwrap();
+        let result = route(&ctx, &mut session, &["MGET", "{a}1", "{b}1"]).await;
+        assert_eq!(
+            result.unwrap(),
+            Some(RespValue::Array(vec![
+                RespValue::BulkString("local".into()),
+                RespValue::BulkString("remote".into()),
+            ]))
+        );
+    }
+
+    /// Starts a node that records the commands it receives and answers `DBSIZE` with
+    /// the selected database index, or with `redirect` if it is set and the command was
+    /// not preceded by `ASKING`.
+    async fn spawn_recording_node(redirect: Option<String>) -> (u16, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let received_clone = received.clone();
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                let received = received_clone.clone();
+                let redirect = redirect.clone();
+                tokio::spawn(async move {
+                    let mut framed = Framed::new(socket, RespFrameCodec);
+                    let (mut db_index, mut asking) = (0, false);
+                    while let Some(Ok(RespFrame::Array(args))) = framed.next().await {
+                        let RespFrame::BulkString(name) = &args[0] else {
+                            break;
+                        };
+                        let name = String::from_utf8_lossy(name).to_ascii_uppercase();
+                        received.lock().unwrap().push(name.clone());
+                        let reply = match name.as_str() {
+                            "SELECT" => {
+                                if let Some(RespFrame::BulkString(index)) = args.get(1) {
+                                    db_index = std::str::from_utf8(index).unwrap().parse().unwrap();
+                                }
+                                RespFrame::SimpleString("OK".into())
+                            }
+                            "ASKING" => {
+                                asking = true;
+                                RespFrame::SimpleString("OK".into())
+                            }
+                            "DBSIZE" => match &redirect {
+                                Some(redirect) if !asking => RespFrame::Error(redirect.clone()),
+                                _ => {
+                                    asking = false;
+                                    RespFrame::Integer(db_index)
+                                }
+                            },
+                            _ => RespFrame::SimpleString("OK".into()),
+                        };
+                        if framed.send(reply).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+        (port, received)
+    }
+
+    #[tokio::test]
+    async fn test_fan_out_selects_database_on_pooled_connections() {
+        let (ctx, _temp_dir) = create_cluster_context().await;
+        let (port, _) = spawn_recording_node(None).await;
+        let remote_slot = slot::get_slot(&Bytes::from_static(b"{b}"));
+        add_node(
+            &ctx,
+            &"b".repeat(40),
+            port,
+            NodeFlags::PRIMARY,
+            None,
+            &[remote_slot],
+        );
+        let cluster = ctx.state.cluster.as_ref().unwrap();
+        let requests = fanout::scatter(cluster, &Command::DbSize(DbSize)).unwrap();
+
+        // The same pooled connection is used for both databases.
+        for db_index in [3, 0] {
+            let replies = fanout::send_remote(cluster, &requests, db_index, None)
+                .await
+                .unwrap();
+            assert_eq!(replies, vec![(0, RespValue::Integer(db_index as i64))]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fan_out_follows_ask_redirect() {
+        let (ctx, _temp_dir) = create_cluster_context().await;
+        let (target_port, target_received) = spawn_recording_node(None).await;
+        let remote_slot = slot::get_slot(&Bytes::from_static(b"{b}"));
+        let (source_port, _) =
+            spawn_recording_node(Some(format!("ASK {remote_slot} 127.0.0.1:{target_port}"))).await;
+        add_node(
+            &ctx,
+            &"b".repeat(40),
+            source_port,
+            NodeFlags::PRIMARY,
+            None,
+            &[remote_slot],
+        );
+        let cluster = ctx.state.cluster.as_ref().unwrap();
+        let requests = fanout::scatter(cluster, &Command::DbSize(DbSize)).unwrap();
+
+        let replies = fanout::send_remote(cluster, &requests, 2, None)
+            .await
+            .unwrap();
+        assert_eq!(replies, vec![(0, RespValue::Integer(2))]);
+        assert_eq!(
+            *target_received.lock().unwrap(),
+            vec!["CLIENT", "SELECT", "ASKING", "DBSIZE"]
+        );
+    }
+
+    #[test]
+    fn test_gather_fails_on_error_reply() {
+        let command = Command::Del(Del {
+            keys: keys(&["{a}1", "{b}1"]),
+        });
+        let replies = vec![RespValue::Integer(1), RespValue::Error("MOVED 1 x".into())];
+        let result = fanout::gather(&command, &[], replies);
+        assert!(matches!(result, Err(SpinelDBError::ClusterDown(_))));
+    }
+}
@@ -1,14 +1,22 @@
 // tests/integration/persistence_test.rs
 
 //! Integration tests for persistence commands
-//! Tests: SAVE, BGSAVE, LASTSAVE, BGREWRITEAOF, and data loading
+//! Tests: SAVE, BGSAVE, LASTSAVE, BGREWRITEAOF, multi-part AOF, and data loading
 
 use super::test_helpers::TestContext;
 use bytes::Bytes;
 use spineldb::config::Config;
+use spineldb::core::Command;
 use spineldb::core::RespValue;
 use spineldb::core::SpinelDBError;
+use spineldb::core::database::Snapshot;
+use spineldb::core::handler::transaction_handler::TransactionHandler;
+use spineldb::core::persistence::aof_manifest::{AofFileType, AofManifest};
+use spineldb::core::persistence::{AofLoader, check, rewrite_aof, spldb};
+use spineldb::core::protocol::RespFrame;
+use spineldb::core::storage::data_types::{DataValue, StoredValue};
 use std::fs;
+use std::io::Write;
 use std::path::Path;
 use tokio::time::{Duration, sleep};
 
@@ -435,6 +443,7 @@ async fn test_bgrewriteaof_basic() {
     config.persistence.spldb_enabled = false;
     config.persistence.aof_enabled = true;
     config.persistence.aof_path = "test_bgrewriteaof.aof".to_string();
+    config.persistence.aof_dirname = "test_bgrewriteaof_aofdir".to_string();
 
     let ctx = TestContext::with_config(config).await;
 
@@ -457,14 +466,15 @@ async fn test_bgrewriteaof_basic() {
     // Give it a bit more time to finish writing
     sleep(Duration::from_millis(1000)).await;
 
-    // Verify AOF file exists (if persistence is working)
-    // If file doesn't exist but bgrewriteaof succeeded, persistence might not be fully implemented
-    if !Path::new("test_bgrewriteaof.aof").exists() {
-        eprintln!("Warning: AOF file not created, but BGREWRITEAOF command succeeded");
-    }
+    // The rewrite produces an SPLDB base and references it from the manifest.
+    let manifest = fs::read_to_string("test_bgrewriteaof_aofdir/test_bgrewriteaof.aof.manifest")
+        .expect("AOF manifest should exist after a rewrite");
+    assert!(manifest.contains("file test_bgrewriteaof.aof.1.base.spldb seq 1 type b"));
+    assert!(manifest.contains("type i"));
+    assert!(Path::new("test_bgrewriteaof_aofdir/test_bgrewriteaof.aof.1.base.spldb").exists());
 
     // Cleanup
-    let _ = fs::remove_file("test_bgrewriteaof.aof");
+    let _ = fs::remove_dir_all("test_bgrewriteaof_aofdir");
 }
 
 #[tokio::test]
@@ -474,6 +484,7 @@ async fn test_bgrewriteaof_concurrent_error() {
     config.persistence.spldb_enabled = false;
     config.persistence.aof_enabled = true;
     config.persistence.aof_path = "test_bgrewriteaof_concurrent.aof".to_string();
+    config.persistence.aof_dirname = "test_bgrewriteaof_concurrent_aofdir".to_string();
 
     let ctx = TestContext::with_config(config).await;
 
@@ -505,7 +516,7 @@ async fn test_bgrewriteaof_concurrent_error() {
     ctx.wait_for_aof_rewrite().await;
 
     // Cleanup
-    let _ = fs::remove_file("test_bgrewriteaof_concurrent.aof");
+    let _ = fs::remove_dir_all("test_bgrewriteaof_concurrent_aofdir");
 }
 
 #[tokio::test]
@@ -516,6 +527,7 @@ async fn test_bgsave_during_aof_rewrite() {
     config.persistence.spldb_path = "test_bgsave_during_aof.spldb".to_string();
     config.persistence.aof_enabled = true;
     config.persistence.aof_path = "test_bgsave_during_aof.aof".to_string();
+    config.persistence.aof_dirname = "test_bgsave_during_aof_aofdir".to_string();
 
     let ctx = TestContext::with_config(config).await;
 
@@ -547,7 +559,402 @@ async fn test_bgsave_during_aof_rewrite() {
 
     // Cleanup
     let _ = fs::remove_file("test_bgsave_during_aof.spldb");
-    let _ = fs::remove_file("test_bgsave_during_aof.aof");
+    let _ = fs::remove_dir_all("test_bgsave_during_aof_aofdir");
+}
+
+#[tokio::test]
+async fn test_aof_rewrite_base_is_loaded_on_startup() {
+    let mut config = Config::default();
+    config.databases = 1;
+    config.persistence.spldb_enabled = false;
+    config.persistence.aof_enabled = true;
+    config.persistence.aof_path = "test_aof_base_load.aof".to_string();
+    config.persistence.aof_dirname = "test_aof_base_load_aofdir".to_string();
+
+    let ctx = TestContext::with_config(config.clone()).await;
+    ctx.set("key1", "value1").await.unwrap();
+    ctx.set("key2", "value2").await.unwrap();
+    rewrite_aof(ctx.state.clone()).await;
+
+    // Commands appended to the incremental file are replayed on top of the base.
+    let manifest = AofManifest::load(&config.persistence)
+        .await
+        .unwrap()
+        .expect("manifest should exist");
+    let incr_path = manifest.current_incr_path().unwrap();
+    let frame = RespFrame::Array(vec![
+        RespFrame::BulkString(Bytes::from_static(b"SET")),
+        RespFrame::BulkString(Bytes::from_static(b"key2")),
+        RespFrame::BulkString(Bytes::from_static(b"updated")),
+    ]);
+    let mut incr = fs::OpenOptions::new().append(true).open(incr_path).unwrap();
+    incr.write_all(&frame.encode_to_vec().unwrap()).unwrap();
+    drop(incr);
+
+    let restarted = TestContext::with_config(config.clone()).await;
+    AofLoader::new(config.persistence.clone())
+        .load_into(&restarted.state)
+        .await
+        .unwrap();
+    assert_eq!(
+        restarted.get("key1").await.unwrap(),
+        RespValue::BulkString(Bytes::from_static(b"value1"))
+    );
+    assert_eq!(
+        restarted.get("key2").await.unwrap(),
+        RespValue::BulkString(Bytes::from_static(b"updated"))
+    );
+
+    let _ = fs::remove_dir_all("test_aof_base_load_aofdir");
+}
+
+#[tokio::test]
+async fn test_aof_rewrite_keeps_cached_scripts() {
+    let mut config = Config::default();
+    config.databases = 1;
+    config.persistence.spldb_enabled = false;
+    config.persistence.aof_enabled = true;
+    config.persistence.aof_path = "test_aof_rewrite_scripts.aof".to_string();
+    config.persistence.aof_dirname = "test_aof_rewrite_scripts_aofdir".to_string();
+    let _ = fs::remove_dir_all(&config.persistence.aof_dirname);
+
+    let ctx = TestContext::with_config(config.clone()).await;
+    let sha1 = ctx.state.scripting.load(Bytes::from_static(b"return 1"));
+    rewrite_aof(ctx.state.clone()).await;
+
+    // The scripts are written to the new incremental file, as the base carries none.
+    let restarted = TestContext::with_config(config.clone()).await;
+    AofLoader::new(config.persistence.clone())
+        .load_into(&restarted.state)
+        .await
+        .unwrap();
+    assert!(restarted.state.scripting.get(&sha1).is_some());
+
+    let _ = fs::remove_dir_all(&config.persistence.aof_dirname);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_aof_rewrite_does_not_replay_writes_twice() {
+    let mut config = Config::default();
+    config.databases = 1;
+    config.persistence.spldb_enabled = false;
+    config.persistence.aof_enabled = true;
+    config.persistence.aof_path = "test_aof_rewrite_cut.aof".to_string();
+    config.persistence.aof_dirname = "test_aof_rewrite_cut_aofdir".to_string();
+    let _ = fs::remove_dir_all(&config.persistence.aof_dirname);
+
+    let (ctx, shutdown_tx, writer) = TestContext::with_aof_writer(config.clone()).await;
+
+    // Transactions are propagated to the AOF, so every INCR below is logged exactly
+    // once while rewrites keep cutting the AOF over to new base files.
+    const INCREMENTS: u64 = 2000;
+    let writer_ctx = ctx.clone();
+    let incrementer = tokio::spawn(async move {
+        let mut handler =
+            TransactionHandler::new(writer_ctx.state.clone(), &writer_ctx.db, 7, None);
+        for _ in 0..INCREMENTS {
+            handler.handle_multi().unwrap();
+            let incr = Command::try_from(RespFrame::Array(vec![
+                RespFrame::BulkString(Bytes::from_static(b"INCR")),
+                RespFrame::BulkString(Bytes::from_static(b"counter")),
+            ]))
+            .unwrap();
+            handler.handle_queueing(incr).await.unwrap();
+            handler.handle_exec().await.unwrap();
+        }
+    });
+    while !incrementer.is_finished() {
+        rewrite_aof(ctx.state.clone()).await;
+    }
+    incrementer.await.unwrap();
+    shutdown_tx.send(()).unwrap();
+    writer.await.unwrap().unwrap();
+
+    let restarted = TestContext::with_config(config.clone()).await;
+    AofLoader::new(config.persistence.clone())
+        .load_into(&restarted.state)
+        .await
+        .unwrap();
+    assert_eq!(
+        restarted.get("counter").await.unwrap(),
+        RespValue::BulkString(Bytes::from(INCREMENTS.to_string()))
+    );
+
+    let _ = fs::remove_dir_all(&config.persistence.aof_dirname);
+}
+
+fn cache_policy_frame(args: &[&str]) -> RespFrame {
+    let mut frames = vec![
+        RespFrame::BulkString(Bytes::from_static(b"CACHE")),
+        RespFrame::BulkString(Bytes::from_static(b"POLICY")),
+    ];
+    frames.extend(
+        args.iter()
+            .map(|arg| RespFrame::BulkString(Bytes::from(arg.to_string()))),
+    );
+    RespFrame::Array(frames)
+}
+
+async fn policy_names(ctx: &TestContext) -> Vec<String> {
+    let policies = ctx.state.cache.policies.read().await;
+    policies.iter().map(|p| p.name.clone()).collect()
+}
+
+#[tokio::test]
+async fn test_cache_policies_survive_spldb_save_and_load() {
+    let mut config = Config::default();
+    config.databases = 1;
+    config.persistence.spldb_enabled = true;
+    config.persistence.spldb_path = "test_cache_policies.spldb".to_string();
+    config.persistence.aof_enabled = false;
+
+    let ctx = TestContext::with_config(config.clone()).await;
+    for args in [
+        ["SET", "low", "low:*", "http://example.com/{1}", "TTL", "60"].as_slice(),
+        &[
+            "SET",
+            "high",
+            "high:*",
+            "http://example.com/{1}",
+            "PRIORITY",
+            "9",
+            "TAGS",
+            "a",
+            "b",
+        ],
+    ] {
+        ctx.execute(Command::try_from(cache_policy_frame(args)).unwrap())
+            .await
+            .unwrap();
+    }
+    ctx.set("key", "value").await.unwrap();
+    let policies = ctx.state.cache.policies.read().await.clone();
+    spldb::save(&ctx.state.dbs, &policies, &config.persistence.spldb_path)
+        .await
+        .unwrap();
+
+    let restarted = TestContext::with_config(config.clone()).await;
+    spldb::SpldbLoader::new(config.persistence.clone())
+        .load_into(&restarted.state)
+        .await
+        .unwrap();
+    assert_eq!(policy_names(&restarted).await, vec!["high", "low"]);
+    let loaded = restarted.state.cache.policies.read().await.clone();
+    assert_eq!(loaded[0].tags, vec!["a", "b"]);
+    assert_eq!(loaded[1].ttl, Some(60));
+    assert_eq!(
+        restarted.get("key").await.unwrap(),
+        RespValue::BulkString(Bytes::from_static(b"value"))
+    );
+
+    let _ = fs::remove_file(&config.persistence.spldb_path);
+}
+
+#[tokio::test]
+async fn test_cache_policies_survive_aof_rewrite_and_replay() {
+    let mut config = Config::default();
+    config.databases = 1;
+    config.persistence.spldb_enabled = false;
+    config.persistence.aof_enabled = true;
+    config.persistence.aof_path = "test_aof_policies.aof".to_string();
+    config.persistence.aof_dirname = "test_aof_policies_aofdir".to_string();
+
+    let ctx = TestContext::with_config(config.clone()).await;
+    for name in ["kept", "deleted"] {
+        let pattern = format!("{name}:*");
+        let args = ["SET", name, &pattern, "http://example.com/{1}"];
+        ctx.execute(Command::try_from(cache_policy_frame(&args)).unwrap())
+            .await
+            .unwrap();
+    }
+    rewrite_aof(ctx.state.clone()).await;
+
+    // Policy changes are logged as the commands they propagate as.
+    let manifest = AofManifest::load(&config.persistence)
+        .await
+        .unwrap()
+        .expect("manifest should exist");
+    let incr_path = manifest.current_incr_path().unwrap();
+    let mut incr = fs::OpenOptions::new().append(true).open(incr_path).unwrap();
+    for args in [
+        ["DEL", "deleted"].as_slice(),
+        &[
+            "SET",
+            "added",
+            "added:*",
+            "http://example.com/{1}",
+            "GRACE",
+            "30",
+            "VARY_ON",
+            "Accept",
+        ],
+    ] {
+        let command = Command::try_from(cache_policy_frame(args)).unwrap();
+        let frame: RespFrame = command.into();
+        incr.write_all(&frame.encode_to_vec().unwrap()).unwrap();
+    }
+    drop(incr);
+
+    let restarted = TestContext::with_config(config.clone()).await;
+    AofLoader::new(config.persistence.clone())
+        .load_into(&restarted.state)
+        .await
+        .unwrap();
+    assert_eq!(policy_names(&restarted).await, vec!["kept", "added"]);
+    let added = restarted.state.cache.policies.read().await[1].clone();
+    assert_eq!(added.grace, Some(30));
+    assert_eq!(added.vary_on, vec!["Accept"]);
+
+    let _ = fs::remove_dir_all("test_aof_policies_aofdir");
+}
+
+#[tokio::test]
+async fn test_aof_upgrades_legacy_single_file() {
+    let _ = fs::create_dir_all("test_aof_legacy");
+    let legacy_path = "test_aof_legacy/legacy.aof";
+    let frame = RespFrame::Array(vec![
+        RespFrame::BulkString(Bytes::from_static(b"SET")),
+        RespFrame::BulkString(Bytes::from_static(b"legacy_key")),
+        RespFrame::BulkString(Bytes::from_static(b"legacy_value")),
+    ]);
+    fs::write(legacy_path, frame.encode_to_vec().unwrap()).unwrap();
+    // A leftover of a legacy rewrite that crashed before replacing the AOF.
+    let legacy_temp_path = "test_aof_legacy/temp-rewrite-legacy.aof";
+    fs::write(legacy_temp_path, b"partial").unwrap();
+
+    let mut config = Config::default();
+    config.databases = 1;
+    config.persistence.spldb_enabled = false;
+    config.persistence.aof_enabled = true;
+    config.persistence.aof_path = legacy_path.to_string();
+
+    let ctx = TestContext::with_config(config.clone()).await;
+    AofLoader::new(config.persistence.clone())
+        .load_into(&ctx.state)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        ctx.get("legacy_key").await.unwrap(),
+        RespValue::BulkString(Bytes::from_static(b"legacy_value"))
+    );
+    // The legacy file becomes the RESP base of the multi-part AOF.
+    assert!(!Path::new(legacy_path).exists());
+    assert!(!Path::new(legacy_temp_path).exists());
+    let manifest = AofManifest::load(&config.persistence)
+        .await
+        .unwrap()
+        .expect("manifest should exist");
+    let base = manifest.base.as_ref().unwrap();
+    assert_eq!(base.file_name, "legacy.aof.1.base.aof");
+    assert!(!base.is_spldb_base());
+    assert_eq!(manifest.incrs.len(), 1);
+
+    let _ = fs::remove_dir_all("test_aof_legacy");
+}
+
+#[tokio::test]
+async fn test_aof_manifest_parse() {
+    let contents = "file app.aof.2.base.spldb seq 2 type b\n\
+                    file app.aof.3.incr.aof seq 3 type i\n\
+                    file app.aof.4.incr.aof seq 4 type i\n";
+    let files = AofManifest::parse(contents).unwrap();
+    assert_eq!(files.len(), 3);
+    assert_eq!(files[0].file_type, AofFileType::Base);
+    assert!(files[0].is_spldb_base());
+    assert_eq!(files[2].seq, 4);
+    assert_eq!(files[2].to_string(), "file app.aof.4.incr.aof seq 4 type i");
+
+    assert!(AofManifest::parse("file app.aof.1.incr.aof seq x type i").is_err());
+    assert!(AofManifest::parse("file ../escape.aof seq 1 type i").is_err());
+    assert!(AofManifest::parse("file app.aof.1.incr.aof seq 1").is_err());
+}
+
+#[tokio::test]
+async fn test_check_aof_reports_and_fixes_truncated_tail() {
+    let path = "test_check_aof.aof";
+    let set = |k: &str| -> Vec<u8> {
+        RespFrame::Array(vec![
+            RespFrame::BulkString(Bytes::from("SET")),
+            RespFrame::BulkString(Bytes::from(k.to_string())),
+            RespFrame::BulkString(Bytes::from("v")),
+        ])
+        .encode_to_vec()
+        .unwrap()
+    };
+    let multi = RespFrame::Array(vec![RespFrame::BulkString(Bytes::from("MULTI"))])
+        .encode_to_vec()
+        .unwrap();
+    let mut contents = set("a");
+    let valid_len = contents.len() as u64;
+    // An unclosed transaction followed by a partial frame must both be discarded.
+    contents.extend_from_slice(&multi);
+    contents.extend_from_slice(&set("b"));
+    contents.extend_from_slice(&set("c")[..10]);
+    fs::write(path, &contents).unwrap();
+
+    let report = check::check_resp_file(Path::new(path)).await.unwrap();
+    assert!(!report.is_valid());
+    assert_eq!(report.valid_up_to, valid_len);
+    assert_eq!(report.commands, 1);
+    assert_eq!(report.per_command.get("set"), Some(&1));
+
+    assert!(!check::run_check_aof(Path::new(path), false).await.unwrap());
+    assert_eq!(fs::metadata(path).unwrap().len(), contents.len() as u64);
+
+    assert!(check::run_check_aof(Path::new(path), true).await.unwrap());
+    assert_eq!(fs::metadata(path).unwrap().len(), valid_len);
+    assert!(
+        check::check_resp_file(Path::new(path))
+            .await
+            .unwrap()
+            .is_valid()
+    );
+
+    let _ = fs::remove_file(path);
+}
+
+#[tokio::test]
+async fn test_check_spldb_reports_types_and_corruption() {
+    let ctx = TestContext::new().await;
+    ctx.set("str_key", "value").await.unwrap();
+    ctx.lpush("list_key", &["a", "b"]).await.unwrap();
+    ctx.sadd("set_key", &["m"]).await.unwrap();
+
+    let data = spldb::save_to_bytes(&ctx.state.dbs, &[]).await.unwrap();
+    let report = spldb::inspect(&data);
+    assert!(report.is_valid(), "unexpected error: {:?}", report.error);
+    assert_eq!(report.total_keys(), 3);
+    assert_eq!(report.keys_per_type.get("string"), Some(&1));
+    assert_eq!(report.keys_per_type.get("list"), Some(&1));
+    assert_eq!(report.keys_per_type.get("set"), Some(&1));
+    assert_eq!(report.valid_up_to, data.len());
+
+    let mut corrupt = data.to_vec();
+    let last = corrupt.len() - 1;
+    corrupt[last] ^= 0xFF;
+    let report = spldb::inspect(&corrupt);
+    assert!(!report.is_valid());
+    assert!(report.error.unwrap().contains("checksum"));
+
+    let truncated = &data[..data.len() - 12];
+    let report = spldb::inspect(truncated);
+    assert!(!report.is_valid());
+    assert!(report.valid_up_to < truncated.len());
+}
+
+#[test]
+fn test_check_spldb_reports_corrupt_length() {
+    // A list key whose length claims 2^60 elements but holds a single one.
+    let mut data = b"SPINELDB0001".to_vec();
+    data.extend_from_slice(b"\x01\x01k\x81");
+    data.extend_from_slice(&(1u64 << 60).to_be_bytes());
+    data.extend_from_slice(b"\x01a");
+
+    let report = spldb::inspect(&data);
+    assert!(!report.is_valid());
+    assert!(report.error.is_some());
+    assert_eq!(report.total_keys(), 0);
 }
 
 // ===== Complex Persistence Scenarios =====
@@ -647,3 +1054,166 @@ async fn test_persistence_with_sorted_sets() {
     // Cleanup
     let _ = fs::remove_file("test_zset.spldb");
 }
+
+// ===== Point-in-Time Snapshot Tests =====
+
+/// Reads every key of database `db_index` from a snapshot, sorted by key.
+async fn read_snapshot(snapshot: &Snapshot, db_index: usize) -> Vec<(Bytes, DataValue)> {
+    let mut kvs = Vec::new();
+    let mut reader = snapshot.reader(db_index);
+    while reader
+        .next_chunk(|key, value| {
+            kvs.push((key.clone(), value.data.clone()));
+            Ok::<(), SpinelDBError>(())
+        })
+        .await
+        .unwrap()
+    {}
+    kvs.sort_by(|a, b| a.0.cmp(&b.0));
+    kvs
+}
+
+fn string_value(value: &str) -> DataValue {
+    DataValue::String(Bytes::from(value.to_string()))
+}
+
+#[tokio::test]
+async fn test_snapshot_ignores_writes_made_after_it_began() {
+    let mut config = Config::default();
+    config.databases = 2;
+    let ctx = TestContext::with_config(config).await;
+    for i in 0..3000 {
+        ctx.set(&format!("key:{i}"), &format!("v{i}"))
+            .await
+            .unwrap();
+    }
+    ctx.state.dbs[1]
+        .insert_value_from_load(Bytes::from("other"), StoredValue::new(string_value("db1")))
+        .await;
+
+    let snapshot = Snapshot::begin(&ctx.state.dbs).await;
+    assert_eq!(snapshot.key_count(0), 3000);
+    assert_eq!(snapshot.key_count(1), 1);
+
+    ctx.set("key:0", "changed").await.unwrap();
+    ctx.del(&["key:1", "key:2"]).await.unwrap();
+    ctx.set("new-key", "new").await.unwrap();
+    for mut guard in ctx.state.dbs[1].lock_all_shards().await {
+        guard.clear();
+    }
+
+    let db0 = read_snapshot(&snapshot, 0).await;
+    assert_eq!(db0.len(), 3000);
+    let mut expected: Vec<_> = (0..3000)
+        .map(|i| {
+            (
+                Bytes::from(format!("key:{i}")),
+                string_value(&format!("v{i}")),
+            )
+        })
+        .collect();
+    expected.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(db0, expected);
+
+    let db1 = read_snapshot(&snapshot, 1).await;
+    assert_eq!(db1, vec![(Bytes::from("other"), string_value("db1"))]);
+
+    // The live data is unaffected by the snapshot.
+    assert_eq!(
+        ctx.get("key:0").await.unwrap(),
+        RespValue::BulkString(Bytes::from("changed"))
+    );
+    assert_eq!(ctx.get("key:1").await.unwrap(), RespValue::Null);
+}
+
+#[tokio::test]
+async fn test_concurrent_snapshots_keep_cleared_values() {
+    let ctx = TestContext::new().await;
+    for i in 0..100 {
+        ctx.set(&format!("key:{i}"), "before").await.unwrap();
+    }
+
+    let first = Snapshot::begin(&ctx.state.dbs).await;
+    ctx.set("key:0", "between").await.unwrap();
+    let second = Snapshot::begin(&ctx.state.dbs).await;
+    for mut guard in ctx.state.dbs[0].lock_all_shards().await {
+        guard.clear();
+    }
+    assert_eq!(ctx.state.dbs[0].get_key_count(), 0);
+
+    let first = read_snapshot(&first, 0).await;
+    assert_eq!(first.len(), 100);
+    assert!(
+        first
+            .iter()
+            .all(|(_, value)| *value == string_value("before"))
+    );
+    let second = read_snapshot(&second, 0).await;
+    assert_eq!(second.len(), 100);
+    assert!(second.contains(&(Bytes::from("key:0"), string_value("between"))));
+}
+
+#[tokio::test]
+async fn test_snapshot_is_consistent_while_being_read() {
+    let ctx = TestContext::new().await;
+    for i in 0..500 {
+        ctx.set(&format!("key:{i}"), "original").await.unwrap();
+    }
+
+    let snapshot = Snapshot::begin(&ctx.state.dbs).await;
+    let mut seen = Vec::new();
+    let mut reader = snapshot.reader(0);
+    let mut chunks = 0;
+    while reader
+        .next_chunk(|key, value| {
+            seen.push((key.clone(), value.data.clone()));
+            Ok::<(), SpinelDBError>(())
+        })
+        .await
+        .unwrap()
+    {
+        // Rewrite and delete keys in shards that were and were not read yet.
+        chunks += 1;
+        for i in (chunks..500).step_by(7) {
+            ctx.set(&format!("key:{i}"), "rewritten").await.unwrap();
+        }
+        for i in (chunks..500).step_by(11) {
+            ctx.del(&[&format!("key:{i}")]).await.unwrap();
+        }
+        ctx.set(&format!("added:{chunks}"), "new").await.unwrap();
+    }
+
+    assert!(chunks > 1);
+    assert_eq!(seen.len(), 500);
+    assert!(
+        seen.iter()
+            .all(|(key, value)| { key.starts_with(b"key:") && *value == string_value("original") })
+    );
+}
+
+#[tokio::test]
+async fn test_snapshot_backed_save_matches_state_at_start() {
+    let ctx = TestContext::new().await;
+    for i in 0..100 {
+        ctx.set(&format!("key:{i}"), "before").await.unwrap();
+    }
+
+    // An abandoned snapshot must not affect later ones.
+    drop(Snapshot::begin(&ctx.state.dbs).await);
+    ctx.set("key:0", "after-abandoned").await.unwrap();
+
+    let bytes = spldb::save_to_bytes(&ctx.state.dbs, &[]).await.unwrap();
+    let restored = TestContext::new().await;
+    spldb::load_from_bytes(&bytes, &restored.state.dbs)
+        .await
+        .unwrap();
+    assert_eq!(
+        restored.get("key:0").await.unwrap(),
+        RespValue::BulkString(Bytes::from("after-abandoned"))
+    );
+    assert_eq!(
+        restored.get("key:99").await.unwrap(),
+        RespValue::BulkString(Bytes::from("before"))
+    );
+    assert_eq!(restored.state.dbs[0].get_key_count(), 100);
+}
@@ -1,10 +1,11 @@
 // tests/integration/pubsub_test.rs
 
 //! Integration tests for Pub/Sub commands
-//! Tests: PUBLISH, PUBSUB (CHANNELS, NUMSUB, NUMPAT)
+//! Tests: PUBLISH, SPUBLISH, PUBSUB (CHANNELS, NUMSUB, NUMPAT, SHARDCHANNELS, SHARDNUMSUB)
 
 use super::test_helpers::TestContext;
 use bytes::Bytes;
+use spineldb::config::Config;
 use spineldb::core::Command;
 use spineldb::core::RespValue;
 use spineldb::core::protocol::RespFrame;
@@ -886,3 +887,129 @@ async fn test_pubsub_resubscribe_same_pattern() {
     drop(receiver2);
     drop(receiver3);
 }
+
+// ===== Sharded Pub/Sub Tests =====
+
+#[tokio::test]
+async fn test_spublish_reaches_only_shard_subscribers() {
+    let ctx = TestContext::new().await;
+    let channel = Bytes::from("{user:1}:events");
+
+    let mut shard_rx = ctx.state.pubsub.subscribe_shard(&channel);
+    let _plain_rx = ctx.state.pubsub.subscribe(&channel);
+    let _pattern_rx = ctx.state.pubsub.subscribe_pattern(&Bytes::from("*"));
+
+    let result = ctx.spublish("{user:1}:events", "hello").await.unwrap();
+    assert_eq!(result, RespValue::Integer(1));
+    assert_eq!(shard_rx.recv().await.unwrap(), Bytes::from("hello"));
+
+    // PUBLISH reaches the plain and pattern subscribers, but not the shard subscriber.
+    let result = ctx.publish("{user:1}:events", "plain").await.unwrap();
+    assert_eq!(result, RespValue::Integer(2));
+    assert!(shard_rx.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn test_spublish_is_sent_to_replicas_but_not_the_aof() {
+    let mut config = Config::default();
+    config.databases = 1;
+    config.persistence.aof_enabled = true;
+    config.persistence.spldb_enabled = false;
+    let ctx = TestContext::with_config(config).await;
+    assert!(ctx.state.event_bus.is_aof_enabled());
+    let mut replication_rx = ctx.state.event_bus.subscribe_for_replication();
+    let published_before = ctx.state.event_bus.replication_published();
+
+    ctx.spublish("orders", "created").await.unwrap();
+
+    let work = replication_rx.recv().await.unwrap();
+    let frame: RespFrame = match work.uow {
+        spineldb::core::events::UnitOfWork::Command(command) => (*command).into(),
+        other => panic!("Expected a single command, got {other:?}"),
+    };
+    assert_eq!(
+        frame,
+        RespFrame::Array(vec![
+            RespFrame::BulkString(Bytes::from_static(b"SPUBLISH")),
+            RespFrame::BulkString(Bytes::from_static(b"orders")),
+            RespFrame::BulkString(Bytes::from_static(b"created")),
+        ])
+    );
+    assert_eq!(
+        ctx.state.event_bus.replication_published(),
+        published_before + 1
+    );
+    assert_eq!(
+        ctx.state
+            .persistence
+            .aof_enqueued_seq
+            .load(std::sync::atomic::Ordering::SeqCst),
+        0
+    );
+}
+
+#[tokio::test]
+async fn test_pubsub_shardchannels_and_shardnumsub() {
+    let ctx = TestContext::new().await;
+    let _rx1 = ctx.state.pubsub.subscribe_shard(&Bytes::from("shard:a"));
+    let _rx2 = ctx.state.pubsub.subscribe_shard(&Bytes::from("shard:a"));
+    let _rx3 = ctx.state.pubsub.subscribe_shard(&Bytes::from("other"));
+    let _plain = ctx.state.pubsub.subscribe(&Bytes::from("plain"));
+
+    let result = ctx
+        .pubsub_shard("SHARDCHANNELS", &["shard:*"])
+        .await
+        .unwrap();
+    assert_eq!(
+        result,
+        RespValue::Array(vec![RespValue::BulkString(Bytes::from("shard:a"))])
+    );
+
+    match ctx.pubsub_shard("SHARDCHANNELS", &[]).await.unwrap() {
+        RespValue::Array(channels) => assert_eq!(channels.len(), 2),
+        other => panic!("Expected array, got {other:?}"),
+    }
+
+    let result = ctx
+        .pubsub_shard("SHARDNUMSUB", &["shard:a", "other", "plain"])
+        .await
+        .unwrap();
+    assert_eq!(
+        result,
+        RespValue::Array(vec![
+            RespValue::BulkString(Bytes::from("shard:a")),
+            RespValue::Integer(2),
+            RespValue::BulkString(Bytes::from("other")),
+            RespValue::Integer(1),
+            RespValue::BulkString(Bytes::from("plain")),
+            RespValue::Integer(0),
+        ])
+    );
+
+    // Regular channel introspection does not list shard channels.
+    assert_eq!(
+        ctx.pubsub_channels(None).await.unwrap(),
+        RespValue::Array(vec![RespValue::BulkString(Bytes::from("plain"))])
+    );
+}
+
+#[tokio::test]
+async fn test_shard_channels_are_routed_by_slot() {
+    use spineldb::core::cluster::slot::get_slot;
+    use spineldb::core::commands::key_extractor::extract_keys_from_command;
+
+    let args = [
+        RespFrame::BulkString(Bytes::from("{room}:a")),
+        RespFrame::BulkString(Bytes::from("{room}:b")),
+    ];
+    let keys = extract_keys_from_command("SSUBSCRIBE", &args).unwrap();
+    assert_eq!(keys.len(), 2);
+    assert_eq!(get_slot(&keys[0]), get_slot(&keys[1]));
+
+    let keys = extract_keys_from_command("SPUBLISH", &args).unwrap();
+    assert_eq!(keys, vec![Bytes::from("{room}:a")]);
+
+    // Regular channels are not routed.
+    let keys = extract_keys_from_command("PUBLISH", &args).unwrap();
+    assert!(keys.is_empty());
+}
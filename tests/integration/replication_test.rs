@@ -1,7 +1,7 @@
 // tests/integration/replication_test.rs
 
 //! Integration tests for replication functionality
-//! Tests: ROLE, INFO replication, REPLCONF, replication backlog, min_replicas policy
+//! Tests: ROLE, INFO replication, REPLCONF, replication backlog, min_replicas policy, WAIT/WAITAOF
 
 use super::test_helpers::TestContext;
 use bytes::Bytes;
@@ -9,7 +9,7 @@ use spineldb::config::{ReplicationConfig, ReplicationPrimaryConfig};
 use spineldb::core::Command;
 use spineldb::core::RespValue;
 use spineldb::core::SpinelDBError;
-use spineldb::core::commands::generic::Role;
+use spineldb::core::commands::generic::{Role, Wait, WaitAof};
 use spineldb::core::protocol::RespFrame;
 use spineldb::core::state::{ReplicaStateInfo, ReplicaSyncState};
 use std::net::SocketAddr;
@@ -226,6 +226,7 @@ async fn test_replconf_ack() {
         ReplicaStateInfo {
             sync_state: ReplicaSyncState::Online,
             ack_offset: 0,
+            aof_ack_offset: 0,
             last_ack_time: std::time::Instant::now(),
         },
     );
@@ -467,6 +468,7 @@ async fn test_min_replicas_policy_with_enough_replicas() {
         ReplicaStateInfo {
             sync_state: ReplicaSyncState::Online,
             ack_offset: 100,
+            aof_ack_offset: 0,
             last_ack_time: std::time::Instant::now(), // Just now, so within lag
         },
     );
@@ -497,6 +499,7 @@ async fn test_min_replicas_policy_with_laggy_replica() {
     let replica_info = ReplicaStateInfo {
         sync_state: ReplicaSyncState::Online,
         ack_offset: 100,
+        aof_ack_offset: 0,
         last_ack_time: std::time::Instant::now() - Duration::from_secs(5), // 5 seconds ago
     };
     ctx.state.replica_states.insert(test_addr, replica_info);
@@ -532,6 +535,7 @@ async fn test_min_replicas_policy_with_awaiting_sync_replica() {
         ReplicaStateInfo {
             sync_state: ReplicaSyncState::AwaitingFullSync,
             ack_offset: 0,
+            aof_ack_offset: 0,
             last_ack_time: std::time::Instant::now(),
         },
     );
@@ -586,6 +590,7 @@ async fn test_min_replicas_policy_multiple_replicas() {
             ReplicaStateInfo {
                 sync_state: ReplicaSyncState::Online,
                 ack_offset: 100,
+                aof_ack_offset: 0,
                 last_ack_time: std::time::Instant::now(),
             },
         );
@@ -620,6 +625,7 @@ async fn test_min_replicas_policy_insufficient_replicas() {
             ReplicaStateInfo {
                 sync_state: ReplicaSyncState::Online,
                 ack_offset: 100,
+                aof_ack_offset: 0,
                 last_ack_time: std::time::Instant::now(),
             },
         );
@@ -656,6 +662,7 @@ async fn test_replica_state_online() {
     let replica_info = ReplicaStateInfo {
         sync_state: ReplicaSyncState::Online,
         ack_offset: 500,
+        aof_ack_offset: 0,
         last_ack_time: std::time::Instant::now(),
     };
 
@@ -683,6 +690,7 @@ async fn test_replica_state_awaiting_sync() {
     let replica_info = ReplicaStateInfo {
         sync_state: ReplicaSyncState::AwaitingFullSync,
         ack_offset: 0,
+        aof_ack_offset: 0,
         last_ack_time: std::time::Instant::now(),
     };
 
@@ -714,6 +722,7 @@ async fn test_replica_state_update_ack() {
         ReplicaStateInfo {
             sync_state: ReplicaSyncState::Online,
             ack_offset: 100,
+            aof_ack_offset: 0,
             last_ack_time: std::time::Instant::now(),
         },
     );
@@ -890,3 +899,206 @@ async fn test_replication_backlog_offset_between_frames() {
     // Should get frames at 200 and 300
     assert!(frames.len() >= 2);
 }
+
+#[tokio::test]
+async fn test_offset_after_units_waits_for_the_feeder() {
+    let ctx = TestContext::new().await;
+    let replication = &ctx.state.replication;
+    let master_repl_offset = &replication.replication_info.master_repl_offset;
+
+    // Two work units were published at the cut, but the feeder has consumed only one.
+    master_repl_offset.store(100, std::sync::atomic::Ordering::SeqCst);
+    replication.advance_feeder(1);
+    let mut offset_rx = replication.offset_after_units(2);
+    assert!(offset_rx.try_recv().is_err());
+
+    // The offset is taken once the feeder consumes the second unit, not later.
+    master_repl_offset.store(150, std::sync::atomic::Ordering::SeqCst);
+    replication.advance_feeder(1);
+    master_repl_offset.store(200, std::sync::atomic::Ordering::SeqCst);
+    replication.advance_feeder(1);
+    assert_eq!(offset_rx.await.unwrap(), 150);
+
+    // A feeder that has already caught up answers right away.
+    let offset_rx = replication.offset_after_units(3);
+    assert_eq!(offset_rx.await.unwrap(), 200);
+}
+
+#[tokio::test]
+async fn test_publish_to_aof_skips_replication() {
+    use spineldb::core::events::{EventBus, UnitOfWork};
+
+    let ctx = TestContext::new().await;
+    let (event_bus, aof_rx) = EventBus::new(true);
+    let mut aof_rx = aof_rx.unwrap();
+    let mut replication_rx = event_bus.subscribe_for_replication();
+
+    // Writes a replica applies from its primary are logged to its own AOF only.
+    let command = Command::try_from(RespFrame::Array(vec![
+        RespFrame::BulkString(Bytes::from_static(b"SET")),
+        RespFrame::BulkString(Bytes::from_static(b"key")),
+        RespFrame::BulkString(Bytes::from_static(b"value")),
+    ]))
+    .unwrap();
+    event_bus.publish_to_aof(UnitOfWork::Command(Box::new(command)), &ctx.state);
+
+    assert!(aof_rx.try_recv().is_ok());
+    assert!(replication_rx.try_recv().is_err());
+    assert_eq!(event_bus.replication_published(), 0);
+}
+
+// ===== WAIT / WAITAOF Tests =====
+
+#[tokio::test]
+async fn test_wait_counts_acked_replicas() {
+    let mut config = spineldb::config::Config::default();
+    config.databases = 1;
+    config.replication = ReplicationConfig::Primary(ReplicationPrimaryConfig::default());
+
+    let ctx = TestContext::with_config(config).await;
+
+    let test_addr = SocketAddr::from_str("127.0.0.1:9999").unwrap();
+    ctx.state.replica_states.insert(
+        test_addr,
+        ReplicaStateInfo {
+            sync_state: ReplicaSyncState::Online,
+            ack_offset: 0,
+            aof_ack_offset: 0,
+            last_ack_time: std::time::Instant::now(),
+        },
+    );
+    ctx.state
+        .replication
+        .replication_info
+        .master_repl_offset
+        .store(100, std::sync::atomic::Ordering::SeqCst);
+
+    // The replica acknowledges the current offset shortly after WAIT starts blocking.
+    let state = ctx.state.clone();
+    tokio::spawn(async move {
+        sleep(Duration::from_millis(50)).await;
+        state
+            .replication
+            .record_replica_ack(&state.replica_states, &test_addr, 100, None);
+    });
+
+    let result = ctx
+        .execute(Command::Wait(Wait {
+            num_replicas: 1,
+            timeout_ms: 5000,
+        }))
+        .await
+        .unwrap();
+    assert_eq!(result, RespValue::Integer(1));
+}
+
+#[tokio::test]
+async fn test_wait_times_out_without_replicas() {
+    let mut config = spineldb::config::Config::default();
+    config.databases = 1;
+    config.replication = ReplicationConfig::Primary(ReplicationPrimaryConfig::default());
+
+    let ctx = TestContext::with_config(config).await;
+
+    let result = ctx
+        .execute(Command::Wait(Wait {
+            num_replicas: 1,
+            timeout_ms: 100,
+        }))
+        .await
+        .unwrap();
+    assert_eq!(result, RespValue::Integer(0));
+}
+
+#[tokio::test]
+async fn test_waitaof_requires_appendonly_for_numlocal() {
+    let mut config = spineldb::config::Config::default();
+    config.databases = 1;
+    config.persistence.aof_enabled = false;
+    config.replication = ReplicationConfig::Primary(ReplicationPrimaryConfig::default());
+
+    let ctx = TestContext::with_config(config).await;
+
+    let result = ctx
+        .execute(Command::WaitAof(WaitAof {
+            num_local: 1,
+            num_replicas: 0,
+            timeout_ms: 100,
+        }))
+        .await;
+    assert!(matches!(result, Err(SpinelDBError::InvalidState(_))));
+
+    let result = ctx
+        .execute(Command::WaitAof(WaitAof {
+            num_local: 0,
+            num_replicas: 0,
+            timeout_ms: 100,
+        }))
+        .await
+        .unwrap();
+    assert_eq!(
+        result,
+        RespValue::Array(vec![RespValue::Integer(0), RespValue::Integer(0)])
+    );
+}
+
+#[tokio::test]
+async fn test_wait_commands_are_rejected_in_transactions() {
+    use spineldb::core::handler::transaction_handler::TransactionHandler;
+
+    let ctx = TestContext::new().await;
+    let handler = TransactionHandler::new(ctx.state.clone(), &ctx.db, 1, None);
+
+    for command in [
+        Command::Wait(Wait {
+            num_replicas: 1,
+            timeout_ms: 0,
+        }),
+        Command::WaitAof(WaitAof {
+            num_local: 0,
+            num_replicas: 1,
+            timeout_ms: 0,
+        }),
+    ] {
+        ctx.multi().await.unwrap();
+        let result = handler.handle_queueing(command).await.unwrap();
+        assert!(
+            matches!(&result, RespValue::Error(e) if e.contains("cannot be used in a transaction")),
+            "got {result:?}"
+        );
+        let result = ctx.exec().await;
+        assert!(
+            matches!(&result, Ok(RespValue::Error(e)) if e.starts_with("EXECABORT")),
+            "got {result:?}"
+        );
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_wait_commands_are_rejected_in_scripts() {
+    let ctx = TestContext::new().await;
+    let eval = |script: &'static str| {
+        Command::try_from(RespFrame::Array(vec![
+            RespFrame::BulkString(Bytes::from_static(b"EVAL")),
+            RespFrame::BulkString(Bytes::from_static(script.as_bytes())),
+            RespFrame::BulkString(Bytes::from_static(b"0")),
+        ]))
+        .unwrap()
+    };
+
+    let result = ctx
+        .execute(eval("return spinel.call('WAIT', '1', '0')"))
+        .await;
+    assert!(
+        matches!(&result, Err(e) if e.to_string().contains("not allowed from scripts")),
+        "got {result:?}"
+    );
+    let result = ctx
+        .execute(eval("return spinel.pcall('WAITAOF', '0', '1', '0')['err']"))
+        .await
+        .unwrap();
+    assert!(
+        matches!(&result, RespValue::BulkString(e) if String::from_utf8_lossy(e).contains("not allowed from scripts")),
+        "got {result:?}"
+    );
+}
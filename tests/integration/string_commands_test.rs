@@ -450,6 +450,19 @@ async fn test_mget_multiple_keys() {
     }
 }
 
+#[tokio::test]
+async fn test_mget_single_key() {
+    let ctx = TestContext::new().await;
+
+    ctx.set(TEST_KEY1, TEST_VALUE1).await.unwrap();
+
+    let result = ctx.mget(&[TEST_KEY1]).await.unwrap();
+    assert_eq!(
+        result,
+        RespValue::Array(vec![RespValue::BulkString(Bytes::from(TEST_VALUE1))])
+    );
+}
+
 #[tokio::test]
 async fn test_mget_empty_key_list() {
     let ctx = TestContext::new().await;
@@ -10,9 +10,12 @@ use spineldb::core::SpinelDBError;
 use spineldb::core::commands::command_trait::CommandExt;
 use spineldb::core::database::context::ExecutionContext;
 use spineldb::core::database::core::Db;
+use spineldb::core::persistence::AofWriterTask;
 use spineldb::core::protocol::RespFrame;
-use spineldb::core::state::ServerState;
+use spineldb::core::state::{ServerInit, ServerState};
 use std::sync::Arc;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
 use tracing_subscriber::prelude::*;
 use tracing_subscriber::{EnvFilter, reload};
 
@@ -37,6 +40,52 @@ impl TestContext {
 
     /// Creates a new test context with custom configuration
     pub async fn with_config(config: Config) -> Self {
+        let state = Self::initialize(config).state;
+        let db = state.get_db(0).expect("Failed to get database 0");
+
+        Self {
+            state,
+            db,
+            db_index: 0,
+        }
+    }
+
+    /// Creates a test context with AOF enabled and a running AOF writer task, so that
+    /// propagated writes reach the AOF files. Sending on the returned channel makes the
+    /// writer drain, fsync and stop.
+    #[allow(dead_code)]
+    pub async fn with_aof_writer(
+        config: Config,
+    ) -> (
+        Self,
+        broadcast::Sender<()>,
+        JoinHandle<Result<(), SpinelDBError>>,
+    ) {
+        let server_init = Self::initialize(config);
+        let state = server_init.state;
+        let writer = AofWriterTask::new(
+            state.clone(),
+            server_init
+                .aof_event_rx
+                .expect("AOF must be enabled in the config"),
+            server_init.aof_fsync_request_rx,
+            server_init.aof_rewrite_complete_rx,
+        )
+        .await
+        .expect("Failed to start the AOF writer");
+        let (shutdown_tx, shutdown_rx) = broadcast::channel(1);
+        let writer_handle = tokio::spawn(writer.run(shutdown_rx));
+
+        let db = state.get_db(0).expect("Failed to get database 0");
+        let ctx = Self {
+            state,
+            db,
+            db_index: 0,
+        };
+        (ctx, shutdown_tx, writer_handle)
+    }
+
+    fn initialize(config: Config) -> ServerInit {
         // Set up minimal tracing for tests
         let env_filter = EnvFilter::new("warn");
         let (filter, reload_handle) = reload::Layer::new(env_filter);
@@ -50,17 +99,7 @@ impl TestContext {
         let reload_handle = Arc::new(reload_handle);
 
         // Initialize server state
-        let server_init = ServerState::initialize(config, reload_handle)
-            .expect("Failed to initialize server state");
-
-        let state = server_init.state;
-        let db = state.get_db(0).expect("Failed to get database 0");
-
-        Self {
-            state,
-            db,
-            db_index: 0,
-        }
+        ServerState::initialize(config, reload_handle).expect("Failed to initialize server state")
     }
 
     /// Executes a command and returns the response value
@@ -128,6 +167,7 @@ impl TestContext {
             command: Some(command.clone()),
             session_id,
             authenticated_user: None,
+            write_guard: None,
         };
 
         let (resp, _outcome) = command.execute(&mut ctx).await?;
@@ -2189,6 +2229,33 @@ impl TestContext {
         self.execute(command).await
     }
 
+    /// Helper to execute SPUBLISH command
+    pub async fn spublish(&self, channel: &str, message: &str) -> Result<RespValue, SpinelDBError> {
+        let command = Command::try_from(RespFrame::Array(vec![
+            RespFrame::BulkString(Bytes::from_static(b"SPUBLISH")),
+            RespFrame::BulkString(Bytes::from(channel.to_string())),
+            RespFrame::BulkString(Bytes::from(message.to_string())),
+        ]))?;
+        self.execute(command).await
+    }
+
+    /// Helper to execute PUBSUB SHARDCHANNELS or PUBSUB SHARDNUMSUB
+    pub async fn pubsub_shard(
+        &self,
+        subcommand: &str,
+        args: &[&str],
+    ) -> Result<RespValue, SpinelDBError> {
+        let mut frames = vec![
+            RespFrame::BulkString(Bytes::from_static(b"PUBSUB")),
+            RespFrame::BulkString(Bytes::from(subcommand.to_string())),
+        ];
+        for arg in args {
+            frames.push(RespFrame::BulkString(Bytes::from(arg.to_string())));
+        }
+        let command = Command::try_from(RespFrame::Array(frames))?;
+        self.execute(command).await
+    }
+
     // ===== Stream Command Helpers =====
 
     #[allow(dead_code)]
@@ -0,0 +1,955 @@
+// tests/integration/warden_test.rs
+
+//! Integration tests for the Warden's Sentinel-compatible command API
+//! Tests: SENTINEL (MASTERS, MASTER, REPLICAS, SENTINELS, CKQUORUM, FAILOVER, RESET,
+//! MONITOR, REMOVE, SET, MYID, IS-MASTER-DOWN-BY-ADDR), INFO, the event channels, and
+//! leader election votes
+
+use futures::{SinkExt, StreamExt};
+use parking_lot::Mutex;
+use spineldb::core::protocol::{RespFrame, RespFrameCodec};
+use spineldb::core::warden::commands::process_warden_command;
+use spineldb::core::warden::config::{MonitoredMaster, WardenConfig};
+use spineldb::core::warden::listener::run_listener;
+use spineldb::core::warden::proxy::run_proxy;
+use spineldb::core::warden::start_monitor;
+use spineldb::core::warden::state::{
+    FailoverState, GlobalWardenState, InstanceState, MasterState, MasterStatus, WardenEvent,
+    WardenPeerState,
+};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_util::codec::Framed;
+
+/// A port nothing listens on, so that monitors started by the tests never connect.
+const UNREACHABLE_PORT: &str = "1";
+
+fn warden() -> Arc<GlobalWardenState> {
+    Arc::new(GlobalWardenState::new(
+        "warden-run-id".to_string(),
+        "127.0.0.1:26379".parse().unwrap(),
+    ))
+}
+
+async fn run(state: &Arc<GlobalWardenState>, args: &[&str]) -> RespFrame {
+    let frames: Vec<RespFrame> = args
+        .iter()
+        .map(|arg| RespFrame::BulkString(arg.to_string().into()))
+        .collect();
+    process_warden_command(&frames, state).await
+}
+
+fn text(frame: &RespFrame) -> String {
+    match frame {
+        RespFrame::BulkString(bytes) => String::from_utf8_lossy(bytes).to_string(),
+        RespFrame::SimpleString(s) => s.clone(),
+        other => panic!("Expected a string, got {other:?}"),
+    }
+}
+
+/// Converts a flat `[field, value, ...]` reply into a map.
+fn field_map(frame: &RespFrame) -> HashMap<String, String> {
+    let RespFrame::Array(items) = frame else {
+        panic!("Expected an array, got {frame:?}");
+    };
+    items
+        .chunks(2)
+        .map(|pair| (text(&pair[0]), text(&pair[1])))
+        .collect()
+}
+
+fn error(frame: RespFrame) -> String {
+    match frame {
+        RespFrame::Error(e) => e,
+        other => panic!("Expected an error, got {other:?}"),
+    }
+}
+
+async fn monitored(name: &str, quorum: &str) -> Arc<GlobalWardenState> {
+    let state = warden();
+    let reply = run(
+        &state,
+        &[
+            "SENTINEL",
+            "MONITOR",
+            name,
+            "127.0.0.1",
+            UNREACHABLE_PORT,
+            quorum,
+        ],
+    )
+    .await;
+    assert_eq!(reply, RespFrame::SimpleString("OK".into()));
+    state
+}
+
+fn free_port() -> u16 {
+    let probe = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    probe.local_addr().unwrap().port()
+}
+
+fn add_replica(state: &GlobalWardenState, name: &str, addr: &str) {
+    let master = state.masters.get(name).unwrap().value().clone();
+    let addr: SocketAddr = addr.parse().unwrap();
+    master
+        .lock()
+        .replicas
+        .insert(addr, InstanceState::new(addr));
+}
+
+fn add_peer(state: &GlobalWardenState, name: &str, run_id: &str) {
+    let master = state.masters.get(name).unwrap().value().clone();
+    master.lock().peers.insert(
+        run_id.to_string(),
+        WardenPeerState {
+            run_id: run_id.to_string(),
+            addr: "127.0.0.1:26380".parse().unwrap(),
+            last_hello_received: Instant::now(),
+        },
+    );
+}
+
+#[tokio::test]
+async fn test_ping_and_myid() {
+    let state = warden();
+    assert_eq!(
+        run(&state, &["PING"]).await,
+        RespFrame::SimpleString("PONG".into())
+    );
+    assert_eq!(
+        text(&run(&state, &["SENTINEL", "MYID"]).await),
+        "warden-run-id"
+    );
+}
+
+#[tokio::test]
+async fn test_monitor_adds_master() {
+    let state = monitored("mymaster", "2").await;
+
+    let RespFrame::Array(masters) = run(&state, &["SENTINEL", "MASTERS"]).await else {
+        panic!("Expected an array");
+    };
+    assert_eq!(masters.len(), 1);
+    let fields = field_map(&masters[0]);
+    assert_eq!(fields["name"], "mymaster");
+    assert_eq!(fields["ip"], "127.0.0.1");
+    assert_eq!(fields["port"], UNREACHABLE_PORT);
+    assert_eq!(fields["flags"], "master");
+    assert_eq!(fields["quorum"], "2");
+    assert_eq!(fields["num-slaves"], "0");
+    assert_eq!(fields["failover-state"], "none");
+
+    assert_eq!(
+        run(&state, &["SENTINEL", "GET-MASTER-ADDR-BY-NAME", "mymaster"]).await,
+        RespFrame::Array(vec![
+            RespFrame::BulkString("127.0.0.1".into()),
+            RespFrame::Integer(1),
+        ])
+    );
+}
+
+#[tokio::test]
+async fn test_monitor_validates_arguments() {
+    let state = monitored("mymaster", "1").await;
+
+    let duplicate = run(
+        &state,
+        &["SENTINEL", "MONITOR", "mymaster", "127.0.0.1", "6380", "1"],
+    )
+    .await;
+    assert_eq!(error(duplicate), "ERR Duplicated master name");
+
+    let bad_addr = run(
+        &state,
+        &["SENTINEL", "MONITOR", "other", "not-an-ip", "6380", "1"],
+    )
+    .await;
+    assert!(error(bad_addr).contains("Invalid IP address or port"));
+
+    let bad_quorum = run(
+        &state,
+        &["SENTINEL", "MONITOR", "other", "127.0.0.1", "6380", "0"],
+    )
+    .await;
+    assert!(error(bad_quorum).contains("Quorum must be 1 or greater"));
+    assert_eq!(state.masters.len(), 1);
+}
+
+#[tokio::test]
+async fn test_remove_stops_monitoring() {
+    let state = monitored("mymaster", "1").await;
+
+    assert_eq!(
+        run(&state, &["SENTINEL", "REMOVE", "mymaster"]).await,
+        RespFrame::SimpleString("OK".into())
+    );
+    assert!(state.masters.is_empty());
+    assert!(state.monitors.is_empty());
+    assert_eq!(
+        run(&state, &["SENTINEL", "GET-MASTER-ADDR-BY-NAME", "mymaster"]).await,
+        RespFrame::Null
+    );
+}
+
+#[tokio::test]
+async fn test_unknown_master_is_an_error() {
+    let state = warden();
+    for subcommand in [
+        "MASTER",
+        "REPLICAS",
+        "SENTINELS",
+        "CKQUORUM",
+        "FAILOVER",
+        "REMOVE",
+    ] {
+        let reply = run(&state, &["SENTINEL", subcommand, "nope"]).await;
+        assert_eq!(error(reply), "ERR No such master with that name");
+    }
+}
+
+#[tokio::test]
+async fn test_replicas_and_sentinels() {
+    let state = monitored("mymaster", "2").await;
+    add_replica(&state, "mymaster", "127.0.0.1:6381");
+    add_peer(&state, "mymaster", "peer-run-id");
+
+    let RespFrame::Array(replicas) = run(&state, &["SENTINEL", "REPLICAS", "mymaster"]).await
+    else {
+        panic!("Expected an array");
+    };
+    assert_eq!(replicas.len(), 1);
+    let replica = field_map(&replicas[0]);
+    assert_eq!(replica["name"], "127.0.0.1:6381");
+    assert_eq!(replica["flags"], "slave");
+    assert_eq!(replica["master-port"], UNREACHABLE_PORT);
+    // SLAVES is an alias of REPLICAS.
+    assert_eq!(
+        run(&state, &["SENTINEL", "SLAVES", "mymaster"]).await,
+        RespFrame::Array(replicas)
+    );
+
+    let RespFrame::Array(peers) = run(&state, &["SENTINEL", "SENTINELS", "mymaster"]).await else {
+        panic!("Expected an array");
+    };
+    assert_eq!(peers.len(), 1);
+    let peer = field_map(&peers[0]);
+    assert_eq!(peer["runid"], "peer-run-id");
+    assert_eq!(peer["flags"], "sentinel");
+
+    let master = field_map(&run(&state, &["SENTINEL", "MASTER", "mymaster"]).await);
+    assert_eq!(master["num-slaves"], "1");
+    assert_eq!(master["num-other-sentinels"], "1");
+}
+
+#[tokio::test]
+async fn test_ckquorum() {
+    let state = monitored("mymaster", "2").await;
+
+    let reply = run(&state, &["SENTINEL", "CKQUORUM", "mymaster"]).await;
+    assert!(error(reply).starts_with("NOQUORUM 1 usable Wardens"));
+
+    add_peer(&state, "mymaster", "peer-run-id");
+    let reply = run(&state, &["SENTINEL", "CKQUORUM", "mymaster"]).await;
+    assert!(text(&reply).starts_with("OK 2 usable Wardens"));
+}
+
+#[tokio::test]
+async fn test_set_options() {
+    let state = monitored("mymaster", "1").await;
+
+    let reply = run(
+        &state,
+        &[
+            "SENTINEL",
+            "SET",
+            "mymaster",
+            "down-after-milliseconds",
+            "5000",
+            "quorum",
+            "3",
+        ],
+    )
+    .await;
+    assert_eq!(reply, RespFrame::SimpleString("OK".into()));
+    let master = field_map(&run(&state, &["SENTINEL", "MASTER", "mymaster"]).await);
+    assert_eq!(master["down-after-milliseconds"], "5000");
+    assert_eq!(master["quorum"], "3");
+
+    // An invalid option is rejected without applying the valid ones before it.
+    let reply = run(
+        &state,
+        &[
+            "SENTINEL",
+            "SET",
+            "mymaster",
+            "quorum",
+            "5",
+            "failover-timeout",
+            "soon",
+        ],
+    )
+    .await;
+    assert!(error(reply).contains("Invalid argument 'soon'"));
+    let master = field_map(&run(&state, &["SENTINEL", "MASTER", "mymaster"]).await);
+    assert_eq!(master["quorum"], "3");
+}
+
+#[tokio::test]
+async fn test_failover_preconditions() {
+    let state = monitored("mymaster", "1").await;
+
+    let reply = run(&state, &["SENTINEL", "FAILOVER", "mymaster"]).await;
+    assert!(error(reply).starts_with("NOGOODSLAVE"));
+
+    add_replica(&state, "mymaster", "127.0.0.1:6381");
+    state.masters.get("mymaster").unwrap().lock().failover_state = FailoverState::SelectReplica;
+    let reply = run(&state, &["SENTINEL", "FAILOVER", "mymaster"]).await;
+    assert_eq!(error(reply), "INPROG Failover already in progress");
+}
+
+#[tokio::test]
+async fn test_reset_matches_pattern() {
+    let state = monitored("cache-1", "1").await;
+    run(
+        &state,
+        &[
+            "SENTINEL",
+            "MONITOR",
+            "cache-2",
+            "127.0.0.1",
+            UNREACHABLE_PORT,
+            "1",
+        ],
+    )
+    .await;
+    run(
+        &state,
+        &[
+            "SENTINEL",
+            "MONITOR",
+            "sessions",
+            "127.0.0.1",
+            UNREACHABLE_PORT,
+            "1",
+        ],
+    )
+    .await;
+    add_replica(&state, "cache-1", "127.0.0.1:6381");
+    add_replica(&state, "sessions", "127.0.0.1:6382");
+    state.masters.get("cache-2").unwrap().lock().status = MasterStatus::Sdown;
+
+    assert_eq!(
+        run(&state, &["SENTINEL", "RESET", "cache-*"]).await,
+        RespFrame::Integer(2)
+    );
+    assert!(
+        state
+            .masters
+            .get("cache-1")
+            .unwrap()
+            .lock()
+            .replicas
+            .is_empty()
+    );
+    assert_eq!(
+        state.masters.get("cache-2").unwrap().lock().status,
+        MasterStatus::Ok
+    );
+    assert_eq!(
+        state.masters.get("sessions").unwrap().lock().replicas.len(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn test_info_sections() {
+    let state = monitored("mymaster", "1").await;
+    state.masters.get("mymaster").unwrap().lock().status = MasterStatus::Odown;
+
+    let info = text(&run(&state, &["INFO"]).await);
+    assert!(info.contains("# Server\r\nrun_id:warden-run-id"));
+    assert!(info.contains("sentinel_masters:1"));
+    assert!(
+        info.contains(
+            "master0:name=mymaster,status=odown,address=127.0.0.1:1,slaves=0,sentinels=1"
+        )
+    );
+
+    let info = text(&run(&state, &["INFO", "sentinel"]).await);
+    assert!(!info.contains("# Server"));
+    assert!(info.contains("# Sentinel"));
+}
+
+#[tokio::test]
+async fn test_commands_publish_events() {
+    let state = warden();
+    let mut events = state.events.subscribe();
+
+    run(
+        &state,
+        &[
+            "SENTINEL",
+            "MONITOR",
+            "mymaster",
+            "127.0.0.1",
+            UNREACHABLE_PORT,
+            "2",
+        ],
+    )
+    .await;
+    run(&state, &["SENTINEL", "REMOVE", "mymaster"]).await;
+
+    assert_eq!(
+        events.recv().await.unwrap(),
+        WardenEvent {
+            channel: "+monitor".to_string(),
+            message: "master mymaster 127.0.0.1 1 quorum 2".to_string(),
+        }
+    );
+    assert_eq!(
+        events.recv().await.unwrap(),
+        WardenEvent {
+            channel: "-monitor".to_string(),
+            message: "master mymaster 127.0.0.1 1".to_string(),
+        }
+    );
+}
+
+#[tokio::test]
+async fn test_listener_delivers_subscribed_events() {
+    let port = free_port();
+    let state = warden();
+    tokio::spawn(run_listener(port, state.clone()));
+
+    let mut client = None;
+    for _ in 0..50 {
+        if let Ok(stream) = TcpStream::connect(("127.0.0.1", port)).await {
+            client = Some(Framed::new(stream, RespFrameCodec));
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    let mut client = client.expect("Warden listener did not start");
+    let command = |args: &[&str]| {
+        RespFrame::Array(
+            args.iter()
+                .map(|arg| RespFrame::BulkString(arg.to_string().into()))
+                .collect(),
+        )
+    };
+
+    client
+        .send(command(&["SUBSCRIBE", "+switch-master"]))
+        .await
+        .unwrap();
+    let reply = client.next().await.unwrap().unwrap();
+    assert_eq!(
+        reply,
+        RespFrame::Array(vec![
+            RespFrame::BulkString("subscribe".into()),
+            RespFrame::BulkString("+switch-master".into()),
+            RespFrame::Integer(1),
+        ])
+    );
+    client.send(command(&["PSUBSCRIBE", "+*"])).await.unwrap();
+    client.next().await.unwrap().unwrap();
+
+    state.publish_event(
+        "+switch-master",
+        "mymaster 127.0.0.1 6379 127.0.0.1 6380".to_string(),
+    );
+    let message = tokio::time::timeout(Duration::from_secs(5), client.next())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        message,
+        RespFrame::Array(vec![
+            RespFrame::BulkString("message".into()),
+            RespFrame::BulkString("+switch-master".into()),
+            RespFrame::BulkString("mymaster 127.0.0.1 6379 127.0.0.1 6380".into()),
+        ])
+    );
+    let pmessage = tokio::time::timeout(Duration::from_secs(5), client.next())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    let RespFrame::Array(items) = pmessage else {
+        panic!("Expected an array");
+    };
+    assert_eq!(items[0], RespFrame::BulkString("pmessage".into()));
+    assert_eq!(items[1], RespFrame::BulkString("+*".into()));
+
+    // Regular commands are still answered while subscribed.
+    client.send(command(&["PING"])).await.unwrap();
+    assert_eq!(
+        client.next().await.unwrap().unwrap(),
+        RespFrame::SimpleString("PONG".into())
+    );
+}
+
+#[tokio::test]
+async fn test_listener_requires_auth_to_change_state() {
+    let port = free_port();
+    let state = Arc::new(
+        GlobalWardenState::new(
+            "warden-run-id".to_string(),
+            "127.0.0.1:26379".parse().unwrap(),
+        )
+        .with_password(Some("secret".to_string())),
+    );
+    tokio::spawn(run_listener(port, state.clone()));
+
+    let mut client = None;
+    for _ in 0..50 {
+        if let Ok(stream) = TcpStream::connect(("127.0.0.1", port)).await {
+            client = Some(Framed::new(stream, RespFrameCodec));
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    let mut client = client.expect("Warden listener did not start");
+    let mut request = async |args: &[&str]| {
+        let frame = RespFrame::Array(
+            args.iter()
+                .map(|arg| RespFrame::BulkString(arg.to_string().into()))
+                .collect(),
+        );
+        client.send(frame).await.unwrap();
+        client.next().await.unwrap().unwrap()
+    };
+    let monitor = [
+        "SENTINEL",
+        "MONITOR",
+        "mymaster",
+        "127.0.0.1",
+        UNREACHABLE_PORT,
+        "1",
+    ];
+
+    // Queries are answered without a password, but changes are refused.
+    assert_eq!(text(&request(&["SENTINEL", "MYID"]).await), "warden-run-id");
+    assert!(error(request(&monitor).await).starts_with("NOAUTH"));
+    assert!(error(request(&["sentinel", "reset", "*"]).await).starts_with("NOAUTH"));
+    let vote = [
+        "SENTINEL",
+        "IS-MASTER-DOWN-BY-ADDR",
+        "127.0.0.1",
+        UNREACHABLE_PORT,
+        "1",
+        "candidate-a",
+    ];
+    assert!(error(request(&vote).await).starts_with("NOAUTH"));
+    assert!(state.masters.is_empty());
+
+    assert!(error(request(&["AUTH", "wrong"]).await).starts_with("WRONGPASS"));
+    assert!(error(request(&monitor).await).starts_with("NOAUTH"));
+
+    assert_eq!(text(&request(&["AUTH", "secret"]).await), "OK");
+    assert_eq!(text(&request(&monitor).await), "OK");
+    assert!(state.masters.contains_key("mymaster"));
+}
+
+// ===== Leader Election Tests =====
+
+fn mark_master_down(state: &GlobalWardenState, name: &str) {
+    state
+        .masters
+        .get(name)
+        .unwrap()
+        .lock()
+        .primary_state
+        .down_since = Some(Instant::now());
+}
+
+async fn request_vote(state: &Arc<GlobalWardenState>, epoch: &str, candidate: &str) -> RespFrame {
+    run(
+        state,
+        &[
+            "SENTINEL",
+            "IS-MASTER-DOWN-BY-ADDR",
+            "127.0.0.1",
+            UNREACHABLE_PORT,
+            epoch,
+            candidate,
+        ],
+    )
+    .await
+}
+
+fn vote_reply(is_down: bool, leader: &str, leader_epoch: i64) -> RespFrame {
+    RespFrame::Array(vec![
+        RespFrame::Integer(is_down as i64),
+        RespFrame::BulkString(leader.to_string().into()),
+        RespFrame::Integer(leader_epoch),
+    ])
+}
+
+#[tokio::test]
+async fn test_vote_requires_master_down() {
+    let state = monitored("mymaster", "2").await;
+
+    assert_eq!(
+        request_vote(&state, "1", "candidate-a").await,
+        vote_reply(false, "*", 0)
+    );
+    // An unknown master address never gets a vote.
+    let reply = run(
+        &state,
+        &[
+            "SENTINEL",
+            "IS-MASTER-DOWN-BY-ADDR",
+            "127.0.0.1",
+            "6390",
+            "1",
+            "candidate-a",
+        ],
+    )
+    .await;
+    assert_eq!(reply, vote_reply(false, "*", 0));
+}
+
+#[tokio::test]
+async fn test_vote_granted_once_per_epoch() {
+    let state = monitored("mymaster", "2").await;
+    mark_master_down(&state, "mymaster");
+    let mut events = state.events.subscribe();
+
+    assert_eq!(
+        request_vote(&state, "5", "candidate-a").await,
+        vote_reply(true, "candidate-a", 5)
+    );
+    // A second candidate in the same epoch is told who got the vote.
+    assert_eq!(
+        request_vote(&state, "5", "candidate-b").await,
+        vote_reply(true, "candidate-a", 5)
+    );
+    // An older epoch does not get a vote either.
+    assert_eq!(
+        request_vote(&state, "4", "candidate-b").await,
+        vote_reply(true, "candidate-a", 5)
+    );
+    // A newer epoch does.
+    assert_eq!(
+        request_vote(&state, "6", "candidate-b").await,
+        vote_reply(true, "candidate-b", 6)
+    );
+
+    let event = events.recv().await.unwrap();
+    assert_eq!(event.channel, "+vote-for-leader");
+    assert!(event.message.ends_with("candidate-a 5"));
+}
+
+#[tokio::test]
+async fn test_vote_in_newer_epoch_aborts_own_election() {
+    let state = monitored("mymaster", "2").await;
+    {
+        let master = state.masters.get("mymaster").unwrap().value().clone();
+        let mut master = master.lock();
+        master.record_vote("warden-run-id".to_string(), 3);
+        master.failover_state = FailoverState::Vote;
+    }
+
+    // The master is still up here, so no vote is granted, but the epoch is adopted.
+    assert_eq!(
+        request_vote(&state, "4", "candidate-a").await,
+        vote_reply(false, "warden-run-id", 3)
+    );
+    let master = state.masters.get("mymaster").unwrap().value().clone();
+    let master = master.lock();
+    assert_eq!(master.config_epoch, 4);
+    assert_eq!(master.failover_state, FailoverState::None);
+}
+
+#[tokio::test]
+async fn test_vote_refuses_epochs_too_far_ahead() {
+    let state = monitored("mymaster", "2").await;
+    mark_master_down(&state, "mymaster");
+
+    let max = u64::MAX.to_string();
+    assert!(error(request_vote(&state, &max, "candidate-a").await).contains("too far ahead"));
+    let master = state.masters.get("mymaster").unwrap().value().clone();
+    assert_eq!(master.lock().config_epoch, 0);
+    assert_eq!(master.lock().voted_for, None);
+
+    // Even at the largest epoch, a failover on request fails instead of overflowing.
+    master.lock().config_epoch = u64::MAX;
+    add_replica(&state, "mymaster", "127.0.0.1:6390");
+    let reply = run(&state, &["SENTINEL", "FAILOVER", "mymaster"]).await;
+    assert!(error(reply).contains("cannot be raised"));
+}
+
+#[tokio::test]
+async fn test_required_votes_is_a_majority() {
+    let state = monitored("mymaster", "1").await;
+    assert_eq!(
+        state
+            .masters
+            .get("mymaster")
+            .unwrap()
+            .lock()
+            .required_votes(),
+        1
+    );
+    add_peer(&state, "mymaster", "peer-1");
+    add_peer(&state, "mymaster", "peer-2");
+    assert_eq!(
+        state
+            .masters
+            .get("mymaster")
+            .unwrap()
+            .lock()
+            .required_votes(),
+        2
+    );
+    state.masters.get("mymaster").unwrap().lock().config.quorum = 3;
+    assert_eq!(
+        state
+            .masters
+            .get("mymaster")
+            .unwrap()
+            .lock()
+            .required_votes(),
+        3
+    );
+}
+
+#[tokio::test]
+async fn test_ckquorum_requires_reachable_majority() {
+    let state = monitored("mymaster", "1").await;
+    add_peer(&state, "mymaster", "peer-1");
+    add_peer(&state, "mymaster", "peer-2");
+    let reply = run(&state, &["SENTINEL", "CKQUORUM", "mymaster"]).await;
+    assert!(text(&reply).starts_with("OK 3 usable Wardens"));
+
+    // Peers that stopped sending hellos are still counted in the majority, but are
+    // not usable.
+    for peer in state
+        .masters
+        .get("mymaster")
+        .unwrap()
+        .lock()
+        .peers
+        .values_mut()
+    {
+        peer.last_hello_received = Instant::now() - Duration::from_secs(3600);
+    }
+    let reply = run(&state, &["SENTINEL", "CKQUORUM", "mymaster"]).await;
+    assert!(error(reply).starts_with("NOAUTH 1 usable Wardens"));
+}
+
+#[tokio::test]
+async fn test_peers_stay_counted_when_the_master_goes_away() {
+    let state = warden();
+    let mut config = MonitoredMaster::new(
+        "mymaster".to_string(),
+        "127.0.0.1".to_string(),
+        UNREACHABLE_PORT.parse().unwrap(),
+        2,
+    );
+    config.down_after = Duration::from_millis(300);
+    start_monitor(&state, config);
+    add_peer(&state, "mymaster", "peer-1");
+    add_peer(&state, "mymaster", "peer-2");
+    // Hellos travel over the master, so none arrive once it is gone.
+    for peer in state
+        .masters
+        .get("mymaster")
+        .unwrap()
+        .lock()
+        .peers
+        .values_mut()
+    {
+        peer.last_hello_received = Instant::now() - Duration::from_secs(3600);
+    }
+
+    // The election still starts, and still needs a majority of all three Wardens.
+    let deadline = Instant::now() + Duration::from_secs(5);
+    loop {
+        {
+            let master = state.masters.get("mymaster").unwrap().value().clone();
+            let master = master.lock();
+            if master.failover_state == FailoverState::Vote {
+                assert_eq!(master.peers.len(), 2);
+                assert_eq!(master.required_votes(), 2);
+                break;
+            }
+        }
+        assert!(Instant::now() < deadline, "the election never started");
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+}
+
+#[tokio::test]
+async fn test_votes_are_persisted_to_config_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("warden.toml");
+    let config = WardenConfig {
+        host: "127.0.0.1".to_string(),
+        port: 26379,
+        announce_ip: None,
+        password: None,
+        my_id: None,
+        masters: Vec::new(),
+    };
+    let state = Arc::new(
+        GlobalWardenState::new(
+            "warden-run-id".to_string(),
+            "127.0.0.1:26379".parse().unwrap(),
+        )
+        .with_config_file(path.clone(), config),
+    );
+    run(
+        &state,
+        &[
+            "SENTINEL",
+            "MONITOR",
+            "mymaster",
+            "127.0.0.1",
+            UNREACHABLE_PORT,
+            "2",
+        ],
+    )
+    .await;
+    add_peer(&state, "mymaster", "peer-1");
+    mark_master_down(&state, "mymaster");
+    request_vote(&state, "7", "candidate-a").await;
+
+    let saved = WardenConfig::from_file(path.to_str().unwrap())
+        .await
+        .unwrap();
+    assert_eq!(saved.my_id.as_deref(), Some("warden-run-id"));
+    assert_eq!(saved.masters.len(), 1);
+    let master = &saved.masters[0];
+    assert_eq!(master.name, "mymaster");
+    assert_eq!(master.quorum, 2);
+    assert_eq!(master.leader.as_deref(), Some("candidate-a"));
+    assert_eq!(master.leader_epoch, 7);
+    assert_eq!(master.current_epoch, 7);
+    assert_eq!(master.known_wardens.len(), 1);
+    assert_eq!(master.known_wardens[0].run_id, "peer-1");
+
+    // A Warden restarted from the file does not vote again in that epoch, and still
+    // counts the Wardens it knew towards the majority.
+    let restored = MasterState::from(master.clone());
+    assert_eq!(restored.last_voted_epoch, 7);
+    assert_eq!(restored.voted_for.as_deref(), Some("candidate-a"));
+    assert_eq!(restored.config_epoch, 7);
+    assert!(restored.peers.contains_key("peer-1"));
+    assert_eq!(restored.required_votes(), 2);
+}
+
+// ===== Proxy Tests =====
+
+/// Spawns a server that answers everything it receives with `<name>:<data>`.
+async fn spawn_tagged_echo(name: &'static str) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let mut buf = [0u8; 64];
+                while let Ok(n) = socket.read(&mut buf).await {
+                    if n == 0 {
+                        return;
+                    }
+                    let mut reply = format!("{name}:").into_bytes();
+                    reply.extend_from_slice(&buf[..n]);
+                    if socket.write_all(&reply).await.is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+    });
+    addr
+}
+
+async fn connect_with_retry(port: u16) -> TcpStream {
+    for _ in 0..50 {
+        if let Ok(stream) = TcpStream::connect(("127.0.0.1", port)).await {
+            return stream;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("Proxy did not start");
+}
+
+async fn round_trip(stream: &mut TcpStream, data: &str) -> String {
+    stream.write_all(data.as_bytes()).await.unwrap();
+    let mut buf = [0u8; 64];
+    let n = tokio::time::timeout(Duration::from_secs(5), stream.read(&mut buf))
+        .await
+        .unwrap()
+        .unwrap();
+    String::from_utf8_lossy(&buf[..n]).to_string()
+}
+
+#[tokio::test]
+async fn test_proxy_follows_switch_master() {
+    let primary_a = spawn_tagged_echo("a").await;
+    let primary_b = spawn_tagged_echo("b").await;
+    let state = warden();
+    let master = Arc::new(Mutex::new(MasterState::from(MonitoredMaster::new(
+        "mymaster".to_string(),
+        primary_a.ip().to_string(),
+        primary_a.port(),
+        1,
+    ))));
+    state.masters.insert("mymaster".to_string(), master.clone());
+    let port = free_port();
+    let proxy = tokio::spawn(run_proxy(
+        port,
+        "mymaster".to_string(),
+        master.clone(),
+        state.clone(),
+    ));
+
+    let mut client = connect_with_retry(port).await;
+    assert_eq!(round_trip(&mut client, "ping").await, "a:ping");
+
+    // A switch of another master leaves the connection alone.
+    state.publish_event("+switch-master", "other 10.0.0.1 1 10.0.0.2 2".to_string());
+    assert_eq!(round_trip(&mut client, "still").await, "a:still");
+
+    // Switch the master the way a failover does: publish, then update the address
+    // under the same lock.
+    {
+        let mut master = master.lock();
+        state.publish_event(
+            "+switch-master",
+            format!(
+                "mymaster {} {} {} {}",
+                primary_a.ip(),
+                primary_a.port(),
+                primary_b.ip(),
+                primary_b.port()
+            ),
+        );
+        master.addr = primary_b;
+    }
+    let mut buf = [0u8; 16];
+    let n = tokio::time::timeout(Duration::from_secs(5), client.read(&mut buf))
+        .await
+        .unwrap()
+        .unwrap_or(0);
+    assert_eq!(n, 0, "The connection to the old primary must be closed");
+
+    let mut client = connect_with_retry(port).await;
+    assert_eq!(round_trip(&mut client, "ping").await, "b:ping");
+
+    // Stopping the proxy closes its connections.
+    proxy.abort();
+    let _ = proxy.await;
+    let n = tokio::time::timeout(Duration::from_secs(5), client.read(&mut buf))
+        .await
+        .unwrap()
+        .unwrap_or(0);
+    assert_eq!(n, 0);
+}
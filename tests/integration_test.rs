@@ -8,6 +8,7 @@
 mod integration {
     pub mod acl_test;
     pub mod blocking_test;
+    pub mod cache_http_test;
     pub mod cache_test;
     pub mod cluster_test;
     pub mod fixtures;
@@ -17,11 +18,13 @@ mod integration {
     pub mod list_commands_test;
     pub mod persistence_test;
     pub mod pubsub_test;
+    pub mod rdb_test;
     pub mod replication_test;
     pub mod set_commands_test;
     pub mod stream_commands_test;
     pub mod string_commands_test;
     pub mod test_helpers;
     pub mod transaction_test;
+    pub mod warden_test;
     pub mod zset_commands_test;
 }
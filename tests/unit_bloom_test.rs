@@ -53,6 +53,7 @@ mod bloom_test_helpers {
                 command: Some(command.clone()),
                 session_id: 1,
                 authenticated_user: None,
+                write_guard: None,
             };
             let (resp, _outcome) = command.execute(&mut ctx).await?;
             Ok(resp)
@@ -0,0 +1,154 @@
+use bytes::Bytes;
+use spineldb::core::commands::cache::cache_policy::{CachePolicyCmd, CachePolicySubcommand};
+use spineldb::core::commands::command_spec::CommandSpec;
+use spineldb::core::commands::command_trait::{CommandFlags, ParseCommand};
+use spineldb::core::protocol::RespFrame;
+
+fn frames(args: &[&str]) -> Vec<RespFrame> {
+    args.iter()
+        .map(|arg| RespFrame::BulkString(Bytes::from(arg.to_string())))
+        .collect()
+}
+
+fn round_trip(cmd: &CachePolicyCmd) -> CachePolicyCmd {
+    let args: Vec<RespFrame> = cmd
+        .to_resp_args()
+        .into_iter()
+        .map(RespFrame::BulkString)
+        .collect();
+    CachePolicyCmd::parse(&args).unwrap()
+}
+
+#[tokio::test]
+async fn test_policy_set_parse_options() {
+    let cmd = CachePolicyCmd::parse(&frames(&[
+        "SET",
+        "products",
+        "/products/*",
+        "https://api.example.com/products/{1}",
+        "TTL",
+        "300",
+        "SWR",
+        "60",
+        "PRIORITY",
+        "5",
+        "PREWARM",
+        "TAGS",
+        "products",
+        "catalog",
+    ]))
+    .unwrap();
+    match cmd.subcommand {
+        CachePolicySubcommand::Set(policy) => {
+            assert_eq!(policy.name, "products");
+            assert_eq!(policy.ttl, Some(300));
+            assert_eq!(policy.swr, Some(60));
+            assert_eq!(policy.grace, None);
+            assert_eq!(policy.priority, 5);
+            assert!(policy.prewarm);
+            assert_eq!(policy.tags, vec!["products", "catalog"]);
+        }
+        other => panic!("Expected SET, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_policy_set_to_resp_args_round_trips() {
+    let cmd = CachePolicyCmd::parse(&frames(&[
+        "SET",
+        "api",
+        "api:*",
+        "https://api.example.com/{1}",
+        "TTL",
+        "60",
+        "SWR",
+        "30",
+        "GRACE",
+        "600",
+        "NEGATIVE_TTL",
+        "5",
+        "PRIORITY",
+        "10",
+        "ORIGIN_CONCURRENCY",
+        "8",
+        "BREAKER_ERROR_RATE",
+        "50",
+        "BREAKER_WINDOW",
+        "30",
+        "BREAKER_MIN_REQUESTS",
+        "10",
+        "BREAKER_BACKOFF",
+        "2",
+        "COMPRESSION",
+        "FORCE-DISK",
+        "RESPECT_ORIGIN_HEADERS",
+        "SURROGATE_KEYS",
+        "NEGOTIATE_ENCODING",
+        "VARY_ON",
+        "Accept",
+        "Accept-Language",
+    ]))
+    .unwrap();
+
+    let (CachePolicySubcommand::Set(original), CachePolicySubcommand::Set(replayed)) =
+        (&cmd.subcommand, &round_trip(&cmd).subcommand)
+    else {
+        panic!("Expected SET subcommands");
+    };
+    assert_eq!(
+        serde_json::to_value(original).unwrap(),
+        serde_json::to_value(replayed).unwrap()
+    );
+}
+
+#[tokio::test]
+async fn test_policy_set_rejects_invalid_origin_limits() {
+    for (option, value) in [
+        ("BREAKER_ERROR_RATE", "0"),
+        ("BREAKER_ERROR_RATE", "101"),
+        ("ORIGIN_CONCURRENCY", "0"),
+        ("ORIGIN_CONCURRENCY", "18446744073709551615"),
+        ("BREAKER_WINDOW", "0"),
+        ("BREAKER_WINDOW", "18446744073709551615"),
+        ("BREAKER_BACKOFF", "0"),
+        ("BREAKER_BACKOFF", "301"),
+        ("BREAKER_BACKOFF", "18446744073709551615"),
+    ] {
+        let result = CachePolicyCmd::parse(&frames(&[
+            "SET",
+            "p",
+            "k:*",
+            "http://example.com/{1}",
+            option,
+            value,
+        ]));
+        assert!(result.is_err(), "{option} {value} should be rejected");
+    }
+}
+
+#[tokio::test]
+async fn test_policy_del_to_resp_args_round_trips() {
+    let cmd = CachePolicyCmd::parse(&frames(&["DEL", "api"])).unwrap();
+    match round_trip(&cmd).subcommand {
+        CachePolicySubcommand::Del(name) => assert_eq!(name, "api"),
+        other => panic!("Expected DEL, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_policy_flags_mark_only_changes_as_writes() {
+    let set =
+        CachePolicyCmd::parse(&frames(&["SET", "p", "k:*", "http://example.com/{1}"])).unwrap();
+    let del = CachePolicyCmd::parse(&frames(&["DEL", "p"])).unwrap();
+    let get = CachePolicyCmd::parse(&frames(&["GET", "p"])).unwrap();
+    let list = CachePolicyCmd::parse(&frames(&["LIST"])).unwrap();
+
+    for cmd in [&set, &del] {
+        assert!(cmd.flags().contains(CommandFlags::WRITE));
+        assert!(!cmd.flags().contains(CommandFlags::NO_PROPAGATE));
+    }
+    for cmd in [&get, &list] {
+        assert!(!cmd.flags().contains(CommandFlags::WRITE));
+        assert!(cmd.flags().contains(CommandFlags::NO_PROPAGATE));
+    }
+}
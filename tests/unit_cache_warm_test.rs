@@ -0,0 +1,143 @@
+use bytes::Bytes;
+use spineldb::core::commands::cache::cache_policy::{CachePolicyCmd, CachePolicySubcommand};
+use spineldb::core::commands::cache::cache_warm::{
+    CacheWarm, CacheWarmSubcommand, UrlKeyMatcher, parse_url_list,
+};
+use spineldb::core::commands::command_spec::CommandSpec;
+use spineldb::core::commands::command_trait::ParseCommand;
+use spineldb::core::protocol::RespFrame;
+use spineldb::core::state::warm::{WarmJob, WarmState};
+use spineldb::core::storage::cache_types::CachePolicy;
+use std::sync::Arc;
+
+fn frames(args: &[&str]) -> Vec<RespFrame> {
+    args.iter()
+        .map(|arg| RespFrame::BulkString(Bytes::from(arg.to_string())))
+        .collect()
+}
+
+fn policy(key_pattern: &str, url_template: &str) -> CachePolicy {
+    let cmd = CachePolicyCmd::parse(&frames(&["SET", "p", key_pattern, url_template])).unwrap();
+    match cmd.subcommand {
+        CachePolicySubcommand::Set(policy) => *policy,
+        other => panic!("Expected SET, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_warm_parse_and_round_trip() {
+    let cmd = CacheWarm::parse(&frames(&[
+        "pages",
+        "FROM",
+        "/srv/sitemap.xml",
+        "https://example.com/a",
+        "CONCURRENCY",
+        "8",
+        "RATE",
+        "2.5/s",
+    ]))
+    .unwrap();
+    let CacheWarmSubcommand::Start(request) = &cmd.subcommand else {
+        panic!("Expected a warmup run");
+    };
+    assert_eq!(request.policy, "pages");
+    assert_eq!(
+        request.sources,
+        vec!["/srv/sitemap.xml", "https://example.com/a"]
+    );
+    assert_eq!(request.concurrency, 8);
+    assert_eq!(request.rate, Some(2.5));
+
+    let args: Vec<RespFrame> = cmd
+        .to_resp_args()
+        .into_iter()
+        .map(RespFrame::BulkString)
+        .collect();
+    let CacheWarmSubcommand::Start(parsed) = CacheWarm::parse(&args).unwrap().subcommand else {
+        panic!("Expected a warmup run");
+    };
+    assert_eq!(parsed.sources, request.sources);
+    assert_eq!(parsed.concurrency, 8);
+    assert_eq!(parsed.rate, Some(2.5));
+}
+
+#[tokio::test]
+async fn test_warm_parse_subcommands_and_errors() {
+    assert!(matches!(
+        CacheWarm::parse(&frames(&["STATUS"])).unwrap().subcommand,
+        CacheWarmSubcommand::Status
+    ));
+    assert!(matches!(
+        CacheWarm::parse(&frames(&["cancel", "pages"])).unwrap().subcommand,
+        CacheWarmSubcommand::Cancel(policy) if policy == "pages"
+    ));
+    // A policy named like a subcommand can still be warmed.
+    assert!(matches!(
+        CacheWarm::parse(&frames(&["status", "FROM", "urls.txt"]))
+            .unwrap()
+            .subcommand,
+        CacheWarmSubcommand::Start(_)
+    ));
+
+    assert!(CacheWarm::parse(&frames(&["pages", "FROM"])).is_err());
+    assert!(CacheWarm::parse(&frames(&["pages", "FROM", "a", "CONCURRENCY", "0"])).is_err());
+    assert!(CacheWarm::parse(&frames(&["pages", "FROM", "a", "RATE", "0/s"])).is_err());
+    assert!(CacheWarm::parse(&frames(&["pages", "FROM", "a", "RATE", "1e-300"])).is_err());
+    assert!(CacheWarm::parse(&frames(&["pages", "FROM", "a", "RATE", "0.001/s"])).is_ok());
+    assert!(CacheWarm::parse(&frames(&["pages", "urls.txt"])).is_err());
+}
+
+#[tokio::test]
+async fn test_warm_job_finishes_when_its_task_panics() {
+    let job = Arc::new(WarmJob::new("pages".into(), 0, 1));
+    let task_job = job.clone();
+    let result = tokio::spawn(async move {
+        let _finish = task_job.finish_on_drop();
+        panic!("warmup task failed");
+    })
+    .await;
+    assert!(result.unwrap_err().is_panic());
+    assert!(!job.is_running());
+    assert_eq!(job.snapshot().state, WarmState::Done);
+}
+
+#[test]
+fn test_url_key_matcher_inverts_policy_templates() {
+    let matcher = UrlKeyMatcher::new(&policy(
+        "product:*:*",
+        "https://api.example.com/{2}/products/{1}?v={2}",
+    ))
+    .unwrap();
+    assert_eq!(
+        matcher.key_for("https://api.example.com/en/products/a%20b?v=en"),
+        Some("product:a b:en".to_string())
+    );
+    assert_eq!(
+        matcher.key_for("https://other.example.com/en/products/1"),
+        None
+    );
+
+    // Wildcards without a placeholder cannot be filled.
+    let matcher = UrlKeyMatcher::new(&policy("page:*", "https://example.com/home")).unwrap();
+    assert_eq!(matcher.key_for("https://example.com/home"), None);
+
+    assert!(UrlKeyMatcher::new(&policy("user:*", "https://example.com/{hdr:x-user}")).is_err());
+}
+
+#[test]
+fn test_parse_url_list_formats() {
+    let sitemap = r#"<urlset>
+  <url><loc> https://example.com/?a=1&amp;b=2 </loc></url>
+  <url><loc>https://example.com/about</loc></url>
+</urlset>"#;
+    assert_eq!(
+        parse_url_list(sitemap),
+        vec!["https://example.com/?a=1&b=2", "https://example.com/about"]
+    );
+
+    let list = "# comment\nhttps://example.com/a\n\n  https://example.com/b  \n";
+    assert_eq!(
+        parse_url_list(list),
+        vec!["https://example.com/a", "https://example.com/b"]
+    );
+}
@@ -134,3 +134,31 @@ async fn test_client_parse_unknown_subcommand() {
     let err = Client::parse(&args).unwrap_err();
     assert!(format!("{:?}", err).contains("UnknownCommand"));
 }
+
+#[tokio::test]
+async fn test_client_crossslot_parse_on_and_off() {
+    for (arg, expected) in [(&b"ON"[..], true), (&b"off"[..], false)] {
+        let args = [
+            RespFrame::BulkString(Bytes::from_static(b"crossslot")),
+            RespFrame::BulkString(Bytes::from_static(arg)),
+        ];
+        match Client::parse(&args).unwrap().subcommand {
+            ClientSubcommand::CrossSlot(enabled) => assert_eq!(enabled, expected),
+            _ => panic!("Expected CrossSlot subcommand"),
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_client_crossslot_parse_invalid_args() {
+    let args = [RespFrame::BulkString(Bytes::from_static(b"crossslot"))];
+    let err = Client::parse(&args).unwrap_err();
+    assert!(format!("{:?}", err).contains("WrongArgumentCount"));
+
+    let args = [
+        RespFrame::BulkString(Bytes::from_static(b"crossslot")),
+        RespFrame::BulkString(Bytes::from_static(b"maybe")),
+    ];
+    let err = Client::parse(&args).unwrap_err();
+    assert!(matches!(err, spineldb::core::SpinelDBError::SyntaxError));
+}
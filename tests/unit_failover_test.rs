@@ -0,0 +1,44 @@
+use bytes::Bytes;
+use spineldb::core::Command;
+use spineldb::core::commands::command_spec::CommandSpec;
+use spineldb::core::commands::command_trait::ParseCommand;
+use spineldb::core::commands::generic::failover::{Failover, FailoverSubcommand};
+use spineldb::core::protocol::RespFrame;
+
+fn bulk(s: &'static str) -> RespFrame {
+    RespFrame::BulkString(Bytes::from_static(s.as_bytes()))
+}
+
+#[tokio::test]
+async fn test_failover_parse_poison() {
+    let cmd = Failover::parse(&[bulk("POISON"), bulk("abc123"), bulk("60")]).unwrap();
+    assert!(matches!(
+        cmd.subcommand,
+        FailoverSubcommand::Poison { ref run_id, ttl_secs: 60 } if run_id == "abc123"
+    ));
+}
+
+#[tokio::test]
+async fn test_failover_parse_epoch() {
+    let cmd = Failover::parse(&[bulk("epoch"), bulk("7")]).unwrap();
+    assert!(matches!(cmd.subcommand, FailoverSubcommand::Epoch(7)));
+    assert_eq!(
+        cmd.to_resp_args(),
+        vec![Bytes::from_static(b"EPOCH"), Bytes::from_static(b"7")]
+    );
+}
+
+#[tokio::test]
+async fn test_failover_parse_epoch_errors() {
+    let err = Failover::parse(&[bulk("EPOCH")]).unwrap_err();
+    assert!(format!("{:?}", err).contains("WrongArgumentCount"));
+    assert!(Failover::parse(&[bulk("EPOCH"), bulk("-1")]).is_err());
+    assert!(Failover::parse(&[bulk("EPOCH"), bulk("1"), bulk("2")]).is_err());
+}
+
+#[tokio::test]
+async fn test_failover_command_from_frame() {
+    let frame = RespFrame::Array(vec![bulk("FAILOVER"), bulk("EPOCH"), bulk("3")]);
+    let command = Command::try_from(frame).unwrap();
+    assert!(matches!(command, Command::Failover(_)));
+}
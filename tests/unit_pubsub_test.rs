@@ -119,3 +119,37 @@ async fn test_pubsub_parse_unknown_subcommand() {
     let err = PubSubInfo::parse(&args).unwrap_err();
     assert!(format!("{:?}", err).contains("UnknownCommand"));
 }
+
+#[tokio::test]
+async fn test_pubsub_shardchannels_parse_with_pattern() {
+    let args = [
+        RespFrame::BulkString(Bytes::from_static(b"shardchannels")),
+        RespFrame::BulkString(Bytes::from_static(b"orders:*")),
+    ];
+    let pubsub_command = PubSubInfo::parse(&args).unwrap();
+    match pubsub_command.subcommand {
+        PubSubSubcommand::ShardChannels(pattern) => {
+            assert_eq!(pattern, Some(Bytes::from_static(b"orders:*")));
+        }
+        _ => panic!("Expected ShardChannels subcommand"),
+    }
+}
+
+#[tokio::test]
+async fn test_pubsub_shardnumsub_parse_channels() {
+    let args = [
+        RespFrame::BulkString(Bytes::from_static(b"shardnumsub")),
+        RespFrame::BulkString(Bytes::from_static(b"a")),
+        RespFrame::BulkString(Bytes::from_static(b"b")),
+    ];
+    let pubsub_command = PubSubInfo::parse(&args).unwrap();
+    match pubsub_command.subcommand {
+        PubSubSubcommand::ShardNumSub(channels) => {
+            assert_eq!(
+                channels,
+                vec![Bytes::from_static(b"a"), Bytes::from_static(b"b")]
+            );
+        }
+        _ => panic!("Expected ShardNumSub subcommand"),
+    }
+}
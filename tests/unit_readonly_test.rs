@@ -0,0 +1,34 @@
+use bytes::Bytes;
+use spineldb::core::Command;
+use spineldb::core::commands::command_spec::CommandSpec;
+use spineldb::core::commands::command_trait::ParseCommand;
+use spineldb::core::commands::generic::readonly::ReadOnly;
+use spineldb::core::protocol::RespFrame;
+
+#[tokio::test]
+async fn test_readonly_parse_no_args() {
+    let args = [];
+    assert!(ReadOnly::parse(&args).is_ok());
+}
+
+#[tokio::test]
+async fn test_readonly_parse_with_args() {
+    let args = [RespFrame::BulkString(Bytes::from_static(b"extra_arg"))];
+    let err = ReadOnly::parse(&args).unwrap_err();
+    assert!(format!("{:?}", err).contains("WrongArgumentCount"));
+}
+
+#[tokio::test]
+async fn test_readonly_command_from_frame() {
+    let frame = RespFrame::Array(vec![RespFrame::BulkString(Bytes::from_static(b"READONLY"))]);
+    let command = Command::try_from(frame).unwrap();
+    assert!(matches!(command, Command::ReadOnly(_)));
+}
+
+#[tokio::test]
+async fn test_readonly_spec() {
+    let cmd = ReadOnly;
+    assert_eq!(cmd.name(), "readonly");
+    assert_eq!(cmd.arity(), 1);
+    assert!(cmd.get_keys().is_empty());
+}
@@ -0,0 +1,36 @@
+use bytes::Bytes;
+use spineldb::core::Command;
+use spineldb::core::commands::command_spec::CommandSpec;
+use spineldb::core::commands::command_trait::ParseCommand;
+use spineldb::core::commands::generic::readwrite::ReadWrite;
+use spineldb::core::protocol::RespFrame;
+
+#[tokio::test]
+async fn test_readwrite_parse_no_args() {
+    let args = [];
+    assert!(ReadWrite::parse(&args).is_ok());
+}
+
+#[tokio::test]
+async fn test_readwrite_parse_with_args() {
+    let args = [RespFrame::BulkString(Bytes::from_static(b"extra_arg"))];
+    let err = ReadWrite::parse(&args).unwrap_err();
+    assert!(format!("{:?}", err).contains("WrongArgumentCount"));
+}
+
+#[tokio::test]
+async fn test_readwrite_command_from_frame() {
+    let frame = RespFrame::Array(vec![RespFrame::BulkString(Bytes::from_static(
+        b"READWRITE",
+    ))]);
+    let command = Command::try_from(frame).unwrap();
+    assert!(matches!(command, Command::ReadWrite(_)));
+}
+
+#[tokio::test]
+async fn test_readwrite_spec() {
+    let cmd = ReadWrite;
+    assert_eq!(cmd.name(), "readwrite");
+    assert_eq!(cmd.arity(), 1);
+    assert!(cmd.get_keys().is_empty());
+}
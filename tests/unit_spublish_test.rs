@@ -0,0 +1,48 @@
+use bytes::Bytes;
+use spineldb::core::commands::command_spec::CommandSpec;
+use spineldb::core::commands::command_trait::ParseCommand;
+use spineldb::core::commands::generic::spublish::SPublish;
+use spineldb::core::protocol::RespFrame;
+
+#[tokio::test]
+async fn test_spublish_parse_valid_args() {
+    let args = [
+        RespFrame::BulkString(Bytes::from_static(b"{user:1}:events")),
+        RespFrame::BulkString(Bytes::from_static(b"mymessage")),
+    ];
+    let spublish_command = SPublish::parse(&args).unwrap();
+    assert_eq!(
+        spublish_command.channel,
+        Bytes::from_static(b"{user:1}:events")
+    );
+    assert_eq!(spublish_command.message, Bytes::from_static(b"mymessage"));
+}
+
+#[tokio::test]
+async fn test_spublish_parse_missing_one_arg() {
+    let args = [RespFrame::BulkString(Bytes::from_static(b"mychannel"))];
+    let err = SPublish::parse(&args).unwrap_err();
+    assert!(format!("{:?}", err).contains("WrongArgumentCount"));
+}
+
+#[tokio::test]
+async fn test_spublish_parse_too_many_args() {
+    let args = [
+        RespFrame::BulkString(Bytes::from_static(b"mychannel")),
+        RespFrame::BulkString(Bytes::from_static(b"mymessage")),
+        RespFrame::BulkString(Bytes::from_static(b"extra")),
+    ];
+    let err = SPublish::parse(&args).unwrap_err();
+    assert!(format!("{:?}", err).contains("WrongArgumentCount"));
+}
+
+#[tokio::test]
+async fn test_spublish_channel_is_a_routing_key_but_not_locked() {
+    let spublish_command = SPublish {
+        channel: Bytes::from_static(b"mychannel"),
+        message: Bytes::from_static(b"mymessage"),
+    };
+    assert_eq!(spublish_command.first_key(), 1);
+    assert_eq!(spublish_command.last_key(), 1);
+    assert!(spublish_command.get_keys().is_empty());
+}
@@ -0,0 +1,45 @@
+use bytes::Bytes;
+use spineldb::core::commands::command_trait::ParseCommand;
+use spineldb::core::commands::generic::ssubscribe::SSubscribe;
+use spineldb::core::commands::generic::sunsubscribe::SUnsubscribe;
+use spineldb::core::protocol::RespFrame;
+
+#[tokio::test]
+async fn test_ssubscribe_parse_valid_channels() {
+    let args = [
+        RespFrame::BulkString(Bytes::from_static(b"{room}:a")),
+        RespFrame::BulkString(Bytes::from_static(b"{room}:b")),
+    ];
+    let ssubscribe_command = SSubscribe::parse(&args).unwrap();
+    assert_eq!(
+        ssubscribe_command.channels,
+        vec![
+            Bytes::from_static(b"{room}:a"),
+            Bytes::from_static(b"{room}:b")
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_ssubscribe_parse_no_args() {
+    let args = [];
+    let err = SSubscribe::parse(&args).unwrap_err();
+    assert!(format!("{:?}", err).contains("WrongArgumentCount"));
+}
+
+#[tokio::test]
+async fn test_ssubscribe_parse_non_bulk_string_arg() {
+    let args = [
+        RespFrame::BulkString(Bytes::from_static(b"channel")),
+        RespFrame::Integer(123),
+    ];
+    let err = SSubscribe::parse(&args).unwrap_err();
+    assert!(matches!(err, spineldb::core::SpinelDBError::WrongType));
+}
+
+#[tokio::test]
+async fn test_sunsubscribe_parse_no_args() {
+    let args = [];
+    let sunsubscribe_command = SUnsubscribe::parse(&args).unwrap();
+    assert!(sunsubscribe_command.channels.is_empty());
+}
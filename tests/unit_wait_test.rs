@@ -0,0 +1,54 @@
+use bytes::Bytes;
+use spineldb::core::commands::command_trait::ParseCommand;
+use spineldb::core::commands::generic::wait::Wait;
+use spineldb::core::commands::generic::waitaof::WaitAof;
+use spineldb::core::protocol::RespFrame;
+
+fn bulk(s: &'static str) -> RespFrame {
+    RespFrame::BulkString(Bytes::from_static(s.as_bytes()))
+}
+
+#[tokio::test]
+async fn test_wait_parse_valid() {
+    let args = [bulk("2"), bulk("500")];
+    let wait = Wait::parse(&args).unwrap();
+    assert_eq!(wait.num_replicas, 2);
+    assert_eq!(wait.timeout_ms, 500);
+}
+
+#[tokio::test]
+async fn test_wait_parse_wrong_arg_count() {
+    let args = [bulk("1")];
+    let err = Wait::parse(&args).unwrap_err();
+    assert!(format!("{:?}", err).contains("WrongArgumentCount"));
+}
+
+#[tokio::test]
+async fn test_wait_parse_negative_values() {
+    let args = [bulk("-1"), bulk("0")];
+    assert!(Wait::parse(&args).is_err());
+    let args = [bulk("1"), bulk("-5")];
+    assert!(Wait::parse(&args).is_err());
+}
+
+#[tokio::test]
+async fn test_waitaof_parse_valid() {
+    let args = [bulk("1"), bulk("0"), bulk("100")];
+    let waitaof = WaitAof::parse(&args).unwrap();
+    assert_eq!(waitaof.num_local, 1);
+    assert_eq!(waitaof.num_replicas, 0);
+    assert_eq!(waitaof.timeout_ms, 100);
+}
+
+#[tokio::test]
+async fn test_waitaof_parse_numlocal_out_of_range() {
+    let args = [bulk("2"), bulk("0"), bulk("100")];
+    assert!(WaitAof::parse(&args).is_err());
+}
+
+#[tokio::test]
+async fn test_waitaof_parse_wrong_arg_count() {
+    let args = [bulk("1"), bulk("0")];
+    let err = WaitAof::parse(&args).unwrap_err();
+    assert!(format!("{:?}", err).contains("WrongArgumentCount"));
+}